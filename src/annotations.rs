@@ -0,0 +1,284 @@
+//! Findings of `lint` and `verify` in the formats CI systems show on pull requests
+//!
+//! `--output gh-annotations` prints GitHub Actions workflow commands
+//! (`::error file=...,line=...::message`) and `--output gitlab-codequality` a
+//! GitLab Code Quality report, so problems appear inline on the diff without
+//! glue scripts parsing `--output json`. `--output sarif` prints a SARIF log
+//! for code-scanning dashboards, with the metadata of each rule from
+//! [`crate::lint::RULES`], [`crate::report::VERIFY_RULES`] and
+//! [`crate::snapshot::SNAPSHOT_RULES`]. Rule ids are kebab-case in every format.
+//!
+//! `lint`, `verify` and `hooks check` print annotations.
+
+use crate::{
+    lint::{Finding, Rule, Severity},
+    utils::calculate_checksum,
+};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// A finding and the file it is about
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Annotation {
+    /// Id of a [`Rule`], e.g. `cql-syntax` or `checksum-mismatch`
+    pub rule: String,
+    pub severity: Severity,
+    /// The migration file, or the migrations directory for problems without one
+    pub file: PathBuf,
+    /// 1-based line in `file`
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl From<&Finding> for Annotation {
+    fn from(finding: &Finding) -> Self {
+        Self {
+            rule: finding.rule.to_string(),
+            severity: finding.severity,
+            file: finding.file.clone(),
+            line: finding.line,
+            message: match &finding.policy_rule {
+                Some(name) => format!("{} (policy rule {})", finding.message, name),
+                None => finding.message.clone(),
+            },
+        }
+    }
+}
+
+/// `path` as CI systems expect it: relative, with forward slashes
+fn display_path(path: &Path) -> String {
+    let path = path.strip_prefix(".").unwrap_or(path);
+    path.to_string_lossy().replace('\\', "/")
+}
+
+/// Escape workflow command data
+fn escape_data(text: &str) -> String {
+    text.replace('%', "%25").replace('\r', "%0D").replace('\n', "%0A")
+}
+
+/// Escape a workflow command property value
+fn escape_property(text: &str) -> String {
+    escape_data(text).replace(':', "%3A").replace(',', "%2C")
+}
+
+/// One GitHub Actions workflow command per annotation
+pub fn github(annotations: &[Annotation]) -> String {
+    annotations
+        .iter()
+        .map(|annotation| {
+            let mut properties = vec![format!("file={}", escape_property(&display_path(&annotation.file)))];
+            if let Some(line) = annotation.line {
+                properties.push(format!("line={}", line));
+            }
+            properties.push(format!("title={}", escape_property(&annotation.rule)));
+            format!(
+                "::{} {}::{}",
+                match annotation.severity {
+                    Severity::Error => "error",
+                    Severity::Warning => "warning",
+                },
+                properties.join(","),
+                escape_data(&annotation.message)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// A GitLab Code Quality report; findings without a line point at the first
+pub fn gitlab(annotations: &[Annotation]) -> serde_json::Value {
+    annotations
+        .iter()
+        .map(|annotation| {
+            let path = display_path(&annotation.file);
+            let line = annotation.line.unwrap_or(1);
+            serde_json::json!({
+                "description": annotation.message,
+                "check_name": annotation.rule,
+                "fingerprint": calculate_checksum(&format!(
+                    "{}\u{0}{}\u{0}{}\u{0}{}",
+                    annotation.rule, path, line, annotation.message
+                )),
+                "severity": match annotation.severity {
+                    Severity::Error => "major",
+                    Severity::Warning => "minor",
+                },
+                "location": {"path": path, "lines": {"begin": line}}
+            })
+        })
+        .collect()
+}
+
+/// Version of the SARIF format written by [`sarif`]
+pub const SARIF_VERSION: &str = "2.1.0";
+
+/// A SARIF log with one run, listing the rules the annotations refer to
+pub fn sarif(annotations: &[Annotation]) -> serde_json::Value {
+    let mut rules: Vec<&str> = Vec::new();
+    for annotation in annotations {
+        if !rules.contains(&annotation.rule.as_str()) {
+            rules.push(&annotation.rule);
+        }
+    }
+
+    let descriptors: Vec<_> = rules
+        .iter()
+        .map(|id| match Rule::find(id) {
+            Some(rule) => serde_json::json!({
+                "id": rule.id,
+                "shortDescription": {"text": rule.description},
+                "helpUri": rule.help_uri
+            }),
+            None => serde_json::json!({"id": id}),
+        })
+        .collect();
+    let results: Vec<_> = annotations
+        .iter()
+        .map(|annotation| {
+            let mut location = serde_json::json!({
+                "artifactLocation": {"uri": display_path(&annotation.file), "uriBaseId": "%SRCROOT%"}
+            });
+            if let Some(line) = annotation.line {
+                location["region"] = serde_json::json!({"startLine": line});
+            }
+            serde_json::json!({
+                "ruleId": annotation.rule,
+                "ruleIndex": rules.iter().position(|id| *id == annotation.rule),
+                "level": match annotation.severity {
+                    Severity::Error => "error",
+                    Severity::Warning => "warning",
+                },
+                "message": {"text": annotation.message},
+                "locations": [{"physicalLocation": location}]
+            })
+        })
+        .collect();
+
+    serde_json::json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": SARIF_VERSION,
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "db-migrate",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": "https://github.com/dimuziop/db-migrate",
+                    "rules": descriptors
+                }
+            },
+            "results": results
+        }]
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::VerifyIssue;
+
+    fn annotation(file: &str, line: Option<usize>, message: &str) -> Annotation {
+        Annotation {
+            rule: "cql-syntax".to_string(),
+            severity: Severity::Error,
+            file: PathBuf::from(file),
+            line,
+            message: message.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_github_annotations() {
+        let annotations = [
+            annotation("./migrations/001_users.sql", Some(4), "Unterminated string: 'a,b\nnext"),
+            annotation("migrations", None, "100% broken"),
+        ];
+        assert_eq!(
+            github(&annotations),
+            "::error file=migrations/001_users.sql,line=4,title=cql-syntax::Unterminated string: 'a,b%0Anext\n\
+             ::error file=migrations,title=cql-syntax::100%25 broken"
+        );
+    }
+
+    #[test]
+    fn test_gitlab_code_quality() {
+        let annotations = [
+            annotation("migrations/001_users.sql", Some(4), "bad"),
+            annotation("./migrations", None, "bad"),
+        ];
+        let report = gitlab(&annotations);
+        assert_eq!(report[0]["location"]["path"], "migrations/001_users.sql");
+        assert_eq!(report[0]["location"]["lines"]["begin"], 4);
+        assert_eq!(report[0]["severity"], "major");
+        assert_eq!(report[1]["location"]["path"], "migrations");
+        assert_ne!(report[0]["fingerprint"], report[1]["fingerprint"]);
+    }
+
+    #[test]
+    fn test_sarif() {
+        let mut mismatch = annotation("migrations/002_orders.sql", None, "changed");
+        mismatch.rule = "checksum-mismatch".to_string();
+        let annotations = [
+            annotation("./migrations/001_users.sql", Some(4), "bad"),
+            mismatch,
+            annotation("migrations/003_items.sql", Some(2), "worse"),
+        ];
+
+        let log = sarif(&annotations);
+        let run = &log["runs"][0];
+        let rules = run["tool"]["driver"]["rules"].as_array().unwrap();
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[0]["id"], "cql-syntax");
+        assert_eq!(rules[1]["helpUri"], "https://github.com/dimuziop/db-migrate#verify-options");
+        assert_eq!(run["results"][2]["ruleIndex"], 0);
+        assert_eq!(run["results"][1]["ruleIndex"], 1);
+        let location = &run["results"][0]["locations"][0]["physicalLocation"];
+        assert_eq!(location["artifactLocation"]["uri"], "migrations/001_users.sql");
+        assert_eq!(location["region"]["startLine"], 4);
+        assert!(run["results"][1]["locations"][0]["physicalLocation"].get("region").is_none());
+    }
+
+    #[test]
+    fn test_every_rule_has_a_descriptor() {
+        let kebab_case = regex::Regex::new("^[a-z]+(-[a-z]+)*$").unwrap();
+        for rule in Rule::all() {
+            assert!(kebab_case.is_match(rule.id), "rule id {} is not kebab-case", rule.id);
+        }
+
+        let version = || "001".to_string();
+        let issues = [
+            VerifyIssue::ChecksumMismatch { version: version(), expected_checksum: version(), actual_checksum: version() },
+            VerifyIssue::DownChanged { version: version(), expected_checksum: version(), actual_checksum: version() },
+            VerifyIssue::MissingFile { version: version() },
+            VerifyIssue::OutOfOrder { version: version(), latest_applied: version() },
+            VerifyIssue::Unparseable { version: version(), reason: version() },
+            VerifyIssue::Other { error: version() },
+        ];
+        let mut ids: Vec<&str> = issues.iter().map(VerifyIssue::rule).collect();
+
+        // Findings name their rule with a literal where they are created
+        let created = regex::Regex::new(r#"(?:Finding::new\(\s*|rule: )"([^"]+)""#).unwrap();
+        let sources = [
+            include_str!("lint.rs"),
+            include_str!("policy.rs"),
+            include_str!("plugins.rs"),
+            include_str!("commands/hooks.rs"),
+            include_str!("commands/verify.rs"),
+        ];
+        ids.extend(sources.iter().flat_map(|source| {
+            created.captures_iter(source).map(|found| found.get(1).unwrap().as_str())
+        }));
+        assert!(ids.contains(&"applied-down-modified") && ids.contains(&"invalid-filename"));
+
+        for id in ids {
+            let annotation = Annotation {
+                rule: id.to_string(),
+                ..annotation("migrations", None, "bad")
+            };
+            let log = sarif(std::slice::from_ref(&annotation));
+            let descriptor = &log["runs"][0]["tool"]["driver"]["rules"][0];
+            assert!(descriptor.get("shortDescription").is_some(), "rule {} has no descriptor", id);
+            assert!(github(std::slice::from_ref(&annotation)).contains(&format!("title={}", id)));
+            assert_eq!(gitlab(std::slice::from_ref(&annotation))[0]["check_name"], id);
+        }
+    }
+}
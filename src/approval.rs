@@ -0,0 +1,256 @@
+//! Human approval of `up` in protected environments
+//!
+//! When `behavior.environment` is listed in `approval.environments`, `up` posts
+//! its plan to `approval.webhook_url` before applying anything. A plan is
+//! identified by a plan id derived from the environment, cluster, keyspace and
+//! the migrations to apply with their checksums, so an approval only covers
+//! what was reviewed.
+//!
+//! If the webhook answers with a JSON object holding a `poll_url`, `up` polls
+//! that URL until it answers
+//!
+//! ```json
+//! {"decision": "approve", "approver": "alice", "token": "..."}
+//! ```
+//!
+//! or `{"decision": "deny", "reason": "..."}` (`pending` keeps it waiting).
+//! Otherwise, e.g. for a chat incoming webhook, `up` stops: an approver holding
+//! the shared secret signs the plan id with `db-migrate approve <plan-id>`, and
+//! the token it prints is passed to `up --approval-token`. Approvers need the
+//! secret, not database credentials. With a secret configured, a polled
+//! approval must carry a valid `token` as well.
+//!
+//! Webhooks need the `webhooks` feature (on by default); tokens work without it.
+
+use crate::{config::Config, http, utils::calculate_checksum, MigrationError, MigrationFile};
+use openssl::{hash::MessageDigest, memcmp, pkey::PKey, sign::Signer};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+/// What is posted to the webhook
+#[derive(Debug, Clone, Serialize)]
+pub struct Plan {
+    pub plan_id: String,
+    pub environment: String,
+    pub cluster_name: Option<String>,
+    pub keyspace: String,
+    pub migrations: Vec<PlannedMigration>,
+    /// Human-readable summary, shown by chat webhooks
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PlannedMigration {
+    pub version: String,
+    pub description: String,
+    pub checksum: String,
+}
+
+impl Plan {
+    pub fn new(config: &Config, cluster_name: Option<&str>, migrations: &[MigrationFile]) -> Self {
+        let environment = config.behavior.environment.clone().unwrap_or_default();
+        let keyspace = config.database.keyspace.clone();
+        let migrations: Vec<PlannedMigration> = migrations
+            .iter()
+            .map(|migration| PlannedMigration {
+                version: migration.version.clone(),
+                description: migration.description.clone(),
+                checksum: migration.checksum.clone(),
+            })
+            .collect();
+
+        let material: Vec<String> = [
+            environment.clone(),
+            cluster_name.unwrap_or_default().to_string(),
+            keyspace.clone(),
+        ]
+        .into_iter()
+        .chain(migrations.iter().map(|m| format!("{}:{}", m.version, m.checksum)))
+        .collect();
+        let plan_id = calculate_checksum(&material.join("\n"))[..16].to_string();
+
+        let mut text = vec![format!(
+            "db-migrate plan {} wants to apply {} migration(s) to {} in {}{}:",
+            plan_id,
+            migrations.len(),
+            keyspace,
+            environment,
+            cluster_name.map(|name| format!(" (cluster {})", name)).unwrap_or_default()
+        )];
+        text.extend(
+            migrations
+                .iter()
+                .map(|m| format!("• {} - {}", m.version, m.description)),
+        );
+
+        Self {
+            plan_id,
+            environment,
+            cluster_name: cluster_name.map(str::to_string),
+            keyspace,
+            migrations,
+            text: text.join("\n"),
+        }
+    }
+}
+
+/// Whether `up` must be approved in the configured environment
+pub fn requires_approval(config: &Config) -> bool {
+    config
+        .behavior
+        .environment
+        .as_ref()
+        .is_some_and(|environment| config.approval.environments.contains(environment))
+}
+
+/// The signing secret, from the environment variable named by `approval.secret_env`
+pub fn approval_secret(config: &Config) -> Option<String> {
+    std::env::var(&config.approval.secret_env)
+        .ok()
+        .filter(|secret| !secret.is_empty())
+}
+
+/// Approval token of a plan: HMAC-SHA256 of the plan id, hex-encoded
+pub fn sign(secret: &str, plan_id: &str) -> Result<String, MigrationError> {
+    let signature = (|| {
+        let key = PKey::hmac(secret.as_bytes())?;
+        let mut signer = Signer::new(MessageDigest::sha256(), &key)?;
+        signer.update(plan_id.as_bytes())?;
+        signer.sign_to_vec()
+    })()
+    .map_err(|e| MigrationError::ConfigError(format!("Could not sign plan {}: {}", plan_id, e)))?;
+
+    Ok(signature.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+/// Whether `token` approves `plan_id`
+pub fn verify(secret: &str, plan_id: &str, token: &str) -> bool {
+    let token = token.trim().to_ascii_lowercase();
+    sign(secret, plan_id).is_ok_and(|expected| {
+        expected.len() == token.len() && memcmp::eq(expected.as_bytes(), token.as_bytes())
+    })
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct WebhookReply {
+    poll_url: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum PollDecision {
+    Approve,
+    Deny,
+    Pending,
+}
+
+#[derive(Debug, Deserialize)]
+struct PollReply {
+    decision: PollDecision,
+    approver: Option<String>,
+    reason: Option<String>,
+    token: Option<String>,
+}
+
+fn not_approved(plan: &Plan, reason: impl Into<String>) -> MigrationError {
+    MigrationError::NotApproved {
+        plan_id: plan.plan_id.clone(),
+        reason: reason.into(),
+    }
+}
+
+/// Post the plan to the webhook, returning the URL to poll for a decision if
+/// the webhook gave one
+pub async fn post_plan(url: &str, plan: &Plan) -> Result<Option<String>, MigrationError> {
+    let (url, body) = (url.to_string(), plan.clone());
+    let reply = tokio::task::spawn_blocking(move || http::post_json(&url, &body))
+        .await
+        .map_err(|e| not_approved(plan, e.to_string()))?
+        .map_err(|e| not_approved(plan, format!("the approval webhook failed: {}", e)))?;
+
+    debug!("Approval webhook replied: {}", reply);
+    // Webhooks that only acknowledge (e.g. with `ok`) leave approval to a token
+    let reply: WebhookReply = serde_json::from_str(&reply).unwrap_or_default();
+    Ok(reply.poll_url)
+}
+
+/// Poll `poll_url` until the plan is approved, returning the approver if named
+pub async fn wait_for_decision(
+    config: &Config,
+    poll_url: &str,
+    plan: &Plan,
+    secret: Option<&str>,
+) -> Result<Option<String>, MigrationError> {
+    let settings = &config.approval;
+    let deadline = Instant::now() + Duration::from_secs(settings.timeout_seconds);
+
+    loop {
+        let url = poll_url.to_string();
+        let reply = tokio::task::spawn_blocking(move || http::get_json::<PollReply>(&url))
+            .await
+            .map_err(|e| not_approved(plan, e.to_string()))?;
+
+        match reply {
+            Ok(PollReply { decision: PollDecision::Approve, approver, token, .. }) => {
+                if let Some(secret) = secret {
+                    if !token.is_some_and(|token| verify(secret, &plan.plan_id, &token)) {
+                        return Err(not_approved(plan, "the approval did not carry a valid token"));
+                    }
+                }
+                return Ok(approver);
+            }
+            Ok(PollReply { decision: PollDecision::Deny, approver, reason, .. }) => {
+                return Err(not_approved(
+                    plan,
+                    format!(
+                        "denied{}{}",
+                        approver.map(|approver| format!(" by {}", approver)).unwrap_or_default(),
+                        reason.map(|reason| format!(": {}", reason)).unwrap_or_default()
+                    ),
+                ));
+            }
+            Ok(PollReply { decision: PollDecision::Pending, .. }) => {}
+            // A flaky approval service should not fail the run before the deadline
+            Err(e) => debug!("Polling {} failed: {}", poll_url, e),
+        }
+
+        if Instant::now() >= deadline {
+            return Err(not_approved(
+                plan,
+                format!("no decision within {}s", settings.timeout_seconds),
+            ));
+        }
+        tokio::time::sleep(Duration::from_secs(settings.poll_interval_seconds)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_tokens() {
+        let mut config = Config::default();
+        config.behavior.environment = Some("prod".to_string());
+        assert!(!requires_approval(&config));
+        config.approval.environments = vec!["prod".to_string()];
+        assert!(requires_approval(&config));
+
+        let mut migration = MigrationFile::for_test("20250101_001_users", "");
+        let plan = Plan::new(&config, Some("eu"), std::slice::from_ref(&migration));
+        assert_eq!(plan.plan_id.len(), 16);
+        assert!(plan.text.contains("20250101_001_users - users"));
+
+        let token = sign("secret", &plan.plan_id).unwrap();
+        assert!(verify("secret", &plan.plan_id, &token));
+        assert!(verify("secret", &plan.plan_id, &token.to_uppercase()));
+        assert!(!verify("other", &plan.plan_id, &token));
+        assert!(!verify("secret", &plan.plan_id, &token[..10]));
+
+        migration.checksum = "def".to_string();
+        let changed = Plan::new(&config, Some("eu"), std::slice::from_ref(&migration));
+        assert_ne!(changed.plan_id, plan.plan_id);
+        assert!(!verify("secret", &changed.plan_id, &token));
+    }
+}
@@ -0,0 +1,163 @@
+//! DOWN statements generated from simple DDL
+//!
+//! Only statements with an unambiguous inverse are handled: creating a table,
+//! index, type or materialized view is undone by dropping it. Anything else is
+//! reported as unsupported and left to the author.
+
+use crate::{
+    cql::{self, Token},
+    statements::{Line, SectionTracker},
+    stream::Section,
+    utils::split_cql_statements,
+};
+
+/// DOWN statements suggested for an UP section
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SuggestedDown {
+    /// Inverse statements, in the order they must run (reverse of UP)
+    pub statements: Vec<String>,
+    /// UP statements without a known inverse
+    pub unsupported: Vec<String>,
+}
+
+impl SuggestedDown {
+    /// Whether every UP statement has an inverse
+    pub fn is_complete(&self) -> bool {
+        self.unsupported.is_empty() && !self.statements.is_empty()
+    }
+}
+
+/// Suggest DOWN statements for the given UP content
+pub fn suggest_down(up_content: &str) -> SuggestedDown {
+    let mut suggestion = SuggestedDown::default();
+
+    for statement in split_cql_statements(up_content).into_iter().rev() {
+        match inverse_statement(&statement) {
+            Some(inverse) => suggestion.statements.push(inverse),
+            None => suggestion.unsupported.insert(0, statement),
+        }
+    }
+
+    suggestion
+}
+
+/// The statement undoing a `CREATE TABLE/INDEX/TYPE/MATERIALIZED VIEW`
+pub fn inverse_statement(statement: &str) -> Option<String> {
+    let tokens = cql::tokenize(statement).ok()?;
+    let keyword = |i: usize, k: &str| tokens.get(i).is_some_and(|t| t.is_keyword(k));
+    let word = |i: usize| match tokens.get(i) {
+        Some(Token::Word(word)) => Some(word.clone()),
+        _ => None,
+    };
+
+    if !keyword(0, "CREATE") {
+        return None;
+    }
+
+    if keyword(1, "TABLE") || keyword(1, "TYPE") {
+        let kind = if keyword(1, "TABLE") { "TABLE" } else { "TYPE" };
+        return Some(format!("DROP {} IF EXISTS {}", kind, word(cql::skip_if_exists(&tokens, 2)?)?));
+    }
+
+    if keyword(1, "MATERIALIZED") && keyword(2, "VIEW") {
+        return Some(format!(
+            "DROP MATERIALIZED VIEW IF EXISTS {}",
+            word(cql::skip_if_exists(&tokens, 3)?)?
+        ));
+    }
+
+    let index_start = if keyword(1, "INDEX") {
+        2
+    } else if keyword(1, "CUSTOM") && keyword(2, "INDEX") {
+        3
+    } else {
+        return None;
+    };
+
+    // Unnamed indexes get a server-generated name; leave those to the author
+    let name_index = cql::skip_if_exists(&tokens, index_start)?;
+    if keyword(name_index, "ON") {
+        return None;
+    }
+    let name = word(name_index)?;
+
+    // Index names live in the keyspace of their table
+    let qualified = match cql::target_object(&tokens)?.split_once('.') {
+        Some((keyspace, _)) if !name.contains('.') => format!("{}.{}", keyspace, name),
+        _ => name,
+    };
+
+    Some(format!("DROP INDEX IF EXISTS {}", qualified))
+}
+
+/// Append DOWN statements to migration content, adding the DOWN marker if needed
+pub fn append_down(content: &str, statements: &[String]) -> String {
+    let mut tracker = SectionTracker::default();
+    let in_down = content.lines().fold(false, |_, line| {
+        matches!(tracker.push_line(line), Line::Marker(Section::Down) | Line::In(Section::Down))
+    });
+
+    let mut result = content.trim_end().to_string();
+    result.push('\n');
+    if !in_down {
+        result.push_str("\n-- +migrate Down\n");
+    }
+    for statement in statements {
+        result.push_str(statement);
+        result.push_str(";\n");
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::parse_migration_content;
+
+    #[test]
+    fn test_suggest_down() {
+        let up = r#"
+CREATE TYPE IF NOT EXISTS app.address (street text);
+CREATE TABLE users (id uuid PRIMARY KEY, email text);
+CREATE INDEX IF NOT EXISTS users_email_idx ON app.users (email);
+CREATE INDEX ON users (name);
+INSERT INTO users (id) VALUES (uuid());
+"#;
+
+        let suggestion = suggest_down(up);
+        assert_eq!(
+            suggestion.statements,
+            vec![
+                "DROP INDEX IF EXISTS app.users_email_idx",
+                "DROP TABLE IF EXISTS users",
+                "DROP TYPE IF EXISTS app.address",
+            ]
+        );
+        assert_eq!(suggestion.unsupported.len(), 2);
+        assert!(!suggestion.is_complete());
+
+        assert_eq!(
+            inverse_statement("CREATE MATERIALIZED VIEW app.by_email AS SELECT * FROM app.users"),
+            Some("DROP MATERIALIZED VIEW IF EXISTS app.by_email".to_string())
+        );
+    }
+
+    #[test]
+    fn test_append_down() {
+        let statements = vec!["DROP TABLE IF EXISTS users".to_string()];
+
+        let with_marker = "-- +migrate Up\nCREATE TABLE users (id int PRIMARY KEY);\n\n-- +migrate Down\n-- nothing yet\n";
+        let content = append_down(with_marker, &statements);
+        assert_eq!(content.matches("-- +migrate Down").count(), 1);
+        assert_eq!(
+            parse_migration_content(&content).unwrap().1.unwrap(),
+            "-- nothing yet\nDROP TABLE IF EXISTS users;"
+        );
+
+        let content = append_down("CREATE TABLE users (id int PRIMARY KEY);\n", &statements);
+        assert_eq!(
+            parse_migration_content(&content).unwrap().1.unwrap(),
+            "DROP TABLE IF EXISTS users;"
+        );
+    }
+}
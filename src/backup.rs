@@ -0,0 +1,197 @@
+//! Backups of the tracking table
+//!
+//! Destructive commands export every tracking record, including stored DOWN
+//! sections, to a timestamped JSON file under `backups.directory` before they
+//! touch the table. `restore-history <file>` writes the records back.
+
+use crate::{history::HistoryAction, migration::MigrationManager, MigrationError, MigrationRecord};
+use chrono::{DateTime, Utc};
+use scylla::frame::value::CqlTimestamp;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+/// Version of the backup file layout, bumped on incompatible changes
+pub const BACKUP_FORMAT: u32 = 1;
+
+/// Content of a backup file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrackingBackup {
+    pub format: u32,
+    /// Command that triggered the backup
+    pub operation: String,
+    pub created_at: DateTime<Utc>,
+    pub cluster_name: Option<String>,
+    /// Keyspace-qualified tracking table the records were read from
+    pub tracking_table: String,
+    pub records: Vec<BackupRecord>,
+}
+
+/// A tracking record with its stored DOWN section
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupRecord {
+    #[serde(flatten)]
+    pub record: MigrationRecord,
+    #[serde(default)]
+    pub down_content: Option<String>,
+}
+
+impl TrackingBackup {
+    /// Read and check a backup file
+    pub fn read(path: &Path) -> Result<Self, MigrationError> {
+        let content = std::fs::read_to_string(path)?;
+        let backup: Self = serde_json::from_str(&content).map_err(|e| {
+            MigrationError::InvalidFormat(format!("{} is not a tracking table backup: {}", path.display(), e))
+        })?;
+        if backup.format != BACKUP_FORMAT {
+            return Err(MigrationError::InvalidFormat(format!(
+                "{} has backup format {}, this version of db-migrate reads format {}",
+                path.display(),
+                backup.format,
+                BACKUP_FORMAT
+            )));
+        }
+        Ok(backup)
+    }
+}
+
+/// File name of a backup taken before `operation` at `at`
+pub fn backup_file_name(table_name: &str, operation: &str, at: DateTime<Utc>) -> String {
+    format!("{}-{}-{}.json", table_name, operation, at.format("%Y%m%dT%H%M%S%.3fZ"))
+}
+
+impl MigrationManager {
+    /// Export the tracking table before a destructive `operation`
+    ///
+    /// Returns the backup file, or `None` when backups are disabled.
+    pub async fn backup_tracking_table(&self, operation: &str) -> Result<Option<PathBuf>, MigrationError> {
+        let config = self.get_config();
+        if !config.backups.enabled {
+            return Ok(None);
+        }
+
+        let applied = self.get_applied_migrations().await?;
+        let mut down_contents = HashMap::new();
+        if !applied.is_empty() {
+            let query = format!("SELECT version, down_content FROM {}", self.tracking_table());
+            let rows = self.session().query(query, &[]).await?;
+            for row in rows
+                .rows_typed::<(String, Option<String>)>()
+                .map_err(|e| MigrationError::IntegrityError(e.to_string()))?
+            {
+                let (version, down_content) =
+                    row.map_err(|e| MigrationError::IntegrityError(e.to_string()))?;
+                if let Some(down_content) = down_content {
+                    down_contents.insert(version, down_content);
+                }
+            }
+        }
+
+        let created_at = Utc::now();
+        let backup = TrackingBackup {
+            format: BACKUP_FORMAT,
+            operation: operation.to_string(),
+            created_at,
+            cluster_name: self.cluster_name().map(str::to_string),
+            tracking_table: self.tracking_table(),
+            records: applied
+                .into_iter()
+                .map(|record| BackupRecord {
+                    down_content: down_contents.remove(&record.version),
+                    record,
+                })
+                .collect(),
+        };
+
+        let path = config.backups.directory.join(backup_file_name(
+            &config.migrations.table_name,
+            operation,
+            created_at,
+        ));
+        tokio::fs::create_dir_all(&config.backups.directory).await?;
+        let json = serde_json::to_string_pretty(&backup)
+            .map_err(|e| MigrationError::IntegrityError(e.to_string()))?;
+        tokio::fs::write(&path, json).await?;
+
+        info!("Backed up {} tracking record(s) to {}", backup.records.len(), path.display());
+        Ok(Some(path))
+    }
+
+    /// Write backed-up records into the tracking table, replacing records of the
+    /// same version
+    pub async fn restore_tracking_records(&self, records: &[BackupRecord]) -> Result<(), MigrationError> {
+        self.ensure_writable()?;
+
+        let query = format!(
+            "INSERT INTO {} (version, applied_at, checksum, description, cluster_name, target_keyspace, down_content, duration_ms, tool_version, up_checksum) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            self.tracking_table()
+        );
+        for BackupRecord { record, down_content } in records {
+            self.session()
+                .query(
+                    query.as_str(),
+                    (
+                        &record.version,
+                        CqlTimestamp(record.applied_at.timestamp_millis()),
+                        &record.checksum,
+                        &record.description,
+                        &record.cluster_name,
+                        &record.target_keyspace,
+                        down_content,
+                        record.duration_ms,
+                        &record.tool_version,
+                        &record.up_checksum,
+                    ),
+                )
+                .await?;
+            self.record_event(&record.version, HistoryAction::Restored, Some(&record.checksum), None)
+                .await;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_backup_round_trip() {
+        let created_at = Utc.with_ymd_and_hms(2025, 3, 1, 12, 30, 0).unwrap();
+        assert_eq!(
+            backup_file_name("schema_migrations", "reset", created_at),
+            "schema_migrations-reset-20250301T123000.000Z.json"
+        );
+
+        let backup = TrackingBackup {
+            format: BACKUP_FORMAT,
+            operation: "reset".to_string(),
+            created_at,
+            cluster_name: Some("prod".to_string()),
+            tracking_table: "app.schema_migrations".to_string(),
+            records: vec![BackupRecord {
+                record: MigrationRecord {
+                    cluster_name: Some("prod".to_string()),
+                    duration_ms: Some(42),
+                    tool_version: Some("0.1.1".to_string()),
+                    ..MigrationRecord::for_test("20250101_001_users", created_at)
+                },
+                down_content: Some("DROP TABLE users;".to_string()),
+            }],
+        };
+
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut file, serde_json::to_string(&backup).unwrap().as_bytes()).unwrap();
+        let read = TrackingBackup::read(file.path()).unwrap();
+        assert_eq!(read.records.len(), 1);
+        assert_eq!(read.records[0].record.version, "20250101_001_users");
+        assert_eq!(read.records[0].record.duration_ms, Some(42));
+        assert_eq!(read.records[0].down_content.as_deref(), Some("DROP TABLE users;"));
+
+        let newer = TrackingBackup { format: BACKUP_FORMAT + 1, ..read };
+        std::fs::write(file.path(), serde_json::to_string(&newer).unwrap()).unwrap();
+        assert!(TrackingBackup::read(file.path()).is_err());
+    }
+}
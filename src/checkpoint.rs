@@ -0,0 +1,229 @@
+//! Where a cancelled migration stopped, so the next run resumes after it
+//!
+//! Cancelling a run (see [`MigrationManager::cancellation_token`]) stops it
+//! between two UP statements and saves the index of the next one to
+//! `<table_name>_checkpoints`, next to the tracking table. The next run of the
+//! migration starts at that statement instead of executing the earlier ones a
+//! second time, provided the file is unchanged; the checkpoint is removed once
+//! the migration is recorded as applied.
+//!
+//! Unlogged batches group statements by partition rather than running them in
+//! file order, so a cancelled unlogged batch migration starts over.
+
+use crate::{
+    cql::{self, suffixed_identifier},
+    drift::normalize_identifier,
+    migration::MigrationManager,
+    MigrationError, MigrationFile,
+};
+use chrono::Utc;
+use scylla::frame::value::CqlTimestamp;
+use serde::Serialize;
+use tracing::{debug, info, warn};
+
+/// Suffix of the checkpoint table, appended to `migrations.table_name`
+pub const CHECKPOINT_TABLE_SUFFIX: &str = "_checkpoints";
+
+/// The first UP statement of a migration not executed yet
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Checkpoint {
+    pub version: String,
+    /// Checksum of the file the statements were executed from
+    pub checksum: String,
+    /// Zero-based index of the next statement to execute
+    pub next_statement: usize,
+}
+
+impl Checkpoint {
+    /// A run of `migration` from its first statement
+    pub fn start(migration: &MigrationFile) -> Self {
+        Self {
+            version: migration.version.clone(),
+            checksum: migration.checksum.clone(),
+            next_statement: 0,
+        }
+    }
+
+    /// Where a run of `migration` starts: at a saved checkpoint if the file is
+    /// unchanged since, otherwise from the beginning
+    pub fn resume(saved: Option<Checkpoint>, migration: &MigrationFile) -> Self {
+        match saved {
+            Some(saved) if saved.checksum == migration.checksum => saved,
+            Some(saved) => {
+                warn!(
+                    "Migration {} changed since it was cancelled after {} statement(s); running it from the start",
+                    migration.version, saved.next_statement
+                );
+                Self::start(migration)
+            }
+            None => Self::start(migration),
+        }
+    }
+
+    /// The same run, stopped before statement `next_statement`
+    pub fn at(&self, next_statement: usize) -> Self {
+        Self {
+            next_statement,
+            ..self.clone()
+        }
+    }
+
+    /// Whether statement `index` runs: those from the checkpoint on, and the
+    /// `USE` statements before it, which the following statements rely on
+    pub fn runs(&self, index: usize, statement: &str) -> bool {
+        index >= self.next_statement || cql::used_keyspace(statement).is_some()
+    }
+}
+
+impl MigrationManager {
+    /// Keyspace-qualified name of the checkpoint table
+    pub fn checkpoint_table(&self) -> String {
+        self.get_config().checkpoint_table()
+    }
+
+    /// Whether the checkpoint table exists, creating it when `create` is set
+    async fn has_checkpoint_table(&self, create: bool) -> Result<bool, MigrationError> {
+        let table = self.checkpoint_table();
+        if self.is_initialized(&table) {
+            return Ok(true);
+        }
+
+        if create {
+            let query = format!(
+                "CREATE TABLE IF NOT EXISTS {} (
+                    version TEXT PRIMARY KEY,
+                    checksum TEXT,
+                    next_statement INT,
+                    cancelled_at TIMESTAMP
+                )",
+                table
+            );
+            debug!("Creating checkpoint table: {}", query);
+            self.session().query(query, &[]).await?;
+        } else {
+            let config = self.get_config();
+            let rows = self
+                .session()
+                .query(
+                    "SELECT table_name FROM system_schema.tables WHERE keyspace_name = ? AND table_name = ?",
+                    (
+                        normalize_identifier(config.tracking_keyspace()),
+                        normalize_identifier(&suffixed_identifier(
+                            &config.migrations.table_name,
+                            CHECKPOINT_TABLE_SUFFIX,
+                        )),
+                    ),
+                )
+                .await?;
+            if rows.rows.unwrap_or_default().is_empty() {
+                return Ok(false);
+            }
+        }
+        self.mark_initialized(table);
+        Ok(true)
+    }
+
+    /// The checkpoint saved when a run of `version` was cancelled, if any
+    pub async fn load_checkpoint(&self, version: &str) -> Result<Option<Checkpoint>, MigrationError> {
+        if !self.has_checkpoint_table(false).await? {
+            return Ok(None);
+        }
+
+        let query = format!(
+            "SELECT checksum, next_statement FROM {} WHERE version = ?",
+            self.checkpoint_table()
+        );
+        let rows = self.session().query(query, (version,)).await?;
+        Ok(rows
+            .rows_typed::<(Option<String>, Option<i32>)>()
+            .map_err(|e| MigrationError::IntegrityError(e.to_string()))?
+            .next()
+            .transpose()
+            .map_err(|e| MigrationError::IntegrityError(e.to_string()))?
+            .and_then(|(checksum, next_statement)| {
+                Some(Checkpoint {
+                    version: version.to_string(),
+                    checksum: checksum?,
+                    next_statement: usize::try_from(next_statement?).ok()?,
+                })
+            }))
+    }
+
+    /// Save where a cancelled run stopped, logging instead of failing: the run
+    /// is being cancelled either way
+    pub(crate) async fn save_checkpoint(&self, checkpoint: &Checkpoint) {
+        let result = async {
+            self.has_checkpoint_table(true).await?;
+            let query = format!(
+                "INSERT INTO {} (version, checksum, next_statement, cancelled_at) VALUES (?, ?, ?, ?)",
+                self.checkpoint_table()
+            );
+            let next_statement = i32::try_from(checkpoint.next_statement).unwrap_or(i32::MAX);
+            self.session()
+                .query(
+                    query,
+                    (
+                        &checkpoint.version,
+                        &checkpoint.checksum,
+                        next_statement,
+                        CqlTimestamp(Utc::now().timestamp_millis()),
+                    ),
+                )
+                .await?;
+            Ok::<_, MigrationError>(())
+        }
+        .await;
+
+        match result {
+            Ok(()) => info!(
+                "Saved checkpoint of {}: the next run starts at statement {}",
+                checkpoint.version,
+                checkpoint.next_statement + 1
+            ),
+            Err(e) => warn!(
+                "Could not save the checkpoint of {}; the next run starts from its first statement: {}",
+                checkpoint.version, e
+            ),
+        }
+    }
+
+    /// Remove the checkpoint of a migration that completed
+    pub(crate) async fn clear_checkpoint(&self, version: &str) -> Result<(), MigrationError> {
+        let query = format!("DELETE FROM {} WHERE version = ?", self.checkpoint_table());
+        self.session().query(query, (version,)).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cancel_then_resume() {
+        let migration = MigrationFile::for_test(
+            "20250115_001_users",
+            "-- +migrate Up\nUSE app;\nCREATE TABLE a (id int PRIMARY KEY);\nCREATE TABLE b (id int PRIMARY KEY);\nCREATE TABLE c (id int PRIMARY KEY);\n",
+        );
+        let statements = ["USE app", "CREATE TABLE a (id int PRIMARY KEY)", "CREATE TABLE b (id int PRIMARY KEY)", "CREATE TABLE c (id int PRIMARY KEY)"];
+
+        // The first run is cancelled before its third statement
+        let first = Checkpoint::resume(None, &migration);
+        assert!(statements.iter().enumerate().all(|(index, statement)| first.runs(index, statement)));
+        let saved = first.at(2);
+
+        // The next run selects the keyspace again and skips the created table
+        let resumed = Checkpoint::resume(Some(saved.clone()), &migration);
+        let executed: Vec<&str> = statements
+            .iter()
+            .enumerate()
+            .filter(|(index, statement)| resumed.runs(*index, statement))
+            .map(|(_, statement)| *statement)
+            .collect();
+        assert_eq!(executed, vec!["USE app", "CREATE TABLE b (id int PRIMARY KEY)", "CREATE TABLE c (id int PRIMARY KEY)"]);
+
+        // An edited file runs from the start
+        let edited = MigrationFile::for_test("20250115_001_users", "-- +migrate Up\nCREATE TABLE d (id int PRIMARY KEY);\n");
+        assert_eq!(Checkpoint::resume(Some(saved), &edited).next_statement, 0);
+    }
+}
@@ -0,0 +1,185 @@
+//! What the nodes of the cluster report about themselves
+//!
+//! `system.local` describes the coordinator and `system.peers` every other node
+//! as the coordinator sees it. Schema versions come from the same tables, so a
+//! node that has not received a schema change shows up with an older version.
+//! Whether a node is up comes from ScyllaDB's `system.cluster_status` and is
+//! unknown on servers without it.
+
+use crate::MigrationError;
+use scylla::Session;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// One node of the cluster
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NodeInfo {
+    pub address: Option<IpAddr>,
+    pub host_id: Option<Uuid>,
+    pub datacenter: Option<String>,
+    pub rack: Option<String>,
+    pub schema_version: Option<Uuid>,
+    /// Server version, e.g. `5.4.3`
+    pub release_version: Option<String>,
+    /// Whether gossip considers the node up; `None` if unknown
+    pub up: Option<bool>,
+    /// Whether this is the node that answered (`system.local`)
+    pub coordinator: bool,
+}
+
+impl NodeInfo {
+    /// Address of the node, or its host ID when the address is unknown
+    pub fn label(&self) -> String {
+        match (self.address, self.host_id) {
+            (Some(address), _) => address.to_string(),
+            (None, Some(host_id)) => host_id.to_string(),
+            (None, None) => "unknown".to_string(),
+        }
+    }
+}
+
+/// Whether the nodes agree on one schema version
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SchemaAgreement {
+    /// Version held by most nodes (the coordinator's on a tie)
+    pub schema_version: Option<Uuid>,
+    /// Nodes with another version, or none at all
+    pub dissenting: Vec<NodeInfo>,
+    pub nodes: usize,
+}
+
+impl SchemaAgreement {
+    pub fn of(nodes: &[NodeInfo]) -> Self {
+        let mut counts: BTreeMap<Option<Uuid>, (usize, bool)> = BTreeMap::new();
+        for node in nodes {
+            let entry = counts.entry(node.schema_version).or_default();
+            entry.0 += 1;
+            entry.1 |= node.coordinator;
+        }
+        let schema_version = counts
+            .into_iter()
+            .max_by_key(|(_, (count, coordinator))| (*count, *coordinator))
+            .and_then(|(version, _)| version);
+
+        Self {
+            schema_version,
+            dissenting: nodes
+                .iter()
+                .filter(|node| node.schema_version.is_none() || node.schema_version != schema_version)
+                .cloned()
+                .collect(),
+            nodes: nodes.len(),
+        }
+    }
+
+    pub fn agreed(&self) -> bool {
+        self.dissenting.is_empty() && self.schema_version.is_some()
+    }
+}
+
+/// The coordinator and its peers
+pub async fn nodes(session: &Session) -> Result<Vec<NodeInfo>, MigrationError> {
+    type Row = (
+        Option<IpAddr>,
+        Option<Uuid>,
+        Option<String>,
+        Option<String>,
+        Option<Uuid>,
+        Option<String>,
+    );
+    let mut nodes = Vec::new();
+    let queries = [
+        ("SELECT broadcast_address, host_id, data_center, rack, schema_version, release_version FROM system.local", true),
+        ("SELECT peer, host_id, data_center, rack, schema_version, release_version FROM system.peers", false),
+    ];
+    for (query, coordinator) in queries {
+        let rows = session.query(query, &[]).await?;
+        for row in rows
+            .rows_typed::<Row>()
+            .map_err(|e| MigrationError::IntegrityError(e.to_string()))?
+        {
+            let (address, host_id, datacenter, rack, schema_version, release_version) =
+                row.map_err(|e| MigrationError::IntegrityError(e.to_string()))?;
+            nodes.push(NodeInfo {
+                address,
+                host_id,
+                datacenter,
+                rack,
+                schema_version,
+                release_version,
+                up: None,
+                coordinator,
+            });
+        }
+    }
+
+    for (address, up) in cluster_status(session).await {
+        if let Some(node) = nodes.iter_mut().find(|node| node.address == Some(address)) {
+            node.up = Some(up);
+        }
+    }
+    Ok(nodes)
+}
+
+/// Liveness of each node by address; empty where `system.cluster_status` is missing
+async fn cluster_status(session: &Session) -> Vec<(IpAddr, bool)> {
+    let Ok(rows) = session.query("SELECT peer, up FROM system.cluster_status", &[]).await else {
+        return Vec::new();
+    };
+    rows.rows_typed::<(IpAddr, Option<bool>)>()
+        .map(|rows| {
+            rows.filter_map(|row| row.ok())
+                .filter_map(|(address, up)| up.map(|up| (address, up)))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Poll the nodes until they agree or `timeout` passes, returning the last state
+pub async fn wait_for_agreement(
+    session: &Session,
+    timeout: Duration,
+    interval: Duration,
+) -> Result<SchemaAgreement, MigrationError> {
+    let started = Instant::now();
+    loop {
+        let agreement = SchemaAgreement::of(&nodes(session).await?);
+        if agreement.agreed() || started.elapsed() >= timeout {
+            return Ok(agreement);
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schema_agreement() {
+        let node = |last: u8, version: Option<u128>, coordinator: bool| NodeInfo {
+            address: Some(IpAddr::from([10, 0, 0, last])),
+            host_id: None,
+            datacenter: Some("dc1".to_string()),
+            rack: None,
+            schema_version: version.map(Uuid::from_u128),
+            release_version: None,
+            up: Some(true),
+            coordinator,
+        };
+
+        let agreed = SchemaAgreement::of(&[node(1, Some(7), true), node(2, Some(7), false)]);
+        assert!(agreed.agreed());
+        assert_eq!(agreed.schema_version, Some(Uuid::from_u128(7)));
+
+        let split = SchemaAgreement::of(&[node(1, Some(7), true), node(2, Some(8), false), node(3, Some(7), false)]);
+        assert!(!split.agreed());
+        assert_eq!(split.dissenting, vec![node(2, Some(8), false)]);
+
+        let unknown = SchemaAgreement::of(&[node(1, Some(7), true), node(2, None, false)]);
+        assert_eq!(unknown.dissenting.len(), 1);
+    }
+}
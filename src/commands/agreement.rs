@@ -0,0 +1,100 @@
+use crate::{
+    cluster::{self, SchemaAgreement},
+    migration::MigrationManager,
+    progress,
+    CommandOutput, MigrationError,
+};
+use anyhow::Result;
+use clap::Args;
+use colored::*;
+use std::time::Duration;
+
+#[derive(Args)]
+pub struct AgreementCommand {
+    /// Wait until all nodes agree instead of reporting the current state
+    #[arg(long)]
+    wait: bool,
+
+    /// With --wait, give up after this many seconds (default: behavior.timeout_seconds)
+    #[arg(long, requires = "wait", value_name = "SECONDS")]
+    timeout: Option<u64>,
+}
+
+impl AgreementCommand {
+    pub async fn execute(&self, manager: &MigrationManager) -> Result<CommandOutput> {
+        let agreement = if self.wait {
+            let timeout = self.timeout.unwrap_or(manager.get_config().behavior.timeout_seconds);
+            progress!("{} Waiting up to {}s for schema agreement...", "⏳".yellow(), timeout);
+            cluster::wait_for_agreement(manager.session(), Duration::from_secs(timeout), Duration::from_secs(1)).await?
+        } else {
+            SchemaAgreement::of(&cluster::nodes(manager.session()).await?)
+        };
+
+        let version = agreement
+            .schema_version
+            .map(|version| version.to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+        let porcelain = agreement
+            .dissenting
+            .iter()
+            .map(|node| {
+                vec![
+                    node.label(),
+                    node.datacenter.clone().unwrap_or_default(),
+                    node.schema_version.map(|version| version.to_string()).unwrap_or_default(),
+                ]
+            })
+            .collect();
+        let data = serde_json::json!({
+            "agreed": agreement.agreed(),
+            "schema_version": agreement.schema_version,
+            "nodes": agreement.nodes,
+            "dissenting": agreement.dissenting
+        });
+
+        if agreement.agreed() {
+            return Ok(CommandOutput::success_with_data(
+                format!(
+                    "{} All {} node(s) agree on schema version {}",
+                    "✅".green(),
+                    agreement.nodes,
+                    version.bright_cyan()
+                ),
+                data,
+            )
+            .with_porcelain(porcelain));
+        }
+
+        let mut output = vec![format!(
+            "{} Schema disagreement: {} of {} node(s) differ from schema version {}",
+            "❌".red(),
+            agreement.dissenting.len(),
+            agreement.nodes,
+            version.bright_cyan()
+        )];
+        for node in &agreement.dissenting {
+            output.push(format!(
+                "  • {} {} {}",
+                node.label().bright_cyan(),
+                node.datacenter.as_deref().unwrap_or("-").dimmed(),
+                node.schema_version
+                    .map(|version| version.to_string())
+                    .unwrap_or_else(|| "no schema version (node down?)".to_string())
+            ));
+        }
+        if !self.wait {
+            output.push(format!(
+                "{} Use --wait to wait for the nodes to converge",
+                "💡".bright_blue()
+            ));
+        }
+
+        let e = MigrationError::SchemaDisagreement {
+            dissenting: agreement.dissenting.len(),
+            nodes: agreement.nodes,
+        };
+        Ok(CommandOutput::error_with_data(output.join("\n"), data)
+            .with_error(&e)
+            .with_porcelain(porcelain))
+    }
+}
@@ -0,0 +1,48 @@
+use crate::{
+    approval::{approval_secret, sign},
+    config::Config,
+    CommandOutput, MigrationError,
+};
+use anyhow::Result;
+use clap::Args;
+use colored::*;
+
+#[derive(Args)]
+pub struct ApproveCommand {
+    /// Plan id printed by `up` (or posted to the approval webhook)
+    plan_id: String,
+}
+
+impl ApproveCommand {
+    pub async fn execute(&self, config: &Config) -> Result<CommandOutput> {
+        let Some(secret) = approval_secret(config) else {
+            let e = MigrationError::ConfigError(format!(
+                "{} is not set; approval tokens are signed with the secret it holds",
+                config.approval.secret_env
+            ));
+            return Ok(CommandOutput::error_with_data(
+                format!("{} {}", "❌".red(), e),
+                serde_json::json!({
+                    "plan_id": self.plan_id,
+                    "error": e.to_string(),
+                    "error_code": e.code()
+                }),
+            )
+            .with_error(&e));
+        };
+
+        let token = sign(&secret, self.plan_id.trim())?;
+        Ok(CommandOutput::success_with_data(
+            format!(
+                "{} Approved plan {}\n\nApply it with: db-migrate up --approval-token {}",
+                "✅".green(),
+                self.plan_id.bright_cyan(),
+                token
+            ),
+            serde_json::json!({
+                "plan_id": self.plan_id,
+                "token": token
+            }),
+        ))
+    }
+}
@@ -0,0 +1,215 @@
+use crate::{
+    estimate::format_duration_ms,
+    migration::{MigrationManager, StatementTiming},
+    progress, scratch, CommandOutput,
+};
+use anyhow::Result;
+use clap::Args;
+use colored::*;
+use std::collections::HashSet;
+
+#[derive(Args)]
+pub struct BenchCommand {
+    /// Prefix for the scratch keyspace name
+    #[arg(long, default_value = "bench")]
+    prefix: String,
+
+    /// Keep the scratch keyspace instead of dropping it afterwards
+    #[arg(long)]
+    keep: bool,
+
+    /// Number of slowest statements to list
+    #[arg(long, default_value = "5")]
+    top: usize,
+}
+
+/// Measured cost of one pending migration
+struct MigrationBench {
+    version: String,
+    statements: Vec<StatementTiming>,
+}
+
+impl MigrationBench {
+    fn execution_ms(&self) -> f64 {
+        self.statements.iter().map(|t| t.execution_ms).sum()
+    }
+
+    fn schema_agreement_ms(&self) -> f64 {
+        self.statements.iter().filter_map(|t| t.schema_agreement_ms).sum()
+    }
+
+    fn total_ms(&self) -> f64 {
+        self.execution_ms() + self.schema_agreement_ms()
+    }
+}
+
+impl BenchCommand {
+    pub async fn execute(&self, manager: &MigrationManager) -> Result<CommandOutput> {
+        let applied: HashSet<String> = manager
+            .get_applied_migrations()
+            .await?
+            .into_iter()
+            .map(|record| record.version)
+            .collect();
+        let files = manager.get_migration_files().await?;
+        let (setup, pending): (Vec<_>, Vec<_>) =
+            files.iter().partition(|file| applied.contains(&file.version));
+
+        if pending.is_empty() {
+            return Ok(CommandOutput::success(format!(
+                "{} No pending migrations to benchmark",
+                "✅".green()
+            )));
+        }
+
+        let keyspace = scratch::keyspace_name(&self.prefix)?;
+        let real_keyspace = manager.get_config().database.keyspace.clone();
+        let retarget = |migration: &crate::MigrationFile| {
+            scratch::retarget_migration(migration, &real_keyspace, &keyspace)
+        };
+        let setup = match setup.into_iter().map(retarget).collect::<Result<Vec<_>, _>>() {
+            Ok(setup) => setup,
+            Err(e) => return Ok(CommandOutput::error(format!("{} {}", "❌".red(), e)).with_error(&e)),
+        };
+        let pending = match pending.into_iter().map(retarget).collect::<Result<Vec<_>, _>>() {
+            Ok(pending) => pending,
+            Err(e) => return Ok(CommandOutput::error(format!("{} {}", "❌".red(), e)).with_error(&e)),
+        };
+
+        let mut bench_config = manager.get_config().clone();
+        bench_config.database.keyspace = keyspace.clone();
+        bench_config.migrations.tracking_keyspace = None;
+        bench_config.behavior.auto_create_keyspace = true;
+        // The benchmark owns its keyspace, so it may always drop it
+        bench_config.behavior.allow_destructive = true;
+
+        let mut scratch = MigrationManager::builder(bench_config)
+            .cancellation_token(manager.cancellation_token())
+            .measure_statements(true)
+            .build()
+            .await?;
+
+        progress!(
+            "{} Benchmarking {} pending migration(s) in scratch keyspace {}",
+            "⏱️ ".cyan(),
+            pending.len(),
+            keyspace.bright_cyan()
+        );
+
+        // Bring the scratch keyspace to the current schema first; only the
+        // pending migrations are measured
+        for migration in &setup {
+            if let Err(e) = scratch.apply_migration(migration).await {
+                return self.failed(&mut scratch, &keyspace, &migration.version, e).await;
+            }
+        }
+        scratch.take_statement_timings();
+
+        let mut results = Vec::new();
+        for migration in &pending {
+            if let Err(e) = scratch.apply_migration(migration).await {
+                return self.failed(&mut scratch, &keyspace, &migration.version, e).await;
+            }
+            let bench = MigrationBench {
+                version: migration.version.clone(),
+                statements: scratch.take_statement_timings(),
+            };
+            progress!(
+                "  {} {:>10.1} ms  ({} statement(s), {:.1} ms schema agreement)",
+                bench.version.bright_cyan(),
+                bench.total_ms(),
+                bench.statements.len(),
+                bench.schema_agreement_ms()
+            );
+            results.push(bench);
+        }
+
+        if !self.keep {
+            scratch.drop_keyspace().await?;
+        }
+
+        Ok(self.report(&keyspace, &results))
+    }
+
+    fn report(&self, keyspace: &str, results: &[MigrationBench]) -> CommandOutput {
+        let execution_ms: f64 = results.iter().map(MigrationBench::execution_ms).sum();
+        let agreement_ms: f64 = results.iter().map(MigrationBench::schema_agreement_ms).sum();
+        let total_ms = execution_ms + agreement_ms;
+
+        let mut slowest: Vec<_> = results.iter().flat_map(|r| &r.statements).collect();
+        slowest.sort_by(|a, b| {
+            let total = |t: &StatementTiming| t.execution_ms + t.schema_agreement_ms.unwrap_or(0.0);
+            total(b).total_cmp(&total(a))
+        });
+        slowest.truncate(self.top);
+
+        let mut output = vec![
+            format!("{} Benchmark report", "📊".bright_blue()),
+            format!("  Statement execution: {:.1} ms", execution_ms),
+            format!("  Schema agreement:    {:.1} ms", agreement_ms),
+            format!("  Estimated duration:  {}", format_duration_ms(total_ms).bright_green()),
+        ];
+        if !slowest.is_empty() {
+            output.push(format!("\n{} Slowest statements:", "🐢".yellow()));
+            for timing in &slowest {
+                output.push(format!(
+                    "  {:>10.1} ms  {} #{}: {}",
+                    timing.execution_ms + timing.schema_agreement_ms.unwrap_or(0.0),
+                    timing.version.bright_cyan(),
+                    timing.index + 1,
+                    timing.statement.replace('\n', " ").dimmed()
+                ));
+            }
+        }
+        if self.keep {
+            output.push(format!(
+                "\n{} Scratch keyspace {} kept. Drop it with: DROP KEYSPACE {};",
+                "💡".bright_blue(),
+                keyspace.bright_cyan(),
+                keyspace
+            ));
+        }
+
+        CommandOutput::success_with_data(
+            output.join("\n"),
+            serde_json::json!({
+                "keyspace": keyspace,
+                "kept": self.keep,
+                "execution_ms": execution_ms,
+                "schema_agreement_ms": agreement_ms,
+                "total_ms": total_ms,
+                "migrations": results.iter().map(|r| serde_json::json!({
+                    "version": r.version,
+                    "execution_ms": r.execution_ms(),
+                    "schema_agreement_ms": r.schema_agreement_ms(),
+                    "total_ms": r.total_ms(),
+                    "statements": r.statements
+                })).collect::<Vec<_>>(),
+                "slowest_statements": slowest
+            }),
+        )
+    }
+
+    async fn failed(
+        &self,
+        scratch: &mut MigrationManager,
+        keyspace: &str,
+        version: &str,
+        e: crate::MigrationError,
+    ) -> Result<CommandOutput> {
+        if !self.keep {
+            scratch.drop_keyspace().await?;
+        }
+        Ok(CommandOutput::error_with_data(
+            format!("{} Benchmark migration {} failed: {}", "❌".red(), version, e),
+            serde_json::json!({
+                "keyspace": keyspace,
+                "failed_migration": version,
+                "error": e.to_string(),
+                "error_code": e.code(),
+                "kept": self.keep
+            }),
+        )
+        .with_error(&e))
+    }
+}
@@ -0,0 +1,159 @@
+use crate::{
+    drift::normalize_identifier,
+    migration::MigrationManager,
+    schema::{retarget_keyspace, SchemaIntrospector},
+    CommandOutput,
+};
+use anyhow::Result;
+use clap::Args;
+use colored::*;
+
+#[derive(Args)]
+pub struct CloneSchemaCommand {
+    /// Source keyspace (defaults to the configured keyspace)
+    #[arg(long)]
+    from: Option<String>,
+
+    /// Target keyspace, which must not exist yet
+    #[arg(long)]
+    to: String,
+
+    /// Also copy the migration tracking table so the clone is up to date
+    #[arg(long)]
+    with_history: bool,
+
+    /// Print the statements without executing them
+    #[arg(long)]
+    dry_run: bool,
+}
+
+impl CloneSchemaCommand {
+    pub async fn execute(&self, manager: &MigrationManager) -> Result<CommandOutput> {
+        let config = manager.get_config();
+        let from = self.from.as_deref().unwrap_or(&config.database.keyspace);
+        let tracking_table = &config.migrations.table_name;
+
+        if self.with_history && config.migrations.tracking_keyspace.is_some() {
+            return Ok(CommandOutput::error(format!(
+                "{} --with-history is not supported when the tracking table lives in migrations.tracking_keyspace",
+                "❌".red()
+            )));
+        }
+
+        for keyspace in [from, self.to.as_str()] {
+            if let Err(e) = crate::cql::validate_identifier(keyspace) {
+                return Ok(CommandOutput::error(format!(
+                    "{} Invalid keyspace name: {}",
+                    "❌".red(),
+                    e
+                )));
+            }
+        }
+
+        if from == self.to {
+            return Ok(CommandOutput::error(format!(
+                "{} Source and target keyspace are the same",
+                "❌".red()
+            )));
+        }
+
+        let session = manager.session();
+        if !SchemaIntrospector::new(session, from).keyspace_exists().await? {
+            return Ok(CommandOutput::error(format!(
+                "{} Source keyspace '{}' does not exist",
+                "❌".red(),
+                from
+            )));
+        }
+        if SchemaIntrospector::new(session, &self.to).keyspace_exists().await? {
+            return Ok(CommandOutput::error(format!(
+                "{} Target keyspace '{}' already exists",
+                "❌".red(),
+                self.to
+            )));
+        }
+
+        let objects = SchemaIntrospector::new(session, from).describe_keyspace().await?;
+        let statements: Vec<String> = objects
+            .iter()
+            .filter(|object| {
+                let is_tracking_table =
+                    object.object_type == "table" && object.name == normalize_identifier(tracking_table);
+                self.with_history || !is_tracking_table
+            })
+            .map(|object| {
+                retarget_keyspace(
+                    &object.create_statement,
+                    &normalize_identifier(from),
+                    &normalize_identifier(&self.to),
+                )
+            })
+            .collect();
+
+        if self.dry_run {
+            let mut output = vec![format!(
+                "{} Dry run - would execute {} statement(s) to clone '{}' into '{}':",
+                "🔍".cyan(),
+                statements.len(),
+                from,
+                self.to
+            )];
+            output.push(String::new());
+            output.extend(statements.iter().cloned());
+
+            return Ok(CommandOutput::success_with_data(
+                output.join("\n"),
+                serde_json::json!({
+                    "from": from,
+                    "to": self.to,
+                    "dry_run": true,
+                    "statements": statements
+                }),
+            ));
+        }
+
+        manager.ensure_writable()?;
+        for statement in &statements {
+            session.query(statement.as_str(), &[]).await?;
+        }
+        session.await_schema_agreement().await?;
+
+        let mut history_rows = 0;
+        if self.with_history {
+            let select = format!("SELECT JSON * FROM {}.{}", from, tracking_table);
+            let insert = format!("INSERT INTO {}.{} JSON ?", self.to, tracking_table);
+
+            let rows = session.query(select, &[]).await?;
+            for row in rows
+                .rows_typed::<(String,)>()
+                .map_err(|e| anyhow::anyhow!(e.to_string()))?
+            {
+                let (json,) = row.map_err(|e| anyhow::anyhow!(e.to_string()))?;
+                session.query(insert.as_str(), (json,)).await?;
+                history_rows += 1;
+            }
+        }
+
+        let mut message = format!(
+            "{} Cloned {} schema object(s) from '{}' into '{}'",
+            "✅".green(),
+            statements.len(),
+            from.bright_cyan(),
+            self.to.bright_cyan()
+        );
+        if self.with_history {
+            message.push_str(&format!(" with {} migration record(s)", history_rows));
+        }
+
+        Ok(CommandOutput::success_with_data(
+            message,
+            serde_json::json!({
+                "from": from,
+                "to": self.to,
+                "objects": statements.len(),
+                "history_rows": history_rows,
+                "with_history": self.with_history
+            }),
+        ))
+    }
+}
@@ -0,0 +1,47 @@
+use crate::{config::Config, CommandOutput};
+use anyhow::Result;
+use clap::{Args, Subcommand};
+use colored::*;
+
+#[derive(Args)]
+pub struct ConfigCommand {
+    #[command(subcommand)]
+    action: ConfigAction,
+}
+
+#[derive(Subcommand)]
+enum ConfigAction {
+    /// Print a JSON Schema for the configuration file (for editor completion and CI checks)
+    Schema,
+    /// Check the configuration file, including environment overrides
+    Validate,
+}
+
+impl ConfigCommand {
+    /// Runs before the configuration is loaded, so it works with a broken file
+    pub async fn execute(&self, config_path: &str) -> Result<CommandOutput> {
+        match self.action {
+            #[cfg(feature = "config-schema")]
+            ConfigAction::Schema => {
+                let schema = Config::json_schema();
+                Ok(CommandOutput::success_with_data(
+                    serde_json::to_string_pretty(&schema)?,
+                    schema,
+                ))
+            }
+            #[cfg(not(feature = "config-schema"))]
+            ConfigAction::Schema => Ok(CommandOutput::error(format!(
+                "{} db-migrate was built without the config-schema feature",
+                "❌".red()
+            ))),
+            ConfigAction::Validate => match Config::load(config_path).await {
+                Ok(_) => Ok(CommandOutput::success(format!(
+                    "{} {} is valid",
+                    "✅".green(),
+                    config_path.bright_cyan()
+                ))),
+                Err(e) => Ok(CommandOutput::error(format!("{} {}", "❌".red(), e))),
+            },
+        }
+    }
+}
@@ -1,41 +1,204 @@
-use crate::{migration::MigrationManager, CommandOutput};
+use crate::{
+    config::Config,
+    drift::split_qualified,
+    migration::{active_migration_files, create_migration_file, MigrationManager},
+    schema::SchemaIntrospector,
+    schemadiff::{SchemaChanges, SchemaDump},
+    template::table_template,
+    utils::{normalize_description, MigrationTemplate},
+    CommandOutput, MigrationError,
+};
 use anyhow::Result;
 use clap::Args;
 use colored::*;
+use std::path::{Path, PathBuf};
 
 #[derive(Args)]
 pub struct CreateCommand {
     /// Description of the migration
     description: String,
+
+    /// Mark the migration as requiring a DOWN section (checked by verify and lint)
+    #[arg(long)]
+    require_down: bool,
+
+    /// Generate the DOWN section from the UP statements when the migration is
+    /// first applied (CREATE TABLE/INDEX/TYPE/MATERIALIZED VIEW only)
+    #[arg(long)]
+    auto_down: bool,
+
+    /// Template for the new file
+    #[arg(long, value_enum, default_value_t = MigrationTemplate::Default)]
+    template: MigrationTemplate,
+
+    /// Pre-fill the migration with the current DDL of an existing table
+    /// (`table` or `keyspace.table`), for ALTER-style changes
+    #[arg(long, value_name = "TABLE", conflicts_with = "template")]
+    from_table: Option<String>,
+
+    /// Fill the migration with the statements turning one schema dump (e.g.
+    /// `DESCRIBE KEYSPACE` output) into another, with the reverse as DOWN
+    #[arg(
+        long,
+        num_args = 2,
+        value_names = ["OLD", "NEW"],
+        conflicts_with_all = ["template", "from_table", "auto_down"]
+    )]
+    from_diff: Option<Vec<PathBuf>>,
 }
 
 impl CreateCommand {
-    pub async fn execute(&self, manager: &MigrationManager) -> Result<CommandOutput> {
+    pub async fn execute(&self, config: &Config) -> Result<CommandOutput> {
         // Validate description
         if self.description.trim().is_empty() {
             return Ok(CommandOutput::error("Migration description cannot be empty"));
         }
+        if let Err(e) = normalize_description(&self.description) {
+            return Ok(CommandOutput::error(e));
+        }
+
+        let body = match &self.from_table {
+            Some(table) => match self.table_body(config, table).await? {
+                Some(body) => Some(body),
+                None => {
+                    return Ok(CommandOutput::error(format!(
+                        "{} Table {} does not exist",
+                        "❌".red(),
+                        table
+                    )))
+                }
+            },
+            None => None,
+        };
+        let (body, changes) = match &self.from_diff {
+            Some(paths) => {
+                let (old, new) = (&paths[0], &paths[1]);
+                let (old_dump, new_dump) = match (read_dump(old), read_dump(new)) {
+                    (Ok(old_dump), Ok(new_dump)) => (old_dump, new_dump),
+                    (Err(e), _) | (_, Err(e)) => {
+                        return Ok(CommandOutput::error(format!("{} {}", "❌".red(), e)).with_error(&e))
+                    }
+                };
+                let changes = old_dump.diff(&new_dump);
+                if changes.is_empty() {
+                    return Ok(CommandOutput::error(format!(
+                        "{} {} and {} describe the same schema; nothing to migrate",
+                        "❌".red(),
+                        old.display(),
+                        new.display()
+                    )));
+                }
+                let body = diff_body(old, new, &changes, &new_dump.diff(&old_dump));
+                (Some(body), Some(changes))
+            }
+            None => (body, None),
+        };
 
         // Create the migration file
-        let file_path = manager.create_migration_file(&self.description).await?;
+        let file_path = create_migration_file(
+            config,
+            &self.description,
+            self.template,
+            self.require_down,
+            self.auto_down,
+            body,
+        )
+        .await?;
 
         let filename = file_path
             .file_name()
             .and_then(|n| n.to_str())
             .unwrap_or("unknown");
 
-        let message = format!(
+        let mut message = format!(
             "{} Created migration file: {}",
             "✅".green(),
             filename.bright_cyan()
         );
 
+        // A clock running behind (or a file from a future-dated branch) puts the
+        // new migration before existing ones
+        let version = filename.trim_end_matches(".cql");
+        let scheme = config.version_scheme()?;
+        let sorts_after: Vec<String> = active_migration_files(config)
+            .await?
+            .into_iter()
+            .map(|file| file.version)
+            .filter(|existing| scheme.compare(existing, version).is_gt())
+            .collect();
+
+        if !sorts_after.is_empty() {
+            message.push_str(&format!(
+                "\n{} It sorts before {} existing migration(s) ({}); check your clock or rename the file",
+                "⚠️ ".yellow(),
+                sorts_after.len(),
+                sorts_after.join(", ")
+            ));
+        }
+
+        if let Some(changes) = &changes {
+            message.push_str(&format!(
+                "\n{} {} generated statement(s); review them before applying",
+                "🔍".cyan(),
+                changes.statements.len()
+            ));
+            for difference in &changes.unsupported {
+                message.push_str(&format!("\n{} Needs a hand-written change: {}", "⚠️ ".yellow(), difference));
+            }
+        }
+
         Ok(CommandOutput::success_with_data(
             message,
             serde_json::json!({
                 "file_path": file_path.to_string_lossy(),
-                "filename": filename
+                "filename": filename,
+                "sorts_before": sorts_after,
+                "generated_statements": changes.as_ref().map(|changes| &changes.statements),
+                "unsupported_changes": changes.as_ref().map(|changes| &changes.unsupported)
             })
         ))
     }
-}
\ No newline at end of file
+
+    /// Template body showing the table's current DDL, introspected from the database
+    async fn table_body(&self, config: &Config, table: &str) -> Result<Option<String>> {
+        let (keyspace, name) = split_qualified(table);
+        let keyspace = keyspace.unwrap_or(&config.database.keyspace);
+
+        let manager = MigrationManager::builder(config.clone())
+            .initialize_schema(false)
+            .build()
+            .await?;
+        let ddl = SchemaIntrospector::new(manager.session(), keyspace)
+            .describe_table(name)
+            .await?;
+
+        Ok(ddl.map(|ddl| table_template(&format!("{}.{}", keyspace, name), &ddl)))
+    }
+}
+
+fn read_dump(path: &Path) -> Result<SchemaDump, MigrationError> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| MigrationError::ConfigError(format!("Failed to read {}: {}", path.display(), e)))?;
+    SchemaDump::parse(&content)
+        .map_err(|e| MigrationError::InvalidFormat(format!("Cannot parse {}: {}", path.display(), e)))
+}
+
+/// UP and DOWN sections of a migration generated from two dumps
+fn diff_body(old: &Path, new: &Path, up: &SchemaChanges, down: &SchemaChanges) -> String {
+    let section = |changes: &SchemaChanges| {
+        let mut lines: Vec<String> = changes
+            .statements
+            .iter()
+            .map(|statement| format!("{};", statement))
+            .collect();
+        lines.extend(changes.unsupported.iter().map(|difference| format!("-- TODO: {}", difference)));
+        lines.join("\n\n")
+    };
+    format!(
+        "-- +migrate Up\n-- Generated from the difference between {} and {}; review before applying\n{}\n\n-- +migrate Down\n{}\n",
+        old.display(),
+        new.display(),
+        section(up),
+        section(down)
+    )
+}
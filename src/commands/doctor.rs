@@ -0,0 +1,210 @@
+use crate::{
+    cluster::{self, NodeInfo, SchemaAgreement},
+    migration::MigrationManager,
+    schema::SchemaIntrospector,
+    CommandOutput,
+};
+use anyhow::Result;
+use clap::Args;
+use colored::*;
+use std::collections::BTreeSet;
+
+#[derive(Args)]
+pub struct DoctorCommand {}
+
+impl DoctorCommand {
+    pub async fn execute(&self, manager: &MigrationManager) -> Result<CommandOutput> {
+        let config = manager.get_config();
+        let nodes = cluster::nodes(manager.session()).await?;
+        let agreement = SchemaAgreement::of(&nodes);
+
+        let mut output = vec![format!(
+            "{} Connected to cluster {}",
+            "🩺".bright_blue(),
+            manager.cluster_name().unwrap_or("(unnamed)").bright_cyan()
+        )];
+        output.push(String::new());
+        output.push("Nodes:".bold().to_string());
+        output.push("─".repeat(30));
+        output.extend(format_nodes(&nodes, &agreement));
+
+        let (warnings, mut problems) = node_health(&nodes, &agreement);
+
+        let mut keyspaces = vec![config.database.keyspace.as_str()];
+        if config.tracking_keyspace() != config.database.keyspace {
+            keyspaces.push(config.tracking_keyspace());
+        }
+        let mut missing = Vec::new();
+        for keyspace in keyspaces {
+            if !SchemaIntrospector::new(manager.session(), keyspace).keyspace_exists().await? {
+                missing.push(keyspace);
+            }
+        }
+        let auto_create = config.behavior.auto_create_keyspace
+            && config.replication.allows_auto_create(config.behavior.environment.as_deref());
+        if !missing.is_empty() && !auto_create {
+            problems.push(format!(
+                "keyspace(s) {} do not exist; see 'db-migrate keyspace create'",
+                missing.join(", ")
+            ));
+        }
+
+
+        output.push(String::new());
+        for warning in &warnings {
+            output.push(format!("{} {}", "⚠️ ".yellow(), warning));
+        }
+        for problem in &problems {
+            output.push(format!("{} {}", "❌".red(), problem));
+        }
+
+        let data = serde_json::json!({
+            "cluster_name": manager.cluster_name(),
+            "nodes": nodes,
+            "schema_agreement": agreement.agreed(),
+            "schema_version": agreement.schema_version,
+            "missing_keyspaces": missing,
+            "warnings": warnings,
+            "problems": problems
+        });
+        let porcelain = nodes
+            .iter()
+            .map(|node| {
+                vec![
+                    node.label(),
+                    node.datacenter.clone().unwrap_or_default(),
+                    node.rack.clone().unwrap_or_default(),
+                    match node.up {
+                        Some(true) => "up".to_string(),
+                        Some(false) => "down".to_string(),
+                        None => "unknown".to_string(),
+                    },
+                    node.release_version.clone().unwrap_or_default(),
+                    node.schema_version.map(|version| version.to_string()).unwrap_or_default(),
+                ]
+            })
+            .collect();
+        if problems.is_empty() {
+            output.push(format!("{} The cluster looks healthy", "✅".green()));
+            Ok(CommandOutput::success_with_data(output.join("\n"), data).with_porcelain(porcelain))
+        } else {
+            Ok(CommandOutput::error_with_data(output.join("\n"), data).with_porcelain(porcelain))
+        }
+    }
+}
+
+/// Warnings and problems of the nodes themselves: nodes down, schema
+/// disagreement, mixed server versions and unknown liveness
+fn node_health(nodes: &[NodeInfo], agreement: &SchemaAgreement) -> (Vec<String>, Vec<String>) {
+    let mut problems = Vec::new();
+    let down: Vec<&NodeInfo> = nodes.iter().filter(|node| node.up == Some(false)).collect();
+    if !down.is_empty() {
+        problems.push(format!(
+            "{} node(s) down: {}",
+            down.len(),
+            down.iter().map(|node| node.label()).collect::<Vec<_>>().join(", ")
+        ));
+    }
+    if !agreement.agreed() {
+        problems.push(format!(
+            "{} of {} node(s) disagree on the schema version; see 'db-migrate agreement'",
+            agreement.dissenting.len(),
+            agreement.nodes
+        ));
+    }
+
+    // Mixed server versions are expected during a rolling upgrade
+    let versions: BTreeSet<&str> = nodes.iter().filter_map(|node| node.release_version.as_deref()).collect();
+    let mut warnings = Vec::new();
+    if versions.len() > 1 {
+        warnings.push(format!(
+            "nodes run different versions ({}); is a rolling upgrade in progress?",
+            versions.into_iter().collect::<Vec<_>>().join(", ")
+        ));
+    }
+    if nodes.iter().all(|node| node.up.is_none()) {
+        warnings.push("node liveness is unknown (system.cluster_status is not available)".to_string());
+    }
+    (warnings, problems)
+}
+
+/// One line per node: address, DC/rack, state, server and schema version
+pub(crate) fn format_nodes(nodes: &[NodeInfo], agreement: &SchemaAgreement) -> Vec<String> {
+    nodes
+        .iter()
+        .map(|node| {
+            let state = match node.up {
+                Some(true) => "UP".green(),
+                Some(false) => "DOWN".red(),
+                None => "?".dimmed(),
+            };
+            let schema = match node.schema_version {
+                Some(version) if Some(version) == agreement.schema_version => "schema agreed".dimmed(),
+                Some(_) => "schema differs".yellow(),
+                None => "no schema version".red(),
+            };
+            format!(
+                "  {} {} {}/{} {} {}{}",
+                state,
+                node.label().bright_cyan(),
+                node.datacenter.as_deref().unwrap_or("-"),
+                node.rack.as_deref().unwrap_or("-"),
+                node.release_version.as_deref().unwrap_or("unknown version").dimmed(),
+                schema,
+                if node.coordinator { " (coordinator)".dimmed().to_string() } else { String::new() }
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::IpAddr;
+    use uuid::Uuid;
+
+    fn node(last: u8, up: Option<bool>, release: &str, version: u128) -> NodeInfo {
+        NodeInfo {
+            address: Some(IpAddr::from([10, 0, 0, last])),
+            host_id: None,
+            datacenter: Some("dc1".to_string()),
+            rack: Some("r1".to_string()),
+            schema_version: Some(Uuid::from_u128(version)),
+            release_version: Some(release.to_string()),
+            up,
+            coordinator: last == 1,
+        }
+    }
+
+    #[test]
+    fn test_node_health() {
+        let healthy = [node(1, Some(true), "5.4.3", 7), node(2, Some(true), "5.4.3", 7)];
+        let agreement = SchemaAgreement::of(&healthy);
+        assert_eq!(node_health(&healthy, &agreement), (Vec::new(), Vec::new()));
+
+        let lines = format_nodes(&healthy, &agreement);
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].contains("10.0.0.1") && lines[0].contains("dc1/r1") && lines[0].contains("(coordinator)"));
+        assert!(lines[1].contains("5.4.3") && !lines[1].contains("(coordinator)"));
+
+        // A node down with an older schema, mid rolling upgrade
+        let degraded = [node(1, Some(true), "5.4.3", 7), node(2, Some(false), "5.2.0", 6), node(3, Some(true), "5.4.3", 7)];
+        let agreement = SchemaAgreement::of(&degraded);
+        let (warnings, problems) = node_health(&degraded, &agreement);
+        assert_eq!(
+            problems,
+            vec![
+                "1 node(s) down: 10.0.0.2".to_string(),
+                "1 of 3 node(s) disagree on the schema version; see 'db-migrate agreement'".to_string(),
+            ]
+        );
+        assert_eq!(warnings, vec!["nodes run different versions (5.2.0, 5.4.3); is a rolling upgrade in progress?"]);
+        assert!(format_nodes(&degraded, &agreement)[1].contains("schema differs"));
+
+        // Without system.cluster_status nothing is known to be down
+        let unknown = [node(1, None, "5.4.3", 7)];
+        let (warnings, problems) = node_health(&unknown, &SchemaAgreement::of(&unknown));
+        assert!(problems.is_empty());
+        assert!(warnings[0].starts_with("node liveness is unknown"));
+    }
+}
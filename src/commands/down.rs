@@ -1,7 +1,11 @@
-use crate::{migration::MigrationManager, CommandOutput};
+use crate::{
+    diagnostics::FailureReport, migration::MigrationManager, progress, queue::DEFAULT_QUEUE_TIMEOUT,
+    report::DownResult, CommandOutput, MigrationError, MigrationFile, MigrationRecord,
+};
 use anyhow::Result;
 use clap::Args;
 use colored::*;
+use std::collections::HashSet;
 
 #[derive(Args)]
 pub struct DownCommand {
@@ -9,6 +13,14 @@ pub struct DownCommand {
     #[arg(short, long, default_value = "1")]
     count: usize,
 
+    /// Roll back only this applied migration, even if later ones are applied
+    #[arg(long, conflicts_with = "count")]
+    version: Option<String>,
+
+    /// Acknowledge that --version rolls back a migration that is not the latest
+    #[arg(long, requires = "version")]
+    allow_out_of_order: bool,
+
     /// Dry run mode - show what would be rolled back without executing
     #[arg(long)]
     dry_run: bool,
@@ -16,10 +28,34 @@ pub struct DownCommand {
     /// Force rollback even if DOWN section is missing (dangerous)
     #[arg(long)]
     force: bool,
+
+    /// Leave migrations marked `-- db-migrate:no-down` applied instead of failing
+    #[arg(long)]
+    skip_irreversible: bool,
+
+    /// Run the DOWN section stored in the migrations table instead of the file's
+    /// (requires `behavior.store_down_content` when the migration was applied)
+    #[arg(long)]
+    from_db: bool,
 }
 
 impl DownCommand {
+    /// Roll back the `count` most recently applied migrations
+    pub fn new(count: usize) -> Self {
+        Self {
+            count,
+            version: None,
+            allow_out_of_order: false,
+            dry_run: false,
+            force: false,
+            skip_irreversible: false,
+            from_db: false,
+        }
+    }
+
     pub async fn execute(&self, manager: &mut MigrationManager) -> Result<CommandOutput> {
+        let _run = manager.exclusive_run("down", DEFAULT_QUEUE_TIMEOUT).await?;
+
         let applied_migrations = manager.get_applied_migrations().await?;
 
         if applied_migrations.is_empty() {
@@ -29,141 +65,281 @@ impl DownCommand {
             )));
         }
 
-        // Get the most recent migrations to rollback (reverse order)
-        let migrations_to_rollback: Vec<_> = applied_migrations
-            .into_iter()
-            .rev()
-            .take(self.count)
-            .collect();
+        let files = manager.get_migration_files().await?;
+        let irreversible = irreversible_versions(&files);
+
+        let migrations_to_rollback: Vec<_> = if let Some(version) = &self.version {
+            match self.select_version(version, applied_migrations, &files) {
+                Ok(record) => vec![record],
+                Err(output) => return Ok(*output),
+            }
+        } else {
+            // Get the most recent migrations to rollback (reverse order)
+            applied_migrations
+                .into_iter()
+                .rev()
+                .take(self.count)
+                .collect()
+        };
 
         if self.dry_run {
-            return self.show_dry_run(&migrations_to_rollback);
+            return self.show_dry_run(&migrations_to_rollback, &irreversible);
+        }
+
+        let (result, stopped_with) = self.roll_back(manager, &migrations_to_rollback, &irreversible).await;
+        if let Some(message) = stopped_with {
+            return stopped(message, result);
+        }
+
+        let mut message = if result.rollback_count == 1 {
+            format!("{} Rolled back 1 migration successfully", "🎉".green())
+        } else {
+            format!("{} Rolled back {} migrations successfully", "🎉".green(), result.rollback_count)
+        };
+        if !result.skipped_migrations.is_empty() {
+            message.push_str(&format!(
+                " ({} irreversible migration(s) left applied)",
+                result.skipped_migrations.len()
+            ));
         }
 
-        let mut rollback_count = 0;
-        let mut rolled_back_migrations = Vec::new();
+        Ok(CommandOutput::success_with_data(message, serde_json::to_value(&result)?))
+    }
+
+    /// [`MigrationManager::down`]: roll back the latest `--count` migrations as
+    /// `execute` does
+    pub(crate) async fn apply(&self, manager: &mut MigrationManager) -> Result<DownResult, MigrationError> {
+        let _run = manager.exclusive_run("down", DEFAULT_QUEUE_TIMEOUT).await?;
+        let applied = manager.get_applied_migrations().await?;
+        let records: Vec<_> = applied.into_iter().rev().take(self.count).collect();
+        let irreversible = irreversible_versions(&manager.get_migration_files().await?);
+        Ok(self.roll_back(manager, &records, &irreversible).await.0)
+    }
+
+    /// Roll back `records` in order, stopping at the first that cannot be; the
+    /// message says why it stopped
+    async fn roll_back(
+        &self,
+        manager: &mut MigrationManager,
+        records: &[MigrationRecord],
+        irreversible: &HashSet<String>,
+    ) -> (DownResult, Option<String>) {
+        let mut result = DownResult::default();
+
+        for migration_record in records {
+            let version = &migration_record.version;
+            if self.skip_irreversible && irreversible.contains(version) {
+                progress!(
+                    "{} Skipped irreversible migration: {}",
+                    "⏭️ ".yellow(),
+                    version.bright_cyan()
+                );
+                result.skipped_migrations.push(version.clone());
+                continue;
+            }
+
+            let outcome = if self.from_db {
+                manager.rollback_migration_from_db(version).await
+            } else {
+                manager.rollback_migration(version).await
+            };
 
-        for migration_record in &migrations_to_rollback {
-            match manager.rollback_migration(&migration_record.version).await {
+            match outcome {
                 Ok(_) => {
-                    rollback_count += 1;
-                    rolled_back_migrations.push(&migration_record.version);
-                    println!(
+                    result.rolled_back(version);
+                    progress!(
                         "{} Rolled back migration: {}",
                         "✅".green(),
-                        migration_record.version.bright_cyan()
+                        version.bright_cyan()
                     );
                 }
-                Err(crate::MigrationError::RollbackError { version, reason }) => {
-                    if self.force {
-                        // Force rollback by just removing the record
-                        match manager.remove_migration_record(&version).await {
-                            Ok(_) => {
-                                rollback_count += 1;
-                                rolled_back_migrations.push(&migration_record.version);
-                                println!(
-                                    "{} Force rolled back migration: {} ({})",
-                                    "⚠️ ".yellow(),
-                                    version.bright_cyan(),
-                                    reason.dimmed()
-                                );
-                            }
-                            Err(e) => {
-                                let error_msg = format!(
-                                    "Failed to force rollback migration {}: {}",
-                                    version, e
-                                );
-
-                                return Ok(CommandOutput::success_with_data(
-                                    format!(
-                                        "{} Rolled back {} migration(s), failed on: {}",
-                                        if rollback_count > 0 { "⚠️ " } else { "❌" },
-                                        rollback_count,
-                                        version
-                                    ),
-                                    serde_json::json!({
-                                        "rollback_count": rollback_count,
-                                        "rolled_back_migrations": rolled_back_migrations,
-                                        "failed_migration": version,
-                                        "error": error_msg
-                                    })
-                                ));
-                            }
-                        }
-                    } else {
+                Err(e @ MigrationError::Irreversible { .. }) if !self.force => {
+                    let error_msg = format!(
+                        "Migration {} is marked irreversible (-- db-migrate:no-down). Use --skip-irreversible to leave it applied, or --force to remove the migration record anyway.",
+                        version
+                    );
+                    let message = format!(
+                        "{} Rolled back {} migration(s), stopped at irreversible migration: {}",
+                        if result.rollback_count > 0 { "⚠️ " } else { "❌" },
+                        result.rollback_count,
+                        version
+                    );
+                    result.irreversible = true;
+                    result.fail(version, error_msg, e);
+                    return (result, Some(message));
+                }
+                Err(e @ (MigrationError::RollbackError { .. } | MigrationError::Irreversible { .. })) => {
+                    let reason = match &e {
+                        MigrationError::RollbackError { reason, .. } => reason.clone(),
+                        _ => "declared irreversible".to_string(),
+                    };
+
+                    if !self.force {
                         let error_msg = format!(
                             "Cannot rollback migration {}: {}. Use --force to remove the migration record anyway.",
                             version, reason
                         );
+                        let message = format!(
+                            "{} Rolled back {} migration(s), failed on: {}",
+                            if result.rollback_count > 0 { "⚠️ " } else { "❌" },
+                            result.rollback_count,
+                            version
+                        );
+                        result.fail(version, error_msg, e);
+                        return (result, Some(message));
+                    }
 
-                        return Ok(CommandOutput::success_with_data(
-                            format!(
+                    // Force rollback by just removing the record
+                    match manager.remove_migration_record(version).await {
+                        Ok(_) => {
+                            result.rolled_back(version);
+                            progress!(
+                                "{} Force rolled back migration: {} ({})",
+                                "⚠️ ".yellow(),
+                                version.bright_cyan(),
+                                reason.dimmed()
+                            );
+                        }
+                        Err(remove_error) => {
+                            let error_msg = format!(
+                                "Failed to force rollback migration {}: {}",
+                                version, remove_error
+                            );
+                            let message = format!(
                                 "{} Rolled back {} migration(s), failed on: {}",
-                                if rollback_count > 0 { "⚠️ " } else { "❌" },
-                                rollback_count,
+                                if result.rollback_count > 0 { "⚠️ " } else { "❌" },
+                                result.rollback_count,
                                 version
-                            ),
-                            serde_json::json!({
-                                "rollback_count": rollback_count,
-                                "rolled_back_migrations": rolled_back_migrations,
-                                "failed_migration": version,
-                                "error": error_msg
-                            })
-                        ));
+                            );
+                            result.fail(version, error_msg, remove_error);
+                            return (result, Some(message));
+                        }
                     }
                 }
                 Err(e) => {
-                    let error_msg = format!(
-                        "Failed to rollback migration {}: {}",
-                        migration_record.version, e
+                    let error_msg = format!("Failed to rollback migration {}: {}", version, e);
+                    let report = FailureReport::new(manager, version, &e);
+                    let message = format!(
+                        "{} Rolled back {} migration(s), failed on: {}\n  {}",
+                        if result.rollback_count > 0 { "⚠️ " } else { "❌" },
+                        result.rollback_count,
+                        version,
+                        report.describe()
                     );
-
-                    return Ok(CommandOutput::success_with_data(
-                        format!(
-                            "{} Rolled back {} migration(s), failed on: {}",
-                            if rollback_count > 0 { "⚠️ " } else { "❌" },
-                            rollback_count,
-                            migration_record.version
-                        ),
-                        serde_json::json!({
-                            "rollback_count": rollback_count,
-                            "rolled_back_migrations": rolled_back_migrations,
-                            "failed_migration": migration_record.version,
-                            "error": error_msg
-                        })
-                    ));
+                    result.failure = Some(report);
+                    result.fail(version, error_msg, e);
+                    return (result, Some(message));
                 }
             }
         }
 
-        let message = if rollback_count == 1 {
-            format!("{} Rolled back 1 migration successfully", "🎉".green())
-        } else {
-            format!("{} Rolled back {} migrations successfully", "🎉".green(), rollback_count)
+        (result, None)
+    }
+
+    /// Pick the applied migration targeted by `--version`
+    ///
+    /// Rolling back a migration with later migrations on top of it requires
+    /// `--allow-out-of-order`; the later migrations, and those that declare a
+    /// dependency on it or share its tags, are reported either way.
+    fn select_version(
+        &self,
+        version: &str,
+        mut applied: Vec<MigrationRecord>,
+        files: &[MigrationFile],
+    ) -> Result<MigrationRecord, Box<CommandOutput>> {
+        let Some(position) = applied.iter().position(|m| m.version == version) else {
+            return Err(Box::new(CommandOutput::error(format!(
+                "{} Migration {} is not applied",
+                "❌".red(),
+                version
+            ))));
         };
 
-        Ok(CommandOutput::success_with_data(
-            message,
-            serde_json::json!({
-                "rollback_count": rollback_count,
-                "rolled_back_migrations": rolled_back_migrations
+        let later: Vec<&str> = applied[position + 1..]
+            .iter()
+            .map(|m| m.version.as_str())
+            .collect();
+        if later.is_empty() {
+            return Ok(applied.swap_remove(position));
+        }
+
+        let target_tags = files
+            .iter()
+            .find(|file| file.version == version)
+            .map(|file| file.directives.tags.clone())
+            .unwrap_or_default();
+        let dependents: Vec<&str> = files
+            .iter()
+            .filter(|file| later.contains(&file.version.as_str()))
+            .filter(|file| {
+                file.directives.depends_on.iter().any(|v| v == version)
+                    || file.directives.tags.iter().any(|tag| target_tags.contains(tag))
             })
-        ))
+            .map(|file| file.version.as_str())
+            .collect();
+
+        let mut warnings = vec![format!(
+            "{} {} is not the latest migration; {} migration(s) were applied after it: {}",
+            "⚠️ ".yellow(),
+            version.bright_cyan(),
+            later.len(),
+            later.join(", ")
+        )];
+        if !dependents.is_empty() {
+            warnings.push(format!(
+                "{} These depend on it or touch the same tags and may break: {}",
+                "⚠️ ".yellow(),
+                dependents.join(", ").bright_red()
+            ));
+        }
+
+        if !self.allow_out_of_order {
+            warnings.push(format!(
+                "{} Re-run with --allow-out-of-order to roll it back anyway",
+                "💡".bright_blue()
+            ));
+            return Err(Box::new(CommandOutput::error_with_data(
+                warnings.join("\n"),
+                serde_json::json!({
+                    "version": version,
+                    "applied_after": later,
+                    "dependents": dependents,
+                    "error": "Targeted rollback of a non-latest migration requires --allow-out-of-order"
+                }),
+            )));
+        }
+
+        for warning in &warnings {
+            progress!("{}", warning);
+        }
+        Ok(applied.swap_remove(position))
     }
 
-    fn show_dry_run(&self, migrations: &[crate::MigrationRecord]) -> Result<CommandOutput> {
+    fn show_dry_run(
+        &self,
+        migrations: &[MigrationRecord],
+        irreversible: &HashSet<String>,
+    ) -> Result<CommandOutput> {
         let mut output = vec![
             format!("{} Dry run mode - showing migrations that would be rolled back:", "🔍".cyan()),
             String::new(),
         ];
 
         for (i, migration) in migrations.iter().enumerate() {
+            let note = if !irreversible.contains(&migration.version) {
+                String::new()
+            } else if self.skip_irreversible {
+                format!(" {}", "[irreversible, skipped]".yellow())
+            } else {
+                format!(" {}", "[irreversible]".red())
+            };
             output.push(format!(
-                "{}. {} - {} (applied at: {})",
+                "{}. {} - {} (applied at: {}){}",
                 i + 1,
                 migration.version.bright_cyan(),
                 migration.description,
-                crate::utils::format_timestamp(migration.applied_at).dimmed()
+                crate::utils::format_timestamp(migration.applied_at).dimmed(),
+                note
             ));
         }
 
@@ -186,10 +362,72 @@ impl DownCommand {
                     serde_json::json!({
                         "version": m.version,
                         "description": m.description,
-                        "applied_at": m.applied_at
+                        "applied_at": m.applied_at,
+                        "irreversible": irreversible.contains(&m.version)
                     })
                 }).collect::<Vec<_>>()
             })
         ))
     }
 }
+
+/// Versions of the migrations marked `-- db-migrate:no-down`
+fn irreversible_versions(files: &[MigrationFile]) -> HashSet<String> {
+    files
+        .iter()
+        .filter(|file| file.directives.no_down)
+        .map(|file| file.version.clone())
+        .collect()
+}
+
+/// Output of a rollback that stopped at `result.failed_migration`
+fn stopped(message: String, result: DownResult) -> Result<CommandOutput> {
+    let output = CommandOutput::success_with_data(message, serde_json::to_value(&result)?);
+    Ok(match &result.cause {
+        Some(e) => output.with_error(e),
+        None => output,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::parse;
+    use chrono::Utc;
+
+    #[test]
+    fn test_select_version() {
+        let applied = || {
+            ["20250101_001_users", "20250102_001_orders", "20250103_001_user_emails"]
+                .map(|version| MigrationRecord::for_test(version, Utc::now()))
+                .to_vec()
+        };
+        let files = [
+            MigrationFile::for_test("20250101_001_users", "-- db-migrate:tags=users\n-- +migrate Up\nSELECT 1;"),
+            MigrationFile::for_test("20250102_001_orders", "-- db-migrate:depends-on=20250101_001_users\n-- +migrate Up\nSELECT 1;"),
+            MigrationFile::for_test("20250103_001_user_emails", "-- db-migrate:tags=users\n-- +migrate Up\nSELECT 1;"),
+        ];
+
+        assert!(parse::<DownCommand>(&["--allow-out-of-order"]).is_err());
+        assert!(parse::<DownCommand>(&["--version", "1", "--count", "2"]).is_err());
+
+        // The latest migration needs no acknowledgment
+        let command = parse::<DownCommand>(&["--version", "20250103_001_user_emails"]).unwrap();
+        let record = command.select_version("20250103_001_user_emails", applied(), &files).unwrap();
+        assert_eq!(record.version, "20250103_001_user_emails");
+
+        let error = command.select_version("20250104_001_missing", applied(), &files).unwrap_err();
+        assert!(!error.success);
+
+        // An earlier one lists what was applied after it and what depends on it
+        let command = parse::<DownCommand>(&["--version", "20250101_001_users"]).unwrap();
+        let refused = command.select_version("20250101_001_users", applied(), &files).unwrap_err();
+        let data = refused.data.unwrap();
+        assert_eq!(data["applied_after"], serde_json::json!(["20250102_001_orders", "20250103_001_user_emails"]));
+        assert_eq!(data["dependents"], serde_json::json!(["20250102_001_orders", "20250103_001_user_emails"]));
+
+        let command = parse::<DownCommand>(&["--version", "20250101_001_users", "--allow-out-of-order"]).unwrap();
+        let record = command.select_version("20250101_001_users", applied(), &files).unwrap();
+        assert_eq!(record.version, "20250101_001_users");
+    }
+}
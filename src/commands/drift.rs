@@ -0,0 +1,56 @@
+use crate::{drift::SchemaDrift, migration::MigrationManager, CommandOutput};
+use anyhow::Result;
+use clap::Args;
+use colored::*;
+
+#[derive(Args)]
+pub struct DriftCommand {}
+
+impl DriftCommand {
+    pub async fn execute(&self, manager: &MigrationManager) -> Result<CommandOutput> {
+        let drift = manager.detect_schema_drift().await?;
+
+        if drift.is_empty() {
+            return Ok(CommandOutput::success_with_data(
+                format!(
+                    "{} Live schema matches the applied migrations",
+                    "✅".green()
+                ),
+                serde_json::json!({ "drift": drift }),
+            ));
+        }
+
+        let mut output = vec![format!(
+            "{} Schema drift detected ({} difference(s)):",
+            "⚠️ ".yellow(),
+            drift.len()
+        )];
+        output.push(String::new());
+        output.extend(drift.iter().map(format_drift));
+
+        Ok(CommandOutput::error_with_data(
+            output.join("\n"),
+            serde_json::json!({ "drift": drift }),
+        ))
+    }
+}
+
+/// One-line rendering of a drift entry
+pub(crate) fn format_drift(drift: &SchemaDrift) -> String {
+    let icon = match drift.kind {
+        "missing" => "❌".red(),
+        "unexpected" | "unmanaged" => "❓".yellow(),
+        _ => "✏️ ".yellow(),
+    };
+    let mut line = format!(
+        "  {} {} {}: {}",
+        icon,
+        drift.object_type.dimmed(),
+        drift.name.bright_cyan(),
+        drift.message
+    );
+    if let Some(version) = &drift.last_migration {
+        line.push_str(&format!(" {}", format!("(last migrated by {})", version).dimmed()));
+    }
+    line
+}
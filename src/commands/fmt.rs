@@ -0,0 +1,154 @@
+use crate::{
+    config::Config,
+    fmt::format_cql,
+    migration::{read_migration_files, MigrationManager},
+    snapshot::AppliedSnapshot,
+    utils::extract_version_from_filename,
+    CommandOutput,
+};
+use anyhow::{Context, Result};
+use clap::Args;
+use colored::*;
+use std::{collections::HashSet, path::{Path, PathBuf}};
+use tokio::fs;
+
+#[derive(Args)]
+pub struct FmtCommand {
+    /// Only report files that are not formatted; don't modify anything
+    #[arg(long)]
+    check: bool,
+
+    /// Also format migrations that are already applied, changing their checksums
+    #[arg(long)]
+    include_applied: bool,
+
+    /// Files to format (default: all migration files)
+    files: Vec<PathBuf>,
+}
+
+impl FmtCommand {
+    pub async fn execute(&self, config: &Config) -> Result<CommandOutput> {
+        let scheme = config.version_scheme()?;
+        let paths: Vec<PathBuf> = if self.files.is_empty() {
+            read_migration_files(&config.migrations.directory, &scheme)
+                .await?
+                .into_iter()
+                .map(|f| f.file_path)
+                .collect()
+        } else {
+            self.files.clone()
+        };
+
+        let applied = if self.include_applied {
+            HashSet::new()
+        } else {
+            applied_versions(config).await?
+        };
+        let is_applied = |path: &Path| {
+            path.file_name()
+                .and_then(|name| extract_version_from_filename(&name.to_string_lossy(), &scheme))
+                .is_some_and(|version| applied.contains(&version))
+        };
+
+        let mut changed = Vec::new();
+        let mut skipped = Vec::new();
+
+        for path in &paths {
+            let content = fs::read_to_string(path).await?;
+            let formatted = format_cql(&content);
+
+            if formatted != content {
+                // Reformatting an applied migration would change its checksum
+                if is_applied(path) {
+                    skipped.push(path.to_string_lossy().to_string());
+                    continue;
+                }
+                if !self.check {
+                    fs::write(path, &formatted).await?;
+                }
+                changed.push(path.to_string_lossy().to_string());
+            }
+        }
+
+        let mut output = Vec::new();
+        for path in &changed {
+            output.push(format!(
+                "  {} {}",
+                if self.check { "❌".red() } else { "✏️ ".yellow() },
+                path.bright_cyan()
+            ));
+        }
+        for path in &skipped {
+            output.push(format!("  {} {} (applied, left as is)", "⏭️ ".dimmed(), path.bright_cyan()));
+        }
+        if !changed.is_empty() || !skipped.is_empty() {
+            output.push(String::new());
+        }
+        if !skipped.is_empty() {
+            output.push(format!(
+                "{} {} applied migration(s) are not formatted; pass --include-applied to format them anyway",
+                "💡".bright_blue(),
+                skipped.len()
+            ));
+        }
+
+        let data = serde_json::json!({
+            "check": self.check,
+            "files_checked": paths.len(),
+            "changed_count": changed.len(),
+            "changed_files": changed,
+            "skipped_applied_files": skipped
+        });
+
+        if self.check {
+            if changed.is_empty() {
+                output.push(format!("{} All {} file(s) are formatted", "✅".green(), paths.len()));
+                return Ok(CommandOutput::success_with_data(output.join("\n"), data));
+            }
+
+            output.push(format!(
+                "{} {} file(s) need formatting. Run 'db-migrate fmt' to fix them.",
+                "❌".red(),
+                changed.len()
+            ));
+            return Ok(CommandOutput::error_with_data(output.join("\n"), data));
+        }
+
+        if changed.is_empty() {
+            output.push(format!("{} All {} file(s) already formatted", "✅".green(), paths.len()));
+        } else {
+            output.push(format!("{} Formatted {} file(s)", "✅".green(), changed.len()));
+            if self.include_applied {
+                output.push(format!(
+                    "{} Formatting changes checksums - reformatted applied migrations will show up in 'db-migrate verify'",
+                    "💡".bright_blue()
+                ));
+            }
+        }
+
+        Ok(CommandOutput::success_with_data(output.join("\n"), data))
+    }
+}
+
+/// Applied versions, from the applied snapshot (`hooks snapshot`) if there is
+/// one so formatting works offline, otherwise from the tracking table
+async fn applied_versions(config: &Config) -> Result<HashSet<String>> {
+    let snapshot_path = config.snapshot_path();
+    if snapshot_path.exists() {
+        let snapshot = AppliedSnapshot::load(&snapshot_path).await?;
+        return Ok(snapshot.migrations.into_keys().collect());
+    }
+
+    let manager = MigrationManager::builder(config.clone())
+        .initialize_schema(false)
+        .read_path(true)
+        .build()
+        .await
+        .context("Could not tell which migrations are applied; pass --include-applied to format every file, or record them with 'db-migrate hooks snapshot'")?;
+    Ok(manager
+        .get_applied_migrations()
+        .await?
+        .into_iter()
+        .map(|record| record.version)
+        .collect())
+}
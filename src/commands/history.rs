@@ -0,0 +1,172 @@
+use crate::{
+    history::{events_to_prune, HistoryEvent},
+    migration::MigrationManager,
+    CommandOutput,
+};
+use anyhow::Result;
+use chrono::{Duration, Utc};
+use clap::{Args, Subcommand};
+use colored::*;
+
+#[derive(Args)]
+pub struct HistoryCommand {
+    #[command(subcommand)]
+    action: HistoryCommandAction,
+}
+
+#[derive(Subcommand)]
+enum HistoryCommandAction {
+    /// List recorded events, oldest first
+    List {
+        /// Only show events of this migration
+        #[arg(long)]
+        version: Option<String>,
+    },
+    /// Delete events older than the retention window, keeping the latest event
+    /// of every migration
+    Prune {
+        /// Retention window in days, instead of `history.retention_days`
+        #[arg(long, value_name = "DAYS", value_parser = clap::value_parser!(u32).range(1..))]
+        older_than_days: Option<u32>,
+
+        /// Show what would be deleted without deleting it
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+impl HistoryCommand {
+    /// Whether this invocation only reads from the database
+    pub fn is_read_only(&self) -> bool {
+        matches!(self.action, HistoryCommandAction::List { .. })
+    }
+
+    pub async fn execute(&self, manager: &MigrationManager) -> Result<CommandOutput> {
+        match &self.action {
+            HistoryCommandAction::List { version } => self.list(manager, version.as_deref()).await,
+            HistoryCommandAction::Prune {
+                older_than_days,
+                dry_run,
+            } => self.prune(manager, *older_than_days, *dry_run).await,
+        }
+    }
+
+    async fn list(&self, manager: &MigrationManager, version: Option<&str>) -> Result<CommandOutput> {
+        let events: Vec<HistoryEvent> = manager
+            .history_events()
+            .await?
+            .into_iter()
+            .filter(|event| version.is_none_or(|version| event.version == version))
+            .collect();
+
+        if events.is_empty() {
+            return Ok(CommandOutput::success_with_data(
+                format!("{} No history recorded", "📭".bright_blue()),
+                serde_json::json!({ "events": events }),
+            ));
+        }
+
+        let mut output = vec![format!("{} History ({} event(s)):", "📜".bright_blue(), events.len())];
+        for event in &events {
+            output.push(format!(
+                "  {} {} {}{}",
+                event.occurred_at.format("%Y-%m-%d %H:%M:%S").to_string().dimmed(),
+                event.action.bright_yellow(),
+                event.version.bright_cyan(),
+                event
+                    .detail
+                    .as_ref()
+                    .map(|detail| format!(" ({})", detail))
+                    .unwrap_or_default()
+            ));
+        }
+
+        let porcelain = events
+            .iter()
+            .map(|event| {
+                vec![
+                    event.occurred_at.to_rfc3339(),
+                    event.action.clone(),
+                    event.version.clone(),
+                    event.detail.clone().unwrap_or_default(),
+                ]
+            })
+            .collect();
+
+        Ok(
+            CommandOutput::success_with_data(output.join("\n"), serde_json::json!({ "events": events }))
+                .with_porcelain(porcelain),
+        )
+    }
+
+    async fn prune(
+        &self,
+        manager: &MigrationManager,
+        older_than_days: Option<u32>,
+        dry_run: bool,
+    ) -> Result<CommandOutput> {
+        let Some(days) = older_than_days.or(manager.get_config().history.retention_days) else {
+            return Ok(CommandOutput::error(format!(
+                "{} No retention window: set history.retention_days or pass --older-than-days",
+                "❌".red()
+            )));
+        };
+
+        let cutoff = Utc::now() - Duration::days(i64::from(days));
+        let events = manager.history_events().await?;
+        let pruned = events_to_prune(&events, cutoff);
+
+        let data = serde_json::json!({
+            "retention_days": days,
+            "cutoff": cutoff,
+            "pruned_count": pruned.len(),
+            "kept_count": events.len() - pruned.len(),
+            "pruned": pruned,
+            "dry_run": dry_run
+        });
+
+        if pruned.is_empty() {
+            return Ok(CommandOutput::success_with_data(
+                format!(
+                    "{} No history events older than {} day(s) to prune",
+                    "✅".green(),
+                    days
+                ),
+                data,
+            ));
+        }
+
+        let mut output = vec![format!(
+            "{} {} {} of {} history event(s) older than {} day(s)",
+            if dry_run { "🔍" } else { "🧹" },
+            if dry_run { "Would prune" } else { "Pruned" },
+            pruned.len(),
+            events.len(),
+            days
+        )];
+        if dry_run {
+            for event in &pruned {
+                output.push(format!(
+                    "  {} {} {}",
+                    event.occurred_at.format("%Y-%m-%d %H:%M:%S").to_string().dimmed(),
+                    event.action.bright_yellow(),
+                    event.version.bright_cyan()
+                ));
+            }
+            return Ok(CommandOutput::success_with_data(output.join("\n"), data));
+        }
+
+        if let Err(e) = manager.delete_history_events(&pruned).await {
+            return Ok(CommandOutput::error_with_data(
+                format!("{} Failed to prune history: {}", "❌".red(), e),
+                serde_json::json!({
+                    "error": e.to_string(),
+                    "error_code": e.code()
+                }),
+            )
+            .with_error(&e));
+        }
+
+        Ok(CommandOutput::success_with_data(output.join("\n"), data))
+    }
+}
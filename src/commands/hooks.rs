@@ -0,0 +1,274 @@
+use crate::{
+    annotations::Annotation,
+    commands::lint::format_finding,
+    config::Config,
+    lint::{lint_migrations, Finding, Severity},
+    migration::{active_migration_files, MigrationManager},
+    snapshot::{AppliedEdit, AppliedSnapshot},
+    CommandOutput,
+};
+use anyhow::{Context, Result};
+use clap::{Args, Subcommand};
+use colored::*;
+use std::path::PathBuf;
+use tokio::fs;
+
+const HOOK_MARKER: &str = "# Installed by db-migrate";
+
+/// The pre-commit hook, running `hooks check` with the configuration at
+/// `config_path` quoted for the shell
+fn hook_script(config_path: &str) -> String {
+    format!(
+        "#!/bin/sh\n{}\nexec db-migrate --config '{}' hooks check\n",
+        HOOK_MARKER,
+        config_path.replace('\'', "'\\''")
+    )
+}
+
+#[derive(Args)]
+pub struct HooksCommand {
+    #[command(subcommand)]
+    action: HooksAction,
+}
+
+#[derive(Subcommand)]
+enum HooksAction {
+    /// Install a git pre-commit hook that runs `db-migrate hooks check`
+    Install {
+        /// Overwrite an existing pre-commit hook
+        #[arg(long)]
+        force: bool,
+    },
+    /// Lint migrations and detect edits to applied migrations (offline)
+    Check,
+    /// Record the currently applied migrations in the snapshot file
+    Snapshot,
+}
+
+impl HooksCommand {
+    /// Whether this action needs a database connection
+    pub fn requires_database(&self) -> bool {
+        matches!(self.action, HooksAction::Snapshot)
+    }
+
+    /// Run actions that work without a database connection
+    pub async fn execute_offline(&self, config: &Config, config_path: &str) -> Result<CommandOutput> {
+        match &self.action {
+            HooksAction::Install { force } => self.install(config_path, *force).await,
+            HooksAction::Check => self.check(config).await,
+            HooksAction::Snapshot => anyhow::bail!("'hooks snapshot' requires a database connection"),
+        }
+    }
+
+    pub async fn execute(&self, manager: &MigrationManager) -> Result<CommandOutput> {
+        let config = manager.get_config();
+        let applied = manager.get_applied_migrations().await?;
+        let snapshot = AppliedSnapshot::from_records(&config.database.keyspace, &applied);
+        let path = config.snapshot_path();
+        snapshot.save(&path).await?;
+
+        Ok(CommandOutput::success_with_data(
+            format!(
+                "{} Wrote snapshot of {} applied migration(s) to {}\n{} Commit this file so the pre-commit hook can detect edits to applied migrations",
+                "✅".green(),
+                applied.len(),
+                path.display().to_string().bright_cyan(),
+                "💡".bright_blue()
+            ),
+            serde_json::json!({
+                "snapshot_file": path.to_string_lossy(),
+                "applied_count": applied.len()
+            })
+        ))
+    }
+
+    async fn install(&self, config_path: &str, force: bool) -> Result<CommandOutput> {
+        let output = std::process::Command::new("git")
+            .args(["rev-parse", "--git-path", "hooks"])
+            .output()
+            .context("Failed to run git")?;
+        if !output.status.success() {
+            return Ok(CommandOutput::error(format!(
+                "{} Not inside a git repository",
+                "❌".red()
+            )));
+        }
+
+        let hooks_dir = PathBuf::from(String::from_utf8_lossy(&output.stdout).trim());
+        let hook_path = hooks_dir.join("pre-commit");
+
+        if hook_path.exists() && !force {
+            let existing = fs::read_to_string(&hook_path).await?;
+            if !existing.contains(HOOK_MARKER) {
+                return Ok(CommandOutput::error(format!(
+                    "{} A pre-commit hook already exists at {}. Use --force to overwrite it.",
+                    "❌".red(),
+                    hook_path.display()
+                )));
+            }
+        }
+
+        let script = hook_script(config_path);
+        fs::create_dir_all(&hooks_dir).await?;
+        fs::write(&hook_path, script).await?;
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&hook_path, std::fs::Permissions::from_mode(0o755)).await?;
+        }
+
+        Ok(CommandOutput::success_with_data(
+            format!(
+                "{} Installed pre-commit hook at {}",
+                "✅".green(),
+                hook_path.display().to_string().bright_cyan()
+            ),
+            serde_json::json!({
+                "hook_path": hook_path.to_string_lossy()
+            })
+        ))
+    }
+
+    async fn check(&self, config: &Config) -> Result<CommandOutput> {
+        let files = active_migration_files(config).await?;
+        let mut findings = lint_migrations(&files, config);
+
+        let snapshot_path = config.snapshot_path();
+        let snapshot_found = snapshot_path.exists();
+        if snapshot_found {
+            let snapshot = AppliedSnapshot::load(&snapshot_path).await?;
+
+            for version in snapshot.migrations.keys() {
+                let Some(file) = files.iter().find(|f| &f.version == version) else {
+                    findings.push(Finding {
+                        rule: "applied-deleted",
+                        severity: Severity::Error,
+                        version: version.clone(),
+                        file: config.migrations.directory.join(format!("{}.cql", version)),
+                        line: None,
+                        message: "Migration is already applied but its file was deleted".to_string(),
+                        policy_rule: None,
+                    });
+                    continue;
+                };
+                match snapshot.edit_of(file) {
+                    Some(AppliedEdit::Modified) => findings.push(Finding::new(
+                        "applied-modified",
+                        Severity::Error,
+                        file,
+                        None,
+                        "Migration is already applied but its file was modified",
+                    )),
+                    Some(AppliedEdit::DownOnly) => findings.push(Finding::new(
+                        "applied-down-modified",
+                        Severity::Warning,
+                        file,
+                        None,
+                        "Only the DOWN section of this applied migration was modified; accept it with 'db-migrate repair --accept-down'",
+                    )),
+                    _ => {}
+                }
+            }
+        }
+
+        let error_count = findings
+            .iter()
+            .filter(|f| f.severity == Severity::Error)
+            .count();
+
+        let mut output: Vec<String> = findings.iter().map(format_finding).collect();
+        if !snapshot_found {
+            output.push(format!(
+                "{} No snapshot at {} - run 'db-migrate hooks snapshot' to detect edits to applied migrations",
+                "💡".bright_blue(),
+                snapshot_path.display()
+            ));
+        }
+        output.push(if error_count > 0 {
+            format!("{} db-migrate: {} error(s) found, commit blocked", "❌".red(), error_count)
+        } else {
+            format!("{} db-migrate: migrations OK", "✅".green())
+        });
+
+        let data = serde_json::json!({
+            "snapshot_found": snapshot_found,
+            "error_count": error_count,
+            "findings": findings
+        });
+
+        let annotations = findings.iter().map(Annotation::from).collect();
+        if error_count > 0 {
+            Ok(CommandOutput::error_with_data(output.join("\n"), data).with_annotations(annotations))
+        } else {
+            Ok(CommandOutput::success_with_data(output.join("\n"), data).with_annotations(annotations))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MigrationRecord;
+    use chrono::Utc;
+
+    #[test]
+    fn test_hook_script() {
+        assert_eq!(
+            hook_script("ops/it's.toml"),
+            "#!/bin/sh\n# Installed by db-migrate\nexec db-migrate --config 'ops/it'\\''s.toml' hooks check\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_check_detects_edits_to_applied_migrations() {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |name: &str, up: &str, down: &str| {
+            let content = format!("-- +migrate Up\n{};\n-- +migrate Down\n{};\n", up, down);
+            std::fs::write(dir.path().join(format!("{}.cql", name)), content).unwrap();
+        };
+        write("20250101_001_users", "CREATE TABLE users (id int PRIMARY KEY)", "DROP TABLE users");
+        write("20250102_001_orders", "CREATE TABLE orders (id int PRIMARY KEY)", "DROP TABLE orders");
+        write("20250103_001_items", "CREATE TABLE items (id int PRIMARY KEY)", "DROP TABLE items");
+        write("20250104_001_tags", "CREATE TABLE tags (id int PRIMARY KEY)", "DROP TABLE tags");
+
+        let mut config = Config::default();
+        config.migrations.directory = dir.path().to_path_buf();
+        let records: Vec<MigrationRecord> = active_migration_files(&config)
+            .await
+            .unwrap()
+            .iter()
+            .map(|file| MigrationRecord {
+                checksum: file.checksum.clone(),
+                up_checksum: Some(file.up_checksum()),
+                ..MigrationRecord::for_test(&file.version, Utc::now())
+            })
+            .collect();
+        AppliedSnapshot::from_records("app", &records).save(&config.snapshot_path()).await.unwrap();
+
+        write("20250101_001_users", "CREATE TABLE users (id bigint PRIMARY KEY)", "DROP TABLE users");
+        write("20250102_001_orders", "CREATE TABLE orders (id int PRIMARY KEY)", "DROP TABLE IF EXISTS orders");
+        std::fs::remove_file(dir.path().join("20250103_001_items.cql")).unwrap();
+
+        let command = HooksCommand { action: HooksAction::Check };
+        let output = command.check(&config).await.unwrap();
+        assert!(!output.success);
+        let data = output.data.unwrap();
+        let found: Vec<(&str, &str)> = data["findings"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|finding| (finding["version"].as_str().unwrap(), finding["rule"].as_str().unwrap()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("20250101_001_users", "applied-modified"),
+                ("20250102_001_orders", "applied-down-modified"),
+                ("20250103_001_items", "applied-deleted"),
+            ]
+        );
+        assert_eq!(data["error_count"], 2);
+        assert_eq!(output.annotations.map(|annotations| annotations.len()), Some(3));
+    }
+}
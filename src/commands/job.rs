@@ -0,0 +1,158 @@
+use crate::{
+    commands::UpCommand,
+    config::Config,
+    migration::MigrationManager,
+    progress,
+    runreport::write_run_report,
+    CommandOutput, MigrationError,
+};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use clap::Args;
+use colored::*;
+use serde::Serialize;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// Longest delay between two connection attempts
+const MAX_CONNECT_DELAY: Duration = Duration::from_secs(30);
+
+#[derive(Args)]
+pub struct JobCommand {
+    /// Seconds to keep retrying while the database does not accept connections
+    #[arg(long, value_name = "SECONDS", default_value = "300")]
+    wait_for_db: u64,
+
+    /// Seconds to wait for the migration lock held by another run
+    #[arg(long, value_name = "SECONDS", default_value = "600")]
+    lock_timeout: u64,
+
+    /// Write the run result as JSON to this file, e.g. /dev/termination-log
+    #[arg(long, value_name = "FILE")]
+    result_file: Option<PathBuf>,
+}
+
+/// What `--result-file` receives
+#[derive(Debug, Serialize)]
+struct JobResult<'a> {
+    /// `up_to_date`, `applied`, `db_unavailable`, `lock_timeout`, `lock_lost` or
+    /// `failed`
+    outcome: &'a str,
+    success: bool,
+    exit_code: i32,
+    error_code: Option<&'static str>,
+    error: Option<String>,
+    applied_migrations: Vec<String>,
+    started_at: DateTime<Utc>,
+    finished_at: DateTime<Utc>,
+}
+
+impl JobCommand {
+    /// Wait for the database, take the lock and apply pending migrations
+    pub async fn execute(&self, config: &Config) -> Result<CommandOutput> {
+        let started_at = Utc::now();
+        let (outcome, output) = self.run(config).await;
+
+        let data = output.data.as_ref();
+        let result = JobResult {
+            outcome,
+            success: output.success,
+            exit_code: output.exit_code,
+            error_code: output.code,
+            error: data.and_then(|data| data["error"].as_str()).map(str::to_string),
+            applied_migrations: data
+                .and_then(|data| data["applied_migrations"].as_array())
+                .map(|versions| {
+                    versions
+                        .iter()
+                        .filter_map(|version| version.as_str().map(str::to_string))
+                        .collect()
+                })
+                .unwrap_or_default(),
+            started_at,
+            finished_at: Utc::now(),
+        };
+
+        if let Some(path) = &self.result_file {
+            tokio::fs::write(path, serde_json::to_string_pretty(&result)?).await?;
+        }
+
+        let mut output = output;
+        output.data = Some(serde_json::to_value(&result)?);
+        Ok(output)
+    }
+
+    async fn run(&self, config: &Config) -> (&'static str, CommandOutput) {
+        let mut manager = match connect(config, Duration::from_secs(self.wait_for_db)).await {
+            Ok(manager) => manager,
+            Err(e) => return ("db_unavailable", failed(&e)),
+        };
+
+        // Stop at the next statement boundary when the pod is terminated
+        manager.cancel_on_shutdown_signal();
+
+        // `up` takes the lock and keeps it while migrations run
+        let started_at = Utc::now();
+        let run = UpCommand::default()
+            .with_lock_timeout(Duration::from_secs(self.lock_timeout))
+            .execute(&mut manager)
+            .await;
+        write_run_report(config.output.run_report.as_deref(), "up", started_at, &run, Some(&manager)).await;
+
+        match run {
+            Ok(output) if output.code == Some("E_LOCK_LOST") => ("lock_lost", output),
+            Ok(output) if !output.success => ("failed", output),
+            Ok(output) => {
+                let applied = output
+                    .data
+                    .as_ref()
+                    .and_then(|data| data["applied_count"].as_u64())
+                    .unwrap_or(0);
+                (if applied > 0 { "applied" } else { "up_to_date" }, output)
+            }
+            Err(e) => match e.chain().find_map(|c| c.downcast_ref::<MigrationError>()) {
+                Some(e @ MigrationError::LockTimeout { .. }) => ("lock_timeout", failed(e)),
+                Some(e @ MigrationError::LockLost { .. }) => ("lock_lost", failed(e)),
+                Some(e) => ("failed", failed(e)),
+                None => ("failed", CommandOutput::error(format!("{} {}", "❌".red(), e))),
+            },
+        }
+    }
+}
+
+fn failed(error: &MigrationError) -> CommandOutput {
+    CommandOutput::error_with_data(
+        format!("{} {}", "❌".red(), error),
+        serde_json::json!({
+            "applied_count": 0,
+            "error": error.to_string(),
+            "error_code": error.code()
+        }),
+    )
+    .with_error(error)
+}
+
+/// Connect, retrying with backoff while the database is unreachable
+async fn connect(config: &Config, wait: Duration) -> Result<MigrationManager, MigrationError> {
+    let deadline = Instant::now() + wait;
+    let mut delay = Duration::from_secs(1);
+
+    loop {
+        match MigrationManager::new(config.clone()).await {
+            Ok(manager) => return Ok(manager),
+            Err(e @ (MigrationError::DatabaseError(_) | MigrationError::QueryError(_)))
+                if Instant::now() + delay < deadline =>
+            {
+                progress!(
+                    "{} Database not ready ({}), retrying in {}s",
+                    "⏳".yellow(),
+                    e,
+                    delay.as_secs()
+                );
+                tokio::time::sleep(delay).await;
+                delay = (delay * 2).min(MAX_CONNECT_DELAY);
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
@@ -0,0 +1,400 @@
+use crate::{
+    config::KeyspaceReplication,
+    cql,
+    history::{keyspace_event_version, HistoryAction},
+    migration::MigrationManager,
+    queue::DEFAULT_QUEUE_TIMEOUT,
+    schema::SchemaIntrospector,
+    CommandOutput, MigrationError,
+};
+use anyhow::Result;
+use clap::{Args, Subcommand};
+use colored::*;
+use std::collections::BTreeMap;
+
+#[derive(Args)]
+pub struct KeyspaceCommand {
+    #[command(subcommand)]
+    action: KeyspaceCommandAction,
+}
+
+#[derive(Subcommand)]
+enum KeyspaceCommandAction {
+    /// Create missing keyspaces with the configured replication
+    Create(KeyspaceTarget),
+    /// Change the replication of keyspaces to the configured one
+    Alter(KeyspaceTarget),
+    /// Compare the replication of keyspaces with the configured one
+    Describe(KeyspaceTarget),
+}
+
+#[derive(Args)]
+struct KeyspaceTarget {
+    /// Only this keyspace, instead of `database.keyspace` and
+    /// `migrations.tracking_keyspace`
+    #[arg(long, value_name = "NAME")]
+    keyspace: Option<String>,
+
+    /// Show the statements without running them
+    #[arg(long)]
+    dry_run: bool,
+}
+
+/// A keyspace as it is and as configured
+struct KeyspaceState {
+    name: String,
+    live: Option<BTreeMap<String, String>>,
+    configured: KeyspaceReplication,
+}
+
+impl KeyspaceState {
+    fn status(&self) -> &'static str {
+        match &self.live {
+            None => "missing",
+            Some(live) if self.configured.matches(live) => "matches",
+            Some(_) => "differs",
+        }
+    }
+
+    fn live_cql(&self) -> Option<String> {
+        self.live.as_ref().map(|live| {
+            let options: Vec<String> = live
+                .iter()
+                .map(|(key, value)| {
+                    let value = if key == "class" {
+                        format!("'{}'", value.rsplit('.').next().unwrap_or(value))
+                    } else {
+                        value.clone()
+                    };
+                    format!("'{}': {}", key, value)
+                })
+                .collect();
+            format!("{{{}}}", options.join(", "))
+        })
+    }
+
+    fn create_statement(&self) -> String {
+        format!(
+            "CREATE KEYSPACE IF NOT EXISTS {} WITH REPLICATION = {}",
+            self.name,
+            self.configured.to_cql()
+        )
+    }
+
+    fn alter_statement(&self) -> String {
+        format!("ALTER KEYSPACE {} WITH REPLICATION = {}", self.name, self.configured.to_cql())
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "keyspace": self.name,
+            "status": self.status(),
+            "replication": self.live,
+            "configured_replication": self.configured.to_cql()
+        })
+    }
+}
+
+impl KeyspaceCommand {
+    /// Whether this invocation only reads from the database
+    pub fn is_read_only(&self) -> bool {
+        matches!(self.action, KeyspaceCommandAction::Describe(_))
+    }
+
+    pub async fn execute(&self, manager: &MigrationManager) -> Result<CommandOutput> {
+        let target = match &self.action {
+            KeyspaceCommandAction::Create(target)
+            | KeyspaceCommandAction::Alter(target)
+            | KeyspaceCommandAction::Describe(target) => target,
+        };
+        if let Some(name) = &target.keyspace {
+            if let Err(reason) = cql::validate_identifier(name) {
+                let e = MigrationError::ConfigError(format!("Invalid keyspace '{}': {}", name, reason));
+                return Ok(CommandOutput::error(format!("{} {}", "❌".red(), e)).with_error(&e));
+            }
+        }
+
+        let states = self.states(manager, target).await?;
+        match &self.action {
+            KeyspaceCommandAction::Describe(_) => Ok(self.describe(manager, &states)),
+            KeyspaceCommandAction::Create(target) => {
+                let _run = manager.exclusive_run("keyspace create", DEFAULT_QUEUE_TIMEOUT).await?;
+                self.create(manager, &states, target.dry_run).await
+            }
+            KeyspaceCommandAction::Alter(target) => {
+                let _run = manager.exclusive_run("keyspace alter", DEFAULT_QUEUE_TIMEOUT).await?;
+                self.alter(manager, &states, target.dry_run).await
+            }
+        }
+    }
+
+    async fn states(&self, manager: &MigrationManager, target: &KeyspaceTarget) -> Result<Vec<KeyspaceState>> {
+        let config = manager.get_config();
+        let names = match &target.keyspace {
+            Some(name) => vec![name.clone()],
+            None => {
+                let mut names = vec![config.database.keyspace.clone()];
+                if config.tracking_keyspace() != config.database.keyspace {
+                    names.push(config.tracking_keyspace().to_string());
+                }
+                names
+            }
+        };
+
+        let configured = config.replication.resolve(config.behavior.environment.as_deref());
+        let mut states = Vec::new();
+        for name in names {
+            let live = SchemaIntrospector::new(manager.session(), &name).replication().await?;
+            states.push(KeyspaceState {
+                name,
+                live,
+                configured: configured.clone(),
+            });
+        }
+        Ok(states)
+    }
+
+    fn describe(&self, manager: &MigrationManager, states: &[KeyspaceState]) -> CommandOutput {
+        let environment = manager.get_config().behavior.environment.as_deref();
+        let mut output = vec![format!(
+            "{} Keyspaces (environment: {}):",
+            "🗄️ ".bright_blue(),
+            environment.unwrap_or("none")
+        )];
+        for state in states {
+            let (icon, note) = match state.status() {
+                "matches" => ("✅".green(), "replication matches the configuration".to_string()),
+                "differs" => ("⚠️ ".yellow(), "replication differs from the configuration".to_string()),
+                _ => ("❌".red(), "does not exist".to_string()),
+            };
+            output.push(format!("  {} {} {}", icon, state.name.bright_cyan(), note));
+            if let Some(live) = state.live_cql() {
+                output.push(format!("     Current:    {}", live));
+            }
+            output.push(format!("     Configured: {}", state.configured.to_cql()));
+        }
+
+        let hints = [
+            ("missing", "Use 'db-migrate keyspace create' to create missing keyspaces"),
+            ("differs", "Use 'db-migrate keyspace alter' to apply the configured replication"),
+        ];
+        for (status, hint) in hints {
+            if states.iter().any(|state| state.status() == status) {
+                output.push(format!("{} {}", "💡".bright_blue(), hint));
+            }
+        }
+
+        let porcelain = states
+            .iter()
+            .map(|state| {
+                vec![
+                    state.name.clone(),
+                    state.status().to_string(),
+                    state.live_cql().unwrap_or_default(),
+                    state.configured.to_cql(),
+                ]
+            })
+            .collect();
+
+        CommandOutput::success_with_data(
+            output.join("\n"),
+            serde_json::json!({
+                "environment": environment,
+                "keyspaces": states.iter().map(KeyspaceState::to_json).collect::<Vec<_>>()
+            }),
+        )
+        .with_porcelain(porcelain)
+    }
+
+    async fn create(
+        &self,
+        manager: &MigrationManager,
+        states: &[KeyspaceState],
+        dry_run: bool,
+    ) -> Result<CommandOutput> {
+        if !dry_run {
+            manager.ensure_writable()?;
+        }
+
+        let mut output = Vec::new();
+        let mut created = Vec::new();
+        for state in states {
+            if state.live.is_some() {
+                output.push(format!("  {} {} already exists", "✅".green(), state.name.bright_cyan()));
+                if state.status() == "differs" {
+                    output.push(format!(
+                        "     {} Its replication differs; see 'db-migrate keyspace describe'",
+                        "⚠️ ".yellow()
+                    ));
+                }
+                continue;
+            }
+
+            let replication = state.configured.to_cql();
+            let statement = state.create_statement();
+            if !dry_run {
+                manager.session().query(statement.clone(), &[]).await?;
+                manager
+                    .record_event(
+                        &keyspace_event_version(&state.name),
+                        HistoryAction::KeyspaceCreated,
+                        None,
+                        Some(&replication),
+                    )
+                    .await;
+            }
+            output.push(format!(
+                "  {} {} {}",
+                "🆕".green(),
+                if dry_run { "Would create" } else { "Created" },
+                state.name.bright_cyan()
+            ));
+            output.push(format!("     {}", statement.dimmed()));
+            created.push(serde_json::json!({ "keyspace": state.name, "statement": statement }));
+        }
+
+        output.insert(
+            0,
+            if created.is_empty() {
+                format!("{} No keyspaces to create", "✅".green())
+            } else {
+                format!("{} Keyspaces:", "🗄️ ".bright_blue())
+            },
+        );
+        Ok(CommandOutput::success_with_data(
+            output.join("\n"),
+            serde_json::json!({
+                "dry_run": dry_run,
+                "created": created,
+                "keyspaces": states.iter().map(KeyspaceState::to_json).collect::<Vec<_>>()
+            }),
+        ))
+    }
+
+    async fn alter(
+        &self,
+        manager: &MigrationManager,
+        states: &[KeyspaceState],
+        dry_run: bool,
+    ) -> Result<CommandOutput> {
+        if let Some(missing) = states.iter().find(|state| state.live.is_none()) {
+            let e = MigrationError::ConfigError(format!(
+                "Keyspace {} does not exist; create it with 'db-migrate keyspace create'",
+                missing.name
+            ));
+            return Ok(CommandOutput::error(format!("{} {}", "❌".red(), e)).with_error(&e));
+        }
+        if !dry_run {
+            manager.ensure_writable()?;
+        }
+
+        let mut output = Vec::new();
+        let mut altered = Vec::new();
+        for state in states {
+            if state.status() == "matches" {
+                output.push(format!(
+                    "  {} {} already has the configured replication",
+                    "✅".green(),
+                    state.name.bright_cyan()
+                ));
+                continue;
+            }
+
+            let replication = state.configured.to_cql();
+            let previous = state.live_cql().unwrap_or_default();
+            let statement = state.alter_statement();
+            if !dry_run {
+                manager.session().query(statement.clone(), &[]).await?;
+                manager
+                    .record_event(
+                        &keyspace_event_version(&state.name),
+                        HistoryAction::KeyspaceAltered,
+                        None,
+                        Some(&format!("{} -> {}", previous, replication)),
+                    )
+                    .await;
+            }
+            output.push(format!(
+                "  {} {} {}",
+                "🔧".cyan(),
+                if dry_run { "Would alter" } else { "Altered" },
+                state.name.bright_cyan()
+            ));
+            output.push(format!("     {} {}", "-".red(), previous.red()));
+            output.push(format!("     {} {}", "+".green(), replication.green()));
+            altered.push(serde_json::json!({
+                "keyspace": state.name,
+                "previous_replication": state.live,
+                "statement": statement
+            }));
+        }
+
+        if !altered.is_empty() && !dry_run {
+            output.push(format!(
+                "{} Run a full repair of the altered keyspaces so new replicas receive their data",
+                "💡".bright_blue()
+            ));
+        }
+        output.insert(
+            0,
+            if altered.is_empty() {
+                format!("{} No keyspaces to alter", "✅".green())
+            } else {
+                format!("{} Keyspaces:", "🗄️ ".bright_blue())
+            },
+        );
+        Ok(CommandOutput::success_with_data(
+            output.join("\n"),
+            serde_json::json!({
+                "dry_run": dry_run,
+                "altered": altered,
+                "keyspaces": states.iter().map(KeyspaceState::to_json).collect::<Vec<_>>()
+            }),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ReplicationStrategy;
+
+    #[test]
+    fn test_keyspace_statements() {
+        let configured = KeyspaceReplication {
+            strategy: ReplicationStrategy::NetworkTopologyStrategy,
+            replication_factor: 1,
+            datacenters: BTreeMap::from([("eu-west".to_string(), 3)]),
+        };
+        let mut state = KeyspaceState {
+            name: "app".to_string(),
+            live: None,
+            configured,
+        };
+        assert_eq!(state.status(), "missing");
+        assert_eq!(state.live_cql(), None);
+        assert_eq!(
+            state.create_statement(),
+            "CREATE KEYSPACE IF NOT EXISTS app WITH REPLICATION = {'class': 'NetworkTopologyStrategy', 'eu-west': 3}"
+        );
+
+        // Live options come from system_schema.keyspaces, with a qualified class
+        state.live = Some(BTreeMap::from([
+            ("class".to_string(), "org.apache.cassandra.locator.SimpleStrategy".to_string()),
+            ("replication_factor".to_string(), "1".to_string()),
+        ]));
+        assert_eq!(state.status(), "differs");
+        assert_eq!(state.live_cql().unwrap(), "{'class': 'SimpleStrategy', 'replication_factor': 1}");
+        assert_eq!(
+            state.alter_statement(),
+            "ALTER KEYSPACE app WITH REPLICATION = {'class': 'NetworkTopologyStrategy', 'eu-west': 3}"
+        );
+        assert_eq!(state.to_json()["status"], "differs");
+
+        state.live = Some(BTreeMap::from([
+            ("class".to_string(), "org.apache.cassandra.locator.NetworkTopologyStrategy".to_string()),
+            ("eu-west".to_string(), "3".to_string()),
+        ]));
+        assert_eq!(state.status(), "matches");
+    }
+}
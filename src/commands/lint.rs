@@ -0,0 +1,86 @@
+use crate::{
+    annotations::Annotation,
+    config::Config,
+    lint::{lint_migrations, Finding, Severity},
+    migration::active_migration_files,
+    plugins::check_with_plugins,
+    CommandOutput,
+};
+use anyhow::Result;
+use clap::Args;
+use colored::*;
+
+#[derive(Args)]
+pub struct LintCommand {}
+
+impl LintCommand {
+    pub async fn execute(&self, config: &Config) -> Result<CommandOutput> {
+        let files = active_migration_files(config).await?;
+
+        let mut findings = lint_migrations(&files, config);
+        findings.extend(check_with_plugins(config, &files).await);
+
+        let error_count = findings
+            .iter()
+            .filter(|f| f.severity == Severity::Error)
+            .count();
+        let warning_count = findings.len() - error_count;
+
+        let mut output = vec![
+            format!("{} Linted {} migration file(s)", "🔍".cyan(), files.len()),
+            String::new(),
+        ];
+
+        for finding in &findings {
+            output.push(format_finding(finding));
+        }
+
+        if !findings.is_empty() {
+            output.push(String::new());
+        }
+
+        output.push(if findings.is_empty() {
+            format!("{} No issues found", "✅".green())
+        } else {
+            format!(
+                "{} {} error(s), {} warning(s)",
+                if error_count > 0 { "❌" } else { "⚠️ " },
+                error_count,
+                warning_count
+            )
+        });
+
+        let data = serde_json::json!({
+            "files_checked": files.len(),
+            "error_count": error_count,
+            "warning_count": warning_count,
+            "findings": findings
+        });
+
+        let annotations = findings.iter().map(Annotation::from).collect();
+        if error_count > 0 {
+            Ok(CommandOutput::error_with_data(output.join("\n"), data).with_annotations(annotations))
+        } else {
+            Ok(CommandOutput::success_with_data(output.join("\n"), data).with_annotations(annotations))
+        }
+    }
+}
+
+/// Render a single finding as a text line
+pub(crate) fn format_finding(finding: &Finding) -> String {
+    let location = match finding.line {
+        Some(line) => format!("{}:{}", finding.version, line),
+        None => finding.version.clone(),
+    };
+
+    format!(
+        "  {} {} {} - {}",
+        match finding.severity {
+            Severity::Error => "❌".red(),
+            Severity::Warning => "⚠️ ".yellow(),
+        },
+        format!("[{}]", finding.rule).dimmed(),
+        location.bright_cyan(),
+        finding.message
+    )
+}
@@ -0,0 +1,132 @@
+use crate::{
+    lock::LockStatus,
+    migration::MigrationManager,
+    utils::format_timestamp,
+    CommandOutput,
+};
+use anyhow::Result;
+use clap::{Args, Subcommand};
+use colored::*;
+
+#[derive(Args)]
+pub struct LockCommand {
+    #[command(subcommand)]
+    action: LockAction,
+}
+
+#[derive(Subcommand)]
+enum LockAction {
+    /// Show who holds the cluster-wide migration lock
+    Status,
+    /// Remove the lock of a run that is gone, whoever holds it
+    Release {
+        /// Confirm releasing a lock held by another run
+        #[arg(long)]
+        yes: bool,
+    },
+}
+
+impl LockCommand {
+    /// Whether this invocation only reads from the database
+    pub fn is_read_only(&self) -> bool {
+        matches!(self.action, LockAction::Status)
+    }
+
+    pub async fn execute(&self, manager: &MigrationManager) -> Result<CommandOutput> {
+        let status = manager.lock_status().await?;
+        let porcelain = status.iter().map(lock_record).collect();
+
+        match (&self.action, status) {
+            (_, None) => Ok(CommandOutput::success_with_data(
+                format!("{} The migration lock is free", "🔓".green()),
+                serde_json::json!({ "held": false, "lock": null }),
+            )
+            .with_porcelain(porcelain)),
+            (LockAction::Status, Some(status)) => Ok(CommandOutput::success_with_data(
+                format!("{} {}", "🔒".yellow(), describe_lock(&status)),
+                serde_json::json!({ "held": true, "lock": status }),
+            )
+            .with_porcelain(porcelain)),
+            (LockAction::Release { yes: false }, Some(status)) => Ok(CommandOutput::error_with_data(
+                format!(
+                    "{} {}\n{} Make sure that run is gone, then re-run with --yes to release the lock",
+                    "⚠️ ".yellow(),
+                    describe_lock(&status),
+                    "💡".bright_blue()
+                ),
+                serde_json::json!({ "held": true, "released": false, "lock": status }),
+            )
+            .with_porcelain(porcelain)),
+            (LockAction::Release { yes: true }, Some(_)) => {
+                let released = manager.force_release_lock().await?;
+                Ok(CommandOutput::success_with_data(
+                    format!(
+                        "{} Released the migration lock held by {}",
+                        "🔓".green(),
+                        released
+                            .as_ref()
+                            .and_then(|status| status.owner.as_deref())
+                            .unwrap_or("an unknown owner")
+                            .bright_cyan()
+                    ),
+                    serde_json::json!({ "held": false, "released": true, "lock": released }),
+                )
+                .with_porcelain(porcelain))
+            }
+        }
+    }
+}
+
+/// `Migration lock held by alice@ci-7:42 since ... (expires in 840s)`
+pub(crate) fn describe_lock(status: &LockStatus) -> String {
+    let mut text = format!(
+        "Migration lock held by {}",
+        status.owner.as_deref().unwrap_or("an unknown owner").bright_cyan()
+    );
+    if let Some(acquired_at) = status.acquired_at {
+        text.push_str(&format!(" since {}", format_timestamp(acquired_at)));
+    }
+    if let Some(ttl) = status.ttl_secs {
+        text.push_str(&format!(" (expires in {}s)", ttl));
+    }
+    text
+}
+
+/// Porcelain record: owner, acquired_at (RFC 3339), ttl_secs
+fn lock_record(status: &LockStatus) -> Vec<String> {
+    vec![
+        status.owner.clone().unwrap_or_default(),
+        status.acquired_at.map(|at| at.to_rfc3339()).unwrap_or_default(),
+        status.ttl_secs.map(|ttl| ttl.to_string()).unwrap_or_default(),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn test_describe_lock() {
+        let status = LockStatus {
+            owner: Some("alice@ci-7:42".to_string()),
+            acquired_at: Some(Utc.with_ymd_and_hms(2025, 6, 1, 12, 0, 0).unwrap()),
+            ttl_secs: Some(840),
+        };
+        let text = describe_lock(&status);
+        assert!(text.starts_with("Migration lock held by "));
+        assert!(text.contains("alice@ci-7:42"));
+        assert!(text.ends_with(" since 2025-06-01 12:00:00 UTC (expires in 840s)"));
+        assert_eq!(lock_record(&status), vec!["alice@ci-7:42", "2025-06-01T12:00:00+00:00", "840"]);
+
+        // A lock row written without an owner or TTL still describes
+        let bare = LockStatus {
+            owner: None,
+            acquired_at: None,
+            ttl_secs: None,
+        };
+        assert!(describe_lock(&bare).contains("an unknown owner"));
+        assert!(!describe_lock(&bare).contains("since"));
+        assert_eq!(lock_record(&bare), vec!["", "", ""]);
+    }
+}
@@ -1,9 +1,34 @@
 mod create;
 mod up;
 mod down;
-mod status;
+pub(crate) mod status;
 mod verify;
 mod reset;
+pub(crate) mod lint;
+mod fmt;
+mod hooks;
+#[cfg(feature = "sandbox")]
+mod sandbox;
+mod clone_schema;
+mod permissions;
+mod drift;
+mod config;
+#[cfg(feature = "bench")]
+mod bench;
+mod rename;
+mod history;
+mod restore_history;
+mod approve;
+#[cfg(feature = "operator")]
+mod operator;
+#[cfg(feature = "job")]
+mod job;
+mod repair;
+mod keyspace;
+mod agreement;
+pub(crate) mod doctor;
+mod version;
+pub(crate) mod lock;
 
 pub use create::CreateCommand;
 pub use up::UpCommand;
@@ -11,3 +36,41 @@ pub use down::DownCommand;
 pub use status::StatusCommand;
 pub use verify::VerifyCommand;
 pub use reset::ResetCommand;
+pub use lint::LintCommand;
+pub use fmt::FmtCommand;
+pub use hooks::HooksCommand;
+#[cfg(feature = "sandbox")]
+pub use sandbox::SandboxCommand;
+pub use clone_schema::CloneSchemaCommand;
+pub use permissions::PermissionsCommand;
+pub use drift::DriftCommand;
+pub use config::ConfigCommand;
+#[cfg(feature = "bench")]
+pub use bench::BenchCommand;
+pub use rename::RenameCommand;
+pub use history::HistoryCommand;
+pub use restore_history::RestoreHistoryCommand;
+pub use approve::ApproveCommand;
+#[cfg(feature = "operator")]
+pub use operator::OperatorCommand;
+#[cfg(feature = "job")]
+pub use job::JobCommand;
+pub use repair::RepairCommand;
+pub use keyspace::KeyspaceCommand;
+pub use agreement::AgreementCommand;
+pub use doctor::DoctorCommand;
+pub use version::VersionCommand;
+pub use lock::LockCommand;
+
+/// Parse the options of one command, as given after its name, for tests
+#[cfg(test)]
+pub(crate) fn parse<T: clap::Args>(args: &[&str]) -> Result<T, clap::Error> {
+    #[derive(clap::Parser)]
+    struct Cli<T: clap::Args> {
+        #[command(flatten)]
+        command: T,
+    }
+
+    <Cli<T> as clap::Parser>::try_parse_from(std::iter::once("db-migrate").chain(args.iter().copied()))
+        .map(|cli| cli.command)
+}
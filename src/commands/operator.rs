@@ -0,0 +1,129 @@
+use crate::{
+    commands::UpCommand,
+    migration::MigrationManager,
+    operator::{serve, OperatorState, Reconciliation},
+    progress, CommandOutput, MigrationError,
+};
+use anyhow::{Context, Result};
+use chrono::Utc;
+use clap::Args;
+use colored::*;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::net::TcpListener;
+
+#[derive(Args)]
+pub struct OperatorCommand {
+    /// Seconds between two reconciliations
+    #[arg(long, default_value = "60", value_parser = clap::value_parser!(u64).range(1..))]
+    interval: u64,
+
+    /// Address the /healthz, /readyz and /status endpoints listen on
+    #[arg(long, default_value = "0.0.0.0:8080")]
+    listen: SocketAddr,
+}
+
+impl OperatorCommand {
+    /// Reconcile until interrupted (Ctrl-C or SIGTERM)
+    pub async fn execute(&self, manager: &mut MigrationManager) -> Result<CommandOutput> {
+        let cancellation = manager.cancellation_token();
+        let state = Arc::new(Mutex::new(OperatorState::new(Utc::now())));
+
+        let listener = TcpListener::bind(self.listen)
+            .await
+            .with_context(|| format!("Could not listen on {}", self.listen))?;
+        let server = tokio::spawn(serve(listener, Arc::clone(&state), cancellation.clone()));
+        progress!(
+            "{} Operator reconciling {} every {}s, health endpoints on {}",
+            "🔁".cyan(),
+            manager.get_config().migrations.directory.display(),
+            self.interval,
+            self.listen
+        );
+
+        while !cancellation.is_cancelled() {
+            let reconciliation = reconcile(manager).await;
+            if !reconciliation.applied.is_empty() || !reconciliation.success {
+                progress!(
+                    "{} Reconciled: {} applied, {} pending{}",
+                    if reconciliation.success { "✅".green() } else { "❌".red() },
+                    reconciliation.applied.len(),
+                    reconciliation.pending_count,
+                    reconciliation
+                        .error
+                        .as_deref()
+                        .map(|error| format!(" ({})", error))
+                        .unwrap_or_default()
+                );
+            }
+            state.lock().unwrap().record(reconciliation);
+
+            tokio::select! {
+                _ = tokio::time::sleep(Duration::from_secs(self.interval)) => {}
+                _ = cancellation.cancelled() => {}
+            }
+        }
+
+        let _ = server.await;
+        let state = state.lock().unwrap().clone();
+        Ok(CommandOutput::success_with_data(
+            format!(
+                "{} Operator stopped after {} reconciliation(s), {} migration(s) applied",
+                "👋".cyan(),
+                state.reconciliations,
+                state.applied_total
+            ),
+            serde_json::to_value(&state)?,
+        ))
+    }
+}
+
+/// Apply pending migrations the way `up` does
+///
+/// While another run holds the migration lock the reconciliation fails and is
+/// retried at the next interval.
+async fn reconcile(manager: &mut MigrationManager) -> Reconciliation {
+    let run = UpCommand::default()
+        .with_lock_timeout(Duration::ZERO)
+        .execute(manager)
+        .await;
+    let pending = manager.get_pending_migrations().await.map(|pending| pending.len());
+
+    let (success, data, error, error_code) = match run {
+        Ok(output) => {
+            let error = output.data.as_ref().and_then(|data| data["error"].as_str()).map(str::to_string);
+            (output.success && error.is_none(), output.data, error, output.code)
+        }
+        Err(e) => {
+            let code = e.chain().find_map(|c| c.downcast_ref::<MigrationError>()).map(MigrationError::code);
+            (false, None, Some(e.to_string()), code)
+        }
+    };
+    let applied = data
+        .as_ref()
+        .and_then(|data| data["applied_migrations"].as_array().cloned())
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|version| version.as_str().map(str::to_string))
+        .collect();
+
+    match pending {
+        Ok(pending_count) => Reconciliation {
+            finished_at: Utc::now(),
+            success,
+            applied,
+            pending_count,
+            error,
+            error_code,
+        },
+        Err(e) => Reconciliation {
+            finished_at: Utc::now(),
+            success: false,
+            applied,
+            pending_count: 0,
+            error: error.or_else(|| Some(e.to_string())),
+            error_code: error_code.or(Some(e.code())),
+        },
+    }
+}
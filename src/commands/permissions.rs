@@ -0,0 +1,205 @@
+use crate::{
+    cql,
+    drift::normalize_identifier,
+    grants::{required_permissions, RequiredPermission},
+    migration::MigrationManager,
+    schema::{is_superuser, list_permissions},
+    stream::{Section, StatementStream},
+    utils::{parse_migration_content, split_cql_statements},
+    CommandOutput, MigrationFile,
+};
+use anyhow::Result;
+use clap::Args;
+use colored::*;
+use std::collections::BTreeMap;
+
+#[derive(Args)]
+pub struct PermissionsCommand {
+    /// Only show permissions of this role (including inherited ones)
+    #[arg(long)]
+    role: Option<String>,
+
+    /// Check that the role (default: the configured user) can apply the pending migrations
+    #[arg(long)]
+    check: bool,
+}
+
+impl PermissionsCommand {
+    pub async fn execute(&self, manager: &MigrationManager) -> Result<CommandOutput> {
+        if self.check {
+            return self.check_pending(manager).await;
+        }
+
+        let permissions = list_permissions(manager.session(), self.role.as_deref()).await?;
+
+        if permissions.is_empty() {
+            return Ok(CommandOutput::success_with_data(
+                format!("{} No permissions granted", "ℹ️ ".blue()),
+                serde_json::json!({ "permissions": permissions }),
+            ));
+        }
+
+        // role -> resource -> permissions
+        let mut by_role: BTreeMap<&str, BTreeMap<&str, Vec<&str>>> = BTreeMap::new();
+        for permission in &permissions {
+            by_role
+                .entry(permission.role.as_str())
+                .or_default()
+                .entry(permission.resource.as_str())
+                .or_default()
+                .push(permission.permission.as_str());
+        }
+
+        let mut output = vec![format!("{} Role permissions:", "🔐".cyan())];
+        for (role, resources) in &by_role {
+            output.push(String::new());
+            output.push(format!("  {}", role.bright_cyan().bold()));
+            for (resource, granted) in resources {
+                output.push(format!("    {} {}", resource, granted.join(", ").dimmed()));
+            }
+        }
+
+        Ok(CommandOutput::success_with_data(
+            output.join("\n"),
+            serde_json::json!({
+                "roles": by_role.len(),
+                "permissions": permissions
+            }),
+        ))
+    }
+
+    async fn check_pending(&self, manager: &MigrationManager) -> Result<CommandOutput> {
+        let config = manager.get_config();
+        let role = match self.role.as_deref() {
+            Some(role) => role,
+            None if !config.database.username.is_empty() => &config.database.username,
+            None => {
+                return Ok(CommandOutput::error(format!(
+                    "{} No role to check: pass --role or configure database.username",
+                    "❌".red()
+                )))
+            }
+        };
+
+        let pending = manager.get_pending_migrations().await?;
+        if is_superuser(manager.session(), role).await? {
+            return Ok(CommandOutput::success_with_data(
+                format!(
+                    "{} Role {} is a superuser and can apply all {} pending migration(s)",
+                    "✅".green(),
+                    role.bright_cyan(),
+                    pending.len()
+                ),
+                serde_json::json!({ "role": role, "superuser": true, "missing": [] }),
+            ));
+        }
+
+        let granted = list_permissions(manager.session(), Some(role)).await?;
+
+        // Every run reads and writes the tracking table
+        let tracking_table = format!(
+            "<table {}.{}>",
+            normalize_identifier(config.tracking_keyspace()),
+            normalize_identifier(&config.migrations.table_name)
+        );
+        let mut needed: Vec<(String, RequiredPermission)> = ["SELECT", "MODIFY"]
+            .into_iter()
+            .map(|permission| {
+                (
+                    "tracking table".to_string(),
+                    RequiredPermission {
+                        permission,
+                        resource: tracking_table.clone(),
+                    },
+                )
+            })
+            .collect();
+
+        for migration in &pending {
+            let keyspace = manager.target_keyspace(migration);
+            let required = if migration.streamed {
+                streamed_permissions(migration, keyspace).await?
+            } else {
+                let (up, _down) =
+                    parse_migration_content(&migration.content).map_err(anyhow::Error::msg)?;
+                required_permissions(&split_cql_statements(&up), keyspace)
+            };
+            needed.extend(
+                required
+                    .into_iter()
+                    .map(|permission| (migration.version.clone(), permission)),
+            );
+        }
+
+        let missing: Vec<&(String, RequiredPermission)> = needed
+            .iter()
+            .filter(|(_, permission)| !permission.is_granted(&granted))
+            .collect();
+
+        let data = serde_json::json!({
+            "role": role,
+            "superuser": false,
+            "pending_count": pending.len(),
+            "missing": missing.iter().map(|(source, permission)| {
+                serde_json::json!({
+                    "migration": source,
+                    "permission": permission.permission,
+                    "resource": permission.resource
+                })
+            }).collect::<Vec<_>>()
+        });
+
+        if missing.is_empty() {
+            return Ok(CommandOutput::success_with_data(
+                format!(
+                    "{} Role {} can apply all {} pending migration(s)",
+                    "✅".green(),
+                    role.bright_cyan(),
+                    pending.len()
+                ),
+                data,
+            ));
+        }
+
+        let mut output = vec![format!(
+            "{} Role {} is missing {} permission(s):",
+            "❌".red(),
+            role.bright_cyan(),
+            missing.len()
+        )];
+        for (source, permission) in &missing {
+            output.push(format!(
+                "  {} {} on {}",
+                format!("{}:", source).dimmed(),
+                permission.permission.bold(),
+                permission.resource
+            ));
+        }
+
+        Ok(CommandOutput::error_with_data(output.join("\n"), data))
+    }
+}
+
+/// Permissions needed by a streamed migration, reading one statement at a time
+async fn streamed_permissions(
+    migration: &MigrationFile,
+    keyspace: &str,
+) -> Result<Vec<RequiredPermission>> {
+    let mut keyspace = keyspace.to_string();
+    let mut required = Vec::new();
+
+    let mut statements = StatementStream::open(&migration.file_path, Section::Up).await?;
+    while let Some(statement) = statements.next_statement().await? {
+        if let Some(used) = cql::used_keyspace(&statement) {
+            keyspace = used;
+            continue;
+        }
+        for permission in required_permissions(std::slice::from_ref(&statement), &keyspace) {
+            if !required.contains(&permission) {
+                required.push(permission);
+            }
+        }
+    }
+
+    Ok(required)
+}
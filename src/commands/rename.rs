@@ -0,0 +1,223 @@
+use crate::{
+    migration::{raw_checksum, read_migration_files, MigrationManager},
+    version::{MigrationVersion, VersionScheme},
+    CommandOutput, MigrationError, MigrationFile,
+};
+use anyhow::Result;
+use clap::Args;
+use colored::*;
+use std::collections::HashSet;
+
+#[derive(Args)]
+pub struct RenameCommand {
+    /// Current version of the migration
+    version: String,
+
+    /// New version; a bare `YYYYMMDD_NNN` keeps the current description
+    new_version: String,
+
+    /// Show what would be renamed without touching the file or the database
+    #[arg(long)]
+    dry_run: bool,
+}
+
+impl RenameCommand {
+    pub async fn execute(&self, manager: &MigrationManager) -> Result<CommandOutput> {
+        let config = manager.get_config();
+        let scheme = manager.version_scheme();
+        let files = read_migration_files(&config.migrations.directory, scheme).await?;
+        let applied = manager.get_applied_migrations().await?;
+        let applied_versions: HashSet<&str> =
+            applied.iter().map(|record| record.version.as_str()).collect();
+
+        let Some(file) = files.iter().find(|file| file.version == self.version) else {
+            return Ok(self.refused(MigrationError::MigrationNotFound(self.version.clone())));
+        };
+        let new_version = match self.resolve_new_version(file, scheme) {
+            Ok(new_version) => new_version,
+            Err(e) => return Ok(self.refused(e)),
+        };
+        let is_applied = applied_versions.contains(file.version.as_str());
+
+        if let Err(e) = self.check_conflicts(&new_version, &files, &applied_versions, scheme) {
+            return Ok(self.refused(e));
+        }
+        if is_applied {
+            // Moving the record would silently accept an edited file
+            if let Some(record) = applied.iter().find(|record| record.version == file.version) {
+                if record.checksum != file.checksum
+                    && raw_checksum(file).await.as_ref() != Some(&record.checksum)
+                {
+                    return Ok(self.refused(MigrationError::ChecksumMismatch {
+                        version: file.version.clone(),
+                        expected: record.checksum.clone(),
+                        actual: file.checksum.clone(),
+                    }));
+                }
+            }
+        }
+
+        let new_path = file.file_path.with_file_name(format!("{}.cql", new_version));
+        let dependents: Vec<&str> = files
+            .iter()
+            .filter(|other| other.directives.depends_on.contains(&file.version))
+            .map(|other| other.version.as_str())
+            .collect();
+
+        let mut output = vec![format!(
+            "{} {} {} → {}",
+            if self.dry_run { "🔍" } else { "✏️ " },
+            if self.dry_run { "Would rename" } else { "Renamed" },
+            file.version.bright_cyan(),
+            new_version.bright_green()
+        )];
+        output.push(format!("  File: {}", new_path.display()));
+        if is_applied {
+            output.push(format!("  Tracking record moved to {}", new_version));
+        }
+        if !dependents.is_empty() {
+            output.push(format!(
+                "\n{} Still declared as a dependency of: {}",
+                "⚠️ ".yellow(),
+                dependents.join(", ")
+            ));
+            output.push("   Update their depends-on directives to the new version".to_string());
+        }
+
+        let data = serde_json::json!({
+            "version": file.version,
+            "new_version": new_version,
+            "file": new_path,
+            "applied": is_applied,
+            "dependents": dependents,
+            "dry_run": self.dry_run
+        });
+
+        if self.dry_run {
+            return Ok(CommandOutput::success_with_data(output.join("\n"), data));
+        }
+
+        manager.ensure_writable()?;
+        tokio::fs::rename(&file.file_path, &new_path).await?;
+
+        if is_applied {
+            let mut renamed = file.clone();
+            renamed.version = new_version.clone();
+            renamed.description = crate::utils::extract_description_from_filename(
+                &format!("{}.cql", new_version),
+                scheme,
+            );
+            renamed.file_path = new_path.clone();
+
+            if let Err(e) = manager.rename_migration_record(&file.version, &renamed).await {
+                // Keep file and record in step
+                tokio::fs::rename(&new_path, &file.file_path).await?;
+                return Ok(CommandOutput::error_with_data(
+                    format!(
+                        "{} Failed to move the tracking record of {}, file left unchanged: {}",
+                        "❌".red(),
+                        file.version,
+                        e
+                    ),
+                    serde_json::json!({
+                        "version": file.version,
+                        "new_version": new_version,
+                        "error": e.to_string(),
+                        "error_code": e.code()
+                    }),
+                )
+                .with_error(&e));
+            }
+        }
+
+        Ok(CommandOutput::success_with_data(output.join("\n"), data))
+    }
+
+    fn resolve_new_version(&self, file: &MigrationFile, scheme: &VersionScheme) -> Result<String, MigrationError> {
+        if !matches!(scheme, VersionScheme::Timestamp) {
+            return Err(MigrationError::ConfigError(
+                "rename only supports versioning.scheme 'timestamp'; rename the file by hand".to_string(),
+            ));
+        }
+        let current: MigrationVersion = file.version.parse().map_err(MigrationError::InvalidFormat)?;
+        let new_version = current
+            .renamed(&self.new_version)
+            .map_err(MigrationError::InvalidFormat)?
+            .to_string();
+
+        if new_version == file.version {
+            return Err(MigrationError::InvalidFormat(format!(
+                "{} already has version {}",
+                file.file_path.display(),
+                new_version
+            )));
+        }
+        Ok(new_version)
+    }
+
+    /// The new version must be free and keep applied migrations ahead of pending ones
+    fn check_conflicts(
+        &self,
+        new_version: &str,
+        files: &[MigrationFile],
+        applied: &HashSet<&str>,
+        scheme: &VersionScheme,
+    ) -> Result<(), MigrationError> {
+        let prefix = crate::utils::version_prefix(new_version);
+        let others = files.iter().filter(|other| other.version != self.version);
+
+        if let Some(taken) = others
+            .clone()
+            .find(|other| crate::utils::version_prefix(&other.version) == prefix)
+        {
+            return Err(MigrationError::IntegrityError(format!(
+                "{} is already used by {}",
+                prefix, taken.version
+            )));
+        }
+        if applied.contains(new_version) {
+            return Err(MigrationError::AlreadyApplied {
+                version: new_version.to_string(),
+            });
+        }
+
+        if applied.contains(self.version.as_str()) {
+            // An applied migration must still sort before every pending one
+            if let Some(pending) = others
+                .filter(|other| !applied.contains(other.version.as_str()))
+                .find(|other| scheme.compare(&other.version, new_version).is_lt())
+            {
+                return Err(MigrationError::OutOfOrder {
+                    version: pending.version.clone(),
+                    latest_applied: new_version.to_string(),
+                });
+            }
+        } else if let Some(latest_applied) = applied
+            .iter()
+            .filter(|version| **version != self.version)
+            .max_by(|a, b| scheme.compare(a, b))
+        {
+            if scheme.compare(new_version, latest_applied).is_lt() {
+                return Err(MigrationError::OutOfOrder {
+                    version: new_version.to_string(),
+                    latest_applied: latest_applied.to_string(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    fn refused(&self, e: MigrationError) -> CommandOutput {
+        CommandOutput::error_with_data(
+            format!("{} Cannot rename {}: {}", "❌".red(), self.version, e),
+            serde_json::json!({
+                "version": self.version,
+                "new_version": self.new_version,
+                "error": e.to_string(),
+                "error_code": e.code()
+            }),
+        )
+        .with_error(&e)
+    }
+}
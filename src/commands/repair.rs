@@ -0,0 +1,363 @@
+use crate::{
+    confirm::{ConfirmationScope, TOKEN_VALIDITY_MINUTES},
+    migration::MigrationManager,
+    progress,
+    progress::ProgressMode,
+    queue::DEFAULT_QUEUE_TIMEOUT,
+    utils::format_timestamp,
+    CommandOutput, MigrationError, MigrationRecord,
+};
+use anyhow::Result;
+use chrono::Utc;
+use clap::{ArgGroup, Args};
+use colored::*;
+use std::collections::HashSet;
+use std::io::{BufRead, IsTerminal, Write};
+
+#[derive(Args)]
+#[command(group(ArgGroup::new("action").required(true).multiple(true)))]
+pub struct RepairCommand {
+    /// Record the new checksum of applied migrations whose DOWN section was
+    /// edited; changed UP sections are never accepted
+    #[arg(long, group = "action")]
+    accept_down: bool,
+
+    /// Delete the tracking records of applied migrations whose file is gone
+    #[arg(long, group = "action")]
+    remove_orphans: bool,
+
+    /// Only repair this version
+    #[arg(long, value_name = "VERSION")]
+    version: Option<String>,
+
+    /// Remove every orphaned record without asking
+    #[arg(long)]
+    yes: bool,
+
+    /// Token printed by a previous unconfirmed run, confirming the same removal
+    #[arg(long, value_name = "TOKEN")]
+    confirm_token: Option<String>,
+
+    /// Show what would be repaired without writing to the tracking table
+    #[arg(long)]
+    dry_run: bool,
+}
+
+/// Lines and JSON entries of one repair action, or the output it stopped with
+type Repaired = std::result::Result<(Vec<String>, Vec<serde_json::Value>), CommandOutput>;
+
+impl RepairCommand {
+    pub async fn execute(&self, manager: &MigrationManager) -> Result<CommandOutput> {
+        let _run = manager.exclusive_run("repair", DEFAULT_QUEUE_TIMEOUT).await?;
+
+        let issues: Vec<MigrationError> = manager
+            .verify_migrations()
+            .await?
+            .into_iter()
+            .filter(|issue| self.version.as_ref().is_none_or(|version| issue_version(issue) == Some(version)))
+            .collect();
+
+        let mut sections = Vec::new();
+        let mut data = serde_json::json!({ "dry_run": self.dry_run });
+
+        if self.accept_down {
+            let (lines, accepted) = match self.accept_down(manager, &issues).await? {
+                Ok(repaired) => repaired,
+                Err(output) => return Ok(output),
+            };
+            if !accepted.is_empty() {
+                sections.push(format!(
+                    "{} {} DOWN section change(s) of {} migration(s):\n{}",
+                    "🔧".cyan(),
+                    if self.dry_run { "Would accept" } else { "Accepted" },
+                    accepted.len(),
+                    lines.join("\n")
+                ));
+            }
+            data["accepted_down"] = accepted.into();
+        }
+
+        if self.remove_orphans {
+            let (lines, removed) = match self.remove_orphans(manager, &issues, &mut data).await? {
+                Ok(repaired) => repaired,
+                Err(output) => return Ok(output),
+            };
+            if !lines.is_empty() {
+                sections.push(lines.join("\n"));
+            }
+            data["removed_orphans"] = removed.into();
+        }
+
+        let message = if sections.is_empty() {
+            format!("{} Nothing to repair", "✅".green())
+        } else {
+            sections.join("\n\n")
+        };
+        Ok(CommandOutput::success_with_data(message, data))
+    }
+
+    async fn accept_down(&self, manager: &MigrationManager, issues: &[MigrationError]) -> Result<Repaired> {
+        // Asked for by version, a changed UP section is an error rather than skipped
+        let up_changed = issues
+            .iter()
+            .find(|issue| matches!(issue, MigrationError::ChecksumMismatch { .. }));
+        if let (Some(changed), Some(_)) = (up_changed, &self.version) {
+            return Ok(Err(self.refused(changed, "its UP section changed, which --accept-down never accepts")));
+        }
+
+        let files = manager.get_migration_files().await?;
+        let (mut lines, mut accepted) = (Vec::new(), Vec::new());
+        for issue in issues {
+            let MigrationError::DownChecksumMismatch { version, expected, actual } = issue else {
+                continue;
+            };
+            let Some(file) = files.iter().find(|file| &file.version == version) else {
+                continue;
+            };
+
+            if !self.dry_run {
+                if let Err(e) = manager
+                    .update_migration_checksum(version, actual, &file.up_checksum())
+                    .await
+                {
+                    return Ok(Err(self.refused(&e, "the tracking record could not be updated")));
+                }
+            }
+            lines.push(format!(
+                "  {} {} {} → {}",
+                "✅".green(),
+                version.bright_cyan(),
+                short(expected).dimmed(),
+                short(actual)
+            ));
+            accepted.push(serde_json::json!({
+                "version": version,
+                "old_checksum": expected,
+                "new_checksum": actual
+            }));
+        }
+        Ok(Ok((lines, accepted)))
+    }
+
+    async fn remove_orphans(
+        &self,
+        manager: &MigrationManager,
+        issues: &[MigrationError],
+        data: &mut serde_json::Value,
+    ) -> Result<Repaired> {
+        let records = manager.get_applied_migrations().await?;
+        let orphans = match orphaned_records(&records, issues, self.version.as_deref()) {
+            Ok(orphans) => orphans,
+            Err(e) => return Ok(Err(self.refused(&e, "nothing to remove"))),
+        };
+        if orphans.is_empty() {
+            return Ok(Ok((Vec::new(), Vec::new())));
+        }
+
+        let keyspace = manager.get_config().database.keyspace.clone();
+        let scope = ConfirmationScope {
+            operation: "repair",
+            mode: "remove-orphans",
+            cluster_name: manager.cluster_name(),
+            keyspace: &keyspace,
+            records: &orphans,
+        };
+        let token_confirmed = match &self.confirm_token {
+            Some(token) if scope.accepts(token, Utc::now()) => true,
+            Some(token) => {
+                let e = MigrationError::ConfigError(format!(
+                    "Confirmation token {} does not match this repair; it has expired, or the orphaned records changed since it was printed",
+                    token
+                ));
+                return Ok(Err(self.refused(&e, "re-run without --confirm-token to get a new token")));
+            }
+            None => false,
+        };
+
+        let describe = |record: &MigrationRecord| {
+            format!(
+                "{} - {} {}",
+                record.version.bright_cyan(),
+                record.description,
+                format!("(applied {})", format_timestamp(record.applied_at)).dimmed()
+            )
+        };
+
+        let selected: Vec<&MigrationRecord> = if self.dry_run || self.yes || token_confirmed {
+            orphans.iter().collect()
+        } else if std::io::stdin().is_terminal() && progress::mode() == ProgressMode::Stdout {
+            progress!("{} Tracking records without a migration file:", "🧹".cyan());
+            orphans
+                .iter()
+                .filter(|record| ask(&format!("  Remove {}? [y/N] ", describe(record))))
+                .collect()
+        } else {
+            let token = scope.token(Utc::now());
+            let mut lines = vec![format!(
+                "{} {} tracking record(s) without a migration file:",
+                "🧹".cyan(),
+                orphans.len()
+            )];
+            lines.extend(orphans.iter().map(|record| format!("  • {}", describe(record))));
+            lines.push(String::new());
+            lines.push(format!("{} Use --yes to remove them, or run in a terminal to choose", "🔒".yellow()));
+            lines.push(format!(
+                "{} Or re-run the same command with --confirm-token {} within {} minutes",
+                "🔑".yellow(),
+                token.bright_yellow(),
+                TOKEN_VALIDITY_MINUTES
+            ));
+            data["orphans"] = orphans.iter().map(|record| record.version.clone()).collect();
+            data["confirm_token"] = token.into();
+            return Ok(Ok((lines, Vec::new())));
+        };
+
+        if selected.is_empty() {
+            return Ok(Ok((vec![format!("{} No tracking records removed", "✅".green())], Vec::new())));
+        }
+
+        let mut lines = vec![format!(
+            "{} {} {} tracking record(s) without a migration file:",
+            "🧹".cyan(),
+            if self.dry_run { "Would remove" } else { "Removed" },
+            selected.len()
+        )];
+        if !self.dry_run {
+            // Keep a copy to restore if a file was only temporarily missing
+            if let Some(backup) = manager.backup_tracking_table("repair").await? {
+                lines.push(format!("  Backup: {}", backup.display()));
+            }
+        }
+
+        let mut removed = Vec::new();
+        for record in selected {
+            if !self.dry_run {
+                if let Err(e) = manager.remove_orphaned_record(record).await {
+                    return Ok(Err(self.refused(&e, "the tracking record could not be removed")));
+                }
+            }
+            lines.push(format!("  {} {}", "✅".green(), describe(record)));
+            removed.push(serde_json::json!({
+                "version": record.version,
+                "checksum": record.checksum,
+                "applied_at": record.applied_at
+            }));
+        }
+        Ok(Ok((lines, removed)))
+    }
+
+    fn refused(&self, error: &MigrationError, reason: &str) -> CommandOutput {
+        CommandOutput::error_with_data(
+            format!("{} Cannot repair: {} ({})", "❌".red(), error, reason),
+            serde_json::json!({
+                "version": self.version,
+                "error": error.to_string(),
+                "error_code": error.code()
+            }),
+        )
+        .with_error(error)
+    }
+}
+
+/// Ask a yes/no question on the terminal; anything but `y` or `yes` is no
+fn ask(question: &str) -> bool {
+    print!("{}", question);
+    let _ = std::io::stdout().flush();
+    let mut answer = String::new();
+    if std::io::stdin().lock().read_line(&mut answer).is_err() {
+        return false;
+    }
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+/// Records of `records` whose file `issues` reports missing; asked for by
+/// `version`, a record that is not orphaned is an error
+fn orphaned_records(
+    records: &[MigrationRecord],
+    issues: &[MigrationError],
+    version: Option<&str>,
+) -> Result<Vec<MigrationRecord>, MigrationError> {
+    let missing: HashSet<&String> = issues
+        .iter()
+        .filter_map(|issue| match issue {
+            MigrationError::MigrationNotFound(version) => Some(version),
+            _ => None,
+        })
+        .collect();
+    let orphans: Vec<MigrationRecord> = records
+        .iter()
+        .filter(|record| missing.contains(&record.version))
+        .cloned()
+        .collect();
+
+    match version {
+        Some(version) if orphans.is_empty() => {
+            if records.iter().any(|record| record.version == version) {
+                Err(MigrationError::IntegrityError(format!(
+                    "{} still has a migration file; only records without one are removed",
+                    version
+                )))
+            } else {
+                Err(MigrationError::MigrationNotFound(version.to_string()))
+            }
+        }
+        _ => Ok(orphans),
+    }
+}
+
+/// Version an integrity issue is about
+fn issue_version(issue: &MigrationError) -> Option<&String> {
+    match issue {
+        MigrationError::ChecksumMismatch { version, .. }
+        | MigrationError::DownChecksumMismatch { version, .. }
+        | MigrationError::MigrationNotFound(version) => Some(version),
+        _ => None,
+    }
+}
+
+/// Leading characters of a checksum, enough to tell two apart
+fn short(checksum: &str) -> &str {
+    checksum.get(..12).unwrap_or(checksum)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::parse;
+
+    #[test]
+    fn test_orphaned_records() {
+        // An action is required
+        assert!(parse::<RepairCommand>(&["--version", "002"]).is_err());
+        assert!(parse::<RepairCommand>(&["--remove-orphans", "--version", "002", "--yes"]).is_ok());
+
+        let records = vec![
+            MigrationRecord::for_test("001", Utc::now()),
+            MigrationRecord::for_test("002", Utc::now()),
+            MigrationRecord::for_test("003", Utc::now()),
+        ];
+        let issues = vec![
+            MigrationError::MigrationNotFound("002".to_string()),
+            MigrationError::ChecksumMismatch {
+                version: "003".to_string(),
+                expected: "a".to_string(),
+                actual: "b".to_string(),
+            },
+        ];
+
+        // Only records whose file is missing are orphans
+        let orphans = orphaned_records(&records, &issues, None).unwrap();
+        assert_eq!(orphans.iter().map(|record| record.version.as_str()).collect::<Vec<_>>(), vec!["002"]);
+        assert!(orphaned_records(&records, &[], None).unwrap().is_empty());
+
+        // A targeted version must be an orphan; `execute` narrows the issues to it
+        assert_eq!(orphaned_records(&records, &issues[..1], Some("002")).unwrap().len(), 1);
+        let narrowed: Vec<MigrationError> = Vec::new();
+        let error = orphaned_records(&records, &narrowed, Some("001")).unwrap_err();
+        assert!(error.to_string().contains("001 still has a migration file"));
+        assert!(matches!(
+            orphaned_records(&records, &narrowed, Some("009")),
+            Err(MigrationError::MigrationNotFound(version)) if version == "009"
+        ));
+    }
+}
@@ -1,5 +1,9 @@
 
-use crate::{migration::MigrationManager, CommandOutput};
+use crate::{
+    confirm::ConfirmationScope, migration::MigrationManager, progress, queue::DEFAULT_QUEUE_TIMEOUT,
+    CommandOutput, MigrationError,
+};
+use chrono::Utc;
 use anyhow::Result;
 use clap::Args;
 use colored::*;
@@ -9,10 +13,61 @@ pub struct ResetCommand {
     /// Skip confirmation prompt
     #[arg(long)]
     yes: bool,
+
+    /// Also tear down the schema by running every DOWN section in reverse order
+    #[arg(long, conflicts_with = "drop_keyspace")]
+    drop_schema: bool,
+
+    /// Drop the entire keyspace, including all tables and data
+    #[arg(long)]
+    drop_keyspace: bool,
+
+    /// With --drop-keyspace, also drop the tracking table kept in a separate
+    /// `tracking_keyspace`, which is otherwise left in place
+    #[arg(long, requires = "drop_keyspace")]
+    drop_tracking_table: bool,
+
+    /// Keyspace name, required to confirm --drop-schema or --drop-keyspace
+    #[arg(long, value_name = "KEYSPACE")]
+    confirm: Option<String>,
+
+    /// Token printed by a previous unconfirmed run, confirming the same reset
+    /// without --yes or --confirm
+    #[arg(long, value_name = "TOKEN")]
+    confirm_token: Option<String>,
 }
 
 impl ResetCommand {
+    /// What is torn down, as named in confirmation tokens
+    fn mode(&self) -> &'static str {
+        if self.drop_keyspace && self.drop_tracking_table {
+            "drop-keyspace-and-tracking"
+        } else if self.drop_keyspace {
+            "drop-keyspace"
+        } else if self.drop_schema {
+            "drop-schema"
+        } else {
+            "tracking"
+        }
+    }
+
+    /// Whether the reset was confirmed: with a token (checked against the
+    /// scope separately) or --yes, plus --confirm naming `keyspace` when the
+    /// schema is torn down
+    fn is_confirmed(&self, keyspace: &str) -> bool {
+        let tears_down_schema = self.drop_schema || self.drop_keyspace;
+        self.confirm_token.is_some()
+            || (self.yes
+                && (!tears_down_schema
+                    || self
+                        .confirm
+                        .as_deref()
+                        .is_some_and(|confirm| confirm.trim_matches('"') == keyspace.trim_matches('"'))))
+    }
+
     pub async fn execute(&self, manager: &mut MigrationManager) -> Result<CommandOutput> {
+        let _run = manager.exclusive_run("reset", DEFAULT_QUEUE_TIMEOUT).await?;
+
         // Safety check - make sure destructive operations are allowed
         if !manager.get_config().behavior.allow_destructive {
             return Ok(CommandOutput::error(format!(
@@ -23,8 +78,10 @@ impl ResetCommand {
 
         // Get current state
         let applied_migrations = manager.get_applied_migrations().await?;
+        let keyspace = manager.get_config().database.keyspace.clone();
+        let tears_down_schema = self.drop_schema || self.drop_keyspace;
 
-        if applied_migrations.is_empty() {
+        if applied_migrations.is_empty() && !self.drop_keyspace {
             return Ok(CommandOutput::success(format!(
                 "{} No migrations to reset - migration table is already empty",
                 "✅".green()
@@ -48,22 +105,89 @@ impl ResetCommand {
         }
 
         warning.push(String::new());
-        warning.push(format!(
-            "{} This operation will NOT drop your actual database tables or data.",
-            "💡".bright_blue()
-        ));
-        warning.push("It only clears the migration tracking table.".dimmed().to_string());
+        let external_tracking = manager.has_external_tracking_table();
+        if self.drop_keyspace {
+            warning.push(format!(
+                "{} The entire keyspace '{}' will be DROPPED, including all tables and data.",
+                "🔥".bright_red(),
+                keyspace.bright_red().bold()
+            ));
+            if external_tracking && self.drop_tracking_table {
+                warning.push(format!(
+                    "{} The tracking table {} will be DROPPED as well.",
+                    "🔥".bright_red(),
+                    manager.get_config().tracking_table().bright_red().bold()
+                ));
+            } else if external_tracking {
+                warning.push(format!(
+                    "{} The tracking table {} is kept; add --drop-tracking-table to drop it too.",
+                    "💡".bright_blue(),
+                    manager.get_config().tracking_table()
+                ));
+            }
+        } else if self.drop_schema {
+            warning.push(format!(
+                "{} The DOWN section of every applied migration will be executed in reverse order.",
+                "🔥".bright_red()
+            ));
+        } else {
+            warning.push(format!(
+                "{} This operation will NOT drop your actual database tables or data.",
+                "💡".bright_blue()
+            ));
+            warning.push("It only clears the migration tracking table.".dimmed().to_string());
+        }
         warning.push(String::new());
         warning.push(format!(
             "Total migrations to reset: {}",
             applied_migrations.len().to_string().bright_red().bold()
         ));
 
-        if !self.yes {
+        let scope = ConfirmationScope {
+            operation: "reset",
+            mode: self.mode(),
+            cluster_name: manager.cluster_name(),
+            keyspace: &keyspace,
+            records: &applied_migrations,
+        };
+
+        if let Some(token) = &self.confirm_token {
+            if !scope.accepts(token, Utc::now()) {
+                let e = MigrationError::ConfigError(format!(
+                    "Confirmation token {} does not match this reset; it has expired, or the keyspace, the reset options or the applied migrations changed since it was printed",
+                    token
+                ));
+                return Ok(CommandOutput::error_with_data(
+                    format!("{} {}. Re-run without --confirm-token to get a new token.", "❌".red(), e),
+                    serde_json::json!({
+                        "error": e.to_string(),
+                        "error_code": e.code()
+                    }),
+                )
+                .with_error(&e));
+            }
+        }
+
+        if !self.is_confirmed(&keyspace) {
+            let token = scope.token(Utc::now());
             warning.push(String::new());
+            if tears_down_schema {
+                warning.push(format!(
+                    "{} Use --yes --confirm {} to confirm this destructive operation",
+                    "🔒".yellow(),
+                    keyspace
+                ));
+            } else {
+                warning.push(format!(
+                    "{} Use --yes to confirm this destructive operation",
+                    "🔒".yellow()
+                ));
+            }
             warning.push(format!(
-                "{} Use --yes to confirm this destructive operation",
-                "🔒".yellow()
+                "{} Or re-run the same command with --confirm-token {} within {} minutes",
+                "🔑".yellow(),
+                token.bright_yellow(),
+                crate::confirm::TOKEN_VALIDITY_MINUTES
             ));
 
             return Ok(CommandOutput::success_with_data(
@@ -72,15 +196,105 @@ impl ResetCommand {
                     "action": "confirmation_required",
                     "migrations_to_reset": applied_migrations.len(),
                     "destructive": true,
-                    "confirmed": false
+                    "confirmed": false,
+                    "confirm_token": token
                 })
             ));
         }
 
+        // Keep a copy of the records to restore if the reset was a mistake
+        let backup = match manager.backup_tracking_table("reset").await {
+            Ok(backup) => backup,
+            Err(e) => {
+                return Ok(CommandOutput::error_with_data(
+                    format!(
+                        "{} Could not back up the tracking table, nothing was reset: {}",
+                        "❌".red(),
+                        e
+                    ),
+                    serde_json::json!({
+                        "error": e.to_string(),
+                        "error_code": e.code()
+                    }),
+                )
+                .with_error(&e));
+            }
+        };
+        if let Some(path) = &backup {
+            progress!(
+                "{} Backed up {} tracking record(s) to {} (undo with restore-history)",
+                "💾".bright_blue(),
+                applied_migrations.len(),
+                path.display()
+            );
+        }
+
+        if self.drop_keyspace {
+            if let Err(e) = manager.drop_keyspace().await {
+                return Ok(CommandOutput::error(format!("Failed to drop keyspace: {}", e)));
+            }
+            // Only an explicit --drop-tracking-table touches a tracking table outside the keyspace
+            let tracking_dropped = external_tracking && self.drop_tracking_table;
+            if tracking_dropped {
+                if let Err(e) = manager.drop_tracking_table().await {
+                    return Ok(CommandOutput::error(format!(
+                        "Dropped keyspace '{}' but failed to drop the tracking table: {}",
+                        keyspace, e
+                    )));
+                }
+            }
+            let records_reset = if external_tracking && !tracking_dropped {
+                0
+            } else {
+                applied_migrations.len()
+            };
+            return Ok(CommandOutput::success_with_data(
+                format!(
+                    "{} Dropped keyspace '{}' and {} migration record(s)",
+                    "✅".green().bold(),
+                    keyspace,
+                    records_reset
+                ),
+                serde_json::json!({
+                    "action": "keyspace_dropped",
+                    "keyspace": keyspace,
+                    "tracking_table_dropped": tracking_dropped,
+                    "migrations_reset": records_reset,
+                    "destructive": true,
+                    "confirmed": true,
+                    "backup": backup
+                })
+            ));
+        }
+
+        if self.drop_schema {
+            return match manager.drop_schema().await {
+                Ok(rolled_back) => Ok(CommandOutput::success_with_data(
+                    format!(
+                        "{} Rolled back {} migration(s) and reset the tracking table",
+                        "✅".green().bold(),
+                        rolled_back.len()
+                    ),
+                    serde_json::json!({
+                        "action": "schema_dropped",
+                        "rolled_back_migrations": rolled_back,
+                        "migrations_reset": applied_migrations.len(),
+                        "destructive": true,
+                        "confirmed": true,
+                        "backup": backup
+                    })
+                )),
+                Err(e) => Ok(CommandOutput::error(format!(
+                    "Failed to tear down schema: {}. Migrations rolled back before the failure have been removed from the tracking table.",
+                    e
+                ))),
+            };
+        }
+
         // Perform the reset
         match manager.reset_migrations().await {
             Ok(_) => {
-                let success_message = vec![
+                let success_message = [
                     format!("{} Successfully reset all migrations!", "✅".green().bold()),
                     String::new(),
                     format!("• Removed {} migration record(s)", applied_migrations.len()),
@@ -98,7 +312,8 @@ impl ResetCommand {
                         "action": "reset_completed",
                         "migrations_reset": applied_migrations.len(),
                         "destructive": true,
-                        "confirmed": true
+                        "confirmed": true,
+                        "backup": backup
                     })
                 ))
             }
@@ -110,4 +325,33 @@ impl ResetCommand {
             }
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::parse;
+
+    #[test]
+    fn test_teardown_modes_and_confirmation() {
+        assert!(parse::<ResetCommand>(&["--drop-schema", "--drop-keyspace"]).is_err());
+        assert!(parse::<ResetCommand>(&["--drop-tracking-table"]).is_err());
+
+        let modes: Vec<_> = [&[][..], &["--drop-schema"], &["--drop-keyspace"], &["--drop-keyspace", "--drop-tracking-table"]]
+            .iter()
+            .map(|args| parse::<ResetCommand>(args).unwrap().mode())
+            .collect();
+        assert_eq!(modes, vec!["tracking", "drop-schema", "drop-keyspace", "drop-keyspace-and-tracking"]);
+
+        // Clearing the tracking table only needs --yes
+        assert!(parse::<ResetCommand>(&["--yes"]).unwrap().is_confirmed("app"));
+        assert!(!parse::<ResetCommand>(&[]).unwrap().is_confirmed("app"));
+
+        // Tearing down the schema also needs the keyspace name
+        assert!(!parse::<ResetCommand>(&["--yes", "--drop-schema"]).unwrap().is_confirmed("app"));
+        assert!(!parse::<ResetCommand>(&["--yes", "--drop-keyspace", "--confirm", "other"]).unwrap().is_confirmed("app"));
+        assert!(parse::<ResetCommand>(&["--yes", "--drop-keyspace", "--confirm", "app"]).unwrap().is_confirmed("app"));
+        assert!(parse::<ResetCommand>(&["--yes", "--drop-schema", "--confirm", "\"App\""]).unwrap().is_confirmed("\"App\""));
+        assert!(parse::<ResetCommand>(&["--drop-schema", "--confirm-token", "abc"]).unwrap().is_confirmed("app"));
+    }
+}
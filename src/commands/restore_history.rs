@@ -0,0 +1,140 @@
+use crate::{
+    backup::{BackupRecord, TrackingBackup},
+    migration::MigrationManager,
+    queue::DEFAULT_QUEUE_TIMEOUT,
+    CommandOutput, MigrationError,
+};
+use anyhow::Result;
+use clap::Args;
+use colored::*;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct RestoreHistoryCommand {
+    /// Backup file written before a destructive command (see `backups.directory`)
+    file: PathBuf,
+
+    /// Show what would be restored without writing to the tracking table
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Restore a backup taken on a different cluster
+    #[arg(long)]
+    allow_other_cluster: bool,
+}
+
+impl RestoreHistoryCommand {
+    pub async fn execute(&self, manager: &MigrationManager) -> Result<CommandOutput> {
+        let _run = manager
+            .exclusive_run("restore-history", DEFAULT_QUEUE_TIMEOUT)
+            .await?;
+
+        let backup = match TrackingBackup::read(&self.file) {
+            Ok(backup) => backup,
+            Err(e) => return Ok(self.refused(e)),
+        };
+
+        if let (Some(taken_on), Some(current)) = (&backup.cluster_name, manager.cluster_name()) {
+            if taken_on != current && !self.allow_other_cluster {
+                return Ok(self.refused(MigrationError::IntegrityError(format!(
+                    "{} was taken on cluster {}, not {}; pass --allow-other-cluster to restore it anyway",
+                    self.file.display(),
+                    taken_on,
+                    current
+                ))));
+            }
+        }
+
+        let current: HashMap<String, String> = manager
+            .get_applied_migrations()
+            .await?
+            .into_iter()
+            .map(|record| (record.version, record.checksum))
+            .collect();
+
+        let (mut added, mut replaced, mut unchanged) = (Vec::new(), Vec::new(), Vec::new());
+        for entry in &backup.records {
+            match current.get(&entry.record.version) {
+                None => added.push(entry),
+                Some(checksum) if *checksum != entry.record.checksum => replaced.push(entry),
+                Some(_) => unchanged.push(entry),
+            }
+        }
+        let to_write: Vec<BackupRecord> = added.iter().chain(&replaced).map(|entry| (*entry).clone()).collect();
+
+        let mut output = vec![format!(
+            "{} {} {} tracking record(s) from {} (taken before {} on {})",
+            if self.dry_run { "🔍" } else { "♻️ " },
+            if self.dry_run { "Would restore" } else { "Restored" },
+            to_write.len(),
+            self.file.display().to_string().bright_cyan(),
+            backup.operation,
+            crate::utils::format_timestamp(backup.created_at)
+        )];
+        for entry in &added {
+            output.push(format!("  + {}", entry.record.version.bright_green()));
+        }
+        for entry in &replaced {
+            output.push(format!(
+                "  ~ {} {}",
+                entry.record.version.bright_yellow(),
+                "(checksum differs from the current record)".dimmed()
+            ));
+        }
+        if !unchanged.is_empty() {
+            output.push(format!("  {} record(s) already match", unchanged.len()).dimmed().to_string());
+        }
+        if backup.tracking_table != manager.tracking_table() {
+            output.push(format!(
+                "\n{} The backup was taken from {}; records are restored into {}",
+                "⚠️ ".yellow(),
+                backup.tracking_table,
+                manager.tracking_table()
+            ));
+        }
+
+        let versions = |entries: &[&BackupRecord]| -> Vec<String> {
+            entries.iter().map(|entry| entry.record.version.clone()).collect()
+        };
+        let data = serde_json::json!({
+            "file": self.file,
+            "operation": backup.operation,
+            "created_at": backup.created_at,
+            "added": versions(&added),
+            "replaced": versions(&replaced),
+            "unchanged": versions(&unchanged),
+            "dry_run": self.dry_run
+        });
+
+        if self.dry_run || to_write.is_empty() {
+            return Ok(CommandOutput::success_with_data(output.join("\n"), data));
+        }
+
+        if let Err(e) = manager.restore_tracking_records(&to_write).await {
+            return Ok(CommandOutput::error_with_data(
+                format!("{} Failed to restore the tracking table: {}", "❌".red(), e),
+                serde_json::json!({
+                    "file": self.file,
+                    "error": e.to_string(),
+                    "error_code": e.code()
+                }),
+            )
+            .with_error(&e));
+        }
+
+        Ok(CommandOutput::success_with_data(output.join("\n"), data))
+    }
+
+    fn refused(&self, error: MigrationError) -> CommandOutput {
+        CommandOutput::error_with_data(
+            format!("{} {}", "❌".red(), error),
+            serde_json::json!({
+                "file": self.file,
+                "error": error.to_string(),
+                "error_code": error.code()
+            }),
+        )
+        .with_error(&error)
+    }
+}
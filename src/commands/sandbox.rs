@@ -0,0 +1,122 @@
+use crate::{config::Config, migration::MigrationManager, progress, scratch, CommandOutput};
+use anyhow::Result;
+use clap::Args;
+use colored::*;
+use std::time::Duration;
+
+#[derive(Args)]
+pub struct SandboxCommand {
+    /// Prefix for the generated keyspace name
+    #[arg(long, default_value = "sandbox")]
+    prefix: String,
+
+    /// Keep the keyspace instead of dropping it on exit
+    #[arg(long, conflicts_with = "ttl")]
+    keep: bool,
+
+    /// Drop the keyspace automatically after this many seconds
+    #[arg(long, value_name = "SECONDS")]
+    ttl: Option<u64>,
+}
+
+impl SandboxCommand {
+    pub async fn execute(&self, config: &Config) -> Result<CommandOutput> {
+        let keyspace = scratch::keyspace_name(&self.prefix)?;
+
+        let mut sandbox_config = config.clone();
+        sandbox_config.database.keyspace = keyspace.clone();
+        // Track inside the sandbox keyspace, never in a shared tracking keyspace
+        sandbox_config.migrations.tracking_keyspace = None;
+        sandbox_config.behavior.auto_create_keyspace = true;
+        // The sandbox owns its keyspace, so it may always drop it
+        sandbox_config.behavior.allow_destructive = true;
+
+        let mut manager = MigrationManager::new(sandbox_config).await?;
+        let pending = match manager
+            .get_pending_migrations()
+            .await?
+            .iter()
+            .map(|migration| scratch::retarget_migration(migration, &config.database.keyspace, &keyspace))
+            .collect::<Result<Vec<_>, _>>()
+        {
+            Ok(pending) => pending,
+            Err(e) => {
+                manager.drop_keyspace().await?;
+                return Ok(CommandOutput::error(format!("{} {}", "❌".red(), e)).with_error(&e));
+            }
+        };
+
+        for migration in &pending {
+            if let Err(e) = manager.apply_migration(migration).await {
+                if !self.keep {
+                    manager.drop_keyspace().await?;
+                }
+                return Ok(CommandOutput::error_with_data(
+                    format!(
+                        "{} Sandbox migration {} failed: {}",
+                        "❌".red(),
+                        migration.version,
+                        e
+                    ),
+                    serde_json::json!({
+                        "keyspace": keyspace,
+                        "failed_migration": migration.version,
+                        "error": e.to_string(),
+                        "error_code": e.code(),
+                        "kept": self.keep
+                    })
+                )
+                .with_error(&e));
+            }
+        }
+
+        progress!("{} Sandbox ready", "🧪".cyan());
+        progress!("  Keyspace:   {}", keyspace.bright_cyan());
+        progress!("  Hosts:      {}", config.database.hosts.join(","));
+        progress!("  Migrations: {} applied", pending.len());
+
+        let data = serde_json::json!({
+            "keyspace": keyspace,
+            "hosts": config.database.hosts,
+            "applied_count": pending.len(),
+            "kept": self.keep
+        });
+
+        if self.keep {
+            return Ok(CommandOutput::success_with_data(
+                format!(
+                    "{} Sandbox keyspace {} kept. Drop it with: DROP KEYSPACE {};",
+                    "✅".green(),
+                    keyspace.bright_cyan(),
+                    keyspace
+                ),
+                data,
+            ));
+        }
+
+        match self.ttl {
+            Some(ttl) => {
+                progress!(
+                    "{} Keyspace will be dropped in {}s (or on Ctrl-C)",
+                    "⏳".yellow(),
+                    ttl
+                );
+                tokio::select! {
+                    _ = tokio::time::sleep(Duration::from_secs(ttl)) => {}
+                    _ = tokio::signal::ctrl_c() => {}
+                }
+            }
+            None => {
+                progress!("{} Press Ctrl-C to drop the sandbox keyspace", "⏳".yellow());
+                tokio::signal::ctrl_c().await?;
+            }
+        }
+
+        manager.drop_keyspace().await?;
+
+        Ok(CommandOutput::success_with_data(
+            format!("{} Dropped sandbox keyspace {}", "✅".green(), keyspace.bright_cyan()),
+            data,
+        ))
+    }
+}
@@ -1,24 +1,200 @@
-use crate::{migration::MigrationManager, CommandOutput, utils::format_timestamp};
+use crate::{
+    cluster::{self, SchemaAgreement},
+    commands::{doctor::format_nodes, drift::format_drift, lock::describe_lock},
+    drift::SchemaDrift, history::applied_at_instant, lock::LockStatus, migration::MigrationManager,
+    report::StatusReport,
+    snapshot::{SnapshotMigration, SnapshotState, StatusSnapshot},
+    utils::format_timestamp, version::MigrationVersion, CommandOutput, MigrationFile, MigrationRecord,
+};
 use anyhow::Result;
+use chrono::{DateTime, NaiveDate, Utc};
 use clap::Args;
 use colored::*;
-use std::collections::HashSet;
+use regex::{Regex, RegexBuilder};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
 
 #[derive(Args)]
 pub struct StatusCommand {
     /// Show detailed information about each migration
     #[arg(short, long)]
     verbose: bool,
+
+    /// Also compare the live schema with the applied migrations
+    #[arg(long)]
+    with_drift: bool,
+
+    /// Also list the nodes of the cluster and whether they agree on the schema
+    #[arg(long)]
+    cluster: bool,
+
+    /// Only list pending migrations
+    #[arg(long, conflicts_with = "applied_only")]
+    pending_only: bool,
+
+    /// Only list applied migrations
+    #[arg(long)]
+    applied_only: bool,
+
+    /// Only list migrations applied (or, if pending, dated) on or after this
+    /// date (`YYYY-MM-DD` or RFC 3339)
+    #[arg(long, value_name = "DATE", value_parser = parse_instant)]
+    since: Option<DateTime<Utc>>,
+
+    /// Show which migrations were applied at this instant (`YYYY-MM-DD` or
+    /// RFC 3339) instead of now, replayed from the history table
+    #[arg(
+        long,
+        value_name = "TIMESTAMP",
+        value_parser = parse_instant,
+        conflicts_with_all = ["with_drift", "pending_only", "since", "limit", "offset"]
+    )]
+    at: Option<DateTime<Utc>>,
+
+    /// Only list migrations whose version or description matches this
+    /// case-insensitive regular expression
+    #[arg(long, value_name = "PATTERN", value_parser = parse_pattern)]
+    grep: Option<Regex>,
+
+    /// List at most this many migrations
+    #[arg(long)]
+    limit: Option<usize>,
+
+    /// Skip this many matching migrations (applied first, then pending)
+    #[arg(long, default_value = "0")]
+    offset: usize,
+
+    /// Also write every applied and pending migration to this file as a
+    /// canonical JSON snapshot, regardless of the listing options
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["at", "compare"])]
+    export: Option<PathBuf>,
+
+    /// Compare the applied and pending migrations with a snapshot written by
+    /// --export, listing added, removed and changed migrations
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = ["at", "with_drift", "cluster", "pending_only", "applied_only", "since", "grep", "limit", "offset"]
+    )]
+    compare: Option<PathBuf>,
+}
+
+pub(crate) fn parse_instant(value: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Ok(date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc());
+    }
+    DateTime::parse_from_rfc3339(value)
+        .map(|timestamp| timestamp.with_timezone(&Utc))
+        .map_err(|_| format!("'{}' is not a YYYY-MM-DD date or an RFC 3339 timestamp", value))
+}
+
+fn parse_pattern(value: &str) -> Result<Regex, String> {
+    RegexBuilder::new(value)
+        .case_insensitive(true)
+        .build()
+        .map_err(|e| e.to_string())
 }
 
 impl StatusCommand {
+    /// Whether any option narrows down the listed migrations
+    fn is_filtered(&self) -> bool {
+        self.pending_only
+            || self.applied_only
+            || self.since.is_some()
+            || self.grep.is_some()
+            || self.limit.is_some()
+            || self.offset > 0
+    }
+
+    fn matches(&self, version: &str, description: &str) -> bool {
+        self.grep
+            .as_ref()
+            .is_none_or(|grep| grep.is_match(version) || grep.is_match(description))
+    }
+
+    fn matches_applied(&self, migration: &MigrationRecord) -> bool {
+        !self.pending_only
+            && self.since.is_none_or(|since| migration.applied_at >= since)
+            && self.matches(&migration.version, &migration.description)
+    }
+
+    fn matches_file(&self, migration: &MigrationFile) -> bool {
+        let dated = |since: DateTime<Utc>| {
+            migration
+                .version
+                .parse::<MigrationVersion>()
+                .is_ok_and(|version| version.date() >= since.date_naive())
+        };
+        !self.applied_only
+            && self.since.is_none_or(dated)
+            && self.matches(&migration.version, &migration.description)
+    }
+
+    /// Migrations to list: filtered, then paged over applied followed by
+    /// pending, with the number that matched before paging
+    fn page<'a>(
+        &self,
+        applied: &'a [MigrationRecord],
+        pending: &'a [MigrationFile],
+    ) -> (usize, Vec<&'a MigrationRecord>, Vec<&'a MigrationFile>) {
+        let matching_applied: Vec<&MigrationRecord> = applied.iter().filter(|m| self.matches_applied(m)).collect();
+        let matching_pending: Vec<&MigrationFile> = pending.iter().filter(|m| self.matches_file(m)).collect();
+        let limit = self.limit.unwrap_or(usize::MAX);
+        let listed_applied: Vec<&MigrationRecord> =
+            matching_applied.iter().copied().skip(self.offset).take(limit).collect();
+        let listed_pending: Vec<&MigrationFile> = matching_pending
+            .iter()
+            .copied()
+            .skip(self.offset.saturating_sub(matching_applied.len()))
+            .take(limit - listed_applied.len())
+            .collect();
+        (matching_applied.len() + matching_pending.len(), listed_applied, listed_pending)
+    }
+
     pub async fn execute(&self, manager: &MigrationManager) -> Result<CommandOutput> {
+        if let Some(at) = self.at {
+            return self.execute_at(manager, at).await;
+        }
+        if let Some(path) = &self.compare {
+            return self.execute_compare(manager, path).await;
+        }
+
         let applied_migrations = manager.get_applied_migrations().await?;
         let all_files = manager.get_migration_files().await?;
         let pending_migrations = manager.get_pending_migrations().await?;
+        let ignored_migrations = manager.get_ignored_migration_files().await?;
+        let invalid_files = manager.get_invalid_migration_files().await?;
 
-        let applied_versions: HashSet<String> =
-            applied_migrations.iter().map(|m| m.version.clone()).collect();
+        let (matched_count, listed_applied, listed_pending) = self.page(&applied_migrations, &pending_migrations);
+        let listed: HashSet<&str> = listed_applied
+            .iter()
+            .map(|m| m.version.as_str())
+            .chain(listed_pending.iter().map(|m| m.version.as_str()))
+            .collect();
+        // Ignored files are neither applied nor pending, and not paged
+        let listed_ignored: Vec<&MigrationFile> = ignored_migrations
+            .iter()
+            .filter(|_| !self.pending_only && !self.applied_only && self.limit.is_none() && self.offset == 0)
+            .filter(|m| self.matches(&m.version, &m.description))
+            .collect();
+
+        // Group by target keyspace (see the `keyspace` directive)
+        let default_keyspace = manager.get_config().database.keyspace.as_str();
+        let mut keyspaces: BTreeMap<&str, (Vec<&MigrationRecord>, Vec<&MigrationFile>)> =
+            BTreeMap::from([(default_keyspace, (Vec::new(), Vec::new()))]);
+        for migration in &applied_migrations {
+            let keyspace = migration.target_keyspace.as_deref().unwrap_or(default_keyspace);
+            keyspaces.entry(keyspace).or_default().0.push(migration);
+        }
+        for migration in &pending_migrations {
+            keyspaces
+                .entry(manager.target_keyspace(migration))
+                .or_default()
+                .1
+                .push(migration);
+        }
+        let grouped = keyspaces.len() > 1;
 
         let mut output = Vec::new();
 
@@ -69,65 +245,184 @@ impl StatusCommand {
             all_files.len().to_string().bright_blue()
         ));
 
-        if self.verbose {
+        if !ignored_migrations.is_empty() {
+            output.push(format!(
+                "{}: {}",
+                "Ignored migration files".bold(),
+                ignored_migrations.len().to_string().dimmed()
+            ));
+        }
+
+        if !invalid_files.is_empty() {
+            output.push(format!(
+                "{}: {}",
+                "Invalid migration files".bold(),
+                format!("{} ❌", invalid_files.len()).bright_red()
+            ));
+        }
+
+        // Only shown while a run holds it
+        let lock = manager.lock_status().await?;
+        if let Some(lock) = &lock {
+            output.push(format!("{} {}", "🔒".yellow(), describe_lock(lock)));
+        }
+
+        if grouped {
             output.push(String::new());
-            output.push("Applied Migrations:".bold().to_string());
-            output.push("─".repeat(30));
+            output.push("Keyspaces:".bold().to_string());
+            for (keyspace, (applied, pending)) in &keyspaces {
+                output.push(format!(
+                    "  {}: {} applied, {} pending",
+                    keyspace.bright_cyan(),
+                    applied.len(),
+                    pending.len()
+                ));
+            }
+        }
 
-            if applied_migrations.is_empty() {
-                output.push("  No migrations applied yet".dimmed().to_string());
+        if self.is_filtered() {
+            output.push(String::new());
+            output.push(if listed.is_empty() {
+                format!("No migrations listed ({} matching)", matched_count)
             } else {
-                for migration in &applied_migrations {
-                    output.push(format!(
-                        "  {} {} - {} {}",
-                        "✅".green(),
-                        migration.version.bright_cyan(),
-                        migration.description,
-                        format!("({})", format_timestamp(migration.applied_at)).dimmed()
-                    ));
+                format!(
+                    "Listing {}–{} of {} matching migration(s)",
+                    self.offset + 1,
+                    self.offset + listed.len(),
+                    matched_count
+                )
+            });
+        }
+
+        if self.verbose || self.is_filtered() {
+            let filtered = self.is_filtered();
+            let empty = |message: &str| {
+                if filtered {
+                    "  No matching migrations".dimmed().to_string()
+                } else {
+                    message.dimmed().to_string()
+                }
+            };
+
+            for (keyspace, (applied, pending)) in &keyspaces {
+                let applied: Vec<_> = applied.iter().filter(|m| listed.contains(m.version.as_str())).collect();
+                let pending: Vec<_> = pending.iter().filter(|m| listed.contains(m.version.as_str())).collect();
+                if filtered && applied.is_empty() && pending.is_empty() {
+                    continue;
+                }
+                let heading = |title: &str| {
+                    if grouped {
+                        format!("{} ({}):", title, keyspace).bold().to_string()
+                    } else {
+                        format!("{}:", title).bold().to_string()
+                    }
+                };
+
+                if !self.pending_only {
+                    output.push(String::new());
+                    output.push(heading("Applied Migrations"));
+                    output.push("─".repeat(30));
+
+                    if applied.is_empty() {
+                        output.push(empty("  No migrations applied yet"));
+                    }
+                    for migration in applied {
+                        output.push(format!(
+                            "  {} {} - {} {}",
+                            "✅".green(),
+                            migration.version.bright_cyan(),
+                            migration.description,
+                            format!("({})", format_timestamp(migration.applied_at)).dimmed()
+                        ));
+                    }
+                }
+
+                if !self.applied_only {
+                    output.push(String::new());
+                    output.push(heading("Pending Migrations"));
+                    output.push("─".repeat(30));
+
+                    if pending.is_empty() {
+                        output.push(empty("  No pending migrations"));
+                    }
+                    for migration in pending {
+                        output.push(format!(
+                            "  {} {} - {}",
+                            "⏳".yellow(),
+                            migration.version.bright_cyan(),
+                            migration.description
+                        ));
+                    }
                 }
             }
 
+        }
+
+        // Misnamed files are never applied, so list them even without --verbose
+        if !invalid_files.is_empty() {
             output.push(String::new());
-            output.push("Pending Migrations:".bold().to_string());
+            output.push("Invalid Migration Files:".bold().to_string());
             output.push("─".repeat(30));
 
-            if pending_migrations.is_empty() {
-                output.push("  No pending migrations".dimmed().to_string());
+            for file in &invalid_files {
+                output.push(format!(
+                    "  {} {} - {}",
+                    "❌".red(),
+                    file.filename,
+                    file.reason.red()
+                ));
+            }
+        }
+
+        let (drift, unmanaged) = if self.with_drift {
+            let (unmanaged, drift): (Vec<_>, Vec<_>) = manager
+                .detect_schema_drift()
+                .await?
+                .into_iter()
+                .partition(|entry| entry.kind == "unmanaged");
+            (Some(drift), Some(unmanaged))
+        } else {
+            (None, None)
+        };
+
+        if let (Some(drift), Some(unmanaged)) = (&drift, &unmanaged) {
+            output.push(String::new());
+            output.push("Schema Drift:".bold().to_string());
+            output.push("─".repeat(30));
+
+            if drift.is_empty() {
+                output.push("  Live schema matches the applied migrations".dimmed().to_string());
             } else {
-                for migration in &pending_migrations {
-                    output.push(format!(
-                        "  {} {} - {}",
-                        "⏳".yellow(),
-                        migration.version.bright_cyan(),
-                        migration.description
-                    ));
-                }
+                output.extend(drift.iter().map(format_drift));
             }
 
-            // Show files without valid migration format
-            let invalid_files: Vec<_> = all_files
-                .iter()
-                .filter(|f| !applied_versions.contains(&f.version) &&
-                    !pending_migrations.iter().any(|p| p.version == f.version))
-                .collect();
-
-            if !invalid_files.is_empty() {
-                output.push(String::new());
-                output.push("Invalid Migration Files:".bold().to_string());
-                output.push("─".repeat(30));
-
-                for file in invalid_files {
-                    output.push(format!(
-                        "  {} {} - {}",
-                        "❌".red(),
-                        file.file_path.file_name().unwrap_or_default().to_string_lossy(),
-                        "Invalid format or duplicate version".red()
-                    ));
-                }
+            output.push(String::new());
+            output.push("Unmanaged Objects:".bold().to_string());
+            output.push("─".repeat(30));
+
+            if unmanaged.is_empty() {
+                output.push("  Every table and index was created by a migration".dimmed().to_string());
+            } else {
+                output.extend(unmanaged.iter().map(format_drift));
             }
         }
 
+        let cluster = if self.cluster {
+            let nodes = cluster::nodes(manager.session()).await?;
+            let agreement = SchemaAgreement::of(&nodes);
+            output.push(String::new());
+            output.push("Cluster:".bold().to_string());
+            output.push("─".repeat(30));
+            output.extend(format_nodes(&nodes, &agreement));
+            Some(serde_json::json!({
+                "nodes": nodes,
+                "schema_agreement": agreement.agreed(),
+                "schema_version": agreement.schema_version
+            }))
+        } else {
+            None
+        };
+
         // Status summary
         output.push(String::new());
         let status_message = if pending_migrations.is_empty() {
@@ -141,30 +436,411 @@ impl StatusCommand {
         };
         output.push(status_message);
 
+        if let Some(path) = &self.export {
+            live_snapshot(manager, &applied_migrations, &pending_migrations)
+                .save(path)
+                .await?;
+            output.push(format!("{} Snapshot written to {}", "📄".bright_blue(), path.display()));
+        }
+
+        // state, version, keyspace, applied_at, duration_ms, checksum
+        let mut porcelain: Vec<Vec<String>> = listed_applied
+            .iter()
+            .map(|m| {
+                vec![
+                    "applied".to_string(),
+                    m.version.clone(),
+                    m.target_keyspace.as_deref().unwrap_or(default_keyspace).to_string(),
+                    m.applied_at.to_rfc3339(),
+                    m.duration_ms.map(|ms| ms.to_string()).unwrap_or_default(),
+                    m.checksum.clone(),
+                ]
+            })
+            .collect();
+        for (state, migrations) in [("pending", &listed_pending), ("ignored", &listed_ignored)] {
+            porcelain.extend(migrations.iter().map(|m| {
+                vec![
+                    state.to_string(),
+                    m.version.clone(),
+                    manager.target_keyspace(m).to_string(),
+                    String::new(),
+                    String::new(),
+                    m.checksum.clone(),
+                ]
+            }));
+        }
+
+        // Listed migrations only, as filtered and paged above
+        let mut report = StatusReport::new(
+            default_keyspace,
+            &applied_migrations,
+            &pending_migrations,
+            all_files.len(),
+            &ignored_migrations,
+            invalid_files,
+        );
+        report.applied_migrations.retain(|m| listed.contains(m.version.as_str()));
+        report.pending_migrations.retain(|m| listed.contains(m.version.as_str()));
+
+        Ok(CommandOutput::success_with_data(
+            output.join("\n"),
+            serde_json::to_value(StatusData {
+                report,
+                drift,
+                unmanaged,
+                cluster,
+                lock,
+                snapshot: self.export.as_deref(),
+                matched_count,
+                offset: self.offset,
+                limit: self.limit,
+            })?,
+        )
+        .with_porcelain(porcelain))
+    }
+
+    /// Differences between the live status and a stored snapshot
+    async fn execute_compare(&self, manager: &MigrationManager, path: &Path) -> Result<CommandOutput> {
+        let stored = match StatusSnapshot::load(path).await {
+            Ok(stored) => stored,
+            Err(e) => return Ok(CommandOutput::error(format!("{} {}", "❌".red(), e)).with_error(&e)),
+        };
+        let applied = manager.get_applied_migrations().await?;
+        let pending = manager.get_pending_migrations().await?;
+        let live = live_snapshot(manager, &applied, &pending);
+        let diff = stored.diff(&live);
+
+        let mut output = vec![format!(
+            "{} Comparing with snapshot {}",
+            "📊".cyan(),
+            path.display().to_string().bright_cyan()
+        )];
+        if stored.keyspace != live.keyspace || stored.cluster_name != live.cluster_name {
+            output.push(format!(
+                "{} The snapshot is of keyspace {} on cluster {}",
+                "⚠️ ".yellow(),
+                stored.keyspace,
+                stored.cluster_name.as_deref().unwrap_or("(unnamed)")
+            ));
+        }
+
+        let describe = |migration: &SnapshotMigration| {
+            format!(
+                "{} ({}) - {}",
+                migration.version.bright_cyan(),
+                migration.state.as_str(),
+                migration.description
+            )
+        };
+        let sections = [
+            ("Added:", "+".green(), diff.added.iter().map(describe).collect::<Vec<_>>()),
+            ("Removed:", "-".red(), diff.removed.iter().map(describe).collect()),
+            (
+                "Changed:",
+                "~".yellow(),
+                diff.changed
+                    .iter()
+                    .map(|change| {
+                        let fields: Vec<String> = change
+                            .fields
+                            .iter()
+                            .map(|field| match *field {
+                                "state" => format!(
+                                    "state {} -> {}",
+                                    change.before.state.as_str(),
+                                    change.after.state.as_str()
+                                ),
+                                field => format!("{} changed", field),
+                            })
+                            .collect();
+                        format!("{}: {}", change.version.bright_cyan(), fields.join(", "))
+                    })
+                    .collect(),
+            ),
+        ];
+        for (title, marker, lines) in sections {
+            if lines.is_empty() {
+                continue;
+            }
+            output.push(String::new());
+            output.push(title.bold().to_string());
+            output.push("─".repeat(30));
+            output.extend(lines.into_iter().map(|line| format!("  {} {}", marker, line)));
+        }
+
+        // change, version, state, fields
+        let mut porcelain: Vec<Vec<String>> = Vec::new();
+        for (kind, migrations) in [("added", &diff.added), ("removed", &diff.removed)] {
+            porcelain.extend(migrations.iter().map(|m| {
+                vec![kind.to_string(), m.version.clone(), m.state.as_str().to_string(), String::new()]
+            }));
+        }
+        porcelain.extend(diff.changed.iter().map(|change| {
+            vec![
+                "changed".to_string(),
+                change.version.clone(),
+                change.after.state.as_str().to_string(),
+                change.fields.join(","),
+            ]
+        }));
+
+        let data = serde_json::json!({
+            "snapshot": path,
+            "matches": diff.is_empty(),
+            "added": diff.added,
+            "removed": diff.removed,
+            "changed": diff.changed
+        });
+        output.push(String::new());
+        if diff.is_empty() {
+            output.push(format!("{} Migrations match the snapshot", "✅".green()));
+            Ok(CommandOutput::success_with_data(output.join("\n"), data).with_porcelain(porcelain))
+        } else {
+            output.push(format!(
+                "{} {} difference(s) from the snapshot",
+                "⚠️ ".yellow(),
+                diff.added.len() + diff.removed.len() + diff.changed.len()
+            ));
+            Ok(CommandOutput::error_with_data(output.join("\n"), data).with_porcelain(porcelain))
+        }
+    }
+
+    /// Status as of a past instant
+    async fn execute_at(&self, manager: &MigrationManager, at: DateTime<Utc>) -> Result<CommandOutput> {
+        let records = manager.get_applied_migrations().await?;
+        let events = manager.history_events().await?;
+        let files = manager.get_migration_files().await?;
+        let default_keyspace = manager.get_config().database.keyspace.as_str();
+
+        let applied = applied_at_instant(&events, &records, at);
+        let changes_since: Vec<_> = events.iter().filter(|event| event.occurred_at > at).collect();
+        let current_version = applied.last().map(|m| m.version.as_str()).unwrap_or("None");
+
+        let records_by_version: HashMap<&str, &MigrationRecord> =
+            records.iter().map(|m| (m.version.as_str(), m)).collect();
+        let files_by_version: HashMap<&str, &MigrationFile> =
+            files.iter().map(|m| (m.version.as_str(), m)).collect();
+        let description = |version: &str| -> String {
+            records_by_version
+                .get(version)
+                .map(|m| m.description.clone())
+                .or_else(|| files_by_version.get(version).map(|m| m.description.clone()))
+                .unwrap_or_default()
+        };
+        let keyspace = |version: &str| -> String {
+            records_by_version
+                .get(version)
+                .and_then(|m| m.target_keyspace.clone())
+                .or_else(|| files_by_version.get(version).map(|m| manager.target_keyspace(m).to_string()))
+                .unwrap_or_else(|| default_keyspace.to_string())
+        };
+        let listed: Vec<_> = applied
+            .iter()
+            .filter(|m| self.matches(&m.version, &description(&m.version)))
+            .collect();
+
+        let mut output = vec![
+            format!("{} Migration Status at {}", "📊".cyan(), format_timestamp(at)),
+            "═".repeat(50),
+            String::new(),
+            format!(
+                "{}: {}",
+                "Schema version".bold(),
+                if current_version == "None" {
+                    "None (no migrations applied)".dimmed().to_string()
+                } else {
+                    current_version.bright_cyan().to_string()
+                }
+            ),
+            format!("{}: {}", "Applied migrations".bold(), applied.len().to_string().bright_green()),
+            format!(
+                "{}: {}",
+                "Changes since".bold(),
+                changes_since.len().to_string().bright_yellow()
+            ),
+        ];
+
+        output.push(String::new());
+        output.push("Applied Migrations:".bold().to_string());
+        output.push("─".repeat(30));
+        if listed.is_empty() {
+            output.push("  No migrations applied at that time".dimmed().to_string());
+        }
+        for migration in &listed {
+            output.push(format!(
+                "  {} {} - {} {}",
+                "✅".green(),
+                migration.version.bright_cyan(),
+                description(&migration.version),
+                format!("({})", format_timestamp(migration.applied_at)).dimmed()
+            ));
+        }
+
+        if !changes_since.is_empty() && !self.applied_only {
+            output.push(String::new());
+            output.push("Changes Since:".bold().to_string());
+            output.push("─".repeat(30));
+            for event in &changes_since {
+                output.push(format!(
+                    "  {} {} {}{}",
+                    format_timestamp(event.occurred_at).dimmed(),
+                    event.version.bright_cyan(),
+                    event.action.replace('_', " "),
+                    event.detail.as_deref().map(|detail| format!(" {}", detail)).unwrap_or_default()
+                ));
+            }
+        }
+
+        if events.is_empty() && !records.is_empty() {
+            output.push(String::new());
+            output.push(format!(
+                "{} No history has been recorded; this is based on when the currently applied migrations were applied, and migrations rolled back since are not shown",
+                "⚠️ ".yellow()
+            ));
+        }
+
+        // state, version, keyspace, applied_at, duration_ms, checksum
+        let porcelain: Vec<Vec<String>> = listed
+            .iter()
+            .map(|m| {
+                vec![
+                    "applied".to_string(),
+                    m.version.clone(),
+                    keyspace(&m.version),
+                    m.applied_at.to_rfc3339(),
+                    String::new(),
+                    m.checksum.clone().unwrap_or_default(),
+                ]
+            })
+            .collect();
+
         Ok(CommandOutput::success_with_data(
             output.join("\n"),
             serde_json::json!({
+                "at": at,
                 "current_version": current_version,
-                "applied_count": applied_migrations.len(),
-                "pending_count": pending_migrations.len(),
-                "total_files": all_files.len(),
-                "up_to_date": pending_migrations.is_empty(),
-                "applied_migrations": applied_migrations.iter().map(|m| {
+                "applied_count": applied.len(),
+                "history_recorded": !events.is_empty(),
+                "applied_migrations": listed.iter().map(|m| {
                     serde_json::json!({
                         "version": m.version,
-                        "description": m.description,
+                        "description": description(&m.version),
                         "applied_at": m.applied_at,
-                        "checksum": m.checksum
+                        "checksum": m.checksum,
+                        "keyspace": keyspace(&m.version),
+                        "from_history": m.from_history
                     })
                 }).collect::<Vec<_>>(),
-                "pending_migrations": pending_migrations.iter().map(|m| {
-                    serde_json::json!({
-                        "version": m.version,
-                        "description": m.description,
-                        "checksum": m.checksum
-                    })
-                }).collect::<Vec<_>>()
+                "changes_since": changes_since
             })
-        ))
+        )
+        .with_porcelain(porcelain))
+    }
+}
+
+/// JSON of a listing: the status report and what the options added to it
+#[derive(Serialize)]
+struct StatusData<'a> {
+    #[serde(flatten)]
+    report: StatusReport,
+    drift: Option<Vec<SchemaDrift>>,
+    unmanaged: Option<Vec<SchemaDrift>>,
+    cluster: Option<serde_json::Value>,
+    lock: Option<LockStatus>,
+    snapshot: Option<&'a Path>,
+    matched_count: usize,
+    offset: usize,
+    limit: Option<usize>,
+}
+
+/// Snapshot of the applied and pending migrations of the configured keyspace
+fn live_snapshot(manager: &MigrationManager, applied: &[MigrationRecord], pending: &[MigrationFile]) -> StatusSnapshot {
+    let config = manager.get_config();
+    let default_keyspace = config.database.keyspace.as_str();
+    let migrations = applied
+        .iter()
+        .map(|m| SnapshotMigration {
+            version: m.version.clone(),
+            state: SnapshotState::Applied,
+            description: m.description.clone(),
+            keyspace: m.target_keyspace.as_deref().unwrap_or(default_keyspace).to_string(),
+            checksum: m.checksum.clone(),
+            applied_at: Some(m.applied_at),
+        })
+        .chain(pending.iter().map(|m| SnapshotMigration {
+            version: m.version.clone(),
+            state: SnapshotState::Pending,
+            description: m.description.clone(),
+            keyspace: manager.target_keyspace(m).to_string(),
+            checksum: m.checksum.clone(),
+            applied_at: None,
+        }))
+        .collect();
+    StatusSnapshot::new(
+        manager.cluster_name().map(str::to_string),
+        default_keyspace,
+        config.behavior.environment.clone(),
+        migrations,
+        manager.version_scheme(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::parse;
+
+    fn versions(args: &[&str], applied: &[MigrationRecord], pending: &[MigrationFile]) -> (usize, Vec<String>) {
+        let command = parse::<StatusCommand>(args).unwrap();
+        let (matched, applied, pending) = command.page(applied, pending);
+        let listed = applied
+            .iter()
+            .map(|m| m.version.clone())
+            .chain(pending.iter().map(|m| m.version.clone()))
+            .collect();
+        (matched, listed)
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_filter_and_page() {
+        let applied_at = |day: u32| NaiveDate::from_ymd_opt(2025, 1, day).unwrap().and_hms_opt(12, 0, 0).unwrap().and_utc();
+        let applied = [
+            MigrationRecord::for_test("20250101_001_users", applied_at(1)),
+            MigrationRecord::for_test("20250102_001_orders", applied_at(2)),
+            MigrationRecord::for_test("20250103_001_users_email", applied_at(3)),
+        ];
+        let pending = [
+            MigrationFile::for_test("20250104_001_items", "-- +migrate Up\nSELECT 1;"),
+            MigrationFile::for_test("20250105_001_users_index", "-- +migrate Up\nSELECT 1;"),
+        ];
+
+        let all = versions(&[], &applied, &pending);
+        assert_eq!(all.0, 5);
+        assert_eq!(all.1.len(), 5);
+        assert!(!parse::<StatusCommand>(&[]).unwrap().is_filtered());
+
+        // Pages run over applied migrations, then pending ones
+        let (matched, listed) = versions(&["--offset", "2", "--limit", "2"], &applied, &pending);
+        assert_eq!(matched, 5);
+        assert_eq!(listed, vec!["20250103_001_users_email", "20250104_001_items"]);
+        let (_, listed) = versions(&["--offset", "4", "--limit", "2"], &applied, &pending);
+        assert_eq!(listed, vec!["20250105_001_users_index"]);
+
+        // --grep is case-insensitive and counts matches before paging
+        let (matched, listed) = versions(&["--grep", "USERS", "--limit", "1", "--offset", "1"], &applied, &pending);
+        assert_eq!(matched, 3);
+        assert_eq!(listed, vec!["20250103_001_users_email"]);
+
+        // --since compares applied migrations by when they were applied and
+        // pending ones by the date of their version
+        let (_, listed) = versions(&["--since", "2025-01-03"], &applied, &pending);
+        assert_eq!(listed, vec!["20250103_001_users_email", "20250104_001_items", "20250105_001_users_index"]);
+
+        let (_, listed) = versions(&["--pending-only"], &applied, &pending);
+        assert_eq!(listed, vec!["20250104_001_items", "20250105_001_users_index"]);
+        let (_, listed) = versions(&["--applied-only", "--grep", "orders"], &applied, &pending);
+        assert_eq!(listed, vec!["20250102_001_orders"]);
+
+        assert!(parse::<StatusCommand>(&["--since", "yesterday"]).is_err());
+    }
+}
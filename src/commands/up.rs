@@ -1,9 +1,35 @@
-use crate::{migration::MigrationManager, CommandOutput};
+use crate::{
+    approval::{approval_secret, post_plan, requires_approval, verify, wait_for_decision, Plan},
+    autodown::suggest_down,
+    commands::{lint::format_finding, status::parse_instant},
+    config::Config,
+    diagnostics::{cluster_info, write_bundle, FailureReport},
+    directives::{plan_parallel_waves, EnvSelector},
+    estimate::{format_duration_ms, load_history, EstimateBasis, Estimator},
+    highlight::highlight_cql,
+    history::{applied_at_instant, PastRecord},
+    lint::{check_limits, Linter, Severity},
+    placeholders::variable_names,
+    plugins::check_with_plugins,
+    migration::{MigrationManager, SharedSession},
+    utils::{format_timestamp, parse_migration_content, split_cql_statements},
+    progress,
+    queue::DEFAULT_QUEUE_TIMEOUT,
+    report::UpResult,
+    tenants, CommandOutput, MigrationError, MigrationFile,
+};
 use anyhow::Result;
+use chrono::{DateTime, Utc};
 use clap::Args;
 use colored::*;
+use futures::stream::{self, StreamExt};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
-#[derive(Args)]
+#[derive(Args, Default)]
 pub struct UpCommand {
     /// Number of migrations to apply (default: all)
     #[arg(short, long)]
@@ -12,17 +38,288 @@ pub struct UpCommand {
     /// Dry run mode - show what would be applied without executing
     #[arg(long)]
     dry_run: bool,
+
+    /// With --dry-run, also print the statements each migration would run
+    #[arg(long, requires = "dry_run")]
+    statements: bool,
+
+    /// With --dry-run, plan against the tracking table as it was at this instant
+    /// (`YYYY-MM-DD` or RFC 3339), replayed from the history table
+    #[arg(
+        long,
+        value_name = "TIMESTAMP",
+        value_parser = parse_instant,
+        requires = "dry_run",
+        conflicts_with_all = ["all_clusters", "all_tenants"]
+    )]
+    as_of: Option<DateTime<Utc>>,
+
+    /// Apply pending migrations to every cluster listed under [[clusters]]
+    #[arg(long)]
+    all_clusters: bool,
+
+    /// Apply pending migrations to every tenant keyspace (see [tenants])
+    #[arg(long, conflicts_with = "all_clusters")]
+    all_tenants: bool,
+
+    /// Number of tenants to migrate concurrently with --all-tenants
+    #[arg(long, default_value = "1", requires = "all_tenants")]
+    parallel_tenants: usize,
+
+    /// With --all-tenants, start no further tenants once one fails
+    #[arg(long, requires = "all_tenants")]
+    fail_fast: bool,
+
+    /// Apply even if applied migrations were modified since they ran (dangerous)
+    #[arg(long)]
+    skip_verification: bool,
+
+    /// Run offline lint checks on the migrations first and abort on errors
+    #[arg(long)]
+    validate: bool,
+
+    /// Apply up to N independent migrations concurrently (see `db-migrate:tags`)
+    #[arg(long, default_value = "1")]
+    parallel: usize,
+
+    /// Apply even if migrations exceed the limits in [limits] with `enforce = true`
+    #[arg(long)]
+    force: bool,
+
+    /// Apply even if the tracking table is empty while the keyspace already has
+    /// tables (see `behavior.empty_tracking_guard`)
+    #[arg(long)]
+    allow_empty_tracking: bool,
+
+    /// Print generated DOWN statements for migrations that have no DOWN section
+    #[arg(long)]
+    suggest_down: bool,
+
+    /// On failure, write a diagnostics bundle (redacted config, cluster info,
+    /// failing statement, driver error, recent log) under this directory
+    #[arg(long, value_name = "DIR")]
+    diagnostics_dir: Option<PathBuf>,
+
+    /// Durations exported from another environment (`status --output json`),
+    /// used to estimate run time in dry-run mode
+    #[arg(long, value_name = "FILE")]
+    history: Option<PathBuf>,
+
+    /// Number of clusters to migrate concurrently with --all-clusters
+    #[arg(long, default_value = "1", requires = "all_clusters")]
+    cluster_parallelism: usize,
+
+    /// Token from `db-migrate approve <plan-id>`, in environments where `up`
+    /// needs approval (see `approval.environments`)
+    #[arg(long, value_name = "TOKEN")]
+    approval_token: Option<String>,
+
+    /// Seconds to wait for the migration lock held by another run
+    #[arg(long, value_name = "SECONDS", default_value = "600")]
+    lock_timeout: u64,
+}
+
+/// Suggested DOWN sections for migrations that lack one, formatted for display
+fn suggest_missing_down(migrations: &[MigrationFile]) -> Vec<String> {
+    migrations
+        .iter()
+        .filter(|migration| !migration.directives.no_down && !migration.directives.auto_down)
+        .filter_map(|migration| {
+            let (up, down) = parse_migration_content(&migration.content).ok()?;
+            if !split_cql_statements(down.as_deref().unwrap_or("")).is_empty() {
+                return None;
+            }
+
+            let suggestion = suggest_down(&up);
+            let mut lines = vec![format!(
+                "{} {} has no DOWN section. Suggested:",
+                "💡".bright_blue(),
+                migration.version.bright_cyan()
+            )];
+            lines.push("-- +migrate Down".dimmed().to_string());
+            lines.extend(
+                suggestion
+                    .statements
+                    .iter()
+                    .map(|statement| highlight_cql(&format!("{};", statement))),
+            );
+            lines.extend(suggestion.unsupported.iter().map(|statement| {
+                format!("-- TODO: no inverse for: {}", statement.replace('\n', " "))
+                    .yellow()
+                    .to_string()
+            }));
+            Some(lines.join("\n"))
+        })
+        .collect()
+}
+
+/// UP statements of a migration that would run in the environment, and those
+/// `only-env` excludes from it
+///
+/// `${secret:...}` placeholders are left unresolved. Statements of streamed files
+/// are not read ahead of execution.
+fn statements_for_environment(
+    migration: &MigrationFile,
+    environment: Option<&str>,
+) -> (Option<Vec<String>>, Vec<String>) {
+    if migration.streamed {
+        return (None, Vec::new());
+    }
+    let Ok((up, _)) = parse_migration_content(&migration.content) else {
+        return (None, Vec::new());
+    };
+    let (included, skipped) = EnvSelector::new(environment, &migration.directives.only_env)
+        .partition(split_cql_statements(&up));
+    (Some(included), skipped)
+}
+
+/// Outcome of applying migrations to a single cluster
+struct ClusterRun {
+    name: String,
+    applied: Vec<String>,
+    failed_migration: Option<String>,
+    error: Option<String>,
+    /// Applied (version, checksum) pairs after the run, used for convergence checks
+    state: Option<Vec<(String, String)>>,
+}
+
+/// A check that stopped a run before anything was applied
+struct Refusal {
+    /// What the command reports
+    output: CommandOutput,
+    /// What library callers get (see [`MigrationManager::up`])
+    error: MigrationError,
+}
+
+impl Refusal {
+    fn new(output: CommandOutput, error: MigrationError) -> Self {
+        Self {
+            output: output.with_error(&error),
+            error,
+        }
+    }
+}
+
+fn refused(check: &str, reasons: Vec<String>) -> MigrationError {
+    MigrationError::Refused {
+        check: check.to_string(),
+        reasons,
+    }
+}
+
+impl ClusterRun {
+    fn new(name: String) -> Self {
+        Self {
+            name,
+            applied: Vec::new(),
+            failed_migration: None,
+            error: None,
+            state: None,
+        }
+    }
+}
+
+/// Outcome of applying migrations to one tenant keyspace
+struct TenantRun {
+    run: ClusterRun,
+    duration_ms: f64,
+    /// Not started because another tenant failed first (`--fail-fast`)
+    skipped: bool,
+}
+
+impl TenantRun {
+    fn status(&self) -> &'static str {
+        match (&self.run.error, self.skipped) {
+            (_, true) => "skipped",
+            (None, false) => "ok",
+            (Some(_), false) => "failed",
+        }
+    }
 }
 
 impl UpCommand {
+    /// Whether this invocation fans out over all configured clusters
+    pub fn targets_all_clusters(&self) -> bool {
+        self.all_clusters
+    }
+
+    /// Whether this invocation fans out over all tenant keyspaces
+    pub fn targets_all_tenants(&self) -> bool {
+        self.all_tenants
+    }
+
+    /// Apply at most `count` pending migrations
+    pub fn with_count(mut self, count: Option<usize>) -> Self {
+        self.count = count;
+        self
+    }
+
+    /// Wait this long for the migration lock instead of `--lock-timeout`
+    pub fn with_lock_timeout(mut self, timeout: Duration) -> Self {
+        self.lock_timeout = timeout.as_secs();
+        self
+    }
+
     pub async fn execute(&self, manager: &mut MigrationManager) -> Result<CommandOutput> {
-        let pending_migrations = manager.get_pending_migrations().await?;
+        let _run = manager.exclusive_run("up", DEFAULT_QUEUE_TIMEOUT).await?;
+        if self.dry_run {
+            return self.run(manager).await;
+        }
+
+        // Runs from other processes, such as Jobs of two rollouts, wait for each
+        // other; the lock is renewed while migrations run
+        let lock = manager.hold_lock(Duration::from_secs(self.lock_timeout)).await?;
+        let output = self.run(manager).await;
+        lock.release(manager).await;
+        output
+    }
+
+    /// [`MigrationManager::up`]: the run `execute` makes, with refusals returned
+    /// as errors
+    pub(crate) async fn apply(&self, manager: &mut MigrationManager) -> Result<UpResult, MigrationError> {
+        let _run = manager.exclusive_run("up", DEFAULT_QUEUE_TIMEOUT).await?;
+        let lock = manager.hold_lock(Duration::from_secs(self.lock_timeout)).await?;
+        let result = self.apply_guarded(manager).await;
+        lock.release(manager).await;
+        result
+    }
+
+    async fn apply_guarded(&self, manager: &mut MigrationManager) -> Result<UpResult, MigrationError> {
+        if let Some(refusal) = self.check_history(manager, "").await? {
+            return Err(refusal.error);
+        }
+
+        let pending = manager.get_pending_migrations().await?;
+        let migrations: Vec<_> = pending.into_iter().take(self.count.unwrap_or(usize::MAX)).collect();
+        if let Some(refusal) = self.pre_apply_checks(manager, &migrations).await? {
+            return Err(refusal.error);
+        }
+        if let Some(refusal) = self.check_approval(manager, &migrations).await {
+            return Err(refusal.error);
+        }
+
+        Ok(manager.apply_migrations(&migrations, |_| {}).await)
+    }
+
+    async fn run(&self, manager: &mut MigrationManager) -> Result<CommandOutput> {
+        if let Some(refusal) = self.check_history(manager, "").await? {
+            return Ok(refusal.output);
+        }
+
+        let pending_migrations = match self.as_of {
+            Some(at) => pending_as_of(manager, at).await?,
+            None => manager.get_pending_migrations().await?,
+        };
 
         if pending_migrations.is_empty() {
-            return Ok(CommandOutput::success(format!(
-                "{} No pending migrations found",
-                "✅".green()
-            )));
+            return Ok(CommandOutput::success(match self.as_of {
+                Some(at) => format!(
+                    "{} No migrations were pending at {}",
+                    "✅".green(),
+                    format_timestamp(at)
+                ),
+                None => format!("{} No pending migrations found", "✅".green()),
+            }));
         }
 
         // Determine how many migrations to apply
@@ -32,73 +329,1065 @@ impl UpCommand {
             pending_migrations
         };
 
+        if let Some(refusal) = self.pre_apply_checks(manager, &migrations_to_apply).await? {
+            return Ok(refusal.output);
+        }
+
+        if self.suggest_down {
+            for suggestion in suggest_missing_down(&migrations_to_apply) {
+                progress!("{}\n", suggestion);
+            }
+        }
+
         if self.dry_run {
-            return self.show_dry_run(&migrations_to_apply);
+            return self.show_dry_run(manager, &migrations_to_apply).await;
         }
 
-        let mut applied_count = 0;
-        let mut applied_migrations = Vec::new();
+        if let Some(refusal) = self.check_approval(manager, &migrations_to_apply).await {
+            return Ok(refusal.output);
+        }
 
-        for migration in &migrations_to_apply {
-            match manager.apply_migration(migration).await {
-                Ok(_) => {
-                    applied_count += 1;
-                    applied_migrations.push(&migration.version);
-                    println!(
-                        "{} Applied migration: {}",
-                        "✅".green(),
-                        migration.version.bright_cyan()
-                    );
-                }
-                Err(e) => {
-                    return Ok(CommandOutput::success_with_data(
-                        format!(
-                            "{} Applied {} migration(s), failed on: {}",
-                            if applied_count > 0 { "⚠️ " } else { "❌" },
-                            applied_count,
-                            migration.version
-                        ),
-                        serde_json::json!({
-                            "applied_count": applied_count,
-                            "applied_migrations": applied_migrations,
-                            "failed_migration": migration.version,
-                            "error": e.to_string()
-                        })
-                    ));
-                }
-            }
+        if self.parallel > 1 {
+            return self.apply_parallel(manager, &migrations_to_apply).await;
+        }
+
+        let mut result = manager
+            .apply_migrations(&migrations_to_apply, |migration| {
+                progress!(
+                    "{} Applied migration: {}",
+                    "✅".green(),
+                    migration.version.bright_cyan()
+                );
+            })
+            .await;
+
+        if let (Some(e), Some(report)) = (result.cause.take(), result.failure.take()) {
+            result.diagnostics_bundle = self.write_diagnostics(manager, std::slice::from_ref(&report)).await;
+            let message = format!(
+                "{} Applied {} migration(s), failed on: {}\n  {}",
+                if result.applied_count > 0 { "⚠️ " } else { "❌" },
+                result.applied_count,
+                report.version,
+                report.describe()
+            );
+            result.failure = Some(report);
+            return Ok(CommandOutput::success_with_data(message, serde_json::to_value(&result)?).with_error(&e));
         }
 
-        let message = if applied_count == 1 {
+        let message = if result.applied_count == 1 {
             format!("{} Applied 1 migration successfully", "🎉".green())
         } else {
-            format!("{} Applied {} migrations successfully", "🎉".green(), applied_count)
+            format!("{} Applied {} migrations successfully", "🎉".green(), result.applied_count)
+        };
+
+        Ok(CommandOutput::success_with_data(message, serde_json::to_value(&result)?))
+    }
+
+    /// Write a diagnostics bundle if `--diagnostics-dir` was given, returning its path
+    async fn write_diagnostics(
+        &self,
+        manager: &MigrationManager,
+        failures: &[FailureReport],
+    ) -> Option<PathBuf> {
+        let dir = self.diagnostics_dir.as_ref()?;
+        let cluster = cluster_info(manager).await;
+
+        match write_bundle(dir, manager.get_config(), &cluster, failures) {
+            Ok(bundle) => {
+                progress!(
+                    "{} Diagnostics bundle written to {}",
+                    "🩺".bright_blue(),
+                    bundle.display().to_string().bright_cyan()
+                );
+                Some(bundle)
+            }
+            Err(e) => {
+                progress!("{} Could not write diagnostics bundle: {}", "⚠️ ".yellow(), e);
+                None
+            }
+        }
+    }
+
+    fn should_verify(&self, config: &Config) -> bool {
+        config.behavior.verify_checksums && !self.skip_verification
+    }
+
+    /// Checks of the tracking table before planning a run: modified applied
+    /// migrations, records from another cluster and an emptied table
+    ///
+    /// Warnings are printed after `prefix`, which names the target of fan-out runs.
+    async fn check_history(
+        &self,
+        manager: &MigrationManager,
+        prefix: &str,
+    ) -> Result<Option<Refusal>, MigrationError> {
+        // Files changed since then say nothing about a past deploy
+        if self.as_of.is_none() && self.should_verify(manager.get_config()) {
+            let (mut mismatches, down_changes) = checksum_mismatches(manager).await?;
+            if !mismatches.is_empty() {
+                let output = self.tampered_history_output(&mismatches);
+                return Ok(Some(Refusal::new(output, mismatches.swap_remove(0))));
+            }
+            for change in &down_changes {
+                progress!(
+                    "{}{} {} (accept it with 'db-migrate repair --accept-down')",
+                    prefix,
+                    "⚠️ ".yellow(),
+                    change
+                );
+            }
+        }
+
+        if let Some(warning) = foreign_cluster_warning(manager).await? {
+            progress!("{}{}", prefix, warning);
+        }
+
+        if self.allow_empty_tracking {
+            return Ok(None);
+        }
+        match empty_tracking_error(manager).await? {
+            Some(e) if !self.dry_run => {
+                let output = CommandOutput::error_with_data(
+                    format!(
+                        "{} {}\n\n{} If the tracking table was reset or lost, reload a backup with 'db-migrate restore-history <file>'.\n   If the schema was created outside db-migrate, record the migrations it already contains first.\n   Pass --allow-empty-tracking to apply every migration anyway.",
+                        "❌".red(),
+                        e,
+                        "💡".bright_blue()
+                    ),
+                    serde_json::json!({
+                        "applied_count": 0,
+                        "error": e.to_string(),
+                        "error_code": e.code()
+                    }),
+                );
+                Ok(Some(Refusal::new(output, e)))
+            }
+            Some(e) => {
+                progress!("{}{} {}", prefix, "⚠️ ".yellow(), e);
+                Ok(None)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn tampered_history_output(&self, mismatches: &[MigrationError]) -> CommandOutput {
+        let mut output = vec![
+            format!(
+                "{} Refusing to apply migrations: {} applied migration(s) were modified after being applied",
+                "❌".red(),
+                mismatches.len()
+            ),
+            String::new(),
+        ];
+
+        for error in mismatches {
+            output.push(format!("  {} {}", "❌".red(), error));
+        }
+
+        output.push(String::new());
+        output.push(format!(
+            "{} Run 'db-migrate verify' for details, or pass --skip-verification to apply anyway",
+            "💡".bright_blue()
+        ));
+
+        let output = CommandOutput::error_with_data(
+            output.join("\n"),
+            serde_json::json!({
+                "applied_count": 0,
+                "checksum_mismatches": mismatches.iter().map(|e| e.to_string()).collect::<Vec<_>>()
+            })
+        );
+        match mismatches.first() {
+            Some(error) => output.with_error(error),
+            None => output,
+        }
+    }
+
+    /// Checks of the migrations about to be applied that every run makes:
+    /// `[limits]`, `--validate`, the policy file and plugins
+    async fn pre_apply_checks(
+        &self,
+        manager: &MigrationManager,
+        migrations: &[MigrationFile],
+    ) -> Result<Option<Refusal>, MigrationError> {
+        let config = manager.get_config();
+        if let Some(refusal) = self.check_limits(config, migrations) {
+            return Ok(Some(refusal));
+        }
+
+        if self.validate {
+            if let Some(refusal) = self.validate_migrations(manager, migrations).await? {
+                return Ok(Some(refusal));
+            }
+        }
+
+        if let Some(refusal) = self.check_policy(config, migrations) {
+            return Ok(Some(refusal));
+        }
+
+        Ok(self.check_plugins(config, migrations).await)
+    }
+
+    /// Check the run against `[limits]`, returning an error output if an enforced
+    /// limit is exceeded without `--force`
+    fn check_limits(&self, config: &Config, migrations: &[MigrationFile]) -> Option<Refusal> {
+        let limits = &config.limits;
+        let findings: Vec<_> = migrations
+            .iter()
+            .flat_map(|migration| check_limits(migration, limits))
+            .collect();
+        let too_many = limits.max_migrations_per_run > 0
+            && migrations.len() > limits.max_migrations_per_run;
+
+        let mut violations: Vec<String> = findings.iter().map(format_finding).collect();
+        let mut reasons: Vec<String> = findings.iter().map(ToString::to_string).collect();
+        if too_many {
+            let reason = format!(
+                "{} migrations would be applied, over the limit of {} per run",
+                migrations.len(),
+                limits.max_migrations_per_run
+            );
+            violations.push(format!(
+                "  {} {} (use --count)",
+                if limits.enforce { "❌".red() } else { "⚠️ ".yellow() },
+                reason
+            ));
+            reasons.push(reason);
+        }
+
+        if violations.is_empty() {
+            return None;
+        }
+
+        if limits.enforce && !self.force {
+            let mut output = vec![
+                format!("{} Limits exceeded - no migrations were applied:", "❌".red()),
+                String::new(),
+            ];
+            output.extend(violations);
+            output.push(String::new());
+            output.push(format!(
+                "{} Re-run with --force if this is intended",
+                "💡".bright_blue()
+            ));
+
+            let output = CommandOutput::error_with_data(
+                output.join("\n"),
+                serde_json::json!({
+                    "applied_count": 0,
+                    "limit_violations": findings,
+                    "max_migrations_per_run_exceeded": too_many
+                }),
+            );
+            return Some(Refusal::new(output, refused("limits", reasons)));
+        }
+
+        for violation in &violations {
+            progress!("{}", violation);
+        }
+        None
+    }
+
+    /// Lint the migrations about to be applied, returning an error output if any fail
+    async fn validate_migrations(
+        &self,
+        manager: &MigrationManager,
+        migrations: &[MigrationFile],
+    ) -> Result<Option<Refusal>, MigrationError> {
+        let all_files = manager.get_migration_files().await?;
+        let linter = Linter::new(manager.get_config(), &all_files);
+
+        let errors: Vec<_> = migrations
+            .iter()
+            .flat_map(|migration| linter.lint(migration))
+            .filter(|finding| finding.severity == Severity::Error)
+            .collect();
+
+        if errors.is_empty() {
+            return Ok(None);
+        }
+
+        let mut output = vec![
+            format!("{} Validation failed - no migrations were applied:", "❌".red()),
+            String::new(),
+        ];
+        output.extend(errors.iter().map(format_finding));
+
+        let output = CommandOutput::error_with_data(
+            output.join("\n"),
+            serde_json::json!({
+                "applied_count": 0,
+                "validation_errors": errors
+            })
+        );
+        Ok(Some(Refusal::new(output, refused("validation", errors.iter().map(ToString::to_string).collect()))))
+    }
+
+    /// Check the migrations against the policy file, returning an error output
+    /// if any violates an error-level rule
+    fn check_policy(&self, config: &Config, migrations: &[MigrationFile]) -> Option<Refusal> {
+        let (violations, warnings): (Vec<_>, Vec<_>) = migrations
+            .iter()
+            .flat_map(|migration| config.policy.check(migration))
+            .partition(|finding| finding.severity == Severity::Error);
+        for warning in &warnings {
+            progress!("{}", format_finding(warning));
+        }
+        if violations.is_empty() {
+            return None;
+        }
+
+        let mut output = vec![
+            format!("{} Forbidden by policy - no migrations were applied:", "❌".red()),
+            String::new(),
+        ];
+        output.extend(violations.iter().map(format_finding));
+
+        let output = CommandOutput::error_with_data(
+            output.join("\n"),
+            serde_json::json!({
+                "applied_count": 0,
+                "policy_violations": violations
+            }),
+        );
+        Some(Refusal::new(output, refused("policy", violations.iter().map(ToString::to_string).collect())))
+    }
+
+    /// Ask the configured plugins about the migrations, returning an error output
+    /// if any denies one
+    async fn check_plugins(
+        &self,
+        config: &Config,
+        migrations: &[MigrationFile],
+    ) -> Option<Refusal> {
+        if config.plugins.is_empty() && config.wasm_plugins.is_empty() {
+            return None;
+        }
+
+        let findings = check_with_plugins(config, migrations).await;
+        let (denials, warnings): (Vec<_>, Vec<_>) = findings
+            .into_iter()
+            .partition(|finding| finding.severity == Severity::Error);
+        for warning in &warnings {
+            progress!("{}", format_finding(warning));
+        }
+        if denials.is_empty() {
+            return None;
+        }
+
+        let mut output = vec![
+            format!("{} Denied by plugins - no migrations were applied:", "❌".red()),
+            String::new(),
+        ];
+        output.extend(denials.iter().map(format_finding));
+
+        let output = CommandOutput::error_with_data(
+            output.join("\n"),
+            serde_json::json!({
+                "applied_count": 0,
+                "plugin_denials": denials
+            }),
+        );
+        Some(Refusal::new(output, refused("plugins", denials.iter().map(ToString::to_string).collect())))
+    }
+
+    /// In approval-gated environments, get the plan approved, returning an error
+    /// output if it is not
+    async fn check_approval(
+        &self,
+        manager: &MigrationManager,
+        migrations: &[MigrationFile],
+    ) -> Option<Refusal> {
+        let config = manager.get_config();
+        if !requires_approval(config) {
+            return None;
+        }
+
+        let plan = Plan::new(config, manager.cluster_name(), migrations);
+        match self.await_approval(config, &plan).await {
+            Ok(how) => {
+                progress!("{} Plan {} {}", "✅".green(), plan.plan_id.bright_cyan(), how);
+                None
+            }
+            Err(e) => {
+                let output = CommandOutput::error_with_data(
+                    format!("{} {}\n\n{}", "❌".red(), e, plan.text),
+                    serde_json::json!({
+                        "applied_count": 0,
+                        "plan": plan,
+                        "error": e.to_string(),
+                        "error_code": e.code()
+                    }),
+                );
+                Some(Refusal::new(output, e))
+            }
+        }
+    }
+
+    /// Wait for the plan to be approved, returning how it was
+    async fn await_approval(&self, config: &Config, plan: &Plan) -> Result<String, MigrationError> {
+        let secret = approval_secret(config);
+        let not_approved = |reason: String| MigrationError::NotApproved {
+            plan_id: plan.plan_id.clone(),
+            reason,
+        };
+
+        if let Some(token) = &self.approval_token {
+            let Some(secret) = &secret else {
+                return Err(MigrationError::ConfigError(format!(
+                    "{} is not set, so --approval-token cannot be checked",
+                    config.approval.secret_env
+                )));
+            };
+            if !verify(secret, &plan.plan_id, token) {
+                return Err(not_approved(
+                    "--approval-token does not match this plan; the migrations to apply may have changed since it was approved".to_string(),
+                ));
+            }
+            return Ok("approved with --approval-token".to_string());
+        }
+
+        let sign_hint = format!(
+            "once approved, re-run with --approval-token <token printed by 'db-migrate approve {}'>",
+            plan.plan_id
+        );
+        let Some(url) = &config.approval.webhook_url else {
+            return Err(not_approved(format!("approval required; {}", sign_hint)));
+        };
+
+        let Some(poll_url) = post_plan(url, plan).await? else {
+            return Err(not_approved(format!("posted for approval; {}", sign_hint)));
         };
+        progress!(
+            "{} Waiting up to {}s for plan {} to be approved...",
+            "⏳".yellow(),
+            config.approval.timeout_seconds,
+            plan.plan_id.bright_cyan()
+        );
+        let approver = wait_for_decision(config, &poll_url, plan, secret.as_deref()).await?;
+        Ok(approver.map_or("approved".to_string(), |approver| format!("approved by {}", approver)))
+    }
+
+    /// Apply migrations in waves of independent migrations with bounded concurrency
+    async fn apply_parallel(
+        &self,
+        manager: &MigrationManager,
+        migrations: &[MigrationFile],
+    ) -> Result<CommandOutput> {
+        let (applied, failures) = self
+            .apply_waves(manager, migrations, |migration| {
+                progress!(
+                    "{} Applied migration: {}",
+                    "✅".green(),
+                    migration.version.bright_cyan()
+                );
+            })
+            .await;
+        let applied_migrations: Vec<&String> = applied.iter().map(|migration| &migration.version).collect();
+
+        if let Some((_, first_error)) = failures.first() {
+            let reports: Vec<_> = failures
+                .iter()
+                .map(|(migration, error)| FailureReport::new(manager, &migration.version, error))
+                .collect();
+            let bundle = self.write_diagnostics(manager, &reports).await;
+            return Ok(CommandOutput::success_with_data(
+                format!(
+                    "{} Applied {} migration(s), failed on: {}\n  {}",
+                    if applied_migrations.is_empty() { "❌" } else { "⚠️ " },
+                    applied_migrations.len(),
+                    failures
+                        .iter()
+                        .map(|(migration, _)| migration.version.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                    reports.iter().map(FailureReport::describe).collect::<Vec<_>>().join("\n  ")
+                ),
+                serde_json::json!({
+                    "applied_count": applied_migrations.len(),
+                    "applied_migrations": applied_migrations,
+                    "failed_migrations": reports,
+                    "diagnostics_bundle": bundle
+                })
+            )
+            .with_error(first_error));
+        }
 
         Ok(CommandOutput::success_with_data(
-            message,
+            format!(
+                "{} Applied {} migration(s) successfully",
+                "🎉".green(),
+                applied_migrations.len()
+            ),
             serde_json::json!({
-                "applied_count": applied_count,
+                "applied_count": applied_migrations.len(),
                 "applied_migrations": applied_migrations
             })
         ))
     }
 
-    fn show_dry_run(&self, migrations: &[crate::MigrationFile]) -> Result<CommandOutput> {
+    /// Apply waves of independent migrations, up to `--parallel` at a time, and
+    /// stop after the first wave in which any failed
+    async fn apply_waves<'a>(
+        &self,
+        manager: &MigrationManager,
+        migrations: &'a [MigrationFile],
+        on_applied: impl Fn(&MigrationFile),
+    ) -> (Vec<&'a MigrationFile>, Vec<(&'a MigrationFile, MigrationError)>) {
+        let mut applied = Vec::new();
+
+        for wave in plan_parallel_waves(migrations) {
+            let results: Vec<_> = stream::iter(wave)
+                .map(|migration| async move {
+                    (migration, manager.apply_migration_concurrently(migration).await)
+                })
+                .buffer_unordered(self.parallel)
+                .collect()
+                .await;
+
+            let mut failures = Vec::new();
+            for (migration, result) in results {
+                match result {
+                    Ok(_) => {
+                        applied.push(migration);
+                        on_applied(migration);
+                    }
+                    Err(e) => failures.push((migration, e)),
+                }
+            }
+            if !failures.is_empty() {
+                return (applied, failures);
+            }
+        }
+
+        (applied, Vec::new())
+    }
+
+    /// Apply pending migrations to each configured cluster and compare the resulting state
+    pub async fn execute_all_clusters(&self, config: &Config) -> Result<CommandOutput> {
+        if config.clusters.is_empty() {
+            return Ok(CommandOutput::error(format!(
+                "{} No clusters configured. Add [[clusters]] entries to your config file.",
+                "❌".red()
+            )));
+        }
+
+        if !self.dry_run && requires_approval(config) {
+            return Ok(CommandOutput::error(format!(
+                "{} --all-clusters cannot apply migrations where they need approval (approval.environments); approve and apply each cluster's plan separately",
+                "❌".red()
+            )));
+        }
+
+        let parallelism = self.cluster_parallelism.max(1);
+        let managers: Vec<_> = stream::iter(&config.clusters)
+            .map(|cluster| MigrationManager::new(config.for_cluster(cluster)))
+            .buffered(parallelism)
+            .collect()
+            .await;
+
+        // A forbidden migration must not reach some clusters before another
+        // cluster refuses it, so the policy is checked for all of them first
+        let mut pending: Vec<MigrationFile> = Vec::new();
+        for manager in managers.iter().flatten() {
+            let Ok(migrations) = manager.get_pending_migrations().await else {
+                continue;
+            };
+            for migration in migrations.into_iter().take(self.count.unwrap_or(usize::MAX)) {
+                if !pending.iter().any(|m| m.version == migration.version) {
+                    pending.push(migration);
+                }
+            }
+        }
+        if let Some(refusal) = self.check_policy(config, &pending) {
+            return Ok(refusal.output);
+        }
+
+        let runs: Vec<ClusterRun> = stream::iter(config.clusters.iter().zip(managers))
+            .map(|(cluster, manager)| self.apply_to_cluster(cluster.name.clone(), manager))
+            .buffered(parallelism)
+            .collect()
+            .await;
+
+        let states: Vec<&Vec<(String, String)>> =
+            runs.iter().filter_map(|r| r.state.as_ref()).collect();
+        let converged = states.len() == runs.len() && states.windows(2).all(|w| w[0] == w[1]);
+        let all_succeeded = runs.iter().all(|r| r.error.is_none());
+
+        let mut output = vec![
+            format!(
+                "{} Multi-cluster {}:",
+                "🌐".cyan(),
+                if self.dry_run { "dry run" } else { "apply" }
+            ),
+            String::new(),
+        ];
+
+        for run in &runs {
+            let line = match (&run.error, &run.failed_migration) {
+                (None, _) => format!(
+                    "  {} {} - {} migration(s) {}",
+                    "✅".green(),
+                    run.name.bright_cyan(),
+                    run.applied.len(),
+                    if self.dry_run { "pending" } else { "applied" }
+                ),
+                (Some(error), Some(version)) => format!(
+                    "  {} {} - applied {}, failed on {}: {}",
+                    "❌".red(),
+                    run.name.bright_cyan(),
+                    run.applied.len(),
+                    version,
+                    error.dimmed()
+                ),
+                (Some(error), None) => format!(
+                    "  {} {} - {}",
+                    "❌".red(),
+                    run.name.bright_cyan(),
+                    error.dimmed()
+                ),
+            };
+            output.push(line);
+        }
+
+        output.push(String::new());
+        if self.dry_run {
+            output.push(format!(
+                "{} Dry run - convergence is not checked",
+                "💡".bright_blue()
+            ));
+        } else if converged {
+            output.push(format!("{} All clusters have converged", "✅".green()));
+        } else {
+            output.push(format!(
+                "{} Clusters have diverged - applied migrations differ between clusters",
+                "⚠️ ".yellow()
+            ));
+        }
+
+        let data = serde_json::json!({
+            "dry_run": self.dry_run,
+            "converged": converged,
+            "clusters": runs.iter().map(|r| {
+                serde_json::json!({
+                    "name": r.name,
+                    "success": r.error.is_none(),
+                    "applied_count": r.applied.len(),
+                    "applied_migrations": r.applied,
+                    "failed_migration": r.failed_migration,
+                    "error": r.error,
+                    "current_version": r.state.as_ref().and_then(|s| s.last()).map(|(v, _)| v)
+                })
+            }).collect::<Vec<_>>()
+        });
+
+        if all_succeeded && (converged || self.dry_run) {
+            Ok(CommandOutput::success_with_data(output.join("\n"), data))
+        } else {
+            Ok(CommandOutput::error_with_data(output.join("\n"), data))
+        }
+    }
+
+    /// Apply pending migrations to each tenant keyspace
+    ///
+    /// Each tenant migrating at the same time needs a connection of its own,
+    /// since the keyspace a session uses is shared by its queries; tenants
+    /// started later reuse the connections of finished ones.
+    ///
+    /// A failing tenant does not stop the others unless `--fail-fast` is given,
+    /// in which case tenants not yet started are skipped.
+    pub async fn execute_all_tenants(&self, config: &Config) -> Result<CommandOutput> {
+        let tenants = tenants::resolve(config).await?;
+        let discovered = tenants.iter().filter(|tenant| tenant.discovered).count();
+        if tenants.is_empty() {
+            return Ok(CommandOutput::error(format!(
+                "{} No tenants found. Add tenants.keyspaces, tenants.manifest or tenants.discovery_query to your config file.",
+                "❌".red()
+            )));
+        }
+        if config.tenants.discovery_query.is_some() {
+            progress!("{} Discovered {} more tenant keyspace(s)", "🔎".bright_blue(), discovered);
+        }
+
+        if !self.dry_run && requires_approval(config) {
+            return Ok(CommandOutput::error(format!(
+                "{} --all-tenants cannot apply migrations where they need approval (approval.environments); approve and apply each tenant's plan separately",
+                "❌".red()
+            )));
+        }
+
+        let started = Instant::now();
+        let parallelism = self.parallel_tenants.max(1);
+        let sessions: Mutex<Vec<SharedSession>> = Mutex::default();
+        let failed = AtomicBool::new(false);
+
+        let runs: Vec<TenantRun> = stream::iter(tenants)
+            .map(|tenant| {
+                let (sessions, failed) = (&sessions, &failed);
+                async move {
+                    if self.fail_fast && failed.load(Ordering::SeqCst) {
+                        let run = ClusterRun::new(tenant.keyspace);
+                        return TenantRun { run, duration_ms: 0.0, skipped: true };
+                    }
+
+                    let tenant_started = Instant::now();
+                    let mut tenant_config = config.for_tenant(&tenant.keyspace);
+                    tenant_config.variables = tenant.variables;
+                    let idle = sessions.lock().unwrap().pop();
+                    let session = match idle {
+                        Some(session) => Ok(session),
+                        None => SharedSession::connect(config, None).await,
+                    };
+                    let run = match session {
+                        Ok(session) => {
+                            let manager = MigrationManager::builder(tenant_config)
+                                .shared_session(session.clone())
+                                .build()
+                                .await;
+                            let run = self.apply_to_cluster(tenant.keyspace, manager).await;
+                            sessions.lock().unwrap().push(session);
+                            run
+                        }
+                        Err(e) => ClusterRun {
+                            error: Some(e.to_string()),
+                            ..ClusterRun::new(tenant.keyspace)
+                        },
+                    };
+
+                    if run.error.is_some() {
+                        failed.store(true, Ordering::SeqCst);
+                    }
+                    progress!(
+                        "{} Tenant {} {}",
+                        if run.error.is_some() { "❌".red() } else { "✅".green() },
+                        run.name.bright_cyan(),
+                        if run.error.is_some() { "failed" } else { "done" }
+                    );
+                    let duration_ms = tenant_started.elapsed().as_secs_f64() * 1000.0;
+                    TenantRun { run, duration_ms, skipped: false }
+                }
+            })
+            .buffered(parallelism)
+            .collect()
+            .await;
+        let duration_ms = started.elapsed().as_secs_f64() * 1000.0;
+
+        let count = |status: &str| runs.iter().filter(|run| run.status() == status).count();
+        let (succeeded, failures, skipped) = (count("ok"), count("failed"), count("skipped"));
+
+        let mut output = vec![
+            format!(
+                "{} Multi-tenant {} ({} tenant(s), up to {} at a time):",
+                "🏢".cyan(),
+                if self.dry_run { "dry run" } else { "apply" },
+                runs.len(),
+                parallelism
+            ),
+            String::new(),
+        ];
+        for tenant in &runs {
+            let run = &tenant.run;
+            let duration = format!("({})", format_duration_ms(tenant.duration_ms)).dimmed();
+            output.push(match (tenant.skipped, &run.error, &run.failed_migration) {
+                (true, _, _) => format!(
+                    "  {} {} - skipped after an earlier failure",
+                    "⏭️ ".yellow(),
+                    run.name.bright_cyan()
+                ),
+                (false, None, _) => format!(
+                    "  {} {} - {} migration(s) {} {}",
+                    "✅".green(),
+                    run.name.bright_cyan(),
+                    run.applied.len(),
+                    if self.dry_run { "pending" } else { "applied" },
+                    duration
+                ),
+                (false, Some(error), Some(version)) => format!(
+                    "  {} {} - applied {}, failed on {}: {} {}",
+                    "❌".red(),
+                    run.name.bright_cyan(),
+                    run.applied.len(),
+                    version,
+                    error.dimmed(),
+                    duration
+                ),
+                (false, Some(error), None) => format!(
+                    "  {} {} - {} {}",
+                    "❌".red(),
+                    run.name.bright_cyan(),
+                    error.dimmed(),
+                    duration
+                ),
+            });
+        }
+
+        output.push(String::new());
+        if failures == 0 && skipped == 0 {
+            output.push(format!(
+                "{} All {} tenant(s) {} in {}",
+                "✅".green(),
+                runs.len(),
+                if self.dry_run { "checked" } else { "migrated" },
+                format_duration_ms(duration_ms)
+            ));
+        } else {
+            output.push(format!(
+                "{} {} of {} tenant(s) failed, {} skipped, {} succeeded in {}",
+                "❌".red(),
+                failures,
+                runs.len(),
+                skipped,
+                succeeded,
+                format_duration_ms(duration_ms)
+            ));
+        }
+
+        let data = serde_json::json!({
+            "dry_run": self.dry_run,
+            "parallel_tenants": parallelism,
+            "discovered": discovered,
+            "fail_fast": self.fail_fast,
+            "duration_ms": duration_ms,
+            "succeeded": succeeded,
+            "failed": failures,
+            "skipped": skipped,
+            "tenants": runs.iter().map(|tenant| {
+                let run = &tenant.run;
+                serde_json::json!({
+                    "keyspace": run.name,
+                    "status": tenant.status(),
+                    "applied_count": run.applied.len(),
+                    "applied_migrations": run.applied,
+                    "failed_migration": run.failed_migration,
+                    "error": run.error,
+                    "current_version": run.state.as_ref().and_then(|s| s.last()).map(|(v, _)| v),
+                    "duration_ms": tenant.duration_ms
+                })
+            }).collect::<Vec<_>>()
+        });
+        // keyspace, status, applied_count, duration_ms, failed_migration
+        let porcelain = runs
+            .iter()
+            .map(|tenant| {
+                vec![
+                    tenant.run.name.clone(),
+                    tenant.status().to_string(),
+                    tenant.run.applied.len().to_string(),
+                    format!("{:.0}", tenant.duration_ms),
+                    tenant.run.failed_migration.clone().unwrap_or_default(),
+                ]
+            })
+            .collect();
+
+        if failures == 0 && skipped == 0 {
+            Ok(CommandOutput::success_with_data(output.join("\n"), data).with_porcelain(porcelain))
+        } else {
+            Ok(CommandOutput::error_with_data(output.join("\n"), data).with_porcelain(porcelain))
+        }
+    }
+
+    /// Apply pending migrations through a manager `--all-clusters` or
+    /// `--all-tenants` fans out to, with the checks of a single run
+    async fn apply_to_cluster(
+        &self,
+        name: String,
+        manager: Result<MigrationManager, MigrationError>,
+    ) -> ClusterRun {
+        let mut run = ClusterRun::new(name);
+
+        let mut manager = match manager {
+            Ok(manager) => manager,
+            Err(e) => {
+                run.error = Some(e.to_string());
+                return run;
+            }
+        };
+
+        if let Err(e) = self.apply_pending(&mut manager, &mut run).await {
+            run.error = Some(e.to_string());
+        }
+
+        match manager.get_applied_migrations().await {
+            Ok(applied) => {
+                let mut state: Vec<(String, String)> =
+                    applied.into_iter().map(|m| (m.version, m.checksum)).collect();
+                state.sort_by(|a, b| manager.version_scheme().compare(&a.0, &b.0));
+                run.state = Some(state);
+            }
+            Err(e) => {
+                if run.error.is_none() {
+                    run.error = Some(e.to_string());
+                }
+            }
+        }
+
+        run
+    }
+
+    /// Body of [`Self::apply_to_cluster`]: refusals and failed migrations are
+    /// recorded in `run`, errors reading the cluster are returned
+    async fn apply_pending(&self, manager: &mut MigrationManager, run: &mut ClusterRun) -> Result<()> {
+        let _permit = manager.exclusive_run("up", DEFAULT_QUEUE_TIMEOUT).await?;
+
+        if let Some(refusal) = self.check_history(manager, &format!("[{}] ", run.name)).await? {
+            run.error = Some(refusal.error.to_string());
+            return Ok(());
+        }
+
+        let pending = manager.get_pending_migrations().await?;
+        let to_apply: Vec<_> = pending.into_iter().take(self.count.unwrap_or(usize::MAX)).collect();
+
+        // Fail before applying anything rather than halfway through
+        let variables = &manager.get_config().variables;
+        if let Some((version, name)) = to_apply.iter().find_map(|migration| {
+            variable_names(&migration.content)
+                .into_iter()
+                .find(|name| !variables.contains_key(name))
+                .map(|name| (&migration.version, name))
+        }) {
+            run.error = Some(format!("{} uses variable '{}', which is not defined for {}", version, name, run.name));
+            return Ok(());
+        }
+
+        if let Some(refusal) = self.pre_apply_checks(manager, &to_apply).await? {
+            run.error = Some(refusal.error.to_string());
+            return Ok(());
+        }
+
+        if self.dry_run {
+            run.applied = to_apply.into_iter().map(|m| m.version).collect();
+            return Ok(());
+        }
+
+        if self.parallel > 1 {
+            let (applied, failures) = self.apply_waves(manager, &to_apply, |_| {}).await;
+            run.applied = applied.iter().map(|migration| migration.version.clone()).collect();
+            if let Some((migration, error)) = failures.first() {
+                let reports: Vec<_> = failures
+                    .iter()
+                    .map(|(migration, error)| FailureReport::new(manager, &migration.version, error))
+                    .collect();
+                self.write_diagnostics(manager, &reports).await;
+                run.failed_migration = Some(migration.version.clone());
+                run.error = Some(error.to_string());
+            }
+        } else {
+            let result = manager.apply_migrations(&to_apply, |_| {}).await;
+            if let Some(report) = &result.failure {
+                self.write_diagnostics(manager, std::slice::from_ref(report)).await;
+            }
+            run.applied = result.applied_migrations;
+            run.failed_migration = result.failed_migration;
+            run.error = result.error;
+        }
+
+        Ok(())
+    }
+
+    async fn show_dry_run(
+        &self,
+        manager: &MigrationManager,
+        migrations: &[MigrationFile],
+    ) -> Result<CommandOutput> {
+        let exported = match &self.history {
+            Some(path) => load_history(path)?,
+            None => HashMap::new(),
+        };
+        let estimator = Estimator::new(
+            &manager.get_applied_migrations().await?,
+            &manager.get_migration_files().await?,
+            exported,
+        );
+        let estimates: Vec<_> = migrations.iter().map(|m| estimator.estimate(m)).collect();
+
+        // Show what would run, after any WASM transforms
+        let mut transformed = Vec::new();
+        for migration in migrations {
+            match crate::wasm::transform(manager.get_config(), migration) {
+                Ok(migration) => transformed.push(migration),
+                Err(e) => {
+                    return Ok(CommandOutput::error_with_data(
+                        format!("{} Cannot transform {}: {}", "❌".red(), migration.version, e),
+                        serde_json::json!({
+                            "version": migration.version,
+                            "error": e.to_string(),
+                            "error_code": e.code()
+                        }),
+                    )
+                    .with_error(&e))
+                }
+            }
+        }
+        let environment = manager.get_config().behavior.environment.as_deref();
+        let (statements, skipped): (Vec<_>, Vec<_>) = migrations
+            .iter()
+            .zip(&transformed)
+            .map(|(migration, transformed)| {
+                statements_for_environment(transformed.as_ref().unwrap_or(migration), environment)
+            })
+            .unzip();
+
+        let all_files = manager.get_migration_files().await?;
+        let linter = Linter::new(manager.get_config(), &all_files);
+        let findings: Vec<_> = migrations.iter().map(|m| linter.lint(m)).collect();
+
         let mut output = vec![
-            format!("{} Dry run mode - showing migrations that would be applied:", "🔍".cyan()),
+            match self.as_of {
+                Some(at) => format!(
+                    "{} Dry run mode - showing migrations that would have been applied at {}:",
+                    "🔍".cyan(),
+                    format_timestamp(at)
+                ),
+                None => format!("{} Dry run mode - showing migrations that would be applied:", "🔍".cyan()),
+            },
             String::new(),
         ];
 
-        for (i, migration) in migrations.iter().enumerate() {
+        for (i, (migration, estimate)) in migrations.iter().zip(&estimates).enumerate() {
+            let eta = match (estimate.duration_ms, estimate.basis) {
+                (Some(ms), EstimateBasis::Recorded) => format!("~{} (recorded)", format_duration_ms(ms)),
+                (Some(ms), _) => format!("~{} (similar migrations)", format_duration_ms(ms)),
+                (None, _) => "no estimate".to_string(),
+            };
             output.push(format!(
-                "{}. {} - {}",
+                "{}. {} - {} {}",
                 i + 1,
                 migration.version.bright_cyan(),
-                migration.description
+                migration.description,
+                format!("[{}]", eta).dimmed()
             ));
+            if !skipped[i].is_empty() {
+                output.push(format!(
+                    "   {} skips {} statement(s) restricted by only-env (environment: {})",
+                    "↳".yellow(),
+                    skipped[i].len(),
+                    environment.unwrap_or("unset")
+                ));
+            }
+            for finding in &findings[i] {
+                output.push(format!(" {}", format_finding(finding)));
+            }
+            if self.statements {
+                match &statements[i] {
+                    Some(statements) => {
+                        for statement in statements {
+                            let statement = format!("{};", statement);
+                            for line in highlight_cql(&statement).lines() {
+                                output.push(format!("     {}", line));
+                            }
+                        }
+                    }
+                    None => output.push(format!(
+                        "     {}",
+                        "(streamed file, statements are read as they run)".dimmed()
+                    )),
+                }
+            }
         }
 
+        let estimated_ms: f64 = estimates.iter().filter_map(|e| e.duration_ms).sum();
+        let unestimated = estimates.iter().filter(|e| e.duration_ms.is_none()).count();
+
         if migrations.is_empty() {
             output.push("No migrations would be applied.".to_string());
         } else {
@@ -107,20 +1396,264 @@ impl UpCommand {
                 "Total: {} migration(s) would be applied",
                 migrations.len()
             ));
+            let mut eta = format!("Estimated duration: ~{}", format_duration_ms(estimated_ms));
+            if unestimated > 0 {
+                eta.push_str(&format!(
+                    " (plus {} migration(s) without history)",
+                    unestimated
+                ));
+            }
+            output.push(eta);
         }
 
         Ok(CommandOutput::success_with_data(
             output.join("\n"),
             serde_json::json!({
                 "dry_run": true,
+                "as_of": self.as_of,
                 "migrations_count": migrations.len(),
-                "migrations": migrations.iter().map(|m| {
+                "estimated_duration_ms": estimated_ms,
+                "unestimated_count": unestimated,
+                "environment": environment,
+                "migrations": migrations.iter().enumerate().map(|(i, m)| {
                     serde_json::json!({
                         "version": m.version,
-                        "description": m.description
+                        "description": m.description,
+                        "keyspace": manager.target_keyspace(m),
+                        "checksum": m.checksum,
+                        "estimated_duration_ms": estimates[i].duration_ms,
+                        "estimate_basis": estimates[i].basis,
+                        "streamed": m.streamed,
+                        "statements": statements[i],
+                        "skipped_statements": skipped[i],
+                        "lint_findings": findings[i]
                     })
                 }).collect::<Vec<_>>()
             })
         ))
     }
-}
\ No newline at end of file
+}
+
+/// Migration files that were not applied at `at`, per the history table
+///
+/// Files are the current ones; run from a checkout of what was deployed at the
+/// time to leave out files added since.
+async fn pending_as_of(manager: &MigrationManager, at: DateTime<Utc>) -> Result<Vec<MigrationFile>, MigrationError> {
+    let events = manager.history_events().await?;
+    if events.is_empty() {
+        progress!(
+            "{} No history recorded; migrations count as applied from their tracking records, and rollbacks are unknown",
+            "⚠️ ".yellow()
+        );
+    }
+    let past = applied_at_instant(&events, &manager.get_applied_migrations().await?, at);
+    Ok(not_applied(manager.get_migration_files().await?, &past))
+}
+
+/// The files of `files` that `past` does not list as applied
+fn not_applied(files: Vec<MigrationFile>, past: &[PastRecord]) -> Vec<MigrationFile> {
+    let applied: HashSet<&str> = past.iter().map(|record| record.version.as_str()).collect();
+    files
+        .into_iter()
+        .filter(|file| !applied.contains(file.version.as_str()))
+        .collect()
+}
+
+/// Applied migrations whose files changed since they were applied, and those
+/// where only the DOWN section changed
+async fn checksum_mismatches(
+    manager: &MigrationManager,
+) -> Result<(Vec<MigrationError>, Vec<MigrationError>), MigrationError> {
+    Ok(partition_mismatches(manager.verify_migrations().await?))
+}
+
+/// Split `verify` issues into the modified migrations that block `up` and the
+/// DOWN-only changes it warns about; missing files block neither
+fn partition_mismatches(issues: Vec<MigrationError>) -> (Vec<MigrationError>, Vec<MigrationError>) {
+    issues
+        .into_iter()
+        .filter(|e| {
+            matches!(
+                e,
+                MigrationError::ChecksumMismatch { .. } | MigrationError::DownChecksumMismatch { .. }
+            )
+        })
+        .partition(|e| matches!(e, MigrationError::ChecksumMismatch { .. }))
+}
+
+/// Refuse to re-run every migration over a live schema after the tracking table
+/// was emptied or lost
+async fn empty_tracking_error(manager: &MigrationManager) -> Result<Option<MigrationError>, MigrationError> {
+    let guard = manager.get_config().behavior.empty_tracking_guard;
+    if guard == 0 {
+        return Ok(None);
+    }
+
+    let tables = manager.tables_without_tracking().await?;
+    Ok(existing_schema_error(&manager.get_config().database.keyspace, &tables, guard))
+}
+
+/// The refusal for an empty tracking table when `keyspace` already has more
+/// than `guard` tables
+fn existing_schema_error(keyspace: &str, tables: &[String], guard: usize) -> Option<MigrationError> {
+    if tables.len() <= guard {
+        return None;
+    }
+
+    let mut shown = tables.iter().take(5).cloned().collect::<Vec<_>>().join(", ");
+    if tables.len() > 5 {
+        shown.push_str(", ...");
+    }
+    Some(MigrationError::IntegrityError(format!(
+        "The tracking table is empty but keyspace {} already has {} table(s) ({}); applying would re-run every migration over the existing schema",
+        keyspace,
+        tables.len(),
+        shown
+    )))
+}
+
+/// Loud warning when the tracking table holds records written on another cluster
+async fn foreign_cluster_warning(manager: &MigrationManager) -> Result<Option<String>, MigrationError> {
+    let applied = manager.get_applied_migrations().await?;
+    let foreign = manager.foreign_cluster_records(&applied);
+    if foreign.is_empty() {
+        return Ok(None);
+    }
+
+    let mut clusters: Vec<&str> = foreign
+        .iter()
+        .filter_map(|record| record.cluster_name.as_deref())
+        .collect();
+    clusters.sort_unstable();
+    clusters.dedup();
+
+    Ok(Some(format!(
+        "{} WARNING: {} applied migration(s) were recorded on cluster(s) {} but this is '{}'. \
+         The tracking table may have been restored from another cluster; checksums can match \
+         even though this schema was never migrated. Run 'db-migrate drift' before continuing.",
+        "⚠️ ".bright_red().bold(),
+        foreign.len(),
+        clusters.join(", ").bright_red(),
+        manager.cluster_name().unwrap_or_default()
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exit_code;
+
+    #[test]
+    fn test_dry_run_as_of() {
+        use crate::{
+            history::{HistoryAction, HistoryEvent},
+            commands::parse,
+            MigrationRecord,
+        };
+        use chrono::TimeZone;
+
+        // --as-of only plans, against a past tracking table
+        assert!(parse::<UpCommand>(&["--as-of", "2025-06-01"]).is_err());
+        assert!(parse::<UpCommand>(&["--dry-run", "--as-of", "June"]).is_err());
+        let up = parse::<UpCommand>(&["--dry-run", "--as-of", "2025-06-01"]).unwrap();
+        let at = Utc.with_ymd_and_hms(2025, 6, 1, 0, 0, 0).unwrap();
+        assert_eq!(up.as_of, Some(at));
+
+        // 002 was applied, then rolled back before the instant; 003 came after it
+        let event = |version: &str, day: u32, action: HistoryAction| HistoryEvent {
+            version: version.to_string(),
+            occurred_at: Utc.with_ymd_and_hms(2025, 5, day, 0, 0, 0).unwrap(),
+            action: action.as_str().to_string(),
+            checksum: None,
+            cluster_name: None,
+            detail: None,
+        };
+        let events = vec![
+            event("20250101_001_users", 1, HistoryAction::Applied),
+            event("20250101_002_orders", 2, HistoryAction::Applied),
+            event("20250101_002_orders", 3, HistoryAction::RolledBack),
+        ];
+        let records = vec![MigrationRecord::for_test("20250101_003_items", at + chrono::Duration::days(1))];
+        let files = ["20250101_001_users", "20250101_002_orders", "20250101_003_items"]
+            .map(|version| MigrationFile::for_test(version, "-- +migrate Up\nSELECT 1;\n"))
+            .to_vec();
+
+        let pending = not_applied(files, &applied_at_instant(&events, &records, at));
+        assert_eq!(
+            pending.iter().map(|file| file.version.as_str()).collect::<Vec<_>>(),
+            vec!["20250101_002_orders", "20250101_003_items"]
+        );
+    }
+
+    #[test]
+    fn test_existing_schema_error() {
+        let tables: Vec<String> = (1..=7).map(|i| format!("t{}", i)).collect();
+        assert!(existing_schema_error("app", &tables[..2], 2).is_none());
+
+        let error = existing_schema_error("app", &tables, 2).unwrap();
+        assert_eq!(error.exit_code(), exit_code::INTEGRITY);
+        assert!(error.to_string().contains("keyspace app already has 7 table(s) (t1, t2, t3, t4, t5, ...)"));
+    }
+
+    #[test]
+    fn test_dry_run_statements() {
+        let migration = MigrationFile::for_test(
+            "20250101_001_users",
+            "-- +migrate Up\nCREATE ROLE app WITH PASSWORD = '${secret:APP_PASSWORD}';\n-- db-migrate:only-env=dev\nINSERT INTO users (id) VALUES (1);\n-- +migrate Down\nDROP ROLE app;\n",
+        );
+
+        // Placeholders stay unresolved and the DOWN section is left out
+        let (statements, skipped) = statements_for_environment(&migration, Some("prod"));
+        assert_eq!(statements, Some(vec!["CREATE ROLE app WITH PASSWORD = '${secret:APP_PASSWORD}'".to_string()]));
+        assert_eq!(skipped.len(), 1);
+
+        let (statements, skipped) = statements_for_environment(&migration, Some("dev"));
+        assert_eq!(statements.map(|s| s.len()), Some(2));
+        assert!(skipped.is_empty());
+
+        // Streamed files are not read ahead, unparseable ones have nothing to show
+        let streamed = MigrationFile { streamed: true, ..migration.clone() };
+        assert_eq!(statements_for_environment(&streamed, None), (None, Vec::new()));
+        let empty = MigrationFile::for_test("20250102_001_empty", "-- +migrate Up\n");
+        assert_eq!(statements_for_environment(&empty, None), (None, Vec::new()));
+    }
+
+    #[test]
+    fn test_modified_applied_migrations_block_up() {
+        let mismatch = |version: &str, down_only: bool| {
+            let (version, expected, actual) = (version.to_string(), "old".to_string(), "new".to_string());
+            if down_only {
+                MigrationError::DownChecksumMismatch { version, expected, actual }
+            } else {
+                MigrationError::ChecksumMismatch { version, expected, actual }
+            }
+        };
+        let issues = vec![
+            mismatch("20250101_001_users", true),
+            MigrationError::MigrationNotFound("20250102_001_orders".to_string()),
+            mismatch("20250103_001_items", false),
+        ];
+
+        let (blocking, down_changes) = partition_mismatches(issues);
+        assert_eq!(blocking.len(), 1);
+        assert_eq!(down_changes.len(), 1);
+        assert!(matches!(&down_changes[0], MigrationError::DownChecksumMismatch { version, .. } if version == "20250101_001_users"));
+
+        let output = UpCommand::default().tampered_history_output(&blocking);
+        assert!(!output.success);
+        assert_eq!(output.code, Some("E_CHECKSUM_MISMATCH"));
+        assert_eq!(output.exit_code, exit_code::INTEGRITY);
+        let data = output.data.unwrap();
+        assert_eq!(data["applied_count"], 0);
+        assert_eq!(data["checksum_mismatches"].as_array().map(Vec::len), Some(1));
+
+        // --skip-verification and behavior.verify_checksums turn the check off
+        let mut config = Config::default();
+        config.behavior.verify_checksums = true;
+        assert!(UpCommand::default().should_verify(&config));
+        let skipping = UpCommand { skip_verification: true, ..UpCommand::default() };
+        assert!(!skipping.should_verify(&config));
+        config.behavior.verify_checksums = false;
+        assert!(!UpCommand::default().should_verify(&config));
+    }
+}
@@ -1,38 +1,137 @@
 
-use crate::{migration::MigrationManager, CommandOutput, MigrationError};
+use crate::{
+    annotations::Annotation,
+    commands::lint::format_finding,
+    lint::Severity,
+    config::BehaviorConfig,
+    migration::MigrationManager,
+    report::{AcceptedChecksum, VerifyIssue},
+    CommandOutput, MigrationError,
+};
 use anyhow::Result;
 use clap::Args;
 use colored::*;
 
 #[derive(Args)]
 pub struct VerifyCommand {
-    /// Fix checksum mismatches automatically (dangerous)
+    /// Accept the current files of migrations with checksum mismatches
+    /// (needs `allow_repair` or `allow_destructive`)
     #[arg(long)]
     fix: bool,
+
+    /// With --fix, only accept the file of this version
+    #[arg(long, requires = "fix", value_name = "VERSION")]
+    version: Option<String>,
+
+    /// Also check that applied migrations match file order and still parse
+    #[arg(long)]
+    strict: bool,
 }
 
 impl VerifyCommand {
+    /// Whether this invocation only reads from the database
+    pub fn is_read_only(&self) -> bool {
+        !self.fix
+    }
+
+    /// The refusal of `--fix` when `behavior` allows neither repairs nor
+    /// destructive operations
+    fn fix_error(&self, behavior: &BehaviorConfig) -> Option<MigrationError> {
+        (self.fix && !(behavior.allow_repair || behavior.allow_destructive)).then(|| {
+            MigrationError::ConfigError(
+                "verify --fix rewrites tracking records; set 'allow_repair = true' (or 'allow_destructive = true') in [behavior] to enable it".to_string(),
+            )
+        })
+    }
+
+    /// The checksum mismatches `--fix` accepts: all of them, or the one of `--version`
+    fn fix_targets<'a>(&self, mismatches: &'a [(String, String, String)]) -> Vec<&'a (String, String, String)> {
+        mismatches
+            .iter()
+            .filter(|(version, _, _)| self.version.as_ref().is_none_or(|only| only == version))
+            .collect()
+    }
+
     pub async fn execute(&self, manager: &MigrationManager) -> Result<CommandOutput> {
-        let errors = manager.verify_migrations().await?;
+        if let Some(e) = self.fix_error(&manager.get_config().behavior) {
+            return Ok(CommandOutput::error(format!("{} {}", "❌".red(), e)).with_error(&e));
+        }
+
+        let mut report = manager.verify(self.strict).await?;
+        report.fixed = self.fix;
 
-        if errors.is_empty() {
+        if report.is_clean() {
             return Ok(CommandOutput::success(format!(
                 "{} All migrations verified successfully - no integrity issues found",
                 "✅".green()
-            )));
+            ))
+            .with_porcelain(Vec::new())
+            .with_annotations(Vec::new()));
         }
 
+        let directory = &manager.get_config().migrations.directory;
+        let files = manager.get_migration_files().await?;
+        let annotations = report
+            .issues
+            .iter()
+            .map(|issue| Annotation {
+                rule: issue.rule().to_string(),
+                severity: issue.severity(),
+                file: files
+                    .iter()
+                    .find(|file| Some(file.version.as_str()) == issue.version())
+                    .map_or_else(|| directory.clone(), |file| file.file_path.clone()),
+                line: None,
+                message: issue.message(),
+            })
+            .chain(report.down_section_issues.iter().map(Annotation::from))
+            .chain(report.invalid_files.iter().map(|file| Annotation {
+                rule: "invalid-filename".to_string(),
+                severity: Severity::Error,
+                file: directory.join(&file.filename),
+                line: None,
+                message: file.reason.clone(),
+            }))
+            .collect();
+
+        let porcelain = report
+            .issues
+            .iter()
+            .map(|issue| match issue {
+                VerifyIssue::ChecksumMismatch { version, expected_checksum, actual_checksum } => {
+                    vec!["checksum_mismatch".to_string(), version.clone(), expected_checksum.clone(), actual_checksum.clone()]
+                }
+                VerifyIssue::DownChanged { version, expected_checksum, actual_checksum } => {
+                    vec!["down_changed".to_string(), version.clone(), expected_checksum.clone(), actual_checksum.clone()]
+                }
+                VerifyIssue::MissingFile { version } => {
+                    vec!["missing_file".to_string(), version.clone()]
+                }
+                VerifyIssue::OutOfOrder { version, latest_applied } => {
+                    vec!["out_of_order".to_string(), version.clone(), latest_applied.clone()]
+                }
+                VerifyIssue::Unparseable { version, reason } => {
+                    vec!["unparseable".to_string(), version.clone(), reason.clone()]
+                }
+                VerifyIssue::Other { error } => vec!["other".to_string(), String::new(), error.clone()],
+            })
+            .chain(report.down_section_issues.iter().map(|finding| {
+                vec!["down_section".to_string(), finding.version.clone(), finding.message.clone()]
+            }))
+            .chain(report.invalid_files.iter().map(|file| {
+                vec!["invalid_filename".to_string(), file.filename.clone(), file.reason.clone()]
+            }))
+            .collect();
+
         let mut output = Vec::new();
         output.push(format!("{} Migration integrity issues found:", "⚠️ ".yellow()));
         output.push(String::new());
 
         let mut checksum_errors = Vec::new();
-        let mut missing_errors = Vec::new();
-
-        for error in &errors {
-            match error {
-                MigrationError::ChecksumMismatch { version, expected, actual } => {
-                    checksum_errors.push((version, expected, actual));
+        for issue in &report.issues {
+            match issue {
+                VerifyIssue::ChecksumMismatch { version, expected_checksum, actual_checksum } => {
+                    checksum_errors.push((version.clone(), expected_checksum.clone(), actual_checksum.clone()));
                     output.push(format!(
                         "  {} Checksum mismatch for migration: {}",
                         "❌".red(),
@@ -40,16 +139,24 @@ impl VerifyCommand {
                     ));
                     output.push(format!(
                         "     Expected: {}",
-                        expected.dimmed()
+                        expected_checksum.dimmed()
                     ));
                     output.push(format!(
                         "     Actual:   {}",
-                        actual.dimmed()
+                        actual_checksum.dimmed()
                     ));
                     output.push(String::new());
                 }
-                MigrationError::MigrationNotFound(version) => {
-                    missing_errors.push(version);
+                VerifyIssue::DownChanged { version, .. } => {
+                    output.push(format!(
+                        "  {} DOWN section changed for migration: {}",
+                        "⚠️ ".yellow(),
+                        version.bright_cyan()
+                    ));
+                    output.push("     The applied UP section is unchanged".dimmed().to_string());
+                    output.push(String::new());
+                }
+                VerifyIssue::MissingFile { version } => {
                     output.push(format!(
                         "  {} Migration file missing: {}",
                         "❌".red(),
@@ -57,47 +164,99 @@ impl VerifyCommand {
                     ));
                     output.push(String::new());
                 }
-                _ => {
+                VerifyIssue::OutOfOrder { version, latest_applied } => {
+                    output.push(format!(
+                        "  {} Pending migration out of order: {} (sorts before applied {})",
+                        "❌".red(),
+                        version.bright_cyan(),
+                        latest_applied.bright_cyan()
+                    ));
+                    output.push(String::new());
+                }
+                VerifyIssue::Unparseable { version, reason } => {
+                    output.push(format!(
+                        "  {} Applied migration no longer parses: {}",
+                        "❌".red(),
+                        version.bright_cyan()
+                    ));
+                    output.push(format!("     {}", reason.dimmed()));
+                    output.push(String::new());
+                }
+                VerifyIssue::Other { error } => {
                     output.push(format!(
                         "  {} Other error: {}",
                         "❌".red(),
-                        error.to_string()
+                        error
                     ));
                     output.push(String::new());
                 }
             }
         }
 
+        if !report.down_section_issues.is_empty() {
+            output.push("  DOWN section issues:".bold().to_string());
+            for finding in &report.down_section_issues {
+                output.push(format!("  {}", format_finding(finding)));
+            }
+            output.push(String::new());
+        }
+
+        if !report.invalid_files.is_empty() {
+            output.push("  Files skipped for their name (neither applied nor pending):".bold().to_string());
+            for file in &report.invalid_files {
+                output.push(format!("  {} {}", "❌".red(), file.filename.bright_cyan()));
+                output.push(format!("     {}", file.reason.dimmed()));
+            }
+            output.push(String::new());
+        }
+
         // Summary
         output.push("Summary:".bold().to_string());
-        if !checksum_errors.is_empty() {
-            output.push(format!(
-                "  • {} migration(s) with checksum mismatches",
-                checksum_errors.len()
-            ));
-        }
-        if !missing_errors.is_empty() {
-            output.push(format!(
-                "  • {} migration(s) with missing files",
-                missing_errors.len()
-            ));
+        for (count, what) in [
+            (report.checksum_mismatches, "migration(s) with checksum mismatches"),
+            (report.down_changes, "migration(s) with a changed DOWN section"),
+            (report.missing_files, "migration(s) with missing files"),
+            (report.down_section_issues.len(), "migration(s) with DOWN section issues"),
+            (report.invalid_files.len(), "file(s) with an invalid migration filename"),
+            (report.out_of_order, "pending migration(s) out of order"),
+            (report.unparseable, "applied migration(s) that no longer parse"),
+        ] {
+            if count > 0 {
+                output.push(format!("  • {} {}", count, what));
+            }
         }
 
         output.push(String::new());
 
-        if self.fix && !checksum_errors.is_empty() {
-            output.push(format!("{} Attempting to fix checksum mismatches...", "🔧".cyan()));
+        if self.fix {
+            let targets = self.fix_targets(&checksum_errors);
+            if let (Some(only), true) = (&self.version, targets.is_empty()) {
+                output.push(format!(
+                    "{} No checksum mismatch for {}; nothing was fixed",
+                    "⚠️ ".yellow(),
+                    only.bright_cyan()
+                ));
+            } else if !targets.is_empty() {
+                output.push(format!("{} Accepting the current files of mismatched migrations:", "🔧".cyan()));
+            }
 
-            let mut fixed_count = 0;
-            for (version, _expected, actual) in &checksum_errors {
-                match self.fix_checksum_mismatch(manager, version, actual).await {
+            for (version, expected, actual) in targets {
+                let file = files.iter().find(|file| &file.version == version);
+                let up_checksum = file.map(|file| file.up_checksum()).unwrap_or_default();
+                match manager.update_migration_checksum(version, actual, &up_checksum).await {
                     Ok(_) => {
-                        fixed_count += 1;
-                        output.push(format!(
-                            "  {} Fixed checksum for: {}",
-                            "✅".green(),
-                            version.bright_cyan()
-                        ));
+                        output.push(format!("  {} Accepted {}", "✅".green(), version.bright_cyan()));
+                        if let Some(file) = file {
+                            output.push(format!("     File: {}", file.file_path.display()));
+                        }
+                        output.push(format!("     {} checksum {}", "-".red(), expected.red()));
+                        output.push(format!("     {} checksum {}", "+".green(), actual.green()));
+                        report.accepted.push(AcceptedChecksum {
+                            version: version.clone(),
+                            file: file.map(|file| file.file_path.clone()),
+                            old_checksum: expected.clone(),
+                            new_checksum: actual.clone(),
+                        });
                     }
                     Err(e) => {
                         output.push(format!(
@@ -110,73 +269,75 @@ impl VerifyCommand {
                 }
             }
 
-            if fixed_count > 0 {
+            if !report.accepted.is_empty() {
                 output.push(String::new());
                 output.push(format!(
                     "{} Fixed {} checksum mismatch(es)",
                     "✅".green(),
-                    fixed_count
+                    report.accepted.len()
                 ));
             }
         } else if !checksum_errors.is_empty() {
             output.push(format!(
-                "{} Use --fix to automatically update checksums in the database",
+                "{} Use --fix (all) or --fix --version <version> (one) to accept the current files",
                 "💡".bright_blue()
             ));
         }
 
-        if !missing_errors.is_empty() {
+        if report.down_changes > 0 {
             output.push(format!(
-                "{} Missing migration files cannot be automatically fixed",
+                "{} Use 'db-migrate repair --accept-down' to accept the changed DOWN sections",
+                "💡".bright_blue()
+            ));
+        }
+
+        if report.missing_files > 0 {
+            output.push(format!(
+                "{} Missing migration files cannot be fixed by --fix",
                 "⚠️ ".yellow()
             ));
             output.push("   These migrations were applied but their files are missing.".dimmed().to_string());
-            output.push("   You may need to recreate them or remove the records manually.".dimmed().to_string());
+            output.push("   Restore the files, or remove the records with 'db-migrate repair --remove-orphans'.".dimmed().to_string());
         }
 
-        Ok(CommandOutput::success_with_data(
-            output.join("\n"),
-            serde_json::json!({
-                "integrity_issues": errors.len(),
-                "checksum_mismatches": checksum_errors.len(),
-                "missing_files": missing_errors.len(),
-                "fixed": self.fix,
-                "issues": errors.iter().map(|e| {
-                    match e {
-                        MigrationError::ChecksumMismatch { version, expected, actual } => {
-                            serde_json::json!({
-                                "type": "checksum_mismatch",
-                                "version": version,
-                                "expected_checksum": expected,
-                                "actual_checksum": actual
-                            })
-                        }
-                        MigrationError::MigrationNotFound(version) => {
-                            serde_json::json!({
-                                "type": "missing_file",
-                                "version": version
-                            })
-                        }
-                        _ => {
-                            serde_json::json!({
-                                "type": "other",
-                                "error": e.to_string()
-                            })
-                        }
-                    }
-                }).collect::<Vec<_>>()
-            })
-        ))
+        Ok(CommandOutput::success_with_data(output.join("\n"), serde_json::to_value(&report)?)
+            .with_porcelain(porcelain)
+            .with_annotations(annotations))
     }
+}
 
-    async fn fix_checksum_mismatch(
-        &self,
-        manager: &MigrationManager,
-        version: &str,
-        new_checksum: &str,
-    ) -> Result<()> {
-        // We'll need to add this method to MigrationManager
-        manager.update_migration_checksum(version, new_checksum).await?;
-        Ok(())
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::parse;
+
+    #[test]
+    fn test_fix_needs_repair_and_selects_versions() {
+        // --version only narrows --fix
+        assert!(parse::<VerifyCommand>(&["--version", "002"]).is_err());
+
+        let fix = parse::<VerifyCommand>(&["--fix"]).unwrap();
+        assert!(!fix.is_read_only());
+        let mut behavior = crate::config::Config::default().behavior;
+        behavior.allow_repair = false;
+        behavior.allow_destructive = false;
+        let error = fix.fix_error(&behavior).unwrap();
+        assert!(error.to_string().contains("allow_repair = true"));
+        assert!(parse::<VerifyCommand>(&[]).unwrap().fix_error(&behavior).is_none());
+        behavior.allow_repair = true;
+        assert!(fix.fix_error(&behavior).is_none());
+        behavior.allow_repair = false;
+        behavior.allow_destructive = true;
+        assert!(fix.fix_error(&behavior).is_none());
+
+        let mismatches = vec![
+            ("001".to_string(), "a".to_string(), "b".to_string()),
+            ("002".to_string(), "c".to_string(), "d".to_string()),
+        ];
+        assert_eq!(fix.fix_targets(&mismatches).len(), 2);
+        let one = parse::<VerifyCommand>(&["--fix", "--version", "002"]).unwrap();
+        assert_eq!(one.fix_targets(&mismatches), vec![&mismatches[1]]);
+        let none = parse::<VerifyCommand>(&["--fix", "--version", "003"]).unwrap();
+        assert!(none.fix_targets(&mismatches).is_empty());
     }
-}
\ No newline at end of file
+}
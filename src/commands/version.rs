@@ -0,0 +1,80 @@
+use crate::{
+    compat::{latest_release, Staleness, TOOL_VERSION},
+    progress, CommandOutput,
+};
+use anyhow::Result;
+use clap::Args;
+use colored::*;
+
+#[derive(Args)]
+pub struct VersionCommand {
+    /// Compare with the latest release (see DB_MIGRATE_RELEASE_URL)
+    #[arg(long)]
+    check: bool,
+
+    /// With --check, fail when the latest release is on a newer compatibility line
+    #[arg(long, requires = "check")]
+    fail_if_outdated: bool,
+}
+
+impl VersionCommand {
+    /// Runs before the configuration is loaded, like `config`
+    pub async fn execute(&self) -> Result<CommandOutput> {
+        if !self.check {
+            return Ok(CommandOutput::success_with_data(
+                format!("db-migrate {}", TOOL_VERSION),
+                serde_json::json!({ "current": TOOL_VERSION }),
+            )
+            .with_porcelain(vec![vec![TOOL_VERSION.to_string()]]));
+        }
+
+        progress!("{} Looking up the latest release...", "🔎".cyan());
+        let latest = match latest_release().await {
+            Ok(latest) => latest,
+            Err(e) => {
+                return Ok(CommandOutput::error(format!(
+                    "{} Could not look up the latest release: {}",
+                    "❌".red(),
+                    e
+                )))
+            }
+        };
+
+        let staleness = Staleness::of(TOOL_VERSION, &latest);
+        let data = serde_json::json!({
+            "current": TOOL_VERSION,
+            "latest": latest,
+            "staleness": staleness,
+            "outdated": staleness != Staleness::UpToDate
+        });
+        let porcelain = vec![vec![TOOL_VERSION.to_string(), latest.clone(), staleness.as_str().to_string()]];
+
+        let message = match staleness {
+            Staleness::UpToDate => format!(
+                "{} db-migrate {} is up to date (latest release: {})",
+                "✅".green(),
+                TOOL_VERSION.bright_cyan(),
+                latest
+            ),
+            Staleness::Behind => format!(
+                "{} db-migrate {} is available (installed: {}); it is compatible with this version",
+                "💡".bright_blue(),
+                latest.bright_cyan(),
+                TOOL_VERSION
+            ),
+            Staleness::Incompatible => format!(
+                "{} db-migrate {} is significantly behind the latest release {}\n   Migrations applied by {} write tracking records this binary refuses; upgrade before running against shared environments.",
+                "⚠️ ".yellow(),
+                TOOL_VERSION,
+                latest.bright_cyan(),
+                latest
+            ),
+        };
+
+        if staleness == Staleness::Incompatible && self.fail_if_outdated {
+            Ok(CommandOutput::error_with_data(message, data).with_porcelain(porcelain))
+        } else {
+            Ok(CommandOutput::success_with_data(message, data).with_porcelain(porcelain))
+        }
+    }
+}
@@ -0,0 +1,222 @@
+//! Compatibility between db-migrate releases sharing a tracking table
+//!
+//! Every tracking record stores the version of db-migrate that wrote it. A
+//! release may change the tracking table in ways older binaries would corrupt,
+//! so a binary refuses to operate on records written by a newer incompatible
+//! release. Compatibility follows Cargo's semver rules: releases are compatible
+//! within the same major version, or the same minor version while the major
+//! version is 0.
+//!
+//! The same rule tells how far an installed binary is behind the latest release:
+//! a release on a newer line writes records this binary refuses.
+
+use crate::{http, MigrationError, MigrationRecord};
+use serde::{Deserialize, Serialize};
+
+/// Version of this binary, recorded with every applied migration
+pub const TOOL_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// The compatibility line of a version: `(major, 0)`, or `(0, minor)` before 1.0
+///
+/// `None` for versions that do not start with `major.minor`.
+pub fn compatibility_line(version: &str) -> Option<(u64, u64)> {
+    let mut parts = version.trim_start_matches('v').split(['.', '-', '+']);
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next()?.parse().ok()?;
+    Some(if major == 0 { (0, minor) } else { (major, 0) })
+}
+
+/// Whether a binary of version `current` may operate on records written by `written_by`
+pub fn is_compatible(written_by: &str, current: &str) -> bool {
+    match (compatibility_line(written_by), compatibility_line(current)) {
+        (Some(written_by), Some(current)) => written_by <= current,
+        // Unrecognized versions predate nothing we know about
+        _ => true,
+    }
+}
+
+/// `(major, minor, patch)` of a version, ignoring a `v` prefix and pre-release
+/// or build suffixes; missing parts are 0
+pub fn parse_version(version: &str) -> Option<(u64, u64, u64)> {
+    let core = version.trim().trim_start_matches('v').split(['-', '+']).next()?;
+    let mut parts = core.split('.').map(|part| part.parse::<u64>().ok());
+    let major = parts.next()??;
+    let minor = parts.next().unwrap_or(Some(0))?;
+    let patch = parts.next().unwrap_or(Some(0))?;
+    parts.next().is_none().then_some((major, minor, patch))
+}
+
+/// Whether `version` meets a Cargo-style requirement such as `>=0.5, <2`
+///
+/// Comparators are `>=`, `>`, `<=`, `<`, `=`, `^` and `~`; a bare version means
+/// `^`. Parts left out of `=` match anything.
+pub fn satisfies(version: &str, requirement: &str) -> Result<bool, String> {
+    let version = parse_version(version).ok_or_else(|| format!("'{}' is not a version", version))?;
+    let mut satisfied = true;
+    for comparator in requirement.split(',').map(str::trim) {
+        let (op, bound) = ["<=", ">=", "<", ">", "=", "^", "~"]
+            .iter()
+            .find_map(|op| comparator.strip_prefix(op).map(|bound| (*op, bound.trim())))
+            .unwrap_or(("^", comparator));
+        let given = bound.trim_start_matches('v').split('.').count();
+        let invalid = || format!("'{}' is not a version requirement", requirement);
+        let lower = parse_version(bound).ok_or_else(invalid)?;
+        let (major, minor, patch) = lower;
+        // Exclusive upper bound of the versions a partial or caret bound covers
+        let next = |part: usize| match part {
+            1 => (major + 1, 0, 0),
+            2 => (major, minor + 1, 0),
+            _ => (major, minor, patch + 1),
+        };
+        let caret_part = if major > 0 { 1 } else if minor > 0 || given == 2 { 2 } else { given.min(3) };
+        satisfied &= match op {
+            ">=" => version >= lower,
+            ">" => version >= next(given),
+            "<=" => version < next(given),
+            "<" => version < lower,
+            "=" => version >= lower && version < next(given),
+            "~" => version >= lower && version < next(given.min(2)),
+            _ => version >= lower && version < next(caret_part),
+        };
+    }
+    Ok(satisfied)
+}
+
+/// Fail unless this binary meets `required_version` of the configuration
+pub fn check_required_version(requirement: &str) -> Result<(), String> {
+    if satisfies(TOOL_VERSION, requirement)? {
+        Ok(())
+    } else {
+        Err(format!(
+            "db-migrate {} does not satisfy required_version \"{}\"; upgrade db-migrate (see 'db-migrate version --check')",
+            TOOL_VERSION, requirement
+        ))
+    }
+}
+
+/// How an installed version compares to the latest release
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Staleness {
+    UpToDate,
+    /// A newer compatible release exists
+    Behind,
+    /// The latest release is on a newer compatibility line
+    Incompatible,
+}
+
+impl Staleness {
+    pub fn of(current: &str, latest: &str) -> Self {
+        match (parse_version(current), parse_version(latest)) {
+            (Some(current_version), Some(latest_version)) if latest_version > current_version => {
+                if is_compatible(latest, current) {
+                    Staleness::Behind
+                } else {
+                    Staleness::Incompatible
+                }
+            }
+            _ => Staleness::UpToDate,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Staleness::UpToDate => "up_to_date",
+            Staleness::Behind => "behind",
+            Staleness::Incompatible => "incompatible",
+        }
+    }
+}
+
+/// Endpoint answering with the latest release as `{"tag_name": "v1.2.3"}`
+pub const RELEASE_URL: &str = "https://api.github.com/repos/dimuziop/db-migrate/releases/latest";
+
+/// Environment variable overriding [`RELEASE_URL`], e.g. for an internal mirror
+pub const RELEASE_URL_ENV: &str = "DB_MIGRATE_RELEASE_URL";
+
+#[derive(Debug, Deserialize)]
+struct Release {
+    tag_name: String,
+}
+
+/// Version of the latest release, without its `v` prefix
+pub async fn latest_release() -> Result<String, String> {
+    let url = std::env::var(RELEASE_URL_ENV).unwrap_or_else(|_| RELEASE_URL.to_string());
+    let release = tokio::task::spawn_blocking(move || http::get_json::<Release>(&url))
+        .await
+        .map_err(|e| e.to_string())??;
+    Ok(release.tag_name.trim_start_matches('v').to_string())
+}
+
+/// Fail on the first record written by a newer, incompatible db-migrate
+pub fn check_records(records: &[MigrationRecord]) -> Result<(), MigrationError> {
+    for record in records {
+        if let Some(written_by) = &record.tool_version {
+            if !is_compatible(written_by, TOOL_VERSION) {
+                return Err(MigrationError::IncompatibleToolVersion {
+                    version: record.version.clone(),
+                    written_by: written_by.clone(),
+                    current: TOOL_VERSION.to_string(),
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compatibility() {
+        assert_eq!(compatibility_line("0.1.1"), Some((0, 1)));
+        assert_eq!(compatibility_line("2.3.0-rc.1"), Some((2, 0)));
+        assert_eq!(compatibility_line("dev"), None);
+
+        assert!(is_compatible("0.1.0", "0.1.5"));
+        assert!(is_compatible("0.1.9", "0.1.0"));
+        assert!(is_compatible("0.1.0", "0.2.0"));
+        assert!(!is_compatible("0.2.0", "0.1.5"));
+        assert!(is_compatible("1.9.0", "1.0.0"));
+        assert!(!is_compatible("2.0.0", "1.9.9"));
+        assert!(is_compatible("unknown", "1.0.0"));
+    }
+
+    #[test]
+    fn test_satisfies() {
+        let check = |version, requirement| satisfies(version, requirement).unwrap();
+        assert!(check("0.5.0", ">=0.5"));
+        assert!(!check("0.4.9", ">=0.5"));
+        assert!(check("0.5.3", ">=0.5, <0.6"));
+        assert!(!check("0.6.0", ">=0.5, <0.6"));
+        assert!(check("0.5.9", "=0.5"));
+        assert!(!check("0.6.0", "=0.5"));
+        assert!(!check("0.5.9", ">0.5"));
+        assert!(check("0.6.0", ">0.5"));
+        assert!(check("0.5.9", "<=0.5"));
+        assert!(check("1.4.0", "^1.2"));
+        assert!(!check("2.0.0", "^1.2"));
+        assert!(check("0.2.5", "0.2.3"));
+        assert!(!check("0.3.0", "0.2.3"));
+        assert!(check("1.2.9", "~1.2"));
+        assert!(!check("1.3.0", "~1.2.3"));
+        assert!(satisfies("0.1.0", ">=").is_err());
+        assert!(satisfies("0.1.0", "at least 0.5").is_err());
+    }
+
+    #[test]
+    fn test_staleness() {
+        assert_eq!(parse_version("v1.2.3-rc.1"), Some((1, 2, 3)));
+        assert_eq!(parse_version("0.5"), Some((0, 5, 0)));
+        assert_eq!(parse_version("1.2.3.4"), None);
+        assert_eq!(parse_version("dev"), None);
+
+        assert_eq!(Staleness::of("0.1.1", "0.1.1"), Staleness::UpToDate);
+        assert_eq!(Staleness::of("0.2.0", "0.1.9"), Staleness::UpToDate);
+        assert_eq!(Staleness::of("0.1.1", "0.1.4"), Staleness::Behind);
+        assert_eq!(Staleness::of("0.1.1", "0.2.0"), Staleness::Incompatible);
+        assert_eq!(Staleness::of("1.0.0", "1.4.0"), Staleness::Behind);
+        assert_eq!(Staleness::of("1.4.0", "2.0.0"), Staleness::Incompatible);
+    }
+}
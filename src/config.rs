@@ -1,17 +1,129 @@
+use crate::checkpoint::CHECKPOINT_TABLE_SUFFIX;
+use crate::compat;
+use crate::cql;
+use crate::history::HISTORY_TABLE_SUFFIX;
+use crate::lock::LOCK_TABLE_SUFFIX;
+use crate::policy::Policy;
+use crate::version::VersionScheme;
+use crate::{MigrationError, MigrationFile};
+use crate::utils::portable_path;
 use anyhow::Result;
+#[cfg(feature = "config-schema")]
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 use tokio::fs;
 
 /// Main configuration structure
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "config-schema", derive(JsonSchema))]
+#[serde(deny_unknown_fields)]
 pub struct Config {
+    /// Versions of db-migrate allowed to use this file, e.g. `>=0.5`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub required_version: Option<String>,
     pub database: DatabaseConfig,
     pub migrations: MigrationsConfig,
     pub behavior: BehaviorConfig,
+    #[serde(default)]
+    pub lint: LintConfig,
+    #[serde(default)]
+    pub limits: LimitsConfig,
+    #[serde(default)]
+    pub secrets: SecretsConfig,
+    #[serde(default)]
+    pub templates: TemplatesConfig,
+    #[serde(default)]
+    pub history: HistoryConfig,
+    #[serde(default)]
+    pub backups: BackupsConfig,
+    #[serde(default)]
+    pub approval: ApprovalConfig,
+    #[serde(default)]
+    pub reads: ReadsConfig,
+    #[serde(default)]
+    pub output: OutputConfig,
+    #[serde(default)]
+    pub replication: ReplicationConfig,
+    #[serde(default)]
+    pub versioning: VersioningConfig,
+    #[serde(default)]
+    pub tenants: TenantsConfig,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub clusters: Vec<ClusterConfig>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub plugins: Vec<PluginConfig>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub wasm_plugins: Vec<WasmPluginConfig>,
+    /// Forbidden patterns, loaded from `lint.policy_file`
+    #[serde(skip)]
+    pub policy: Policy,
+    /// Values of `${var:NAME}` placeholders, set per tenant from `tenants.manifest`
+    #[serde(skip)]
+    pub variables: BTreeMap<String, String>,
 }
 
+/// External executable that approves or denies migrations during `lint` and
+/// before `up` (see [`crate::plugins`])
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "config-schema", derive(JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct PluginConfig {
+    /// Name shown with the plugin's messages
+    pub name: String,
+    /// Executable to run, looked up on `PATH` unless it is a path
+    pub command: PathBuf,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Seconds to wait for a verdict; a plugin that takes longer denies
+    #[serde(default = "default_timeout")]
+    pub timeout_seconds: u64,
+}
+
+/// Sandboxed WebAssembly module hooked into `lint`, `up` and execution (see
+/// [`crate::wasm`]); needs the `wasm-plugins` feature
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "config-schema", derive(JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct WasmPluginConfig {
+    /// Name shown with the module's messages
+    pub name: String,
+    /// Compiled `.wasm` module (or `.wat` text)
+    pub module: PathBuf,
+    /// Hooks the module may implement and host functions it may import
+    pub capabilities: Vec<WasmCapability>,
+    /// Instruction budget for each call
+    #[serde(default = "default_wasm_fuel")]
+    pub fuel: u64,
+    /// Largest memory the module may grow to
+    #[serde(default = "default_wasm_memory_mb")]
+    pub max_memory_mb: u64,
+}
+
+impl WasmPluginConfig {
+    pub fn allows(&self, capability: WasmCapability) -> bool {
+        self.capabilities.contains(&capability)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+
+#[cfg_attr(feature = "config-schema", derive(JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum WasmCapability {
+    /// Approve or deny migrations, like an external plugin
+    Validate,
+    /// Rewrite migration content before it runs
+    Transform,
+    /// Import `db_migrate.log` to write to the log
+    Log,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+
+#[cfg_attr(feature = "config-schema", derive(JsonSchema))]
+#[serde(deny_unknown_fields)]
 pub struct DatabaseConfig {
     pub hosts: Vec<String>,
     pub keyspace: String,
@@ -23,17 +135,97 @@ pub struct DatabaseConfig {
     pub port: u16,
     #[serde(default = "default_datacenter")]
     pub datacenter: String,
+    #[serde(default)]
+    pub tls: TlsConfig,
+}
+
+/// TLS settings, including client certificates for mutual TLS
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "config-schema", derive(JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct TlsConfig {
+    #[serde(default = "default_false")]
+    pub enabled: bool,
+    /// CA bundle used to verify the server certificate
+    #[serde(default)]
+    pub ca_cert: Option<PathBuf>,
+    /// Client certificate (PEM) presented for mutual TLS
+    #[serde(default)]
+    pub client_cert: Option<PathBuf>,
+    /// Private key (PEM) matching the client certificate
+    #[serde(default)]
+    pub client_key: Option<PathBuf>,
+    /// Passphrase for an encrypted client key
+    #[serde(default)]
+    pub client_key_password: Option<String>,
+    #[serde(default = "default_true")]
+    pub verify_peer: bool,
+}
+
+/// An additional cluster that mirrors the schema of the primary one
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "config-schema", derive(JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct ClusterConfig {
+    pub name: String,
+    pub hosts: Vec<String>,
+    /// Overrides `database.keyspace` for this cluster
+    #[serde(default)]
+    pub keyspace: Option<String>,
+    /// Overrides `database.username` for this cluster
+    #[serde(default)]
+    pub username: Option<String>,
+    /// Overrides `database.password` for this cluster
+    #[serde(default)]
+    pub password: Option<String>,
+    /// Overrides `database.tls` for this cluster
+    #[serde(default)]
+    pub tls: Option<TlsConfig>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+
+#[cfg_attr(feature = "config-schema", derive(JsonSchema))]
+#[serde(deny_unknown_fields)]
 pub struct MigrationsConfig {
     #[serde(default = "default_migrations_dir")]
     pub directory: PathBuf,
     #[serde(default = "default_table_name")]
     pub table_name: String,
+    /// Keyspace holding the tracking table (defaults to `database.keyspace`)
+    #[serde(default)]
+    pub tracking_keyspace: Option<String>,
+    /// Committed snapshot of applied migrations used by the pre-commit hook
+    #[serde(default)]
+    pub snapshot_file: Option<PathBuf>,
+    /// Glob patterns (matched against file names and versions) of files that are
+    /// kept in the directory but never applied or verified
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ignore: Vec<String>,
+}
+
+impl MigrationsConfig {
+    /// Whether a file is left out by `ignore` or its `skip` directive
+    pub fn is_ignored(&self, file: &MigrationFile) -> bool {
+        let file_name = file
+            .file_path
+            .file_name()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_default();
+
+        file.directives.skip
+            || self
+                .ignore
+                .iter()
+                .filter_map(|pattern| glob::Pattern::new(pattern).ok())
+                .any(|pattern| pattern.matches(&file_name) || pattern.matches(&file.version))
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+
+#[cfg_attr(feature = "config-schema", derive(JsonSchema))]
+#[serde(deny_unknown_fields)]
 pub struct BehaviorConfig {
     #[serde(default = "default_true")]
     pub auto_create_keyspace: bool,
@@ -41,8 +233,422 @@ pub struct BehaviorConfig {
     pub verify_checksums: bool,
     #[serde(default = "default_false")]
     pub allow_destructive: bool,
+    /// Allow `verify --fix` to accept edited migration files (also allowed by
+    /// `allow_destructive`)
+    #[serde(default = "default_false")]
+    pub allow_repair: bool,
     #[serde(default = "default_timeout")]
     pub timeout_seconds: u64,
+    #[serde(default)]
+    pub down_validation: DownValidation,
+    /// Stamp the comment of each table a migration creates or alters with its version
+    #[serde(default = "default_false")]
+    pub stamp_table_comments: bool,
+    /// Refuse every write (DDL, DML and tracking records), e.g. for audit users
+    #[serde(default = "default_false")]
+    pub read_only: bool,
+    /// Store each migration's DOWN section in the tracking table, so it can be
+    /// rolled back after its file was deleted
+    #[serde(default = "default_false")]
+    pub store_down_content: bool,
+    /// Name of the environment being migrated (e.g. `dev`, `prod`), matched
+    /// against `only-env` directives. Unset, restricted statements never run.
+    #[serde(default)]
+    pub environment: Option<String>,
+    /// `up` refuses to run when the tracking table is empty but the keyspace
+    /// already holds more than this many tables; `0` disables the check
+    #[serde(default = "default_empty_tracking_guard")]
+    pub empty_tracking_guard: usize,
+    /// How configured keyspace and table names are matched
+    #[serde(default)]
+    pub identifier_quoting: IdentifierQuoting,
+}
+
+/// Settings for offline checks run by `lint` and `up --validate`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "config-schema", derive(JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct LintConfig {
+    /// Require tables and types to be keyspace-qualified (`ks.table`)
+    #[serde(default = "default_false")]
+    pub require_qualified_names: bool,
+    /// Report migrations mixing schema changes with data writes as errors instead
+    /// of warnings; `-- db-migrate:allow-mixed` exempts a migration
+    #[serde(default = "default_false")]
+    pub separate_ddl_dml: bool,
+    /// Forbidden patterns with their justification (see [`crate::policy`]);
+    /// `policy.toml` is used when it exists
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub policy_file: Option<PathBuf>,
+}
+
+/// Guardrails against oversized migrations and runs; `0` disables a limit
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "config-schema", derive(JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct LimitsConfig {
+    /// Largest migration file, in kilobytes
+    #[serde(default = "default_max_file_size_kb")]
+    pub max_file_size_kb: u64,
+    /// Most statements in the UP or DOWN section of a migration
+    #[serde(default = "default_max_statements")]
+    pub max_statements: usize,
+    /// Most migrations `up` applies in one run
+    #[serde(default = "default_max_migrations_per_run")]
+    pub max_migrations_per_run: usize,
+    /// Refuse to apply migrations over a limit unless `up --force` is given,
+    /// instead of only warning
+    #[serde(default = "default_false")]
+    pub enforce: bool,
+}
+
+/// Where `${secret:NAME}` placeholders are looked up
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "config-schema", derive(JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct SecretsConfig {
+    /// Directory containing one file per secret (e.g. `/run/secrets`), consulted
+    /// after the environment
+    #[serde(default)]
+    pub directory: Option<PathBuf>,
+}
+
+/// Content of files generated by `create`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "config-schema", derive(JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct TemplatesConfig {
+    /// Team template replacing the built-in body of new migrations
+    #[serde(default)]
+    pub file: Option<PathBuf>,
+    /// Values for `{{name}}` placeholders in templates
+    #[serde(default)]
+    pub variables: BTreeMap<String, String>,
+}
+
+/// Audit trail of tracking table changes (see [`crate::history`])
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "config-schema", derive(JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct HistoryConfig {
+    /// Record applies, rollbacks, renames, checksum updates and resets
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Age in days past which `history prune` deletes events; the latest event
+    /// of each migration is always kept
+    #[serde(default)]
+    pub retention_days: Option<u32>,
+}
+
+/// Tracking table exports taken before destructive commands (see [`crate::backup`])
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "config-schema", derive(JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct BackupsConfig {
+    /// Back up the tracking table before destructive commands, which abort
+    /// if the backup fails
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Directory the timestamped JSON files are written to
+    #[serde(default = "default_backups_dir")]
+    pub directory: PathBuf,
+}
+
+/// Human approval of `up` in protected environments (see [`crate::approval`])
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "config-schema", derive(JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct ApprovalConfig {
+    /// Environments (`behavior.environment`) in which `up` waits for approval
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub environments: Vec<String>,
+    /// Endpoint the plan is posted to before applying
+    #[serde(default)]
+    pub webhook_url: Option<String>,
+    /// Environment variable holding the secret approval tokens are signed with
+    #[serde(default = "default_approval_secret_env")]
+    pub secret_env: String,
+    /// Seconds to wait for a decision when the webhook returns a `poll_url`
+    #[serde(default = "default_approval_timeout")]
+    pub timeout_seconds: u64,
+    /// Seconds between two polls of the `poll_url`
+    #[serde(default = "default_approval_poll_interval")]
+    pub poll_interval_seconds: u64,
+}
+
+/// Consistency and routing of read-only commands (`status`, `drift`,
+/// `permissions`, `history list` and `verify` without `--fix`); every other
+/// command uses the driver defaults
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "config-schema", derive(JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct ReadsConfig {
+    /// Consistency level of their queries (driver default: `local_quorum`)
+    #[serde(default)]
+    pub consistency: Option<ConsistencyLevel>,
+    /// Datacenter their queries go to first, e.g. the one nearest to where
+    /// they run; others are used when it is unreachable
+    #[serde(default)]
+    pub datacenter: Option<String>,
+}
+
+/// Presentation of human-readable output (see [`crate::messages`]) and run reports
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "config-schema", derive(JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct OutputConfig {
+    /// Decorate messages with emoji; when off, status emoji become plain
+    /// labels such as `OK` and `ERROR`
+    #[serde(default = "default_true")]
+    pub emoji: bool,
+    /// Message catalog translating output lines
+    #[serde(default)]
+    pub messages: Option<PathBuf>,
+    /// JSON report rewritten after every `up` and `down` run (see
+    /// [`crate::runreport`]), e.g. `.db-migrate/last-run.json`
+    #[serde(default)]
+    pub run_report: Option<PathBuf>,
+}
+
+impl Default for OutputConfig {
+    fn default() -> Self {
+        Self {
+            emoji: default_true(),
+            messages: None,
+            run_report: None,
+        }
+    }
+}
+
+/// How versions are read from migration file names and ordered (see [`crate::version`])
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "config-schema", derive(JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct VersioningConfig {
+    #[serde(default)]
+    pub scheme: VersioningScheme,
+    /// With `custom-regex`: regular expression a file name (without `.cql`) must
+    /// match; its capture groups define the order
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+    /// With `custom-regex`: capture groups (names or numbers) compared in this
+    /// order, numerically where both values are digits; all groups if empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub order: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+
+#[cfg_attr(feature = "config-schema", derive(JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub enum VersioningScheme {
+    /// `YYYYMMDD_NNN_description`
+    #[default]
+    Timestamp,
+    /// `NNN_description`, a plain counter
+    Sequential,
+    /// Any file name matching `versioning.pattern`
+    CustomRegex,
+}
+
+/// Keyspaces that each get the full set of migrations (`up --all-tenants`)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "config-schema", derive(JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct TenantsConfig {
+    /// Tenant keyspaces, each with its own tracking table
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub keyspaces: Vec<String>,
+    /// Query whose first column lists more tenant keyspaces, run at the start
+    /// of each `--all-tenants` run (e.g. `SELECT keyspace_name FROM system_schema.keyspaces`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub discovery_query: Option<String>,
+    /// Discovered keyspaces must match one of these regular expressions (any if empty)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<String>,
+    /// Discovered keyspaces matching one of these regular expressions are skipped
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude: Vec<String>,
+    /// TOML file with a table of `${var:NAME}` values per tenant keyspace; its
+    /// keyspaces are tenants too
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub manifest: Option<PathBuf>,
+}
+
+/// Replication of keyspaces created by `auto_create_keyspace`
+///
+/// The top-level settings apply everywhere unless the entry of the current
+/// `behavior.environment` in `environments` overrides them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "config-schema", derive(JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct ReplicationConfig {
+    #[serde(default)]
+    pub strategy: ReplicationStrategy,
+    /// Replicas with `SimpleStrategy`
+    #[serde(default = "default_replication_factor")]
+    pub replication_factor: u32,
+    /// Replicas per datacenter with `NetworkTopologyStrategy`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub datacenters: BTreeMap<String, u32>,
+    /// Overrides per `behavior.environment`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub environments: BTreeMap<String, ReplicationProfile>,
+}
+
+impl Default for ReplicationConfig {
+    fn default() -> Self {
+        Self {
+            strategy: ReplicationStrategy::default(),
+            replication_factor: default_replication_factor(),
+            datacenters: BTreeMap::new(),
+            environments: BTreeMap::new(),
+        }
+    }
+}
+
+/// Replication settings of one environment; unset fields keep the top-level value
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "config-schema", derive(JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct ReplicationProfile {
+    /// `false` refuses to create missing keyspaces in this environment, even
+    /// with `behavior.auto_create_keyspace`
+    #[serde(default)]
+    pub auto_create: Option<bool>,
+    #[serde(default)]
+    pub strategy: Option<ReplicationStrategy>,
+    #[serde(default)]
+    pub replication_factor: Option<u32>,
+    #[serde(default)]
+    pub datacenters: Option<BTreeMap<String, u32>>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+
+#[cfg_attr(feature = "config-schema", derive(JsonSchema))]
+pub enum ReplicationStrategy {
+    #[default]
+    SimpleStrategy,
+    NetworkTopologyStrategy,
+}
+
+/// Replication a keyspace is created with in the current environment
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyspaceReplication {
+    pub strategy: ReplicationStrategy,
+    pub replication_factor: u32,
+    pub datacenters: BTreeMap<String, u32>,
+}
+
+impl ReplicationConfig {
+    /// Replication of keyspaces created in `environment`
+    pub fn resolve(&self, environment: Option<&str>) -> KeyspaceReplication {
+        let profile = self.profile(environment);
+        KeyspaceReplication {
+            strategy: profile.strategy.unwrap_or(self.strategy),
+            replication_factor: profile.replication_factor.unwrap_or(self.replication_factor),
+            datacenters: profile.datacenters.clone().unwrap_or_else(|| self.datacenters.clone()),
+        }
+    }
+
+    /// Whether missing keyspaces may be created implicitly in `environment`
+    pub fn allows_auto_create(&self, environment: Option<&str>) -> bool {
+        self.profile(environment).auto_create != Some(false)
+    }
+
+    fn profile(&self, environment: Option<&str>) -> &ReplicationProfile {
+        static NONE: ReplicationProfile = ReplicationProfile {
+            auto_create: None,
+            strategy: None,
+            replication_factor: None,
+            datacenters: None,
+        };
+        environment
+            .and_then(|environment| self.environments.get(environment))
+            .unwrap_or(&NONE)
+    }
+}
+
+impl KeyspaceReplication {
+    /// The map of the `WITH REPLICATION` clause
+    pub fn to_cql(&self) -> String {
+        let options: Vec<String> = self
+            .options()
+            .iter()
+            .map(|(key, value)| format!(", '{}': {}", key.replace('\'', "''"), value))
+            .collect();
+        format!("{{'class': '{}'{}}}", self.class(), options.concat())
+    }
+
+    fn class(&self) -> &'static str {
+        match self.strategy {
+            ReplicationStrategy::SimpleStrategy => "SimpleStrategy",
+            ReplicationStrategy::NetworkTopologyStrategy => "NetworkTopologyStrategy",
+        }
+    }
+
+    /// Replication options besides the class
+    fn options(&self) -> BTreeMap<String, u32> {
+        match self.strategy {
+            ReplicationStrategy::SimpleStrategy => {
+                BTreeMap::from([("replication_factor".to_string(), self.replication_factor)])
+            }
+            ReplicationStrategy::NetworkTopologyStrategy => self.datacenters.clone(),
+        }
+    }
+
+    /// Whether a keyspace's `replication` from `system_schema.keyspaces` is this one
+    pub fn matches(&self, live: &BTreeMap<String, String>) -> bool {
+        let class = live.get("class").map(|class| class.rsplit('.').next().unwrap_or(class));
+        let options: BTreeMap<String, u32> = live
+            .iter()
+            .filter(|(key, _)| key.as_str() != "class")
+            .filter_map(|(key, value)| value.parse().ok().map(|value| (key.clone(), value)))
+            .collect();
+        class == Some(self.class()) && options == self.options()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+
+#[cfg_attr(feature = "config-schema", derive(JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum ConsistencyLevel {
+    One,
+    Two,
+    Three,
+    Quorum,
+    All,
+    LocalQuorum,
+    EachQuorum,
+    LocalOne,
+}
+
+/// How configured keyspace and table names are interpreted
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "config-schema", derive(JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum IdentifierQuoting {
+    /// As in CQL: case-insensitive unless written quoted (`keyspace = '"MyApp"'`)
+    #[default]
+    Auto,
+    /// Every name is case-sensitive exactly as written, and always quoted
+    Always,
+}
+
+/// How strictly DOWN sections are validated by `verify` and `lint`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "config-schema", derive(JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum DownValidation {
+    /// Report missing or empty DOWN sections as warnings
+    #[default]
+    Warn,
+    /// DOWN sections without statements are errors; missing ones are warnings
+    Error,
+    /// Every migration must have a DOWN section with at least one statement
+    Required,
 }
 
 // Default value functions
@@ -58,6 +664,10 @@ fn default_migrations_dir() -> PathBuf {
     PathBuf::from("./migrations")
 }
 
+fn default_backups_dir() -> PathBuf {
+    PathBuf::from("./backups")
+}
+
 fn default_table_name() -> String {
     "schema_migrations".to_string()
 }
@@ -74,9 +684,126 @@ fn default_timeout() -> u64 {
     30
 }
 
+fn default_wasm_fuel() -> u64 {
+    1_000_000_000
+}
+
+fn default_wasm_memory_mb() -> u64 {
+    64
+}
+
+fn default_approval_secret_env() -> String {
+    "DB_MIGRATE_APPROVAL_SECRET".to_string()
+}
+
+fn default_approval_timeout() -> u64 {
+    3600
+}
+
+fn default_approval_poll_interval() -> u64 {
+    15
+}
+
+fn default_replication_factor() -> u32 {
+    1
+}
+
+fn default_empty_tracking_guard() -> usize {
+    3
+}
+
+fn default_max_file_size_kb() -> u64 {
+    5 * 1024
+}
+
+fn default_max_statements() -> usize {
+    1000
+}
+
+fn default_max_migrations_per_run() -> usize {
+    100
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        Self {
+            max_file_size_kb: default_max_file_size_kb(),
+            max_statements: default_max_statements(),
+            max_migrations_per_run: default_max_migrations_per_run(),
+            enforce: default_false(),
+        }
+    }
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_true(),
+            retention_days: None,
+        }
+    }
+}
+
+impl Default for BackupsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_true(),
+            directory: default_backups_dir(),
+        }
+    }
+}
+
+impl Default for ApprovalConfig {
+    fn default() -> Self {
+        Self {
+            environments: Vec::new(),
+            webhook_url: None,
+            secret_env: default_approval_secret_env(),
+            timeout_seconds: default_approval_timeout(),
+            poll_interval_seconds: default_approval_poll_interval(),
+        }
+    }
+}
+
+impl Default for TlsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_false(),
+            ca_cert: None,
+            client_cert: None,
+            client_key: None,
+            client_key_password: None,
+            verify_peer: default_true(),
+        }
+    }
+}
+
+impl TlsConfig {
+    /// Check that the files of an enabled configuration exist and pair up
+    fn validate(&self) -> Result<()> {
+        if !self.enabled {
+            return Ok(());
+        }
+        if self.client_cert.is_some() != self.client_key.is_some() {
+            anyhow::bail!("TLS client_cert and client_key must be specified together");
+        }
+
+        for path in [&self.ca_cert, &self.client_cert, &self.client_key]
+            .into_iter()
+            .flatten()
+        {
+            if !path.exists() {
+                anyhow::bail!("TLS file '{}' does not exist", path.display());
+            }
+        }
+        Ok(())
+    }
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self {
+            required_version: None,
             database: DatabaseConfig {
                 hosts: vec!["127.0.0.1".to_string()],
                 keyspace: "migrations_test".to_string(),
@@ -84,17 +811,46 @@ impl Default for Config {
                 password: String::new(),
                 port: default_port(),
                 datacenter: default_datacenter(),
+                tls: TlsConfig::default(),
             },
             migrations: MigrationsConfig {
                 directory: default_migrations_dir(),
                 table_name: default_table_name(),
+                tracking_keyspace: None,
+                snapshot_file: None,
+                ignore: Vec::new(),
             },
             behavior: BehaviorConfig {
                 auto_create_keyspace: default_true(),
                 verify_checksums: default_true(),
                 allow_destructive: default_false(),
+                allow_repair: default_false(),
                 timeout_seconds: default_timeout(),
+                down_validation: DownValidation::default(),
+                stamp_table_comments: default_false(),
+                read_only: default_false(),
+                store_down_content: default_false(),
+                environment: None,
+                empty_tracking_guard: default_empty_tracking_guard(),
+                identifier_quoting: IdentifierQuoting::default(),
             },
+            lint: LintConfig::default(),
+            limits: LimitsConfig::default(),
+            secrets: SecretsConfig::default(),
+            templates: TemplatesConfig::default(),
+            history: HistoryConfig::default(),
+            backups: BackupsConfig::default(),
+            approval: ApprovalConfig::default(),
+            reads: ReadsConfig::default(),
+            output: OutputConfig::default(),
+            replication: ReplicationConfig::default(),
+            versioning: VersioningConfig::default(),
+            tenants: TenantsConfig::default(),
+            clusters: Vec::new(),
+            plugins: Vec::new(),
+            wasm_plugins: Vec::new(),
+            policy: Policy::default(),
+            variables: BTreeMap::new(),
         }
     }
 }
@@ -103,8 +859,8 @@ impl Config {
     /// Load configuration from file and environment variables
     pub async fn load<P: AsRef<Path>>(config_path: P) -> Result<Self> {
         let mut config = if config_path.as_ref().exists() {
-            let content = fs::read_to_string(config_path).await?;
-            toml::from_str::<Config>(&content)?
+            let content = fs::read_to_string(&config_path).await?;
+            Self::parse(config_path.as_ref(), &content)?
         } else {
             tracing::info!("Config file not found, using defaults");
             Config::default()
@@ -112,13 +868,60 @@ impl Config {
 
         // Override with environment variables if present
         config.override_from_env();
+        config.normalize_paths();
+        config.apply_identifier_quoting();
 
         // Validate configuration
         config.validate()?;
+        config.policy = Policy::load(config.lint.policy_file.as_deref())?;
 
         Ok(config)
     }
 
+    /// Parse a TOML configuration, reporting where in the file an error occurred
+    pub fn parse(path: &Path, content: &str) -> Result<Self> {
+        // Checked first, so that a file written for a newer release is rejected
+        // for its version rather than for keys this one does not know
+        let table = content.parse::<toml::Table>().ok();
+        if let Some(requirement) = table.as_ref().and_then(|t| t.get("required_version")?.as_str()) {
+            compat::check_required_version(requirement)
+                .map_err(|e| anyhow::anyhow!("Invalid configuration in {}: {}", path.display(), e))?;
+        }
+
+        toml::from_str::<Config>(content).map_err(|e| {
+            let Some(span) = e.span() else {
+                return anyhow::anyhow!("Invalid configuration in {}: {}", path.display(), e.message());
+            };
+
+            let before = &content[..span.start];
+            let line = before.matches('\n').count() + 1;
+            let column = before.len() - before.rfind('\n').map_or(0, |i| i + 1) + 1;
+            let section = before
+                .lines()
+                .rev()
+                .map(str::trim)
+                .find(|l| l.starts_with('[') && l.ends_with(']'))
+                .map(|header| format!(" in {}", header))
+                .unwrap_or_default();
+
+            anyhow::anyhow!(
+                "Invalid configuration in {} at line {}, column {}{}: {}",
+                path.display(),
+                line,
+                column,
+                section,
+                e.message()
+            )
+        })
+    }
+
+    /// JSON Schema describing the configuration file format
+    #[cfg(feature = "config-schema")]
+    pub fn json_schema() -> serde_json::Value {
+        serde_json::to_value(schemars::schema_for!(Config))
+            .expect("generated schema is valid JSON")
+    }
+
     /// Override configuration values from environment variables
     fn override_from_env(&mut self) {
         if let Ok(hosts) = std::env::var("DB_MIGRATE_HOSTS") {
@@ -140,6 +943,26 @@ impl Config {
             self.database.password = password;
         }
 
+        if let Ok(tls_enabled) = std::env::var("DB_MIGRATE_TLS_ENABLED") {
+            self.database.tls.enabled = tls_enabled.parse().unwrap_or(false);
+        }
+
+        if let Ok(ca_cert) = std::env::var("DB_MIGRATE_TLS_CA_CERT") {
+            self.database.tls.ca_cert = Some(PathBuf::from(ca_cert));
+        }
+
+        if let Ok(client_cert) = std::env::var("DB_MIGRATE_TLS_CLIENT_CERT") {
+            self.database.tls.client_cert = Some(PathBuf::from(client_cert));
+        }
+
+        if let Ok(client_key) = std::env::var("DB_MIGRATE_TLS_CLIENT_KEY") {
+            self.database.tls.client_key = Some(PathBuf::from(client_key));
+        }
+
+        if let Ok(key_password) = std::env::var("DB_MIGRATE_TLS_CLIENT_KEY_PASSWORD") {
+            self.database.tls.client_key_password = Some(key_password);
+        }
+
         if let Ok(migrations_dir) = std::env::var("DB_MIGRATE_MIGRATIONS_DIR") {
             self.migrations.directory = PathBuf::from(migrations_dir);
         }
@@ -148,6 +971,10 @@ impl Config {
             self.migrations.table_name = table_name;
         }
 
+        if let Ok(tracking_keyspace) = std::env::var("DB_MIGRATE_TRACKING_KEYSPACE") {
+            self.migrations.tracking_keyspace = Some(tracking_keyspace);
+        }
+
         if let Ok(auto_create) = std::env::var("DB_MIGRATE_AUTO_CREATE_KEYSPACE") {
             self.behavior.auto_create_keyspace = auto_create.parse().unwrap_or(true);
         }
@@ -159,6 +986,84 @@ impl Config {
         if let Ok(allow_destructive) = std::env::var("DB_MIGRATE_ALLOW_DESTRUCTIVE") {
             self.behavior.allow_destructive = allow_destructive.parse().unwrap_or(false);
         }
+
+        if let Ok(read_only) = std::env::var("DB_MIGRATE_READ_ONLY") {
+            self.behavior.read_only = read_only.parse().unwrap_or(false);
+        }
+
+        if let Ok(environment) = std::env::var("DB_MIGRATE_ENV") {
+            self.behavior.environment = Some(environment).filter(|e| !e.is_empty());
+        }
+
+        if let Ok(secrets_dir) = std::env::var("DB_MIGRATE_SECRETS_DIR") {
+            self.secrets.directory = Some(PathBuf::from(secrets_dir));
+        }
+
+        if let Ok(datacenter) = std::env::var("DB_MIGRATE_READS_DATACENTER") {
+            self.reads.datacenter = Some(datacenter).filter(|dc| !dc.is_empty());
+        }
+
+        if let Ok(emoji) = std::env::var("DB_MIGRATE_EMOJI") {
+            self.output.emoji = emoji.parse().unwrap_or(true);
+        }
+
+        if let Ok(run_report) = std::env::var("DB_MIGRATE_RUN_REPORT") {
+            self.output.run_report = Some(PathBuf::from(run_report)).filter(|path| !path.as_os_str().is_empty());
+        }
+    }
+
+    /// Make paths written on another operating system usable here
+    fn normalize_paths(&mut self) {
+        self.migrations.directory = portable_path(&self.migrations.directory);
+        self.backups.directory = portable_path(&self.backups.directory);
+        for path in [
+            &mut self.migrations.snapshot_file,
+            &mut self.templates.file,
+            &mut self.secrets.directory,
+            &mut self.database.tls.ca_cert,
+            &mut self.database.tls.client_cert,
+            &mut self.database.tls.client_key,
+            &mut self.lint.policy_file,
+            &mut self.output.messages,
+            &mut self.output.run_report,
+            &mut self.tenants.manifest,
+        ]
+        .into_iter()
+        .flatten()
+        .chain(
+            self.clusters
+                .iter_mut()
+                .filter_map(|cluster| cluster.tls.as_mut())
+                .flat_map(|tls| [&mut tls.ca_cert, &mut tls.client_cert, &mut tls.client_key])
+                .flatten(),
+        )
+        .chain(self.plugins.iter_mut().map(|plugin| &mut plugin.command))
+        .chain(self.wasm_plugins.iter_mut().map(|plugin| &mut plugin.module))
+        {
+            *path = portable_path(path);
+        }
+    }
+
+    /// Quote every configured name with `identifier_quoting = "always"`
+    fn apply_identifier_quoting(&mut self) {
+        if self.behavior.identifier_quoting != IdentifierQuoting::Always {
+            return;
+        }
+
+        for name in [
+            Some(&mut self.database.keyspace),
+            Some(&mut self.migrations.table_name),
+            self.migrations.tracking_keyspace.as_mut(),
+        ]
+        .into_iter()
+        .flatten()
+        .chain(self.clusters.iter_mut().filter_map(|cluster| cluster.keyspace.as_mut()))
+        .chain(self.tenants.keyspaces.iter_mut())
+        {
+            if !name.is_empty() && !name.starts_with('"') {
+                *name = format!("\"{}\"", name);
+            }
+        }
     }
 
     /// Validate configuration values
@@ -175,6 +1080,149 @@ impl Config {
             anyhow::bail!("Migrations table name cannot be empty");
         }
 
+        if self.migrations.tracking_keyspace.as_deref() == Some("") {
+            anyhow::bail!("Tracking keyspace cannot be empty when specified");
+        }
+
+        // Names are formatted into statements, so they must be plain identifiers
+        let mut identifiers = vec![
+            ("database.keyspace", self.database.keyspace.clone()),
+            ("migrations.table_name", self.migrations.table_name.clone()),
+            (
+                "migrations.table_name",
+                cql::suffixed_identifier(&self.migrations.table_name, HISTORY_TABLE_SUFFIX),
+            ),
+            (
+                "migrations.table_name",
+                cql::suffixed_identifier(&self.migrations.table_name, LOCK_TABLE_SUFFIX),
+            ),
+            (
+                "migrations.table_name",
+                cql::suffixed_identifier(&self.migrations.table_name, CHECKPOINT_TABLE_SUFFIX),
+            ),
+        ];
+        identifiers.extend(
+            self.migrations
+                .tracking_keyspace
+                .clone()
+                .map(|keyspace| ("migrations.tracking_keyspace", keyspace)),
+        );
+        identifiers.extend(self.clusters.iter().filter_map(|cluster| {
+            cluster.keyspace.clone().map(|keyspace| ("clusters.keyspace", keyspace))
+        }));
+        identifiers.extend(
+            self.tenants
+                .keyspaces
+                .iter()
+                .map(|keyspace| ("tenants.keyspaces", keyspace.clone())),
+        );
+        for (setting, name) in identifiers {
+            if let Err(e) = cql::validate_identifier(&name) {
+                anyhow::bail!("Invalid {}: {}", setting, e);
+            }
+        }
+
+        if let Some(query) = &self.tenants.discovery_query {
+            if !query.trim_start().get(..6).is_some_and(|verb| verb.eq_ignore_ascii_case("select")) {
+                anyhow::bail!("tenants.discovery_query must be a SELECT statement");
+            }
+        }
+        for pattern in self.tenants.include.iter().chain(&self.tenants.exclude) {
+            if let Err(e) = regex::Regex::new(pattern) {
+                anyhow::bail!("Invalid tenants pattern '{}': {}", pattern, e);
+            }
+        }
+
+        if self.history.retention_days == Some(0) {
+            anyhow::bail!("history.retention_days must be at least 1");
+        }
+
+        if let Some(url) = &self.approval.webhook_url {
+            if !url.starts_with("https://") && !url.starts_with("http://") {
+                anyhow::bail!("approval.webhook_url must be an http(s) URL, got '{}'", url);
+            }
+        }
+        if self.approval.poll_interval_seconds == 0 {
+            anyhow::bail!("approval.poll_interval_seconds must be at least 1");
+        }
+
+        let environments = std::iter::once((None, self.replication.resolve(None)))
+            .chain(self.replication.environments.keys().map(|name| {
+                (Some(name.as_str()), self.replication.resolve(Some(name)))
+            }));
+        for (environment, replication) in environments {
+            let setting = match environment {
+                Some(name) => format!("replication.environments.{}", name),
+                None => "replication".to_string(),
+            };
+            match replication.strategy {
+                ReplicationStrategy::SimpleStrategy if replication.replication_factor == 0 => {
+                    anyhow::bail!("{}: replication_factor must be at least 1", setting);
+                }
+                ReplicationStrategy::NetworkTopologyStrategy if replication.datacenters.is_empty() => {
+                    anyhow::bail!("{}: NetworkTopologyStrategy needs at least one entry in datacenters", setting);
+                }
+                _ => {}
+            }
+        }
+
+        if let Err(e) = self.version_scheme() {
+            anyhow::bail!("{}", e);
+        }
+
+        for pattern in &self.migrations.ignore {
+            if let Err(e) = glob::Pattern::new(pattern) {
+                anyhow::bail!("Invalid migrations.ignore pattern '{}': {}", pattern, e);
+            }
+        }
+
+        self.database.tls.validate()?;
+
+        let mut cluster_names = std::collections::HashSet::new();
+        for cluster in &self.clusters {
+            if cluster.hosts.is_empty() {
+                anyhow::bail!("Cluster '{}' must specify at least one host", cluster.name);
+            }
+            if let Some(tls) = &cluster.tls {
+                tls.validate()
+                    .map_err(|e| anyhow::anyhow!("Cluster '{}': {}", cluster.name, e))?;
+            }
+            if !cluster_names.insert(&cluster.name) {
+                anyhow::bail!("Duplicate cluster name '{}'", cluster.name);
+            }
+        }
+
+        let mut plugin_names = std::collections::HashSet::new();
+        for plugin in &self.plugins {
+            if plugin.name.is_empty() || plugin.command.as_os_str().is_empty() {
+                anyhow::bail!("Every plugin must have a name and a command");
+            }
+            if !plugin_names.insert(&plugin.name) {
+                anyhow::bail!("Duplicate plugin name '{}'", plugin.name);
+            }
+        }
+
+        if self.approval.webhook_url.is_some() && !cfg!(feature = "webhooks") {
+            anyhow::bail!("approval.webhook_url is set but db-migrate was built without the webhooks feature");
+        }
+        if !self.plugins.is_empty() && !cfg!(feature = "plugins") {
+            anyhow::bail!("plugins are configured but db-migrate was built without the plugins feature");
+        }
+        if !self.wasm_plugins.is_empty() && !cfg!(feature = "wasm-plugins") {
+            anyhow::bail!("wasm_plugins are configured but db-migrate was built without the wasm-plugins feature");
+        }
+        for plugin in &self.wasm_plugins {
+            if plugin.name.is_empty() || plugin.capabilities.is_empty() {
+                anyhow::bail!("Every WASM plugin must have a name and at least one capability");
+            }
+            if !plugin_names.insert(&plugin.name) {
+                anyhow::bail!("Duplicate plugin name '{}'", plugin.name);
+            }
+            if !plugin.module.exists() {
+                anyhow::bail!("WASM module '{}' does not exist", plugin.module.display());
+            }
+        }
+
         // Validate that migrations directory exists or can be created
         if !self.migrations.directory.exists() {
             if let Some(parent) = self.migrations.directory.parent() {
@@ -190,6 +1238,115 @@ impl Config {
         Ok(())
     }
 
+    /// How migration versions are read from file names and ordered (`[versioning]`)
+    pub fn version_scheme(&self) -> Result<VersionScheme, MigrationError> {
+        VersionScheme::from_config(&self.versioning)
+            .map_err(|e| MigrationError::ConfigError(format!("Invalid versioning: {}", e)))
+    }
+
+    /// Build the effective configuration for one of the configured clusters
+    pub fn for_cluster(&self, cluster: &ClusterConfig) -> Config {
+        let mut config = self.clone();
+        config.database.hosts = cluster.hosts.clone();
+        if let Some(keyspace) = &cluster.keyspace {
+            config.database.keyspace = keyspace.clone();
+        }
+        if let Some(username) = &cluster.username {
+            config.database.username = username.clone();
+        }
+        if let Some(password) = &cluster.password {
+            config.database.password = password.clone();
+        }
+        if let Some(tls) = &cluster.tls {
+            config.database.tls = tls.clone();
+        }
+        config.clusters.clear();
+        config
+    }
+
+    /// Build the effective configuration for one tenant keyspace
+    ///
+    /// Each tenant tracks its migrations in its own keyspace, since one tracking
+    /// table cannot tell the tenants' records apart.
+    pub fn for_tenant(&self, keyspace: &str) -> Config {
+        let mut config = self.clone();
+        config.database.keyspace = keyspace.to_string();
+        config.migrations.tracking_keyspace = None;
+        config.tenants = TenantsConfig::default();
+        config.clusters.clear();
+        config
+    }
+
+    /// A copy with passwords replaced, safe to write to files and tickets
+    pub fn redacted(&self) -> Self {
+        const REDACTED: &str = "<redacted>";
+
+        let mut config = self.clone();
+        if !config.database.password.is_empty() {
+            config.database.password = REDACTED.to_string();
+        }
+        if config.database.tls.client_key_password.is_some() {
+            config.database.tls.client_key_password = Some(REDACTED.to_string());
+        }
+        for cluster in &mut config.clusters {
+            if cluster.password.is_some() {
+                cluster.password = Some(REDACTED.to_string());
+            }
+            if let Some(tls) = cluster.tls.as_mut().filter(|tls| tls.client_key_password.is_some()) {
+                tls.client_key_password = Some(REDACTED.to_string());
+            }
+        }
+        config
+    }
+
+    /// Keyspace holding the migrations tracking table
+    pub fn tracking_keyspace(&self) -> &str {
+        self.migrations
+            .tracking_keyspace
+            .as_deref()
+            .unwrap_or(&self.database.keyspace)
+    }
+
+    /// Keyspace-qualified name of the tracking table, as formatted into statements
+    pub fn tracking_table(&self) -> String {
+        format!("{}.{}", self.tracking_keyspace(), self.migrations.table_name)
+    }
+
+    /// Keyspace-qualified name of the history table (see [`crate::history`])
+    pub fn history_table(&self) -> String {
+        format!(
+            "{}.{}",
+            self.tracking_keyspace(),
+            cql::suffixed_identifier(&self.migrations.table_name, HISTORY_TABLE_SUFFIX)
+        )
+    }
+
+    /// Keyspace-qualified name of the lock table (see [`crate::lock`])
+    pub fn lock_table(&self) -> String {
+        format!(
+            "{}.{}",
+            self.tracking_keyspace(),
+            cql::suffixed_identifier(&self.migrations.table_name, LOCK_TABLE_SUFFIX)
+        )
+    }
+
+    /// Keyspace-qualified name of the checkpoint table (see [`crate::checkpoint`])
+    pub fn checkpoint_table(&self) -> String {
+        format!(
+            "{}.{}",
+            self.tracking_keyspace(),
+            cql::suffixed_identifier(&self.migrations.table_name, CHECKPOINT_TABLE_SUFFIX)
+        )
+    }
+
+    /// Path of the applied-migrations snapshot (defaults to `<directory>/.applied.json`)
+    pub fn snapshot_path(&self) -> PathBuf {
+        self.migrations
+            .snapshot_file
+            .clone()
+            .unwrap_or_else(|| self.migrations.directory.join(".applied.json"))
+    }
+
     /// Get the full connection string for ScyllaDB
     pub fn connection_uri(&self) -> String {
         format!(
@@ -206,4 +1363,170 @@ impl Config {
         fs::write(path, content).await?;
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_config_round_trips() {
+        let content = toml::to_string_pretty(&Config::default()).unwrap();
+        let config = Config::parse(Path::new("db-migrate.toml"), &content).unwrap();
+        assert_eq!(config.database.keyspace, "migrations_test");
+    }
+
+    #[test]
+    fn test_parse_error_locations() {
+        let content = "[database]\nhosts = [\"127.0.0.1\"]\nkeyspace = \"app\"\nhost = \"typo\"\n\n[migrations]\n\n[behavior]\n";
+        let error = Config::parse(Path::new("db-migrate.toml"), content)
+            .unwrap_err()
+            .to_string();
+        assert!(error.starts_with("Invalid configuration in db-migrate.toml at line 4, column 1 in [database]: unknown field `host`"), "{}", error);
+
+        let content = "[database]\nhosts = [\"127.0.0.1\"]\nkeyspace = \"app\"\nport = \"9042\"\n\n[migrations]\n\n[behavior]\n";
+        let error = Config::parse(Path::new("db-migrate.toml"), content)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("at line 4, column 8 in [database]"), "{}", error);
+    }
+
+    #[test]
+    fn test_required_version() {
+        let content = "required_version = \">=0.1\"\n[database]\nhosts = [\"127.0.0.1\"]\nkeyspace = \"app\"\n\n[migrations]\n\n[behavior]\n";
+        let config = Config::parse(Path::new("db-migrate.toml"), content).unwrap();
+        assert_eq!(config.required_version.as_deref(), Some(">=0.1"));
+
+        // A newer file is rejected for its version before its unknown keys
+        let content = "required_version = \">=99\"\n[database]\nhosts = [\"127.0.0.1\"]\nkeyspace = \"app\"\n\n[migrations]\n\n[behavior]\n\n[future]\n";
+        let error = Config::parse(Path::new("db-migrate.toml"), content)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("does not satisfy required_version \">=99\""), "{}", error);
+    }
+
+    #[test]
+    fn test_ignored_migrations() {
+        let migrations = MigrationsConfig {
+            ignore: vec!["20240101_001_*".to_string()],
+            ..Config::default().migrations
+        };
+        let file = MigrationFile::for_test;
+
+        assert!(migrations.is_ignored(&file("20240101_001_abandoned", "")));
+        assert!(!migrations.is_ignored(&file("20240101_002_kept", "")));
+        assert!(migrations.is_ignored(&file("20240102_001_superseded", "-- db-migrate:skip")));
+    }
+
+    #[test]
+    #[cfg(feature = "config-schema")]
+    fn test_json_schema() {
+        let schema = Config::json_schema();
+        assert_eq!(schema["additionalProperties"], false);
+        assert!(schema["properties"]["database"].is_object());
+        assert!(schema["definitions"]["DownValidation"].is_object());
+    }
+
+    #[test]
+    fn test_reads_config() {
+        let content = "[database]\nhosts = [\"127.0.0.1\"]\nkeyspace = \"app\"\n\n[migrations]\n\n[behavior]\n\n[reads]\nconsistency = \"local_one\"\ndatacenter = \"us-west\"\n";
+        let config = Config::parse(Path::new("db-migrate.toml"), content).unwrap();
+        assert_eq!(config.reads.consistency, Some(ConsistencyLevel::LocalOne));
+        assert_eq!(config.reads.datacenter.as_deref(), Some("us-west"));
+
+        let invalid = content.replace("local_one", "local_two");
+        assert!(Config::parse(Path::new("db-migrate.toml"), &invalid).is_err());
+    }
+
+    #[test]
+    fn test_identifier_quoting() {
+        let content = "[database]\nhosts = [\"127.0.0.1\"]\nkeyspace = '\"MyApp\"'\n\n[migrations]\ntable_name = \"Schema_Migrations\"\n\n[behavior]\n";
+        let mut config = Config::parse(Path::new("db-migrate.toml"), content).unwrap();
+        config.apply_identifier_quoting();
+        config.validate().unwrap();
+        assert_eq!(config.tracking_table(), "\"MyApp\".Schema_Migrations");
+        assert_eq!(config.history_table(), "\"MyApp\".Schema_Migrations_history");
+
+        config.behavior.identifier_quoting = IdentifierQuoting::Always;
+        config.migrations.tracking_keyspace = Some("Ops".to_string());
+        config.apply_identifier_quoting();
+        config.validate().unwrap();
+        assert_eq!(config.database.keyspace, "\"MyApp\"");
+        assert_eq!(config.tracking_table(), "\"Ops\".\"Schema_Migrations\"");
+        assert_eq!(config.history_table(), "\"Ops\".\"Schema_Migrations_history\"");
+        assert_eq!(config.lock_table(), "\"Ops\".\"Schema_Migrations_lock\"");
+
+        config.database.keyspace = "my app".to_string();
+        assert!(config.validate().is_err());
+        config.database.keyspace = "\"my\"\"app\"".to_string();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_replication_profiles() {
+        let content = "[database]\nhosts = [\"127.0.0.1\"]\nkeyspace = \"app\"\n\n[migrations]\n\n[behavior]\n\n[replication.environments.prod]\nauto_create = false\n\n[replication.environments.staging]\nstrategy = \"NetworkTopologyStrategy\"\ndatacenters = { \"us-east\" = 3, \"eu-west\" = 2 }\n";
+        let mut config = Config::parse(Path::new("db-migrate.toml"), content).unwrap();
+        config.validate().unwrap();
+
+        let replication = &config.replication;
+        assert_eq!(
+            replication.resolve(Some("dev")).to_cql(),
+            "{'class': 'SimpleStrategy', 'replication_factor': 1}"
+        );
+        assert_eq!(
+            replication.resolve(Some("staging")).to_cql(),
+            "{'class': 'NetworkTopologyStrategy', 'eu-west': 2, 'us-east': 3}"
+        );
+        assert!(!replication.allows_auto_create(Some("prod")));
+        assert!(replication.allows_auto_create(Some("staging")));
+
+        let live = BTreeMap::from([
+            ("class".to_string(), "org.apache.cassandra.locator.NetworkTopologyStrategy".to_string()),
+            ("eu-west".to_string(), "2".to_string()),
+            ("us-east".to_string(), "3".to_string()),
+        ]);
+        assert!(replication.resolve(Some("staging")).matches(&live));
+        assert!(!replication.resolve(Some("dev")).matches(&live));
+
+        config.replication.environments.get_mut("staging").unwrap().datacenters = None;
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_for_cluster() {
+        let content = "[database]\nhosts = [\"127.0.0.1\"]\nkeyspace = \"app\"\n\n[database.tls]\nenabled = true\n\n[migrations]\n\n[behavior]\n\n[[clusters]]\nname = \"eu\"\nhosts = [\"10.1.1.10\"]\nkeyspace = \"app_eu\"\n\n[clusters.tls]\nenabled = true\nverify_peer = false\nclient_key_password = \"secret\"\n\n[[clusters]]\nname = \"us\"\nhosts = [\"10.0.1.10\"]\n";
+        let config = Config::parse(Path::new("db-migrate.toml"), content).unwrap();
+        config.validate().unwrap();
+
+        let eu = config.for_cluster(&config.clusters[0]);
+        assert_eq!(eu.database.hosts, vec!["10.1.1.10"]);
+        assert_eq!(eu.database.keyspace, "app_eu");
+        assert!(!eu.database.tls.verify_peer);
+        assert!(eu.clusters.is_empty());
+
+        // Clusters without their own TLS settings use those of [database]
+        let us = config.for_cluster(&config.clusters[1]);
+        assert!(us.database.tls.enabled && us.database.tls.verify_peer);
+
+        let redacted = config.redacted();
+        assert_eq!(
+            redacted.clusters[0].tls.as_ref().unwrap().client_key_password.as_deref(),
+            Some("<redacted>")
+        );
+    }
+
+    #[test]
+    fn test_tenants() {
+        let content = "[database]\nhosts = [\"127.0.0.1\"]\nkeyspace = \"app\"\n\n[migrations]\ntracking_keyspace = \"ops\"\n\n[behavior]\n\n[tenants]\nkeyspaces = [\"tenant_a\", \"tenant_b\"]\n";
+        let mut config = Config::parse(Path::new("db-migrate.toml"), content).unwrap();
+        config.validate().unwrap();
+
+        let tenant = config.for_tenant("tenant_b");
+        assert_eq!(tenant.database.keyspace, "tenant_b");
+        assert_eq!(tenant.tracking_table(), "tenant_b.schema_migrations");
+        assert!(tenant.tenants.keyspaces.is_empty());
+
+        config.tenants.keyspaces.push("tenant c".to_string());
+        assert!(config.validate().is_err());
+    }
+}
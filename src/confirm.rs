@@ -0,0 +1,92 @@
+//! Confirmation tokens for destructive commands run from scripts
+//!
+//! A destructive command run without confirmation prints a token derived from
+//! the operation, its target (cluster, keyspace, mode) and the tracking records
+//! it would affect. Passing the token back with `--confirm-token` runs the
+//! command. The token stops matching once the target or the records change, and
+//! expires one to two [`TOKEN_VALIDITY_MINUTES`] windows after it was printed.
+
+use crate::utils::calculate_checksum;
+use crate::MigrationRecord;
+use chrono::{DateTime, Utc};
+
+/// Length of the window a token is issued in; it is also accepted in the next one
+pub const TOKEN_VALIDITY_MINUTES: i64 = 15;
+
+/// What a token authorizes
+pub struct ConfirmationScope<'a> {
+    pub operation: &'a str,
+    /// Operation variant, e.g. `drop-keyspace`
+    pub mode: &'a str,
+    pub cluster_name: Option<&'a str>,
+    pub keyspace: &'a str,
+    pub records: &'a [MigrationRecord],
+}
+
+impl ConfirmationScope<'_> {
+    /// Token for the window containing `at`
+    pub fn token(&self, at: DateTime<Utc>) -> String {
+        self.token_for_window(window(at))
+    }
+
+    /// Whether `token` was issued for this scope in the current or previous window
+    pub fn accepts(&self, token: &str, now: DateTime<Utc>) -> bool {
+        let current = window(now);
+        [current, current - 1]
+            .into_iter()
+            .any(|window| self.token_for_window(window) == token.trim())
+    }
+
+    fn token_for_window(&self, window: i64) -> String {
+        let records: Vec<String> = self
+            .records
+            .iter()
+            .map(|record| format!("{}:{}", record.version, record.checksum))
+            .collect();
+        let material = [
+            self.operation,
+            self.mode,
+            self.cluster_name.unwrap_or_default(),
+            self.keyspace,
+            &records.join(","),
+            &window.to_string(),
+        ]
+        .join("\n");
+
+        format!("{}-{}", self.operation, &calculate_checksum(&material)[..12])
+    }
+}
+
+fn window(at: DateTime<Utc>) -> i64 {
+    at.timestamp().div_euclid(TOKEN_VALIDITY_MINUTES * 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+
+    #[test]
+    fn test_confirmation_tokens() {
+        let records = vec![MigrationRecord::for_test("20250101_001_users", Utc::now())];
+        let scope = ConfirmationScope {
+            operation: "reset",
+            mode: "tracking",
+            cluster_name: Some("prod"),
+            keyspace: "app",
+            records: &records,
+        };
+        let issued = Utc.with_ymd_and_hms(2025, 3, 1, 12, 1, 0).unwrap();
+        let token = scope.token(issued);
+        assert!(token.starts_with("reset-"));
+
+        assert!(scope.accepts(&token, issued));
+        assert!(scope.accepts(&token, issued + Duration::minutes(20)));
+        assert!(!scope.accepts(&token, issued + Duration::minutes(40)));
+
+        let other_keyspace = ConfirmationScope { keyspace: "app2", ..scope };
+        assert!(!other_keyspace.accepts(&token, issued));
+        let other_mode = ConfirmationScope { mode: "drop-keyspace", ..other_keyspace };
+        assert!(!other_mode.accepts(&token, issued));
+    }
+}
@@ -0,0 +1,827 @@
+//! Best-effort offline CQL checks
+//!
+//! This is not a full CQL grammar. It tokenizes statements well enough to catch
+//! structural mistakes (unbalanced brackets, unterminated strings, unknown
+//! statements) and to inspect the shapes of common DDL/DML statements.
+
+use std::collections::HashSet;
+
+const STATEMENT_KEYWORDS: &[&str] = &[
+    "ALTER", "APPLY", "BEGIN", "CREATE", "DELETE", "DROP", "GRANT", "INSERT", "LIST", "REVOKE",
+    "SELECT", "TRUNCATE", "UPDATE", "USE",
+];
+
+pub(crate) const NATIVE_TYPES: &[&str] = &[
+    "ascii", "bigint", "blob", "boolean", "counter", "date", "decimal", "double", "duration",
+    "float", "inet", "int", "smallint", "text", "time", "timestamp", "timeuuid", "tinyint", "uuid",
+    "varchar", "varint",
+];
+
+/// Longest keyspace or table name the server accepts
+pub const MAX_IDENTIFIER_LEN: usize = 48;
+
+/// Check that a configured keyspace or table name is a single plain identifier
+///
+/// Unquoted names (a letter followed by letters, digits and underscores) are
+/// case-insensitive; case-sensitive names are quoted, e.g. `"Orders"`. Nothing
+/// else is accepted, so validated names are safe to format into statements.
+pub fn validate_identifier(name: &str) -> Result<(), String> {
+    let (inner, quoted) = match name.strip_prefix('"').and_then(|rest| rest.strip_suffix('"')) {
+        Some(inner) => (inner, true),
+        None => (name, false),
+    };
+
+    let Some(first) = inner.chars().next() else {
+        return Err("must not be empty".to_string());
+    };
+    if !inner.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return Err(format!(
+            "'{}' may only contain letters, digits and underscores",
+            name
+        ));
+    }
+    if !quoted && !first.is_ascii_alphabetic() {
+        return Err(format!("'{}' must start with a letter, or be quoted", name));
+    }
+    if inner.len() > MAX_IDENTIFIER_LEN {
+        return Err(format!(
+            "'{}' is longer than {} characters",
+            name, MAX_IDENTIFIER_LEN
+        ));
+    }
+    Ok(())
+}
+
+/// Quote a name exactly as the server stores it (see `system_schema`)
+pub fn quoted(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Quoted form of a name as written in configuration or a statement, which is
+/// case-sensitive only when already quoted
+pub fn quote_identifier(name: &str) -> String {
+    quoted(&crate::drift::normalize_identifier(name))
+}
+
+/// A name as written in configuration with `suffix` appended, inside its quotes
+/// if it has them
+pub fn suffixed_identifier(name: &str, suffix: &str) -> String {
+    match name.strip_suffix('"') {
+        Some(unterminated) if name.len() > 1 && name.starts_with('"') => {
+            format!("{}{}\"", unterminated, suffix)
+        }
+        _ => format!("{}{}", name, suffix),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    /// Keyword or identifier, possibly keyspace-qualified and/or quoted
+    Word(String),
+    Punct(char),
+    /// String literal as written, quotes included
+    Literal(String),
+}
+
+impl Token {
+    pub fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self, Token::Word(w) if w.eq_ignore_ascii_case(keyword))
+    }
+}
+
+/// A problem found in a single statement
+#[derive(Debug, Clone, PartialEq)]
+pub enum Issue {
+    /// The statement is structurally broken and would be rejected by the server
+    Syntax(String),
+    /// A type name that is neither native nor a known user-defined type
+    UnknownType(String),
+    /// A table or type referenced without a keyspace qualifier
+    Unqualified(String),
+}
+
+/// Tokenize a statement, skipping comments
+pub fn tokenize(statement: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = statement.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+
+        if c.is_whitespace() {
+            i += 1;
+        } else if (c == '-' && next == Some('-')) || (c == '/' && next == Some('/')) {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if c == '/' && next == Some('*') {
+            i += 2;
+            while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                i += 1;
+            }
+            if i >= chars.len() {
+                return Err("Unterminated block comment".to_string());
+            }
+            i += 2;
+        } else if c == '\'' {
+            let start = i;
+            i += 1;
+            loop {
+                match chars.get(i) {
+                    None => return Err("Unterminated string literal".to_string()),
+                    Some('\'') if chars.get(i + 1) == Some(&'\'') => i += 2,
+                    Some('\'') => break,
+                    Some(_) => i += 1,
+                }
+            }
+            i += 1;
+            tokens.push(Token::Literal(chars[start..i].iter().collect()));
+        } else if c == '$' && next == Some('$') {
+            let start = i;
+            i += 2;
+            while i < chars.len() && !(chars[i] == '$' && chars.get(i + 1) == Some(&'$')) {
+                i += 1;
+            }
+            if i >= chars.len() {
+                return Err("Unterminated $$ string literal".to_string());
+            }
+            i += 2;
+            tokens.push(Token::Literal(chars[start..i].iter().collect()));
+        } else if c == '"' || c.is_alphanumeric() || c == '_' {
+            let mut word = String::new();
+            loop {
+                if chars.get(i) == Some(&'"') {
+                    word.push('"');
+                    i += 1;
+                    loop {
+                        match chars.get(i) {
+                            None => return Err("Unterminated quoted identifier".to_string()),
+                            Some('"') if chars.get(i + 1) == Some(&'"') => {
+                                word.push_str("\"\"");
+                                i += 2;
+                            }
+                            Some('"') => break,
+                            Some(&ch) => {
+                                word.push(ch);
+                                i += 1;
+                            }
+                        }
+                    }
+                    word.push('"');
+                    i += 1;
+                } else {
+                    while let Some(&ch) = chars.get(i) {
+                        if ch.is_alphanumeric() || ch == '_' {
+                            word.push(ch);
+                            i += 1;
+                        } else {
+                            break;
+                        }
+                    }
+                }
+
+                // Continue through keyspace qualifiers like ks.table
+                let continues = chars.get(i) == Some(&'.')
+                    && chars
+                        .get(i + 1)
+                        .is_some_and(|ch| *ch == '"' || ch.is_alphanumeric() || *ch == '_');
+                if !continues {
+                    break;
+                }
+                word.push('.');
+                i += 1;
+            }
+            tokens.push(Token::Word(word));
+        } else {
+            tokens.push(Token::Punct(c));
+            i += 1;
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Split a comma-separated list of CQL literals, e.g. `42, 'a, b'`
+pub fn split_literals(list: &str) -> Result<Vec<String>, String> {
+    let mut literals = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    for c in list.chars() {
+        match c {
+            '\'' => {
+                // A doubled quote inside a string toggles twice
+                quoted = !quoted;
+                current.push(c);
+            }
+            ',' if !quoted => literals.push(std::mem::take(&mut current)),
+            _ => current.push(c),
+        }
+    }
+    if quoted {
+        return Err(format!("Unterminated string literal in '{}'", list));
+    }
+    literals.push(current);
+
+    let literals: Vec<String> = literals.iter().map(|literal| literal.trim().to_string()).collect();
+    if literals.iter().any(String::is_empty) {
+        return Err(format!("Empty value in '{}'", list));
+    }
+    Ok(literals)
+}
+
+/// The text of a string literal, or `None` if `literal` is not quoted
+pub fn unquote_literal(literal: &str) -> Option<String> {
+    let inner = literal.strip_prefix('\'')?.strip_suffix('\'')?;
+    Some(inner.replace("''", "'"))
+}
+
+/// Check that brackets are balanced
+fn check_balanced(tokens: &[Token]) -> Result<(), String> {
+    let mut stack = Vec::new();
+
+    for token in tokens {
+        match token {
+            Token::Punct(open @ ('(' | '[' | '{')) => stack.push(*open),
+            Token::Punct(close @ (')' | ']' | '}')) => {
+                let expected = match close {
+                    ')' => '(',
+                    ']' => '[',
+                    _ => '{',
+                };
+                if stack.pop() != Some(expected) {
+                    return Err(format!("Unbalanced '{}'", close));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    match stack.last() {
+        Some(open) => Err(format!("Unclosed '{}'", open)),
+        None => Ok(()),
+    }
+}
+
+/// Names of user-defined types created by a statement, if any
+pub fn created_type_name(statement: &str) -> Option<String> {
+    let tokens = tokenize(statement).ok()?;
+    if tokens.first()?.is_keyword("CREATE") && tokens.get(1)?.is_keyword("TYPE") {
+        return match skip_if_exists(&tokens, 2).and_then(|i| tokens.get(i)) {
+            Some(Token::Word(name)) => Some(unqualified(name).to_lowercase()),
+            _ => None,
+        };
+    }
+    None
+}
+
+/// Whether a statement changes the schema (`CREATE`, `ALTER` or `DROP`)
+pub fn is_schema_change(statement: &str) -> bool {
+    tokenize(statement).ok().and_then(|tokens| tokens.first().cloned()).is_some_and(|token| {
+        token.is_keyword("CREATE") || token.is_keyword("ALTER") || token.is_keyword("DROP")
+    })
+}
+
+/// Whether a statement writes data: `INSERT`, `UPDATE` or `DELETE`
+pub fn is_dml(statement: &str) -> bool {
+    tokenize(statement).ok().and_then(|tokens| tokens.first().cloned()).is_some_and(|token| {
+        token.is_keyword("INSERT") || token.is_keyword("UPDATE") || token.is_keyword("DELETE")
+    })
+}
+
+/// Values an `INSERT`, `UPDATE` or `DELETE` gives the `partition_key` columns, as written
+///
+/// `None` unless every column is set to a single value, e.g. with `IN` or when
+/// a column is missing. Values are compared as text, so `1` and `01` differ.
+pub fn partition_values(tokens: &[Token], partition_key: &[String]) -> Option<Vec<String>> {
+    let keyword = |i: usize, k: &str| tokens.get(i).is_some_and(|t| t.is_keyword(k));
+    let mut values: Vec<(String, String)> = Vec::new();
+
+    if keyword(0, "INSERT") {
+        let open = tokens.iter().position(|t| *t == Token::Punct('('))?;
+        let close = open + tokens[open..].iter().position(|t| *t == Token::Punct(')'))?;
+        let columns = split_top_level(&tokens[open + 1..close]);
+        if !keyword(close + 1, "VALUES") || tokens.get(close + 2) != Some(&Token::Punct('(')) {
+            return None;
+        }
+        let rest = &tokens[close + 3..];
+        let end = rest.iter().rposition(|t| *t == Token::Punct(')'))?;
+        for (column, value) in columns.iter().zip(split_top_level(&rest[..end])) {
+            if let [Token::Word(column)] = column.as_slice() {
+                values.push((crate::drift::normalize_identifier(column), raw(&value)));
+            }
+        }
+    } else if keyword(0, "UPDATE") || keyword(0, "DELETE") {
+        let start = tokens.iter().position(|t| t.is_keyword("WHERE"))? + 1;
+        let end = tokens[start..]
+            .iter()
+            .position(|t| t.is_keyword("IF"))
+            .map_or(tokens.len(), |i| start + i);
+        for condition in tokens[start..end].split(|t| t.is_keyword("AND")) {
+            if let [Token::Word(column), Token::Punct('='), value @ ..] = condition {
+                values.push((crate::drift::normalize_identifier(column), raw(value)));
+            }
+        }
+    } else {
+        return None;
+    }
+
+    partition_key
+        .iter()
+        .map(|column| {
+            values
+                .iter()
+                .find(|(name, value)| name == column && !value.is_empty())
+                .map(|(_, value)| value.clone())
+        })
+        .collect()
+}
+
+/// Split tokens at commas outside brackets
+fn split_top_level(tokens: &[Token]) -> Vec<Vec<Token>> {
+    let mut parts = vec![Vec::new()];
+    let mut depth = 0usize;
+    for token in tokens {
+        match token {
+            Token::Punct('(' | '[' | '{' | '<') => depth += 1,
+            Token::Punct(')' | ']' | '}' | '>') => depth = depth.saturating_sub(1),
+            Token::Punct(',') if depth == 0 => {
+                parts.push(Vec::new());
+                continue;
+            }
+            _ => {}
+        }
+        parts.last_mut().expect("parts is never empty").push(token.clone());
+    }
+    parts
+}
+
+/// Tokens as written, without whitespace
+fn raw(tokens: &[Token]) -> String {
+    tokens
+        .iter()
+        .map(|t| match t {
+            Token::Word(w) | Token::Literal(w) => w.clone(),
+            Token::Punct(c) => c.to_string(),
+        })
+        .collect()
+}
+
+/// Keyspace selected by a `USE` statement, if the statement is one
+pub fn used_keyspace(statement: &str) -> Option<String> {
+    let tokens = tokenize(statement).ok()?;
+    if !tokens.first()?.is_keyword("USE") {
+        return None;
+    }
+    word_at(&tokens, 1)
+}
+
+/// Whether a `CREATE`/`ALTER ROLE` (or `USER`) statement sets a password
+pub fn sets_password(statement: &str) -> bool {
+    let Ok(tokens) = tokenize(statement) else {
+        return false;
+    };
+    let keyword = |i: usize, k: &str| tokens.get(i).is_some_and(|t| t.is_keyword(k));
+
+    (keyword(0, "CREATE") || keyword(0, "ALTER"))
+        && (keyword(1, "ROLE") || keyword(1, "USER"))
+        && tokens.iter().any(|t| t.is_keyword("PASSWORD"))
+}
+
+/// Validate a single statement
+pub fn check_statement(
+    statement: &str,
+    user_types: &HashSet<String>,
+    require_qualified: bool,
+) -> Vec<Issue> {
+    let tokens = match tokenize(statement) {
+        Ok(tokens) => tokens,
+        Err(e) => return vec![Issue::Syntax(e)],
+    };
+
+    if tokens.is_empty() {
+        return Vec::new();
+    }
+
+    if let Err(e) = check_balanced(&tokens) {
+        return vec![Issue::Syntax(e)];
+    }
+
+    let first = match &tokens[0] {
+        Token::Word(word) => word.to_uppercase(),
+        _ => String::new(),
+    };
+    if !STATEMENT_KEYWORDS.contains(&first.as_str()) {
+        return vec![Issue::Syntax(format!(
+            "Unrecognized statement starting with '{}'",
+            statement.split_whitespace().next().unwrap_or_default()
+        ))];
+    }
+
+    let mut issues = Vec::new();
+
+    if require_qualified {
+        if let Some(name) = target_object(&tokens) {
+            if !name.contains('.') {
+                issues.push(Issue::Unqualified(name));
+            }
+        }
+    }
+
+    for type_tokens in column_types(&tokens) {
+        if let Some(issue) = check_type(&type_tokens, user_types) {
+            issues.push(issue);
+        }
+    }
+
+    issues
+}
+
+/// Index after an optional `IF [NOT] EXISTS` starting at `i`
+pub fn skip_if_exists(tokens: &[Token], i: usize) -> Option<usize> {
+    if tokens.get(i)?.is_keyword("IF") {
+        if tokens.get(i + 1)?.is_keyword("NOT") {
+            Some(i + 3)
+        } else {
+            Some(i + 2)
+        }
+    } else {
+        Some(i)
+    }
+}
+
+fn word_at(tokens: &[Token], i: usize) -> Option<String> {
+    match tokens.get(i)? {
+        Token::Word(word) => Some(word.clone()),
+        _ => None,
+    }
+}
+
+fn unqualified(name: &str) -> &str {
+    name.rsplit('.').next().unwrap_or(name)
+}
+
+/// The table, type or view a statement operates on
+pub fn target_object(tokens: &[Token]) -> Option<String> {
+    let keyword = |i: usize, k: &str| tokens.get(i).is_some_and(|t| t.is_keyword(k));
+
+    if keyword(0, "CREATE") || keyword(0, "ALTER") || keyword(0, "DROP") {
+        let object_start = if keyword(1, "MATERIALIZED") { 3 } else { 2 };
+        if keyword(1, "TABLE") || keyword(1, "TYPE") || keyword(1, "MATERIALIZED") {
+            return word_at(tokens, skip_if_exists(tokens, object_start)?);
+        }
+        if keyword(0, "CREATE") {
+            // CREATE [CUSTOM] INDEX ... ON table
+            let on = tokens.iter().position(|t| t.is_keyword("ON"))?;
+            return word_at(tokens, on + 1);
+        }
+        return None;
+    }
+
+    if keyword(0, "INSERT") && keyword(1, "INTO") {
+        return word_at(tokens, 2);
+    }
+    if keyword(0, "UPDATE") {
+        return word_at(tokens, 1);
+    }
+    if keyword(0, "TRUNCATE") {
+        return word_at(tokens, if keyword(1, "TABLE") { 2 } else { 1 });
+    }
+    if keyword(0, "DELETE") || keyword(0, "SELECT") {
+        let from = tokens.iter().position(|t| t.is_keyword("FROM"))?;
+        return word_at(tokens, from + 1);
+    }
+
+    None
+}
+
+/// Type expressions declared by CREATE TABLE / CREATE TYPE / ALTER ... ADD
+fn column_types(tokens: &[Token]) -> Vec<Vec<Token>> {
+    declared_columns(tokens)
+        .into_iter()
+        .map(|(_, type_tokens)| type_tokens)
+        .collect()
+}
+
+/// Column (or field) names and type expressions declared by CREATE TABLE /
+/// CREATE TYPE / ALTER ... ADD
+pub fn declared_columns(tokens: &[Token]) -> Vec<(String, Vec<Token>)> {
+    let keyword = |i: usize, k: &str| tokens.get(i).is_some_and(|t| t.is_keyword(k));
+    let mut definitions = Vec::new();
+
+    if keyword(0, "CREATE") && (keyword(1, "TABLE") || keyword(1, "TYPE")) {
+        if let Some(open) = tokens.iter().position(|t| *t == Token::Punct('(')) {
+            definitions = split_definitions(&tokens[open + 1..]);
+        }
+    } else if keyword(0, "ALTER") && (keyword(1, "TABLE") || keyword(1, "TYPE")) {
+        if let Some(add) = tokens.iter().position(|t| t.is_keyword("ADD")) {
+            let rest = &tokens[add + 1..];
+            definitions = if rest.first() == Some(&Token::Punct('(')) {
+                split_definitions(&rest[1..])
+            } else {
+                split_definitions(rest)
+            };
+        }
+    }
+
+    definitions
+        .into_iter()
+        .filter(|def| !def.first().is_some_and(|t| t.is_keyword("PRIMARY")))
+        .filter_map(|def| {
+            let name = match def.first() {
+                Some(Token::Word(name)) => name.clone(),
+                _ => return None,
+            };
+            let type_tokens: Vec<Token> = def
+                .into_iter()
+                .skip(1)
+                .take_while(|t| !t.is_keyword("STATIC") && !t.is_keyword("PRIMARY"))
+                .collect();
+            (!type_tokens.is_empty()).then_some((name, type_tokens))
+        })
+        .collect()
+}
+
+/// The primary key of a CREATE TABLE, normalized so that `id int PRIMARY KEY`
+/// and `PRIMARY KEY (id)` compare equal
+pub fn primary_key(tokens: &[Token]) -> Option<String> {
+    let open = tokens.iter().position(|t| *t == Token::Punct('('))?;
+    for definition in split_definitions(&tokens[open + 1..]) {
+        if definition.first().is_some_and(|t| t.is_keyword("PRIMARY")) {
+            let key = normalized(&definition[2..]);
+            return Some(match key.strip_prefix('(').and_then(|key| key.strip_suffix(')')) {
+                Some(single) if !single.contains(['(', ',']) => single.to_string(),
+                _ => key,
+            });
+        }
+        if definition.iter().any(|t| t.is_keyword("PRIMARY")) {
+            return definition.first().map(|column| normalized(std::slice::from_ref(column)));
+        }
+    }
+    None
+}
+
+/// Everything after the column list of a CREATE TABLE/TYPE, normalized
+pub fn table_options(tokens: &[Token]) -> String {
+    let Some(open) = tokens.iter().position(|t| *t == Token::Punct('(')) else {
+        return String::new();
+    };
+    let mut depth = 0usize;
+    for (i, token) in tokens.iter().enumerate().skip(open) {
+        match token {
+            Token::Punct('(') => depth += 1,
+            Token::Punct(')') => {
+                depth -= 1;
+                if depth == 0 {
+                    return normalized(&tokens[i + 1..]);
+                }
+            }
+            _ => {}
+        }
+    }
+    String::new()
+}
+
+/// Tokens joined by single spaces, unquoted words lowercased, for comparisons
+pub fn normalized(tokens: &[Token]) -> String {
+    tokens
+        .iter()
+        .map(|t| match t {
+            Token::Word(word) if word.contains('"') => word.clone(),
+            Token::Word(word) => word.to_lowercase(),
+            Token::Punct(c) => c.to_string(),
+            Token::Literal(literal) => literal.clone(),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+        .replace("( ", "(")
+        .replace(" )", ")")
+        .replace(" ,", ",")
+}
+
+/// A type expression as written, e.g. `map<text, frozen<address>>`
+pub fn type_text(tokens: &[Token]) -> String {
+    tokens
+        .iter()
+        .map(|t| match t {
+            Token::Word(word) | Token::Literal(word) => word.clone(),
+            Token::Punct(',') => ", ".to_string(),
+            Token::Punct(c) => c.to_string(),
+        })
+        .collect()
+}
+
+/// Split a definition list on top-level commas, stopping at the closing parenthesis
+fn split_definitions(tokens: &[Token]) -> Vec<Vec<Token>> {
+    let mut definitions = Vec::new();
+    let mut current = Vec::new();
+    let mut depth = 0usize;
+
+    for token in tokens {
+        match token {
+            Token::Punct('(' | '<') => depth += 1,
+            Token::Punct(')') if depth == 0 => break,
+            Token::Punct(')' | '>') => depth = depth.saturating_sub(1),
+            Token::Punct(',') if depth == 0 => {
+                definitions.push(std::mem::take(&mut current));
+                continue;
+            }
+            _ => {}
+        }
+        current.push(token.clone());
+    }
+
+    if !current.is_empty() {
+        definitions.push(current);
+    }
+
+    definitions
+}
+
+/// Validate a type expression such as `map<text, frozen<list<int>>>`
+fn check_type(tokens: &[Token], user_types: &HashSet<String>) -> Option<Issue> {
+    let mut position = 0;
+    let result = parse_type(tokens, &mut position, user_types);
+
+    match result {
+        Err(issue) => Some(issue),
+        Ok(()) if position != tokens.len() => Some(Issue::Syntax(format!(
+            "Malformed type '{}'",
+            render(tokens)
+        ))),
+        Ok(()) => None,
+    }
+}
+
+fn parse_type(
+    tokens: &[Token],
+    position: &mut usize,
+    user_types: &HashSet<String>,
+) -> Result<(), Issue> {
+    let malformed = || Issue::Syntax(format!("Malformed type '{}'", render(tokens)));
+
+    let name = match tokens.get(*position) {
+        Some(Token::Word(word)) => word.to_lowercase(),
+        _ => return Err(malformed()),
+    };
+    *position += 1;
+
+    let arity = match name.as_str() {
+        "list" | "set" | "frozen" => Some(1),
+        "map" => Some(2),
+        "tuple" => None,
+        _ => {
+            if tokens.get(*position) == Some(&Token::Punct('<')) {
+                return Err(malformed());
+            }
+            let is_known = NATIVE_TYPES.contains(&name.as_str())
+                || name.contains('.')
+                || user_types.contains(name.trim_matches('"'));
+            return if is_known {
+                Ok(())
+            } else {
+                Err(Issue::UnknownType(name))
+            };
+        }
+    };
+
+    if tokens.get(*position) != Some(&Token::Punct('<')) {
+        return Err(malformed());
+    }
+    *position += 1;
+
+    let mut args = 0;
+    loop {
+        parse_type(tokens, position, user_types)?;
+        args += 1;
+        match tokens.get(*position) {
+            Some(Token::Punct(',')) => *position += 1,
+            Some(Token::Punct('>')) => {
+                *position += 1;
+                break;
+            }
+            _ => return Err(malformed()),
+        }
+    }
+
+    if arity.is_some_and(|expected| expected != args) {
+        return Err(malformed());
+    }
+
+    Ok(())
+}
+
+fn render(tokens: &[Token]) -> String {
+    tokens
+        .iter()
+        .map(|t| match t {
+            Token::Word(w) => w.clone(),
+            Token::Punct(c) => c.to_string(),
+            Token::Literal(_) => "'...'".to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(statement: &str) -> Vec<Issue> {
+        check_statement(statement, &HashSet::from(["address".to_string()]), false)
+    }
+
+    #[test]
+    fn test_valid_statements() {
+        assert!(check("CREATE TABLE IF NOT EXISTS users (id UUID PRIMARY KEY, tags set<text>, attrs map<text, frozen<list<int>>>, home address)").is_empty());
+        assert!(check("INSERT INTO users (id, name) VALUES (uuid(), 'it''s; fine')").is_empty());
+        assert!(check("ALTER TABLE users ADD phone text").is_empty());
+        assert!(check("CREATE INDEX IF NOT EXISTS users_email_idx ON users (email)").is_empty());
+    }
+
+    #[test]
+    fn test_structural_errors() {
+        assert!(matches!(check("CREATE TABLE t (id int PRIMARY KEY")[0], Issue::Syntax(_)));
+        assert!(matches!(check("INSERT INTO t (a) VALUES ('oops)")[0], Issue::Syntax(_)));
+        assert!(matches!(check("CREAT TABLE t (id int PRIMARY KEY)")[0], Issue::Syntax(_)));
+    }
+
+    #[test]
+    fn test_type_errors() {
+        assert!(matches!(check("CREATE TABLE t (id int PRIMARY KEY, m map<text>)")[0], Issue::Syntax(_)));
+        assert!(matches!(check("CREATE TABLE t (id int PRIMARY KEY, l list<int)")[0], Issue::Syntax(_)));
+        assert_eq!(
+            check("CREATE TABLE t (id int PRIMARY KEY, n integer)"),
+            vec![Issue::UnknownType("integer".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_qualified_names() {
+        let types = HashSet::new();
+        assert_eq!(
+            check_statement("DROP TABLE IF EXISTS users", &types, true),
+            vec![Issue::Unqualified("users".to_string())]
+        );
+        assert!(check_statement("DROP TABLE IF EXISTS app.users", &types, true).is_empty());
+        assert!(check_statement("UPDATE \"App\".users SET a = 1 WHERE id = 2", &types, true).is_empty());
+    }
+
+    #[test]
+    fn test_created_type_name() {
+        assert_eq!(
+            created_type_name("CREATE TYPE IF NOT EXISTS app.Address (street text)"),
+            Some("address".to_string())
+        );
+        assert_eq!(created_type_name("CREATE TABLE t (id int PRIMARY KEY)"), None);
+    }
+
+    #[test]
+    fn test_partition_values() {
+        let key = vec!["region".to_string(), "id".to_string()];
+        let values = |statement: &str| partition_values(&tokenize(statement).unwrap(), &key);
+
+        assert_eq!(
+            values("INSERT INTO t (\"Region\", id, tags) VALUES ('eu', -1, {'a', 'b'})"),
+            None,
+            "quoted column names are case-sensitive"
+        );
+        assert_eq!(
+            values("INSERT INTO t (region, id, tags) VALUES ('eu', -1, {'a', 'b'}) IF NOT EXISTS"),
+            Some(vec!["'eu'".to_string(), "-1".to_string()])
+        );
+        assert_eq!(
+            values("UPDATE t SET n = 1 WHERE id = 7 AND region = 'eu' AND day > 3 IF n = 0"),
+            Some(vec!["'eu'".to_string(), "7".to_string()])
+        );
+        assert_eq!(values("DELETE FROM t WHERE region IN ('eu', 'us') AND id = 7"), None);
+        assert_eq!(values("SELECT * FROM t WHERE region = 'eu' AND id = 7"), None);
+    }
+
+    #[test]
+    fn test_literals() {
+        assert_eq!(split_literals("42, 'a, b', 'it''s'").unwrap(), vec!["42", "'a, b'", "'it''s'"]);
+        assert!(split_literals("1,").is_err());
+        assert!(split_literals("'open").is_err());
+        assert_eq!(unquote_literal("'it''s'").as_deref(), Some("it's"));
+        assert_eq!(unquote_literal("42"), None);
+    }
+
+    #[test]
+    fn test_identifiers() {
+        assert!(validate_identifier("schema_migrations").is_ok());
+        assert!(validate_identifier("\"Orders\"").is_ok());
+        assert!(validate_identifier("").is_err());
+        assert!(validate_identifier("1st").is_err());
+        assert!(validate_identifier("\"1st\"").is_ok());
+        assert!(validate_identifier("ks; DROP KEYSPACE prod").is_err());
+        assert!(validate_identifier("\"a\" WITH x").is_err());
+        assert!(validate_identifier(&"a".repeat(49)).is_err());
+
+        assert_eq!(quote_identifier("Users"), "\"users\"");
+        assert_eq!(quote_identifier("\"Users\""), "\"Users\"");
+        assert_eq!(suffixed_identifier("migrations", "_history"), "migrations_history");
+        assert_eq!(suffixed_identifier("\"Migrations\"", "_history"), "\"Migrations_history\"");
+    }
+}
@@ -0,0 +1,257 @@
+//! Diagnostics bundles for failed runs
+//!
+//! A bundle is a timestamped directory holding everything needed to investigate
+//! a failed `up` without access to the machine it ran on: the effective config
+//! with passwords redacted, what is known about the cluster, the failing
+//! statements with their driver errors and the most recent log lines.
+
+use crate::{
+    config::Config,
+    migration::{FailedStatement, MigrationManager},
+    MigrationError,
+};
+use chrono::Utc;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use tracing_subscriber::fmt::MakeWriter;
+
+/// Number of log lines kept for bundles
+pub const RECENT_LOG_LINES: usize = 500;
+
+/// In-memory ring buffer of the most recent log lines
+///
+/// Register it as the writer of a `tracing_subscriber::fmt` layer; it captures
+/// debug output even when the console only shows `info`.
+#[derive(Clone, Default)]
+pub struct RecentLogs {
+    buffer: Arc<Mutex<LogBuffer>>,
+}
+
+#[derive(Default)]
+struct LogBuffer {
+    lines: VecDeque<String>,
+    /// Text written since the last newline
+    partial: String,
+    /// Messages of the first warnings logged
+    warnings: Vec<String>,
+}
+
+impl RecentLogs {
+    /// The buffer shared by the logger and bundle writer of this process
+    pub fn global() -> &'static RecentLogs {
+        static LOGS: OnceLock<RecentLogs> = OnceLock::new();
+        LOGS.get_or_init(RecentLogs::default)
+    }
+
+    /// Buffered lines, oldest first
+    pub fn snapshot(&self) -> Vec<String> {
+        let buffer = self.buffer.lock().unwrap();
+        let mut lines: Vec<_> = buffer.lines.iter().cloned().collect();
+        if !buffer.partial.is_empty() {
+            lines.push(buffer.partial.clone());
+        }
+        lines
+    }
+
+    /// Messages of the warnings logged so far, oldest first, without timestamps
+    /// and targets; only the first [`RECENT_LOG_LINES`] are kept
+    pub fn warnings(&self) -> Vec<String> {
+        self.buffer.lock().unwrap().warnings.clone()
+    }
+
+    fn push(&self, text: &str) {
+        let mut buffer = self.buffer.lock().unwrap();
+        buffer.partial.push_str(text);
+
+        while let Some(end) = buffer.partial.find('\n') {
+            let line: String = buffer.partial.drain(..=end).collect();
+            let line = line.trim_end();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some((_, message)) = line.split_once(" WARN ") {
+                if buffer.warnings.len() < RECENT_LOG_LINES {
+                    let message = message.split_once(": ").map_or(message, |(_, message)| message);
+                    buffer.warnings.push(message.to_string());
+                }
+            }
+            if buffer.lines.len() == RECENT_LOG_LINES {
+                buffer.lines.pop_front();
+            }
+            buffer.lines.push_back(line.to_string());
+        }
+    }
+}
+
+impl Write for RecentLogs {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.push(&String::from_utf8_lossy(buf));
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> MakeWriter<'a> for RecentLogs {
+    type Writer = RecentLogs;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.clone()
+    }
+}
+
+/// A migration that failed, as recorded in `failure.json`
+#[derive(Debug, Serialize)]
+pub struct FailureReport {
+    pub version: String,
+    /// Zero-based index of the failing statement within its section
+    pub statement_index: Option<usize>,
+    /// 1-based lines of the failing statement in the migration file
+    pub first_line: Option<usize>,
+    pub last_line: Option<usize>,
+    /// Statement text before `${secret:...}` placeholders are resolved
+    pub statement: Option<String>,
+    /// The statement with line numbers, as shown in text output
+    pub snippet: Option<String>,
+    pub error: String,
+    pub error_code: &'static str,
+    #[serde(skip)]
+    location: Option<String>,
+}
+
+impl FailureReport {
+    pub fn new(manager: &MigrationManager, version: &str, error: &MigrationError) -> Self {
+        let failed = manager.failed_statement(version);
+        Self {
+            version: version.to_string(),
+            statement_index: failed.as_ref().map(|failed| failed.index),
+            first_line: failed.as_ref().and_then(|failed| failed.lines).map(|(first, _)| first),
+            last_line: failed.as_ref().and_then(|failed| failed.lines).map(|(_, last)| last),
+            snippet: failed.as_ref().map(FailedStatement::snippet),
+            location: failed.as_ref().map(FailedStatement::location),
+            statement: failed.map(|failed| failed.statement),
+            error: error.to_string(),
+            error_code: error.code(),
+        }
+    }
+
+    /// `migration 20250101_002, statement 3 (lines 14–19): <error>` and the snippet
+    pub fn describe(&self) -> String {
+        let mut text = match &self.location {
+            Some(location) => format!("migration {}, {}: {}", self.version, location, self.error),
+            None => format!("migration {}: {}", self.version, self.error),
+        };
+        if let Some(snippet) = &self.snippet {
+            for line in snippet.lines() {
+                text.push_str("\n    ");
+                text.push_str(line);
+            }
+        }
+        text
+    }
+}
+
+/// Cluster details for `cluster.json`, as far as they can still be queried
+pub async fn cluster_info(manager: &MigrationManager) -> serde_json::Value {
+    let config = manager.get_config();
+    let release_version = match manager
+        .session()
+        .query("SELECT release_version FROM system.local", &[])
+        .await
+    {
+        Ok(rows) => rows
+            .rows_typed::<(Option<String>,)>()
+            .ok()
+            .and_then(|mut rows| rows.next())
+            .and_then(|row| row.ok())
+            .and_then(|(version,)| version),
+        Err(_) => None,
+    };
+
+    serde_json::json!({
+        "cluster_name": manager.cluster_name(),
+        "release_version": release_version,
+        "hosts": config.database.hosts,
+        "port": config.database.port,
+        "datacenter": config.database.datacenter,
+        "keyspace": config.database.keyspace,
+        "tracking_keyspace": config.tracking_keyspace(),
+        "tls": config.database.tls.enabled,
+    })
+}
+
+/// Write a bundle to a new `db-migrate-diagnostics-<timestamp>` directory under `dir`
+pub fn write_bundle(
+    dir: &Path,
+    config: &Config,
+    cluster: &serde_json::Value,
+    failures: &[FailureReport],
+) -> anyhow::Result<PathBuf> {
+    let bundle = dir.join(format!(
+        "db-migrate-diagnostics-{}",
+        Utc::now().format("%Y%m%dT%H%M%SZ")
+    ));
+    std::fs::create_dir_all(&bundle)?;
+
+    std::fs::write(bundle.join("config.toml"), toml::to_string_pretty(&config.redacted())?)?;
+    std::fs::write(bundle.join("cluster.json"), serde_json::to_string_pretty(cluster)?)?;
+    std::fs::write(
+        bundle.join("failure.json"),
+        serde_json::to_string_pretty(&serde_json::json!({
+            "tool_version": env!("CARGO_PKG_VERSION"),
+            "failed_at": Utc::now(),
+            "failures": failures,
+        }))?,
+    )?;
+
+    let mut log = RecentLogs::global().snapshot().join("\n");
+    log.push('\n');
+    std::fs::write(bundle.join("trace.log"), log)?;
+
+    Ok(bundle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recent_logs_keep_last_lines() {
+        let mut logs = RecentLogs::default();
+        for i in 0..RECENT_LOG_LINES + 5 {
+            writeln!(logs, "line {}", i).unwrap();
+        }
+
+        let lines = logs.snapshot();
+        assert_eq!(lines.len(), RECENT_LOG_LINES);
+        assert_eq!(lines[0], "line 5");
+    }
+
+    #[test]
+    fn test_recent_logs_collect_warnings() {
+        let mut logs = RecentLogs::default();
+        writeln!(logs, "2025-01-28T14:30:22.000Z  INFO db_migrate::migration: Applying migration: 001").unwrap();
+        writeln!(logs, "2025-01-28T14:30:23.000Z  WARN db_migrate::lock: Could not renew the migration lock").unwrap();
+
+        assert_eq!(logs.warnings(), vec!["Could not renew the migration lock"]);
+    }
+
+    #[test]
+    fn test_bundle_redacts_passwords() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default();
+        config.database.password = "hunter2".to_string();
+
+        let bundle = write_bundle(dir.path(), &config, &serde_json::json!({}), &[]).unwrap();
+
+        let written = std::fs::read_to_string(bundle.join("config.toml")).unwrap();
+        assert!(!written.contains("hunter2"));
+        assert!(written.contains("<redacted>"));
+        assert!(bundle.join("trace.log").exists());
+    }
+}
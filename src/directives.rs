@@ -0,0 +1,453 @@
+use crate::{cql, utils::split_cql_statements, MigrationFile};
+use serde::Serialize;
+
+/// Prefix for directive comments inside migration files
+pub const DIRECTIVE_PREFIX: &str = "-- db-migrate:";
+
+/// Statements per batch of a `batch` migration without `batch-size`
+pub const DEFAULT_BATCH_SIZE: usize = 20;
+
+/// Metadata declared in a migration file via `-- db-migrate:<name>[=<value>]` comments
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Directives {
+    /// Versions that must be applied before this migration
+    pub depends_on: Vec<String>,
+    /// Resources (usually tables) touched by this migration
+    pub tags: Vec<String>,
+    /// The DOWN section must contain at least one statement
+    pub require_down: bool,
+    /// The migration cannot be rolled back; it intentionally has no DOWN section
+    pub no_down: bool,
+    /// The DOWN section is generated from the UP statements before the first apply
+    pub auto_down: bool,
+    /// Keyspace unqualified names resolve to, instead of `database.keyspace`
+    pub keyspace: Option<String>,
+    /// Environments the whole migration runs in (`only-env` before the first
+    /// section); elsewhere `only-env` restricts a statement or section, see [`EnvSelector`]
+    pub only_env: Vec<String>,
+    /// The file is kept for history but never applied or verified
+    pub skip: bool,
+    /// Schema changes and data writes are mixed on purpose
+    pub allow_mixed: bool,
+    /// Driver options for every statement (`page-size`, `token-aware`, `batch`
+    /// before the first section); statements may override some, see [`ExecutionOptions`]
+    pub execution: ExecutionOptions,
+}
+
+impl Directives {
+    /// Parse all directives found in the migration content
+    pub fn parse(content: &str) -> Self {
+        let mut directives = Self::default();
+        let mut in_header = true;
+
+        for line in content.lines() {
+            let trimmed = line.trim();
+            if !trimmed.is_empty() && !trimmed.starts_with(DIRECTIVE_PREFIX) && !is_plain_comment(trimmed) {
+                in_header = false;
+            }
+            let Some(directive) = trimmed.strip_prefix(DIRECTIVE_PREFIX) else {
+                continue;
+            };
+
+            let (name, value) = match directive.split_once('=') {
+                Some((name, value)) => (name.trim(), value.trim()),
+                None => (directive.trim(), ""),
+            };
+
+            match name {
+                "depends-on" => directives.depends_on.extend(split_list(value)),
+                "tags" => directives.tags.extend(split_list(value)),
+                "require-down" => directives.require_down = true,
+                "no-down" => directives.no_down = true,
+                "auto-down" => directives.auto_down = true,
+                "keyspace" => {
+                    directives.keyspace = Some(value.to_string()).filter(|v| !v.is_empty())
+                }
+                "only-env" if in_header => directives.only_env.extend(split_list(value)),
+                "only-env" => {}
+                "skip" => directives.skip = true,
+                "allow-mixed" => directives.allow_mixed = true,
+                "page-size" | "token-aware" | "routing-key" | "batch" | "batch-size" if in_header => {
+                    if let Err(e) = directives.execution.apply(name, value) {
+                        tracing::warn!("Ignoring migration directive {}: {}", name, e);
+                    }
+                }
+                "page-size" | "token-aware" | "routing-key" | "batch" | "batch-size" => {}
+                // Statement block delimiters, handled by the statement splitter
+                "statement-begin" | "statement-end" => {}
+                _ => tracing::warn!("Unknown migration directive: {}", name),
+            }
+        }
+
+        directives
+    }
+
+    /// Whether this migration may run concurrently with another one
+    ///
+    /// Migrations without tags are treated as touching everything.
+    pub fn is_independent_of(&self, version: &str, other: &Directives, other_version: &str) -> bool {
+        if self.tags.is_empty() || other.tags.is_empty() {
+            return false;
+        }
+
+        if self.depends_on.iter().any(|v| v == other_version)
+            || other.depends_on.iter().any(|v| v == version)
+        {
+            return false;
+        }
+
+        !self.tags.iter().any(|tag| other.tags.contains(tag))
+    }
+}
+
+/// Comment lines other than section markers
+fn is_plain_comment(line: &str) -> bool {
+    line.starts_with("--")
+        && !line.starts_with("-- UP")
+        && !line.starts_with("-- +migrate Up")
+        && !line.starts_with("-- DOWN")
+        && !line.starts_with("-- +migrate Down")
+}
+
+/// Decides which statements of one section run in the active environment
+///
+/// `-- db-migrate:only-env=dev,staging` in the comments right above a statement
+/// restricts that statement. At the top of a section, separated from the first
+/// statement by a blank line, it restricts the whole section. Restrictions of
+/// the migration, its section and the statement must all include the
+/// environment; without a configured environment restricted statements are skipped.
+pub struct EnvSelector<'a> {
+    environment: Option<&'a str>,
+    restrictions: Vec<Vec<String>>,
+    first: bool,
+}
+
+impl<'a> EnvSelector<'a> {
+    pub fn new(environment: Option<&'a str>, migration_only_env: &[String]) -> Self {
+        let restrictions = if migration_only_env.is_empty() {
+            Vec::new()
+        } else {
+            vec![migration_only_env.to_vec()]
+        };
+
+        Self {
+            environment,
+            restrictions,
+            first: true,
+        }
+    }
+
+    /// Whether the next statement of the section runs; call once per statement, in order
+    pub fn includes(&mut self, statement: &str) -> bool {
+        let mut statement_restrictions = Vec::new();
+        let mut pending = Vec::new();
+
+        for line in statement.lines().map(str::trim) {
+            if line.is_empty() {
+                // Directives separated from the first statement apply to the section
+                if self.first {
+                    self.restrictions.append(&mut pending);
+                } else {
+                    statement_restrictions.append(&mut pending);
+                }
+            } else if let Some(value) = line
+                .strip_prefix(DIRECTIVE_PREFIX)
+                .and_then(|directive| directive.strip_prefix("only-env="))
+            {
+                pending.push(split_list(value).collect::<Vec<_>>());
+            } else if !line.starts_with("--") {
+                break;
+            }
+        }
+        statement_restrictions.append(&mut pending);
+        self.first = false;
+
+        self.restrictions
+            .iter()
+            .chain(&statement_restrictions)
+            .all(|envs| self.environment.is_some_and(|env| envs.iter().any(|e| e == env)))
+    }
+
+    /// Split statements into those that run and those skipped
+    pub fn partition(mut self, statements: Vec<String>) -> (Vec<String>, Vec<String>) {
+        statements.into_iter().partition(|statement| self.includes(statement))
+    }
+}
+
+/// How the driver executes a statement, for large data migrations
+///
+/// `-- db-migrate:page-size=<rows>` sets the page size of reads.
+/// `-- db-migrate:token-aware` prepares the statement so the driver sends it to
+/// a replica of the partition it writes instead of any coordinator; its bind
+/// markers take the CQL literals of `-- db-migrate:routing-key=<value>, ...`, in
+/// order, which implies `token-aware`. Before the first section they apply to
+/// every statement; in the comments right above a statement, to that statement.
+///
+/// `-- db-migrate:batch=logged|unlogged` (before the first section only) sends
+/// the statements of each section in batches of `-- db-migrate:batch-size=<n>`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ExecutionOptions {
+    pub page_size: Option<i32>,
+    pub token_aware: bool,
+    /// Values bound to the statement's bind markers
+    pub routing_key: Vec<String>,
+    pub batch: Option<BatchKind>,
+    /// Statements per batch; [`DEFAULT_BATCH_SIZE`] if unset
+    pub batch_size: Option<usize>,
+}
+
+/// How the statements of a `batch` migration are grouped
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BatchKind {
+    /// Batches of consecutive statements, applied all or nothing
+    Logged,
+    /// Batches of statements writing the same partition, without the batch log
+    Unlogged,
+}
+
+impl ExecutionOptions {
+    /// Options of one statement: the migration's, overridden by the directives
+    /// in the comments above it
+    pub fn for_statement(&self, statement: &str) -> Result<Self, String> {
+        let mut options = self.clone();
+        for line in statement.lines().map(str::trim) {
+            if let Some(directive) = line.strip_prefix(DIRECTIVE_PREFIX) {
+                let (name, value) = match directive.split_once('=') {
+                    Some((name, value)) => (name.trim(), value.trim()),
+                    None => (directive.trim(), ""),
+                };
+                if matches!(name, "page-size" | "token-aware" | "routing-key") {
+                    options.apply(name, value)?;
+                }
+            } else if !line.is_empty() && !line.starts_with("--") {
+                break;
+            }
+        }
+        Ok(options)
+    }
+
+    fn apply(&mut self, name: &str, value: &str) -> Result<(), String> {
+        match name {
+            "page-size" => {
+                let page_size = value
+                    .parse()
+                    .ok()
+                    .filter(|page_size: &i32| *page_size > 0)
+                    .ok_or_else(|| format!("page-size must be a positive number of rows, got '{}'", value))?;
+                self.page_size = Some(page_size);
+            }
+            "token-aware" => self.token_aware = !matches!(value, "false" | "off"),
+            "routing-key" => {
+                self.routing_key = cql::split_literals(value)?;
+                self.token_aware = true;
+            }
+            "batch" => {
+                self.batch = Some(match value {
+                    "logged" => BatchKind::Logged,
+                    "unlogged" => BatchKind::Unlogged,
+                    _ => return Err(format!("batch must be 'logged' or 'unlogged', got '{}'", value)),
+                });
+            }
+            "batch-size" => {
+                let size = value
+                    .parse()
+                    .ok()
+                    .filter(|size: &usize| *size > 0)
+                    .ok_or_else(|| format!("batch-size must be a positive number of statements, got '{}'", value))?;
+                self.batch_size = Some(size);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+/// Group migrations into waves whose members may be applied concurrently
+///
+/// Waves preserve file order: a migration only joins the current wave if it is
+/// independent of every migration already in it, otherwise a new wave starts.
+/// Switching keyspaces (`USE` or the `keyspace` directive) affects the whole
+/// session, so such migrations always run alone.
+pub fn plan_parallel_waves(migrations: &[MigrationFile]) -> Vec<Vec<&MigrationFile>> {
+    let mut waves: Vec<Vec<&MigrationFile>> = Vec::new();
+
+    for migration in migrations {
+        let joins_current = !switches_keyspace(migration)
+            && waves.last().is_some_and(|wave| {
+                wave.iter().all(|other| {
+                    !switches_keyspace(other)
+                        && migration.directives.is_independent_of(
+                            &migration.version,
+                            &other.directives,
+                            &other.version,
+                        )
+                })
+            });
+
+        match waves.last_mut() {
+            Some(wave) if joins_current => wave.push(migration),
+            _ => waves.push(vec![migration]),
+        }
+    }
+
+    waves
+}
+
+fn switches_keyspace(migration: &MigrationFile) -> bool {
+    migration.directives.keyspace.is_some()
+        || split_cql_statements(&migration.content)
+            .iter()
+            .any(|statement| cql::used_keyspace(statement).is_some())
+}
+
+fn split_list(value: &str) -> impl Iterator<Item = String> + '_ {
+    value
+        .split(',')
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_directives() {
+        let content = r#"
+-- db-migrate:tags=users, user_events
+-- db-migrate:depends-on=20250101_001_create_users
+-- db-migrate:keyspace=analytics
+-- db-migrate:no-down
+-- +migrate Up
+CREATE TABLE user_events (id UUID PRIMARY KEY);
+"#;
+
+        let directives = Directives::parse(content);
+        assert_eq!(directives.tags, vec!["users", "user_events"]);
+        assert_eq!(directives.depends_on, vec!["20250101_001_create_users"]);
+        assert_eq!(directives.keyspace.as_deref(), Some("analytics"));
+        assert!(directives.no_down);
+        assert!(!directives.require_down);
+    }
+
+    #[test]
+    fn test_independence() {
+        let users = Directives::parse("-- db-migrate:tags=users");
+        let orders = Directives::parse("-- db-migrate:tags=orders");
+        let untagged = Directives::default();
+
+        assert!(users.is_independent_of("a", &orders, "b"));
+        assert!(!users.is_independent_of("a", &users, "b"));
+        assert!(!users.is_independent_of("a", &untagged, "b"));
+
+        let dependent = Directives::parse("-- db-migrate:tags=orders\n-- db-migrate:depends-on=a");
+        assert!(!users.is_independent_of("a", &dependent, "b"));
+    }
+
+    #[test]
+    fn test_only_env() {
+        let content = r#"-- db-migrate:only-env=dev, staging
+-- +migrate Up
+-- db-migrate:only-env=dev
+
+CREATE TABLE fixtures (id INT PRIMARY KEY);
+-- db-migrate:only-env=staging
+INSERT INTO fixtures (id) VALUES (1);
+INSERT INTO fixtures (id) VALUES (2);
+"#;
+        let directives = Directives::parse(content);
+        assert_eq!(directives.only_env, vec!["dev", "staging"]);
+
+        let (up, _) = crate::utils::parse_migration_content(content).unwrap();
+        let statements = split_cql_statements(&up);
+
+        let (run, skipped) = EnvSelector::new(Some("dev"), &directives.only_env).partition(statements.clone());
+        assert_eq!(run.len(), 2);
+        assert!(skipped[0].contains("VALUES (1)"));
+
+        // The section is dev-only, so the staging statement never runs
+        let (run, _) = EnvSelector::new(Some("staging"), &directives.only_env).partition(statements.clone());
+        assert!(run.is_empty());
+
+        let (run, _) = EnvSelector::new(None, &[]).partition(split_cql_statements(
+            "-- db-migrate:only-env=dev\nINSERT INTO t (id) VALUES (1);\nINSERT INTO t (id) VALUES (2);",
+        ));
+        assert_eq!(run, vec!["INSERT INTO t (id) VALUES (2)"]);
+    }
+
+    #[test]
+    fn test_execution_options() {
+        let content = r#"-- db-migrate:page-size=500
+-- +migrate Up
+UPDATE users SET active = true WHERE id = 1;
+-- db-migrate:routing-key=42, 'it''s'
+UPDATE users SET name = ? WHERE id = ? ;
+"#;
+        let directives = Directives::parse(content);
+        assert_eq!(directives.execution.page_size, Some(500));
+        assert!(!directives.execution.token_aware);
+
+        let (up, _) = crate::utils::parse_migration_content(content).unwrap();
+        let statements = split_cql_statements(&up);
+        assert_eq!(directives.execution.for_statement(&statements[0]).unwrap(), directives.execution);
+
+        let routed = directives.execution.for_statement(&statements[1]).unwrap();
+        assert!(routed.token_aware);
+        assert_eq!(routed.page_size, Some(500));
+        assert_eq!(routed.routing_key, vec!["42", "'it''s'"]);
+
+        let invalid = ExecutionOptions::default().for_statement("-- db-migrate:page-size=0\nSELECT * FROM t");
+        assert!(invalid.is_err());
+
+        let batched = Directives::parse("-- db-migrate:batch=unlogged\n-- db-migrate:batch-size=50\n-- +migrate Up\n");
+        assert_eq!(batched.execution.batch, Some(BatchKind::Unlogged));
+        assert_eq!(batched.execution.batch_size, Some(50));
+        // Batching is per migration; statements cannot opt in
+        let statement = ExecutionOptions::default().for_statement("-- db-migrate:batch=logged\nINSERT INTO t (id) VALUES (1)");
+        assert_eq!(statement.unwrap().batch, None);
+    }
+
+    fn migration(version: &str, content: &str) -> MigrationFile {
+        MigrationFile::for_test(version, content)
+    }
+
+    #[test]
+    fn test_plan_parallel_waves() {
+        let migrations = vec![
+            migration("1", "-- db-migrate:tags=users"),
+            migration("2", "-- db-migrate:tags=orders"),
+            migration("3", "-- db-migrate:tags=users"),
+            migration("4", "CREATE TABLE untagged (id INT PRIMARY KEY);"),
+            migration("5", "-- db-migrate:tags=payments"),
+            migration("6", "-- db-migrate:tags=audit\nUSE audit;\nCREATE TABLE log (id INT PRIMARY KEY);"),
+            migration("7", "-- db-migrate:tags=reports"),
+        ];
+
+        let waves: Vec<Vec<&str>> = plan_parallel_waves(&migrations)
+            .into_iter()
+            .map(|wave| wave.into_iter().map(|m| m.version.as_str()).collect())
+            .collect();
+
+        assert_eq!(waves, vec![vec!["1", "2"], vec!["3"], vec!["4"], vec!["5"], vec!["6"], vec!["7"]]);
+    }
+
+    #[test]
+    fn test_keyspace_directive_runs_alone() {
+        let migrations = vec![
+            migration("1", "-- db-migrate:tags=users"),
+            migration("2", "-- db-migrate:tags=events\n-- db-migrate:keyspace=analytics"),
+            migration("3", "-- db-migrate:tags=orders"),
+            migration("4", "-- db-migrate:tags=items\n-- db-migrate:keyspace="),
+        ];
+
+        let waves: Vec<Vec<&str>> = plan_parallel_waves(&migrations)
+            .into_iter()
+            .map(|wave| wave.into_iter().map(|m| m.version.as_str()).collect())
+            .collect();
+
+        // An empty directive keeps the configured keyspace
+        assert_eq!(waves, vec![vec!["1"], vec!["2"], vec!["3", "4"]]);
+        assert_eq!(migrations[3].directives.keyspace, None);
+    }
+}
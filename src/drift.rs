@@ -0,0 +1,739 @@
+//! Drift detection: the schema applied migrations should have produced vs. the live one
+//!
+//! The expected schema is rebuilt by replaying the DDL of applied migrations with the
+//! offline CQL tokenizer. Only the shapes the tool understands are tracked: tables and
+//! their columns, user-defined types and their fields, and materialized views with
+//! their base table and selected columns. Every table and index a migration ever
+//! created is also remembered, so objects created by hand can be reported as unmanaged.
+
+use crate::cql::{self, skip_if_exists, Token};
+use crate::schema::{comment_stamp, IndexInfo, TableInfo, TypeInfo, ViewInfo};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// A materialized view as declared by its `CREATE MATERIALIZED VIEW` statement
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExpectedView {
+    pub base_table: String,
+    /// Selected columns, or `None` for `SELECT *`
+    pub columns: Option<Vec<String>>,
+}
+
+/// Schema produced by replaying migration DDL
+#[derive(Debug, Clone, Default)]
+pub struct ExpectedSchema {
+    keyspace: String,
+    /// Keyspace unqualified names currently resolve to, when not `keyspace`
+    current_keyspace: Option<String>,
+    /// Table name -> column name -> normalized type
+    pub tables: BTreeMap<String, BTreeMap<String, String>>,
+    /// Type name -> fields in declaration order
+    pub types: BTreeMap<String, Vec<(String, String)>>,
+    pub views: BTreeMap<String, ExpectedView>,
+    /// Tables created by any migration, even if later dropped
+    pub created_tables: BTreeSet<String>,
+    /// Indexes created by any migration, even if later dropped
+    pub created_indexes: BTreeSet<String>,
+}
+
+/// A difference between the expected and the live schema
+#[derive(Debug, Clone, Serialize)]
+pub struct SchemaDrift {
+    /// `table`, `type`, `view` or `index`
+    pub object_type: &'static str,
+    pub name: String,
+    /// `missing`, `unexpected`, `changed` or `unmanaged`
+    pub kind: &'static str,
+    pub message: String,
+    /// Last migration stamped on the object, see `behavior.stamp_table_comments`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_migration: Option<String>,
+}
+
+impl SchemaDrift {
+    fn new(
+        object_type: &'static str,
+        name: &str,
+        kind: &'static str,
+        message: impl Into<String>,
+    ) -> Self {
+        Self {
+            object_type,
+            name: name.to_string(),
+            kind,
+            message: message.into(),
+            last_migration: None,
+        }
+    }
+}
+
+impl ExpectedSchema {
+    pub fn new(keyspace: &str) -> Self {
+        Self {
+            keyspace: normalize_identifier(keyspace),
+            ..Self::default()
+        }
+    }
+
+    /// Start replaying a migration whose unqualified names resolve to `keyspace`
+    /// (the `keyspace` directive), or to the schema's own keyspace when `None`
+    pub fn start_migration(&mut self, keyspace: Option<&str>) {
+        self.current_keyspace = keyspace
+            .map(normalize_identifier)
+            .filter(|keyspace| *keyspace != self.keyspace);
+    }
+
+    /// Replay a statement; statements the tool does not model are ignored
+    pub fn apply(&mut self, statement: &str) {
+        let Ok(tokens) = cql::tokenize(statement) else {
+            return;
+        };
+        let keyword = |i: usize, k: &str| tokens.get(i).is_some_and(|t| t.is_keyword(k));
+
+        if keyword(0, "USE") {
+            if let Some(Token::Word(keyspace)) = tokens.get(1) {
+                self.start_migration(Some(keyspace));
+            }
+        } else if keyword(0, "CREATE") && keyword(1, "MATERIALIZED") && keyword(2, "VIEW") {
+            self.create_view(&tokens);
+        } else if keyword(0, "CREATE") && (keyword(1, "TABLE") || keyword(1, "TYPE")) {
+            let Some(name) = self.object_name(&tokens, 2) else {
+                return;
+            };
+            let columns = cql::declared_columns(&tokens)
+                .into_iter()
+                .map(|(column, type_tokens)| {
+                    (normalize_identifier(&column), normalize_type(&type_tokens))
+                });
+
+            if keyword(1, "TABLE") {
+                self.created_tables.insert(name.clone());
+                self.tables.entry(name).or_insert_with(|| columns.collect());
+            } else {
+                self.types.entry(name).or_insert_with(|| columns.collect());
+            }
+        } else if keyword(0, "CREATE") && (keyword(1, "INDEX") || keyword(2, "INDEX")) {
+            self.create_index(&tokens);
+        } else if keyword(0, "ALTER") && (keyword(1, "TABLE") || keyword(1, "TYPE")) {
+            self.alter(&tokens, keyword(1, "TABLE"));
+        } else if keyword(0, "DROP") {
+            let (start, object_type) = if keyword(1, "MATERIALIZED") {
+                (3, "view")
+            } else if keyword(1, "TABLE") {
+                (2, "table")
+            } else if keyword(1, "TYPE") {
+                (2, "type")
+            } else {
+                return;
+            };
+            let Some(name) = self.object_name(&tokens, start) else {
+                return;
+            };
+            match object_type {
+                "view" => self.views.remove(&name).map(|_| ()),
+                "table" => self.tables.remove(&name).map(|_| ()),
+                _ => self.types.remove(&name).map(|_| ()),
+            };
+        }
+    }
+
+    /// Treat a table as managed even though no migration creates it (e.g. the tracking table)
+    pub fn mark_managed_table(&mut self, table: &str) {
+        self.created_tables.insert(normalize_identifier(table));
+    }
+
+    /// `CREATE [CUSTOM] INDEX [IF NOT EXISTS] [name] ON table (target)`
+    fn create_index(&mut self, tokens: &[Token]) {
+        let Some(on) = tokens.iter().position(|t| t.is_keyword("ON")) else {
+            return;
+        };
+        let Some(table) = self.object_name(tokens, on + 1) else {
+            return;
+        };
+
+        let index_keyword = if tokens[1].is_keyword("CUSTOM") { 2 } else { 1 };
+        let name = match skip_if_exists(tokens, index_keyword + 1) {
+            Some(i) if i < on => match &tokens[i] {
+                Token::Word(name) => Some(normalize_identifier(name)),
+                _ => None,
+            },
+            _ => None,
+        };
+
+        // Unnamed indexes get the server default <table>_<column>_idx; the target
+        // may be wrapped as keys(col), values(col), entries(col) or full(col)
+        let name = name.or_else(|| {
+            let column = tokens[on + 2..]
+                .iter()
+                .filter_map(|t| match t {
+                    Token::Word(w) => Some(w),
+                    _ => None,
+                })
+                .find(|w| {
+                    !["keys", "values", "entries", "full"]
+                        .iter()
+                        .any(|k| w.eq_ignore_ascii_case(k))
+                })?;
+            Some(format!("{}_{}_idx", table, normalize_identifier(column)))
+        });
+
+        if let Some(name) = name {
+            self.created_indexes.insert(name);
+        }
+    }
+
+    fn create_view(&mut self, tokens: &[Token]) {
+        let Some(name) = self.object_name(tokens, 3) else {
+            return;
+        };
+        let Some(select) = tokens.iter().position(|t| t.is_keyword("SELECT")) else {
+            return;
+        };
+        let Some(from) = tokens.iter().position(|t| t.is_keyword("FROM")) else {
+            return;
+        };
+        let Some(base_table) = self.object_name(tokens, from + 1) else {
+            return;
+        };
+
+        let selected = &tokens[select + 1..from];
+        let columns = if selected == [Token::Punct('*')] {
+            None
+        } else {
+            Some(
+                selected
+                    .iter()
+                    .filter_map(|t| match t {
+                        Token::Word(word) => Some(normalize_identifier(word)),
+                        _ => None,
+                    })
+                    .collect(),
+            )
+        };
+
+        self.views
+            .entry(name)
+            .or_insert(ExpectedView { base_table, columns });
+    }
+
+    fn alter(&mut self, tokens: &[Token], is_table: bool) {
+        let Some(name) = self.object_name(tokens, 2) else {
+            return;
+        };
+        let mut columns = if is_table {
+            self.tables.get_mut(&name)
+        } else {
+            None
+        };
+        let mut fields = if is_table {
+            None
+        } else {
+            self.types.get_mut(&name)
+        };
+
+        let action = tokens.get(3);
+        let is = |k: &str| action.is_some_and(|t| t.is_keyword(k));
+
+        if is("ADD") {
+            let added = cql::declared_columns(tokens)
+                .into_iter()
+                .map(|(column, type_tokens)| {
+                    (normalize_identifier(&column), normalize_type(&type_tokens))
+                });
+            if let Some(columns) = columns {
+                columns.extend(added);
+            } else if let Some(fields) = fields {
+                fields.extend(added);
+            }
+        } else if is("DROP") {
+            if let Some(columns) = columns {
+                for token in tokens[4..].iter().take_while(|t| !t.is_keyword("USING")) {
+                    if let Token::Word(column) = token {
+                        columns.remove(&normalize_identifier(column));
+                    }
+                }
+            }
+        } else if is("RENAME") {
+            // RENAME a TO b [AND c TO d ...]
+            let words: Vec<String> = tokens[4..]
+                .iter()
+                .filter_map(|t| match t {
+                    Token::Word(w) if !t.is_keyword("TO") && !t.is_keyword("AND") => {
+                        Some(normalize_identifier(w))
+                    }
+                    _ => None,
+                })
+                .collect();
+            for pair in words.chunks(2) {
+                let [from, to] = pair else { continue };
+                if let Some(columns) = columns.as_deref_mut() {
+                    if let Some(data_type) = columns.remove(from) {
+                        columns.insert(to.clone(), data_type);
+                    }
+                } else if let Some(fields) = fields.as_deref_mut() {
+                    if let Some(field) = fields.iter_mut().find(|(f, _)| f == from) {
+                        field.0 = to.clone();
+                    }
+                }
+            }
+        } else if is("ALTER") {
+            // ALTER <column> TYPE <type>
+            let (Some(Token::Word(column)), true) =
+                (tokens.get(4), tokens.get(5).is_some_and(|t| t.is_keyword("TYPE")))
+            else {
+                return;
+            };
+            let column = normalize_identifier(column);
+            let data_type = normalize_type(&tokens[6..]);
+            if let Some(columns) = columns {
+                columns.insert(column, data_type);
+            } else if let Some(fields) = fields {
+                if let Some(field) = fields.iter_mut().find(|(f, _)| *f == column) {
+                    field.1 = data_type;
+                }
+            }
+        }
+    }
+
+    /// Name of the object at `start` (after an optional IF [NOT] EXISTS), if it
+    /// belongs to this keyspace
+    fn object_name(&self, tokens: &[Token], start: usize) -> Option<String> {
+        let index = skip_if_exists(tokens, start)?;
+        let Token::Word(word) = tokens.get(index)? else {
+            return None;
+        };
+
+        match split_qualified(word) {
+            (Some(keyspace), name) if normalize_identifier(keyspace) == self.keyspace => {
+                Some(normalize_identifier(name))
+            }
+            (Some(_), _) => None,
+            (None, _) if self.current_keyspace.is_some() => None,
+            (None, name) => Some(normalize_identifier(name)),
+        }
+    }
+}
+
+/// Compare the expected schema with introspected objects
+pub fn compare(
+    expected: &ExpectedSchema,
+    tables: &[TableInfo],
+    types: &[TypeInfo],
+    views: &[ViewInfo],
+    indexes: &[IndexInfo],
+) -> Vec<SchemaDrift> {
+    let mut drift = Vec::new();
+
+    for table in tables {
+        if !expected.created_tables.contains(&table.table_name) {
+            drift.push(SchemaDrift::new(
+                "table",
+                &table.table_name,
+                "unmanaged",
+                "Table was not created by any applied migration",
+            ));
+        }
+    }
+    for index in indexes {
+        if !expected.created_indexes.contains(&index.name) {
+            drift.push(SchemaDrift::new(
+                "index",
+                &index.name,
+                "unmanaged",
+                format!(
+                    "Index on {}({}) was not created by any applied migration",
+                    index.table_name, index.column_name
+                ),
+            ));
+        }
+    }
+
+    let actual_tables: BTreeMap<&str, &TableInfo> =
+        tables.iter().map(|t| (t.table_name.as_str(), t)).collect();
+    for (name, expected_columns) in &expected.tables {
+        match actual_tables.get(name.as_str()) {
+            None => drift.push(SchemaDrift::new("table", name, "missing", "Table does not exist")),
+            Some(table) => {
+                let actual_columns: BTreeMap<String, String> = table
+                    .columns
+                    .iter()
+                    .map(|c| (c.name.clone(), normalize_type_str(&c.data_type)))
+                    .collect();
+                let last_migration = comment_stamp(&table.comment).map(str::to_string);
+                drift.extend(
+                    compare_columns("table", name, expected_columns, &actual_columns)
+                        .into_iter()
+                        .map(|entry| SchemaDrift {
+                            last_migration: last_migration.clone(),
+                            ..entry
+                        }),
+                );
+            }
+        }
+    }
+
+    let actual_types: BTreeMap<&str, &TypeInfo> =
+        types.iter().map(|t| (t.type_name.as_str(), t)).collect();
+    for (name, expected_fields) in &expected.types {
+        match actual_types.get(name.as_str()) {
+            None => drift.push(SchemaDrift::new("type", name, "missing", "Type does not exist")),
+            Some(udt) => {
+                let expected_fields: BTreeMap<String, String> =
+                    expected_fields.iter().cloned().collect();
+                let actual_fields: BTreeMap<String, String> = udt
+                    .fields
+                    .iter()
+                    .map(|(field, data_type)| (field.clone(), normalize_type_str(data_type)))
+                    .collect();
+                drift.extend(compare_columns("type", name, &expected_fields, &actual_fields));
+            }
+        }
+    }
+    for udt in types {
+        if !expected.types.contains_key(&udt.type_name) {
+            drift.push(SchemaDrift::new(
+                "type",
+                &udt.type_name,
+                "unexpected",
+                "Type was not created by any applied migration",
+            ));
+        }
+    }
+
+    let actual_views: BTreeMap<&str, &ViewInfo> =
+        views.iter().map(|v| (v.view_name.as_str(), v)).collect();
+    for (name, expected_view) in &expected.views {
+        let Some(view) = actual_views.get(name.as_str()) else {
+            drift.push(SchemaDrift::new("view", name, "missing", "Materialized view does not exist"));
+            continue;
+        };
+
+        if view.base_table != expected_view.base_table {
+            drift.push(SchemaDrift::new(
+                "view",
+                name,
+                "changed",
+                format!(
+                    "Base table is '{}', expected '{}'",
+                    view.base_table, expected_view.base_table
+                ),
+            ));
+        } else if !actual_tables.contains_key(view.base_table.as_str()) {
+            drift.push(SchemaDrift::new(
+                "view",
+                name,
+                "changed",
+                format!("Base table '{}' does not exist", view.base_table),
+            ));
+        }
+
+        if let Some(expected_columns) = &expected_view.columns {
+            let expected_columns: BTreeSet<&str> =
+                expected_columns.iter().map(String::as_str).collect();
+            let actual_columns: BTreeSet<&str> =
+                view.columns.iter().map(|c| c.name.as_str()).collect();
+            for column in expected_columns.difference(&actual_columns) {
+                drift.push(SchemaDrift::new(
+                    "view",
+                    name,
+                    "changed",
+                    format!("Column '{}' is not selected", column),
+                ));
+            }
+            for column in actual_columns.difference(&expected_columns) {
+                drift.push(SchemaDrift::new(
+                    "view",
+                    name,
+                    "changed",
+                    format!("Unexpected column '{}'", column),
+                ));
+            }
+        }
+    }
+    for view in views {
+        // ScyllaDB backs secondary indexes with views named <index>_index
+        let backs_index = view
+            .view_name
+            .strip_suffix("_index")
+            .is_some_and(|index| indexes.iter().any(|i| i.name == index));
+        if !backs_index && !expected.views.contains_key(&view.view_name) {
+            drift.push(SchemaDrift::new(
+                "view",
+                &view.view_name,
+                "unexpected",
+                format!(
+                    "Materialized view on '{}' was not created by any applied migration",
+                    view.base_table
+                ),
+            ));
+        }
+    }
+
+    drift
+}
+
+fn compare_columns(
+    object_type: &'static str,
+    name: &str,
+    expected: &BTreeMap<String, String>,
+    actual: &BTreeMap<String, String>,
+) -> Vec<SchemaDrift> {
+    let label = if object_type == "type" { "Field" } else { "Column" };
+    let mut drift = Vec::new();
+
+    for (column, expected_type) in expected {
+        match actual.get(column) {
+            None => drift.push(SchemaDrift::new(
+                object_type,
+                name,
+                "changed",
+                format!("{} '{}' is missing", label, column),
+            )),
+            Some(actual_type) if actual_type != expected_type => drift.push(SchemaDrift::new(
+                object_type,
+                name,
+                "changed",
+                format!(
+                    "{} '{}' has type {}, expected {}",
+                    label, column, actual_type, expected_type
+                ),
+            )),
+            Some(_) => {}
+        }
+    }
+
+    for column in actual.keys().filter(|c| !expected.contains_key(*c)) {
+        drift.push(SchemaDrift::new(
+            object_type,
+            name,
+            "changed",
+            format!("Unexpected {} '{}'", label.to_lowercase(), column),
+        ));
+    }
+
+    drift
+}
+
+pub(crate) fn split_qualified(word: &str) -> (Option<&str>, &str) {
+    // Qualifiers are only split outside quotes: "a.b" is a single identifier
+    let mut in_quotes = false;
+    for (i, c) in word.char_indices() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            '.' if !in_quotes => return (Some(&word[..i]), &word[i + 1..]),
+            _ => {}
+        }
+    }
+    (None, word)
+}
+
+/// Unquoted identifiers are case-insensitive; quoted ones keep their case
+pub(crate) fn normalize_identifier(identifier: &str) -> String {
+    if identifier.len() >= 2 && identifier.starts_with('"') && identifier.ends_with('"') {
+        identifier[1..identifier.len() - 1].replace("\"\"", "\"")
+    } else {
+        identifier.to_lowercase()
+    }
+}
+
+/// Render type tokens the way system_schema stores them, minus whitespace
+pub(crate) fn normalize_type(tokens: &[Token]) -> String {
+    tokens
+        .iter()
+        .map(|t| match t {
+            Token::Word(word) => {
+                let (_, name) = split_qualified(word);
+                match normalize_identifier(name).as_str() {
+                    "varchar" => "text".to_string(),
+                    other => other.to_string(),
+                }
+            }
+            Token::Punct(c) => c.to_string(),
+            Token::Literal(_) => String::new(),
+        })
+        .collect()
+}
+
+fn normalize_type_str(data_type: &str) -> String {
+    data_type
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>()
+        .to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::ColumnInfo;
+
+    fn replay(statements: &[&str]) -> ExpectedSchema {
+        let mut schema = ExpectedSchema::new("app");
+        for statement in statements {
+            schema.apply(statement);
+        }
+        schema
+    }
+
+    fn column(name: &str, data_type: &str) -> ColumnInfo {
+        ColumnInfo {
+            name: name.to_string(),
+            data_type: data_type.to_string(),
+            kind: "regular".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_replay_ddl() {
+        let schema = replay(&[
+            "CREATE TYPE app.address (street text, city varchar)",
+            "ALTER TYPE address ADD zip text",
+            "ALTER TYPE address RENAME city TO town",
+            "CREATE TABLE IF NOT EXISTS app.users (id uuid PRIMARY KEY, home frozen<app.address>, tags set<text>)",
+            "ALTER TABLE users ADD (email text, age int)",
+            "ALTER TABLE users DROP age",
+            "CREATE MATERIALIZED VIEW app.users_by_email AS SELECT id, email FROM app.users WHERE email IS NOT NULL AND id IS NOT NULL PRIMARY KEY (email, id)",
+            "CREATE TABLE other.ignored (id int PRIMARY KEY)",
+            "CREATE TABLE tmp (id int PRIMARY KEY)",
+            "DROP TABLE tmp",
+            "USE analytics",
+            "CREATE TABLE events (id int PRIMARY KEY)",
+        ]);
+
+        assert_eq!(
+            schema.types["address"],
+            vec![
+                ("street".to_string(), "text".to_string()),
+                ("town".to_string(), "text".to_string()),
+                ("zip".to_string(), "text".to_string()),
+            ]
+        );
+        assert_eq!(
+            schema.tables["users"].keys().collect::<Vec<_>>(),
+            vec!["email", "home", "id", "tags"]
+        );
+        assert_eq!(schema.tables["users"]["home"], "frozen<address>");
+        assert_eq!(schema.views["users_by_email"].base_table, "users");
+        assert_eq!(schema.tables.len(), 1);
+    }
+
+    #[test]
+    fn test_keyspace_directive() {
+        let mut schema = ExpectedSchema::new("app");
+        schema.start_migration(Some("analytics"));
+        schema.apply("CREATE TABLE events (id int PRIMARY KEY)");
+        schema.apply("CREATE TABLE app.users (id int PRIMARY KEY)");
+
+        // The next migration resolves names to the schema's keyspace again
+        schema.start_migration(None);
+        schema.apply("CREATE TABLE orders (id int PRIMARY KEY)");
+        schema.start_migration(Some("APP"));
+        schema.apply("CREATE TABLE items (id int PRIMARY KEY)");
+
+        assert_eq!(schema.tables.keys().collect::<Vec<_>>(), vec!["items", "orders", "users"]);
+    }
+
+    #[test]
+    fn test_udt_and_view_drift() {
+        let expected = replay(&[
+            "CREATE TYPE address (street text, city text)",
+            "CREATE TABLE users (id uuid PRIMARY KEY, email text)",
+            "CREATE MATERIALIZED VIEW users_by_email AS SELECT * FROM users WHERE email IS NOT NULL AND id IS NOT NULL PRIMARY KEY (email, id)",
+        ]);
+
+        let tables = vec![TableInfo {
+            keyspace: "app".to_string(),
+            table_name: "users".to_string(),
+            columns: vec![column("id", "uuid"), column("email", "text")],
+            primary_key: vec!["id".to_string()],
+            comment: String::new(),
+        }];
+        let types = vec![
+            TypeInfo {
+                keyspace: "app".to_string(),
+                type_name: "address".to_string(),
+                fields: vec![
+                    ("street".to_string(), "text".to_string()),
+                    ("city".to_string(), "int".to_string()),
+                    ("zip".to_string(), "text".to_string()),
+                ],
+            },
+            TypeInfo {
+                keyspace: "app".to_string(),
+                type_name: "manual".to_string(),
+                fields: Vec::new(),
+            },
+        ];
+        let views = vec![ViewInfo {
+            keyspace: "app".to_string(),
+            view_name: "users_by_email".to_string(),
+            base_table: "accounts".to_string(),
+            columns: Vec::new(),
+        }];
+
+        let drift = compare(&expected, &tables, &types, &views, &[]);
+        let summary: Vec<(&str, &str, &str)> = drift
+            .iter()
+            .map(|d| (d.object_type, d.name.as_str(), d.kind))
+            .collect();
+
+        assert_eq!(
+            summary,
+            vec![
+                ("type", "address", "changed"),
+                ("type", "address", "changed"),
+                ("type", "manual", "unexpected"),
+                ("view", "users_by_email", "changed"),
+            ]
+        );
+        assert!(drift[0].message.contains("'city' has type int"));
+        assert!(drift[1].message.contains("'zip'"));
+    }
+
+    #[test]
+    fn test_unmanaged_objects() {
+        let mut expected = replay(&[
+            "CREATE TABLE users (id uuid PRIMARY KEY, email text, tags set<text>)",
+            "CREATE INDEX ON users (email)",
+            "CREATE INDEX IF NOT EXISTS users_tags ON app.users (values(tags))",
+            "CREATE TABLE old (id int PRIMARY KEY)",
+            "DROP TABLE old",
+        ]);
+        expected.mark_managed_table("schema_migrations");
+
+        let table = |name: &str| TableInfo {
+            keyspace: "app".to_string(),
+            table_name: name.to_string(),
+            columns: Vec::new(),
+            primary_key: Vec::new(),
+            comment: String::new(),
+        };
+        let index = |name: &str, column: &str| IndexInfo {
+            name: name.to_string(),
+            table_name: "users".to_string(),
+            column_name: column.to_string(),
+            index_type: "COMPOSITES".to_string(),
+        };
+
+        let tables = vec![
+            table("users"),
+            table("old"),
+            table("schema_migrations"),
+            table("snowflake"),
+        ];
+        let indexes = vec![
+            index("users_email_idx", "email"),
+            index("users_tags", "values(tags)"),
+            index("users_name_idx", "name"),
+        ];
+
+        let drift = compare(&expected, &tables, &[], &[], &indexes);
+        let unmanaged: Vec<(&str, &str)> = drift
+            .iter()
+            .filter(|d| d.kind == "unmanaged")
+            .map(|d| (d.object_type, d.name.as_str()))
+            .collect();
+
+        assert_eq!(unmanaged, vec![("table", "snowflake"), ("index", "users_name_idx")]);
+    }
+}
@@ -0,0 +1,234 @@
+//! Run-time estimates for pending migrations
+//!
+//! Estimates come from the durations recorded in the tracking table. A migration
+//! that already ran in another environment (an exported `status --output json`)
+//! uses its own duration; otherwise the average time per statement of past
+//! migrations of the same kind is scaled by its statement count.
+
+use crate::{
+    cql,
+    utils::{parse_migration_content, split_cql_statements},
+    MigrationFile, MigrationRecord,
+};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// What a migration's statements do, used to match similar migrations
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MigrationKind {
+    /// Only schema changes
+    Schema,
+    /// Only data changes
+    Data,
+    Mixed,
+}
+
+/// Where an estimate comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EstimateBasis {
+    /// The same migration's recorded duration (e.g. from another environment)
+    Recorded,
+    /// Per-statement time of past migrations of the same kind
+    Similar,
+    /// No usable history
+    Unknown,
+}
+
+/// Estimated duration of one pending migration
+#[derive(Debug, Clone, Serialize)]
+pub struct Estimate {
+    pub version: String,
+    pub duration_ms: Option<f64>,
+    pub basis: EstimateBasis,
+}
+
+/// Estimates durations from past runs
+#[derive(Debug, Default)]
+pub struct Estimator {
+    recorded: HashMap<String, i64>,
+    /// Average milliseconds per statement, per kind
+    rates: HashMap<MigrationKind, f64>,
+    overall_rate: Option<f64>,
+}
+
+impl Estimator {
+    /// Learn from applied migrations (with their files, for statement counts) and
+    /// durations exported from other environments
+    pub fn new(
+        applied: &[MigrationRecord],
+        files: &[MigrationFile],
+        exported: HashMap<String, i64>,
+    ) -> Self {
+        let files: HashMap<&str, &MigrationFile> =
+            files.iter().map(|file| (file.version.as_str(), file)).collect();
+
+        let mut totals: HashMap<MigrationKind, (i64, usize)> = HashMap::new();
+        for record in applied {
+            let Some(duration_ms) = record.duration_ms else {
+                continue;
+            };
+            let Some((kind, statements)) = files
+                .get(record.version.as_str())
+                .and_then(|file| classify(file))
+                .filter(|(_, statements)| *statements > 0)
+            else {
+                continue;
+            };
+            let total = totals.entry(kind).or_default();
+            total.0 += duration_ms;
+            total.1 += statements;
+        }
+
+        let rate = |(ms, statements): (i64, usize)| ms as f64 / statements as f64;
+        let overall = totals
+            .values()
+            .fold((0, 0), |acc, (ms, statements)| (acc.0 + ms, acc.1 + statements));
+
+        let mut recorded = exported;
+        for record in applied {
+            if let Some(duration_ms) = record.duration_ms {
+                recorded.insert(record.version.clone(), duration_ms);
+            }
+        }
+
+        Self {
+            recorded,
+            rates: totals.into_iter().map(|(kind, total)| (kind, rate(total))).collect(),
+            overall_rate: (overall.1 > 0).then(|| rate(overall)),
+        }
+    }
+
+    pub fn estimate(&self, migration: &MigrationFile) -> Estimate {
+        let estimate = |duration_ms, basis| Estimate {
+            version: migration.version.clone(),
+            duration_ms,
+            basis,
+        };
+
+        if let Some(duration_ms) = self.recorded.get(&migration.version) {
+            return estimate(Some(*duration_ms as f64), EstimateBasis::Recorded);
+        }
+
+        match classify(migration) {
+            Some((kind, statements)) => {
+                match self.rates.get(&kind).copied().or(self.overall_rate) {
+                    Some(rate) => estimate(Some(rate * statements as f64), EstimateBasis::Similar),
+                    None => estimate(None, EstimateBasis::Unknown),
+                }
+            }
+            None => estimate(None, EstimateBasis::Unknown),
+        }
+    }
+}
+
+/// Kind and UP statement count of a migration; unknown for streamed files
+fn classify(migration: &MigrationFile) -> Option<(MigrationKind, usize)> {
+    if migration.streamed {
+        return None;
+    }
+    let (up, _) = parse_migration_content(&migration.content).ok()?;
+    let statements = split_cql_statements(&up);
+    let schema = statements.iter().filter(|s| cql::is_schema_change(s)).count();
+
+    let kind = if schema == statements.len() {
+        MigrationKind::Schema
+    } else if schema == 0 {
+        MigrationKind::Data
+    } else {
+        MigrationKind::Mixed
+    };
+    Some((kind, statements.len()))
+}
+
+#[derive(Deserialize)]
+struct ExportedRecord {
+    version: String,
+    duration_ms: Option<i64>,
+}
+
+/// Recorded durations from `status --output json` of another environment
+pub fn load_history(path: &Path) -> Result<HashMap<String, i64>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read history file {}", path.display()))?;
+    let value: serde_json::Value = serde_json::from_str(&content)
+        .with_context(|| format!("Failed to parse history file {}", path.display()))?;
+
+    let records = value
+        .pointer("/data/applied_migrations")
+        .or_else(|| value.get("applied_migrations"))
+        .unwrap_or(&value)
+        .clone();
+    let records: Vec<ExportedRecord> = serde_json::from_value(records).with_context(|| {
+        format!("History file {} has no applied_migrations list", path.display())
+    })?;
+
+    Ok(records
+        .into_iter()
+        .filter_map(|record| Some((record.version, record.duration_ms?)))
+        .collect())
+}
+
+/// Human-readable duration, e.g. `2m 03.4s`
+pub fn format_duration_ms(ms: f64) -> String {
+    let seconds = ms / 1000.0;
+    if seconds < 60.0 {
+        format!("{:.1}s", seconds)
+    } else {
+        let minutes = (seconds / 60.0).floor();
+        format!("{}m {:04.1}s", minutes, seconds - minutes * 60.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn file(version: &str, up: &str) -> MigrationFile {
+        MigrationFile::for_test(version, &format!("-- +migrate Up\n{}", up))
+    }
+
+    fn record(version: &str, duration_ms: i64) -> MigrationRecord {
+        MigrationRecord {
+            duration_ms: Some(duration_ms),
+            ..MigrationRecord::for_test(version, Utc::now())
+        }
+    }
+
+    #[test]
+    fn test_estimates() {
+        let files = vec![
+            file("20250101_001_users", "CREATE TABLE a (id int PRIMARY KEY);\nCREATE TABLE b (id int PRIMARY KEY);"),
+            file("20250101_002_seed", "INSERT INTO a (id) VALUES (1);"),
+            file("20250102_001_orders", "CREATE TABLE c (id int PRIMARY KEY);"),
+            file("20250102_002_more_seed", "INSERT INTO a (id) VALUES (2);\nINSERT INTO a (id) VALUES (3);"),
+            file("20250102_003_shipped", "UPDATE a SET x = 1 WHERE id = 1;"),
+        ];
+        let applied = vec![record("20250101_001_users", 4000), record("20250101_002_seed", 10)];
+        let exported = HashMap::from([("20250102_003_shipped".to_string(), 250)]);
+        let estimator = Estimator::new(&applied, &files, exported);
+
+        let schema = estimator.estimate(&files[2]);
+        assert_eq!(schema.basis, EstimateBasis::Similar);
+        assert_eq!(schema.duration_ms, Some(2000.0));
+
+        assert_eq!(estimator.estimate(&files[3]).duration_ms, Some(20.0));
+
+        let shipped = estimator.estimate(&files[4]);
+        assert_eq!(shipped.basis, EstimateBasis::Recorded);
+        assert_eq!(shipped.duration_ms, Some(250.0));
+
+        let empty = Estimator::new(&[], &files, HashMap::new());
+        assert_eq!(empty.estimate(&files[2]).basis, EstimateBasis::Unknown);
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration_ms(1300.0), "1.3s");
+        assert_eq!(format_duration_ms(123_400.0), "2m 03.4s");
+    }
+}
@@ -0,0 +1,264 @@
+//! Canonical formatting of migration files
+//!
+//! Formatting is conservative: it never reflows statements. Keywords are
+//! upper-cased (unquoted identifiers are case-insensitive in CQL, so this is
+//! safe), lines are indented by bracket depth, each statement starts on its own
+//! line, and runs of blank lines are collapsed. String literals, quoted
+//! identifiers, comments and statement blocks are left untouched.
+
+use crate::statements::{is_block_begin, is_block_end};
+
+const INDENT: &str = "    ";
+
+pub(crate) const KEYWORDS: &[&str] = &[
+    "ADD", "AGGREGATE", "ALL", "ALLOW", "ALTER", "AND", "APPLY", "AS", "ASC", "ASCII", "BATCH",
+    "BEGIN", "BIGINT", "BLOB", "BOOLEAN", "BY", "CLUSTERING", "COMPACT", "CONTAINS", "COUNTER",
+    "CREATE", "CUSTOM", "DATE", "DECIMAL", "DELETE", "DESC", "DISTINCT", "DOUBLE", "DROP",
+    "DURATION", "ENTRIES", "EXISTS", "FILTERING", "FLOAT", "FROM", "FROZEN", "FULL", "FUNCTION",
+    "GRANT", "IF", "IN", "INDEX", "INET", "INSERT", "INT", "INTO", "IS", "JSON", "KEY", "KEYS",
+    "KEYSPACE", "LIMIT", "LIST", "LOGGED", "LOGIN", "MAP", "MATERIALIZED", "MODIFY", "NOT", "NULL",
+    "OF", "ON", "OR", "ORDER", "PARTITION", "PASSWORD", "PER", "PERMISSION", "PERMISSIONS",
+    "PRIMARY", "RENAME", "REVOKE", "ROLE", "SELECT", "SET", "SMALLINT", "STATIC", "STORAGE",
+    "SUPERUSER", "TABLE", "TEXT", "TIME", "TIMESTAMP", "TIMEUUID", "TINYINT", "TO", "TOKEN",
+    "TRUNCATE", "TTL", "TUPLE", "TYPE", "UNLOGGED", "UPDATE", "USE", "USING", "UUID", "VALUES",
+    "VARCHAR", "VARINT", "VIEW", "WHERE", "WITH", "WRITETIME",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Code,
+    SingleQuote,
+    DoubleQuote,
+    Dollar,
+    BlockComment,
+}
+
+/// Format migration file content
+pub fn format_cql(content: &str) -> String {
+    let mut state = State::Code;
+    let mut depth = 0usize;
+    let mut in_block = false;
+    let mut lines = Vec::new();
+
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if in_block || (state == State::Code && is_block_begin(trimmed)) {
+            in_block = !is_block_end(trimmed);
+            lines.push(line.trim_end().to_string());
+            continue;
+        }
+        lines.extend(format_line(line, &mut state, &mut depth));
+    }
+
+    // Collapse blank-line runs and trim leading/trailing blank lines
+    let mut output = String::new();
+    let mut previous_blank = true;
+    for line in lines {
+        let blank = line.is_empty();
+        if blank && previous_blank {
+            continue;
+        }
+        output.push_str(&line);
+        output.push('\n');
+        previous_blank = blank;
+    }
+
+    while output.ends_with("\n\n") {
+        output.pop();
+    }
+
+    output
+}
+
+/// Format one input line, which may be split into several output lines
+fn format_line(line: &str, state: &mut State, depth: &mut usize) -> Vec<String> {
+    // Lines that start inside a multi-line string or comment are kept verbatim
+    if *state != State::Code {
+        let chars: Vec<char> = line.chars().collect();
+        let mut scratch = String::new();
+        scan(&chars, 0, state, depth, &mut scratch, false);
+        return vec![line.trim_end().to_string()];
+    }
+
+    let mut output = Vec::new();
+    let mut rest: Vec<char> = line.trim().chars().collect();
+
+    loop {
+        if rest.is_empty() {
+            if output.is_empty() {
+                output.push(String::new());
+            }
+            break;
+        }
+
+        let indent = if rest[0] == ')' {
+            depth.saturating_sub(1)
+        } else {
+            *depth
+        };
+
+        let mut formatted = INDENT.repeat(indent);
+        let consumed = scan(&rest, 0, state, depth, &mut formatted, true);
+        output.push(formatted.trim_end().to_string());
+
+        rest = rest[consumed..].iter().copied().collect::<String>().trim().chars().collect();
+    }
+
+    output
+}
+
+/// Scan characters from `start`, appending (and transforming when `transform`) into `out`
+///
+/// Returns the index at which scanning stopped: either the end of the input or just
+/// after a statement terminator followed by more code on the same line.
+fn scan(
+    chars: &[char],
+    start: usize,
+    state: &mut State,
+    depth: &mut usize,
+    out: &mut String,
+    transform: bool,
+) -> usize {
+    let mut i = start;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+
+        match *state {
+            State::SingleQuote | State::DoubleQuote => {
+                let quote = if *state == State::SingleQuote { '\'' } else { '"' };
+                out.push(c);
+                if c == quote && next == Some(quote) {
+                    out.push(quote);
+                    i += 1;
+                } else if c == quote {
+                    *state = State::Code;
+                }
+                i += 1;
+            }
+            State::Dollar => {
+                out.push(c);
+                if c == '$' && next == Some('$') {
+                    out.push('$');
+                    i += 1;
+                    *state = State::Code;
+                }
+                i += 1;
+            }
+            State::BlockComment => {
+                out.push(c);
+                if c == '*' && next == Some('/') {
+                    out.push('/');
+                    i += 1;
+                    *state = State::Code;
+                }
+                i += 1;
+            }
+            State::Code => {
+                if (c == '-' && next == Some('-')) || (c == '/' && next == Some('/')) {
+                    out.extend(&chars[i..]);
+                    return chars.len();
+                }
+
+                match c {
+                    '\'' => *state = State::SingleQuote,
+                    '"' => *state = State::DoubleQuote,
+                    '$' if next == Some('$') => {
+                        out.push_str("$$");
+                        *state = State::Dollar;
+                        i += 2;
+                        continue;
+                    }
+                    '/' if next == Some('*') => {
+                        out.push_str("/*");
+                        *state = State::BlockComment;
+                        i += 2;
+                        continue;
+                    }
+                    '(' => *depth += 1,
+                    ')' => *depth = depth.saturating_sub(1),
+                    _ => {}
+                }
+
+                if c.is_alphabetic() || c == '_' {
+                    let word_start = i;
+                    while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                        i += 1;
+                    }
+                    let word: String = chars[word_start..i].iter().collect();
+                    let upper = word.to_uppercase();
+                    let after_dot = word_start > 0 && chars[word_start - 1] == '.';
+
+                    if transform && !after_dot && KEYWORDS.contains(&upper.as_str()) {
+                        out.push_str(&upper);
+                    } else {
+                        out.push_str(&word);
+                    }
+                    continue;
+                }
+
+                out.push(c);
+                i += 1;
+
+                if c == ';' && transform {
+                    let remainder: String = chars[i..].iter().collect();
+                    let remainder = remainder.trim_start();
+                    if !remainder.is_empty()
+                        && !remainder.starts_with("--")
+                        && !remainder.starts_with("//")
+                    {
+                        return i;
+                    }
+                }
+            }
+        }
+    }
+
+    chars.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keywords_indentation_and_blank_lines() {
+        let input = "-- +migrate Up\ncreate table if not exists users (\nid uuid primary key,\n  name text\n);\n\n\n\ncreate index on users (name);\n";
+        let expected = "-- +migrate Up\nCREATE TABLE IF NOT EXISTS users (\n    id UUID PRIMARY KEY,\n    name TEXT\n);\n\nCREATE INDEX ON users (name);\n";
+        assert_eq!(format_cql(input), expected);
+    }
+
+    #[test]
+    fn test_strings_comments_and_identifiers_untouched() {
+        let input = "insert into t (\"Select\", v) values ('select from', 1); -- select me\n";
+        let expected = "INSERT INTO t (\"Select\", v) VALUES ('select from', 1); -- select me\n";
+        assert_eq!(format_cql(input), expected);
+    }
+
+    #[test]
+    fn test_statements_split_onto_own_lines() {
+        let input = "drop table a; drop table b;\n";
+        assert_eq!(format_cql(input), "DROP TABLE a;\nDROP TABLE b;\n");
+    }
+
+    #[test]
+    fn test_multiline_string_kept_verbatim() {
+        let input = "insert into t (v) values ('line one\n   line two');\n";
+        assert_eq!(format_cql(input), "INSERT INTO t (v) VALUES ('line one\n   line two');\n");
+    }
+
+    #[test]
+    fn test_idempotent() {
+        let input = "create table t (\nid int,\nv map<text, int>,\nprimary key ((id), v)\n) with comment = 'x';\n";
+        let once = format_cql(input);
+        assert_eq!(format_cql(&once), once);
+    }
+
+    #[test]
+    fn test_statement_block_kept_verbatim() {
+        let input = "-- +migrate StatementBegin\ncreate function f(a int) returns null on null input returns int\n  language lua as $$ return a; $$;\n-- +migrate StatementEnd\ndrop table a;\n";
+        let expected = "-- +migrate StatementBegin\ncreate function f(a int) returns null on null input returns int\n  language lua as $$ return a; $$;\n-- +migrate StatementEnd\nDROP TABLE a;\n";
+        assert_eq!(format_cql(input), expected);
+    }
+}
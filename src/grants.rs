@@ -0,0 +1,248 @@
+//! Permissions a migration needs, derived from its statements
+//!
+//! Used by `permissions --check` to verify that the connected role can apply the
+//! pending migrations before anything is executed.
+
+use crate::{
+    cql::{self, Token},
+    drift::{normalize_identifier, split_qualified},
+    schema::PermissionInfo,
+};
+use serde::Serialize;
+
+const ALL_KEYSPACES: &str = "<all keyspaces>";
+const ALL_ROLES: &str = "<all roles>";
+
+/// A permission the connected role needs on a resource
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct RequiredPermission {
+    pub permission: &'static str,
+    /// Resource in `LIST PERMISSIONS` notation, e.g. `<table app.users>`
+    pub resource: String,
+}
+
+impl RequiredPermission {
+    fn new(permission: &'static str, resource: impl Into<String>) -> Self {
+        Self {
+            permission,
+            resource: resource.into(),
+        }
+    }
+
+    /// Whether any of the granted permissions covers this one
+    ///
+    /// Permissions on `<all keyspaces>` cover every keyspace and table, and
+    /// permissions on a keyspace cover its tables.
+    pub fn is_granted(&self, granted: &[PermissionInfo]) -> bool {
+        granted.iter().any(|grant| {
+            grant.permission.eq_ignore_ascii_case(self.permission)
+                && covers(&grant.resource, &self.resource)
+        })
+    }
+}
+
+fn covers(granted: &str, required: &str) -> bool {
+    if granted == required {
+        return true;
+    }
+    if granted == ALL_KEYSPACES {
+        return required.starts_with("<keyspace ") || required.starts_with("<table ");
+    }
+    if granted == ALL_ROLES {
+        return required.starts_with("<role ");
+    }
+    match (
+        granted.strip_prefix("<keyspace ").and_then(|r| r.strip_suffix('>')),
+        required.strip_prefix("<table ").and_then(|r| r.strip_suffix('>')),
+    ) {
+        (Some(keyspace), Some(table)) => table
+            .split_once('.')
+            .is_some_and(|(table_keyspace, _)| table_keyspace == keyspace),
+        _ => false,
+    }
+}
+
+/// Permissions needed to run a migration's statements, in order and deduplicated
+///
+/// Unqualified names resolve to `keyspace` until a `USE` statement switches it.
+pub fn required_permissions(statements: &[String], keyspace: &str) -> Vec<RequiredPermission> {
+    let mut keyspace = normalize_identifier(keyspace);
+    let mut required = Vec::new();
+
+    for statement in statements {
+        if let Some(used) = cql::used_keyspace(statement) {
+            keyspace = normalize_identifier(&used);
+            continue;
+        }
+        if let Some(permission) = statement_permission(statement, &keyspace) {
+            if !required.contains(&permission) {
+                required.push(permission);
+            }
+        }
+    }
+
+    required
+}
+
+fn statement_permission(statement: &str, keyspace: &str) -> Option<RequiredPermission> {
+    let tokens = cql::tokenize(statement).ok()?;
+    let keyword = |i: usize, k: &str| tokens.get(i).is_some_and(|t| t.is_keyword(k));
+    let word = |i: usize| match tokens.get(i) {
+        Some(Token::Word(word)) => Some(word.as_str()),
+        _ => None,
+    };
+    let table = |name: &str| {
+        let (table_keyspace, table) = split_qualified(name);
+        format!(
+            "<table {}.{}>",
+            table_keyspace.map(normalize_identifier).unwrap_or_else(|| keyspace.to_string()),
+            normalize_identifier(table)
+        )
+    };
+    let keyspace_of = |name: &str| {
+        let (object_keyspace, _) = split_qualified(name);
+        format!(
+            "<keyspace {}>",
+            object_keyspace.map(normalize_identifier).unwrap_or_else(|| keyspace.to_string())
+        )
+    };
+    let named = |start: usize| cql::skip_if_exists(&tokens, start).and_then(word);
+
+    let verb = if keyword(0, "CREATE") {
+        "CREATE"
+    } else if keyword(0, "ALTER") {
+        "ALTER"
+    } else if keyword(0, "DROP") {
+        "DROP"
+    } else {
+        ""
+    };
+
+    let permission = match verb {
+        "CREATE" if keyword(1, "KEYSPACE") => RequiredPermission::new("CREATE", ALL_KEYSPACES),
+        "ALTER" | "DROP" if keyword(1, "KEYSPACE") => {
+            RequiredPermission::new(verb, format!("<keyspace {}>", normalize_identifier(named(2)?)))
+        }
+        "CREATE" if keyword(1, "ROLE") || keyword(1, "USER") => {
+            RequiredPermission::new("CREATE", ALL_ROLES)
+        }
+        "ALTER" | "DROP" if keyword(1, "ROLE") || keyword(1, "USER") => {
+            RequiredPermission::new(verb, format!("<role {}>", normalize_identifier(named(2)?)))
+        }
+        "CREATE" if keyword(1, "MATERIALIZED") => {
+            // Requires ALTER on the base table
+            let from = tokens.iter().position(|t| t.is_keyword("FROM"))?;
+            RequiredPermission::new("ALTER", table(word(from + 1)?))
+        }
+        "CREATE" if keyword(1, "INDEX") || keyword(2, "INDEX") => {
+            RequiredPermission::new("ALTER", table(&cql::target_object(&tokens)?))
+        }
+        "CREATE" if keyword(1, "TABLE") || keyword(1, "TYPE") => {
+            RequiredPermission::new("CREATE", keyspace_of(named(2)?))
+        }
+        "ALTER" | "DROP" if keyword(1, "TABLE") => RequiredPermission::new(verb, table(named(2)?)),
+        "ALTER" | "DROP" if keyword(1, "MATERIALIZED") => {
+            RequiredPermission::new("ALTER", keyspace_of(named(3)?))
+        }
+        "DROP" if keyword(1, "TYPE") => RequiredPermission::new("DROP", keyspace_of(named(2)?)),
+        "ALTER" | "DROP" if keyword(1, "TYPE") || keyword(1, "INDEX") => {
+            RequiredPermission::new("ALTER", keyspace_of(named(2)?))
+        }
+        _ if keyword(0, "INSERT")
+            || keyword(0, "UPDATE")
+            || keyword(0, "DELETE")
+            || keyword(0, "TRUNCATE") =>
+        {
+            RequiredPermission::new("MODIFY", table(&cql::target_object(&tokens)?))
+        }
+        _ if keyword(0, "SELECT") => {
+            RequiredPermission::new("SELECT", table(&cql::target_object(&tokens)?))
+        }
+        _ if keyword(0, "GRANT") || keyword(0, "REVOKE") => {
+            let resource = match tokens.iter().position(|t| t.is_keyword("ON")) {
+                Some(on) if keyword(on + 1, "ALL") && keyword(on + 2, "KEYSPACES") => {
+                    ALL_KEYSPACES.to_string()
+                }
+                Some(on) if keyword(on + 1, "ALL") && keyword(on + 2, "ROLES") => {
+                    ALL_ROLES.to_string()
+                }
+                Some(on) if keyword(on + 1, "KEYSPACE") => {
+                    format!("<keyspace {}>", normalize_identifier(word(on + 2)?))
+                }
+                Some(on) if keyword(on + 1, "ROLE") => {
+                    format!("<role {}>", normalize_identifier(word(on + 2)?))
+                }
+                Some(on) => {
+                    let index = if keyword(on + 1, "TABLE") { on + 2 } else { on + 1 };
+                    table(word(index)?)
+                }
+                // GRANT role TO role
+                None => format!("<role {}>", normalize_identifier(word(1)?)),
+            };
+            RequiredPermission::new("AUTHORIZE", resource)
+        }
+        _ => return None,
+    };
+
+    Some(permission)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grant(permission: &str, resource: &str) -> PermissionInfo {
+        PermissionInfo {
+            role: "deployer".to_string(),
+            resource: resource.to_string(),
+            permission: permission.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_required_permissions() {
+        let statements: Vec<String> = [
+            "CREATE TABLE IF NOT EXISTS users (id uuid PRIMARY KEY)",
+            "CREATE INDEX ON users (email)",
+            "ALTER TABLE app.Users ADD email text",
+            "INSERT INTO users (id) VALUES (uuid())",
+            "CREATE ROLE reporter WITH LOGIN = true",
+            "GRANT SELECT ON KEYSPACE app TO reporter",
+            "USE analytics",
+            "DROP TABLE events",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+
+        let required: Vec<(&str, String)> = required_permissions(&statements, "app")
+            .into_iter()
+            .map(|p| (p.permission, p.resource))
+            .collect();
+
+        assert_eq!(
+            required,
+            vec![
+                ("CREATE", "<keyspace app>".to_string()),
+                ("ALTER", "<table app.users>".to_string()),
+                ("MODIFY", "<table app.users>".to_string()),
+                ("CREATE", "<all roles>".to_string()),
+                ("AUTHORIZE", "<keyspace app>".to_string()),
+                ("DROP", "<table analytics.events>".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_resource_hierarchy() {
+        let modify = RequiredPermission::new("MODIFY", "<table app.users>");
+
+        assert!(modify.is_granted(&[grant("MODIFY", "<table app.users>")]));
+        assert!(modify.is_granted(&[grant("MODIFY", "<keyspace app>")]));
+        assert!(modify.is_granted(&[grant("MODIFY", "<all keyspaces>")]));
+        assert!(!modify.is_granted(&[grant("MODIFY", "<keyspace other>")]));
+        assert!(!modify.is_granted(&[grant("SELECT", "<table app.users>")]));
+        assert!(RequiredPermission::new("ALTER", "<role app>")
+            .is_granted(&[grant("ALTER", "<all roles>")]));
+    }
+}
@@ -0,0 +1,151 @@
+//! Terminal syntax highlighting for CQL
+//!
+//! Keywords, native types, literals and comments are colored so long
+//! migrations stay reviewable in a terminal. Output that is not a terminal,
+//! `--porcelain` and `NO_COLOR` get the text unchanged.
+
+use crate::{cql::NATIVE_TYPES, fmt::KEYWORDS};
+use colored::*;
+use std::io::IsTerminal;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Keyword,
+    Type,
+    String,
+    Number,
+    Comment,
+    Plain,
+}
+
+/// Highlight CQL for display on stdout, if stdout is a terminal
+pub fn highlight_cql(text: &str) -> String {
+    if !std::io::stdout().is_terminal() {
+        return text.to_string();
+    }
+
+    spans(text)
+        .into_iter()
+        .map(|(kind, span)| match kind {
+            Kind::Keyword => span.bright_blue().bold().to_string(),
+            Kind::Type => span.cyan().to_string(),
+            Kind::String => span.green().to_string(),
+            Kind::Number => span.magenta().to_string(),
+            Kind::Comment => span.dimmed().to_string(),
+            Kind::Plain => span.to_string(),
+        })
+        .collect()
+}
+
+/// Split text into classified spans; concatenated, they give back the text
+fn spans(text: &str) -> Vec<(Kind, &str)> {
+    let mut spans = Vec::new();
+    let mut plain_start = 0;
+    let mut i = 0;
+    let bytes = text.as_bytes();
+
+    while i < text.len() {
+        let rest = &text[i..];
+        let (kind, len) = if rest.starts_with("--") || rest.starts_with("//") {
+            (Kind::Comment, rest.find('\n').unwrap_or(rest.len()))
+        } else if rest.starts_with("/*") {
+            (Kind::Comment, rest.find("*/").map_or(rest.len(), |end| end + 2))
+        } else if let Some(body) = rest.strip_prefix("$$") {
+            (Kind::String, body.find("$$").map_or(rest.len(), |end| end + 4))
+        } else if rest.starts_with('\'') || rest.starts_with('"') {
+            let quote = bytes[i];
+            let kind = if quote == b'\'' { Kind::String } else { Kind::Plain };
+            (kind, quoted_len(rest.as_bytes(), quote))
+        } else if bytes[i].is_ascii_digit() && (i == 0 || !is_word_byte(bytes[i - 1])) {
+            let len = rest
+                .bytes()
+                .position(|b| !(b.is_ascii_alphanumeric() || b == b'.' || b == b'-'))
+                .unwrap_or(rest.len());
+            (Kind::Number, len)
+        } else if is_word_byte(bytes[i]) && (i == 0 || !is_word_byte(bytes[i - 1])) {
+            let len = rest.bytes().position(|b| !is_word_byte(b)).unwrap_or(rest.len());
+            let word = &rest[..len];
+            let after_dot = i > 0 && bytes[i - 1] == b'.';
+            let kind = if after_dot {
+                Kind::Plain
+            } else if NATIVE_TYPES.iter().any(|t| t.eq_ignore_ascii_case(word)) {
+                Kind::Type
+            } else if KEYWORDS.iter().any(|k| k.eq_ignore_ascii_case(word)) {
+                Kind::Keyword
+            } else {
+                Kind::Plain
+            };
+            (kind, len)
+        } else {
+            // Advance by a whole character
+            let len = rest.chars().next().map_or(1, char::len_utf8);
+            i += len;
+            continue;
+        };
+
+        if kind == Kind::Plain {
+            i += len;
+            continue;
+        }
+        if plain_start < i {
+            spans.push((Kind::Plain, &text[plain_start..i]));
+        }
+        spans.push((kind, &text[i..i + len]));
+        i += len;
+        plain_start = i;
+    }
+
+    if plain_start < text.len() {
+        spans.push((Kind::Plain, &text[plain_start..]));
+    }
+    spans
+}
+
+fn is_word_byte(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'_' || !b.is_ascii()
+}
+
+/// Length of a quoted literal or identifier, with doubled quotes as escapes
+fn quoted_len(bytes: &[u8], quote: u8) -> usize {
+    let mut i = 1;
+    while i < bytes.len() {
+        if bytes[i] == quote {
+            if bytes.get(i + 1) == Some(&quote) {
+                i += 2;
+                continue;
+            }
+            return i + 1;
+        }
+        i += 1;
+    }
+    bytes.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spans() {
+        let text = "create table ks.\"Users\" (id uuid, note text) -- it's\nWITH comment = 'don''t' AND ttl = 60;";
+        let spans = spans(text);
+
+        assert_eq!(spans.iter().map(|(_, s)| *s).collect::<String>(), text);
+        let classified: Vec<_> = spans.into_iter().filter(|(kind, _)| *kind != Kind::Plain).collect();
+        assert_eq!(
+            classified,
+            vec![
+                (Kind::Keyword, "create"),
+                (Kind::Keyword, "table"),
+                (Kind::Type, "uuid"),
+                (Kind::Type, "text"),
+                (Kind::Comment, "-- it's"),
+                (Kind::Keyword, "WITH"),
+                (Kind::String, "'don''t'"),
+                (Kind::Keyword, "AND"),
+                (Kind::Keyword, "ttl"),
+                (Kind::Number, "60"),
+            ]
+        );
+    }
+}
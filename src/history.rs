@@ -0,0 +1,429 @@
+//! Audit trail of changes to the tracking table
+//!
+//! Every apply, rollback, rename, checksum update, reset and restore is appended to
+//! `<table_name>_history` next to the tracking table, which only ever holds the
+//! current state. Events are kept until `history prune` deletes those older
+//! than `history.retention_days`; the latest event of every migration is never
+//! pruned, so each version keeps a record of how it got to its current state.
+//!
+//! Recording is best effort: a failed write is logged and the migration run
+//! carries on.
+
+use crate::{
+    cql::suffixed_identifier, drift::normalize_identifier, migration::MigrationManager, MigrationError,
+    MigrationRecord,
+};
+use chrono::{DateTime, TimeZone, Utc};
+use scylla::frame::value::CqlTimestamp;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use tracing::{debug, warn};
+
+/// Suffix of the history table, appended to `migrations.table_name`
+pub const HISTORY_TABLE_SUFFIX: &str = "_history";
+
+/// What happened to a migration's tracking record
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HistoryAction {
+    Applied,
+    RolledBack,
+    /// The record moved to another version (the event's `detail`)
+    RenamedTo,
+    /// The record moved here from another version (the event's `detail`)
+    RenamedFrom,
+    ChecksumUpdated,
+    /// The tracking table was reset while the migration was applied
+    Reset,
+    /// The record was written back from a backup (see [`crate::backup`])
+    Restored,
+    /// The record was deleted by `repair --remove-orphans` because its file is gone
+    OrphanRemoved,
+    /// `keyspace create` created the keyspace named by the event's version
+    /// (see [`keyspace_event_version`]) with the replication in `detail`
+    KeyspaceCreated,
+    /// `keyspace alter` changed the replication of a keyspace (`detail`)
+    KeyspaceAltered,
+}
+
+impl HistoryAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HistoryAction::Applied => "applied",
+            HistoryAction::RolledBack => "rolled_back",
+            HistoryAction::RenamedTo => "renamed_to",
+            HistoryAction::RenamedFrom => "renamed_from",
+            HistoryAction::ChecksumUpdated => "checksum_updated",
+            HistoryAction::Reset => "reset",
+            HistoryAction::Restored => "restored",
+            HistoryAction::OrphanRemoved => "orphan_removed",
+            HistoryAction::KeyspaceCreated => "keyspace_created",
+            HistoryAction::KeyspaceAltered => "keyspace_altered",
+        }
+    }
+
+    pub fn parse(action: &str) -> Option<Self> {
+        [
+            HistoryAction::Applied,
+            HistoryAction::RolledBack,
+            HistoryAction::RenamedTo,
+            HistoryAction::RenamedFrom,
+            HistoryAction::ChecksumUpdated,
+            HistoryAction::Reset,
+            HistoryAction::Restored,
+            HistoryAction::OrphanRemoved,
+            HistoryAction::KeyspaceCreated,
+            HistoryAction::KeyspaceAltered,
+        ]
+        .into_iter()
+        .find(|candidate| candidate.as_str() == action)
+    }
+}
+
+/// Version column of keyspace events, which never clashes with a migration version
+pub fn keyspace_event_version(keyspace: &str) -> String {
+    format!("keyspace:{}", keyspace)
+}
+
+/// One row of the history table
+#[derive(Debug, Clone, Serialize)]
+pub struct HistoryEvent {
+    pub version: String,
+    pub occurred_at: DateTime<Utc>,
+    /// One of [`HistoryAction`]; kept as text so events written by newer
+    /// versions still list
+    pub action: String,
+    pub checksum: Option<String>,
+    pub cluster_name: Option<String>,
+    pub detail: Option<String>,
+}
+
+/// Events older than `cutoff`, except the latest event of each version
+pub fn events_to_prune(events: &[HistoryEvent], cutoff: DateTime<Utc>) -> Vec<&HistoryEvent> {
+    let mut latest: HashMap<&str, DateTime<Utc>> = HashMap::new();
+    for event in events {
+        let entry = latest.entry(&event.version).or_insert(event.occurred_at);
+        if event.occurred_at > *entry {
+            *entry = event.occurred_at;
+        }
+    }
+
+    events
+        .iter()
+        .filter(|event| event.occurred_at < cutoff && latest[event.version.as_str()] != event.occurred_at)
+        .collect()
+}
+
+/// A migration as it stood at a past instant
+#[derive(Debug, Clone, Serialize)]
+pub struct PastRecord {
+    pub version: String,
+    /// When the migration was applied, as of the instant
+    pub applied_at: DateTime<Utc>,
+    pub checksum: Option<String>,
+    /// Whether the state was replayed from the history table rather than
+    /// taken from the tracking table
+    pub from_history: bool,
+}
+
+/// Migrations that were applied at `at`, ordered by version
+///
+/// Versions with events are replayed from `events` (oldest first). Versions
+/// without any, e.g. applied before the history table existed, count as
+/// applied from their tracking record's `applied_at`; rollbacks of such
+/// versions before `at` are not known.
+pub fn applied_at_instant(
+    events: &[HistoryEvent],
+    records: &[MigrationRecord],
+    at: DateTime<Utc>,
+) -> Vec<PastRecord> {
+    let mut applied: BTreeMap<String, PastRecord> = BTreeMap::new();
+    // Records moved away by a rename, keyed by the version they moved to
+    let mut renamed: HashMap<String, PastRecord> = HashMap::new();
+
+    for event in events.iter().filter(|event| event.occurred_at <= at) {
+        let applied_record = |applied_at| PastRecord {
+            version: event.version.clone(),
+            applied_at,
+            checksum: event.checksum.clone(),
+            from_history: true,
+        };
+        match HistoryAction::parse(&event.action) {
+            Some(HistoryAction::Applied | HistoryAction::Restored) => {
+                applied.insert(event.version.clone(), applied_record(event.occurred_at));
+            }
+            Some(HistoryAction::RenamedFrom) => {
+                let original = event.detail.as_deref().and_then(|from| {
+                    renamed
+                        .remove(&event.version)
+                        .or_else(|| applied.get(from).cloned())
+                });
+                let applied_at = original.map_or(event.occurred_at, |record| record.applied_at);
+                applied.insert(event.version.clone(), applied_record(applied_at));
+            }
+            Some(HistoryAction::RenamedTo) => {
+                if let (Some(record), Some(to)) = (applied.remove(&event.version), &event.detail) {
+                    renamed.insert(to.clone(), record);
+                }
+            }
+            Some(HistoryAction::ChecksumUpdated) => {
+                if let Some(record) = applied.get_mut(&event.version) {
+                    record.checksum = event.checksum.clone();
+                }
+            }
+            Some(HistoryAction::RolledBack | HistoryAction::Reset | HistoryAction::OrphanRemoved) => {
+                applied.remove(&event.version);
+            }
+            Some(HistoryAction::KeyspaceCreated | HistoryAction::KeyspaceAltered) | None => {}
+        }
+    }
+
+    let with_events: HashSet<&str> =
+        events.iter().map(|event| event.version.as_str()).collect();
+    for record in records {
+        if record.applied_at <= at && !with_events.contains(record.version.as_str()) {
+            applied.insert(
+                record.version.clone(),
+                PastRecord {
+                    version: record.version.clone(),
+                    applied_at: record.applied_at,
+                    checksum: Some(record.checksum.clone()),
+                    from_history: false,
+                },
+            );
+        }
+    }
+
+    applied.into_values().collect()
+}
+
+impl MigrationManager {
+    /// Keyspace-qualified name of the history table
+    pub fn history_table(&self) -> String {
+        self.get_config().history_table()
+    }
+
+    /// Create the history table once per session
+    async fn ensure_history_table(&self) -> Result<(), MigrationError> {
+        let table = self.history_table();
+        if self.is_initialized(&table) {
+            return Ok(());
+        }
+
+        let query = format!(
+            "CREATE TABLE IF NOT EXISTS {} (
+                version TEXT,
+                occurred_at TIMESTAMP,
+                action TEXT,
+                checksum TEXT,
+                cluster_name TEXT,
+                detail TEXT,
+                PRIMARY KEY (version, occurred_at)
+            ) WITH CLUSTERING ORDER BY (occurred_at DESC)",
+            table
+        );
+        debug!("Creating history table: {}", query);
+        self.session().query(query, &[]).await?;
+        self.mark_initialized(table);
+        Ok(())
+    }
+
+    /// Append an event to the history table, logging instead of failing
+    pub(crate) async fn record_event(
+        &self,
+        version: &str,
+        action: HistoryAction,
+        checksum: Option<&str>,
+        detail: Option<&str>,
+    ) {
+        if !self.get_config().history.enabled || self.get_config().behavior.read_only {
+            return;
+        }
+
+        let result = async {
+            self.ensure_history_table().await?;
+            let query = format!(
+                "INSERT INTO {} (version, occurred_at, action, checksum, cluster_name, detail) VALUES (?, ?, ?, ?, ?, ?)",
+                self.history_table()
+            );
+            self.session()
+                .query(
+                    query,
+                    (
+                        version,
+                        CqlTimestamp(Utc::now().timestamp_millis()),
+                        action.as_str(),
+                        checksum,
+                        self.cluster_name(),
+                        detail,
+                    ),
+                )
+                .await?;
+            Ok::<_, MigrationError>(())
+        }
+        .await;
+
+        if let Err(e) = result {
+            warn!("Could not record {} of {} in the history table: {}", action.as_str(), version, e);
+        }
+    }
+
+    /// Every recorded event, oldest first; empty if nothing was ever recorded
+    pub async fn history_events(&self) -> Result<Vec<HistoryEvent>, MigrationError> {
+        let config = self.get_config();
+        let rows = self
+            .session()
+            .query(
+                "SELECT table_name FROM system_schema.tables WHERE keyspace_name = ? AND table_name = ?",
+                (
+                    normalize_identifier(config.tracking_keyspace()),
+                    normalize_identifier(&suffixed_identifier(&config.migrations.table_name, HISTORY_TABLE_SUFFIX)),
+                ),
+            )
+            .await?;
+        if rows.rows.unwrap_or_default().is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let query = format!(
+            "SELECT version, occurred_at, action, checksum, cluster_name, detail FROM {}",
+            self.history_table()
+        );
+        let rows = self.session().query(query, &[]).await?;
+
+        let mut events = Vec::new();
+        for row in rows
+            .rows_typed::<(
+                String,
+                CqlTimestamp,
+                String,
+                Option<String>,
+                Option<String>,
+                Option<String>,
+            )>()
+            .map_err(|e| MigrationError::IntegrityError(e.to_string()))?
+        {
+            let (version, occurred_at, action, checksum, cluster_name, detail) =
+                row.map_err(|e| MigrationError::IntegrityError(e.to_string()))?;
+            let occurred_at = Utc
+                .timestamp_millis_opt(occurred_at.0)
+                .single()
+                .ok_or_else(|| MigrationError::IntegrityError("Invalid timestamp".into()))?;
+
+            events.push(HistoryEvent {
+                version,
+                occurred_at,
+                action,
+                checksum,
+                cluster_name,
+                detail,
+            });
+        }
+
+        events.sort_by_key(|event| event.occurred_at);
+        Ok(events)
+    }
+
+    /// Delete events from the history table
+    pub async fn delete_history_events(&self, events: &[&HistoryEvent]) -> Result<(), MigrationError> {
+        self.ensure_writable()?;
+
+        let query = format!(
+            "DELETE FROM {} WHERE version = ? AND occurred_at = ?",
+            self.history_table()
+        );
+        for event in events {
+            self.session()
+                .query(
+                    query.as_str(),
+                    (&event.version, CqlTimestamp(event.occurred_at.timestamp_millis())),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn event(version: &str, days_ago: i64, action: HistoryAction) -> HistoryEvent {
+        HistoryEvent {
+            version: version.to_string(),
+            occurred_at: Utc.with_ymd_and_hms(2025, 6, 1, 0, 0, 0).unwrap() - Duration::days(days_ago),
+            action: action.as_str().to_string(),
+            checksum: None,
+            cluster_name: None,
+            detail: None,
+        }
+    }
+
+    #[test]
+    fn test_events_to_prune() {
+        let events = vec![
+            event("20250101_001_users", 100, HistoryAction::Applied),
+            event("20250101_001_users", 90, HistoryAction::RolledBack),
+            event("20250101_001_users", 80, HistoryAction::Applied),
+            event("20250101_002_orders", 70, HistoryAction::Applied),
+            event("20250101_003_items", 40, HistoryAction::Applied),
+            event("20250101_003_items", 5, HistoryAction::ChecksumUpdated),
+        ];
+        let cutoff = Utc.with_ymd_and_hms(2025, 6, 1, 0, 0, 0).unwrap() - Duration::days(30);
+
+        let pruned: Vec<_> = events_to_prune(&events, cutoff)
+            .into_iter()
+            .map(|event| (event.version.as_str(), event.action.as_str()))
+            .collect();
+        assert_eq!(
+            pruned,
+            vec![
+                ("20250101_001_users", "applied"),
+                ("20250101_001_users", "rolled_back"),
+                ("20250101_003_items", "applied"),
+            ]
+        );
+        assert_eq!(HistoryAction::parse("renamed_from"), Some(HistoryAction::RenamedFrom));
+    }
+
+    #[test]
+    fn test_applied_at_instant() {
+        let renamed_to = HistoryEvent {
+            detail: Some("20250101_004_items".to_string()),
+            ..event("20250101_003_items", 20, HistoryAction::RenamedTo)
+        };
+        let renamed_from = HistoryEvent {
+            detail: Some("20250101_003_items".to_string()),
+            ..event("20250101_004_items", 20, HistoryAction::RenamedFrom)
+        };
+        let events = vec![
+            event("20250101_002_orders", 60, HistoryAction::Applied),
+            event("20250101_003_items", 50, HistoryAction::Applied),
+            event("20250101_002_orders", 40, HistoryAction::RolledBack),
+            renamed_to,
+            renamed_from,
+        ];
+        let records = vec![MigrationRecord::for_test(
+            "20250101_001_users",
+            Utc.with_ymd_and_hms(2025, 6, 1, 0, 0, 0).unwrap() - Duration::days(90),
+        )];
+        let at = |days_ago: i64| Utc.with_ymd_and_hms(2025, 6, 1, 0, 0, 0).unwrap() - Duration::days(days_ago);
+        let versions = |days_ago: i64| -> Vec<String> {
+            applied_at_instant(&events, &records, at(days_ago))
+                .into_iter()
+                .map(|record| record.version)
+                .collect()
+        };
+
+        assert!(versions(100).is_empty());
+        assert_eq!(versions(55), vec!["20250101_001_users", "20250101_002_orders"]);
+        assert_eq!(versions(45), vec!["20250101_001_users", "20250101_002_orders", "20250101_003_items"]);
+        assert_eq!(versions(30), vec!["20250101_001_users", "20250101_003_items"]);
+        assert_eq!(versions(10), vec!["20250101_001_users", "20250101_004_items"]);
+
+        let renamed = applied_at_instant(&events, &records, at(10)).pop().unwrap();
+        assert_eq!(renamed.applied_at, at(50));
+        assert!(renamed.from_history);
+    }
+}
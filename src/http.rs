@@ -0,0 +1,47 @@
+//! Blocking JSON requests to approval webhooks and the release endpoint
+//!
+//! Requests need the `webhooks` or `update-check` feature; without either they
+//! fail with an error naming the missing feature.
+
+#[cfg(any(feature = "webhooks", feature = "update-check"))]
+mod client {
+    use serde::{de::DeserializeOwned, Serialize};
+    use std::time::Duration;
+
+    /// Timeout of a single request
+    const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+    fn agent() -> ureq::Agent {
+        ureq::Agent::config_builder()
+            .timeout_global(Some(REQUEST_TIMEOUT))
+            .build()
+            .into()
+    }
+
+    pub fn post_json(url: &str, body: &impl Serialize) -> Result<String, String> {
+        let reply = || -> Result<String, ureq::Error> { agent().post(url).send_json(body)?.body_mut().read_to_string() };
+        reply().map_err(|e| e.to_string())
+    }
+
+    pub fn get_json<T: DeserializeOwned>(url: &str) -> Result<T, String> {
+        let reply = || -> Result<T, ureq::Error> { agent().get(url).call()?.body_mut().read_json() };
+        reply().map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(not(any(feature = "webhooks", feature = "update-check")))]
+mod client {
+    use serde::{de::DeserializeOwned, Serialize};
+
+    const DISABLED: &str = "db-migrate was built without the webhooks and update-check features";
+
+    pub fn post_json(_url: &str, _body: &impl Serialize) -> Result<String, String> {
+        Err(DISABLED.to_string())
+    }
+
+    pub fn get_json<T: DeserializeOwned>(_url: &str) -> Result<T, String> {
+        Err(DISABLED.to_string())
+    }
+}
+
+pub use client::{get_json, post_json};
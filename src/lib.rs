@@ -1,9 +1,51 @@
+pub mod annotations;
+pub mod approval;
+pub mod autodown;
+pub mod backup;
+pub mod checkpoint;
+pub mod cluster;
 pub mod commands;
+pub mod compat;
 pub mod config;
+pub mod confirm;
+pub mod cql;
+pub mod diagnostics;
+pub mod directives;
+pub mod drift;
+pub mod estimate;
+pub mod fmt;
+pub mod grants;
+pub mod highlight;
+pub mod history;
+pub mod http;
+pub mod lint;
+pub mod lock;
+pub mod messages;
 pub mod migration;
+#[cfg(feature = "operator")]
+pub mod operator;
+pub mod placeholders;
+pub mod plugins;
+pub mod policy;
+pub mod progress;
+pub mod queue;
+pub mod report;
+pub mod runreport;
 pub mod schema;
+#[cfg(any(feature = "bench", feature = "sandbox"))]
+pub mod scratch;
+pub mod schemadiff;
+pub mod snapshot;
+pub mod statements;
+pub mod stream;
+pub mod template;
+pub mod tenants;
+pub mod tls;
 pub mod utils;
+pub mod version;
+pub mod wasm;
 
+use annotations::Annotation;
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
 
@@ -14,6 +56,39 @@ pub struct MigrationRecord {
     pub applied_at: DateTime<Utc>,
     pub checksum: String,
     pub description: String,
+    /// Name of the cluster the migration was applied on (absent for older records)
+    pub cluster_name: Option<String>,
+    /// Keyspace the migration targeted (absent for older records, meaning `database.keyspace`)
+    pub target_keyspace: Option<String>,
+    /// How long applying the migration took, in milliseconds (absent for older records)
+    #[serde(default)]
+    pub duration_ms: Option<i64>,
+    /// Version of db-migrate that recorded the migration (absent for older records)
+    #[serde(default)]
+    pub tool_version: Option<String>,
+    /// Checksum of the file without its DOWN section (see
+    /// [`MigrationFile::up_checksum`]; absent for older records)
+    #[serde(default)]
+    pub up_checksum: Option<String>,
+}
+
+impl MigrationRecord {
+    /// A record of `version` applied at `applied_at`, with only the columns of
+    /// the first tracking table, for tests
+    #[cfg(test)]
+    pub(crate) fn for_test(version: &str, applied_at: DateTime<Utc>) -> Self {
+        Self {
+            version: version.to_string(),
+            applied_at,
+            checksum: "abc".to_string(),
+            description: utils::extract_description_from_filename(version, &version::VersionScheme::Timestamp),
+            cluster_name: None,
+            target_keyspace: None,
+            duration_ms: None,
+            tool_version: None,
+            up_checksum: None,
+        }
+    }
 }
 
 /// Represents a migration file on disk
@@ -24,6 +99,46 @@ pub struct MigrationFile {
     pub file_path: std::path::PathBuf,
     pub content: String,
     pub checksum: String,
+    pub directives: directives::Directives,
+    /// The file exceeds [`stream::STREAMING_THRESHOLD`]: `content` only holds its
+    /// leading comments (directives) and statements are read from `file_path`
+    pub streamed: bool,
+}
+
+impl MigrationFile {
+    /// Checksum of the file without its DOWN section, telling rollback fixes
+    /// apart from changes to what was applied
+    ///
+    /// Streamed files are not held in memory, so any change to them counts.
+    pub fn up_checksum(&self) -> String {
+        if self.streamed {
+            self.checksum.clone()
+        } else {
+            utils::calculate_up_checksum(&self.content)
+        }
+    }
+
+    /// A migration file `<version>.cql` with `content`, for tests
+    #[cfg(test)]
+    pub(crate) fn for_test(version: &str, content: &str) -> Self {
+        Self {
+            version: version.to_string(),
+            description: utils::extract_description_from_filename(version, &version::VersionScheme::Timestamp),
+            file_path: format!("{}.cql", version).into(),
+            content: content.to_string(),
+            checksum: utils::calculate_checksum(content),
+            directives: directives::Directives::parse(content),
+            streamed: false,
+        }
+    }
+}
+
+/// A `.cql` file in the migrations directory whose name is not a migration
+/// version, so it is neither applied nor pending
+#[derive(Debug, Clone, Serialize)]
+pub struct InvalidMigrationFile {
+    pub filename: String,
+    pub reason: String,
 }
 
 /// Represents the result of a command execution
@@ -33,6 +148,18 @@ pub struct CommandOutput {
     pub message: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub data: Option<serde_json::Value>,
+    /// Stable code of the error that made the command fail (see [`MigrationError::code`])
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<&'static str>,
+    /// Process exit code
+    pub exit_code: i32,
+    /// Records printed instead of the message with `--porcelain`
+    #[serde(skip)]
+    pub porcelain: Option<Vec<Vec<String>>>,
+    /// Findings printed instead of the message with `--output gh-annotations`,
+    /// `--output gitlab-codequality` and `--output sarif`
+    #[serde(skip)]
+    pub annotations: Option<Vec<Annotation>>,
 }
 
 impl CommandOutput {
@@ -41,6 +168,10 @@ impl CommandOutput {
             success: true,
             message: message.into(),
             data: None,
+            code: None,
+            exit_code: exit_code::SUCCESS,
+            porcelain: None,
+            annotations: None,
         }
     }
 
@@ -49,6 +180,10 @@ impl CommandOutput {
             success: true,
             message: message.into(),
             data: Some(data),
+            code: None,
+            exit_code: exit_code::SUCCESS,
+            porcelain: None,
+            annotations: None,
         }
     }
 
@@ -57,16 +192,134 @@ impl CommandOutput {
             success: false,
             message: message.into(),
             data: None,
+            code: None,
+            exit_code: exit_code::FAILURE,
+            porcelain: None,
+            annotations: None,
+        }
+    }
+
+    pub fn error_with_data(message: impl Into<String>, data: serde_json::Value) -> Self {
+        Self {
+            success: false,
+            message: message.into(),
+            data: Some(data),
+            code: None,
+            exit_code: exit_code::FAILURE,
+            porcelain: None,
+            annotations: None,
         }
     }
 }
 
+impl CommandOutput {
+    /// Mark the output as failed because of `error`, attaching its code
+    pub fn with_error(mut self, error: &MigrationError) -> Self {
+        self.success = false;
+        self.code = Some(error.code());
+        self.exit_code = error.exit_code();
+        self
+    }
+
+    /// Attach the records printed by `--porcelain`, one line each
+    pub fn with_porcelain(mut self, records: Vec<Vec<String>>) -> Self {
+        self.porcelain = Some(records);
+        self
+    }
+
+    /// Attach the findings printed by `--output gh-annotations`,
+    /// `--output gitlab-codequality` and `--output sarif`
+    pub fn with_annotations(mut self, annotations: Vec<Annotation>) -> Self {
+        self.annotations = Some(annotations);
+        self
+    }
+
+    /// Porcelain output: tab-separated records, or the message for commands
+    /// without them
+    pub fn porcelain_text(&self) -> String {
+        match &self.porcelain {
+            Some(records) => records
+                .iter()
+                .map(|record| porcelain_line(record))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            None => messages::render(&self.message).into_owned(),
+        }
+    }
+
+    /// GitHub Actions workflow commands, or the message for commands without
+    /// annotations
+    pub fn gh_annotations_text(&self) -> String {
+        match &self.annotations {
+            Some(annotations) => annotations::github(annotations),
+            None => messages::render(&self.message).into_owned(),
+        }
+    }
+
+    /// SARIF log, or the message for commands without annotations
+    pub fn sarif_text(&self) -> String {
+        match &self.annotations {
+            Some(annotations) => serde_json::to_string_pretty(&annotations::sarif(annotations)).unwrap_or_default(),
+            None => messages::render(&self.message).into_owned(),
+        }
+    }
+
+    /// GitLab Code Quality report, or the message for commands without annotations
+    pub fn gitlab_codequality_text(&self) -> String {
+        match &self.annotations {
+            Some(annotations) => serde_json::to_string_pretty(&annotations::gitlab(annotations))
+                .unwrap_or_default(),
+            None => messages::render(&self.message).into_owned(),
+        }
+    }
+}
+
+/// One porcelain record; tabs and line breaks inside fields become spaces and
+/// empty fields are written as `-` so every line has the same number of columns
+pub fn porcelain_line(fields: &[String]) -> String {
+    fields
+        .iter()
+        .map(|field| {
+            if field.is_empty() {
+                "-".to_string()
+            } else {
+                field.replace(['\t', '\r', '\n'], " ")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\t")
+}
+
 impl std::fmt::Display for CommandOutput {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.message)
+        write!(f, "{}", messages::render(&self.message))
     }
 }
 
+/// Process exit codes, one per class of failure
+pub mod exit_code {
+    pub const SUCCESS: i32 = 0;
+    /// The command ran but reported a failure not covered below
+    pub const FAILURE: i32 = 1;
+    /// Invalid configuration or TLS settings
+    pub const CONFIG: i32 = 2;
+    /// Applied history does not match the migration files
+    pub const INTEGRITY: i32 = 3;
+    /// The cluster could not be reached
+    pub const CONNECTION: i32 = 4;
+    /// A statement failed or a migration could not be rolled back
+    pub const EXECUTION: i32 = 5;
+    /// A migration file is missing, unreadable or malformed
+    pub const MIGRATION_FILE: i32 = 6;
+    /// Another run held the cluster-wide migration lock for too long
+    pub const LOCK_TIMEOUT: i32 = 7;
+    /// The migration lock was lost during a run, which stopped before its next
+    /// statement
+    pub const LOCK_LOST: i32 = 8;
+    /// Interrupted (Ctrl-C or SIGTERM)
+    pub const CANCELLED: i32 = 130;
+}
+
 /// Custom error types for the migration tool
 #[derive(thiserror::Error, Debug)]
 pub enum MigrationError {
@@ -82,6 +335,9 @@ pub enum MigrationError {
     #[error("Configuration error: {0}")]
     ConfigError(String),
 
+    #[error("TLS error: {0}")]
+    TlsError(String),
+
     #[error("Migration integrity error: {0}")]
     IntegrityError(String),
 
@@ -95,12 +351,215 @@ pub enum MigrationError {
         actual: String,
     },
 
+    #[error("DOWN section of migration {version} changed since it was applied")]
+    DownChecksumMismatch {
+        version: String,
+        expected: String,
+        actual: String,
+    },
+
     #[error("Cannot rollback migration {version}: {reason}")]
     RollbackError { version: String, reason: String },
 
+    #[error("Migration {version} is irreversible (declared with -- db-migrate:no-down)")]
+    Irreversible { version: String },
+
     #[error("Migration {version} is already applied")]
     AlreadyApplied { version: String },
 
     #[error("Invalid migration format: {0}")]
     InvalidFormat(String),
-}
\ No newline at end of file
+
+    #[error("Pending migration {version} sorts before applied migration {latest_applied}")]
+    OutOfOrder {
+        version: String,
+        latest_applied: String,
+    },
+
+    #[error("Applied migration {version} no longer parses: {reason}")]
+    UnparseableMigration { version: String, reason: String },
+
+    #[error("Migration {version} cancelled after {statements_executed} statement(s)")]
+    Cancelled {
+        version: String,
+        statements_executed: usize,
+    },
+
+    #[error("Gave up on {operation} after waiting {waited_secs}s for the run in progress")]
+    QueueTimeout { operation: String, waited_secs: u64 },
+
+    #[error("Plugin {plugin} failed: {reason}")]
+    PluginError { plugin: String, reason: String },
+
+    #[error("Migration {version} was recorded by db-migrate {written_by}, which is incompatible with this version ({current}); upgrade db-migrate to operate on this tracking table")]
+    IncompatibleToolVersion {
+        version: String,
+        written_by: String,
+        current: String,
+    },
+
+    #[error("Plan {plan_id} was not approved: {reason}")]
+    NotApproved { plan_id: String, reason: String },
+
+    #[error("Gave up after waiting {waited_secs}s for the migration lock held by {}", holder.as_deref().unwrap_or("another run"))]
+    LockTimeout {
+        holder: Option<String>,
+        waited_secs: u64,
+    },
+
+    #[error("Migration {version} stopped after {statements_executed} statement(s): the migration lock was lost ({reason})")]
+    LockLost {
+        version: String,
+        statements_executed: usize,
+        reason: String,
+    },
+
+    #[error("Schema disagreement: {dissenting} of {nodes} node(s) report another schema version")]
+    SchemaDisagreement { dissenting: usize, nodes: usize },
+
+    #[error("No migrations were applied, refused by {check}: {}", reasons.join("; "))]
+    Refused { check: String, reasons: Vec<String> },
+}
+
+impl MigrationError {
+    /// Stable identifier for scripts, included in JSON output
+    pub fn code(&self) -> &'static str {
+        match self {
+            MigrationError::DatabaseError(_) => "E_CONNECTION",
+            MigrationError::QueryError(_) => "E_QUERY",
+            MigrationError::FileError(_) => "E_FILE",
+            MigrationError::ConfigError(_) => "E_CONFIG",
+            MigrationError::TlsError(_) => "E_TLS",
+            MigrationError::IntegrityError(_) => "E_INTEGRITY",
+            MigrationError::MigrationNotFound(_) => "E_MIGRATION_NOT_FOUND",
+            MigrationError::ChecksumMismatch { .. } => "E_CHECKSUM_MISMATCH",
+            MigrationError::DownChecksumMismatch { .. } => "E_DOWN_CHECKSUM_MISMATCH",
+            MigrationError::RollbackError { .. } => "E_ROLLBACK",
+            MigrationError::Irreversible { .. } => "E_IRREVERSIBLE",
+            MigrationError::AlreadyApplied { .. } => "E_ALREADY_APPLIED",
+            MigrationError::InvalidFormat(_) => "E_INVALID_FORMAT",
+            MigrationError::OutOfOrder { .. } => "E_OUT_OF_ORDER",
+            MigrationError::UnparseableMigration { .. } => "E_UNPARSEABLE_MIGRATION",
+            MigrationError::Cancelled { .. } => "E_CANCELLED",
+            MigrationError::QueueTimeout { .. } => "E_QUEUE_TIMEOUT",
+            MigrationError::PluginError { .. } => "E_PLUGIN",
+            MigrationError::IncompatibleToolVersion { .. } => "E_INCOMPATIBLE_TOOL_VERSION",
+            MigrationError::NotApproved { .. } => "E_NOT_APPROVED",
+            MigrationError::LockTimeout { .. } => "E_LOCK_TIMEOUT",
+            MigrationError::LockLost { .. } => "E_LOCK_LOST",
+            MigrationError::SchemaDisagreement { .. } => "E_SCHEMA_DISAGREEMENT",
+            MigrationError::Refused { .. } => "E_REFUSED",
+        }
+    }
+
+    /// Process exit code for the class of this error, see [`exit_code`]
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            MigrationError::ConfigError(_) | MigrationError::TlsError(_) => exit_code::CONFIG,
+            MigrationError::IntegrityError(_)
+            | MigrationError::ChecksumMismatch { .. }
+            | MigrationError::DownChecksumMismatch { .. }
+            | MigrationError::AlreadyApplied { .. }
+            | MigrationError::OutOfOrder { .. }
+            | MigrationError::UnparseableMigration { .. }
+            | MigrationError::IncompatibleToolVersion { .. } => exit_code::INTEGRITY,
+            MigrationError::DatabaseError(_) => exit_code::CONNECTION,
+            MigrationError::QueryError(_)
+            | MigrationError::RollbackError { .. }
+            | MigrationError::Irreversible { .. } => exit_code::EXECUTION,
+            MigrationError::FileError(_)
+            | MigrationError::MigrationNotFound(_)
+            | MigrationError::InvalidFormat(_) => exit_code::MIGRATION_FILE,
+            MigrationError::Cancelled { .. } => exit_code::CANCELLED,
+            MigrationError::LockTimeout { .. } => exit_code::LOCK_TIMEOUT,
+            MigrationError::LockLost { .. } => exit_code::LOCK_LOST,
+            MigrationError::QueueTimeout { .. }
+            | MigrationError::PluginError { .. }
+            | MigrationError::NotApproved { .. }
+            | MigrationError::SchemaDisagreement { .. }
+            | MigrationError::Refused { .. } => exit_code::FAILURE,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_porcelain_line() {
+        let fields = vec!["applied".to_string(), String::new(), "bad\tname\r\nhere".to_string()];
+        assert_eq!(porcelain_line(&fields), "applied\t-\tbad name  here");
+    }
+
+    #[test]
+    fn test_error_codes_and_exit_codes() {
+        let version = || "20250101_001".to_string();
+        let cases = [
+            (MigrationError::ConfigError("bad".into()), "E_CONFIG", exit_code::CONFIG),
+            (MigrationError::TlsError("bad".into()), "E_TLS", exit_code::CONFIG),
+            (
+                MigrationError::ChecksumMismatch { version: version(), expected: "a".into(), actual: "b".into() },
+                "E_CHECKSUM_MISMATCH",
+                exit_code::INTEGRITY,
+            ),
+            (
+                MigrationError::OutOfOrder { version: version(), latest_applied: version() },
+                "E_OUT_OF_ORDER",
+                exit_code::INTEGRITY,
+            ),
+            (
+                MigrationError::RollbackError { version: version(), reason: "no DOWN".into() },
+                "E_ROLLBACK",
+                exit_code::EXECUTION,
+            ),
+            (MigrationError::Irreversible { version: version() }, "E_IRREVERSIBLE", exit_code::EXECUTION),
+            (
+                MigrationError::FileError(std::io::Error::new(std::io::ErrorKind::NotFound, "gone")),
+                "E_FILE",
+                exit_code::MIGRATION_FILE,
+            ),
+            (MigrationError::InvalidFormat("no UP".into()), "E_INVALID_FORMAT", exit_code::MIGRATION_FILE),
+            (
+                MigrationError::Cancelled { version: version(), statements_executed: 2 },
+                "E_CANCELLED",
+                exit_code::CANCELLED,
+            ),
+            (MigrationError::LockTimeout { holder: None, waited_secs: 30 }, "E_LOCK_TIMEOUT", exit_code::LOCK_TIMEOUT),
+            (
+                MigrationError::LockLost { version: version(), statements_executed: 1, reason: "expired".into() },
+                "E_LOCK_LOST",
+                exit_code::LOCK_LOST,
+            ),
+            (
+                MigrationError::Refused { check: "policy".into(), reasons: vec!["no".into()] },
+                "E_REFUSED",
+                exit_code::FAILURE,
+            ),
+        ];
+
+        let mut codes = Vec::new();
+        for (error, code, exit) in &cases {
+            assert_eq!(error.code(), *code);
+            assert_eq!(error.exit_code(), *exit, "exit code of {}", code);
+            codes.push(error.code());
+
+            // Commands that report a failed output carry the same code and exit code
+            let output = CommandOutput::success("done").with_error(error);
+            let json = serde_json::to_value(&output).unwrap();
+            assert_eq!(json["success"], false);
+            assert_eq!(json["code"], *code);
+            assert_eq!(output.exit_code, *exit);
+        }
+        codes.sort_unstable();
+        codes.dedup();
+        assert_eq!(codes.len(), cases.len());
+
+        // Errors returned with context are still classified
+        let error = anyhow::Error::from(MigrationError::LockTimeout { holder: None, waited_secs: 1 }).context("up failed");
+        let found = error.chain().find_map(|cause| cause.downcast_ref::<MigrationError>());
+        assert_eq!(found.map(MigrationError::exit_code), Some(exit_code::LOCK_TIMEOUT));
+        assert_eq!(CommandOutput::success("ok").exit_code, exit_code::SUCCESS);
+        assert_eq!(CommandOutput::error("failed").exit_code, exit_code::FAILURE);
+    }
+}
@@ -0,0 +1,643 @@
+use crate::{
+    autodown::suggest_down,
+    config::{Config, DownValidation, LimitsConfig},
+    cql::{self, Issue},
+    placeholders::SECRET_PREFIX,
+    statements::{self, Line, SectionTracker, Statement},
+    stream::Section,
+    utils::{parse_migration_content, split_cql_statements, DOWN_TODO},
+    MigrationFile,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+/// A check producing findings, with what code-scanning dashboards show about it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Rule {
+    pub id: &'static str,
+    /// What the rule catches, in one sentence
+    pub description: &'static str,
+    /// Documentation of the rule
+    pub help_uri: &'static str,
+}
+
+impl Rule {
+    /// The lint rule, `verify` issue type or `hooks check` rule with this id
+    pub fn find(id: &str) -> Option<&'static Rule> {
+        Self::all().find(|rule| rule.id == id)
+    }
+
+    /// Every rule findings and annotations refer to
+    pub fn all() -> impl Iterator<Item = &'static Rule> {
+        RULES
+            .iter()
+            .chain(crate::report::VERIFY_RULES)
+            .chain(crate::snapshot::SNAPSHOT_RULES)
+    }
+}
+
+/// Rules of `lint`, and of `up` and `verify` where they run the same checks
+pub const RULES: &[Rule] = &[
+    Rule {
+        id: "up-section",
+        description: "The migration file has no UP section that can be parsed",
+        help_uri: "https://github.com/dimuziop/db-migrate#file-structure",
+    },
+    Rule {
+        id: "down-section",
+        description: "The DOWN section is missing, empty or unusable, as judged by behavior.down_validation and the no-down, require-down and auto-down directives",
+        help_uri: "https://github.com/dimuziop/db-migrate#lint",
+    },
+    Rule {
+        id: "cql-syntax",
+        description: "Unbalanced brackets, unterminated strings, unknown statements or malformed types",
+        help_uri: "https://github.com/dimuziop/db-migrate#lint",
+    },
+    Rule {
+        id: "unknown-type",
+        description: "Types that are neither native nor created by a CREATE TYPE migration",
+        help_uri: "https://github.com/dimuziop/db-migrate#lint",
+    },
+    Rule {
+        id: "unqualified-name",
+        description: "Tables and types without a keyspace qualifier (with lint.require_qualified_names)",
+        help_uri: "https://github.com/dimuziop/db-migrate#lint",
+    },
+    Rule {
+        id: "plaintext-password",
+        description: "Role passwords written literally instead of as ${secret:NAME}",
+        help_uri: "https://github.com/dimuziop/db-migrate#secrets-in-migrations",
+    },
+    Rule {
+        id: "use-statement",
+        description: "USE statements, which only switch the keyspace until the migration completes",
+        help_uri: "https://github.com/dimuziop/db-migrate#lint",
+    },
+    Rule {
+        id: "ddl-dml-mix",
+        description: "Sections mixing schema changes with INSERT, UPDATE or DELETE statements",
+        help_uri: "https://github.com/dimuziop/db-migrate#lint",
+    },
+    Rule {
+        id: "batch-dml",
+        description: "Statements other than INSERT, UPDATE and DELETE in a migration with the batch directive",
+        help_uri: "https://github.com/dimuziop/db-migrate#directives",
+    },
+    Rule {
+        id: "file-size",
+        description: "Files larger than limits.max_file_size_kb, such as a committed data dump",
+        help_uri: "https://github.com/dimuziop/db-migrate#lint",
+    },
+    Rule {
+        id: "statement-count",
+        description: "UP or DOWN sections with more than limits.max_statements statements",
+        help_uri: "https://github.com/dimuziop/db-migrate#lint",
+    },
+    Rule {
+        id: "policy",
+        description: "Patterns forbidden by a rule of the policy file",
+        help_uri: "https://github.com/dimuziop/db-migrate#lint",
+    },
+    Rule {
+        id: "plugin",
+        description: "Migrations a policy plugin warned about or denied",
+        help_uri: "https://github.com/dimuziop/db-migrate#policy-plugins",
+    },
+];
+
+/// A problem found in a migration file without touching the database
+#[derive(Debug, Clone, Serialize)]
+pub struct Finding {
+    pub rule: &'static str,
+    pub severity: Severity,
+    pub version: String,
+    pub file: PathBuf,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    pub message: String,
+    /// Name of the violated rule from the policy file (see [`crate::policy`])
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub policy_rule: Option<String>,
+}
+
+impl std::fmt::Display for Finding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "[{}] {}:{} - {}", self.rule, self.version, line, self.message),
+            None => write!(f, "[{}] {} - {}", self.rule, self.version, self.message),
+        }
+    }
+}
+
+impl Finding {
+    pub(crate) fn new(
+        rule: &'static str,
+        severity: Severity,
+        file: &MigrationFile,
+        line: Option<usize>,
+        message: impl Into<String>,
+    ) -> Self {
+        Self {
+            rule,
+            severity,
+            version: file.version.clone(),
+            file: file.file_path.clone(),
+            line,
+            message: message.into(),
+            policy_rule: None,
+        }
+    }
+
+    pub(crate) fn with_policy_rule(mut self, name: &str) -> Self {
+        self.policy_rule = Some(name.to_string());
+        self
+    }
+}
+
+/// Runs lint rules with knowledge of the whole migration set
+pub struct Linter<'a> {
+    config: &'a Config,
+    /// User-defined types created anywhere in the migration set
+    user_types: HashSet<String>,
+}
+
+impl<'a> Linter<'a> {
+    pub fn new(config: &'a Config, files: &[MigrationFile]) -> Self {
+        let user_types = files
+            .iter()
+            .flat_map(|file| split_cql_statements(&file.content))
+            .filter_map(|statement| cql::created_type_name(&statement))
+            .collect();
+
+        Self { config, user_types }
+    }
+
+    /// Run all lint rules against a migration file
+    pub fn lint(&self, file: &MigrationFile) -> Vec<Finding> {
+        let mut findings = Vec::new();
+
+        findings.extend(check_limits(file, &self.config.limits));
+
+        // Files too large to load are only checked as their statements execute
+        if file.streamed {
+            return findings;
+        }
+
+        if let Err(reason) = parse_migration_content(&file.content) {
+            findings.push(Finding::new("up-section", Severity::Error, file, None, reason));
+            return findings;
+        }
+
+        findings.extend(check_down_section(file, self.config.behavior.down_validation));
+        findings.extend(self.config.policy.check(file));
+
+        for section in [Section::Up, Section::Down] {
+            let statements = statements::split_section(&file.content, section);
+            findings.extend(self.check_mixed(file, &statements));
+            for statement in &statements {
+                findings.extend(self.check_statement(file, statement));
+            }
+        }
+
+        findings
+    }
+
+    /// Schema changes and data writes in one section: writes may reach nodes that
+    /// have not seen the schema change yet, and a failure leaves half of each
+    fn check_mixed(&self, file: &MigrationFile, statements: &[Statement]) -> Option<Finding> {
+        if file.directives.allow_mixed {
+            return None;
+        }
+        let first_ddl = statements.iter().position(|s| cql::is_schema_change(&s.text))?;
+        let first_dml = statements.iter().position(|s| cql::is_dml(&s.text))?;
+        let second = &statements[first_ddl.max(first_dml)];
+
+        Some(Finding::new(
+            "ddl-dml-mix",
+            if self.config.lint.separate_ddl_dml {
+                Severity::Error
+            } else {
+                Severity::Warning
+            },
+            file,
+            Some(second.first_line),
+            "Schema changes and data writes are mixed; split them into separate migrations or add '-- db-migrate:allow-mixed'",
+        ))
+    }
+
+    fn check_statement(&self, file: &MigrationFile, statement: &Statement) -> Vec<Finding> {
+        let line = Some(statement.first_line);
+        let statement = statement.text.as_str();
+        let mut findings = Vec::new();
+
+        if cql::sets_password(statement) && !statement.contains(SECRET_PREFIX) {
+            findings.push(Finding::new(
+                "plaintext-password",
+                Severity::Warning,
+                file,
+                line,
+                format!(
+                    "Role password is committed in plain text; use '{}NAME}}' instead",
+                    SECRET_PREFIX
+                ),
+            ));
+        }
+
+        if let Some(keyspace) = cql::used_keyspace(statement) {
+            findings.push(Finding::new(
+                "use-statement",
+                Severity::Warning,
+                file,
+                line,
+                format!(
+                    "USE switches to '{}' only until this migration completes; prefer keyspace-qualified names",
+                    keyspace
+                ),
+            ));
+        }
+
+        if file.directives.execution.batch.is_some() && !cql::is_dml(statement) {
+            findings.push(Finding::new(
+                "batch-dml",
+                Severity::Error,
+                file,
+                line,
+                "The batch directive only applies to INSERT, UPDATE and DELETE statements",
+            ));
+        }
+
+        let issues = cql::check_statement(
+            statement,
+            &self.user_types,
+            self.config.lint.require_qualified_names,
+        )
+        .into_iter()
+        .map(|issue| match issue {
+            Issue::Syntax(message) => {
+                Finding::new("cql-syntax", Severity::Error, file, line, message)
+            }
+            Issue::UnknownType(name) => Finding::new(
+                "unknown-type",
+                Severity::Warning,
+                file,
+                line,
+                format!("Unknown type '{}' (not a native type or a user-defined type created by a migration)", name),
+            ),
+            Issue::Unqualified(name) => Finding::new(
+                "unqualified-name",
+                Severity::Warning,
+                file,
+                line,
+                format!("'{}' is not keyspace-qualified", name),
+            ),
+        });
+
+        findings.extend(issues);
+        findings
+    }
+}
+
+/// Check a migration against the file size and statement count limits
+///
+/// Findings are errors when `limits.enforce` is set, warnings otherwise.
+pub fn check_limits(file: &MigrationFile, limits: &LimitsConfig) -> Vec<Finding> {
+    let severity = if limits.enforce {
+        Severity::Error
+    } else {
+        Severity::Warning
+    };
+    let mut findings = Vec::new();
+
+    let size = if file.streamed {
+        std::fs::metadata(&file.file_path).map(|m| m.len()).unwrap_or_default()
+    } else {
+        file.content.len() as u64
+    };
+    if limits.max_file_size_kb > 0 && size > limits.max_file_size_kb * 1024 {
+        findings.push(Finding::new(
+            "file-size",
+            severity,
+            file,
+            None,
+            format!(
+                "File is {} KB, over the {} KB limit; is this a data dump?",
+                size / 1024,
+                limits.max_file_size_kb
+            ),
+        ));
+    }
+
+    if limits.max_statements > 0 && !file.streamed {
+        if let Ok((up, down)) = parse_migration_content(&file.content) {
+            for (section, content) in [("UP", Some(up)), ("DOWN", down)] {
+                let count = split_cql_statements(content.as_deref().unwrap_or("")).len();
+                if count > limits.max_statements {
+                    findings.push(Finding::new(
+                        "statement-count",
+                        severity,
+                        file,
+                        None,
+                        format!(
+                            "{} section has {} statements, over the limit of {}",
+                            section, count, limits.max_statements
+                        ),
+                    ));
+                }
+            }
+        }
+    }
+
+    findings
+}
+
+/// Lint every migration file in the set
+pub fn lint_migrations(files: &[MigrationFile], config: &Config) -> Vec<Finding> {
+    let linter = Linter::new(config, files);
+    files.iter().flat_map(|file| linter.lint(file)).collect()
+}
+
+
+/// Check that the DOWN section parses into at least one statement
+///
+/// The `require-down` directive upgrades the mode to [`DownValidation::Required`];
+/// `no-down` migrations are irreversible on purpose and only flagged when they
+/// still carry DOWN statements that would never run.
+pub fn check_down_section(file: &MigrationFile, mode: DownValidation) -> Option<Finding> {
+    if file.directives.no_down {
+        return check_irreversible(file);
+    }
+    if file.directives.auto_down {
+        return check_auto_down(file);
+    }
+
+    let mode = if file.directives.require_down {
+        DownValidation::Required
+    } else {
+        mode
+    };
+
+    let (_up, down) = parse_migration_content(&file.content).ok()?;
+    let marker_line = find_down_marker(&file.content);
+
+    let (severity, message) = match (marker_line, down) {
+        (None, _) => (
+            if mode == DownValidation::Required {
+                Severity::Error
+            } else {
+                Severity::Warning
+            },
+            "Migration has no DOWN section",
+        ),
+        (Some(_), down) if split_cql_statements(down.as_deref().unwrap_or("")).is_empty() => {
+            let todo = down.as_deref().unwrap_or("").lines().any(|line| line.trim() == DOWN_TODO);
+            if todo {
+                (Severity::Warning, "DOWN section is still a TODO")
+            } else if mode == DownValidation::Warn {
+                (Severity::Warning, "DOWN section contains no statements")
+            } else {
+                (Severity::Error, "DOWN section contains no statements")
+            }
+        }
+        _ => return None,
+    };
+
+    Some(Finding::new("down-section", severity, file, marker_line, message))
+}
+
+fn check_irreversible(file: &MigrationFile) -> Option<Finding> {
+    let (_up, down) = parse_migration_content(&file.content).ok()?;
+    let marker_line = find_down_marker(&file.content);
+
+    let (severity, message) = if file.directives.require_down {
+        (Severity::Error, "Migration declares both no-down and require-down")
+    } else if !split_cql_statements(down.as_deref().unwrap_or("")).is_empty() {
+        (
+            Severity::Warning,
+            "DOWN section is never run because the migration is marked no-down",
+        )
+    } else {
+        return None;
+    };
+
+    Some(Finding::new("down-section", severity, file, marker_line, message))
+}
+
+/// `auto-down` migrations get their DOWN section when first applied, which only
+/// works if every UP statement has a known inverse
+fn check_auto_down(file: &MigrationFile) -> Option<Finding> {
+    let (up, down) = parse_migration_content(&file.content).ok()?;
+    if !split_cql_statements(down.as_deref().unwrap_or("")).is_empty() {
+        return None;
+    }
+
+    let suggestion = suggest_down(&up);
+    if suggestion.is_complete() {
+        return None;
+    }
+
+    Some(Finding::new(
+        "down-section",
+        Severity::Error,
+        file,
+        find_down_marker(&file.content),
+        "auto-down cannot generate a DOWN section: only CREATE TABLE/INDEX/TYPE/MATERIALIZED VIEW can be reversed",
+    ))
+}
+
+/// 1-based line number of the DOWN section marker, if present
+fn find_down_marker(content: &str) -> Option<usize> {
+    let mut tracker = SectionTracker::default();
+    content
+        .lines()
+        .position(|line| tracker.push_line(line) == Line::Marker(Section::Down))
+        .map(|index| index + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rule_ids_are_unique() {
+        let mut ids: Vec<_> = Rule::all().map(|rule| rule.id).collect();
+        let count = ids.len();
+        ids.sort_unstable();
+        ids.dedup();
+        assert_eq!(ids.len(), count);
+        assert_eq!(Rule::find("cql-syntax").map(|rule| rule.id), Some("cql-syntax"));
+    }
+
+    fn migration(content: &str) -> MigrationFile {
+        MigrationFile::for_test("20250101_001_test", content)
+    }
+
+    #[test]
+    fn test_down_section_with_statements_passes() {
+        let file = migration("-- +migrate Up\nCREATE TABLE a (id INT PRIMARY KEY);\n-- +migrate Down\nDROP TABLE a;\n");
+        assert!(check_down_section(&file, DownValidation::Required).is_none());
+    }
+
+    #[test]
+    fn test_commented_down_section_is_empty() {
+        let file = migration("-- +migrate Up\nCREATE TABLE a (id INT PRIMARY KEY);\n\n-- +migrate Down\n-- DROP TABLE a;\n");
+
+        let finding = check_down_section(&file, DownValidation::Warn).unwrap();
+        assert_eq!(finding.severity, Severity::Warning);
+        assert_eq!(finding.line, Some(4));
+
+        let finding = check_down_section(&file, DownValidation::Error).unwrap();
+        assert_eq!(finding.severity, Severity::Error);
+    }
+
+    #[test]
+    fn test_lint_reports_statement_line() {
+        let file = migration("-- +migrate Up\nCREATE TABLE a (id INT PRIMARY KEY);\nCREATE TABLE b (id INT PRIMARY KEY, n integer);\n-- +migrate Down\nDROP TABLE b;\nDROP TABLE a;\n");
+        let findings = lint_migrations(std::slice::from_ref(&file), &Config::default());
+
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].rule, "unknown-type");
+        assert_eq!(findings[0].line, Some(3));
+
+        // Repeated and prefix-sharing statements, in either section, point at their own line
+        let file = migration("-- +migrate Up\nCREATE TABLE b (id INT PRIMARY KEY);\nCREATE TABLE b (id INT PRIMARY KEY, n integer);\nUSE app;\n-- +migrate Down\nUSE app;\nDROP TABLE b;\n");
+        let lines: Vec<(&str, Option<usize>)> = lint_migrations(std::slice::from_ref(&file), &Config::default())
+            .iter()
+            .map(|finding| (finding.rule, finding.line))
+            .collect();
+        assert_eq!(lines, vec![("unknown-type", Some(3)), ("use-statement", Some(4)), ("use-statement", Some(6))]);
+    }
+
+    #[test]
+    fn test_plaintext_role_password() {
+        let file = migration("-- +migrate Up\nCREATE ROLE app WITH PASSWORD = 'hunter2' AND LOGIN = true;\nALTER ROLE ops WITH PASSWORD = '${secret:OPS_PASSWORD}';\nGRANT SELECT ON KEYSPACE app TO app;\n-- +migrate Down\nDROP ROLE app;\n");
+        let findings = lint_migrations(std::slice::from_ref(&file), &Config::default());
+
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].rule, "plaintext-password");
+        assert_eq!(findings[0].line, Some(2));
+    }
+
+    #[test]
+    fn test_use_statement() {
+        let file = migration("-- +migrate Up\nUSE analytics;\nCREATE TABLE events (id INT PRIMARY KEY);\n-- +migrate Down\nDROP TABLE analytics.events;\n");
+        let findings = lint_migrations(std::slice::from_ref(&file), &Config::default());
+
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].rule, "use-statement");
+        assert_eq!(findings[0].line, Some(2));
+    }
+
+    #[test]
+    fn test_batch_requires_dml() {
+        let content = "-- db-migrate:batch=logged\n-- +migrate Up\nINSERT INTO app.t (id) VALUES (1);\nCREATE TABLE app.u (id int PRIMARY KEY);\n-- +migrate Down\nTRUNCATE app.t;\n";
+        let rules: Vec<&str> = lint_migrations(&[migration(content)], &Config::default())
+            .iter()
+            .map(|f| f.rule)
+            .collect();
+        assert_eq!(rules, vec!["ddl-dml-mix", "batch-dml", "batch-dml"]);
+    }
+
+    #[test]
+    fn test_ddl_dml_mix() {
+        let content = "-- +migrate Up\nCREATE TABLE app.t (id int PRIMARY KEY);\nINSERT INTO app.t (id) VALUES (1);\n-- +migrate Down\nDROP TABLE app.t;\n";
+        let findings = lint_migrations(&[migration(content)], &Config::default());
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].rule, "ddl-dml-mix");
+        assert_eq!(findings[0].severity, Severity::Warning);
+        assert_eq!(findings[0].line, Some(3));
+
+        let mut config = Config::default();
+        config.lint.separate_ddl_dml = true;
+        assert_eq!(lint_migrations(&[migration(content)], &config)[0].severity, Severity::Error);
+
+        let allowed = format!("-- db-migrate:allow-mixed\n{}", content);
+        assert!(lint_migrations(&[migration(&allowed)], &config).is_empty());
+    }
+
+    #[test]
+    fn test_missing_down_section() {
+        let file = migration("CREATE TABLE a (id INT PRIMARY KEY);");
+        assert_eq!(
+            check_down_section(&file, DownValidation::Error).unwrap().severity,
+            Severity::Warning
+        );
+        assert_eq!(
+            check_down_section(&file, DownValidation::Required).unwrap().severity,
+            Severity::Error
+        );
+
+        let file = migration("-- db-migrate:require-down\nCREATE TABLE a (id INT PRIMARY KEY);");
+        assert_eq!(
+            check_down_section(&file, DownValidation::Warn).unwrap().severity,
+            Severity::Error
+        );
+
+        // A file just created with --require-down is a reminder, not an error
+        let created = crate::utils::generate_migration_template(
+            "add a",
+            crate::utils::MigrationTemplate::Default.body(),
+            true,
+            false,
+            &Default::default(),
+        );
+        let finding = check_down_section(&migration(&created), DownValidation::Required).unwrap();
+        assert_eq!(finding.severity, Severity::Warning);
+        assert_eq!(finding.message, "DOWN section is still a TODO");
+
+        let filled = created.replace(DOWN_TODO, "DROP TABLE a;");
+        assert!(check_down_section(&migration(&filled), DownValidation::Required).is_none());
+    }
+
+    #[test]
+    fn test_no_down_directive() {
+        let file = migration("-- db-migrate:no-down\n-- +migrate Up\nDROP TABLE legacy;\n");
+        assert!(check_down_section(&file, DownValidation::Required).is_none());
+
+        let file = migration("-- db-migrate:no-down\n-- +migrate Up\nDROP TABLE legacy;\n-- +migrate Down\nCREATE TABLE legacy (id INT PRIMARY KEY);\n");
+        let finding = check_down_section(&file, DownValidation::Warn).unwrap();
+        assert_eq!(finding.severity, Severity::Warning);
+        assert_eq!(finding.line, Some(4));
+    }
+
+    #[test]
+    fn test_auto_down_directive() {
+        let file = migration("-- db-migrate:auto-down\n-- +migrate Up\nCREATE TABLE a (id INT PRIMARY KEY);\n");
+        assert!(check_down_section(&file, DownValidation::Required).is_none());
+
+        let file = migration("-- db-migrate:auto-down\n-- +migrate Up\nALTER TABLE a ADD b text;\n");
+        assert_eq!(
+            check_down_section(&file, DownValidation::Warn).unwrap().severity,
+            Severity::Error
+        );
+    }
+
+    #[test]
+    fn test_limits() {
+        let limits = LimitsConfig {
+            max_file_size_kb: 1,
+            max_statements: 2,
+            ..LimitsConfig::default()
+        };
+
+        let file = migration(&"INSERT INTO t (id) VALUES (1);\n".repeat(40));
+        let findings = check_limits(&file, &limits);
+        let rules: Vec<&str> = findings.iter().map(|f| f.rule).collect();
+        assert_eq!(rules, vec!["file-size", "statement-count"]);
+        assert!(findings.iter().all(|f| f.severity == Severity::Warning));
+
+        let enforced = LimitsConfig {
+            enforce: true,
+            ..limits
+        };
+        let file = migration("CREATE TABLE a (id INT PRIMARY KEY);");
+        assert!(check_limits(&file, &enforced).is_empty());
+    }
+}
@@ -0,0 +1,391 @@
+//! Cluster-wide lock serializing migration runs across processes
+//!
+//! [`crate::queue`] only serializes runs sharing a connection. Runs that may
+//! start concurrently from several processes, such as Kubernetes Jobs of two
+//! rollouts, take this lock: a row in `<table_name>_lock` next to the tracking
+//! table, inserted with a lightweight transaction. The row expires after
+//! [`LOCK_TTL_SECS`], so a holder that crashed cannot block runs forever.
+//!
+//! Runs that outlast the TTL, such as hours-long backfills, renew the row in the
+//! background (see [`MigrationManager::keep_lock`]). A run that can no longer
+//! renew it stops at its next statement boundary rather than keep writing while
+//! another run may have taken the lock.
+
+use crate::{cql, drift::normalize_identifier, migration::MigrationManager, MigrationError};
+use chrono::{DateTime, Utc};
+use scylla::frame::value::CqlTimestamp;
+use scylla::{QueryResult, Session};
+use serde::Serialize;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+/// Suffix of the lock table, appended to `migrations.table_name`
+pub const LOCK_TABLE_SUFFIX: &str = "_lock";
+
+/// Seconds after which an unreleased lock expires
+pub const LOCK_TTL_SECS: i32 = 900;
+
+/// How long `up` waits for the lock held by another run, unless told otherwise
+pub const DEFAULT_LOCK_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// Name of the lock row taken by migration runs
+const LOCK_NAME: &str = "migrations";
+
+/// Delay between two attempts to take a held lock
+const LOCK_RETRY_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Delay between two renewals of a held lock; two can fail before it expires
+const LOCK_RENEW_INTERVAL: Duration = Duration::from_secs(LOCK_TTL_SECS as u64 / 3);
+
+/// Delay between two queries through the session while the lock is held
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Name identifying this process as a lock holder: the user, if known, the host
+/// name (the pod name in Kubernetes) and the process id
+pub fn lock_owner() -> String {
+    let host = std::env::var("HOSTNAME").unwrap_or_else(|_| "db-migrate".to_string());
+    match std::env::var("USER").or_else(|_| std::env::var("USERNAME")) {
+        Ok(user) if !user.is_empty() => format!("{}@{}:{}", user, host, std::process::id()),
+        _ => format!("{}:{}", host, std::process::id()),
+    }
+}
+
+/// The lock as currently held
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LockStatus {
+    /// See [`lock_owner`]
+    pub owner: Option<String>,
+    pub acquired_at: Option<DateTime<Utc>>,
+    /// Seconds until the lock expires unless released
+    pub ttl_secs: Option<i32>,
+}
+
+/// Why the lock of the current run was lost, if it was
+#[derive(Debug, Clone, Default)]
+pub(crate) struct LockLease(Arc<Mutex<Option<String>>>);
+
+impl LockLease {
+    fn lose(&self, reason: String) {
+        self.0.lock().unwrap().get_or_insert(reason);
+    }
+
+    fn reset(&self) {
+        self.0.lock().unwrap().take();
+    }
+
+    pub(crate) fn lost(&self) -> Option<String> {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+/// Background renewal of a held lock, stopped when dropped
+pub struct LockKeepalive {
+    task: Option<JoinHandle<()>>,
+}
+
+impl Drop for LockKeepalive {
+    fn drop(&mut self) {
+        if let Some(task) = &self.task {
+            task.abort();
+        }
+    }
+}
+
+/// The lock taken for a run by [`MigrationManager::hold_lock`], renewed until
+/// released; dropping it stops the renewal and lets the lock expire
+pub struct HeldLock {
+    owner: String,
+    keepalive: LockKeepalive,
+}
+
+impl HeldLock {
+    /// Stop renewing the lock and release it
+    pub async fn release(self, manager: &MigrationManager) {
+        let HeldLock { owner, keepalive } = self;
+        drop(keepalive);
+        manager.release_lock(&owner).await;
+    }
+}
+
+/// Whether a lightweight transaction was applied
+fn lwt_applied(result: &QueryResult) -> bool {
+    result
+        .rows
+        .as_ref()
+        .and_then(|rows| rows.first())
+        .and_then(|row| row.columns.first())
+        .and_then(|column| column.as_ref())
+        .and_then(|value| value.as_boolean())
+        .unwrap_or(false)
+}
+
+/// Renew the lock and query through the session every interval, until the lock
+/// is lost
+async fn keep_alive(session: Arc<Session>, renewal: String, values: (String, CqlTimestamp), lease: LockLease) {
+    let (owner, acquired_at) = values;
+    let start = tokio::time::Instant::now();
+    let mut renew = tokio::time::interval_at(start + LOCK_RENEW_INTERVAL, LOCK_RENEW_INTERVAL);
+    let mut heartbeat = tokio::time::interval_at(start + HEARTBEAT_INTERVAL, HEARTBEAT_INTERVAL);
+    let mut renewed = Instant::now();
+
+    loop {
+        tokio::select! {
+            _ = renew.tick() => {
+                match session.query(renewal.as_str(), (&owner, acquired_at, LOCK_NAME, &owner)).await {
+                    Ok(result) if lwt_applied(&result) => {
+                        debug!("Renewed the migration lock for {}s", LOCK_TTL_SECS);
+                        renewed = Instant::now();
+                    }
+                    Ok(_) => {
+                        warn!("The migration lock was released or taken by another run");
+                        lease.lose("it was released or taken by another run".to_string());
+                        return;
+                    }
+                    // The next renewal would come after the lock expired
+                    Err(e) if renewed.elapsed() + LOCK_RENEW_INTERVAL >= Duration::from_secs(LOCK_TTL_SECS as u64) => {
+                        warn!("Could not renew the migration lock before it expires: {}", e);
+                        lease.lose(format!("renewing it failed: {}", e));
+                        return;
+                    }
+                    Err(e) => warn!(
+                        "Could not renew the migration lock, retrying in {}s: {}",
+                        LOCK_RENEW_INTERVAL.as_secs(),
+                        e
+                    ),
+                }
+            }
+            _ = heartbeat.tick() => {
+                if let Err(e) = session.query("SELECT release_version FROM system.local", &[]).await {
+                    debug!("Session heartbeat failed: {}", e);
+                }
+            }
+        }
+    }
+}
+
+impl MigrationManager {
+    /// Keyspace-qualified name of the lock table
+    pub fn lock_table(&self) -> String {
+        self.get_config().lock_table()
+    }
+
+    /// Create the lock table once per session
+    async fn ensure_lock_table(&self) -> Result<(), MigrationError> {
+        let table = self.lock_table();
+        if self.is_initialized(&table) {
+            return Ok(());
+        }
+
+        let query = format!(
+            "CREATE TABLE IF NOT EXISTS {} (
+                name TEXT PRIMARY KEY,
+                owner TEXT,
+                acquired_at TIMESTAMP
+            )",
+            table
+        );
+        debug!("Creating lock table: {}", query);
+        self.session().query(query, &[]).await?;
+        self.mark_initialized(table);
+        Ok(())
+    }
+
+    /// Take the lock if it is free, returning whether it was taken
+    pub async fn try_acquire_lock(&self, owner: &str) -> Result<bool, MigrationError> {
+        self.ensure_writable()?;
+        self.ensure_lock_table().await?;
+
+        let query = format!(
+            "INSERT INTO {} (name, owner, acquired_at) VALUES (?, ?, ?) IF NOT EXISTS USING TTL {}",
+            self.lock_table(),
+            LOCK_TTL_SECS
+        );
+        let result = self
+            .session()
+            .query(query, (LOCK_NAME, owner, CqlTimestamp(Utc::now().timestamp_millis())))
+            .await?;
+
+        Ok(lwt_applied(&result))
+    }
+
+    /// Current holder of the lock, if any
+    pub async fn lock_holder(&self) -> Result<Option<String>, MigrationError> {
+        self.ensure_lock_table().await?;
+
+        let query = format!("SELECT owner FROM {} WHERE name = ?", self.lock_table());
+        let rows = self.session().query(query, (LOCK_NAME,)).await?;
+        Ok(rows
+            .rows_typed::<(Option<String>,)>()
+            .map_err(|e| MigrationError::IntegrityError(e.to_string()))?
+            .next()
+            .transpose()
+            .map_err(|e| MigrationError::IntegrityError(e.to_string()))?
+            .and_then(|(owner,)| owner))
+    }
+
+    /// The lock if it is held, without creating the lock table
+    pub async fn lock_status(&self) -> Result<Option<LockStatus>, MigrationError> {
+        if !self.is_initialized(&self.lock_table()) {
+            let config = self.get_config();
+            let table = cql::suffixed_identifier(&config.migrations.table_name, LOCK_TABLE_SUFFIX);
+            let rows = self
+                .session()
+                .query(
+                    "SELECT table_name FROM system_schema.tables WHERE keyspace_name = ? AND table_name = ?",
+                    (normalize_identifier(config.tracking_keyspace()), normalize_identifier(&table)),
+                )
+                .await?;
+            if rows.rows.unwrap_or_default().is_empty() {
+                return Ok(None);
+            }
+        }
+
+        let query = format!(
+            "SELECT owner, acquired_at, TTL(owner) FROM {} WHERE name = ?",
+            self.lock_table()
+        );
+        let rows = self.session().query(query, (LOCK_NAME,)).await?;
+        Ok(rows
+            .rows_typed::<(Option<String>, Option<CqlTimestamp>, Option<i32>)>()
+            .map_err(|e| MigrationError::IntegrityError(e.to_string()))?
+            .next()
+            .transpose()
+            .map_err(|e| MigrationError::IntegrityError(e.to_string()))?
+            .map(|(owner, acquired_at, ttl_secs)| LockStatus {
+                owner,
+                acquired_at: acquired_at.and_then(|CqlTimestamp(millis)| DateTime::from_timestamp_millis(millis)),
+                ttl_secs,
+            }))
+    }
+
+    /// Remove the lock whoever holds it, returning what was removed
+    ///
+    /// For operators clearing the lock of a run that is known to be gone; a run
+    /// still in progress keeps going without it.
+    pub async fn force_release_lock(&self) -> Result<Option<LockStatus>, MigrationError> {
+        self.ensure_writable()?;
+        let Some(status) = self.lock_status().await? else {
+            return Ok(None);
+        };
+        let query = format!("DELETE FROM {} WHERE name = ?", self.lock_table());
+        self.session().query(query, (LOCK_NAME,)).await?;
+        warn!(
+            "Released the migration lock held by {}",
+            status.owner.as_deref().unwrap_or("an unknown owner")
+        );
+        Ok(Some(status))
+    }
+
+    /// Wait until the lock is taken, failing with [`MigrationError::LockTimeout`]
+    /// after `timeout`
+    pub async fn acquire_lock(&self, owner: &str, timeout: Duration) -> Result<(), MigrationError> {
+        let deadline = Instant::now() + timeout;
+        let mut announced = false;
+
+        while !self.try_acquire_lock(owner).await? {
+            if Instant::now() >= deadline {
+                return Err(MigrationError::LockTimeout {
+                    holder: self.lock_holder().await.ok().flatten(),
+                    waited_secs: timeout.as_secs(),
+                });
+            }
+            if !announced {
+                let holder = self.lock_holder().await.ok().flatten();
+                info!(
+                    "Waiting for the migration lock held by {}",
+                    holder.as_deref().unwrap_or("another run")
+                );
+                announced = true;
+            }
+            tokio::time::sleep(LOCK_RETRY_INTERVAL).await;
+        }
+
+        debug!("Took the migration lock as {}", owner);
+        Ok(())
+    }
+
+    /// Keep the lock taken by `owner` for as long as the returned guard lives
+    ///
+    /// The lock is renewed every [`LOCK_TTL_SECS`]` / 3` seconds, and the session
+    /// queried every minute so its connections do not sit idle during long
+    /// statements. When the lock was released or taken by another run, or
+    /// renewing it failed until it would expire, the run stops at its next
+    /// statement boundary with [`MigrationError::LockLost`], as it does right
+    /// away if `owner` does not hold the lock.
+    pub async fn keep_lock(&self, owner: &str) -> Result<LockKeepalive, MigrationError> {
+        let lease = self.lock_lease().clone();
+        lease.reset();
+        let acquired_at = match self.lock_status().await? {
+            Some(LockStatus {
+                owner: Some(holder),
+                acquired_at,
+                ..
+            }) if holder == owner => acquired_at.unwrap_or_else(Utc::now),
+            _ => {
+                lease.lose(format!("it is not held by {}", owner));
+                return Ok(LockKeepalive { task: None });
+            }
+        };
+
+        // Rewriting both columns renews the expiry of the whole row
+        let renewal = format!(
+            "UPDATE {} USING TTL {} SET owner = ?, acquired_at = ? WHERE name = ? IF owner = ?",
+            self.lock_table(),
+            LOCK_TTL_SECS
+        );
+        let task = tokio::spawn(keep_alive(
+            self.shared_session().session_arc(),
+            renewal,
+            (owner.to_string(), CqlTimestamp(acquired_at.timestamp_millis())),
+            lease,
+        ));
+        Ok(LockKeepalive { task: Some(task) })
+    }
+
+    /// Take the lock as [`lock_owner`], waiting up to `timeout`, and keep it
+    /// (see [`MigrationManager::keep_lock`]) until the returned guard is released
+    pub async fn hold_lock(&self, timeout: Duration) -> Result<HeldLock, MigrationError> {
+        let owner = lock_owner();
+        self.acquire_lock(&owner, timeout).await?;
+        match self.keep_lock(&owner).await {
+            Ok(keepalive) => Ok(HeldLock { owner, keepalive }),
+            Err(e) => {
+                self.release_lock(&owner).await;
+                Err(e)
+            }
+        }
+    }
+
+    /// Fail with [`MigrationError::LockLost`] once [`MigrationManager::keep_lock`]
+    /// could not renew the lock, before `version` runs another statement
+    pub(crate) fn check_lock_lease(&self, version: &str, executed: usize) -> Result<(), MigrationError> {
+        match self.lock_lease().lost() {
+            Some(reason) => {
+                warn!(
+                    "Stopped migration {} after {} statement(s): the migration lock was lost",
+                    version, executed
+                );
+                Err(MigrationError::LockLost {
+                    version: version.to_string(),
+                    statements_executed: executed,
+                    reason,
+                })
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Release the lock if `owner` still holds it; failures are logged, the
+    /// lock then expires on its own
+    pub async fn release_lock(&self, owner: &str) {
+        let query = format!("DELETE FROM {} WHERE name = ? IF owner = ?", self.lock_table());
+        if let Err(e) = self.session().query(query, (LOCK_NAME, owner)).await {
+            warn!(
+                "Could not release the migration lock, it expires within {}s: {}",
+                LOCK_TTL_SECS, e
+            );
+        }
+    }
+}
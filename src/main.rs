@@ -1,12 +1,31 @@
 use anyhow::Result;
+use chrono::Utc;
 use clap::{Parser, Subcommand};
 use colored::*;
 use db_migrate::{
     config::Config,
-    commands::{CreateCommand, DownCommand, StatusCommand, UpCommand, VerifyCommand, ResetCommand},
+    diagnostics::RecentLogs,
+    commands::{
+        AgreementCommand, ApproveCommand, CloneSchemaCommand, ConfigCommand, CreateCommand, DoctorCommand, DownCommand, DriftCommand, FmtCommand, HistoryCommand, HooksCommand, KeyspaceCommand, LintCommand, PermissionsCommand, RenameCommand, RepairCommand, ResetCommand, RestoreHistoryCommand, StatusCommand, UpCommand,
+        VerifyCommand, VersionCommand, LockCommand,
+    },
+    exit_code, messages,
     migration::MigrationManager,
+    progress::{self, ProgressMode},
+    runreport::write_run_report,
+    CommandOutput, MigrationError,
+};
+#[cfg(feature = "bench")]
+use db_migrate::commands::BenchCommand;
+#[cfg(feature = "job")]
+use db_migrate::commands::JobCommand;
+#[cfg(feature = "operator")]
+use db_migrate::commands::OperatorCommand;
+#[cfg(feature = "sandbox")]
+use db_migrate::commands::SandboxCommand;
+use tracing_subscriber::{
+    fmt::writer::BoxMakeWriter, layer::SubscriberExt, util::SubscriberInitExt, Layer,
 };
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 #[derive(Parser)]
 #[command(
@@ -26,9 +45,21 @@ struct Cli {
     #[arg(short, long)]
     verbose: bool,
 
-    /// Output format (text, json)
+    /// Output format (text, json, gh-annotations, gitlab-codequality, sarif)
     #[arg(long, default_value = "text")]
     output: String,
+
+    /// Don't create the keyspace or tracking table (for read-only credentials)
+    #[arg(long, global = true)]
+    no_init: bool,
+
+    /// Only log errors and hide progress lines
+    #[arg(short, long, global = true)]
+    quiet: bool,
+
+    /// Stable tab-separated records without color or emoji (status, verify)
+    #[arg(long, global = true)]
+    porcelain: bool,
 }
 
 #[derive(Subcommand)]
@@ -45,6 +76,53 @@ enum Commands {
     Verify(VerifyCommand),
     /// Reset all migrations (destructive)
     Reset(ResetCommand),
+    /// Check migration files for problems without connecting to the database
+    Lint(LintCommand),
+    /// Format migration files canonically
+    Fmt(FmtCommand),
+    /// Manage git hooks that guard migration files
+    Hooks(HooksCommand),
+    /// Apply all migrations to a temporary keyspace for development
+    #[cfg(feature = "sandbox")]
+    Sandbox(SandboxCommand),
+    /// Recreate a keyspace's schema (no data) in another keyspace
+    CloneSchema(CloneSchemaCommand),
+    /// Show permissions granted to roles
+    Permissions(PermissionsCommand),
+    /// Compare the live schema with the applied migrations
+    Drift(DriftCommand),
+    /// Inspect and validate the configuration file
+    Config(ConfigCommand),
+    /// Measure pending migrations in a scratch keyspace to estimate run time
+    #[cfg(feature = "bench")]
+    Bench(BenchCommand),
+    /// Move a migration to a new version, renaming its file and tracking record
+    Rename(RenameCommand),
+    /// List or prune the audit trail of tracking table changes
+    History(HistoryCommand),
+    /// Load a tracking table backup taken before a destructive command
+    RestoreHistory(RestoreHistoryCommand),
+    /// Fix tracking records that no longer match the migration files
+    Repair(RepairCommand),
+    /// Create, alter or describe keyspaces with the configured replication
+    Keyspace(KeyspaceCommand),
+    /// Check that all nodes agree on the schema version
+    Agreement(AgreementCommand),
+    /// Check nodes, schema agreement and keyspaces of the cluster
+    Doctor(DoctorCommand),
+    /// Sign an `up` plan in an approval-gated environment
+    Approve(ApproveCommand),
+    /// Keep applying pending migrations, serving health and readiness endpoints
+    #[cfg(feature = "operator")]
+    Operator(OperatorCommand),
+    /// Apply pending migrations once, as a Kubernetes Job: wait for the database,
+    /// take the cluster-wide lock and exit with a code per outcome
+    #[cfg(feature = "job")]
+    Job(JobCommand),
+    /// Print the version, or compare it with the latest release
+    Version(VersionCommand),
+    /// Show or release the cluster-wide migration lock
+    Lock(LockCommand),
 }
 
 #[tokio::main]
@@ -52,38 +130,55 @@ async fn main() -> Result<()> {
     let cli = Cli::parse();
 
     // Initialize logging
-    init_logging(cli.verbose)?;
-
-    // Load configuration
-    let config = Config::load(&cli.config).await?;
-
-    // Create migration manager
-    let mut manager = MigrationManager::new(config).await?;
+    // Structured output owns stdout; logs and progress lines go to stderr
+    let annotations = ["gh-annotations", "gitlab-codequality", "sarif"].contains(&cli.output.as_str());
+    let structured = cli.porcelain || progress::STRUCTURED_OUTPUTS.contains(&cli.output.as_str());
+    init_logging(cli.verbose, cli.quiet, structured)?;
+    if cli.porcelain || annotations {
+        colored::control::set_override(false);
+    }
+    progress::set_mode(ProgressMode::for_output(cli.quiet, &cli.output, cli.porcelain));
 
     // Execute command
     let result = match cli.command {
-        Commands::Create(cmd) => cmd.execute(&manager).await,
-        Commands::Up(cmd) => cmd.execute(&mut manager).await,
-        Commands::Down(cmd) => cmd.execute(&mut manager).await,
-        Commands::Status(cmd) => cmd.execute(&manager).await,
-        Commands::Verify(cmd) => cmd.execute(&manager).await,
-        Commands::Reset(cmd) => cmd.execute(&mut manager).await,
+        Commands::Config(cmd) => cmd.execute(&cli.config).await,
+        Commands::Version(cmd) => cmd.execute().await,
+        command => run(command, &cli.config, cli.no_init).await,
     };
 
     match result {
         Ok(output) => {
             if cli.output == "json" {
                 println!("{}", serde_json::to_string_pretty(&output)?);
+            } else if cli.output == "gh-annotations" {
+                let text = output.gh_annotations_text();
+                if !text.is_empty() {
+                    println!("{}", text);
+                }
+            } else if cli.output == "gitlab-codequality" {
+                println!("{}", output.gitlab_codequality_text());
+            } else if cli.output == "sarif" {
+                println!("{}", output.sarif_text());
+            } else if cli.porcelain {
+                let text = output.porcelain_text();
+                if !text.is_empty() {
+                    println!("{}", text);
+                }
             } else {
                 println!("{}", output);
             }
-            std::process::exit(0);
+            std::process::exit(output.exit_code);
         }
         Err(e) => {
+            let migration_error = e.chain().find_map(|c| c.downcast_ref::<MigrationError>());
+            let exit_code = migration_error.map_or(exit_code::FAILURE, MigrationError::exit_code);
+
             if cli.output == "json" {
                 let error_output = serde_json::json!({
                     "success": false,
-                    "error": e.to_string()
+                    "error": e.to_string(),
+                    "code": migration_error.map(MigrationError::code),
+                    "exit_code": exit_code
                 });
                 println!("{}", serde_json::to_string_pretty(&error_output)?);
             } else {
@@ -96,24 +191,152 @@ async fn main() -> Result<()> {
                     source = err.source();
                 }
             }
-            std::process::exit(1);
+            std::process::exit(exit_code);
+        }
+    }
+}
+
+/// Load the configuration and run a command that needs it
+async fn run(command: Commands, config_path: &str, no_init: bool) -> Result<CommandOutput> {
+    // Load configuration
+    let config = Config::load(config_path)
+        .await
+        .map_err(|e| MigrationError::ConfigError(e.to_string()))?;
+    messages::configure(&config.output)?;
+
+    let run_report = config.output.run_report.clone();
+    let started_at = Utc::now();
+    match command {
+        Commands::Up(cmd) if cmd.targets_all_clusters() || cmd.targets_all_tenants() => {
+            let output = if cmd.targets_all_clusters() {
+                cmd.execute_all_clusters(&config).await
+            } else {
+                cmd.execute_all_tenants(&config).await
+            };
+            write_run_report(run_report.as_deref(), "up", started_at, &output, None).await;
+            output
+        }
+        Commands::Create(cmd) => cmd.execute(&config).await,
+        Commands::Lint(cmd) => cmd.execute(&config).await,
+        Commands::Fmt(cmd) => cmd.execute(&config).await,
+        #[cfg(feature = "sandbox")]
+        Commands::Sandbox(cmd) => cmd.execute(&config).await,
+        Commands::Approve(cmd) => cmd.execute(&config).await,
+        #[cfg(feature = "job")]
+        Commands::Job(cmd) => cmd.execute(&config).await,
+        Commands::Hooks(cmd) if !cmd.requires_database() => {
+            cmd.execute_offline(&config, config_path).await
+        }
+        command => {
+            // Read-only commands may use cheaper consistency and a nearer datacenter
+            let read_path = match &command {
+                Commands::Status(_)
+                | Commands::Drift(_)
+                | Commands::Permissions(_)
+                | Commands::Agreement(_)
+                | Commands::Doctor(_) => true,
+                Commands::Verify(cmd) => cmd.is_read_only(),
+                Commands::History(cmd) => cmd.is_read_only(),
+                Commands::Keyspace(cmd) => cmd.is_read_only(),
+                Commands::Lock(cmd) => cmd.is_read_only(),
+                _ => false,
+            };
+
+            // Only commands that write to the database create the keyspace and
+            // create or upgrade the tracking table, so informational commands
+            // work with read-only roles; `keyspace` creates keyspaces itself
+            let initialize = !no_init && !read_path && !matches!(command, Commands::Keyspace(_));
+
+            // Create migration manager
+            let mut manager = MigrationManager::builder(config)
+                .initialize_schema(initialize)
+                // `keyspace` creates the keyspaces others require to exist and
+                // `doctor` reports them missing
+                .require_keyspaces(!matches!(command, Commands::Keyspace(_) | Commands::Doctor(_)))
+                .read_path(read_path)
+                .build()
+                .await?;
+
+            // Stop gracefully at the next statement boundary on Ctrl-C or SIGTERM
+            manager.cancel_on_shutdown_signal();
+
+            match command {
+                Commands::Up(cmd) => {
+                    let output = cmd.execute(&mut manager).await;
+                    write_run_report(run_report.as_deref(), "up", started_at, &output, Some(&manager)).await;
+                    output
+                }
+                Commands::Down(cmd) => {
+                    let output = cmd.execute(&mut manager).await;
+                    write_run_report(run_report.as_deref(), "down", started_at, &output, Some(&manager)).await;
+                    output
+                }
+                Commands::Status(cmd) => cmd.execute(&manager).await,
+                Commands::Verify(cmd) => cmd.execute(&manager).await,
+                Commands::Reset(cmd) => cmd.execute(&mut manager).await,
+                Commands::Hooks(cmd) => cmd.execute(&manager).await,
+                Commands::CloneSchema(cmd) => cmd.execute(&manager).await,
+                Commands::Permissions(cmd) => cmd.execute(&manager).await,
+                Commands::Drift(cmd) => cmd.execute(&manager).await,
+                #[cfg(feature = "bench")]
+                Commands::Bench(cmd) => cmd.execute(&manager).await,
+                Commands::Rename(cmd) => cmd.execute(&manager).await,
+                Commands::History(cmd) => cmd.execute(&manager).await,
+                Commands::RestoreHistory(cmd) => cmd.execute(&manager).await,
+                Commands::Repair(cmd) => cmd.execute(&manager).await,
+                Commands::Keyspace(cmd) => cmd.execute(&manager).await,
+                Commands::Agreement(cmd) => cmd.execute(&manager).await,
+                Commands::Doctor(cmd) => cmd.execute(&manager).await,
+                Commands::Lock(cmd) => cmd.execute(&manager).await,
+                #[cfg(feature = "operator")]
+                Commands::Operator(cmd) => cmd.execute(&mut manager).await,
+                #[cfg(feature = "sandbox")]
+                Commands::Sandbox(_) => unreachable!("handled before the shared manager is created"),
+                #[cfg(feature = "job")]
+                Commands::Job(_) => unreachable!("handled before the shared manager is created"),
+                Commands::Create(_)
+                | Commands::Lint(_)
+                | Commands::Fmt(_)
+                | Commands::Approve(_)
+                | Commands::Config(_)
+                | Commands::Version(_) => {
+                    unreachable!("handled before the shared manager is created")
+                }
+            }
         }
     }
 }
 
-fn init_logging(verbose: bool) -> Result<()> {
+fn init_logging(verbose: bool, quiet: bool, structured: bool) -> Result<()> {
     let filter = if verbose {
         "db_migrate=debug,info"
+    } else if quiet {
+        "error"
     } else {
         "db_migrate=info,warn,error"
     };
 
+    // Recent debug output is kept in memory for diagnostics bundles regardless
+    // of what is shown on the console
     tracing_subscriber::registry()
         .with(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| filter.into()),
+            tracing_subscriber::fmt::layer()
+                .with_writer(if structured {
+                    BoxMakeWriter::new(std::io::stderr)
+                } else {
+                    BoxMakeWriter::new(std::io::stdout)
+                })
+                .with_filter(
+                tracing_subscriber::EnvFilter::try_from_default_env()
+                    .unwrap_or_else(|_| filter.into()),
+            ),
+        )
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_ansi(false)
+                .with_writer(RecentLogs::global().clone())
+                .with_filter(tracing_subscriber::EnvFilter::new("db_migrate=debug,info")),
         )
-        .with(tracing_subscriber::fmt::layer())
         .init();
 
     Ok(())
@@ -0,0 +1,246 @@
+//! Presentation of user-facing text: emoji and translations
+//!
+//! Commands write English messages decorated with emoji. Everything printed for
+//! people, i.e. command output and [`progress!`](crate::progress!) lines, goes
+//! through [`render`], which applies `[output]`:
+//!
+//! - with `emoji = false`, status emoji become plain labels (`OK`, `ERROR`,
+//!   `WARNING`, `HINT`) and decorative ones are dropped, for terminals and log
+//!   processors that mangle them;
+//! - with `messages = "<file>"`, each line is looked up in a message catalog and
+//!   replaced by its translation.
+//!
+//! A catalog maps English lines to translations. `{}` stands for a value
+//! filled in at run time; in translations, `{1}`, `{2}`... refer to values by
+//! position, and `{}` takes the next one:
+//!
+//! ```toml
+//! [messages]
+//! "No pending migrations found" = "Keine ausstehenden Migrationen"
+//! "Applied {} migrations successfully" = "{} Migrationen erfolgreich angewendet"
+//! ```
+//!
+//! Leading indentation, bullets and emoji are kept; translated lines lose their
+//! colors. Lines without an entry are printed unchanged.
+
+use crate::{config::OutputConfig, MigrationError};
+use regex::Regex;
+use serde::Deserialize;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// Message catalog translating output lines
+#[derive(Debug, Default)]
+pub struct Catalog {
+    entries: Vec<(Regex, String)>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CatalogFile {
+    messages: BTreeMap<String, String>,
+}
+
+impl Catalog {
+    pub fn parse(content: &str) -> Result<Self, MigrationError> {
+        let file: CatalogFile = toml::from_str(content)
+            .map_err(|e| MigrationError::ConfigError(format!("Invalid message catalog: {}", e)))?;
+
+        let mut entries = Vec::new();
+        for (source, translation) in file.messages {
+            let pattern = source
+                .split("{}")
+                .map(regex::escape)
+                .collect::<Vec<_>>()
+                .join("(.+?)");
+            let pattern = Regex::new(&format!("^{}$", pattern)).map_err(|e| {
+                MigrationError::ConfigError(format!("Invalid message catalog entry '{}': {}", source, e))
+            })?;
+            entries.push((pattern, translation));
+        }
+        // Longer sources first, so specific entries win over generic ones
+        entries.sort_by_key(|(pattern, _)| std::cmp::Reverse(pattern.as_str().len()));
+        Ok(Self { entries })
+    }
+
+    pub fn load(path: &Path) -> Result<Self, MigrationError> {
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            MigrationError::ConfigError(format!("Could not read message catalog {}: {}", path.display(), e))
+        })?;
+        Self::parse(&content)
+    }
+
+    /// Translation of one line without colors, if the catalog has one
+    pub fn translate_line(&self, line: &str) -> Option<String> {
+        let line = strip_ansi(line);
+        // Keep indentation, bullets and emoji in front of the message
+        let start = line
+            .char_indices()
+            .find(|(_, c)| c.is_alphanumeric() || matches!(c, '\'' | '"' | '(' | '[' | '{'))
+            .map_or(line.len(), |(index, _)| index);
+        let (prefix, message) = line.split_at(start);
+        let message = message.trim_end();
+
+        self.entries.iter().find_map(|(pattern, translation)| {
+            let captures = pattern.captures(message)?;
+            let values: Vec<&str> = captures.iter().skip(1).flatten().map(|m| m.as_str()).collect();
+            Some(format!("{}{}", prefix, fill(translation, &values)))
+        })
+    }
+}
+
+/// Substitute `{}` (next value) and `{N}` (Nth value, from 1) in a translation
+fn fill(translation: &str, values: &[&str]) -> String {
+    let placeholder = Regex::new(r"\{(\d*)\}").expect("valid placeholder pattern");
+    let mut next = 0;
+    placeholder
+        .replace_all(translation, |captures: &regex::Captures| {
+            let index = match captures[1].parse::<usize>() {
+                Ok(position) => position.saturating_sub(1),
+                Err(_) => {
+                    next += 1;
+                    next - 1
+                }
+            };
+            values.get(index).copied().unwrap_or_default().to_string()
+        })
+        .into_owned()
+}
+
+fn strip_ansi(text: &str) -> Cow<'_, str> {
+    static ANSI: OnceLock<Regex> = OnceLock::new();
+    ANSI.get_or_init(|| Regex::new("\x1b\\[[0-9;]*m").expect("valid ANSI pattern"))
+        .replace_all(text, "")
+}
+
+fn is_emoji(c: char) -> bool {
+    matches!(c, '\u{1F300}'..='\u{1FAFF}' | '\u{2600}'..='\u{27BF}' | '\u{23E9}'..='\u{23FA}')
+}
+
+/// Plain-text replacement of an emoji; empty for decorative ones
+fn emoji_label(c: char) -> &'static str {
+    match c {
+        '✅' => "OK",
+        '❌' => "ERROR",
+        '⚠' => "WARNING",
+        '💡' => "HINT",
+        _ => "",
+    }
+}
+
+/// Replace or drop emoji, with the spaces that separated them from the text
+fn strip_emoji(line: &str) -> String {
+    let mut output = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    let mut skip_spaces = false;
+
+    while let Some(c) = chars.next() {
+        if is_emoji(c) {
+            let label = emoji_label(c);
+            output.push_str(label);
+            if !label.is_empty() {
+                output.push(' ');
+            }
+            skip_spaces = true;
+        } else if c == '\u{FE0F}' || (skip_spaces && c == ' ') {
+            // Variation selectors and separating spaces go with the emoji
+        } else if c == '\x1b' {
+            // Colors stay; they do not end the separating spaces
+            output.push(c);
+            for c in chars.by_ref() {
+                output.push(c);
+                if c == 'm' {
+                    break;
+                }
+            }
+        } else {
+            skip_spaces = false;
+            output.push(c);
+        }
+    }
+    output.trim_end().to_string()
+}
+
+#[derive(Debug, Default)]
+struct Settings {
+    plain: bool,
+    catalog: Option<Catalog>,
+}
+
+static SETTINGS: OnceLock<Settings> = OnceLock::new();
+
+/// Apply `[output]` to everything rendered afterwards; only the first call of
+/// a process takes effect
+pub fn configure(config: &OutputConfig) -> Result<(), MigrationError> {
+    let catalog = config.messages.as_deref().map(Catalog::load).transpose()?;
+    let _ = SETTINGS.set(Settings {
+        plain: !config.emoji,
+        catalog,
+    });
+    Ok(())
+}
+
+/// Text as it should be shown, see the [module docs](crate::messages)
+pub fn render(text: &str) -> Cow<'_, str> {
+    match SETTINGS.get() {
+        Some(settings) => render_with(settings, text),
+        None => Cow::Borrowed(text),
+    }
+}
+
+fn render_with<'a>(settings: &Settings, text: &'a str) -> Cow<'a, str> {
+    if !settings.plain && settings.catalog.is_none() {
+        return Cow::Borrowed(text);
+    }
+
+    let lines: Vec<String> = text
+        .split('\n')
+        .map(|line| {
+            let line = settings
+                .catalog
+                .as_ref()
+                .and_then(|catalog| catalog.translate_line(line))
+                .unwrap_or_else(|| line.to_string());
+            if settings.plain {
+                strip_emoji(&line)
+            } else {
+                line
+            }
+        })
+        .collect();
+    Cow::Owned(lines.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let plain = Settings {
+            plain: true,
+            catalog: None,
+        };
+        assert_eq!(render_with(&plain, "✅ Applied 2 migrations"), "OK Applied 2 migrations");
+        assert_eq!(render_with(&plain, "⚠️  Drift detected\n🔍 Would apply"), "WARNING Drift detected\nWould apply");
+        assert_eq!(render_with(&plain, "\x1b[32m✅\x1b[0m Done"), "\x1b[32mOK \x1b[0mDone");
+        assert_eq!(render_with(&plain, "Pending: 0 ✅"), "Pending: 0 OK");
+        assert_eq!(render_with(&plain, "  ─ v1 → v2"), "  ─ v1 → v2");
+
+        let catalog = Catalog::parse(
+            "[messages]\n\"Applied {} migrations successfully\" = \"{} Migrationen erfolgreich angewendet\"\n\"Renamed {} to {}\" = \"{2} heißt jetzt {1}\"\n",
+        )
+        .unwrap();
+        let translated = Settings {
+            plain: false,
+            catalog: Some(catalog),
+        };
+        assert_eq!(
+            render_with(&translated, "🎉 Applied \x1b[1m3\x1b[0m migrations successfully\n  Renamed a to b\nUnknown line"),
+            "🎉 3 Migrationen erfolgreich angewendet\n  b heißt jetzt a\nUnknown line"
+        );
+        assert!(Catalog::parse("[messages]\nkey = 1\n").is_err());
+    }
+}
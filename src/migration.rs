@@ -1,62 +1,1046 @@
 use crate::{
-    config::Config,
-    utils::{calculate_checksum, extract_version_from_filename, parse_migration_content},
-    MigrationError, MigrationFile, MigrationRecord,
+    autodown::{append_down, suggest_down},
+    checkpoint::Checkpoint,
+    compat,
+    config::{Config, ConsistencyLevel, DatabaseConfig, ReadsConfig},
+    directives::{BatchKind, Directives, EnvSelector, ExecutionOptions, DEFAULT_BATCH_SIZE, DIRECTIVE_PREFIX},
+    cql,
+    drift::{normalize_identifier, split_qualified, ExpectedSchema, SchemaDrift},
+    history::HistoryAction,
+    lock::LockLease,
+    placeholders::{resolve_secrets, resolve_variables},
+    queue::{RunPermit, RunQueue},
+    schema::SchemaIntrospector,
+    template::template_variables,
+    stream::{checksum_file, read_header, Section, StatementStream, STREAMING_THRESHOLD},
+    utils::{
+        calculate_checksum, extract_description_from_filename, extract_version_from_filename, generate_migration_version,
+        next_free_prefix, normalize_line_endings, parse_migration_content, split_cql_statements,
+        statement_lines, version_prefix, MigrationTemplate,
+    },
+    version::VersionScheme,
+    InvalidMigrationFile, MigrationError, MigrationFile, MigrationRecord,
 };
 use anyhow::Result;
 use chrono::{TimeZone, Utc};
+use scylla::authentication::AuthenticatorProvider;
+use scylla::batch::{Batch, BatchType};
+use scylla::execution_profile::ExecutionProfile;
+use scylla::frame::response::result::{ColumnType, CqlValue, Row};
+use scylla::load_balancing::DefaultPolicy;
+use scylla::query::Query;
+use scylla::statement::Consistency;
 use scylla::{Session, SessionBuilder};
-use std::collections::HashMap;
-use std::path::PathBuf;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock, Weak};
+use std::time::{Duration, Instant, SystemTime};
+use tokio_util::sync::CancellationToken;
 use scylla::frame::value::CqlTimestamp;
 use tokio::fs;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
 use tracing::{debug, info, warn};
 use walkdir::WalkDir;
 
 /// Main migration manager that handles all migration operations
 pub struct MigrationManager {
-    session: Session,
+    session: Arc<Session>,
     config: Config,
+    cancellation: CancellationToken,
+    /// `cluster_name` from `system.local`, recorded with every applied migration
+    cluster_name: Option<String>,
+    /// Tracking tables already initialized through this session
+    initialized: Arc<Mutex<HashSet<String>>>,
+    /// Serializes runs through this session, see [`MigrationManager::exclusive_run`]
+    run_queue: RunQueue,
+    /// The session belongs to the application, whose keyspace is put back after
+    /// every migration
+    restore_keyspace: bool,
+    /// How versions are read from file names and ordered, from `[versioning]`
+    version_scheme: Arc<VersionScheme>,
+    /// Statement that failed, per migration version
+    failed_statements: Arc<Mutex<HashMap<String, FailedStatement>>>,
+    /// Per-statement timings, recorded when built with `measure_statements`
+    timings: Option<Arc<Mutex<Vec<StatementTiming>>>>,
+    /// Set by [`MigrationManager::keep_lock`] when the migration lock is lost
+    lock_lease: LockLease,
+    /// Migrations applied or rolled back through this manager, in start order
+    runs: Arc<Mutex<Vec<MigrationRun>>>,
+    /// Columns of the tracking table, looked up once for reading commands, which
+    /// do not upgrade it
+    tracking_columns: Arc<Mutex<Option<HashSet<String>>>>,
 }
 
-impl MigrationManager {
-    /// Create a new migration manager and establish database connection
-    pub async fn new(config: Config) -> Result<Self, MigrationError> {
+/// A migration applied or rolled back through a manager
+#[derive(Debug, Clone, Serialize)]
+pub struct MigrationRun {
+    pub version: String,
+    /// `up` or `down`
+    pub direction: &'static str,
+    /// Statements that completed, including those of a failed run
+    pub statements_executed: usize,
+    pub duration_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// A statement that failed, with where it is in its migration file
+#[derive(Debug, Clone, Serialize)]
+pub struct FailedStatement {
+    /// Zero-based index of the statement within its section
+    pub index: usize,
+    /// 1-based first and last line in the file; unknown for streamed files and
+    /// DOWN sections run from the tracking table
+    pub lines: Option<(usize, usize)>,
+    /// Statement text before `${secret:...}` placeholders are resolved
+    pub statement: String,
+}
+
+impl FailedStatement {
+    /// Where the statement is, e.g. `statement 3 (lines 14–19)`
+    pub fn location(&self) -> String {
+        match self.lines {
+            Some((first, last)) if first == last => format!("statement {} (line {})", self.index + 1, first),
+            Some((first, last)) => format!("statement {} (lines {}–{})", self.index + 1, first, last),
+            None => format!("statement {}", self.index + 1),
+        }
+    }
+
+    /// The statement with line numbers in the margin, when they are known
+    pub fn snippet(&self) -> String {
+        let first = self.lines.map_or(1, |(first, _)| first);
+        let width = (first + self.statement.lines().count().saturating_sub(1)).to_string().len();
+        self.statement
+            .lines()
+            .enumerate()
+            .map(|(i, line)| match self.lines {
+                Some(_) => format!("{:>width$} | {}", first + i, line, width = width),
+                None => format!("{} | {}", " ".repeat(width), line),
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// How long one statement took, as recorded for `bench`
+#[derive(Debug, Clone, Serialize)]
+pub struct StatementTiming {
+    pub version: String,
+    /// Zero-based index of the statement within its section
+    pub index: usize,
+    pub statement: String,
+    /// Time until the coordinator answered
+    pub execution_ms: f64,
+    /// Time spent waiting for all nodes to agree on the schema, for schema changes
+    pub schema_agreement_ms: Option<f64>,
+}
+
+/// A connection that can be reused by several managers (tenants, cluster lists,
+/// long-running modes) instead of reconnecting for each of them
+///
+/// Managers sharing a session must not run migrations concurrently: the keyspace
+/// unqualified names resolve to is a property of the session. Runs started with
+/// [`MigrationManager::exclusive_run`] wait for each other.
+#[derive(Clone)]
+pub struct SharedSession {
+    session: Arc<Session>,
+    cluster_name: Option<String>,
+    initialized: Arc<Mutex<HashSet<String>>>,
+    run_queue: RunQueue,
+    /// Opened by the application (see [`SharedSession::from_session`])
+    app_owned: bool,
+}
+
+/// Run queue and initialized tracking tables of a session
+type SessionState = (RunQueue, Arc<Mutex<HashSet<String>>>);
+
+/// State of a session handed over by the application, the same for every
+/// [`SharedSession::from_session`] wrapping it so that their runs wait for each other
+fn app_session_state(session: &Arc<Session>) -> SessionState {
+    type States = Mutex<Vec<(Weak<Session>, SessionState)>>;
+    static STATES: OnceLock<States> = OnceLock::new();
+    let mut states = STATES.get_or_init(Mutex::default).lock().unwrap();
+    states.retain(|(weak, _)| weak.strong_count() > 0);
+    if let Some((_, state)) = states.iter().find(|(weak, _)| std::ptr::eq(weak.as_ptr(), Arc::as_ptr(session))) {
+        return state.clone();
+    }
+    let state: SessionState = (RunQueue::default(), Arc::default());
+    states.push((Arc::downgrade(session), state.clone()));
+    state
+}
+
+impl From<ConsistencyLevel> for Consistency {
+    fn from(level: ConsistencyLevel) -> Self {
+        match level {
+            ConsistencyLevel::One => Consistency::One,
+            ConsistencyLevel::Two => Consistency::Two,
+            ConsistencyLevel::Three => Consistency::Three,
+            ConsistencyLevel::Quorum => Consistency::Quorum,
+            ConsistencyLevel::All => Consistency::All,
+            ConsistencyLevel::LocalQuorum => Consistency::LocalQuorum,
+            ConsistencyLevel::EachQuorum => Consistency::EachQuorum,
+            ConsistencyLevel::LocalOne => Consistency::LocalOne,
+        }
+    }
+}
+
+/// How a session authenticates
+#[derive(Debug, PartialEq)]
+enum Credentials<'a, P> {
+    /// A provider passed to [`MigrationManagerBuilder::authenticator_provider`]
+    Provider(P),
+    Password { username: &'a str, password: &'a str },
+    Anonymous,
+}
+
+/// The credentials of a session: a custom provider wins over
+/// `database.username` and `database.password`
+fn credentials<P>(database: &DatabaseConfig, provider: Option<P>) -> Credentials<'_, P> {
+    match provider {
+        Some(provider) => Credentials::Provider(provider),
+        None if !database.username.is_empty() => Credentials::Password {
+            username: &database.username,
+            password: &database.password,
+        },
+        None => Credentials::Anonymous,
+    }
+}
+
+/// Execution profile of read-only commands, if `[reads]` overrides anything
+fn read_profile(reads: &ReadsConfig) -> Option<ExecutionProfile> {
+    if reads.consistency.is_none() && reads.datacenter.is_none() {
+        return None;
+    }
+
+    let mut profile = ExecutionProfile::builder();
+    if let Some(consistency) = reads.consistency {
+        profile = profile.consistency(consistency.into());
+    }
+    if let Some(datacenter) = &reads.datacenter {
+        // Other datacenters are still tried when the preferred one is unreachable
+        profile = profile.load_balancing_policy(
+            DefaultPolicy::builder()
+                .prefer_datacenter(datacenter.clone())
+                .token_aware(true)
+                .permit_dc_failover(true)
+                .build(),
+        );
+    }
+    Some(profile.build())
+}
+
+impl SharedSession {
+    /// Connect to the cluster described by `config.database`
+    pub async fn connect(
+        config: &Config,
+        authenticator: Option<Arc<dyn AuthenticatorProvider>>,
+    ) -> Result<Self, MigrationError> {
+        Self::connect_with(config, authenticator, true, false).await
+    }
+
+    async fn connect_with(
+        config: &Config,
+        authenticator: Option<Arc<dyn AuthenticatorProvider>>,
+        auto_await_schema_agreement: bool,
+        read_path: bool,
+    ) -> Result<Self, MigrationError> {
         info!("Connecting to ScyllaDB at: {:?}", config.database.hosts);
 
-        let mut session_builder = SessionBuilder::new().known_nodes(&config.database.hosts);
+        let mut session_builder = SessionBuilder::new()
+            .known_nodes(&config.database.hosts)
+            .auto_await_schema_agreement(auto_await_schema_agreement)
+            .keepalive_interval(SESSION_KEEPALIVE_INTERVAL);
+
+        session_builder = match credentials(&config.database, authenticator) {
+            Credentials::Provider(provider) => session_builder.authenticator_provider(provider),
+            Credentials::Password { username, password } => session_builder.user(username, password),
+            Credentials::Anonymous => session_builder,
+        };
 
-        if !config.database.username.is_empty() {
-            session_builder =
-                session_builder.user(&config.database.username, &config.database.password);
+        if config.database.tls.enabled {
+            let ssl_context = crate::tls::build_ssl_context(&config.database.tls)?;
+            session_builder = session_builder.ssl_context(Some(ssl_context));
+        }
+
+        if let Some(profile) = read_path.then(|| read_profile(&config.reads)).flatten() {
+            session_builder = session_builder.default_execution_profile_handle(profile.into_handle());
         }
 
         let session = session_builder.build().await?;
+        let cluster_name = fetch_cluster_name(&session).await?;
+
+        Ok(Self {
+            session: Arc::new(session),
+            cluster_name,
+            initialized: Arc::default(),
+            run_queue: RunQueue::default(),
+            app_owned: false,
+        })
+    }
 
-        let manager = Self { session, config };
+    /// Wrap a session opened by the application
+    ///
+    /// Wrappers of the same session share its run queue. The keyspace the session
+    /// uses is put back after each migration selects its own.
+    pub async fn from_session(session: Arc<Session>) -> Result<Self, MigrationError> {
+        let cluster_name = fetch_cluster_name(&session).await?;
+        let (run_queue, initialized) = app_session_state(&session);
+        Ok(Self {
+            session,
+            cluster_name,
+            initialized,
+            run_queue,
+            app_owned: true,
+        })
+    }
+
+    pub fn session(&self) -> &Session {
+        &self.session
+    }
+
+    pub(crate) fn session_arc(&self) -> Arc<Session> {
+        self.session.clone()
+    }
+}
+
+/// Interval of the driver's keepalive requests on idle connections, so long
+/// statements or pauses between them do not let firewalls drop the connections
+const SESSION_KEEPALIVE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Columns of the tracking table since its first version
+const RECORD_COLUMNS: &[&str] = &["version", "applied_at", "checksum", "description"];
+
+/// Columns a tracking record is read from, in order: [`RECORD_COLUMNS`], then
+/// the added ones that exist
+const READ_COLUMNS: &[&str] = &[
+    "cluster_name",
+    "target_keyspace",
+    "duration_ms",
+    "tool_version",
+    "up_checksum",
+];
+
+/// The query for all tracking records, selecting only columns in `existing`
+fn applied_migrations_query(table: &str, existing: &HashSet<String>) -> (String, Vec<&'static str>) {
+    let columns: Vec<&str> = RECORD_COLUMNS
+        .iter()
+        .copied()
+        .chain(READ_COLUMNS.iter().copied().filter(|column| existing.contains(*column)))
+        .collect();
+    (format!("SELECT {} FROM {}", columns.join(", "), table), columns)
+}
+
+/// A tracking record from a row of `columns`; columns not selected are absent
+fn record_from_row(columns: &[&str], row: Row) -> Result<MigrationRecord, MigrationError> {
+    let value = |name: &str| {
+        columns
+            .iter()
+            .position(|column| *column == name)
+            .and_then(|i| row.columns.get(i))
+            .and_then(Option::as_ref)
+    };
+    let text = |name: &str| value(name).and_then(CqlValue::as_text).cloned();
+    let required = |name: &str| {
+        text(name).ok_or_else(|| MigrationError::IntegrityError(format!("Tracking record without {}", name)))
+    };
+
+    let applied_at = value("applied_at")
+        .and_then(CqlValue::as_cql_timestamp)
+        .and_then(|timestamp| Utc.timestamp_millis_opt(timestamp.0).single())
+        .ok_or_else(|| MigrationError::IntegrityError("Invalid timestamp".into()))?;
+
+    Ok(MigrationRecord {
+        version: required("version")?,
+        applied_at,
+        checksum: required("checksum")?,
+        description: text("description").unwrap_or_default(),
+        cluster_name: text("cluster_name"),
+        target_keyspace: text("target_keyspace"),
+        duration_ms: value("duration_ms").and_then(CqlValue::as_bigint),
+        tool_version: text("tool_version"),
+        up_checksum: text("up_checksum"),
+    })
+}
+
+/// Columns added to the tracking table after its initial release, with their types
+///
+/// Existing tracking tables are upgraded in place when a manager is built to
+/// write to them (see [`MigrationManagerBuilder::initialize_schema`]); reading
+/// commands select only the columns that exist.
+const TRACKING_COLUMNS: &[(&str, &str)] = &[
+    ("cluster_name", "TEXT"),
+    ("target_keyspace", "TEXT"),
+    ("down_content", "TEXT"),
+    ("duration_ms", "BIGINT"),
+    ("tool_version", "TEXT"),
+    ("up_checksum", "TEXT"),
+];
+
+/// Every column of a tracking table created or upgraded by this version
+fn upgraded_tracking_columns() -> HashSet<String> {
+    RECORD_COLUMNS
+        .iter()
+        .chain(TRACKING_COLUMNS.iter().map(|(column, _)| column))
+        .map(|column| column.to_string())
+        .collect()
+}
+
+/// Columns of the tracking table known without reading `system_schema`: those
+/// a manager looked up already, or every column when the table was
+/// initialized through the session, possibly by another manager
+fn known_tracking_columns(cached: Option<HashSet<String>>, initialized: bool) -> Option<HashSet<String>> {
+    cached.or_else(|| initialized.then(upgraded_tracking_columns))
+}
+
+/// Builder for a [`MigrationManager`], for callers that need to customize the connection
+pub struct MigrationManagerBuilder {
+    config: Config,
+    authenticator: Option<Arc<dyn AuthenticatorProvider>>,
+    cancellation: Option<CancellationToken>,
+    shared_session: Option<SharedSession>,
+    initialize_schema: bool,
+    require_keyspaces: bool,
+    measure_statements: bool,
+    read_path: bool,
+}
+
+impl MigrationManagerBuilder {
+    /// Authenticate with a custom provider instead of username/password
+    pub fn authenticator_provider(mut self, provider: Arc<dyn AuthenticatorProvider>) -> Self {
+        self.authenticator = Some(provider);
+        self
+    }
+
+    /// Stop migration runs between statements once the token is cancelled
+    pub fn cancellation_token(mut self, token: CancellationToken) -> Self {
+        self.cancellation = Some(token);
+        self
+    }
+
+    /// Reuse an existing connection instead of connecting again
+    ///
+    /// The tracking table is only initialized the first time it is seen through
+    /// the shared session.
+    pub fn shared_session(mut self, session: SharedSession) -> Self {
+        self.shared_session = Some(session);
+        self
+    }
+
+    /// Whether to create the keyspace and tracking table (default: true)
+    ///
+    /// Without initialization no DDL is issued, so read-only credentials suffice;
+    /// a missing tracking table then reads as "no migrations applied".
+    pub fn initialize_schema(mut self, initialize: bool) -> Self {
+        self.initialize_schema = initialize;
+        self
+    }
+
+    /// Whether to fail when a configured keyspace is missing and
+    /// `auto_create_keyspace` is off (default: true); off for commands that
+    /// manage keyspaces themselves
+    pub fn require_keyspaces(mut self, require: bool) -> Self {
+        self.require_keyspaces = require;
+        self
+    }
+
+    /// Record how long each statement takes (see [`MigrationManager::take_statement_timings`])
+    ///
+    /// Schema agreement is then awaited by the manager instead of the driver, so
+    /// that it can be timed separately. Ignored for shared sessions.
+    pub fn measure_statements(mut self, measure: bool) -> Self {
+        self.measure_statements = measure;
+        self
+    }
+
+    /// Run every query with the `[reads]` consistency and datacenter (default:
+    /// false), for commands that only read. Ignored for shared sessions.
+    pub fn read_path(mut self, read_path: bool) -> Self {
+        self.read_path = read_path;
+        self
+    }
+
+    /// Establish the database connection and initialize the schema
+    pub async fn build(self) -> Result<MigrationManager, MigrationError> {
+        let version_scheme = Arc::new(self.config.version_scheme()?);
+        let (shared, measure) = match self.shared_session {
+            Some(shared) => (shared, false),
+            None => {
+                let shared = SharedSession::connect_with(
+                    &self.config,
+                    self.authenticator,
+                    !self.measure_statements,
+                    self.read_path,
+                )
+                .await?;
+                (shared, self.measure_statements)
+            }
+        };
+
+        let manager = MigrationManager {
+            session: shared.session,
+            config: self.config,
+            cancellation: self.cancellation.unwrap_or_default(),
+            cluster_name: shared.cluster_name,
+            initialized: shared.initialized,
+            run_queue: shared.run_queue,
+            restore_keyspace: shared.app_owned,
+            version_scheme,
+            failed_statements: Arc::default(),
+            timings: measure.then(Arc::default),
+            lock_lease: LockLease::default(),
+            runs: Arc::default(),
+            tracking_columns: Arc::default(),
+        };
+
+        if self.require_keyspaces && !manager.config.behavior.auto_create_keyspace {
+            manager.ensure_keyspaces_exist().await?;
+        }
 
         // Ensure keyspace and migrations table exist
-        manager.initialize_schema().await?;
+        let tracking_table = manager.tracking_table();
+        if self.initialize_schema
+            && !manager.config.behavior.read_only
+            && !manager.is_initialized(&tracking_table)
+        {
+            manager.initialize_schema().await?;
+            manager.mark_initialized(tracking_table);
+        }
 
         Ok(manager)
     }
+}
+
+impl MigrationManager {
+    /// Create a new migration manager and establish database connection
+    pub async fn new(config: Config) -> Result<Self, MigrationError> {
+        Self::builder(config).build().await
+    }
+
+    /// Create a migration manager on a session owned by the application instead
+    /// of opening another connection pool
+    ///
+    /// `config.database` connection settings are not used. Migrations select their
+    /// keyspace with `USE`; the keyspace the session used before is selected again
+    /// once each migration completes. Managers built on the same session share
+    /// its run queue.
+    pub async fn with_session(session: Arc<Session>, config: Config) -> Result<Self, MigrationError> {
+        Self::builder(config)
+            .shared_session(SharedSession::from_session(session).await?)
+            .build()
+            .await
+    }
+
+    /// Start building a migration manager with custom connection options
+    pub fn builder(config: Config) -> MigrationManagerBuilder {
+        MigrationManagerBuilder {
+            config,
+            authenticator: None,
+            cancellation: None,
+            shared_session: None,
+            initialize_schema: true,
+            require_keyspaces: true,
+            measure_statements: false,
+            read_path: false,
+        }
+    }
+
+    /// How migration versions are read from file names and ordered
+    pub fn version_scheme(&self) -> &VersionScheme {
+        &self.version_scheme
+    }
+
+    /// The connection used by this manager, for reuse by other managers
+    pub fn shared_session(&self) -> SharedSession {
+        SharedSession {
+            session: self.session.clone(),
+            cluster_name: self.cluster_name.clone(),
+            initialized: self.initialized.clone(),
+            run_queue: self.run_queue.clone(),
+            app_owned: self.restore_keyspace,
+        }
+    }
+
+    /// Wait until no other run through this connection is in progress
+    ///
+    /// Runs are started in arrival order; hold the permit for the whole run.
+    /// Fails with [`MigrationError::QueueTimeout`] after waiting `timeout`.
+    pub async fn exclusive_run(
+        &self,
+        operation: &str,
+        timeout: Duration,
+    ) -> Result<RunPermit, MigrationError> {
+        self.run_queue.acquire(operation, timeout).await
+    }
+
+    pub(crate) fn is_initialized(&self, table: &str) -> bool {
+        self.initialized.lock().unwrap().contains(table)
+    }
+
+    /// Remember that `table` was created through this session
+    pub(crate) fn mark_initialized(&self, table: String) {
+        self.initialized.lock().unwrap().insert(table);
+    }
+
+    /// Token that cancels in-progress migration runs when triggered
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancellation.clone()
+    }
+
+    /// Whether the current run has been cancelled
+    pub fn is_cancelled(&self) -> bool {
+        self.cancellation.is_cancelled()
+    }
+
+    /// Cancel runs of this manager on Ctrl-C or SIGTERM, so they stop gracefully
+    /// at the next statement boundary
+    pub fn cancel_on_shutdown_signal(&self) {
+        let cancellation = self.cancellation_token();
+        tokio::spawn(async move {
+            if shutdown_signal().await {
+                cancellation.cancel();
+            }
+        });
+    }
+
+    pub(crate) fn lock_lease(&self) -> &LockLease {
+        &self.lock_lease
+    }
+
+    /// Execute statements in order, stopping at the next statement boundary on cancellation
+    ///
+    /// Unqualified names resolve to `keyspace` (the `keyspace` directive) or to the
+    /// configured keyspace. It is selected at the start of every migration, so a
+    /// `USE` statement only lasts until the migration completes.
+    ///
+    /// Statements restricted to other environments (`only-env`) are skipped.
+    /// With the `batch` directive the remaining statements are sent in batches.
+    /// UP sections pass the `checkpoint` they start at, which is saved again if
+    /// the run is cancelled.
+    #[allow(clippy::too_many_arguments)]
+    async fn execute_statements(
+        &self,
+        version: &str,
+        keyspace: Option<&str>,
+        only_env: &[String],
+        execution: &ExecutionOptions,
+        statements: Vec<String>,
+        lines: &[(usize, usize)],
+        checkpoint: Option<&Checkpoint>,
+    ) -> Result<(), MigrationError> {
+        self.prepare_session(version, keyspace).await?;
+
+        let mut selector = EnvSelector::new(self.config.behavior.environment.as_deref(), only_env);
+        if let Some(kind) = execution.batch {
+            let mut selected = Vec::new();
+            for (index, statement) in statements.iter().enumerate() {
+                if !selector.includes(statement) {
+                    self.log_skipped(version, statement);
+                } else if checkpoint.is_none_or(|checkpoint| checkpoint.runs(index, statement)) {
+                    selected.push((index, statement.as_str()));
+                }
+            }
+            let size = execution.batch_size.unwrap_or(DEFAULT_BATCH_SIZE);
+            return self
+                .execute_batches(version, keyspace, kind, size, &selected, lines, checkpoint)
+                .await;
+        }
+
+        for (executed, statement) in statements.iter().enumerate() {
+            if !selector.includes(statement) {
+                self.log_skipped(version, statement);
+                continue;
+            }
+            if checkpoint.is_some_and(|checkpoint| !checkpoint.runs(executed, statement)) {
+                continue;
+            }
+            self.check_lock_lease(version, executed)?;
+            if self.cancellation.is_cancelled() {
+                warn!(
+                    "Cancelled migration {} after {} of {} statement(s)",
+                    version,
+                    executed,
+                    statements.len()
+                );
+                if let Some(checkpoint) = checkpoint {
+                    self.save_checkpoint(&checkpoint.at(executed)).await;
+                }
+                return Err(MigrationError::Cancelled {
+                    version: version.to_string(),
+                    statements_executed: executed,
+                });
+            }
+
+            self.execute_statement(version, executed, lines.get(executed).copied(), execution, statement)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Execute the statements of a `batch` migration, `size` statements per batch
+    ///
+    /// Logged batches take consecutive statements. Unlogged batches only group
+    /// statements writing the same partition, so each goes to one replica set;
+    /// statements whose partition cannot be told from their text go alone.
+    #[allow(clippy::too_many_arguments)]
+    async fn execute_batches(
+        &self,
+        version: &str,
+        keyspace: Option<&str>,
+        kind: BatchKind,
+        size: usize,
+        statements: &[(usize, &str)],
+        lines: &[(usize, usize)],
+        checkpoint: Option<&Checkpoint>,
+    ) -> Result<(), MigrationError> {
+        if let Some((index, statement)) = statements.iter().find(|(_, statement)| !cql::is_dml(statement)) {
+            return Err(MigrationError::InvalidFormat(format!(
+                "Migration {} uses the batch directive, but statement {} is not an INSERT, UPDATE or DELETE: {}",
+                version,
+                index + 1,
+                statement.lines().last().unwrap_or_default().trim()
+            )));
+        }
+
+        let groups = match kind {
+            BatchKind::Logged => vec![statements.to_vec()],
+            BatchKind::Unlogged => {
+                let keyspace = keyspace.unwrap_or(&self.config.database.keyspace);
+                self.group_by_partition(keyspace, statements).await?
+            }
+        };
+
+        let mut executed = 0;
+        for chunk in groups.iter().flat_map(|group| group.chunks(size)) {
+            self.check_lock_lease(version, executed)?;
+            if self.cancellation.is_cancelled() {
+                warn!(
+                    "Cancelled migration {} after {} of {} statement(s)",
+                    version,
+                    executed,
+                    statements.len()
+                );
+                // Unlogged batches are not sent in file order, so they cannot resume
+                match (checkpoint, kind) {
+                    (Some(checkpoint), BatchKind::Logged) => {
+                        self.save_checkpoint(&checkpoint.at(statements[executed].0)).await
+                    }
+                    (Some(_), BatchKind::Unlogged) if executed > 0 => {
+                        warn!("Migration {} uses unlogged batches; the next run starts from its first statement", version)
+                    }
+                    _ => {}
+                }
+                return Err(MigrationError::Cancelled {
+                    version: version.to_string(),
+                    statements_executed: executed,
+                });
+            }
+
+            let mut batch = Batch::new(match kind {
+                BatchKind::Logged => BatchType::Logged,
+                BatchKind::Unlogged => BatchType::Unlogged,
+            });
+            for (_, statement) in chunk {
+                batch.append_statement(self.resolve_placeholders(statement)?.as_str());
+            }
+            debug!("Executing a {:?} batch of {} statement(s)", kind, chunk.len());
+            if let Err(e) = self.session.batch(&batch, vec![(); chunk.len()]).await {
+                // Point at the whole batch; any of its statements may have failed
+                let (first, _) = chunk[0];
+                let (last, _) = chunk[chunk.len() - 1];
+                let span = lines
+                    .get(first)
+                    .zip(lines.get(last))
+                    .map(|((start, _), (_, end))| (*start, *end));
+                self.failed_statements.lock().unwrap().insert(
+                    version.to_string(),
+                    FailedStatement {
+                        index: first,
+                        lines: span,
+                        statement: chunk
+                            .iter()
+                            .map(|(_, statement)| statement.trim())
+                            .collect::<Vec<_>>()
+                            .join(";\n"),
+                    },
+                );
+                return Err(e.into());
+            }
+            executed += chunk.len();
+            self.count_executed(version, chunk.len());
+        }
+
+        Ok(())
+    }
+
+    /// Group statements by the partition they write, in order of first appearance
+    async fn group_by_partition<'s>(
+        &self,
+        keyspace: &str,
+        statements: &[(usize, &'s str)],
+    ) -> Result<Vec<Vec<(usize, &'s str)>>, MigrationError> {
+        let mut partition_keys: HashMap<(String, String), Vec<String>> = HashMap::new();
+        let mut by_partition: HashMap<(String, String, Vec<String>), usize> = HashMap::new();
+        let mut groups: Vec<Vec<(usize, &str)>> = Vec::new();
+
+        for &(index, statement) in statements {
+            let tokens = cql::tokenize(statement).unwrap_or_default();
+            let mut partition = None;
+            if let Some(target) = cql::target_object(&tokens) {
+                let (qualifier, table) = split_qualified(&target);
+                let keyspace = qualifier.unwrap_or(keyspace);
+                let key = (normalize_identifier(keyspace), normalize_identifier(table));
+                if !partition_keys.contains_key(&key) {
+                    let columns = SchemaIntrospector::new(&self.session, keyspace)
+                        .partition_key(table)
+                        .await?;
+                    partition_keys.insert(key.clone(), columns);
+                }
+                let columns = &partition_keys[&key];
+                if !columns.is_empty() {
+                    partition = cql::partition_values(&tokens, columns).map(|values| (key.0, key.1, values));
+                }
+            }
+
+            match partition {
+                Some(partition) => {
+                    let group = *by_partition.entry(partition).or_insert_with(|| {
+                        groups.push(Vec::new());
+                        groups.len() - 1
+                    });
+                    groups[group].push((index, statement));
+                }
+                None => groups.push(vec![(index, statement)]),
+            }
+        }
+
+        Ok(groups)
+    }
+
+    /// Execute one section of a streamed migration file, reading one statement at a time
+    ///
+    /// Returns the number of statements in the section, including skipped ones.
+    async fn execute_streamed(
+        &self,
+        migration: &MigrationFile,
+        section: Section,
+        checkpoint: Option<&Checkpoint>,
+    ) -> Result<usize, MigrationError> {
+        let version = &migration.version;
+        self.prepare_session(version, migration.directives.keyspace.as_deref())
+            .await?;
+
+        let mut selector = EnvSelector::new(
+            self.config.behavior.environment.as_deref(),
+            &migration.directives.only_env,
+        );
+        let mut statements = StatementStream::open(&migration.file_path, section).await?;
+        let mut executed = 0;
+        while let Some(statement) = statements.next_statement().await? {
+            if !selector.includes(&statement) {
+                self.log_skipped(version, &statement);
+                executed += 1;
+                continue;
+            }
+            if checkpoint.is_some_and(|checkpoint| !checkpoint.runs(executed, &statement)) {
+                executed += 1;
+                continue;
+            }
+            self.check_lock_lease(version, executed)?;
+            if self.cancellation.is_cancelled() {
+                warn!("Cancelled migration {} after {} statement(s)", version, executed);
+                if let Some(checkpoint) = checkpoint {
+                    self.save_checkpoint(&checkpoint.at(executed)).await;
+                }
+                return Err(MigrationError::Cancelled {
+                    version: version.to_string(),
+                    statements_executed: executed,
+                });
+            }
+
+            self.execute_statement(version, executed, None, &migration.directives.execution, &statement)
+                .await?;
+            executed += 1;
+        }
+
+        debug!("Streamed {} statement(s) from {}", executed, version);
+        Ok(executed)
+    }
+
+    fn log_skipped(&self, version: &str, statement: &str) {
+        info!(
+            "Skipping statement of {} restricted by only-env (environment: {}): {}",
+            version,
+            self.config.behavior.environment.as_deref().unwrap_or("unset"),
+            statement.lines().last().unwrap_or_default().trim()
+        );
+    }
+
+    /// Select the migration's keyspace before its first statement
+    async fn prepare_session(&self, version: &str, keyspace: Option<&str>) -> Result<(), MigrationError> {
+        self.ensure_writable()?;
+
+        let keyspace = keyspace.unwrap_or(&self.config.database.keyspace);
+        debug!("Migration {} targets keyspace {}", version, keyspace);
+        // Quoted names are case-sensitive
+        let case_sensitive = keyspace.starts_with('"');
+        self.session
+            .use_keyspace(normalize_identifier(keyspace), case_sensitive)
+            .await?;
+        Ok(())
+    }
+
+    /// Select again the keyspace an application's session used before a migration
+    ///
+    /// A session that had none keeps the migration's: the driver cannot unset it,
+    /// and the application's queries on it are keyspace-qualified anyway.
+    async fn restore_session_keyspace(&self, previous: Option<Arc<String>>) {
+        let Some(previous) = previous.filter(|_| self.restore_keyspace) else {
+            return;
+        };
+        if self.session.get_keyspace() == Some(previous.clone()) {
+            return;
+        }
+        if let Err(e) = self.session.use_keyspace(previous.as_str(), true).await {
+            warn!("Could not select keyspace {} again on the application's session: {}", previous, e);
+        }
+    }
+
+    /// A statement with its `${var:...}` and `${secret:...}` placeholders filled in
+    fn resolve_placeholders(&self, statement: &str) -> Result<String, MigrationError> {
+        resolve_secrets(&resolve_variables(statement, &self.config.variables)?, &self.config.secrets)
+    }
+
+    async fn execute_statement(
+        &self,
+        version: &str,
+        index: usize,
+        lines: Option<(usize, usize)>,
+        execution: &ExecutionOptions,
+        statement: &str,
+    ) -> Result<(), MigrationError> {
+        debug!("Executing: {}", statement.trim());
+        if let Some(keyspace) = cql::used_keyspace(statement) {
+            warn!(
+                "Migration {} switches to keyspace {} until it completes",
+                version, keyspace
+            );
+        }
+        let resolved = self.resolve_placeholders(statement)?;
+        let options = execution.for_statement(statement).map_err(|e| {
+            MigrationError::InvalidFormat(format!("Statement {} of {}: {}", index + 1, version, e))
+        })?;
+        let started = Instant::now();
+        if let Err(e) = self.run_statement(resolved, &options).await {
+            // Keep the unresolved text so secrets never end up in diagnostics
+            self.failed_statements.lock().unwrap().insert(
+                version.to_string(),
+                FailedStatement {
+                    index,
+                    lines,
+                    statement: statement.trim().to_string(),
+                },
+            );
+            return Err(e);
+        }
+        self.count_executed(version, 1);
+
+        if let Some(timings) = &self.timings {
+            let execution = started.elapsed();
+            let schema_agreement = if cql::is_schema_change(statement) {
+                let started = Instant::now();
+                self.session.await_schema_agreement().await?;
+                Some(started.elapsed().as_secs_f64() * 1000.0)
+            } else {
+                None
+            };
+
+            timings.lock().unwrap().push(StatementTiming {
+                version: version.to_string(),
+                index,
+                statement: statement.trim().to_string(),
+                execution_ms: execution.as_secs_f64() * 1000.0,
+                schema_agreement_ms: schema_agreement,
+            });
+        }
+        Ok(())
+    }
+
+    /// Send one statement with its driver options
+    ///
+    /// Token-aware statements are prepared so the driver can compute the token of
+    /// the partition from the bound `routing-key` values and pick a replica.
+    async fn run_statement(&self, statement: String, options: &ExecutionOptions) -> Result<(), MigrationError> {
+        if !options.token_aware {
+            let mut query = Query::new(statement);
+            if let Some(page_size) = options.page_size {
+                query.set_page_size(page_size);
+            }
+            self.session.query(query, &[]).await?;
+            return Ok(());
+        }
+
+        let mut prepared = self.session.prepare(statement).await?;
+        if let Some(page_size) = options.page_size {
+            prepared.set_page_size(page_size);
+        }
+        let markers = prepared.get_variable_col_specs();
+        if markers.len() != options.routing_key.len() {
+            return Err(MigrationError::InvalidFormat(format!(
+                "The statement has {} bind marker(s) but routing-key gives {} value(s)",
+                markers.len(),
+                options.routing_key.len()
+            )));
+        }
+        let values = markers
+            .iter()
+            .zip(&options.routing_key)
+            .map(|(marker, literal)| {
+                bind_literal(literal, &marker.typ).map_err(|e| {
+                    MigrationError::InvalidFormat(format!("routing-key value for {}: {}", marker.name, e))
+                })
+            })
+            .collect::<Result<Vec<CqlValue>, _>>()?;
+        self.session.execute(&prepared, values).await?;
+        Ok(())
+    }
 
     /// Initialize the keyspace and migrations tracking table
     async fn initialize_schema(&self) -> Result<(), MigrationError> {
-        // Create keyspace if it doesn't exist and auto_create is enabled
+        // Create keyspaces if they don't exist and auto_create is enabled
         if self.config.behavior.auto_create_keyspace {
-            let create_keyspace_query = format!(
-                "CREATE KEYSPACE IF NOT EXISTS {} WITH REPLICATION = {{'class': 'SimpleStrategy', 'replication_factor': 1}}",
-                self.config.database.keyspace
-            );
+            let mut keyspaces = vec![self.config.database.keyspace.as_str()];
+            if self.config.tracking_keyspace() != self.config.database.keyspace {
+                keyspaces.push(self.config.tracking_keyspace());
+            }
 
-            debug!("Creating keyspace: {}", create_keyspace_query);
-            self.session.query(create_keyspace_query, &[]).await?;
-        }
+            let environment = self.config.behavior.environment.as_deref();
+            let replication = self.config.replication.resolve(environment);
+            for keyspace in keyspaces {
+                if !self.config.replication.allows_auto_create(environment) {
+                    // The environment refuses to create keyspaces; missing ones are an error
+                    if !SchemaIntrospector::new(&self.session, keyspace).keyspace_exists().await? {
+                        return Err(MigrationError::ConfigError(format!(
+                            "Keyspace {} does not exist and replication.environments.{} sets auto_create = false; create it with 'db-migrate keyspace create' first",
+                            keyspace,
+                            environment.unwrap_or_default()
+                        )));
+                    }
+                    continue;
+                }
 
-        // Use the keyspace
-        let use_keyspace_query = format!("USE {}", self.config.database.keyspace);
-        self.session.query(use_keyspace_query, &[]).await?;
+                let create_keyspace_query = format!(
+                    "CREATE KEYSPACE IF NOT EXISTS {} WITH REPLICATION = {}",
+                    keyspace,
+                    replication.to_cql()
+                );
+
+                debug!("Creating keyspace: {}", create_keyspace_query);
+                self.session.query(create_keyspace_query, &[]).await?;
+            }
+        }
 
         // Create migrations table
         let create_table_query = format!(
@@ -64,97 +1048,348 @@ impl MigrationManager {
                 version TEXT PRIMARY KEY,
                 applied_at TIMESTAMP,
                 checksum TEXT,
-                description TEXT
+                description TEXT,
+                cluster_name TEXT,
+                target_keyspace TEXT,
+                down_content TEXT,
+                duration_ms BIGINT,
+                tool_version TEXT,
+                up_checksum TEXT
             )",
-            self.config.migrations.table_name
+            self.tracking_table()
         );
 
         debug!("Creating migrations table: {}", create_table_query);
         self.session.query(create_table_query, &[]).await?;
 
+        self.upgrade_tracking_table().await?;
+
         info!("Schema initialization completed");
         Ok(())
     }
 
-    /// Get all applied migrations from the database
-    pub async fn get_applied_migrations(&self) -> Result<Vec<MigrationRecord>, MigrationError> {
-        let query = format!(
-            "SELECT version, applied_at, checksum, description FROM {}",
-            self.config.migrations.table_name
+    /// Fail with suggestions when a configured keyspace does not exist
+    ///
+    /// Only checked when keyspaces are not created automatically; otherwise the
+    /// first `USE` or `CREATE TABLE` fails with a bare driver error.
+    async fn ensure_keyspaces_exist(&self) -> Result<(), MigrationError> {
+        let rows = self
+            .session
+            .query("SELECT keyspace_name FROM system_schema.keyspaces", &[])
+            .await?;
+        let mut existing = Vec::new();
+        for row in rows
+            .rows_typed::<(String,)>()
+            .map_err(|e| MigrationError::IntegrityError(e.to_string()))?
+        {
+            let (keyspace,) = row.map_err(|e| MigrationError::IntegrityError(e.to_string()))?;
+            existing.push(keyspace);
+        }
+
+        let mut keyspaces = vec![("database.keyspace", self.config.database.keyspace.as_str())];
+        if self.config.tracking_keyspace() != self.config.database.keyspace {
+            keyspaces.push(("migrations.tracking_keyspace", self.config.tracking_keyspace()));
+        }
+
+        for (setting, keyspace) in keyspaces {
+            if existing.contains(&normalize_identifier(keyspace)) {
+                continue;
+            }
+
+            let candidates = existing.iter().map(String::as_str).filter(|name| !name.starts_with("system"));
+            let hint = match crate::utils::similar_names(keyspace, candidates).as_slice() {
+                [] => String::new(),
+                [only] => format!(" Did you mean `{}`?", only),
+                several => format!(
+                    " Did you mean one of: {}?",
+                    several.iter().map(|name| format!("`{}`", name)).collect::<Vec<_>>().join(", ")
+                ),
+            };
+            return Err(MigrationError::ConfigError(format!(
+                "Keyspace `{}` ({}) does not exist and behavior.auto_create_keyspace is false.{} Create it, fix the name, or enable auto_create_keyspace.",
+                keyspace, setting, hint
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Add columns introduced by newer versions to an existing tracking table
+    async fn upgrade_tracking_table(&self) -> Result<(), MigrationError> {
+        let existing = self.query_tracking_columns().await?;
+
+        for (column, data_type) in TRACKING_COLUMNS {
+            if !existing.contains(*column) {
+                info!("Adding column {} to the migrations table", column);
+                let alter = format!(
+                    "ALTER TABLE {} ADD {} {}",
+                    self.tracking_table(),
+                    column,
+                    data_type
+                );
+                self.session.query(alter, &[]).await?;
+            }
+        }
+
+        *self.tracking_columns.lock().unwrap() = Some(upgraded_tracking_columns());
+        Ok(())
+    }
+
+    /// Columns of the tracking table, from `system_schema` unless known already
+    async fn tracking_columns(&self) -> Result<HashSet<String>, MigrationError> {
+        let known = known_tracking_columns(
+            self.tracking_columns.lock().unwrap().clone(),
+            self.is_initialized(&self.tracking_table()),
         );
+        let columns = match known {
+            Some(columns) => columns,
+            None => self.query_tracking_columns().await?,
+        };
+        *self.tracking_columns.lock().unwrap() = Some(columns.clone());
+        Ok(columns)
+    }
 
-        let rows = self.session.query(query, &[]).await?;
-        let mut migrations = Vec::new();
+    async fn query_tracking_columns(&self) -> Result<HashSet<String>, MigrationError> {
+        let query = "SELECT column_name FROM system_schema.columns WHERE keyspace_name = ? AND table_name = ?";
+        let rows = self
+            .session
+            .query(
+                query,
+                (
+                    normalize_identifier(self.config.tracking_keyspace()),
+                    normalize_identifier(&self.config.migrations.table_name),
+                ),
+            )
+            .await?;
 
+        let mut existing = HashSet::new();
         for row in rows
-            .rows_typed::<(String, CqlTimestamp, String, String)>()
+            .rows_typed::<(String,)>()
             .map_err(|e| MigrationError::IntegrityError(e.to_string()))?
         {
-            let (version, applied_at_ts, checksum, description) =
-                row.map_err(|e| MigrationError::IntegrityError(e.to_string()))?;
+            let (column,) = row.map_err(|e| MigrationError::IntegrityError(e.to_string()))?;
+            existing.insert(column);
+        }
+        Ok(existing)
+    }
 
-            let applied_at = Utc
-                .timestamp_millis_opt(applied_at_ts.0)
-                .single()
-                .ok_or_else(|| MigrationError::IntegrityError("Invalid timestamp".into()))?;
+    /// Name of the connected cluster
+    pub fn cluster_name(&self) -> Option<&str> {
+        self.cluster_name.as_deref()
+    }
 
-            migrations.push(MigrationRecord {
-                version,
-                applied_at,
-                checksum,
-                description,
-            });
+    /// Timings recorded since the last call, when built with `measure_statements`
+    pub fn take_statement_timings(&self) -> Vec<StatementTiming> {
+        self.timings
+            .as_ref()
+            .map(|timings| std::mem::take(&mut *timings.lock().unwrap()))
+            .unwrap_or_default()
+    }
+
+    /// The statement that failed in a migration
+    pub fn failed_statement(&self, version: &str) -> Option<FailedStatement> {
+        self.failed_statements.lock().unwrap().get(version).cloned()
+    }
+
+    /// Migrations applied or rolled back through this manager, in start order
+    pub fn migration_runs(&self) -> Vec<MigrationRun> {
+        self.runs.lock().unwrap().clone()
+    }
+
+    fn start_run(&self, version: &str, direction: &'static str) -> Instant {
+        self.runs.lock().unwrap().push(MigrationRun {
+            version: version.to_string(),
+            direction,
+            statements_executed: 0,
+            duration_ms: 0,
+            error: None,
+        });
+        Instant::now()
+    }
+
+    /// Count `statements` completed by the run of `version` in progress
+    fn count_executed(&self, version: &str, statements: usize) {
+        if let Some(run) = self.runs.lock().unwrap().iter_mut().rev().find(|run| run.version == version) {
+            run.statements_executed += statements;
         }
+    }
 
-        Ok(migrations)
+    /// Statements completed so far by the run of `version` in progress
+    fn executed_in_run(&self, version: &str) -> usize {
+        self.runs
+            .lock()
+            .unwrap()
+            .iter()
+            .rev()
+            .find(|run| run.version == version)
+            .map_or(0, |run| run.statements_executed)
     }
 
-    /// Get all migration files from the filesystem
-    pub async fn get_migration_files(&self) -> Result<Vec<MigrationFile>, MigrationError> {
-        let migrations_dir = &self.config.migrations.directory;
+    fn finish_run(&self, version: &str, started: Instant, result: &Result<(), MigrationError>) {
+        if let Some(run) = self.runs.lock().unwrap().iter_mut().rev().find(|run| run.version == version) {
+            run.duration_ms = started.elapsed().as_millis() as u64;
+            run.error = result.as_ref().err().map(ToString::to_string);
+        }
+    }
+
+    /// Applied migrations that were recorded on a different cluster
+    ///
+    /// Non-empty when the tracking table was restored from another cluster, in which
+    /// case checksums may match even though the schema here was never migrated.
+    pub fn foreign_cluster_records<'r>(
+        &self,
+        applied: &'r [MigrationRecord],
+    ) -> Vec<&'r MigrationRecord> {
+        Self::foreign_records(self.cluster_name.as_deref(), applied)
+    }
+
+    /// Records naming a cluster other than `current`; records from before cluster
+    /// names were kept, and every record when the cluster has no name, are not
+    fn foreign_records<'r>(
+        current: Option<&str>,
+        applied: &'r [MigrationRecord],
+    ) -> Vec<&'r MigrationRecord> {
+        let Some(current) = current else {
+            return Vec::new();
+        };
+
+        applied
+            .iter()
+            .filter(|record| record.cluster_name.as_deref().is_some_and(|name| name != current))
+            .collect()
+    }
+
+    /// Keyspace-qualified name of the tracking table
+    pub(crate) fn tracking_table(&self) -> String {
+        self.config.tracking_table()
+    }
 
-        if !migrations_dir.exists() {
-            fs::create_dir_all(migrations_dir).await?;
+    /// Keyspace a migration's unqualified names resolve to
+    pub fn target_keyspace<'m>(&'m self, migration: &'m MigrationFile) -> &'m str {
+        migration
+            .directives
+            .keyspace
+            .as_deref()
+            .unwrap_or(&self.config.database.keyspace)
+    }
+
+    /// Whether the tracking table exists, for managers built without initialization
+    async fn tracking_table_exists(&self) -> Result<bool, MigrationError> {
+        if self.is_initialized(&self.tracking_table()) {
+            return Ok(true);
+        }
+
+        let rows = self
+            .session
+            .query(
+                "SELECT table_name FROM system_schema.tables WHERE keyspace_name = ? AND table_name = ?",
+                (
+                    normalize_identifier(self.config.tracking_keyspace()),
+                    normalize_identifier(&self.config.migrations.table_name),
+                ),
+            )
+            .await?;
+        Ok(!rows.rows.unwrap_or_default().is_empty())
+    }
+
+    /// Tables already in the keyspace although the tracking table has no records
+    ///
+    /// Empty as soon as any migration is recorded. The tracking table itself and
+    /// its history table are not counted.
+    pub async fn tables_without_tracking(&self) -> Result<Vec<String>, MigrationError> {
+        if !self.get_applied_migrations().await?.is_empty() {
             return Ok(Vec::new());
         }
 
-        let mut files = Vec::new();
+        let keyspace = &self.config.database.keyspace;
+        let rows = self
+            .session
+            .query(
+                "SELECT table_name FROM system_schema.tables WHERE keyspace_name = ?",
+                (normalize_identifier(keyspace),),
+            )
+            .await?;
 
-        for entry in WalkDir::new(migrations_dir)
-            .min_depth(1)
-            .max_depth(1)
-            .sort_by_file_name()
-        {
-            let entry = entry.map_err(|e| MigrationError::ConfigError(e.to_string()))?;
-            let path = entry.path();
+        let own_tables = Self::own_tables(&self.config);
 
-            if path.extension().and_then(|s| s.to_str()) != Some("cql") {
-                continue;
+        let mut tables = Vec::new();
+        for row in rows
+            .rows_typed::<(String,)>()
+            .map_err(|e| MigrationError::IntegrityError(e.to_string()))?
+        {
+            let (table,) = row.map_err(|e| MigrationError::IntegrityError(e.to_string()))?;
+            if !own_tables.contains(&table) {
+                tables.push(table);
             }
+        }
 
-            let filename = path
-                .file_name()
-                .and_then(|n| n.to_str())
-                .ok_or_else(|| MigrationError::InvalidFormat("Invalid filename".to_string()))?;
+        tables.sort();
+        Ok(tables)
+    }
 
-            if let Some(version) = extract_version_from_filename(filename) {
-                let content = fs::read_to_string(path).await?;
-                let checksum = calculate_checksum(&content);
-                let description = crate::utils::extract_description_from_filename(filename);
+    /// Tables db-migrate creates in `database.keyspace`: the tracking table and its
+    /// history and checkpoint tables, unless they live in `tracking_keyspace`
+    fn own_tables(config: &Config) -> Vec<String> {
+        if normalize_identifier(config.tracking_keyspace()) != normalize_identifier(&config.database.keyspace) {
+            return Vec::new();
+        }
 
-                files.push(MigrationFile {
-                    version,
-                    description,
-                    file_path: path.to_path_buf(),
-                    content,
-                    checksum,
-                });
-            } else {
-                warn!("Skipping file with invalid format: {}", filename);
-            }
+        let table_name = &config.migrations.table_name;
+        vec![
+            normalize_identifier(table_name),
+            normalize_identifier(&cql::suffixed_identifier(table_name, crate::history::HISTORY_TABLE_SUFFIX)),
+            normalize_identifier(&cql::suffixed_identifier(table_name, crate::checkpoint::CHECKPOINT_TABLE_SUFFIX)),
+        ]
+    }
+
+    /// Get all applied migrations from the database
+    pub async fn get_applied_migrations(&self) -> Result<Vec<MigrationRecord>, MigrationError> {
+        if !self.tracking_table_exists().await? {
+            debug!("Tracking table {} does not exist yet", self.tracking_table());
+            return Ok(Vec::new());
         }
 
-        Ok(files)
+        // Reading commands do not upgrade the table, so columns added since it
+        // was created may be missing
+        let existing = self.tracking_columns().await?;
+        let (query, columns) = applied_migrations_query(&self.tracking_table(), &existing);
+
+        let rows = self.session.query(query, &[]).await?;
+        let mut migrations = rows
+            .rows
+            .unwrap_or_default()
+            .into_iter()
+            .map(|row| record_from_row(&columns, row))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Rows come back in token order
+        migrations.sort_by(|a, b| self.version_scheme.compare(&a.version, &b.version));
+
+        // Records from a newer, incompatible db-migrate must not be rewritten by this one
+        compat::check_records(&migrations)?;
+
+        Ok(migrations)
+    }
+
+    /// Get all migration files from the filesystem
+    ///
+    /// Files ignored by `migrations.ignore` or marked `skip` are left out.
+    pub async fn get_migration_files(&self) -> Result<Vec<MigrationFile>, MigrationError> {
+        active_migration_files(&self.config).await
+    }
+
+    /// Files left out by `migrations.ignore` or the `skip` directive
+    pub async fn get_ignored_migration_files(&self) -> Result<Vec<MigrationFile>, MigrationError> {
+        let files = read_migration_files(&self.config.migrations.directory, &self.version_scheme).await?;
+        Ok(files
+            .into_iter()
+            .filter(|file| self.config.migrations.is_ignored(file))
+            .collect())
+    }
+
+    /// `.cql` files skipped because their name is not a migration version
+    pub async fn get_invalid_migration_files(&self) -> Result<Vec<InvalidMigrationFile>, MigrationError> {
+        read_invalid_migration_files(&self.config.migrations.directory, &self.version_scheme).await
     }
 
     /// Get pending migrations (files that haven't been applied)
@@ -177,8 +1412,41 @@ impl MigrationManager {
     pub async fn apply_migration(
         &mut self,
         migration: &MigrationFile,
+    ) -> Result<(), MigrationError> {
+        self.apply_migration_inner(migration, false).await
+    }
+
+    /// Apply a migration while other migrations may be applied concurrently
+    ///
+    /// The tracking record is written with a lightweight transaction so that
+    /// concurrent runners cannot both record the same version.
+    pub async fn apply_migration_concurrently(
+        &self,
+        migration: &MigrationFile,
+    ) -> Result<(), MigrationError> {
+        self.apply_migration_inner(migration, true).await
+    }
+
+    async fn apply_migration_inner(
+        &self,
+        migration: &MigrationFile,
+        use_lwt: bool,
+    ) -> Result<(), MigrationError> {
+        let started = self.start_run(&migration.version, "up");
+        let previous_keyspace = self.session.get_keyspace();
+        let result = self.apply_migration_steps(migration, use_lwt).await;
+        self.restore_session_keyspace(previous_keyspace).await;
+        self.finish_run(&migration.version, started, &result);
+        result
+    }
+
+    async fn apply_migration_steps(
+        &self,
+        migration: &MigrationFile,
+        use_lwt: bool,
     ) -> Result<(), MigrationError> {
         info!("Applying migration: {}", migration.version);
+        self.check_lock_lease(&migration.version, 0)?;
 
         // Check if already applied
         if self.is_migration_applied(&migration.version).await? {
@@ -187,87 +1455,357 @@ impl MigrationManager {
             });
         }
 
-        // Parse migration content
-        let (up_content, _down_content) = parse_migration_content(&migration.content)
-            .map_err(|e| MigrationError::InvalidFormat(e))?;
+        // A generated DOWN section leaves the UP checksum as it is on disk
+        let generated = generate_auto_down(migration).await?;
+        let migration = generated.as_ref().unwrap_or(migration);
+        let (transformed, up_checksum) = crate::wasm::transform_for_apply(&self.config, migration)?;
+        let migration = transformed.as_ref().unwrap_or(migration);
+
+        // Resume after the statements a cancelled run already executed
+        let saved = self.load_checkpoint(&migration.version).await?;
+        let resumed = saved.is_some();
+        let checkpoint = Checkpoint::resume(saved, migration);
+        if checkpoint.next_statement > 0 {
+            info!(
+                "Resuming migration {} at statement {}, where a cancelled run stopped",
+                migration.version,
+                checkpoint.next_statement + 1
+            );
+        }
 
-        // Execute UP statements
-        for statement in split_cql_statements(&up_content) {
-            if !statement.trim().is_empty() {
-                debug!("Executing: {}", statement.trim());
-                self.session.query(statement, &[]).await?;
+        let started = Instant::now();
+        if migration.streamed {
+            self.execute_streamed(migration, Section::Up, Some(&checkpoint)).await?;
+        } else {
+            // Parse migration content
+            let (up_content, _down_content) = parse_migration_content(&migration.content)
+                .map_err(MigrationError::InvalidFormat)?;
+
+            // Execute UP statements
+            self.execute_statements(
+                &migration.version,
+                migration.directives.keyspace.as_deref(),
+                &migration.directives.only_env,
+                &migration.directives.execution,
+                split_cql_statements(&up_content),
+                &statement_lines(&migration.content, Section::Up),
+                Some(&checkpoint),
+            )
+            .await?;
+
+            if self.config.behavior.stamp_table_comments {
+                self.stamp_tables(&migration.version, self.target_keyspace(migration), &up_content)
+                    .await;
             }
         }
 
+        // The lock may have been lost during the last statement
+        self.check_lock_lease(&migration.version, self.executed_in_run(&migration.version))?;
+        if resumed {
+            self.clear_checkpoint(&migration.version).await?;
+        }
+
         // Record the migration as applied
-        self.record_migration_applied(migration).await?;
+        let duration_ms = started.elapsed().as_millis() as i64;
+        if use_lwt {
+            self.record_migration_applied_lwt(migration, &up_checksum, duration_ms).await?;
+        } else {
+            self.record_migration_applied(migration, &up_checksum, duration_ms).await?;
+        }
+        self.record_event(&migration.version, HistoryAction::Applied, Some(&migration.checksum), None)
+            .await;
 
         info!("✅ Applied migration: {}", migration.version);
         Ok(())
     }
 
+    /// Stamp every table created or altered by the UP section with the migration version
+    ///
+    /// Stamping is best-effort: the migration has already been applied, so failures
+    /// are logged rather than returned.
+    async fn stamp_tables(&self, version: &str, keyspace: &str, up_content: &str) {
+        let mut default_keyspace = normalize_identifier(keyspace);
+        let mut tables = Vec::new();
+
+        for statement in split_cql_statements(up_content) {
+            if let Some(keyspace) = cql::used_keyspace(&statement) {
+                default_keyspace = normalize_identifier(&keyspace);
+                continue;
+            }
+            let Ok(tokens) = cql::tokenize(&statement) else {
+                continue;
+            };
+            let creates_or_alters = tokens
+                .first()
+                .is_some_and(|t| t.is_keyword("CREATE") || t.is_keyword("ALTER"))
+                && tokens.get(1).is_some_and(|t| t.is_keyword("TABLE"));
+            if !creates_or_alters {
+                continue;
+            }
+
+            if let Some(name) = cql::target_object(&tokens) {
+                let (keyspace, table) = split_qualified(&name);
+                let target = (
+                    keyspace
+                        .map(normalize_identifier)
+                        .unwrap_or_else(|| default_keyspace.clone()),
+                    normalize_identifier(table),
+                );
+                if !tables.contains(&target) {
+                    tables.push(target);
+                }
+            }
+        }
+
+        for (keyspace, table) in tables {
+            if let Err(e) = SchemaIntrospector::new(&self.session, &keyspace)
+                .stamp_table(&table, version)
+                .await
+            {
+                warn!("Could not stamp {}.{} with {}: {}", keyspace, table, version, e);
+            }
+        }
+    }
+
     /// Rollback a single migration
+    ///
+    /// If the migration file no longer exists, the DOWN section stored in the
+    /// tracking table (see `behavior.store_down_content`) is used instead.
     pub async fn rollback_migration(&mut self, version: &str) -> Result<(), MigrationError> {
+        self.rollback_migration_inner(version, false).await
+    }
+
+    /// Rollback a single migration using the DOWN section stored in the tracking
+    /// table, ignoring the migration file
+    pub async fn rollback_migration_from_db(&mut self, version: &str) -> Result<(), MigrationError> {
+        self.rollback_migration_inner(version, true).await
+    }
+
+    async fn rollback_migration_inner(
+        &mut self,
+        version: &str,
+        from_db: bool,
+    ) -> Result<(), MigrationError> {
+        let started = self.start_run(version, "down");
+        let previous_keyspace = self.session.get_keyspace();
+        let result = self.rollback_migration_steps(version, from_db).await;
+        self.restore_session_keyspace(previous_keyspace).await;
+        self.finish_run(version, started, &result);
+        result
+    }
+
+    async fn rollback_migration_steps(
+        &mut self,
+        version: &str,
+        from_db: bool,
+    ) -> Result<(), MigrationError> {
         info!("Rolling back migration: {}", version);
+        self.check_lock_lease(version, 0)?;
 
         // Check if migration is applied
         if !self.is_migration_applied(version).await? {
             return Err(MigrationError::MigrationNotFound(version.to_string()));
         }
 
-        // Find the migration file
-        let files = self.get_migration_files().await?;
-        let migration_file = files
-            .iter()
-            .find(|f| f.version == version)
-            .ok_or_else(|| MigrationError::MigrationNotFound(version.to_string()))?;
+        // Find the migration file, even if it is ignored
+        let files = read_migration_files(&self.config.migrations.directory, &self.version_scheme).await?;
+        let migration_file = files.iter().find(|f| f.version == version);
 
-        // Parse migration content
-        let (_up_content, down_content) = parse_migration_content(&migration_file.content)
-            .map_err(|e| MigrationError::InvalidFormat(e))?;
+        if let Some(migration_file) = migration_file.filter(|f| f.streamed && !from_db) {
+            if migration_file.directives.no_down {
+                return Err(MigrationError::Irreversible {
+                    version: version.to_string(),
+                });
+            }
+            if self.execute_streamed(migration_file, Section::Down, None).await? == 0 {
+                return Err(MigrationError::RollbackError {
+                    version: version.to_string(),
+                    reason: "No DOWN section found in migration".to_string(),
+                });
+            }
 
-        let down_content = down_content.ok_or_else(|| MigrationError::RollbackError {
-            version: version.to_string(),
-            reason: "No DOWN section found in migration".to_string(),
-        })?;
+            self.check_lock_lease(version, self.executed_in_run(version))?;
+            self.remove_migration_record(version).await?;
+            info!("✅ Rolled back migration: {}", version);
+            return Ok(());
+        }
 
-        // Execute DOWN statements
-        for statement in split_cql_statements(&down_content) {
-            if !statement.trim().is_empty() {
-                debug!("Executing rollback: {}", statement.trim());
-                self.session.query(statement, &[]).await?;
+        let (keyspace, only_env, execution, down_content, lines) = match migration_file {
+            Some(migration_file) if !from_db => {
+                if migration_file.directives.no_down {
+                    return Err(MigrationError::Irreversible {
+                        version: version.to_string(),
+                    });
+                }
+
+                let transformed = crate::wasm::transform(&self.config, migration_file)?;
+                let migration_file = transformed.as_ref().unwrap_or(migration_file);
+
+                // Parse migration content
+                let (_up_content, down_content) = parse_migration_content(&migration_file.content)
+                    .map_err(MigrationError::InvalidFormat)?;
+
+                let down_content = down_content.ok_or_else(|| MigrationError::RollbackError {
+                    version: version.to_string(),
+                    reason: "No DOWN section found in migration".to_string(),
+                })?;
+
+                (
+                    migration_file.directives.keyspace.clone(),
+                    migration_file.directives.only_env.clone(),
+                    migration_file.directives.execution.clone(),
+                    down_content,
+                    statement_lines(&migration_file.content, Section::Down),
+                )
             }
-        }
+            _ => match self.stored_down_content(version).await? {
+                Some((keyspace, down_content)) => {
+                    info!("Using the DOWN section stored for {}", version);
+                    (keyspace, Vec::new(), ExecutionOptions::default(), down_content, Vec::new())
+                }
+                None if from_db => {
+                    return Err(MigrationError::RollbackError {
+                        version: version.to_string(),
+                        reason: "No DOWN section stored in the migrations table".to_string(),
+                    })
+                }
+                None => return Err(MigrationError::MigrationNotFound(version.to_string())),
+            },
+        };
 
-        // Remove the migration record
+        // Execute DOWN statements
+        self.execute_statements(
+            version,
+            keyspace.as_deref(),
+            &only_env,
+            &execution,
+            split_cql_statements(&down_content),
+            &lines,
+            None,
+        )
+        .await?;
+
+        // Remove the migration record, unless the lock was lost meanwhile
+        self.check_lock_lease(version, self.executed_in_run(version))?;
         self.remove_migration_record(version).await?;
 
         info!("✅ Rolled back migration: {}", version);
         Ok(())
     }
 
+    /// Target keyspace and DOWN section recorded when the migration was applied
+    async fn stored_down_content(
+        &self,
+        version: &str,
+    ) -> Result<Option<(Option<String>, String)>, MigrationError> {
+        let query = format!(
+            "SELECT target_keyspace, down_content FROM {} WHERE version = ?",
+            self.tracking_table()
+        );
+
+        let rows = self.session.query(query, (version,)).await?;
+        for row in rows
+            .rows_typed::<(Option<String>, Option<String>)>()
+            .map_err(|e| MigrationError::IntegrityError(e.to_string()))?
+        {
+            let (keyspace, down_content) =
+                row.map_err(|e| MigrationError::IntegrityError(e.to_string()))?;
+            if let Some(down_content) = down_content {
+                return Ok(Some((keyspace, down_content)));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// DOWN section to store with the tracking record, if enabled
+    fn down_content_to_store(&self, migration: &MigrationFile) -> Option<String> {
+        if !self.config.behavior.store_down_content {
+            return None;
+        }
+        Self::storable_down_content(migration)
+    }
+
+    /// The DOWN section of `migration` as stored in the tracking table, if it has
+    /// statements; a migration-wide `only-env` is kept as a section restriction
+    fn storable_down_content(migration: &MigrationFile) -> Option<String> {
+        let (_up, down) = parse_migration_content(&migration.content).ok()?;
+        let down = down.filter(|down| !split_cql_statements(down).is_empty())?;
+
+        if migration.directives.only_env.is_empty() {
+            Some(down)
+        } else {
+            Some(format!(
+                "{}only-env={}\n\n{}",
+                DIRECTIVE_PREFIX,
+                migration.directives.only_env.join(","),
+                down
+            ))
+        }
+    }
+
     /// Check if a migration is already applied
     pub async fn is_migration_applied(&self, version: &str) -> Result<bool, MigrationError> {
         let query = format!(
-            "SELECT version FROM {} WHERE version = ? LIMIT 1",
-            self.config.migrations.table_name
+            "SELECT version FROM {} WHERE version = ? LIMIT 1",
+            self.tracking_table()
+        );
+
+        let rows = self.session.query(query, (version,)).await?;
+        Ok(!rows.rows.unwrap_or_default().is_empty())
+    }
+
+    /// Record a migration as applied, with the UP checksum of its file as written
+    async fn record_migration_applied(
+        &self,
+        migration: &MigrationFile,
+        up_checksum: &str,
+        duration_ms: i64,
+    ) -> Result<(), MigrationError> {
+        self.ensure_writable()?;
+
+        let query = format!(
+            "INSERT INTO {} (version, applied_at, checksum, description, cluster_name, target_keyspace, down_content, duration_ms, tool_version, up_checksum) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            self.tracking_table()
         );
 
-        let rows = self.session.query(query, (version,)).await?;
-        Ok(!rows.rows.unwrap_or_default().is_empty())
+        self.session
+            .query(
+                query,
+                (
+                    &migration.version,
+                    CqlTimestamp(Utc::now().timestamp_millis()),
+                    &migration.checksum,
+                    &migration.description,
+                    &self.cluster_name,
+                    self.target_keyspace(migration),
+                    self.down_content_to_store(migration),
+                    duration_ms,
+                    compat::TOOL_VERSION,
+                    up_checksum,
+                ),
+            )
+            .await?;
+
+        Ok(())
     }
 
-    /// Record a migration as applied
-    async fn record_migration_applied(
+    /// Record a migration as applied using a lightweight transaction
+    async fn record_migration_applied_lwt(
         &self,
         migration: &MigrationFile,
+        up_checksum: &str,
+        duration_ms: i64,
     ) -> Result<(), MigrationError> {
+        self.ensure_writable()?;
+
         let query = format!(
-            "INSERT INTO {} (version, applied_at, checksum, description) VALUES (?, ?, ?, ?)",
-            self.config.migrations.table_name
+            "INSERT INTO {} (version, applied_at, checksum, description, cluster_name, target_keyspace, down_content, duration_ms, tool_version, up_checksum) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?) IF NOT EXISTS",
+            self.tracking_table()
         );
 
-        self.session
+        let result = self
+            .session
             .query(
                 query,
                 (
@@ -275,10 +1813,31 @@ impl MigrationManager {
                     CqlTimestamp(Utc::now().timestamp_millis()),
                     &migration.checksum,
                     &migration.description,
+                    &self.cluster_name,
+                    self.target_keyspace(migration),
+                    self.down_content_to_store(migration),
+                    duration_ms,
+                    compat::TOOL_VERSION,
+                    up_checksum,
                 ),
             )
             .await?;
 
+        let applied = result
+            .rows
+            .as_ref()
+            .and_then(|rows| rows.first())
+            .and_then(|row| row.columns.first())
+            .and_then(|column| column.as_ref())
+            .and_then(|value| value.as_boolean())
+            .unwrap_or(false);
+
+        if !applied {
+            return Err(MigrationError::AlreadyApplied {
+                version: migration.version.clone(),
+            });
+        }
+
         Ok(())
     }
 
@@ -287,12 +1846,102 @@ impl MigrationManager {
         &self,
         version: &str,
     ) -> Result<(), MigrationError> {
+        self.ensure_writable()?;
+
         let query = format!(
             "DELETE FROM {} WHERE version = ?",
-            self.config.migrations.table_name
+            self.tracking_table()
         );
 
         self.session.query(query, (version,)).await?;
+        self.record_event(version, HistoryAction::RolledBack, None, None).await;
+        Ok(())
+    }
+
+    /// Delete the record of an applied migration whose file no longer exists
+    pub(crate) async fn remove_orphaned_record(&self, record: &MigrationRecord) -> Result<(), MigrationError> {
+        self.ensure_writable()?;
+
+        let query = format!("DELETE FROM {} WHERE version = ?", self.tracking_table());
+        self.session.query(query, (&record.version,)).await?;
+        self.record_event(&record.version, HistoryAction::OrphanRemoved, Some(&record.checksum), None)
+            .await;
+        Ok(())
+    }
+
+    /// Move the record of an applied migration to the version of its renamed file
+    ///
+    /// The new row keeps when, where and how long the migration ran, with the
+    /// file's checksum. Insert and delete go in one logged batch so the record is
+    /// neither lost nor duplicated.
+    pub(crate) async fn rename_migration_record(
+        &self,
+        old_version: &str,
+        migration: &MigrationFile,
+    ) -> Result<(), MigrationError> {
+        self.ensure_writable()?;
+
+        let query = format!(
+            "SELECT applied_at, cluster_name, target_keyspace, down_content, duration_ms, tool_version FROM {} WHERE version = ?",
+            self.tracking_table()
+        );
+        let rows = self.session.query(query, (old_version,)).await?;
+        let (applied_at, cluster_name, target_keyspace, down_content, duration_ms, tool_version) = rows
+            .rows_typed::<(
+                CqlTimestamp,
+                Option<String>,
+                Option<String>,
+                Option<String>,
+                Option<i64>,
+                Option<String>,
+            )>()
+            .map_err(|e| MigrationError::IntegrityError(e.to_string()))?
+            .next()
+            .ok_or_else(|| MigrationError::MigrationNotFound(old_version.to_string()))?
+            .map_err(|e| MigrationError::IntegrityError(e.to_string()))?;
+
+        let mut batch = Batch::new(BatchType::Logged);
+        batch.append_statement(
+            format!(
+                "INSERT INTO {} (version, applied_at, checksum, description, cluster_name, target_keyspace, down_content, duration_ms, tool_version, up_checksum) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                self.tracking_table()
+            )
+            .as_str(),
+        );
+        batch.append_statement(
+            format!("DELETE FROM {} WHERE version = ?", self.tracking_table()).as_str(),
+        );
+
+        self.session
+            .batch(
+                &batch,
+                (
+                    (
+                        &migration.version,
+                        applied_at,
+                        &migration.checksum,
+                        &migration.description,
+                        cluster_name,
+                        target_keyspace,
+                        down_content,
+                        duration_ms,
+                        tool_version,
+                        migration.up_checksum(),
+                    ),
+                    (old_version,),
+                ),
+            )
+            .await?;
+
+        self.record_event(old_version, HistoryAction::RenamedTo, None, Some(&migration.version))
+            .await;
+        self.record_event(
+            &migration.version,
+            HistoryAction::RenamedFrom,
+            Some(&migration.checksum),
+            Some(old_version),
+        )
+        .await;
         Ok(())
     }
 
@@ -300,6 +1949,12 @@ impl MigrationManager {
     pub async fn verify_migrations(&self) -> Result<Vec<MigrationError>, MigrationError> {
         let applied = self.get_applied_migrations().await?;
         let files = self.get_migration_files().await?;
+        let ignored: HashSet<String> = self
+            .get_ignored_migration_files()
+            .await?
+            .into_iter()
+            .map(|file| file.version)
+            .collect();
 
         let file_map: HashMap<String, &MigrationFile> =
             files.iter().map(|f| (f.version.clone(), f)).collect();
@@ -307,14 +1962,31 @@ impl MigrationManager {
         let mut errors = Vec::new();
 
         for applied_migration in applied {
+            if ignored.contains(&applied_migration.version) {
+                debug!("Not verifying ignored migration {}", applied_migration.version);
+                continue;
+            }
             if let Some(file) = file_map.get(&applied_migration.version) {
-                if file.checksum != applied_migration.checksum {
-                    errors.push(MigrationError::ChecksumMismatch {
-                        version: applied_migration.version,
-                        expected: applied_migration.checksum,
-                        actual: file.checksum.clone(),
-                    });
+                if file.checksum == applied_migration.checksum {
+                    continue;
+                }
+                // Recorded before line endings were normalized: the file is unchanged
+                if raw_checksum(file).await.as_ref() == Some(&applied_migration.checksum) {
+                    debug!(
+                        "Migration {} was recorded with its CRLF checksum, updating the record",
+                        file.version
+                    );
+                    if !self.config.behavior.read_only {
+                        if let Err(e) = self
+                            .update_migration_checksum(&file.version, &file.checksum, &file.up_checksum())
+                            .await
+                        {
+                            debug!("Could not update the checksum of {}: {}", file.version, e);
+                        }
+                    }
+                    continue;
                 }
+                errors.push(Self::changed_file_error(applied_migration, file));
             } else {
                 errors.push(MigrationError::MigrationNotFound(applied_migration.version));
             }
@@ -323,43 +1995,253 @@ impl MigrationManager {
         Ok(errors)
     }
 
+    /// How the file of an applied migration changed since it was recorded
+    ///
+    /// Only records that know their UP checksum can tell a fixed rollback from a
+    /// changed migration.
+    fn changed_file_error(record: MigrationRecord, file: &MigrationFile) -> MigrationError {
+        if record.up_checksum.as_ref() == Some(&file.up_checksum()) {
+            MigrationError::DownChecksumMismatch {
+                version: record.version,
+                expected: record.checksum,
+                actual: file.checksum.clone(),
+            }
+        } else {
+            MigrationError::ChecksumMismatch {
+                version: record.version,
+                expected: record.checksum,
+                actual: file.checksum.clone(),
+            }
+        }
+    }
+
+    /// Verify integrity plus ordering and parseability of the applied history
+    ///
+    /// Applied versions must form a prefix of the file ordering: a pending file that
+    /// sorts before the latest applied version is reported as out of order.
+    pub async fn verify_migrations_strict(&self) -> Result<Vec<MigrationError>, MigrationError> {
+        let mut errors = self.verify_migrations().await?;
+
+        let applied = self.get_applied_migrations().await?;
+        let files = self.get_migration_files().await?;
+        errors.extend(Self::strict_issues(&applied, &files, &self.version_scheme));
+
+        Ok(errors)
+    }
+
+    /// Issues only `verify --strict` reports: pending files sorting before the
+    /// latest applied version, and applied files that no longer parse
+    fn strict_issues(
+        applied: &[MigrationRecord],
+        files: &[MigrationFile],
+        scheme: &VersionScheme,
+    ) -> Vec<MigrationError> {
+        let mut errors = Vec::new();
+        let applied_versions: HashSet<&str> = applied.iter().map(|m| m.version.as_str()).collect();
+
+        if let Some(latest_applied) = applied.last().map(|m| &m.version) {
+            for file in files {
+                if !applied_versions.contains(file.version.as_str()) && scheme.compare(&file.version, latest_applied).is_lt() {
+                    errors.push(MigrationError::OutOfOrder {
+                        version: file.version.clone(),
+                        latest_applied: latest_applied.clone(),
+                    });
+                }
+            }
+        }
+
+        // Streamed files are parsed as they are executed
+        for file in files.iter().filter(|file| !file.streamed) {
+            if applied_versions.contains(file.version.as_str()) {
+                if let Err(reason) = parse_migration_content(&file.content) {
+                    errors.push(MigrationError::UnparseableMigration {
+                        version: file.version.clone(),
+                        reason,
+                    });
+                }
+            }
+        }
+
+        errors
+    }
+
+    /// Compare the live schema with the one applied migrations should have produced
+    pub async fn detect_schema_drift(&self) -> Result<Vec<SchemaDrift>, MigrationError> {
+        let applied: HashSet<String> = self
+            .get_applied_migrations()
+            .await?
+            .into_iter()
+            .map(|record| record.version)
+            .collect();
+
+        let mut expected = ExpectedSchema::new(&self.config.database.keyspace);
+        if self.config.tracking_keyspace() == self.config.database.keyspace {
+            expected.mark_managed_table(&self.config.migrations.table_name);
+        }
+        // Ignored files that were applied still shaped the schema
+        for file in read_migration_files(&self.config.migrations.directory, &self.version_scheme).await? {
+            if !applied.contains(&file.version) {
+                continue;
+            }
+            // Statements skipped in this environment left no schema behind
+            let mut selector = EnvSelector::new(
+                self.config.behavior.environment.as_deref(),
+                &file.directives.only_env,
+            );
+            if file.streamed {
+                expected.start_migration(file.directives.keyspace.as_deref());
+                let mut statements = StatementStream::open(&file.file_path, Section::Up).await?;
+                while let Some(statement) = statements.next_statement().await? {
+                    if selector.includes(&statement) {
+                        expected.apply(&statement);
+                    }
+                }
+            } else if let Ok((up, _down)) = parse_migration_content(&file.content) {
+                expected.start_migration(file.directives.keyspace.as_deref());
+                for statement in split_cql_statements(&up) {
+                    if selector.includes(&statement) {
+                        expected.apply(&statement);
+                    }
+                }
+            }
+        }
+
+        SchemaIntrospector::new(&self.session, &self.config.database.keyspace)
+            .detect_schema_drift(&expected)
+            .await
+    }
+
     /// Reset all migrations (destructive operation)
     pub async fn reset_migrations(&mut self) -> Result<(), MigrationError> {
-        if !self.config.behavior.allow_destructive {
-            return Err(MigrationError::ConfigError(
-                "Destructive operations are disabled in configuration".to_string(),
-            ));
-        }
+        self.ensure_destructive_allowed()?;
 
         warn!("Resetting all migrations - this is destructive!");
 
+        // The history table outlives the reset, with one event per dropped record
+        let applied = self.get_applied_migrations().await.unwrap_or_default();
+
         // Drop and recreate the migrations table
-        let drop_query = format!("DROP TABLE IF EXISTS {}", self.config.migrations.table_name);
+        let drop_query = format!("DROP TABLE IF EXISTS {}", self.tracking_table());
         self.session.query(drop_query, &[]).await?;
 
         self.initialize_schema().await?;
+        for record in &applied {
+            self.record_event(&record.version, HistoryAction::Reset, Some(&record.checksum), None)
+                .await;
+        }
 
         info!("✅ All migrations reset");
         Ok(())
     }
 
+    /// Roll back every applied migration in reverse order, then reset tracking
+    ///
+    /// Returns the versions that were rolled back. Stops at the first migration
+    /// that cannot be rolled back.
+    pub async fn drop_schema(&mut self) -> Result<Vec<String>, MigrationError> {
+        self.ensure_destructive_allowed()?;
+
+        let mut applied = self.get_applied_migrations().await?;
+        applied.sort_by(|a, b| self.version_scheme.compare(&b.version, &a.version));
+
+        warn!("Rolling back all {} applied migration(s)", applied.len());
+
+        let mut rolled_back = Vec::new();
+        for record in applied {
+            self.rollback_migration(&record.version).await?;
+            rolled_back.push(record.version);
+        }
+
+        self.reset_migrations().await?;
+        Ok(rolled_back)
+    }
+
+    /// Drop the entire keyspace and the tracking table
+    pub async fn drop_keyspace(&mut self) -> Result<(), MigrationError> {
+        self.ensure_destructive_allowed()?;
+
+        warn!("Dropping keyspace {} - this is destructive!", self.config.database.keyspace);
+
+        let query = format!("DROP KEYSPACE IF EXISTS {}", self.config.database.keyspace);
+        self.session.query(query, &[]).await?;
+
+        info!("✅ Dropped keyspace {}", self.config.database.keyspace);
+        Ok(())
+    }
+
+    /// Whether the tracking table lives outside the migrated keyspace
+    /// (`migrations.tracking_keyspace`), so [`Self::drop_keyspace`] leaves it in place
+    pub fn has_external_tracking_table(&self) -> bool {
+        self.config.tracking_keyspace() != self.config.database.keyspace
+    }
+
+    /// Drop the tracking table, e.g. after [`Self::drop_keyspace`] when it lives in
+    /// a separate keyspace; the history table keeps one event per dropped record
+    pub async fn drop_tracking_table(&mut self) -> Result<(), MigrationError> {
+        self.ensure_destructive_allowed()?;
+
+        warn!("Dropping tracking table {} - this is destructive!", self.tracking_table());
+
+        let applied = self.get_applied_migrations().await.unwrap_or_default();
+        let query = format!("DROP TABLE IF EXISTS {}", self.tracking_table());
+        self.session.query(query, &[]).await?;
+        *self.tracking_columns.lock().unwrap() = None;
+        for record in &applied {
+            self.record_event(&record.version, HistoryAction::Reset, Some(&record.checksum), None)
+                .await;
+        }
+
+        info!("✅ Dropped tracking table {}", self.tracking_table());
+        Ok(())
+    }
+
+    fn ensure_destructive_allowed(&self) -> Result<(), MigrationError> {
+        self.ensure_writable()?;
+        if !self.config.behavior.allow_destructive {
+            return Err(MigrationError::ConfigError(
+                "Destructive operations are disabled in configuration".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Refuse writes when `behavior.read_only` is set
+    pub fn ensure_writable(&self) -> Result<(), MigrationError> {
+        if self.config.behavior.read_only {
+            return Err(MigrationError::ConfigError(
+                "Refusing to write: behavior.read_only is enabled".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
     /// Get the configuration
     pub fn get_config(&self) -> &Config {
         &self.config
     }
 
-    /// Update the checksum of an existing migration record
+    /// Get the underlying database session
+    pub fn session(&self) -> &Session {
+        &self.session
+    }
+
+    /// Update the checksums of an existing migration record
     pub async fn update_migration_checksum(
         &self,
         version: &str,
         new_checksum: &str,
+        up_checksum: &str,
     ) -> Result<(), MigrationError> {
+        self.ensure_writable()?;
+
         let query = format!(
-            "UPDATE {} SET checksum = ? WHERE version = ?",
-            self.config.migrations.table_name
+            "UPDATE {} SET checksum = ?, up_checksum = ? WHERE version = ?",
+            self.tracking_table()
         );
 
-        self.session.query(query, (new_checksum, version)).await?;
+        self.session.query(query, (new_checksum, up_checksum, version)).await?;
+        self.record_event(version, HistoryAction::ChecksumUpdated, Some(new_checksum), None)
+            .await;
         Ok(())
     }
 
@@ -367,31 +2249,714 @@ impl MigrationManager {
     pub async fn create_migration_file(
         &self,
         description: &str,
+        template: MigrationTemplate,
+        require_down: bool,
+        auto_down: bool,
+        body: Option<String>,
     ) -> Result<PathBuf, MigrationError> {
-        let filename = crate::utils::create_migration_filename(description);
-        let file_path = self.config.migrations.directory.join(&filename);
+        create_migration_file(&self.config, description, template, require_down, auto_down, body)
+            .await
+    }
+}
+
+/// Migration files of the configured directory, without those ignored by
+/// `migrations.ignore` or marked with the `skip` directive
+pub async fn active_migration_files(config: &Config) -> Result<Vec<MigrationFile>, MigrationError> {
+    let files = read_migration_files(&config.migrations.directory, &config.version_scheme()?).await?;
+    Ok(files
+        .into_iter()
+        .filter(|file| {
+            let ignored = config.migrations.is_ignored(file);
+            if ignored {
+                debug!("Ignoring migration {}", file.version);
+            }
+            !ignored
+        })
+        .collect())
+}
 
-        // Ensure migrations directory exists
-        if let Some(parent) = file_path.parent() {
-            fs::create_dir_all(parent).await?;
+/// Create a new migration file, without a database connection
+///
+/// `body` replaces the configured (`templates.file`) or built-in template body.
+pub async fn create_migration_file(
+    config: &Config,
+    description: &str,
+    template: MigrationTemplate,
+    require_down: bool,
+    auto_down: bool,
+    body: Option<String>,
+) -> Result<PathBuf, MigrationError> {
+    let scheme = config.version_scheme()?;
+    let existing = read_migration_files(&config.migrations.directory, &scheme).await?;
+    let version = match scheme {
+        VersionScheme::Timestamp => {
+            // Never reuse the timestamp of an existing migration (e.g. two developers
+            // creating one within the same second)
+            let taken: HashSet<&str> = existing
+                .iter()
+                .map(|file| version_prefix(&file.version))
+                .collect();
+            let generated = generate_migration_version();
+            let version = next_free_prefix(&generated, &taken);
+            if version != generated {
+                warn!("Version {} is already taken, using {}", generated, version);
+            }
+            version
+        }
+        VersionScheme::Sequential => {
+            VersionScheme::next_sequential(existing.iter().map(|file| file.version.as_str()))
         }
+        VersionScheme::Custom { .. } => {
+            return Err(MigrationError::ConfigError(
+                "versioning.scheme 'custom-regex' cannot generate versions; create the file by hand"
+                    .to_string(),
+            ))
+        }
+    };
+
+    let filename = crate::utils::create_migration_filename(&version, description)
+        .map_err(MigrationError::InvalidFormat)?;
+    let file_path = config.migrations.directory.join(&filename);
+
+    // Ensure migrations directory exists
+    if let Some(parent) = file_path.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+
+    // Generate template content
+    let body = match (body, &config.templates.file) {
+        (Some(body), _) => body,
+        (None, Some(template_file)) => fs::read_to_string(template_file).await.map_err(|e| {
+            MigrationError::ConfigError(format!(
+                "Failed to read template {}: {}",
+                template_file.display(),
+                e
+            ))
+        })?,
+        (None, None) => template.body().to_string(),
+    };
+    let variables = template_variables(config, description, filename.trim_end_matches(".cql"));
+    let content = crate::utils::generate_migration_template(
+        description,
+        &body,
+        require_down,
+        auto_down,
+        &variables,
+    );
+
+    // Write the file
+    fs::write(&file_path, content).await?;
+
+    info!("✅ Created migration file: {}", filename);
+    Ok(file_path)
+}
 
-        // Generate template content
-        let content = crate::utils::generate_migration_template(description);
+/// Fill in the DOWN section of an `auto-down` migration and rewrite its file
+///
+/// Returns the updated migration, or `None` if there was nothing to generate.
+async fn generate_auto_down(
+    migration: &MigrationFile,
+) -> Result<Option<MigrationFile>, MigrationError> {
+    if !migration.directives.auto_down || migration.streamed {
+        return Ok(None);
+    }
 
-        // Write the file
-        fs::write(&file_path, content).await?;
+    let (up_content, down_content) =
+        parse_migration_content(&migration.content).map_err(MigrationError::InvalidFormat)?;
+    if !split_cql_statements(down_content.as_deref().unwrap_or("")).is_empty() {
+        return Ok(None);
+    }
 
-        info!("✅ Created migration file: {}", filename);
-        Ok(file_path)
+    let suggestion = suggest_down(&up_content);
+    if !suggestion.is_complete() {
+        return Err(MigrationError::InvalidFormat(format!(
+            "Cannot generate a DOWN section for {}, no inverse for: {}",
+            migration.version,
+            suggestion.unsupported.join("; ")
+        )));
     }
+
+    let content = append_down(&migration.content, &suggestion.statements);
+    fs::write(&migration.file_path, &content).await?;
+    info!("Generated DOWN section for {}", migration.version);
+
+    Ok(Some(MigrationFile {
+        checksum: calculate_checksum(&content),
+        content,
+        ..migration.clone()
+    }))
 }
 
-/// Split CQL content into individual statements
-fn split_cql_statements(content: &str) -> Vec<String> {
-    content
-        .split(';')
-        .map(|s| s.trim().to_string())
-        .filter(|s| !s.is_empty())
-        .collect()
+/// Name of the connected cluster, as reported by the coordinator
+async fn fetch_cluster_name(session: &Session) -> Result<Option<String>, MigrationError> {
+    let rows = session
+        .query("SELECT cluster_name FROM system.local", &[])
+        .await?;
+
+    let mut cluster_name = None;
+    for row in rows
+        .rows_typed::<(Option<String>,)>()
+        .map_err(|e| MigrationError::IntegrityError(e.to_string()))?
+    {
+        let (name,) = row.map_err(|e| MigrationError::IntegrityError(e.to_string()))?;
+        cluster_name = name;
+    }
+
+    Ok(cluster_name)
+}
+
+/// Convert a `routing-key` literal to a value of its bind marker's column type
+fn bind_literal(literal: &str, typ: &ColumnType) -> Result<CqlValue, String> {
+    let invalid = || format!("{} is not a valid {:?} literal", literal, typ);
+    let text = || cql::unquote_literal(literal).ok_or_else(invalid);
+    Ok(match typ {
+        ColumnType::Text => CqlValue::Text(text()?),
+        ColumnType::Ascii => CqlValue::Ascii(text()?),
+        ColumnType::Int => CqlValue::Int(literal.parse().map_err(|_| invalid())?),
+        ColumnType::BigInt => CqlValue::BigInt(literal.parse().map_err(|_| invalid())?),
+        ColumnType::SmallInt => CqlValue::SmallInt(literal.parse().map_err(|_| invalid())?),
+        ColumnType::TinyInt => CqlValue::TinyInt(literal.parse().map_err(|_| invalid())?),
+        ColumnType::Double => CqlValue::Double(literal.parse().map_err(|_| invalid())?),
+        ColumnType::Float => CqlValue::Float(literal.parse().map_err(|_| invalid())?),
+        ColumnType::Boolean if literal.eq_ignore_ascii_case("true") => CqlValue::Boolean(true),
+        ColumnType::Boolean if literal.eq_ignore_ascii_case("false") => CqlValue::Boolean(false),
+        ColumnType::Boolean => return Err(invalid()),
+        ColumnType::Uuid => CqlValue::Uuid(uuid::Uuid::parse_str(literal).map_err(|_| invalid())?),
+        _ => return Err(format!("{:?} columns are not supported in routing-key", typ)),
+    })
+}
+
+/// Number of migration files read and hashed concurrently
+const READ_CONCURRENCY: usize = 16;
+
+/// A parsed migration file and the metadata it was read with
+struct CachedFile {
+    modified: SystemTime,
+    len: u64,
+    file: MigrationFile,
+}
+
+/// Parsed migration files keyed by path, reused while (mtime, size) and the
+/// checksum of the content are unchanged
+///
+/// Timestamps are too coarse on some filesystems to tell edits of the same size
+/// apart, so files are always read and hashed; the cache saves parsing them.
+fn file_cache() -> &'static Mutex<HashMap<PathBuf, CachedFile>> {
+    static CACHE: OnceLock<Mutex<HashMap<PathBuf, CachedFile>>> = OnceLock::new();
+    CACHE.get_or_init(Default::default)
+}
+
+/// Read all migration files from a directory, without a database connection
+///
+/// Files are read and hashed concurrently, and files unchanged since the last
+/// call are served from an in-memory cache.
+pub async fn read_migration_files(
+    migrations_dir: &Path,
+    scheme: &VersionScheme,
+) -> Result<Vec<MigrationFile>, MigrationError> {
+    if !migrations_dir.exists() {
+        fs::create_dir_all(migrations_dir).await?;
+        return Ok(Vec::new());
+    }
+
+    let started = Instant::now();
+    let semaphore = Arc::new(Semaphore::new(READ_CONCURRENCY));
+    let mut tasks = JoinSet::new();
+    let mut listed = HashSet::new();
+
+    for entry in WalkDir::new(migrations_dir)
+        .min_depth(1)
+        .max_depth(1)
+    {
+        let entry = entry.map_err(|e| MigrationError::ConfigError(e.to_string()))?;
+        let path = entry.path();
+
+        if path.extension().and_then(|s| s.to_str()) != Some("cql") {
+            continue;
+        }
+
+        let filename = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .ok_or_else(|| MigrationError::InvalidFormat("Invalid filename".to_string()))?;
+
+        if let Some(version) = extract_version_from_filename(filename, scheme) {
+            let path = path.to_path_buf();
+            listed.insert(path.clone());
+            let description = extract_description_from_filename(filename, scheme);
+            let semaphore = Arc::clone(&semaphore);
+            tasks.spawn(async move {
+                let _permit = semaphore.acquire_owned().await;
+                read_migration_file(path, version, description).await
+            });
+        } else {
+            warn!("Skipping file with invalid format: {} (listed by `verify`)", filename);
+        }
+    }
+
+    let mut files = Vec::new();
+    let mut cached = 0;
+    while let Some(joined) = tasks.join_next().await {
+        let (file, from_cache) =
+            joined.map_err(|e| MigrationError::ConfigError(e.to_string()))??;
+        cached += usize::from(from_cache);
+        files.push(file);
+    }
+
+    files.sort_by(|a, b| scheme.compare(&a.version, &b.version));
+
+    // Forget files of this directory that were deleted or renamed
+    file_cache()
+        .lock()
+        .unwrap()
+        .retain(|path, _| path.parent() != Some(migrations_dir) || listed.contains(path));
+
+    debug!(
+        "Read {} migration file(s) from {} in {:?} ({} from cache)",
+        files.len(),
+        migrations_dir.display(),
+        started.elapsed(),
+        cached
+    );
+
+    Ok(files)
+}
+
+/// `.cql` files of a directory that [`read_migration_files`] skips because
+/// their name is not a version of the configured scheme
+pub async fn read_invalid_migration_files(
+    migrations_dir: &Path,
+    scheme: &VersionScheme,
+) -> Result<Vec<InvalidMigrationFile>, MigrationError> {
+    if !migrations_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut invalid = Vec::new();
+    for entry in WalkDir::new(migrations_dir).min_depth(1).max_depth(1).sort_by_file_name() {
+        let entry = entry.map_err(|e| MigrationError::ConfigError(e.to_string()))?;
+        let path = entry.path();
+        if path.extension().and_then(|s| s.to_str()) != Some("cql") {
+            continue;
+        }
+
+        let filename = path.file_name().unwrap_or_default().to_string_lossy();
+        let stem = filename.strip_suffix(".cql").unwrap_or(&filename);
+        if let Err(reason) = scheme.parse(stem) {
+            invalid.push(InvalidMigrationFile {
+                filename: filename.to_string(),
+                reason,
+            });
+        }
+    }
+    Ok(invalid)
+}
+
+/// Wait for Ctrl-C, or SIGTERM as sent by container runtimes; `false` if no
+/// signal handler could be installed
+pub async fn shutdown_signal() -> bool {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        if let Ok(mut terminate) = signal(SignalKind::terminate()) {
+            return tokio::select! {
+                result = tokio::signal::ctrl_c() => result.is_ok(),
+                _ = terminate.recv() => true,
+            };
+        }
+    }
+    tokio::signal::ctrl_c().await.is_ok()
+}
+
+/// Checksum of a file's bytes as they are on disk, when normalizing its line
+/// endings changed [`MigrationFile::checksum`]
+///
+/// Records written before normalization carry this checksum.
+pub(crate) async fn raw_checksum(file: &MigrationFile) -> Option<String> {
+    if file.streamed {
+        return None;
+    }
+    let raw = fs::read_to_string(&file.file_path).await.ok()?;
+    raw.contains("\r\n").then(|| calculate_checksum(&raw))
+}
+
+/// Read, hash and parse a single migration file, returning whether its parse was
+/// cached
+async fn read_migration_file(
+    path: PathBuf,
+    version: String,
+    description: String,
+) -> Result<(MigrationFile, bool), MigrationError> {
+    let metadata = fs::metadata(&path).await?;
+    let modified = metadata.modified()?;
+    let len = metadata.len();
+
+    // Very large (seed) files are not loaded; only their directives are kept
+    let streamed = len > STREAMING_THRESHOLD;
+    let (content, checksum) = if streamed {
+        debug!("Streaming {} ({} bytes)", path.display(), len);
+        (None, checksum_file(&path).await?)
+    } else {
+        let content = normalize_line_endings(fs::read_to_string(&path).await?);
+        let checksum = calculate_checksum(&content);
+        (Some(content), checksum)
+    };
+
+    if let Some(cached) = file_cache().lock().unwrap().get(&path) {
+        if cached.modified == modified && cached.len == len && cached.file.checksum == checksum {
+            // The version and description depend on the scheme of the manager reading it
+            let file = MigrationFile { version, description, ..cached.file.clone() };
+            return Ok((file, true));
+        }
+    }
+
+    let content = match content {
+        Some(content) => content,
+        None => read_header(&path).await?,
+    };
+
+    let file = MigrationFile {
+        version,
+        description,
+        file_path: path.clone(),
+        checksum,
+        directives: Directives::parse(&content),
+        content,
+        streamed,
+    };
+
+    file_cache().lock().unwrap().insert(
+        path,
+        CachedFile {
+            modified,
+            len,
+            file: file.clone(),
+        },
+    );
+
+    Ok((file, false))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_raw_checksum() {
+        let dir = tempfile::tempdir().unwrap();
+        let crlf = "-- +migrate Up\r\nCREATE TABLE a (id int PRIMARY KEY);\r\n";
+        std::fs::write(dir.path().join("20250115_01_crlf.cql"), crlf).unwrap();
+        std::fs::write(dir.path().join("20250115_02_lf.cql"), "CREATE TABLE b (id int PRIMARY KEY);\n").unwrap();
+
+        let files = read_migration_files(dir.path(), &VersionScheme::Timestamp).await.unwrap();
+        assert_ne!(files[0].checksum, calculate_checksum(crlf));
+        assert_eq!(raw_checksum(&files[0]).await, Some(calculate_checksum(crlf)));
+        assert_eq!(raw_checksum(&files[1]).await, None);
+    }
+
+    #[test]
+    fn test_own_tables() {
+        let mut config = Config::default();
+        config.database.keyspace = "app".to_string();
+        config.migrations.table_name = "schema_migrations".to_string();
+        assert_eq!(
+            MigrationManager::own_tables(&config),
+            vec!["schema_migrations", "schema_migrations_history", "schema_migrations_checkpoints"]
+        );
+
+        // A tracking table in another keyspace leaves every table in this one to count
+        config.migrations.tracking_keyspace = Some("ops".to_string());
+        assert!(MigrationManager::own_tables(&config).is_empty());
+    }
+
+    #[test]
+    fn test_storable_down_content() {
+        let stored = |content: &str| {
+            MigrationManager::storable_down_content(&MigrationFile::for_test("20250101_001_users", content))
+        };
+
+        assert_eq!(
+            stored("-- +migrate Up\nCREATE TABLE users (id int PRIMARY KEY);\n-- +migrate Down\nDROP TABLE users;\n").as_deref(),
+            Some("DROP TABLE users;")
+        );
+        assert_eq!(stored("-- +migrate Up\nCREATE TABLE users (id int PRIMARY KEY);\n"), None);
+        assert_eq!(stored("-- +migrate Up\nCREATE TABLE users (id int PRIMARY KEY);\n-- +migrate Down\n-- nothing to undo\n"), None);
+
+        // A rollback from the stored section is restricted to the same environments
+        let down = stored("-- db-migrate:only-env=staging\n-- +migrate Up\nCREATE TABLE users (id int PRIMARY KEY);\n-- +migrate Down\nDROP TABLE users;\n").unwrap();
+        assert_eq!(Directives::parse(&down).only_env, vec!["staging"]);
+        assert!(down.ends_with("\n\nDROP TABLE users;"));
+    }
+
+    #[test]
+    fn test_foreign_records() {
+        let record = |version: &str, cluster: Option<&str>| MigrationRecord {
+            cluster_name: cluster.map(str::to_string),
+            ..MigrationRecord::for_test(version, Utc::now())
+        };
+        let applied = [
+            record("20250101_001_users", Some("prod-eu")),
+            record("20250102_001_orders", None),
+            record("20250103_001_items", Some("staging")),
+        ];
+
+        let foreign: Vec<&str> = MigrationManager::foreign_records(Some("prod-eu"), &applied).iter().map(|r| r.version.as_str()).collect();
+        assert_eq!(foreign, vec!["20250103_001_items"]);
+        assert!(MigrationManager::foreign_records(None, &applied).is_empty());
+    }
+
+    #[test]
+    fn test_changed_file_error() {
+        let applied = MigrationFile::for_test("20250101_001_users", "-- +migrate Up\nCREATE TABLE users (id int PRIMARY KEY);\n-- +migrate Down\nDROP TABLE users;");
+        let record = MigrationRecord {
+            checksum: applied.checksum.clone(),
+            up_checksum: Some(applied.up_checksum()),
+            ..MigrationRecord::for_test(&applied.version, Utc::now())
+        };
+
+        let down_fixed = MigrationFile::for_test("20250101_001_users", "-- +migrate Up\nCREATE TABLE users (id int PRIMARY KEY);\n-- +migrate Down\nDROP TABLE IF EXISTS users;");
+        assert!(matches!(MigrationManager::changed_file_error(record.clone(), &down_fixed), MigrationError::DownChecksumMismatch { .. }));
+
+        let up_changed = MigrationFile::for_test("20250101_001_users", "-- +migrate Up\nCREATE TABLE users (id bigint PRIMARY KEY);\n-- +migrate Down\nDROP TABLE users;");
+        assert!(matches!(
+            MigrationManager::changed_file_error(record.clone(), &up_changed),
+            MigrationError::ChecksumMismatch { expected, .. } if expected == applied.checksum
+        ));
+
+        // Records written before UP checksums were kept treat every edit as a change
+        let legacy = MigrationRecord { up_checksum: None, ..record };
+        assert!(matches!(MigrationManager::changed_file_error(legacy, &down_fixed), MigrationError::ChecksumMismatch { .. }));
+    }
+
+    #[test]
+    fn test_strict_issues() {
+        let applied_at = Utc::now();
+        let applied = [
+            MigrationRecord::for_test("20250101_001_users", applied_at),
+            MigrationRecord::for_test("20250103_001_orders", applied_at),
+        ];
+        let files = [
+            MigrationFile::for_test("20250101_001_users", "-- +migrate Up\n-- +migrate Down\nDROP TABLE users;"),
+            MigrationFile::for_test("20250102_001_items", "-- +migrate Up\nCREATE TABLE items (id int PRIMARY KEY);"),
+            MigrationFile::for_test("20250103_001_orders", "-- +migrate Up\nCREATE TABLE orders (id int PRIMARY KEY);"),
+            MigrationFile::for_test("20250104_001_later", "-- +migrate Up\nCREATE TABLE later (id int PRIMARY KEY);"),
+        ];
+
+        let issues = MigrationManager::strict_issues(&applied, &files, &VersionScheme::Timestamp);
+        assert_eq!(issues.len(), 2);
+        assert!(matches!(
+            &issues[0],
+            MigrationError::OutOfOrder { version, latest_applied }
+                if version == "20250102_001_items" && latest_applied == "20250103_001_orders"
+        ));
+        assert!(matches!(&issues[1], MigrationError::UnparseableMigration { version, .. } if version == "20250101_001_users"));
+
+        // Nothing applied yet: nothing is out of order
+        assert!(MigrationManager::strict_issues(&[], &files, &VersionScheme::Timestamp).is_empty());
+    }
+
+    #[test]
+    fn test_credentials() {
+        let mut config = Config::default();
+        config.database.username = String::new();
+        assert_eq!(credentials::<()>(&config.database, None), Credentials::Anonymous);
+
+        config.database.username = "migrator".to_string();
+        config.database.password = "secret".to_string();
+        assert_eq!(
+            credentials::<()>(&config.database, None),
+            Credentials::Password { username: "migrator", password: "secret" }
+        );
+
+        // A custom provider is used even when a username is configured
+        assert_eq!(credentials(&config.database, Some("kerberos")), Credentials::Provider("kerberos"));
+    }
+
+    #[test]
+    fn test_managers_sharing_a_session_read_every_column() {
+        // The first manager on a session initializes the tracking table; the
+        // second, built on `shared_session()`, skips that with an empty cache
+        let initialized: Arc<Mutex<HashSet<String>>> = Arc::default();
+        let first_cache = Some(upgraded_tracking_columns());
+        initialized.lock().unwrap().insert("app.schema_migrations".to_string());
+        assert_eq!(known_tracking_columns(first_cache.clone(), true), first_cache);
+
+        let second = initialized.clone();
+        let columns = known_tracking_columns(None, second.lock().unwrap().contains("app.schema_migrations")).unwrap();
+        let (query, _) = applied_migrations_query("app.schema_migrations", &columns);
+        assert_eq!(
+            query,
+            "SELECT version, applied_at, checksum, description, cluster_name, target_keyspace, duration_ms, tool_version, up_checksum FROM app.schema_migrations"
+        );
+
+        // Tables not initialized through the session are looked up
+        assert_eq!(known_tracking_columns(None, false), None);
+        let looked_up: HashSet<String> = RECORD_COLUMNS.iter().map(|c| c.to_string()).collect();
+        assert_eq!(known_tracking_columns(Some(looked_up.clone()), true), Some(looked_up));
+    }
+
+    #[test]
+    fn test_read_pre_upgrade_tracking_table() {
+        let existing: HashSet<String> = RECORD_COLUMNS.iter().map(|c| c.to_string()).collect();
+        let (query, columns) = applied_migrations_query("app.schema_migrations", &existing);
+        assert_eq!(
+            query,
+            "SELECT version, applied_at, checksum, description FROM app.schema_migrations"
+        );
+
+        let row = Row {
+            columns: vec![
+                Some(CqlValue::Text("001".to_string())),
+                Some(CqlValue::Timestamp(CqlTimestamp(1_736_899_200_000))),
+                Some(CqlValue::Text("abc".to_string())),
+                Some(CqlValue::Text("users".to_string())),
+            ],
+        };
+        let record = record_from_row(&columns, row).unwrap();
+        assert_eq!(record.version, "001");
+        assert_eq!(record.applied_at.timestamp_millis(), 1_736_899_200_000);
+        assert_eq!(record.checksum, "abc");
+        assert_eq!(record.up_checksum, None);
+        assert_eq!(record.duration_ms, None);
+    }
+
+    #[test]
+    fn test_read_partly_upgraded_tracking_table() {
+        let existing: HashSet<String> = ["version", "applied_at", "checksum", "description", "duration_ms"]
+            .iter()
+            .map(|c| c.to_string())
+            .collect();
+        let (query, columns) = applied_migrations_query("schema_migrations", &existing);
+        assert!(query.starts_with("SELECT version, applied_at, checksum, description, duration_ms FROM"));
+
+        let row = Row {
+            columns: vec![
+                Some(CqlValue::Text("001".to_string())),
+                Some(CqlValue::Timestamp(CqlTimestamp(0))),
+                Some(CqlValue::Text("abc".to_string())),
+                None,
+                Some(CqlValue::BigInt(42)),
+            ],
+        };
+        let record = record_from_row(&columns, row).unwrap();
+        assert_eq!(record.description, "");
+        assert_eq!(record.duration_ms, Some(42));
+        assert_eq!(record.tool_version, None);
+    }
+
+    #[tokio::test]
+    async fn test_read_migration_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |name: &str, content: &str| std::fs::write(dir.path().join(name), content).unwrap();
+        write("20250115_10_ten.cql", "CREATE TABLE b (id int PRIMARY KEY);");
+        write("20250115_9_nine.cql", "CREATE TABLE a (id int PRIMARY KEY);");
+        write("notes.txt", "ignored");
+
+        let files = read_migration_files(dir.path(), &VersionScheme::Timestamp).await.unwrap();
+        let versions: Vec<&str> = files.iter().map(|f| f.version.as_str()).collect();
+        assert_eq!(versions, vec!["20250115_9_nine", "20250115_10_ten"]);
+
+        // A changed size invalidates the cached entry
+        write("20250115_9_nine.cql", "CREATE TABLE a (id int PRIMARY KEY, v text);");
+        let files = read_migration_files(dir.path(), &VersionScheme::Timestamp).await.unwrap();
+        assert!(files[0].content.contains("v text"));
+        assert_eq!(files[0].checksum, calculate_checksum(&files[0].content));
+
+        // Misnamed files are skipped, but reported with a reason
+        write("2025-01-16_add_index.cql", "CREATE INDEX ON b (v);");
+        assert_eq!(read_migration_files(dir.path(), &VersionScheme::Timestamp).await.unwrap().len(), 2);
+        let invalid = read_invalid_migration_files(dir.path(), &VersionScheme::Timestamp).await.unwrap();
+        assert_eq!(invalid.len(), 1);
+        assert_eq!(invalid[0].filename, "2025-01-16_add_index.cql");
+        assert!(invalid[0].reason.contains("YYYYMMDD"));
+    }
+
+    #[tokio::test]
+    async fn test_read_migration_files_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("20250115_001_a.cql");
+        std::fs::write(&path, "CREATE TABLE a (id int PRIMARY KEY);").unwrap();
+        let modified = std::fs::metadata(&path).unwrap().modified().unwrap();
+        read_migration_files(dir.path(), &VersionScheme::Timestamp).await.unwrap();
+
+        // An edit of the same size within the timestamp resolution is not served stale
+        std::fs::write(&path, "CREATE TABLE b (id int PRIMARY KEY);").unwrap();
+        std::fs::File::options().write(true).open(&path).unwrap().set_modified(modified).unwrap();
+        let files = read_migration_files(dir.path(), &VersionScheme::Timestamp).await.unwrap();
+        assert!(files[0].content.contains("TABLE b"));
+        assert_eq!(files[0].checksum, calculate_checksum(&files[0].content));
+
+        // Deleted files are evicted
+        std::fs::remove_file(&path).unwrap();
+        assert!(read_migration_files(dir.path(), &VersionScheme::Timestamp).await.unwrap().is_empty());
+        assert!(!file_cache().lock().unwrap().contains_key(&path));
+    }
+
+    #[tokio::test]
+    async fn test_read_migration_files_with_each_scheme() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("20250115_001_users.cql"), "CREATE TABLE users (id int PRIMARY KEY);").unwrap();
+        std::fs::write(dir.path().join("002_orders.cql"), "CREATE TABLE orders (id int PRIMARY KEY);").unwrap();
+
+        let timestamp = read_migration_files(dir.path(), &VersionScheme::Timestamp).await.unwrap();
+        assert_eq!(timestamp.len(), 1);
+        assert_eq!(timestamp[0].description, "users");
+
+        // Cached files are read again with the scheme of the caller
+        let sequential = read_migration_files(dir.path(), &VersionScheme::Sequential).await.unwrap();
+        let versions: Vec<&str> = sequential.iter().map(|f| f.version.as_str()).collect();
+        assert_eq!(versions, vec!["002_orders", "20250115_001_users"]);
+        assert_eq!(sequential[1].description, "001 users");
+    }
+
+    #[tokio::test]
+    async fn test_files_ordered_by_version() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in [
+            "20250201_001_später.cql",
+            "20250115_143022_zebra.cql",
+            "20250115_143022_ärger.cql",
+            "20250115_143022_Add_users.cql",
+            "20241231_235959_año_nuevo.cql",
+        ] {
+            std::fs::write(dir.path().join(name), "CREATE TABLE a (id int PRIMARY KEY);").unwrap();
+        }
+
+        let files = read_migration_files(dir.path(), &VersionScheme::Timestamp).await.unwrap();
+        let versions: Vec<&str> = files.iter().map(|f| f.version.as_str()).collect();
+        assert_eq!(
+            versions,
+            vec![
+                "20241231_235959_año_nuevo",
+                "20250115_143022_Add_users",
+                "20250115_143022_zebra",
+                "20250115_143022_ärger",
+                "20250201_001_später",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_bind_literal() {
+        assert_eq!(bind_literal("42", &ColumnType::BigInt), Ok(CqlValue::BigInt(42)));
+        assert_eq!(bind_literal("'it''s'", &ColumnType::Text), Ok(CqlValue::Text("it's".to_string())));
+        assert_eq!(bind_literal("TRUE", &ColumnType::Boolean), Ok(CqlValue::Boolean(true)));
+        assert!(bind_literal("300", &ColumnType::TinyInt).is_err());
+        assert!(bind_literal("abc", &ColumnType::Text).is_err());
+        assert!(bind_literal("'x'", &ColumnType::Blob).is_err());
+    }
+
+    #[test]
+    fn test_failed_statement_location() {
+        let failed = FailedStatement {
+            index: 2,
+            lines: Some((9, 10)),
+            statement: "ALTER TABLE users\n  ADD email text".to_string(),
+        };
+        assert_eq!(failed.location(), "statement 3 (lines 9–10)");
+        assert_eq!(failed.snippet(), " 9 | ALTER TABLE users\n10 |   ADD email text");
+
+        let streamed = FailedStatement { lines: None, ..failed };
+        assert_eq!(streamed.location(), "statement 3");
+    }
 }
@@ -0,0 +1,153 @@
+//! Long-running reconciliation for `db-migrate operator`
+//!
+//! The operator applies pending migrations from the (mounted) migrations
+//! directory every interval, like a GitOps controller converging manifests, and
+//! reports its state over HTTP:
+//!
+//! - `/healthz`: 200 while the operator runs
+//! - `/readyz`: 200 once the last reconciliation succeeded and left nothing
+//!   pending, 503 otherwise
+//! - `/status`: the state below as JSON
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, warn};
+
+/// Outcome of one reconciliation
+#[derive(Debug, Clone, Serialize)]
+pub struct Reconciliation {
+    pub finished_at: DateTime<Utc>,
+    pub success: bool,
+    pub applied: Vec<String>,
+    /// Migrations still pending afterwards
+    pub pending_count: usize,
+    pub error: Option<String>,
+    pub error_code: Option<&'static str>,
+}
+
+/// What the operator reports on its endpoints
+#[derive(Debug, Clone, Serialize)]
+pub struct OperatorState {
+    pub started_at: DateTime<Utc>,
+    pub reconciliations: u64,
+    pub consecutive_failures: u64,
+    /// Every migration applied since the operator started
+    pub applied_total: usize,
+    pub last_reconciliation: Option<Reconciliation>,
+}
+
+impl OperatorState {
+    pub fn new(started_at: DateTime<Utc>) -> Self {
+        Self {
+            started_at,
+            reconciliations: 0,
+            consecutive_failures: 0,
+            applied_total: 0,
+            last_reconciliation: None,
+        }
+    }
+
+    pub fn record(&mut self, reconciliation: Reconciliation) {
+        self.reconciliations += 1;
+        self.applied_total += reconciliation.applied.len();
+        if reconciliation.success {
+            self.consecutive_failures = 0;
+        } else {
+            self.consecutive_failures += 1;
+        }
+        self.last_reconciliation = Some(reconciliation);
+    }
+
+    /// Whether the schema has converged
+    pub fn is_ready(&self) -> bool {
+        self.last_reconciliation
+            .as_ref()
+            .is_some_and(|last| last.success && last.pending_count == 0)
+    }
+
+    /// Status line and JSON body answering a request for `path`
+    pub fn respond(&self, path: &str) -> (&'static str, String) {
+        let status = serde_json::to_string(self).unwrap_or_default();
+        match path.split('?').next().unwrap_or_default() {
+            "/healthz" => ("200 OK", r#"{"status":"ok"}"#.to_string()),
+            "/readyz" if self.is_ready() => ("200 OK", status),
+            "/readyz" => ("503 Service Unavailable", status),
+            "/status" => ("200 OK", status),
+            _ => ("404 Not Found", r#"{"error":"not found"}"#.to_string()),
+        }
+    }
+}
+
+/// Answer health requests until `cancellation` triggers
+pub async fn serve(listener: TcpListener, state: Arc<Mutex<OperatorState>>, cancellation: CancellationToken) {
+    loop {
+        let mut stream = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    warn!("Health endpoint could not accept a connection: {}", e);
+                    continue;
+                }
+            },
+            _ = cancellation.cancelled() => return,
+        };
+
+        let state = Arc::clone(&state);
+        tokio::spawn(async move {
+            // Only the request line matters; probes send small requests
+            let mut request = [0u8; 2048];
+            let read = stream.read(&mut request).await.unwrap_or(0);
+            let request = String::from_utf8_lossy(&request[..read]);
+            let path = request.split_whitespace().nth(1).unwrap_or("/");
+
+            let (status, body) = state.lock().unwrap().respond(path);
+            debug!("Health endpoint {} -> {}", path, status);
+            let response = format!(
+                "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            let _ = stream.write_all(response.as_bytes()).await;
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reconciliation(success: bool, pending_count: usize) -> Reconciliation {
+        Reconciliation {
+            finished_at: Utc::now(),
+            success,
+            applied: vec!["20250101_001_users".to_string()],
+            pending_count,
+            error: None,
+            error_code: None,
+        }
+    }
+
+    #[test]
+    fn test_operator_endpoints() {
+        let mut state = OperatorState::new(Utc::now());
+        assert_eq!(state.respond("/healthz").0, "200 OK");
+        assert_eq!(state.respond("/readyz").0, "503 Service Unavailable");
+        assert_eq!(state.respond("/metrics").0, "404 Not Found");
+
+        state.record(reconciliation(true, 0));
+        assert_eq!(state.respond("/readyz?verbose=1").0, "200 OK");
+
+        state.record(reconciliation(false, 2));
+        state.record(reconciliation(false, 2));
+        assert_eq!(state.consecutive_failures, 2);
+        assert_eq!(state.applied_total, 3);
+        let (status, body) = state.respond("/readyz");
+        assert_eq!(status, "503 Service Unavailable");
+        assert!(body.contains("\"pending_count\":2"));
+    }
+}
@@ -0,0 +1,181 @@
+//! Placeholders substituted into statements at execution time
+//!
+//! `${secret:NAME}` is replaced with the value of the `DB_MIGRATE_SECRET_NAME`
+//! environment variable or, failing that, the contents of `<secrets.directory>/NAME`.
+//! Only variables with that prefix are read, so a migration cannot copy any other
+//! part of the environment into the database. `${var:NAME}` is
+//! replaced with the value of `NAME` for the tenant being migrated (see
+//! `tenants.manifest`). Checksums are always computed over the file as written,
+//! so secret values never reach the tracking table and every tenant records the
+//! same checksum.
+
+use crate::{config::SecretsConfig, MigrationError};
+use std::collections::BTreeMap;
+
+pub const SECRET_PREFIX: &str = "${secret:";
+/// Prefix of the environment variables `${secret:NAME}` reads
+pub const SECRET_ENV_PREFIX: &str = "DB_MIGRATE_SECRET_";
+pub const VARIABLE_PREFIX: &str = "${var:";
+
+/// Replace every secret placeholder in a statement
+///
+/// Values are escaped for use inside single-quoted CQL string literals.
+pub fn resolve_secrets(statement: &str, config: &SecretsConfig) -> Result<String, MigrationError> {
+    substitute(statement, SECRET_PREFIX, "secret", |name| {
+        lookup_secret(name, config).map(|value| value.replace('\'', "''"))
+    })
+}
+
+/// Replace every variable placeholder in a statement
+///
+/// Values are inserted as written, so a variable can stand for a number, part of
+/// a name or, quoted in the file, a string.
+pub fn resolve_variables(
+    statement: &str,
+    variables: &BTreeMap<String, String>,
+) -> Result<String, MigrationError> {
+    substitute(statement, VARIABLE_PREFIX, "variable", |name| {
+        variables.get(name).cloned().ok_or_else(|| {
+            MigrationError::ConfigError(format!(
+                "Variable '{}' is not defined for this keyspace (see tenants.manifest)",
+                name
+            ))
+        })
+    })
+}
+
+/// Names of the variable placeholders in `content`, without duplicates
+pub fn variable_names(content: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut rest = content;
+    while let Some(start) = rest.find(VARIABLE_PREFIX) {
+        rest = &rest[start + VARIABLE_PREFIX.len()..];
+        let Some(end) = rest.find('}') else {
+            break;
+        };
+        let name = rest[..end].trim().to_string();
+        if !names.contains(&name) {
+            names.push(name);
+        }
+        rest = &rest[end + 1..];
+    }
+    names
+}
+
+fn substitute(
+    statement: &str,
+    prefix: &str,
+    kind: &str,
+    mut lookup: impl FnMut(&str) -> Result<String, MigrationError>,
+) -> Result<String, MigrationError> {
+    let mut output = String::with_capacity(statement.len());
+    let mut rest = statement;
+
+    while let Some(start) = rest.find(prefix) {
+        output.push_str(&rest[..start]);
+        let after_prefix = &rest[start + prefix.len()..];
+
+        let end = after_prefix.find('}').ok_or_else(|| {
+            MigrationError::InvalidFormat(format!(
+                "Unterminated {} placeholder in: {}",
+                kind,
+                statement.trim()
+            ))
+        })?;
+
+        output.push_str(&lookup(after_prefix[..end].trim())?);
+        rest = &after_prefix[end + 1..];
+    }
+
+    output.push_str(rest);
+    Ok(output)
+}
+
+fn lookup_secret(name: &str, config: &SecretsConfig) -> Result<String, MigrationError> {
+    // Names also select a file, which must not be outside the secrets directory
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return Err(MigrationError::InvalidFormat(format!(
+            "Secret name '{}' may only contain letters, digits and underscores",
+            name
+        )));
+    }
+
+    let variable = format!("{}{}", SECRET_ENV_PREFIX, name);
+    if let Ok(value) = std::env::var(&variable) {
+        return Ok(value);
+    }
+
+    if let Some(directory) = &config.directory {
+        let path = directory.join(name);
+        if path.is_file() {
+            let value = std::fs::read_to_string(&path)?;
+            return Ok(value.trim_end_matches(['\n', '\r']).to_string());
+        }
+    }
+
+    Err(MigrationError::ConfigError(format!(
+        "Secret '{}' not found in the environment ({}){}",
+        name,
+        variable,
+        config
+            .directory
+            .as_ref()
+            .map(|dir| format!(" or in {}", dir.display()))
+            .unwrap_or_default()
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_secrets() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("app_password"), "it's secret\n").unwrap();
+        let config = SecretsConfig {
+            directory: Some(dir.path().to_path_buf()),
+        };
+
+        let resolved = resolve_secrets(
+            "CREATE ROLE app WITH PASSWORD = '${secret:app_password}' AND LOGIN = true",
+            &config,
+        )
+        .unwrap();
+        assert_eq!(
+            resolved,
+            "CREATE ROLE app WITH PASSWORD = 'it''s secret' AND LOGIN = true"
+        );
+
+        assert!(resolve_secrets("'${secret:missing_secret_name}'", &config).is_err());
+        assert!(resolve_secrets("'${secret:../app_password}'", &config).is_err());
+
+        // Only prefixed variables are read
+        std::env::set_var("DB_MIGRATE_SECRET_TEST_ENV_PASSWORD", "from env");
+        std::env::set_var("TEST_ENV_PASSWORD", "unrelated");
+        assert_eq!(
+            resolve_secrets("'${secret:TEST_ENV_PASSWORD}'", &SecretsConfig::default()).unwrap(),
+            "'from env'"
+        );
+        std::env::remove_var("DB_MIGRATE_SECRET_TEST_ENV_PASSWORD");
+        assert!(resolve_secrets("'${secret:TEST_ENV_PASSWORD}'", &SecretsConfig::default()).is_err());
+        assert!(resolve_secrets("'${secret:app_password'", &config).is_err());
+        assert_eq!(resolve_secrets("SELECT 1", &config).unwrap(), "SELECT 1");
+    }
+
+    #[test]
+    fn test_resolve_variables() {
+        let statement = "CREATE TABLE events_${var:region} (id int PRIMARY KEY) WITH comment = '${var:shards} shards'";
+        assert_eq!(variable_names(statement), vec!["region", "shards"]);
+
+        let variables = BTreeMap::from([
+            ("region".to_string(), "eu".to_string()),
+            ("shards".to_string(), "16".to_string()),
+        ]);
+        assert_eq!(
+            resolve_variables(statement, &variables).unwrap(),
+            "CREATE TABLE events_eu (id int PRIMARY KEY) WITH comment = '16 shards'"
+        );
+        assert!(resolve_variables(statement, &BTreeMap::new()).is_err());
+    }
+}
@@ -0,0 +1,227 @@
+//! External check plugins
+//!
+//! Platform teams enforce their own policies with executables listed under
+//! `[[plugins]]`. Each plugin runs once per migration, during `lint` and before
+//! `up`, with a JSON description of the migration on stdin, and answers on stdout:
+//!
+//! ```json
+//! {"decision": "deny", "messages": ["Tables must have a TTL"]}
+//! ```
+//!
+//! `decision` is `approve` or `deny`. Messages of an approving plugin are
+//! reported as warnings. A plugin that exits non-zero, times out or prints
+//! anything else denies the migration. WASM modules with the `validate`
+//! capability (see [`crate::wasm`]) answer the same way. Running executables
+//! requires building with the `plugins` feature.
+
+use crate::{
+    config::{Config, PluginConfig, WasmCapability},
+    directives::Directives,
+    lint::{Finding, Severity},
+    utils::{parse_migration_content, split_cql_statements},
+    MigrationFile,
+};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// What a plugin receives on stdin
+#[derive(Debug, Serialize)]
+pub struct PluginRequest<'a> {
+    pub version: &'a str,
+    pub description: &'a str,
+    pub file: &'a Path,
+    pub checksum: &'a str,
+    pub environment: Option<&'a str>,
+    pub directives: &'a Directives,
+    /// Full file content; absent for streamed (very large) files
+    pub content: Option<&'a str>,
+    pub up_statements: Vec<String>,
+    pub down_statements: Vec<String>,
+}
+
+impl<'a> PluginRequest<'a> {
+    pub fn new(migration: &'a MigrationFile, config: &'a Config) -> Self {
+        let (up, down) = if migration.streamed {
+            (Vec::new(), Vec::new())
+        } else {
+            match parse_migration_content(&migration.content) {
+                Ok((up, down)) => (
+                    split_cql_statements(&up),
+                    down.map(|down| split_cql_statements(&down)).unwrap_or_default(),
+                ),
+                Err(_) => (Vec::new(), Vec::new()),
+            }
+        };
+
+        Self {
+            version: &migration.version,
+            description: &migration.description,
+            file: &migration.file_path,
+            checksum: &migration.checksum,
+            environment: config.behavior.environment.as_deref(),
+            directives: &migration.directives,
+            content: (!migration.streamed).then_some(migration.content.as_str()),
+            up_statements: up,
+            down_statements: down,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Decision {
+    Approve,
+    Deny,
+}
+
+/// What a plugin answers on stdout
+#[derive(Debug, Deserialize)]
+pub struct Verdict {
+    pub decision: Decision,
+    #[serde(default)]
+    pub messages: Vec<String>,
+}
+
+/// Ask one plugin about one migration
+pub async fn run_plugin(plugin: &PluginConfig, request: &PluginRequest<'_>) -> Verdict {
+    let deny = |message: String| Verdict {
+        decision: Decision::Deny,
+        messages: vec![message],
+    };
+
+    let input = match serde_json::to_vec(request) {
+        Ok(input) => input,
+        Err(e) => return deny(format!("could not encode the request: {}", e)),
+    };
+    match process::run(plugin, &input).await {
+        Ok(output) => serde_json::from_slice(&output)
+            .unwrap_or_else(|e| deny(format!("printed an invalid verdict: {}", e))),
+        Err(e) => deny(e),
+    }
+}
+
+#[cfg(feature = "plugins")]
+mod process {
+    use crate::config::PluginConfig;
+    use std::process::Stdio;
+    use std::time::Duration;
+    use tokio::io::AsyncWriteExt;
+    use tokio::process::Command;
+
+    /// Run a plugin with `input` on stdin, returning its stdout
+    pub async fn run(plugin: &PluginConfig, input: &[u8]) -> Result<Vec<u8>, String> {
+        let mut child = Command::new(&plugin.command)
+            .args(&plugin.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| format!("could not run {}: {}", plugin.command.display(), e))?;
+
+        // A plugin may decide without reading its input, so a closed pipe is fine
+        if let Some(mut stdin) = child.stdin.take() {
+            let _ = stdin.write_all(input).await;
+        }
+
+        let timeout = Duration::from_secs(plugin.timeout_seconds);
+        let output = match tokio::time::timeout(timeout, child.wait_with_output()).await {
+            Ok(Ok(output)) => output,
+            Ok(Err(e)) => return Err(format!("failed: {}", e)),
+            Err(_) => return Err(format!("gave no verdict within {}s", plugin.timeout_seconds)),
+        };
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(format!("exited with {}: {}", output.status, stderr.trim()));
+        }
+        Ok(output.stdout)
+    }
+}
+
+#[cfg(not(feature = "plugins"))]
+mod process {
+    use crate::config::PluginConfig;
+
+    pub async fn run(_plugin: &PluginConfig, _input: &[u8]) -> Result<Vec<u8>, String> {
+        Err("db-migrate was built without the plugins feature".to_string())
+    }
+}
+
+/// Run every configured plugin against each migration, as lint findings
+pub async fn check_with_plugins(config: &Config, migrations: &[MigrationFile]) -> Vec<Finding> {
+    let mut findings = Vec::new();
+
+    for migration in migrations {
+        let request = PluginRequest::new(migration, config);
+        let mut verdicts = Vec::new();
+        for plugin in &config.plugins {
+            verdicts.push((plugin.name.as_str(), run_plugin(plugin, &request).await));
+        }
+        for plugin in &config.wasm_plugins {
+            if plugin.allows(WasmCapability::Validate) {
+                verdicts.push((plugin.name.as_str(), crate::wasm::validate(plugin, &request)));
+            }
+        }
+
+        for (name, verdict) in verdicts {
+            let severity = match verdict.decision {
+                Decision::Approve => Severity::Warning,
+                Decision::Deny => Severity::Error,
+            };
+
+            let mut messages = verdict.messages;
+            if messages.is_empty() && verdict.decision == Decision::Deny {
+                messages.push("denied the migration".to_string());
+            }
+            findings.extend(messages.into_iter().map(|message| {
+                Finding::new(
+                    "plugin",
+                    severity,
+                    migration,
+                    None,
+                    format!("{}: {}", name, message),
+                )
+            }));
+        }
+    }
+
+    findings
+}
+
+#[cfg(all(test, unix, feature = "plugins"))]
+mod tests {
+    use super::*;
+
+    fn plugin(script: &str) -> PluginConfig {
+        PluginConfig {
+            name: "policy".to_string(),
+            command: "sh".into(),
+            args: vec!["-c".to_string(), script.to_string()],
+            timeout_seconds: 5,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_plugin_verdicts() {
+        let mut config = Config::default();
+        let migration =
+            MigrationFile::for_test("20250101_001_users", "-- +migrate Up\nCREATE TABLE users (id int PRIMARY KEY);");
+
+        config.plugins = vec![plugin(
+            r#"grep -q 'CREATE TABLE users' && echo '{"decision": "deny", "messages": ["no users table"]}'"#,
+        )];
+        let findings = check_with_plugins(&config, std::slice::from_ref(&migration)).await;
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].severity, Severity::Error);
+        assert_eq!(findings[0].message, "policy: no users table");
+
+        config.plugins = vec![plugin(r#"cat > /dev/null; echo '{"decision": "approve"}'"#)];
+        assert!(check_with_plugins(&config, std::slice::from_ref(&migration)).await.is_empty());
+
+        config.plugins = vec![plugin("echo oops >&2; exit 3")];
+        let findings = check_with_plugins(&config, std::slice::from_ref(&migration)).await;
+        assert_eq!(findings[0].severity, Severity::Error);
+        assert!(findings[0].message.contains("oops"));
+    }
+}
@@ -0,0 +1,184 @@
+//! Forbidden CQL patterns
+//!
+//! Teams list the constructs they never want in a migration in `policy.toml`
+//! (or the file set by `lint.policy_file`), each with the reason it is banned:
+//!
+//! ```toml
+//! [[rule]]
+//! name = "no-allow-filtering"
+//! pattern = "ALLOW\\s+FILTERING"
+//! justification = "Filtering scans whole partitions and times out on large tables"
+//! # severity = "warning"
+//! ```
+//!
+//! Patterns are case-insensitive regular expressions matched against both
+//! sections of a migration, comments excluded. `lint` reports each match with
+//! its line and justification; `up` refuses to apply migrations that violate
+//! an error-level rule.
+
+use crate::{
+    lint::{Finding, Severity},
+    MigrationFile,
+};
+use anyhow::{Context, Result};
+use regex::{Regex, RegexBuilder};
+use serde::Deserialize;
+use std::path::Path;
+
+/// Policy file looked up when `lint.policy_file` is not set
+pub const DEFAULT_POLICY_FILE: &str = "policy.toml";
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PolicyFile {
+    #[serde(default, rename = "rule")]
+    rules: Vec<RuleSpec>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleSpec {
+    name: String,
+    pattern: String,
+    justification: String,
+    #[serde(default)]
+    severity: Option<Severity>,
+}
+
+/// One forbidden pattern
+#[derive(Debug, Clone)]
+pub struct PolicyRule {
+    pub name: String,
+    pub pattern: Regex,
+    pub justification: String,
+    pub severity: Severity,
+}
+
+/// Rules loaded from the policy file; empty when there is none
+#[derive(Debug, Clone, Default)]
+pub struct Policy {
+    pub rules: Vec<PolicyRule>,
+}
+
+impl Policy {
+    /// Load the configured policy file, or `policy.toml` if it exists
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let path = match path {
+            Some(path) => path,
+            None if Path::new(DEFAULT_POLICY_FILE).exists() => Path::new(DEFAULT_POLICY_FILE),
+            None => return Ok(Self::default()),
+        };
+
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Cannot read policy file {}", path.display()))?;
+        Self::parse(&content).with_context(|| format!("Invalid policy file {}", path.display()))
+    }
+
+    pub fn parse(content: &str) -> Result<Self> {
+        let file: PolicyFile = toml::from_str(content)?;
+        let mut rules = Vec::with_capacity(file.rules.len());
+
+        for spec in file.rules {
+            if spec.name.is_empty() || spec.justification.is_empty() {
+                anyhow::bail!("Every policy rule must have a name and a justification");
+            }
+            if rules.iter().any(|rule: &PolicyRule| rule.name == spec.name) {
+                anyhow::bail!("Duplicate policy rule '{}'", spec.name);
+            }
+            let pattern = RegexBuilder::new(&spec.pattern)
+                .case_insensitive(true)
+                .build()
+                .with_context(|| format!("Invalid pattern in policy rule '{}'", spec.name))?;
+
+            rules.push(PolicyRule {
+                name: spec.name,
+                pattern,
+                justification: spec.justification,
+                severity: spec.severity.unwrap_or(Severity::Error),
+            });
+        }
+
+        Ok(Self { rules })
+    }
+
+    /// Every violation in a migration, with the line it starts on
+    pub fn check(&self, file: &MigrationFile) -> Vec<Finding> {
+        if self.rules.is_empty() || file.streamed {
+            return Vec::new();
+        }
+
+        // Blank out comments, keeping line breaks so offsets map to lines
+        let code: String = file
+            .content
+            .lines()
+            .map(|line| match line.find("--") {
+                Some(index) => &line[..index],
+                None => line,
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let mut findings = Vec::new();
+        for rule in &self.rules {
+            for found in rule.pattern.find_iter(&code) {
+                let line = code[..found.start()].matches('\n').count() + 1;
+                findings.push(
+                    Finding::new(
+                        "policy",
+                        rule.severity,
+                        file,
+                        Some(line),
+                        format!(
+                            "Violates policy rule '{}' ({}): {}",
+                            rule.name,
+                            found.as_str().split_whitespace().collect::<Vec<_>>().join(" "),
+                            rule.justification
+                        ),
+                    )
+                    .with_policy_rule(&rule.name),
+                );
+            }
+        }
+
+        findings.sort_by_key(|finding| finding.line);
+        findings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policy_violations() {
+        let policy = Policy::parse(
+            r#"
+            [[rule]]
+            name = "no-allow-filtering"
+            pattern = "ALLOW\\s+FILTERING"
+            justification = "Filtering scans whole partitions"
+
+            [[rule]]
+            name = "no-simple-strategy"
+            pattern = "'class'\\s*:\\s*'SimpleStrategy'"
+            justification = "SimpleStrategy ignores datacenters"
+            severity = "warning"
+            "#,
+        )
+        .unwrap();
+
+        let content = "-- +migrate Up\n-- ALLOW FILTERING is fine in comments\nCREATE KEYSPACE app WITH replication = {'class': 'SimpleStrategy', 'replication_factor': 1};\nCREATE MATERIALIZED VIEW v AS SELECT * FROM t\n  WHERE b IS NOT NULL allow\n  filtering;\n";
+        let file = MigrationFile::for_test("20250101_001_app", content);
+
+        let findings = policy.check(&file);
+        assert_eq!(findings.len(), 2);
+        assert_eq!(findings[0].line, Some(3));
+        assert_eq!(findings[0].severity, Severity::Warning);
+        assert_eq!(findings[0].policy_rule.as_deref(), Some("no-simple-strategy"));
+        assert_eq!(findings[1].line, Some(5));
+        assert_eq!(findings[1].severity, Severity::Error);
+        assert!(findings[1].message.contains("Filtering scans whole partitions"));
+
+        assert!(Policy::parse("[[rule]]\nname = \"x\"\npattern = \"(\"\njustification = \"y\"").is_err());
+    }
+}
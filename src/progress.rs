@@ -0,0 +1,94 @@
+//! Progress lines printed while a command runs
+//!
+//! Commands report progress (applied migrations, warnings, bundle locations)
+//! through [`progress!`](crate::progress!) instead of `println!`, so structured
+//! output on stdout is not interleaved with human-facing lines: with
+//! `--output json` or `--porcelain` progress goes to stderr, and `--quiet`
+//! silences it. Lines are rendered through [`crate::messages`] like command
+//! output.
+
+use std::fmt;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// Where progress lines go
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ProgressMode {
+    Stdout,
+    Stderr,
+    Silent,
+}
+
+/// `--output` formats that own stdout, so progress lines and logs go to stderr
+pub const STRUCTURED_OUTPUTS: &[&str] = &["json", "gh-annotations", "gitlab-codequality", "sarif"];
+
+impl ProgressMode {
+    /// Where progress goes for the global `--quiet`, `--output` and `--porcelain`
+    pub fn for_output(quiet: bool, output: &str, porcelain: bool) -> Self {
+        if quiet {
+            Self::Silent
+        } else if porcelain || STRUCTURED_OUTPUTS.contains(&output) {
+            Self::Stderr
+        } else {
+            Self::Stdout
+        }
+    }
+}
+
+static MODE: AtomicU8 = AtomicU8::new(ProgressMode::Stdout as u8);
+
+/// Select where progress lines go for the rest of the process
+pub fn set_mode(mode: ProgressMode) {
+    MODE.store(mode as u8, Ordering::Relaxed);
+}
+
+pub fn mode() -> ProgressMode {
+    match MODE.load(Ordering::Relaxed) {
+        0 => ProgressMode::Stdout,
+        1 => ProgressMode::Stderr,
+        _ => ProgressMode::Silent,
+    }
+}
+
+/// Print one progress line according to the current [`ProgressMode`]
+pub fn line(message: fmt::Arguments<'_>) {
+    let mode = mode();
+    if mode == ProgressMode::Silent {
+        return;
+    }
+    let message = message.to_string();
+    let message = crate::messages::render(&message);
+    if mode == ProgressMode::Stdout {
+        println!("{}", message);
+    } else {
+        eprintln!("{}", message);
+    }
+}
+
+/// `println!` for progress lines, see the [module docs](crate::progress)
+#[macro_export]
+macro_rules! progress {
+    ($($arg:tt)*) => {
+        $crate::progress::line(format_args!($($arg)*))
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mode_for_output() {
+        assert_eq!(ProgressMode::for_output(false, "text", false), ProgressMode::Stdout);
+        assert_eq!(ProgressMode::for_output(false, "text", true), ProgressMode::Stderr);
+        for output in STRUCTURED_OUTPUTS {
+            assert_eq!(ProgressMode::for_output(false, output, false), ProgressMode::Stderr);
+        }
+        assert_eq!(ProgressMode::for_output(true, "json", false), ProgressMode::Silent);
+
+        for selected in [ProgressMode::Stderr, ProgressMode::Silent, ProgressMode::Stdout] {
+            set_mode(selected);
+            assert_eq!(mode(), selected);
+        }
+    }
+}
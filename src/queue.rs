@@ -0,0 +1,100 @@
+//! Serialized migration runs within one process
+//!
+//! Long-running callers (a service embedding the library, a watch loop) may be
+//! asked to migrate again while a run is still in progress. Runs through the
+//! same connection (see [`SharedSession`](crate::migration::SharedSession)) wait
+//! for each other in arrival order; a run that waits longer than its timeout
+//! gives up instead of piling up behind a stuck one.
+
+use crate::MigrationError;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, OwnedMutexGuard};
+use tracing::info;
+
+/// How long the CLI commands wait for a run in progress in the same process
+pub const DEFAULT_QUEUE_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// FIFO queue of runs sharing a connection
+#[derive(Clone, Default)]
+pub struct RunQueue {
+    lock: Arc<Mutex<()>>,
+    /// Runs holding or waiting for the lock
+    queued: Arc<AtomicUsize>,
+}
+
+/// Exclusive use of the connection until dropped
+pub struct RunPermit {
+    _guard: OwnedMutexGuard<()>,
+    queued: Arc<AtomicUsize>,
+}
+
+impl Drop for RunPermit {
+    fn drop(&mut self) {
+        self.queued.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl RunQueue {
+    /// Number of runs in progress or waiting
+    pub fn len(&self) -> usize {
+        self.queued.load(Ordering::SeqCst)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Wait for the runs queued before this one, for at most `timeout`
+    pub async fn acquire(&self, operation: &str, timeout: Duration) -> Result<RunPermit, MigrationError> {
+        let ahead = self.queued.fetch_add(1, Ordering::SeqCst);
+        if ahead > 0 {
+            info!("⏳ {} queued behind {} run(s) in progress or waiting", operation, ahead);
+        }
+
+        let started = Instant::now();
+        match tokio::time::timeout(timeout, self.lock.clone().lock_owned()).await {
+            Ok(guard) => {
+                if ahead > 0 {
+                    info!("{} starting after waiting {:.1}s", operation, started.elapsed().as_secs_f64());
+                }
+                Ok(RunPermit {
+                    _guard: guard,
+                    queued: self.queued.clone(),
+                })
+            }
+            Err(_) => {
+                self.queued.fetch_sub(1, Ordering::SeqCst);
+                Err(MigrationError::QueueTimeout {
+                    operation: operation.to_string(),
+                    waited_secs: timeout.as_secs(),
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_runs_are_serialized() {
+        let queue = RunQueue::default();
+        let first = queue.acquire("up", Duration::from_secs(1)).await.unwrap();
+        assert_eq!(queue.len(), 1);
+
+        let timed_out = queue.acquire("down", Duration::from_millis(20)).await;
+        assert!(matches!(timed_out, Err(MigrationError::QueueTimeout { .. })));
+        assert_eq!(queue.len(), 1);
+
+        let waiting = {
+            let queue = queue.clone();
+            tokio::spawn(async move { queue.acquire("down", Duration::from_secs(5)).await.is_ok() })
+        };
+        drop(first);
+        assert!(waiting.await.unwrap());
+        assert!(queue.is_empty());
+    }
+}
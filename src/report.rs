@@ -0,0 +1,560 @@
+//! Typed results of `up`, `down`, `status` and `verify`
+//!
+//! Library users get these from [`MigrationManager`] instead of picking fields
+//! out of JSON. The commands build the same structs and serialize them as the
+//! `data` of their output, so field names match the `--output json` payloads.
+
+use crate::{
+    commands::{DownCommand, UpCommand},
+    diagnostics::FailureReport, lint::{Finding, Rule, Severity}, lock::DEFAULT_LOCK_TIMEOUT, migration::MigrationManager,
+    InvalidMigrationFile, MigrationError, MigrationFile, MigrationRecord,
+};
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Serializer};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// Outcome of applying migrations in order
+#[derive(Debug, Default, Serialize)]
+pub struct UpResult {
+    pub applied_count: usize,
+    pub applied_migrations: Vec<String>,
+    /// Migration that failed; later migrations were not attempted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub failed_migration: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub failure: Option<FailureReport>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<&'static str>,
+    /// Directory written by `--diagnostics-dir`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub diagnostics_bundle: Option<PathBuf>,
+    /// The error behind `error`, for callers that need more than its text
+    #[serde(skip)]
+    pub cause: Option<MigrationError>,
+}
+
+impl UpResult {
+    pub fn applied(&mut self, version: &str) {
+        self.applied_count += 1;
+        self.applied_migrations.push(version.to_string());
+    }
+
+    pub fn fail(&mut self, failure: FailureReport, error: MigrationError) {
+        self.failed_migration = Some(failure.version.clone());
+        self.error = Some(error.to_string());
+        self.error_code = Some(error.code());
+        self.failure = Some(failure);
+        self.cause = Some(error);
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.failed_migration.is_none()
+    }
+}
+
+/// Outcome of rolling migrations back, newest first
+#[derive(Debug, Default, Serialize)]
+pub struct DownResult {
+    pub rollback_count: usize,
+    pub rolled_back_migrations: Vec<String>,
+    /// Irreversible migrations left applied by `--skip-irreversible`
+    pub skipped_migrations: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub failed_migration: Option<String>,
+    /// The rollback stopped at a migration marked `-- db-migrate:no-down`
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub irreversible: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub failure: Option<FailureReport>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<&'static str>,
+    #[serde(skip)]
+    pub cause: Option<MigrationError>,
+}
+
+impl DownResult {
+    pub fn rolled_back(&mut self, version: &str) {
+        self.rollback_count += 1;
+        self.rolled_back_migrations.push(version.to_string());
+    }
+
+    /// Stop at `version`; `message` replaces the error text when it explains more
+    pub fn fail(&mut self, version: &str, message: impl Into<String>, error: MigrationError) {
+        self.failed_migration = Some(version.to_string());
+        self.error = Some(message.into());
+        self.error_code = Some(error.code());
+        self.cause = Some(error);
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.failed_migration.is_none()
+    }
+}
+
+/// Applied and pending migrations of the configured keyspace
+#[derive(Debug, Clone, Serialize)]
+pub struct StatusReport {
+    /// Latest applied version
+    #[serde(serialize_with = "version_or_none")]
+    pub current_version: Option<String>,
+    pub applied_count: usize,
+    pub pending_count: usize,
+    pub total_files: usize,
+    pub ignored_migrations: Vec<String>,
+    pub invalid_files: Vec<InvalidMigrationFile>,
+    pub up_to_date: bool,
+    /// Counts per target keyspace (see the `keyspace` directive)
+    pub keyspaces: BTreeMap<String, KeyspaceStatus>,
+    pub applied_migrations: Vec<AppliedMigration>,
+    pub pending_migrations: Vec<PendingMigration>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct KeyspaceStatus {
+    pub applied_count: usize,
+    pub pending_count: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AppliedMigration {
+    pub version: String,
+    pub description: String,
+    pub applied_at: DateTime<Utc>,
+    pub checksum: String,
+    pub keyspace: String,
+    pub duration_ms: Option<i64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PendingMigration {
+    pub version: String,
+    pub description: String,
+    pub checksum: String,
+    pub keyspace: String,
+}
+
+/// `None` was written as a string before the report was typed
+fn version_or_none<S: Serializer>(version: &Option<String>, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(version.as_deref().unwrap_or("None"))
+}
+
+impl StatusReport {
+    /// Status of `applied` and `pending`, which list every migration; `keyspace`
+    /// is the default target keyspace
+    pub fn new(
+        keyspace: &str,
+        applied: &[MigrationRecord],
+        pending: &[MigrationFile],
+        total_files: usize,
+        ignored: &[MigrationFile],
+        invalid_files: Vec<InvalidMigrationFile>,
+    ) -> Self {
+        let applied_migrations: Vec<_> = applied
+            .iter()
+            .map(|m| AppliedMigration {
+                version: m.version.clone(),
+                description: m.description.clone(),
+                applied_at: m.applied_at,
+                checksum: m.checksum.clone(),
+                keyspace: m.target_keyspace.clone().unwrap_or_else(|| keyspace.to_string()),
+                duration_ms: m.duration_ms,
+            })
+            .collect();
+        let pending_migrations: Vec<_> = pending
+            .iter()
+            .map(|m| PendingMigration {
+                version: m.version.clone(),
+                description: m.description.clone(),
+                checksum: m.checksum.clone(),
+                keyspace: m.directives.keyspace.clone().unwrap_or_else(|| keyspace.to_string()),
+            })
+            .collect();
+
+        let mut keyspaces = BTreeMap::from([(keyspace.to_string(), KeyspaceStatus::default())]);
+        for m in &applied_migrations {
+            keyspaces.entry(m.keyspace.clone()).or_default().applied_count += 1;
+        }
+        for m in &pending_migrations {
+            keyspaces.entry(m.keyspace.clone()).or_default().pending_count += 1;
+        }
+
+        Self {
+            current_version: applied.last().map(|m| m.version.clone()),
+            applied_count: applied.len(),
+            pending_count: pending.len(),
+            total_files,
+            ignored_migrations: ignored.iter().map(|m| m.version.clone()).collect(),
+            invalid_files,
+            up_to_date: pending.is_empty(),
+            keyspaces,
+            applied_migrations,
+            pending_migrations,
+        }
+    }
+}
+
+/// Integrity problems found by `verify`
+#[derive(Debug, Clone, Serialize)]
+pub struct VerifyReport {
+    pub integrity_issues: usize,
+    pub checksum_mismatches: usize,
+    pub down_changes: usize,
+    pub missing_files: usize,
+    pub out_of_order: usize,
+    pub unparseable: usize,
+    pub down_section_issues: Vec<Finding>,
+    pub invalid_files: Vec<InvalidMigrationFile>,
+    /// Ordering and parseability were checked too
+    pub strict: bool,
+    /// `--fix` was given
+    pub fixed: bool,
+    /// Checksums accepted by `--fix`
+    pub accepted: Vec<AcceptedChecksum>,
+    pub issues: Vec<VerifyIssue>,
+}
+
+/// Issue types of `verify`, by [`VerifyIssue::rule`], plus `invalid-filename`;
+/// DOWN section issues are reported under the `down-section` lint rule
+pub const VERIFY_RULES: &[Rule] = &[
+    Rule {
+        id: "checksum-mismatch",
+        description: "An applied migration's file changed after it was applied",
+        help_uri: "https://github.com/dimuziop/db-migrate#verify-options",
+    },
+    Rule {
+        id: "down-changed",
+        description: "Only the DOWN section of an applied migration's file changed",
+        help_uri: "https://github.com/dimuziop/db-migrate#verify-options",
+    },
+    Rule {
+        id: "missing-file",
+        description: "The file of an applied migration is missing",
+        help_uri: "https://github.com/dimuziop/db-migrate#verify-options",
+    },
+    Rule {
+        id: "out-of-order",
+        description: "A pending migration sorts before the latest applied one (verify --strict)",
+        help_uri: "https://github.com/dimuziop/db-migrate#verify-options",
+    },
+    Rule {
+        id: "unparseable",
+        description: "The file of an applied migration no longer parses (verify --strict)",
+        help_uri: "https://github.com/dimuziop/db-migrate#verify-options",
+    },
+    Rule {
+        id: "invalid-filename",
+        description: "A file in the migrations directory is skipped because of its name",
+        help_uri: "https://github.com/dimuziop/db-migrate#file-naming-convention",
+    },
+    Rule {
+        id: "other",
+        description: "The migrations could not be verified",
+        help_uri: "https://github.com/dimuziop/db-migrate#verify-options",
+    },
+];
+
+/// A problem with an applied migration or its file
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum VerifyIssue {
+    ChecksumMismatch {
+        version: String,
+        expected_checksum: String,
+        actual_checksum: String,
+    },
+    /// Only the DOWN section of the file changed
+    #[serde(rename = "down_changed")]
+    DownChanged {
+        version: String,
+        expected_checksum: String,
+        actual_checksum: String,
+    },
+    MissingFile {
+        version: String,
+    },
+    /// A pending migration sorts before the latest applied one
+    OutOfOrder {
+        version: String,
+        latest_applied: String,
+    },
+    Unparseable {
+        version: String,
+        reason: String,
+    },
+    Other {
+        error: String,
+    },
+}
+
+impl From<&MigrationError> for VerifyIssue {
+    fn from(error: &MigrationError) -> Self {
+        match error {
+            MigrationError::ChecksumMismatch { version, expected, actual } => Self::ChecksumMismatch {
+                version: version.clone(),
+                expected_checksum: expected.clone(),
+                actual_checksum: actual.clone(),
+            },
+            MigrationError::DownChecksumMismatch { version, expected, actual } => Self::DownChanged {
+                version: version.clone(),
+                expected_checksum: expected.clone(),
+                actual_checksum: actual.clone(),
+            },
+            MigrationError::MigrationNotFound(version) => Self::MissingFile { version: version.clone() },
+            MigrationError::OutOfOrder { version, latest_applied } => Self::OutOfOrder {
+                version: version.clone(),
+                latest_applied: latest_applied.clone(),
+            },
+            MigrationError::UnparseableMigration { version, reason } => Self::Unparseable {
+                version: version.clone(),
+                reason: reason.clone(),
+            },
+            _ => Self::Other { error: error.to_string() },
+        }
+    }
+}
+
+impl VerifyIssue {
+    /// Id of the rule the issue is annotated with, in [`VERIFY_RULES`]; the
+    /// `type` it is serialized with in snake_case
+    pub fn rule(&self) -> &'static str {
+        match self {
+            Self::ChecksumMismatch { .. } => "checksum-mismatch",
+            Self::DownChanged { .. } => "down-changed",
+            Self::MissingFile { .. } => "missing-file",
+            Self::OutOfOrder { .. } => "out-of-order",
+            Self::Unparseable { .. } => "unparseable",
+            Self::Other { .. } => "other",
+        }
+    }
+
+    pub fn version(&self) -> Option<&str> {
+        match self {
+            Self::ChecksumMismatch { version, .. }
+            | Self::DownChanged { version, .. }
+            | Self::MissingFile { version }
+            | Self::OutOfOrder { version, .. }
+            | Self::Unparseable { version, .. } => Some(version),
+            Self::Other { .. } => None,
+        }
+    }
+
+    /// A changed DOWN section does not block `up`; everything else does
+    pub fn severity(&self) -> Severity {
+        match self {
+            Self::DownChanged { .. } => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+
+    /// One line describing the issue
+    pub fn message(&self) -> String {
+        match self {
+            Self::ChecksumMismatch { version, expected_checksum, actual_checksum } => format!(
+                "Migration {} changed after it was applied (checksum {} instead of {})",
+                version, actual_checksum, expected_checksum
+            ),
+            Self::DownChanged { version, .. } => format!(
+                "The DOWN section of applied migration {} changed; accept it with 'db-migrate repair --accept-down'",
+                version
+            ),
+            Self::MissingFile { version } => format!("The file of applied migration {} is missing", version),
+            Self::OutOfOrder { version, latest_applied } => format!(
+                "Pending migration {} sorts before applied migration {}",
+                version, latest_applied
+            ),
+            Self::Unparseable { version, reason } => {
+                format!("Applied migration {} no longer parses: {}", version, reason)
+            }
+            Self::Other { error } => error.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AcceptedChecksum {
+    pub version: String,
+    pub file: Option<PathBuf>,
+    pub old_checksum: String,
+    pub new_checksum: String,
+}
+
+impl VerifyReport {
+    pub fn new(
+        errors: &[MigrationError],
+        down_section_issues: Vec<Finding>,
+        invalid_files: Vec<InvalidMigrationFile>,
+        strict: bool,
+    ) -> Self {
+        let issues: Vec<VerifyIssue> = errors.iter().map(VerifyIssue::from).collect();
+        let count = |matches: fn(&VerifyIssue) -> bool| issues.iter().filter(|issue| matches(issue)).count();
+        Self {
+            integrity_issues: issues.len(),
+            checksum_mismatches: count(|issue| matches!(issue, VerifyIssue::ChecksumMismatch { .. })),
+            down_changes: count(|issue| matches!(issue, VerifyIssue::DownChanged { .. })),
+            missing_files: count(|issue| matches!(issue, VerifyIssue::MissingFile { .. })),
+            out_of_order: count(|issue| matches!(issue, VerifyIssue::OutOfOrder { .. })),
+            unparseable: count(|issue| matches!(issue, VerifyIssue::Unparseable { .. })),
+            down_section_issues,
+            invalid_files,
+            strict,
+            fixed: false,
+            accepted: Vec::new(),
+            issues,
+        }
+    }
+
+    /// Nothing to report
+    pub fn is_clean(&self) -> bool {
+        self.issues.is_empty() && self.down_section_issues.is_empty() && self.invalid_files.is_empty()
+    }
+}
+
+impl MigrationManager {
+    /// Apply up to `count` pending migrations (all if `None`), stopping at the
+    /// first failure, with the checks and the migration lock of the `up` command
+    ///
+    /// A run those checks refuse fails with the error the command reports: a
+    /// modified applied migration, an emptied tracking table, exceeded limits, a
+    /// policy violation, a plugin denial or a missing approval.
+    pub async fn up(&mut self, count: Option<usize>) -> Result<UpResult, MigrationError> {
+        UpCommand::default()
+            .with_count(count)
+            .with_lock_timeout(DEFAULT_LOCK_TIMEOUT)
+            .apply(self)
+            .await
+    }
+
+    /// Apply `migrations` in order, calling `on_applied` after each, and stop at
+    /// the first failure; the caller holds the run permit (see [`MigrationManager::exclusive_run`])
+    pub async fn apply_migrations(
+        &mut self,
+        migrations: &[MigrationFile],
+        mut on_applied: impl FnMut(&MigrationFile),
+    ) -> UpResult {
+        let mut result = UpResult::default();
+        for migration in migrations {
+            match self.apply_migration(migration).await {
+                Ok(_) => {
+                    result.applied(&migration.version);
+                    on_applied(migration);
+                }
+                Err(e) => {
+                    result.fail(FailureReport::new(self, &migration.version, &e), e);
+                    break;
+                }
+            }
+        }
+        result
+    }
+
+    /// Roll back the `count` most recently applied migrations, stopping at the
+    /// first one that cannot be, as the `down` command does
+    pub async fn down(&mut self, count: usize) -> Result<DownResult, MigrationError> {
+        DownCommand::new(count).apply(self).await
+    }
+
+    /// Applied, pending, ignored and misnamed migrations
+    pub async fn status(&self) -> Result<StatusReport, MigrationError> {
+        Ok(StatusReport::new(
+            &self.get_config().database.keyspace,
+            &self.get_applied_migrations().await?,
+            &self.get_pending_migrations().await?,
+            self.get_migration_files().await?.len(),
+            &self.get_ignored_migration_files().await?,
+            self.get_invalid_migration_files().await?,
+        ))
+    }
+
+    /// Check applied migrations against their files; `strict` adds the ordering
+    /// and parseability checks of `verify --strict`
+    pub async fn verify(&self, strict: bool) -> Result<VerifyReport, MigrationError> {
+        let errors = if strict {
+            self.verify_migrations_strict().await?
+        } else {
+            self.verify_migrations().await?
+        };
+        let down_validation = self.get_config().behavior.down_validation;
+        let down_findings = self
+            .get_migration_files()
+            .await?
+            .iter()
+            .filter_map(|file| crate::lint::check_down_section(file, down_validation))
+            .collect();
+        Ok(VerifyReport::new(
+            &errors,
+            down_findings,
+            self.get_invalid_migration_files().await?,
+            strict,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reports_serialize_like_the_json_output() {
+        let report = VerifyReport::new(
+            &[
+                MigrationError::ChecksumMismatch {
+                    version: "001".to_string(),
+                    expected: "a".to_string(),
+                    actual: "b".to_string(),
+                },
+                MigrationError::MigrationNotFound("002".to_string()),
+            ],
+            Vec::new(),
+            Vec::new(),
+            false,
+        );
+        assert_eq!(report.checksum_mismatches, 1);
+        assert_eq!(report.missing_files, 1);
+        assert!(!report.is_clean());
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["issues"][0]["type"], "checksum_mismatch");
+        assert_eq!(json["issues"][0]["actual_checksum"], "b");
+        assert_eq!(json["issues"][1], serde_json::json!({"type": "missing_file", "version": "002"}));
+
+        let status = StatusReport::new("app", &[], &[], 0, &[], Vec::new());
+        let json = serde_json::to_value(&status).unwrap();
+        assert_eq!(json["current_version"], "None");
+        assert_eq!(json["keyspaces"]["app"], serde_json::json!({"applied_count": 0, "pending_count": 0}));
+
+        let mut up = UpResult::default();
+        up.applied("001");
+        assert!(up.is_success());
+        assert_eq!(
+            serde_json::to_value(&up).unwrap(),
+            serde_json::json!({"applied_count": 1, "applied_migrations": ["001"]})
+        );
+    }
+
+    #[test]
+    fn test_invalid_files_are_reported() {
+        let invalid = vec![InvalidMigrationFile {
+            filename: "2025-01-16_add_index.cql".to_string(),
+            reason: "expected a YYYYMMDD_NNN version".to_string(),
+        }];
+
+        // A misnamed file alone fails verify
+        let report = VerifyReport::new(&[], Vec::new(), invalid.clone(), false);
+        assert!(report.issues.is_empty());
+        assert!(!report.is_clean());
+        assert_eq!(
+            serde_json::to_value(&report).unwrap()["invalid_files"],
+            serde_json::json!([{"filename": "2025-01-16_add_index.cql", "reason": "expected a YYYYMMDD_NNN version"}])
+        );
+        assert!(VerifyReport::new(&[], Vec::new(), Vec::new(), false).is_clean());
+
+        // Status lists it next to the applied and pending migrations
+        let status = StatusReport::new("app", &[], &[], 0, &[], invalid);
+        let json = serde_json::to_value(&status).unwrap();
+        assert_eq!(json["invalid_files"][0]["filename"], "2025-01-16_add_index.cql");
+    }
+}
@@ -0,0 +1,168 @@
+//! Machine-readable report of the last `up` or `down` run (`output.run_report`)
+//!
+//! CI jobs archive the file instead of capturing stdout, where progress lines
+//! and the result are mixed and which is lost with the runner. It is rewritten
+//! after every run, whether it succeeded or not, and whatever `--output` says.
+
+use crate::{
+    diagnostics::{cluster_info, RecentLogs},
+    exit_code,
+    migration::{MigrationManager, MigrationRun},
+    CommandOutput, MigrationError,
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::path::Path;
+use tracing::{debug, warn};
+
+/// What `output.run_report` receives
+#[derive(Debug, Serialize)]
+pub struct RunReport {
+    /// `up` or `down`
+    pub command: String,
+    pub tool_version: &'static str,
+    pub success: bool,
+    pub exit_code: i32,
+    pub error_code: Option<&'static str>,
+    pub error: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub duration_ms: u64,
+    /// See [`cluster_info`]; unknown for `--all-clusters` and `--all-tenants`
+    pub cluster: Option<serde_json::Value>,
+    /// Migrations applied or rolled back, including the one that failed
+    pub migrations: Vec<MigrationRun>,
+    pub statements_executed: usize,
+    /// Warnings logged during the run
+    pub warnings: Vec<String>,
+    /// The `data` of the command's `--output json` result
+    pub result: Option<serde_json::Value>,
+}
+
+impl RunReport {
+    pub fn new(
+        command: &str,
+        started_at: DateTime<Utc>,
+        output: &anyhow::Result<CommandOutput>,
+        cluster: Option<serde_json::Value>,
+        migrations: Vec<MigrationRun>,
+    ) -> Self {
+        let finished_at = Utc::now();
+        let (success, exit_code, error_code, error, result) = match output {
+            Ok(output) => (
+                output.success,
+                output.exit_code,
+                output.code,
+                output
+                    .data
+                    .as_ref()
+                    .and_then(|data| data["error"].as_str())
+                    .map(str::to_string)
+                    .or_else(|| (!output.success).then(|| output.message.clone())),
+                output.data.clone(),
+            ),
+            Err(e) => {
+                let migration_error = e.chain().find_map(|c| c.downcast_ref::<MigrationError>());
+                (
+                    false,
+                    migration_error.map_or(exit_code::FAILURE, MigrationError::exit_code),
+                    migration_error.map(MigrationError::code),
+                    Some(e.to_string()),
+                    None,
+                )
+            }
+        };
+
+        Self {
+            command: command.to_string(),
+            tool_version: env!("CARGO_PKG_VERSION"),
+            success,
+            exit_code,
+            error_code,
+            error,
+            started_at,
+            finished_at,
+            duration_ms: (finished_at - started_at).num_milliseconds().max(0) as u64,
+            cluster,
+            statements_executed: migrations.iter().map(|run| run.statements_executed).sum(),
+            migrations,
+            warnings: RecentLogs::global().warnings(),
+            result,
+        }
+    }
+
+    /// Write the report as JSON to `path`, creating its directory
+    pub fn write(&self, path: &Path) -> anyhow::Result<()> {
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// Write the report of a run to `output.run_report`, if set; failures are logged
+///
+/// `manager` is the one the command ran with, if it used a single one.
+pub async fn write_run_report(
+    path: Option<&Path>,
+    command: &str,
+    started_at: DateTime<Utc>,
+    output: &anyhow::Result<CommandOutput>,
+    manager: Option<&MigrationManager>,
+) {
+    let Some(path) = path else {
+        return;
+    };
+    let (cluster, migrations) = match manager {
+        Some(manager) => (Some(cluster_info(manager).await), manager.migration_runs()),
+        None => (None, Vec::new()),
+    };
+
+    let report = RunReport::new(command, started_at, output, cluster, migrations);
+    match report.write(path) {
+        Ok(()) => debug!("Wrote the run report to {}", path.display()),
+        Err(e) => warn!("Could not write the run report to {}: {}", path.display(), e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_report() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".db-migrate").join("last-run.json");
+        let output = Ok(CommandOutput::error_with_data(
+            "❌ Migration failed",
+            serde_json::json!({"applied_count": 1, "error": "Query error: timeout"}),
+        ));
+        let migrations = vec![
+            MigrationRun {
+                version: "001".to_string(),
+                direction: "up",
+                statements_executed: 3,
+                duration_ms: 120,
+                error: None,
+            },
+            MigrationRun {
+                version: "002".to_string(),
+                direction: "up",
+                statements_executed: 1,
+                duration_ms: 30_000,
+                error: Some("Query error: timeout".to_string()),
+            },
+        ];
+
+        RunReport::new("up", Utc::now(), &output, None, migrations).write(&path).unwrap();
+
+        let written: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(written["command"], "up");
+        assert_eq!(written["success"], false);
+        assert_eq!(written["error"], "Query error: timeout");
+        assert_eq!(written["statements_executed"], 4);
+        assert_eq!(written["migrations"][1]["version"], "002");
+        assert_eq!(written["result"]["applied_count"], 1);
+    }
+}
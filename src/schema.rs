@@ -1,9 +1,11 @@
-// This module is for future schema introspection and drift detection
-// Currently contains placeholder implementations that can be extended
+// Schema introspection via system_schema, used by drift detection and clone-schema
 
+use crate::drift::{self, normalize_identifier, ExpectedSchema, SchemaDrift};
+use crate::cql::{quote_identifier, quoted};
 use crate::MigrationError;
 use scylla::Session;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TableInfo {
@@ -11,6 +13,7 @@ pub struct TableInfo {
     pub table_name: String,
     pub columns: Vec<ColumnInfo>,
     pub primary_key: Vec<String>,
+    pub comment: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -20,6 +23,38 @@ pub struct ColumnInfo {
     pub kind: String, // partition_key, clustering, regular
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TypeInfo {
+    pub keyspace: String,
+    pub type_name: String,
+    /// Field names and types, in declaration order
+    pub fields: Vec<(String, String)>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ViewInfo {
+    pub keyspace: String,
+    pub view_name: String,
+    pub base_table: String,
+    pub columns: Vec<ColumnInfo>,
+}
+
+/// A schema object and the statement that recreates it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SchemaObject {
+    pub object_type: String,
+    pub name: String,
+    pub create_statement: String,
+}
+
+/// A permission granted to a role on a resource
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PermissionInfo {
+    pub role: String,
+    pub resource: String,
+    pub permission: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IndexInfo {
     pub name: String,
@@ -30,68 +65,534 @@ pub struct IndexInfo {
 
 pub struct SchemaIntrospector<'a> {
     session: &'a Session,
-    keyspace: &'a str,
+    /// Keyspace name as stored in `system_schema`
+    keyspace: String,
 }
 
 impl<'a> SchemaIntrospector<'a> {
-    pub fn new(session: &'a Session, keyspace: &'a str) -> Self {
-        Self { session, keyspace }
+    /// Inspect `keyspace`, written as in configuration (quoted if case-sensitive)
+    pub fn new(session: &'a Session, keyspace: &str) -> Self {
+        Self {
+            session,
+            keyspace: normalize_identifier(keyspace),
+        }
     }
 
     /// Get all tables in the current keyspace
     pub async fn get_tables(&self) -> Result<Vec<TableInfo>, MigrationError> {
-        // This is a placeholder implementation
-        // In a full implementation, you would query system.schema_columns
-        // and system.schema_keyspaces to get the actual schema information
-
-        let query = "SELECT table_name FROM system_schema.tables WHERE keyspace_name = ?";
-        let rows = self.session.query(query, (self.keyspace,)).await?;
+        let query = "SELECT table_name, comment FROM system_schema.tables WHERE keyspace_name = ?";
+        let rows = self.session.query(query, (&self.keyspace,)).await?;
+        let mut columns = self.get_columns().await?;
 
         let mut tables = Vec::new();
         for row in rows
-            .rows_typed::<(String,)>()
+            .rows_typed::<(String, Option<String>)>()
             .map_err(|e| MigrationError::IntegrityError(e.to_string()))?
         {
-            let (table_name,) = row.map_err(|e| MigrationError::IntegrityError(e.to_string()))?;
+            let (table_name, comment) =
+                row.map_err(|e| MigrationError::IntegrityError(e.to_string()))?;
+            let (columns, primary_key) = columns.remove(&table_name).unwrap_or_default();
 
-            // For now, just return basic table info
-            // In a full implementation, you'd fetch column details
             tables.push(TableInfo {
                 keyspace: self.keyspace.to_string(),
                 table_name,
-                columns: Vec::new(),     // TODO: Implement column introspection
-                primary_key: Vec::new(), // TODO: Implement primary key detection
+                columns,
+                primary_key,
+                comment: comment.unwrap_or_default(),
             });
         }
 
         Ok(tables)
     }
 
+    /// Get all user-defined types in the current keyspace
+    pub async fn get_types(&self) -> Result<Vec<TypeInfo>, MigrationError> {
+        let query = "SELECT type_name, field_names, field_types FROM system_schema.types WHERE keyspace_name = ?";
+        let rows = self.session.query(query, (&self.keyspace,)).await?;
+
+        let mut types = Vec::new();
+        for row in rows
+            .rows_typed::<(String, Vec<String>, Vec<String>)>()
+            .map_err(|e| MigrationError::IntegrityError(e.to_string()))?
+        {
+            let (type_name, field_names, field_types) =
+                row.map_err(|e| MigrationError::IntegrityError(e.to_string()))?;
+
+            types.push(TypeInfo {
+                keyspace: self.keyspace.to_string(),
+                type_name,
+                fields: field_names.into_iter().zip(field_types).collect(),
+            });
+        }
+
+        Ok(types)
+    }
+
+    /// Get all materialized views in the current keyspace
+    pub async fn get_views(&self) -> Result<Vec<ViewInfo>, MigrationError> {
+        let query = "SELECT view_name, base_table_name FROM system_schema.views WHERE keyspace_name = ?";
+        let rows = self.session.query(query, (&self.keyspace,)).await?;
+        let mut columns = self.get_columns().await?;
+
+        let mut views = Vec::new();
+        for row in rows
+            .rows_typed::<(String, String)>()
+            .map_err(|e| MigrationError::IntegrityError(e.to_string()))?
+        {
+            let (view_name, base_table) =
+                row.map_err(|e| MigrationError::IntegrityError(e.to_string()))?;
+            let (columns, _) = columns.remove(&view_name).unwrap_or_default();
+
+            views.push(ViewInfo {
+                keyspace: self.keyspace.to_string(),
+                view_name,
+                base_table,
+                columns,
+            });
+        }
+
+        Ok(views)
+    }
+
+    /// Columns and primary key of every table and view, keyed by table/view name
+    async fn get_columns(
+        &self,
+    ) -> Result<BTreeMap<String, (Vec<ColumnInfo>, Vec<String>)>, MigrationError> {
+        let query = "SELECT table_name, column_name, kind, position, type FROM system_schema.columns WHERE keyspace_name = ?";
+        let rows = self.session.query(query, (&self.keyspace,)).await?;
+
+        let mut by_table: BTreeMap<String, Vec<(i32, ColumnInfo)>> = BTreeMap::new();
+        for row in rows
+            .rows_typed::<(String, String, String, i32, String)>()
+            .map_err(|e| MigrationError::IntegrityError(e.to_string()))?
+        {
+            let (table_name, name, kind, position, data_type) =
+                row.map_err(|e| MigrationError::IntegrityError(e.to_string()))?;
+
+            by_table
+                .entry(table_name)
+                .or_default()
+                .push((position, ColumnInfo { name, data_type, kind }));
+        }
+
+        Ok(by_table
+            .into_iter()
+            .map(|(table, mut columns)| {
+                // Partition key columns first, then clustering columns, each by position
+                columns.sort_by_key(|(position, column)| {
+                    let rank = match column.kind.as_str() {
+                        "partition_key" => 0,
+                        "clustering" => 1,
+                        _ => 2,
+                    };
+                    (rank, *position)
+                });
+                let primary_key = columns
+                    .iter()
+                    .filter(|(_, c)| c.kind == "partition_key" || c.kind == "clustering")
+                    .map(|(_, c)| c.name.clone())
+                    .collect();
+                let columns = columns.into_iter().map(|(_, c)| c).collect();
+                (table, (columns, primary_key))
+            })
+            .collect())
+    }
+
+    /// Record in a table's comment that `version` was the last migration to alter it
+    ///
+    /// Any existing comment text is preserved; only the stamp is replaced.
+    pub async fn stamp_table(&self, table: &str, version: &str) -> Result<(), MigrationError> {
+        let query = "SELECT comment FROM system_schema.tables WHERE keyspace_name = ? AND table_name = ?";
+        let result = self.session.query(query, (&self.keyspace, table)).await?;
+
+        let mut current = String::new();
+        for row in result
+            .rows_typed::<(Option<String>,)>()
+            .map_err(|e| MigrationError::IntegrityError(e.to_string()))?
+        {
+            let (comment,) = row.map_err(|e| MigrationError::IntegrityError(e.to_string()))?;
+            current = comment.unwrap_or_default();
+        }
+
+        let comment = stamp_comment(&current, version);
+        let alter = format!(
+            "ALTER TABLE {}.{} WITH comment = '{}'",
+            quoted(&self.keyspace),
+            quoted(table),
+            comment.replace('\'', "''")
+        );
+        self.session.query(alter, &[]).await?;
+        Ok(())
+    }
+
+    /// Whether the keyspace exists
+    pub async fn keyspace_exists(&self) -> Result<bool, MigrationError> {
+        let query = "SELECT keyspace_name FROM system_schema.keyspaces WHERE keyspace_name = ?";
+        let result = self.session.query(query, (&self.keyspace,)).await?;
+        Ok(result.rows.is_some_and(|rows| !rows.is_empty()))
+    }
+
+    /// Replication options of the keyspace (`class` and its settings), or
+    /// `None` if it does not exist
+    pub async fn replication(&self) -> Result<Option<BTreeMap<String, String>>, MigrationError> {
+        let query = "SELECT replication FROM system_schema.keyspaces WHERE keyspace_name = ?";
+        let result = self.session.query(query, (&self.keyspace,)).await?;
+        let row = result
+            .rows_typed::<(HashMap<String, String>,)>()
+            .map_err(|e| MigrationError::IntegrityError(e.to_string()))?
+            .next()
+            .transpose()
+            .map_err(|e| MigrationError::IntegrityError(e.to_string()))?;
+        Ok(row.map(|(replication,)| replication.into_iter().collect()))
+    }
+
+    /// Partition key columns of a table, in order; empty if it does not exist
+    pub async fn partition_key(&self, table: &str) -> Result<Vec<String>, MigrationError> {
+        let query = "SELECT column_name, kind, position FROM system_schema.columns WHERE keyspace_name = ? AND table_name = ?";
+        let rows = self
+            .session
+            .query(query, (&self.keyspace, normalize_identifier(table)))
+            .await?;
+
+        let mut columns = Vec::new();
+        for row in rows
+            .rows_typed::<(String, String, i32)>()
+            .map_err(|e| MigrationError::IntegrityError(e.to_string()))?
+        {
+            let (name, kind, position) = row.map_err(|e| MigrationError::IntegrityError(e.to_string()))?;
+            if kind == "partition_key" {
+                columns.push((position, name));
+            }
+        }
+        columns.sort();
+        Ok(columns.into_iter().map(|(_, name)| name).collect())
+    }
+
+    /// DDL for every object in the keyspace, in dependency order
+    ///
+    /// Uses server-side `DESCRIBE`, available since ScyllaDB 5.2.
+    pub async fn describe_keyspace(&self) -> Result<Vec<SchemaObject>, MigrationError> {
+        let query = format!("DESCRIBE KEYSPACE {}", quoted(&self.keyspace));
+        let rows = self.session.query(query, &[]).await?;
+
+        let mut objects = Vec::new();
+        for row in rows
+            .rows_typed::<(String, String, String, String)>()
+            .map_err(|e| MigrationError::IntegrityError(e.to_string()))?
+        {
+            let (_keyspace, object_type, name, create_statement) =
+                row.map_err(|e| MigrationError::IntegrityError(e.to_string()))?;
+
+            objects.push(SchemaObject {
+                object_type,
+                name,
+                create_statement,
+            });
+        }
+
+        Ok(objects)
+    }
+
+    /// `CREATE` statements of a table and its indexes and views, or `None` if the
+    /// table does not exist
+    pub async fn describe_table(&self, table: &str) -> Result<Option<String>, MigrationError> {
+        let query = format!("DESCRIBE TABLE {}.{}", quoted(&self.keyspace), quote_identifier(table));
+        let rows = match self.session.query(query, &[]).await {
+            Ok(rows) => rows,
+            Err(_) if !self.table_exists(table).await? => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut statements = Vec::new();
+        for row in rows
+            .rows_typed::<(String, String, String, String)>()
+            .map_err(|e| MigrationError::IntegrityError(e.to_string()))?
+        {
+            let (_keyspace, _object_type, _name, create_statement) =
+                row.map_err(|e| MigrationError::IntegrityError(e.to_string()))?;
+            statements.push(create_statement);
+        }
+
+        Ok(Some(statements.join("\n\n")))
+    }
+
+    async fn table_exists(&self, table: &str) -> Result<bool, MigrationError> {
+        let query = "SELECT table_name FROM system_schema.tables WHERE keyspace_name = ? AND table_name = ?";
+        let result = self
+            .session
+            .query(query, (&self.keyspace, normalize_identifier(table)))
+            .await?;
+        Ok(result.rows.is_some_and(|rows| !rows.is_empty()))
+    }
+
     /// Get all indexes in the current keyspace
     pub async fn get_indexes(&self) -> Result<Vec<IndexInfo>, MigrationError> {
-        // Placeholder implementation
-        // In a full implementation, you would query system.schema_columns
-        // to find secondary indexes
+        let query = "SELECT index_name, table_name, kind, options FROM system_schema.indexes WHERE keyspace_name = ?";
+        let rows = self.session.query(query, (&self.keyspace,)).await?;
+
+        let mut indexes = Vec::new();
+        for row in rows
+            .rows_typed::<(String, String, String, HashMap<String, String>)>()
+            .map_err(|e| MigrationError::IntegrityError(e.to_string()))?
+        {
+            let (name, table_name, index_type, options) =
+                row.map_err(|e| MigrationError::IntegrityError(e.to_string()))?;
 
-        Ok(Vec::new()) // TODO: Implement index introspection
+            indexes.push(IndexInfo {
+                name,
+                table_name,
+                column_name: options.get("target").cloned().unwrap_or_default(),
+                index_type,
+            });
+        }
+
+        Ok(indexes)
     }
 
-    /// Compare current schema with expected schema
+    /// Compare the live schema with the schema expected from applied migrations
     pub async fn detect_schema_drift(
         &self,
-        _expected_schema: &[TableInfo],
-    ) -> Result<Vec<String>, MigrationError> {
-        // Placeholder for schema drift detection
-        // This would compare the current database schema with what's expected
-        // based on the applied migrations
+        expected: &ExpectedSchema,
+    ) -> Result<Vec<SchemaDrift>, MigrationError> {
+        let tables = self.get_tables().await?;
+        let types = self.get_types().await?;
+        let views = self.get_views().await?;
+        let indexes = self.get_indexes().await?;
+
+        Ok(drift::compare(expected, &tables, &types, &views, &indexes))
+    }
+}
+
+/// Permissions granted to all roles, or to one role including those it inherits
+///
+/// Requires authorization to be enabled on the cluster.
+pub async fn list_permissions(
+    session: &Session,
+    role: Option<&str>,
+) -> Result<Vec<PermissionInfo>, MigrationError> {
+    let rows = session.query(permissions_query(role), &[]).await?;
+
+    let mut permissions = Vec::new();
+    for row in rows
+        .rows_typed::<(String, String, String, String)>()
+        .map_err(|e| MigrationError::IntegrityError(e.to_string()))?
+    {
+        let (role, _username, resource, permission) =
+            row.map_err(|e| MigrationError::IntegrityError(e.to_string()))?;
 
-        Ok(Vec::new()) // TODO: Implement drift detection
+        permissions.push(PermissionInfo {
+            role,
+            resource,
+            permission,
+        });
     }
+
+    Ok(permissions)
+}
+
+/// `LIST ALL PERMISSIONS`, of one role if given
+///
+/// Role names are matched exactly, like the usernames they log in with, so they
+/// are always quoted.
+fn permissions_query(role: Option<&str>) -> String {
+    match role {
+        Some(role) => format!("LIST ALL PERMISSIONS OF {}", quoted(role)),
+        None => "LIST ALL PERMISSIONS".to_string(),
+    }
+}
+
+/// Whether a role is a superuser, directly or through a role granted to it
+pub async fn is_superuser(session: &Session, role: &str) -> Result<bool, MigrationError> {
+    let rows = session.query(format!("LIST ROLES OF {}", quoted(role)), &[]).await?;
+
+    for row in rows
+        .rows_typed::<(String, bool, bool, HashMap<String, String>)>()
+        .map_err(|e| MigrationError::IntegrityError(e.to_string()))?
+    {
+        let (_role, is_super, _login, _options) =
+            row.map_err(|e| MigrationError::IntegrityError(e.to_string()))?;
+        if is_super {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+const STAMP_PREFIX: &str = "[db-migrate:";
+
+/// Replace (or append) the migration stamp in a table comment
+pub fn stamp_comment(comment: &str, version: &str) -> String {
+    let stamp = format!("{}{}]", STAMP_PREFIX, version);
+    let text = strip_stamp(comment);
+
+    if text.is_empty() {
+        stamp
+    } else {
+        format!("{} {}", text, stamp)
+    }
+}
+
+/// Migration version recorded in a table comment, if stamped
+pub fn comment_stamp(comment: &str) -> Option<&str> {
+    let start = comment.rfind(STAMP_PREFIX)? + STAMP_PREFIX.len();
+    let end = comment[start..].find(']')? + start;
+    Some(&comment[start..end])
+}
+
+fn strip_stamp(comment: &str) -> String {
+    match comment.rfind(STAMP_PREFIX) {
+        Some(start) => {
+            let end = comment[start..]
+                .find(']')
+                .map(|i| start + i + 1)
+                .unwrap_or(comment.len());
+            format!("{}{}", &comment[..start], &comment[end..])
+                .trim()
+                .to_string()
+        }
+        None => comment.trim().to_string(),
+    }
+}
+
+/// Rewrite a DESCRIBE statement so it targets another keyspace
+///
+/// Replaces the keyspace in `CREATE KEYSPACE <name>`, `USE <name>` and in every
+/// `<name>.` qualifier, leaving string literals and other identifiers untouched. Both names are given
+/// as stored in `system_schema`; `to` is quoted where its case requires it.
+pub fn retarget_keyspace(statement: &str, from: &str, to: &str) -> String {
+    let unquoted_to = if to.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_') {
+        to.to_string()
+    } else {
+        quoted(to)
+    };
+    let chars: Vec<char> = statement.chars().collect();
+    let mut output = String::with_capacity(statement.len());
+    let mut previous_word = String::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+
+        if c == '\'' || c == '"' {
+            let start = i;
+            i += 1;
+            while i < chars.len() {
+                if chars[i] == c && chars.get(i + 1) == Some(&c) {
+                    i += 2;
+                } else if chars[i] == c {
+                    i += 1;
+                    break;
+                } else {
+                    i += 1;
+                }
+            }
+
+            let token: String = chars[start..i].iter().collect();
+            if c == '"' && token.trim_matches('"') == from && is_keyspace_position(&chars, i, &previous_word) {
+                output.push_str(&format!("\"{}\"", to));
+            } else {
+                output.push_str(&token);
+            }
+            if c == '"' {
+                previous_word = token;
+            }
+            continue;
+        }
+
+        if c.is_alphanumeric() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+
+            let word: String = chars[start..i].iter().collect();
+            if word == from && is_keyspace_position(&chars, i, &previous_word) {
+                output.push_str(&unquoted_to);
+            } else {
+                output.push_str(&word);
+            }
+            previous_word = word;
+            continue;
+        }
+
+        output.push(c);
+        i += 1;
+    }
+
+    output
+}
+
+/// Whether the identifier ending at `end` names a keyspace
+fn is_keyspace_position(chars: &[char], end: usize, previous_word: &str) -> bool {
+    chars.get(end) == Some(&'.')
+        || previous_word.eq_ignore_ascii_case("KEYSPACE")
+        || previous_word.eq_ignore_ascii_case("USE")
 }
 
 // Future features that could be implemented:
 // - Full CQL schema parsing and comparison
-// - Detection of manual schema changes outside of migrations
 // - Schema validation against migration files
 // - Automatic schema documentation generation
 // - Schema export/import functionality
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_permissions_query() {
+        assert_eq!(permissions_query(None), "LIST ALL PERMISSIONS");
+        assert_eq!(permissions_query(Some("App_User")), "LIST ALL PERMISSIONS OF \"App_User\"");
+        assert_eq!(
+            permissions_query(Some("x\"; DROP KEYSPACE prod; --")),
+            "LIST ALL PERMISSIONS OF \"x\"\"; DROP KEYSPACE prod; --\""
+        );
+    }
+
+    #[test]
+    fn test_comment_stamps() {
+        assert_eq!(stamp_comment("", "20250101_001_a"), "[db-migrate:20250101_001_a]");
+
+        let stamped = stamp_comment("User accounts", "20250101_001_a");
+        assert_eq!(stamped, "User accounts [db-migrate:20250101_001_a]");
+        assert_eq!(comment_stamp(&stamped), Some("20250101_001_a"));
+
+        let restamped = stamp_comment(&stamped, "20250102_001_b");
+        assert_eq!(restamped, "User accounts [db-migrate:20250102_001_b]");
+        assert_eq!(comment_stamp("User accounts"), None);
+    }
+
+    #[test]
+    fn test_retarget_keyspace() {
+        assert_eq!(
+            retarget_keyspace(
+                "CREATE KEYSPACE prod WITH replication = {'class': 'NetworkTopologyStrategy', 'prod': '3'};",
+                "prod",
+                "staging"
+            ),
+            "CREATE KEYSPACE staging WITH replication = {'class': 'NetworkTopologyStrategy', 'prod': '3'};"
+        );
+        assert_eq!(
+            retarget_keyspace(
+                "CREATE TABLE prod.users (id uuid PRIMARY KEY, prod text, addr frozen<prod.address>)",
+                "prod",
+                "staging"
+            ),
+            "CREATE TABLE staging.users (id uuid PRIMARY KEY, prod text, addr frozen<staging.address>)"
+        );
+        assert_eq!(
+            retarget_keyspace("CREATE INDEX ON \"prod\".users (name)", "prod", "staging"),
+            "CREATE INDEX ON \"staging\".users (name)"
+        );
+        assert_eq!(
+            retarget_keyspace("CREATE TABLE \"MyApp\".users (id int PRIMARY KEY)", "MyApp", "MyApp_Copy"),
+            "CREATE TABLE \"MyApp_Copy\".users (id int PRIMARY KEY)"
+        );
+        assert_eq!(
+            retarget_keyspace("CREATE TABLE prod.users (id int PRIMARY KEY)", "prod", "Staging"),
+            "CREATE TABLE \"Staging\".users (id int PRIMARY KEY)"
+        );
+        assert_eq!(retarget_keyspace("USE prod;", "prod", "staging"), "USE staging;");
+    }
+}
@@ -0,0 +1,299 @@
+//! Migrations computed from two schema dumps (`create --from-diff`)
+//!
+//! Both dumps (e.g. `DESCRIBE KEYSPACE` output) are read with the offline CQL
+//! tokenizer. Tables and their columns, user-defined types and their fields,
+//! indexes and materialized views are compared by name; keyspace, function and
+//! other statements are ignored. New objects are created with their statement
+//! from the new dump, removed ones dropped, and columns and fields added or
+//! dropped with `ALTER`. Changes CQL cannot express in place, such as a new
+//! column type or primary key, are reported for the author to handle.
+
+use crate::{
+    cql::{self, Token},
+    drift::{normalize_identifier, normalize_type, split_qualified},
+    utils::split_cql_statements,
+};
+use std::collections::BTreeMap;
+
+/// A table of a dump
+#[derive(Debug, Clone)]
+struct DumpTable {
+    /// Name as written
+    name: String,
+    statement: String,
+    /// Columns as written, with their type as written and normalized
+    columns: Vec<(String, String, String)>,
+    primary_key: Option<String>,
+    options: String,
+}
+
+/// An index or materialized view of a dump
+#[derive(Debug, Clone)]
+struct DumpObject {
+    name: String,
+    statement: String,
+    normalized: String,
+}
+
+/// The objects of a schema dump, by normalized name
+#[derive(Debug, Clone, Default)]
+pub struct SchemaDump {
+    tables: BTreeMap<String, DumpTable>,
+    types: BTreeMap<String, DumpTable>,
+    indexes: BTreeMap<String, DumpObject>,
+    views: BTreeMap<String, DumpObject>,
+}
+
+/// Statements turning one schema into another
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SchemaChanges {
+    /// Statements in the order they must run, without trailing semicolons
+    pub statements: Vec<String>,
+    /// Differences that need a hand-written migration
+    pub unsupported: Vec<String>,
+}
+
+impl SchemaChanges {
+    pub fn is_empty(&self) -> bool {
+        self.statements.is_empty() && self.unsupported.is_empty()
+    }
+}
+
+/// Normalized form of a possibly keyspace-qualified name
+fn key(name: &str) -> String {
+    match split_qualified(name) {
+        (Some(keyspace), name) => format!("{}.{}", normalize_identifier(keyspace), normalize_identifier(name)),
+        (None, name) => normalize_identifier(name),
+    }
+}
+
+impl SchemaDump {
+    pub fn parse(content: &str) -> Result<Self, String> {
+        let mut dump = Self::default();
+        for statement in split_cql_statements(content) {
+            let tokens = cql::tokenize(&statement).map_err(|e| format!("{}: {}", e, statement.trim()))?;
+            let keyword = |i: usize, k: &str| tokens.get(i).is_some_and(|t| t.is_keyword(k));
+            if !keyword(0, "CREATE") {
+                continue;
+            }
+
+            if keyword(1, "TABLE") || keyword(1, "TYPE") {
+                let Some(Token::Word(name)) = cql::skip_if_exists(&tokens, 2).and_then(|i| tokens.get(i)) else {
+                    continue;
+                };
+                let table = DumpTable {
+                    name: name.clone(),
+                    columns: cql::declared_columns(&tokens)
+                        .into_iter()
+                        .map(|(column, type_tokens)| (column, cql::type_text(&type_tokens), normalize_type(&type_tokens)))
+                        .collect(),
+                    primary_key: cql::primary_key(&tokens),
+                    options: cql::table_options(&tokens),
+                    statement: statement.trim().to_string(),
+                };
+                let objects = if keyword(1, "TABLE") { &mut dump.tables } else { &mut dump.types };
+                objects.insert(key(name), table);
+            } else if keyword(1, "MATERIALIZED") && keyword(2, "VIEW") {
+                if let Some(Token::Word(name)) = cql::skip_if_exists(&tokens, 3).and_then(|i| tokens.get(i)) {
+                    dump.views.insert(key(name), object(name, &statement, &tokens));
+                }
+            } else if keyword(1, "INDEX") || (keyword(1, "CUSTOM") && keyword(2, "INDEX")) {
+                if let Some((key, name)) = index_name(&tokens) {
+                    dump.indexes.insert(key, object(&name, &statement, &tokens));
+                }
+            }
+        }
+        Ok(dump)
+    }
+
+    /// Statements turning `self` into `new`
+    pub fn diff(&self, new: &SchemaDump) -> SchemaChanges {
+        let mut changes = SchemaChanges::default();
+        let statements = &mut changes.statements;
+
+        // Views and indexes go first, as they may depend on what is dropped next,
+        // and are recreated last
+        let changed = |old: &DumpObject, new: &DumpObject| old.normalized != new.normalized;
+        for (kind, old_objects, new_objects) in [
+            ("MATERIALIZED VIEW", &self.views, &new.views),
+            ("INDEX", &self.indexes, &new.indexes),
+        ] {
+            for (key, object) in old_objects {
+                if new_objects.get(key).is_none_or(|new| changed(object, new)) {
+                    statements.push(format!("DROP {} IF EXISTS {}", kind, object.name));
+                }
+            }
+        }
+
+        for (key, new_type) in &new.types {
+            match self.types.get(key) {
+                None => statements.push(new_type.statement.clone()),
+                Some(old_type) => {
+                    for (field, data_type, normalized) in &new_type.columns {
+                        match old_type.columns.iter().find(|(old, _, _)| normalize_identifier(old) == normalize_identifier(field)) {
+                            None => statements.push(format!("ALTER TYPE {} ADD {} {}", new_type.name, field, data_type)),
+                            Some((_, _, old)) if old != normalized => changes.unsupported.push(format!(
+                                "field {} of type {} changes from {} to {}",
+                                field, new_type.name, old, normalized
+                            )),
+                            Some(_) => {}
+                        }
+                    }
+                    for (field, _, _) in &old_type.columns {
+                        if !new_type.columns.iter().any(|(new, _, _)| normalize_identifier(new) == normalize_identifier(field)) {
+                            changes.unsupported.push(format!(
+                                "field {} of type {} is removed; fields of a type cannot be dropped",
+                                field, new_type.name
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+
+        for (key, new_table) in &new.tables {
+            let Some(old_table) = self.tables.get(key) else {
+                statements.push(new_table.statement.clone());
+                continue;
+            };
+            if old_table.primary_key != new_table.primary_key {
+                changes.unsupported.push(format!(
+                    "primary key of {} changes from {} to {}; create a new table and copy the data",
+                    new_table.name,
+                    old_table.primary_key.as_deref().unwrap_or("none"),
+                    new_table.primary_key.as_deref().unwrap_or("none")
+                ));
+                continue;
+            }
+            let find = |columns: &[(String, String, String)], column: &str| {
+                columns
+                    .iter()
+                    .find(|(name, _, _)| normalize_identifier(name) == normalize_identifier(column))
+                    .cloned()
+            };
+            for (column, data_type, normalized) in &new_table.columns {
+                match find(&old_table.columns, column) {
+                    None => statements.push(format!("ALTER TABLE {} ADD {} {}", new_table.name, column, data_type)),
+                    Some((_, _, old)) if old != *normalized => changes.unsupported.push(format!(
+                        "column {} of {} changes type from {} to {}",
+                        column, new_table.name, old, normalized
+                    )),
+                    Some(_) => {}
+                }
+            }
+            for (column, _, _) in &old_table.columns {
+                if find(&new_table.columns, column).is_none() {
+                    statements.push(format!("ALTER TABLE {} DROP {}", new_table.name, column));
+                }
+            }
+            if old_table.options != new_table.options {
+                changes.unsupported.push(format!(
+                    "options of {} change; compare the WITH clauses of both dumps",
+                    new_table.name
+                ));
+            }
+        }
+
+        for (kind, old_objects, new_objects) in [("TABLE", &self.tables, &new.tables), ("TYPE", &self.types, &new.types)] {
+            for (key, object) in old_objects {
+                if !new_objects.contains_key(key) {
+                    statements.push(format!("DROP {} IF EXISTS {}", kind, object.name));
+                }
+            }
+        }
+
+        for (old_objects, new_objects) in [(&self.indexes, &new.indexes), (&self.views, &new.views)] {
+            for (key, object) in new_objects {
+                if old_objects.get(key).is_none_or(|old| changed(old, object)) {
+                    statements.push(object.statement.clone());
+                }
+            }
+        }
+
+        changes
+    }
+}
+
+fn object(name: &str, statement: &str, tokens: &[Token]) -> DumpObject {
+    DumpObject {
+        name: name.to_string(),
+        statement: statement.trim().to_string(),
+        normalized: cql::normalized(tokens),
+    }
+}
+
+/// Key and droppable name of an index; unnamed indexes get the server default
+/// `<table>_<column>_idx`
+fn index_name(tokens: &[Token]) -> Option<(String, String)> {
+    let on = tokens.iter().position(|t| t.is_keyword("ON"))?;
+    let Some(Token::Word(table)) = tokens.get(on + 1) else {
+        return None;
+    };
+    let (keyspace, table_name) = split_qualified(table);
+    let index_keyword = if tokens[1].is_keyword("CUSTOM") { 2 } else { 1 };
+    let name = match cql::skip_if_exists(tokens, index_keyword + 1) {
+        Some(i) if i < on => match &tokens[i] {
+            Token::Word(name) => name.clone(),
+            _ => return None,
+        },
+        _ => {
+            let column = tokens[on + 2..].iter().find_map(|t| match t {
+                Token::Word(w) if !["keys", "values", "entries", "full"].iter().any(|k| w.eq_ignore_ascii_case(k)) => {
+                    Some(w)
+                }
+                _ => None,
+            })?;
+            format!("{}_{}_idx", normalize_identifier(table_name), normalize_identifier(column))
+        }
+    };
+    let name = match keyspace {
+        Some(keyspace) => format!("{}.{}", keyspace, name),
+        None => name,
+    };
+    Some((key(&name), name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_dumps() {
+        let old = SchemaDump::parse(
+            "CREATE KEYSPACE app WITH replication = {'class': 'SimpleStrategy', 'replication_factor': 1};
+             CREATE TYPE app.address (street text);
+             CREATE TABLE app.users (id uuid PRIMARY KEY, email text, legacy int) WITH comment = 'users';
+             CREATE INDEX users_email_idx ON app.users (email);
+             CREATE TABLE app.sessions (id uuid PRIMARY KEY);",
+        )
+        .unwrap();
+        let new = SchemaDump::parse(
+            "CREATE TYPE app.address (street text, city text);
+             CREATE TABLE app.users (id uuid, email text, home frozen<address>, PRIMARY KEY (id)) WITH comment = 'users';
+             CREATE TABLE app.orders (id uuid, user_id uuid, PRIMARY KEY ((user_id), id));
+             CREATE INDEX ON app.orders (user_id);",
+        )
+        .unwrap();
+
+        let changes = old.diff(&new);
+        assert_eq!(
+            changes.statements,
+            vec![
+                "DROP INDEX IF EXISTS app.users_email_idx",
+                "ALTER TYPE app.address ADD city text",
+                "CREATE TABLE app.orders (id uuid, user_id uuid, PRIMARY KEY ((user_id), id))",
+                "ALTER TABLE app.users ADD home frozen<address>",
+                "ALTER TABLE app.users DROP legacy",
+                "DROP TABLE IF EXISTS app.sessions",
+                "CREATE INDEX ON app.orders (user_id)",
+            ]
+        );
+        assert!(changes.unsupported.is_empty());
+        assert!(new.diff(&new).is_empty());
+
+        let rekeyed = SchemaDump::parse("CREATE TABLE app.users (id uuid, email text, PRIMARY KEY (email, id));").unwrap();
+        let changes = old.diff(&rekeyed);
+        assert_eq!(changes.unsupported.len(), 1);
+        assert!(changes.unsupported[0].starts_with("primary key of app.users changes from id to (email, id)"));
+    }
+}
@@ -0,0 +1,184 @@
+//! Throwaway keyspaces that `sandbox` and `bench` apply migrations to
+//!
+//! Migrations are written against `database.keyspace`, sometimes naming it
+//! explicitly: a `keyspace` directive, `USE`, or keyspace-qualified names.
+//! [`retarget_migration`] rewrites those references to the scratch keyspace, and
+//! refuses migrations that reach into any other keyspace, so replaying them can
+//! never touch real data.
+
+use crate::{
+    cql::{self, Token},
+    directives::Directives,
+    drift::normalize_identifier,
+    schema::retarget_keyspace,
+    utils::split_cql_statements,
+    MigrationError, MigrationFile,
+};
+
+/// Unique keyspace name: <prefix>_<timestamp>_<suffix>, within the 48 character limit
+///
+/// The prefix must be an unquoted identifier; a long one is shortened so the
+/// timestamp and suffix, which keep concurrent runs apart, are always kept.
+pub fn keyspace_name(prefix: &str) -> Result<String, MigrationError> {
+    if prefix.starts_with('"') {
+        return Err(MigrationError::ConfigError(format!(
+            "Invalid keyspace prefix '{}': quoted names are not supported",
+            prefix
+        )));
+    }
+    cql::validate_identifier(prefix)
+        .map_err(|reason| MigrationError::ConfigError(format!("Invalid keyspace prefix: {}", reason)))?;
+
+    let now = chrono::Utc::now();
+    let suffix = (now.timestamp_subsec_nanos() ^ std::process::id()) & 0xffff;
+    let unique = format!("_{}_{:04x}", now.format("%Y%m%d%H%M%S"), suffix);
+    let prefix: String = prefix.chars().take(cql::MAX_IDENTIFIER_LEN - unique.len()).collect();
+    Ok(format!("{}{}", prefix, unique).to_lowercase())
+}
+
+/// `migration` with every reference to keyspace `from` pointed at `to`
+///
+/// Both names are given as written in configuration. Migrations that use any
+/// other keyspace are refused, since the scratch keyspace cannot stand in for it.
+pub fn retarget_migration(
+    migration: &MigrationFile,
+    from: &str,
+    to: &str,
+) -> Result<MigrationFile, MigrationError> {
+    let from = normalize_identifier(from);
+    let to = normalize_identifier(to);
+
+    // Streamed files only hold their directives in memory
+    let content = if migration.streamed {
+        std::fs::read_to_string(&migration.file_path)?
+    } else {
+        migration.content.clone()
+    };
+    let content = retarget_keyspace(&content, &from, &to);
+    let directives = Directives::parse(&content);
+
+    let foreign = directives
+        .keyspace
+        .iter()
+        .cloned()
+        .chain(split_cql_statements(&content).iter().flat_map(|s| referenced_keyspaces(s)))
+        .find(|keyspace| normalize_identifier(keyspace) != to);
+    if let Some(keyspace) = foreign {
+        return Err(MigrationError::InvalidFormat(format!(
+            "Migration {} uses keyspace {}, which a scratch keyspace cannot stand in for; only {} is replayed",
+            migration.version, keyspace, from
+        )));
+    }
+
+    Ok(MigrationFile {
+        content,
+        directives,
+        streamed: false,
+        ..migration.clone()
+    })
+}
+
+/// Keyspaces a statement names: `USE`, `... KEYSPACE <name>` and qualified names
+fn referenced_keyspaces(statement: &str) -> Vec<String> {
+    let Ok(tokens) = cql::tokenize(statement) else {
+        return Vec::new();
+    };
+    let mut keyspaces = Vec::new();
+    if let Some(keyspace) = cql::used_keyspace(statement) {
+        keyspaces.push(keyspace);
+    }
+    for (i, token) in tokens.iter().enumerate() {
+        if token.is_keyword("KEYSPACE") {
+            let name = cql::skip_if_exists(&tokens, i + 1).and_then(|at| tokens.get(at));
+            if let Some(Token::Word(name)) = name {
+                keyspaces.push(name.clone());
+            }
+        } else if let Token::Word(word) = token {
+            if let Some((keyspace, _)) = split_qualified(word) {
+                keyspaces.push(keyspace.to_string());
+            }
+        }
+    }
+    keyspaces
+}
+
+/// `keyspace.name` split at the dot outside quotes; numbers like `1.5` are not names
+fn split_qualified(word: &str) -> Option<(&str, &str)> {
+    if !word.starts_with(|c: char| c == '"' || c.is_alphabetic()) {
+        return None;
+    }
+    let mut in_quotes = false;
+    for (i, c) in word.char_indices() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            '.' if !in_quotes => return Some((&word[..i], &word[i + 1..])),
+            _ => {}
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keyspace_name() {
+        let name = keyspace_name("Sandbox").unwrap();
+        assert!(name.starts_with("sandbox_"));
+        assert!(cql::validate_identifier(&name).is_ok());
+
+        // Long prefixes are cut, never the unique part
+        let long = "a".repeat(40);
+        let name = keyspace_name(&long).unwrap();
+        assert_eq!(name.len(), cql::MAX_IDENTIFIER_LEN);
+        let (prefix, unique) = name.split_at(28);
+        assert_eq!(prefix, "a".repeat(28));
+        assert_eq!(unique.len(), 20);
+        assert!(unique[1..15].chars().all(|c| c.is_ascii_digit()));
+
+        for invalid in ["", "my-app", "1st", "\"Sandbox\"", "app; DROP KEYSPACE prod"] {
+            assert!(keyspace_name(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    fn migration(content: &str) -> MigrationFile {
+        MigrationFile::for_test("20250101_001_test", content)
+    }
+
+    #[test]
+    fn test_retarget_migration() {
+        let original = migration(
+            "-- db-migrate:keyspace=app\n-- +migrate Up\nCREATE TABLE app.users (id int PRIMARY KEY, score double);\nUSE app;\nINSERT INTO users (id, score) VALUES (1, 1.5);\n-- +migrate Down\nDROP TABLE app.users;\n",
+        );
+
+        let retargeted = retarget_migration(&original, "app", "sandbox_1").unwrap();
+        assert_eq!(retargeted.directives.keyspace.as_deref(), Some("sandbox_1"));
+        assert!(retargeted.content.contains("CREATE TABLE sandbox_1.users"));
+        assert!(retargeted.content.contains("USE sandbox_1;"));
+        assert!(retargeted.content.contains("DROP TABLE sandbox_1.users"));
+        assert!(!retargeted.content.contains("app."));
+    }
+
+    #[test]
+    fn test_retarget_migration_refuses_other_keyspaces() {
+        let cases = [
+            "CREATE TABLE analytics.events (id int PRIMARY KEY);",
+            "USE analytics;",
+            "-- db-migrate:keyspace=analytics\nCREATE TABLE events (id int PRIMARY KEY);",
+            "ALTER KEYSPACE analytics WITH durable_writes = true;",
+        ];
+        for content in cases {
+            let e = retarget_migration(&migration(content), "app", "sandbox_1").unwrap_err();
+            assert!(e.to_string().contains("analytics"), "{}", content);
+        }
+    }
+
+    #[test]
+    fn test_split_qualified() {
+        assert_eq!(split_qualified("app.users"), Some(("app", "users")));
+        assert_eq!(split_qualified("\"My.App\".users"), Some(("\"My.App\"", "users")));
+        assert_eq!(split_qualified("users"), None);
+        assert_eq!(split_qualified("1.5"), None);
+    }
+}
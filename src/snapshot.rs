@@ -0,0 +1,320 @@
+use crate::{lint::Rule, version::VersionScheme, MigrationError, MigrationFile, MigrationRecord};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use tokio::fs;
+
+/// Committed record of which migrations are applied, used for offline checks
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppliedSnapshot {
+    pub generated_at: DateTime<Utc>,
+    pub keyspace: String,
+    /// Applied version -> checksum
+    pub migrations: BTreeMap<String, String>,
+    /// Applied version -> checksum without the DOWN section, for records that
+    /// have one
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub up_checksums: BTreeMap<String, String>,
+}
+
+/// How a migration file differs from the snapshot of its applied version
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AppliedEdit {
+    Unchanged,
+    /// Only the DOWN section was edited
+    DownOnly,
+    Modified,
+}
+
+/// Rules of `hooks check` comparing migration files with the snapshot
+pub const SNAPSHOT_RULES: &[Rule] = &[
+    Rule {
+        id: "applied-deleted",
+        description: "The file of a migration recorded as applied in the snapshot was deleted",
+        help_uri: "https://github.com/dimuziop/db-migrate#hooks-installchecksnapshot",
+    },
+    Rule {
+        id: "applied-modified",
+        description: "The UP section of a migration recorded as applied in the snapshot was modified",
+        help_uri: "https://github.com/dimuziop/db-migrate#hooks-installchecksnapshot",
+    },
+    Rule {
+        id: "applied-down-modified",
+        description: "Only the DOWN section of a migration recorded as applied in the snapshot was modified",
+        help_uri: "https://github.com/dimuziop/db-migrate#hooks-installchecksnapshot",
+    },
+];
+
+impl AppliedSnapshot {
+    pub fn from_records(keyspace: &str, records: &[MigrationRecord]) -> Self {
+        Self {
+            generated_at: Utc::now(),
+            keyspace: keyspace.to_string(),
+            migrations: records
+                .iter()
+                .map(|r| (r.version.clone(), r.checksum.clone()))
+                .collect(),
+            up_checksums: records
+                .iter()
+                .filter_map(|r| Some((r.version.clone(), r.up_checksum.clone()?)))
+                .collect(),
+        }
+    }
+
+    /// How `file` was edited since its version was applied; `None` when it is not
+    /// in the snapshot
+    pub fn edit_of(&self, file: &MigrationFile) -> Option<AppliedEdit> {
+        let checksum = self.migrations.get(&file.version)?;
+        Some(if *checksum == file.checksum {
+            AppliedEdit::Unchanged
+        } else if self.up_checksums.get(&file.version) == Some(&file.up_checksum()) {
+            AppliedEdit::DownOnly
+        } else {
+            AppliedEdit::Modified
+        })
+    }
+
+    pub async fn load(path: &Path) -> Result<Self, MigrationError> {
+        let content = fs::read_to_string(path).await?;
+        serde_json::from_str(&content).map_err(|e| {
+            MigrationError::ConfigError(format!("Invalid snapshot file '{}': {}", path.display(), e))
+        })
+    }
+
+    pub async fn save(&self, path: &Path) -> Result<(), MigrationError> {
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| MigrationError::ConfigError(e.to_string()))?;
+        fs::write(path, content + "\n").await?;
+        Ok(())
+    }
+}
+
+/// Version of the status snapshot layout, bumped on incompatible changes
+pub const STATUS_SNAPSHOT_FORMAT: u32 = 1;
+
+/// Applied and pending migrations of an environment, as `status --export` writes it
+///
+/// Migrations are kept in version order and nothing depends on when the snapshot
+/// was taken, so an unchanged environment exports byte-identical files.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StatusSnapshot {
+    pub format: u32,
+    pub cluster_name: Option<String>,
+    pub keyspace: String,
+    pub environment: Option<String>,
+    pub migrations: Vec<SnapshotMigration>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SnapshotState {
+    Applied,
+    Pending,
+}
+
+impl SnapshotState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Applied => "applied",
+            Self::Pending => "pending",
+        }
+    }
+}
+
+/// One migration of a [`StatusSnapshot`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotMigration {
+    pub version: String,
+    pub state: SnapshotState,
+    pub description: String,
+    pub keyspace: String,
+    pub checksum: String,
+    #[serde(default)]
+    pub applied_at: Option<DateTime<Utc>>,
+}
+
+/// A migration present in both snapshots with different values
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SnapshotChange {
+    pub version: String,
+    /// Names of the fields that differ
+    pub fields: Vec<&'static str>,
+    pub before: SnapshotMigration,
+    pub after: SnapshotMigration,
+}
+
+/// Differences between a stored snapshot and another one, usually the live state
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SnapshotDiff {
+    pub added: Vec<SnapshotMigration>,
+    pub removed: Vec<SnapshotMigration>,
+    pub changed: Vec<SnapshotChange>,
+}
+
+impl SnapshotDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+impl StatusSnapshot {
+    pub fn new(
+        cluster_name: Option<String>,
+        keyspace: &str,
+        environment: Option<String>,
+        mut migrations: Vec<SnapshotMigration>,
+        scheme: &VersionScheme,
+    ) -> Self {
+        migrations.sort_by(|a, b| scheme.compare(&a.version, &b.version));
+        Self {
+            format: STATUS_SNAPSHOT_FORMAT,
+            cluster_name,
+            keyspace: keyspace.to_string(),
+            environment,
+            migrations,
+        }
+    }
+
+    pub async fn load(path: &Path) -> Result<Self, MigrationError> {
+        let content = fs::read_to_string(path).await?;
+        let snapshot: Self = serde_json::from_str(&content).map_err(|e| {
+            MigrationError::InvalidFormat(format!("{} is not a status snapshot: {}", path.display(), e))
+        })?;
+        if snapshot.format != STATUS_SNAPSHOT_FORMAT {
+            return Err(MigrationError::InvalidFormat(format!(
+                "{} has snapshot format {}, this version of db-migrate reads format {}",
+                path.display(),
+                snapshot.format,
+                STATUS_SNAPSHOT_FORMAT
+            )));
+        }
+        Ok(snapshot)
+    }
+
+    pub async fn save(&self, path: &Path) -> Result<(), MigrationError> {
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| MigrationError::ConfigError(e.to_string()))?;
+        fs::write(path, content + "\n").await?;
+        Ok(())
+    }
+
+    /// What changed from `self` to `other`
+    ///
+    /// `applied_at` is not compared, so snapshots of two environments that
+    /// applied the same migrations at different times are equal.
+    pub fn diff(&self, other: &Self) -> SnapshotDiff {
+        let before: BTreeMap<&str, &SnapshotMigration> =
+            self.migrations.iter().map(|m| (m.version.as_str(), m)).collect();
+        let after: BTreeMap<&str, &SnapshotMigration> =
+            other.migrations.iter().map(|m| (m.version.as_str(), m)).collect();
+
+        let mut diff = SnapshotDiff::default();
+        for migration in &other.migrations {
+            let Some(previous) = before.get(migration.version.as_str()) else {
+                diff.added.push(migration.clone());
+                continue;
+            };
+            let fields: Vec<&'static str> = [
+                ("state", previous.state != migration.state),
+                ("checksum", previous.checksum != migration.checksum),
+                ("description", previous.description != migration.description),
+                ("keyspace", previous.keyspace != migration.keyspace),
+            ]
+            .into_iter()
+            .filter_map(|(field, differs)| differs.then_some(field))
+            .collect();
+            if !fields.is_empty() {
+                diff.changed.push(SnapshotChange {
+                    version: migration.version.clone(),
+                    fields,
+                    before: (*previous).clone(),
+                    after: migration.clone(),
+                });
+            }
+        }
+        diff.removed = self
+            .migrations
+            .iter()
+            .filter(|m| !after.contains_key(m.version.as_str()))
+            .cloned()
+            .collect();
+        diff
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn migration(version: &str, state: SnapshotState, checksum: &str) -> SnapshotMigration {
+        SnapshotMigration {
+            version: version.to_string(),
+            state,
+            description: "add users".to_string(),
+            keyspace: "app".to_string(),
+            checksum: checksum.to_string(),
+            applied_at: None,
+        }
+    }
+
+    #[test]
+    fn test_applied_snapshot_edit_of() {
+        let original = "-- +migrate Up\nCREATE TABLE a (id int PRIMARY KEY);\n-- +migrate Down\nDROP TABLE a;\n";
+        let file = MigrationFile::for_test("20250115_001_a", original);
+        let mut record = MigrationRecord::for_test("20250115_001_a", Utc::now());
+        record.checksum = file.checksum.clone();
+        record.up_checksum = Some(file.up_checksum());
+        let snapshot = AppliedSnapshot::from_records("app", &[record.clone()]);
+
+        assert_eq!(snapshot.edit_of(&file), Some(AppliedEdit::Unchanged));
+        let down_fixed = MigrationFile::for_test("20250115_001_a", &original.replace("DROP TABLE a", "DROP TABLE IF EXISTS a"));
+        assert_eq!(snapshot.edit_of(&down_fixed), Some(AppliedEdit::DownOnly));
+        let up_changed = MigrationFile::for_test("20250115_001_a", &original.replace("id int", "id bigint"));
+        assert_eq!(snapshot.edit_of(&up_changed), Some(AppliedEdit::Modified));
+        assert_eq!(snapshot.edit_of(&MigrationFile::for_test("20250116_001_b", original)), None);
+
+        // Records without a DOWN-less checksum cannot tell the two apart
+        record.up_checksum = None;
+        let snapshot = AppliedSnapshot::from_records("app", &[record]);
+        assert_eq!(snapshot.edit_of(&down_fixed), Some(AppliedEdit::Modified));
+    }
+
+    #[test]
+    fn test_status_snapshot_diff() {
+        let stored = StatusSnapshot::new(
+            None,
+            "app",
+            None,
+            vec![
+                migration("20250115_002_b", SnapshotState::Pending, "b"),
+                migration("20250115_001_a", SnapshotState::Applied, "a"),
+                migration("20250115_003_c", SnapshotState::Pending, "c"),
+            ],
+            &VersionScheme::Timestamp,
+        );
+        assert_eq!(stored.migrations[0].version, "20250115_001_a");
+        assert!(stored.diff(&stored).is_empty());
+
+        let mut applied = migration("20250115_002_b", SnapshotState::Applied, "b2");
+        applied.applied_at = Some(Utc::now());
+        let live = StatusSnapshot::new(
+            None,
+            "app",
+            None,
+            vec![
+                migration("20250115_001_a", SnapshotState::Applied, "a"),
+                applied,
+                migration("20250116_001_d", SnapshotState::Pending, "d"),
+            ],
+            &VersionScheme::Timestamp,
+        );
+        let diff = stored.diff(&live);
+        assert_eq!(diff.added.len(), 1);
+        assert_eq!(diff.added[0].version, "20250116_001_d");
+        assert_eq!(diff.removed.len(), 1);
+        assert_eq!(diff.removed[0].version, "20250115_003_c");
+        assert_eq!(diff.changed.len(), 1);
+        assert_eq!(diff.changed[0].fields, vec!["state", "checksum"]);
+    }
+}
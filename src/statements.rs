@@ -0,0 +1,344 @@
+//! Splitting migration files into sections and statements
+//!
+//! A statement ends at a `;` outside string literals (`'...'`, `$$...$$`),
+//! quoted identifiers and comments. Section markers (`-- +migrate Up`,
+//! `-- +migrate Down`) only count at the top level: a marker inside a block
+//! comment, a multi-line string or a statement block is ordinary text.
+//!
+//! Constructs that must not be split at all go in a statement block, which is
+//! executed as one statement without its trailing `;`:
+//!
+//! ```sql
+//! -- +migrate StatementBegin
+//! CREATE FUNCTION ...;
+//! -- +migrate StatementEnd
+//! ```
+//!
+//! `-- db-migrate:statement-begin` and `-- db-migrate:statement-end` are
+//! accepted as well.
+
+use crate::stream::Section;
+
+const BLOCK_BEGIN: &[&str] = &["-- +migrate StatementBegin", "-- db-migrate:statement-begin"];
+const BLOCK_END: &[&str] = &["-- +migrate StatementEnd", "-- db-migrate:statement-end"];
+
+/// Whether a trimmed line opens a statement block
+pub fn is_block_begin(line: &str) -> bool {
+    BLOCK_BEGIN.iter().any(|marker| line.starts_with(marker))
+}
+
+/// Whether a trimmed line closes a statement block
+pub fn is_block_end(line: &str) -> bool {
+    BLOCK_END.iter().any(|marker| line.starts_with(marker))
+}
+
+/// One statement and the 1-based lines it spans
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Statement {
+    pub text: String,
+    pub first_line: usize,
+    pub last_line: usize,
+}
+
+/// Where the scanner is, lexically, at the end of a line
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum Lexical {
+    #[default]
+    Code,
+    BlockComment,
+    /// Inside a `'` string or a `"` identifier
+    Quoted(char),
+    DollarQuoted,
+}
+
+/// Call `on_text` with the byte offset of every character of `line` outside
+/// comments, and whether it is code rather than part of a string or quoted
+/// identifier
+fn scan(lexical: &mut Lexical, line: &str, mut on_text: impl FnMut(usize, char, bool)) {
+    let mut chars = line.char_indices().peekable();
+    while let Some((offset, c)) = chars.next() {
+        let next = chars.peek().map(|(_, next)| *next);
+        match *lexical {
+            Lexical::Code => match (c, next) {
+                ('-', Some('-')) | ('/', Some('/')) => return,
+                ('/', Some('*')) => {
+                    chars.next();
+                    *lexical = Lexical::BlockComment;
+                }
+                ('$', Some('$')) => {
+                    chars.next();
+                    on_text(offset, c, false);
+                    *lexical = Lexical::DollarQuoted;
+                }
+                ('\'' | '"', _) => {
+                    on_text(offset, c, false);
+                    *lexical = Lexical::Quoted(c);
+                }
+                _ => on_text(offset, c, true),
+            },
+            Lexical::BlockComment => {
+                if c == '*' && next == Some('/') {
+                    chars.next();
+                    *lexical = Lexical::Code;
+                }
+            }
+            Lexical::DollarQuoted => {
+                on_text(offset, c, false);
+                if c == '$' && next == Some('$') {
+                    chars.next();
+                    *lexical = Lexical::Code;
+                }
+            }
+            // A doubled quote closes and immediately reopens, which is the same
+            Lexical::Quoted(quote) => {
+                on_text(offset, c, false);
+                if c == quote {
+                    *lexical = Lexical::Code;
+                }
+            }
+        }
+    }
+}
+
+/// Whether a fragment holds anything but whitespace and comments
+pub fn has_code(fragment: &str) -> bool {
+    let mut lexical = Lexical::Code;
+    let mut found = false;
+    for line in fragment.lines() {
+        scan(&mut lexical, line, |_, c, _| found |= !c.is_whitespace());
+    }
+    found
+}
+
+/// Splits lines of one section into statements as they arrive
+#[derive(Debug, Default)]
+pub struct StatementSplitter {
+    lexical: Lexical,
+    /// First line of the open statement block
+    block: Option<usize>,
+    fragment: String,
+    span: Option<(usize, usize)>,
+}
+
+impl StatementSplitter {
+    /// Whether the next line starts outside any comment, string or statement
+    /// block, where section markers count
+    pub fn at_top_level(&self) -> bool {
+        self.lexical == Lexical::Code && self.block.is_none()
+    }
+
+    /// First line of a statement block that was never closed
+    pub fn unterminated_block(&self) -> Option<usize> {
+        self.block
+    }
+
+    /// Feed the line numbered `number`, returning the statements it completes
+    pub fn push_line(&mut self, number: usize, line: &str) -> Vec<Statement> {
+        let mut statements = Vec::new();
+        let trimmed = line.trim();
+
+        if self.block.is_some() && self.lexical == Lexical::Code && is_block_end(trimmed) {
+            self.block = None;
+            let fragment = std::mem::take(&mut self.fragment);
+            let text = fragment.trim();
+            let text = text.strip_suffix(';').unwrap_or(text).trim_end();
+            if let (true, Some((first_line, last_line))) = (has_code(text), self.span.take()) {
+                statements.push(Statement { text: text.to_string(), first_line, last_line });
+            }
+            return statements;
+        }
+        if self.at_top_level() && is_block_begin(trimmed) {
+            // Comments above the block, such as `only-env`, stay with it
+            if self.span.is_some() {
+                statements.extend(self.finish());
+            }
+            self.block = Some(number);
+            return statements;
+        }
+
+        let in_block = self.block.is_some();
+        let mut text = Vec::new();
+        let mut ends = Vec::new();
+        scan(&mut self.lexical, line, |offset, c, code| {
+            if c == ';' && code && !in_block {
+                ends.push(offset);
+            } else if !c.is_whitespace() {
+                text.push(offset);
+            }
+        });
+
+        let mut start = 0;
+        for end in ends {
+            self.append(number, &line[start..end], text.iter().any(|offset| (start..end).contains(offset)));
+            statements.extend(self.finish());
+            start = end + 1;
+        }
+        self.append(number, &line[start..], text.iter().any(|offset| *offset >= start));
+        self.fragment.push('\n');
+        statements
+    }
+
+    /// The statement left without a terminating `;`, if any
+    pub fn finish(&mut self) -> Option<Statement> {
+        let fragment = std::mem::take(&mut self.fragment);
+        let (first_line, last_line) = self.span.take()?;
+        Some(Statement {
+            text: fragment.trim().to_string(),
+            first_line,
+            last_line,
+        })
+    }
+
+    /// Forget the statement read so far, keeping only the lexical state
+    fn discard(&mut self) {
+        self.fragment.clear();
+        self.span = None;
+    }
+
+    fn append(&mut self, number: usize, text: &str, has_code: bool) {
+        self.fragment.push_str(text);
+        if has_code {
+            self.span = Some((self.span.map_or(number, |(first, _)| first), number));
+        }
+    }
+}
+
+/// Tracks which section each line of a migration file belongs to
+#[derive(Debug, Default)]
+pub struct SectionTracker {
+    current: Option<Section>,
+    /// Only used for its lexical state, to tell markers from text
+    splitter: StatementSplitter,
+}
+
+/// What a line of a migration file is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Line {
+    /// A marker starting a section
+    Marker(Section),
+    /// Part of a section
+    In(Section),
+    /// A comment or blank line before the first section
+    Header,
+}
+
+impl SectionTracker {
+    /// Classify the next line of the file
+    pub fn push_line(&mut self, line: &str) -> Line {
+        let trimmed = line.trim();
+        if self.splitter.at_top_level() {
+            if let Some(section) = Section::of_marker(trimmed) {
+                self.current = Some(section);
+                return Line::Marker(section);
+            }
+        }
+        self.splitter.push_line(0, line);
+        self.splitter.discard();
+
+        match self.current {
+            Some(section) => Line::In(section),
+            // Leading comments and blank lines belong to no section; anything
+            // else before a marker is UP
+            None if trimmed.is_empty() || trimmed.starts_with("--") => Line::Header,
+            None => {
+                self.current = Some(Section::Up);
+                Line::In(Section::Up)
+            }
+        }
+    }
+
+    /// First line of a statement block that was never closed
+    pub fn unterminated_block(&self) -> Option<usize> {
+        self.splitter.unterminated_block()
+    }
+}
+
+/// Split the content of one section into statements
+pub fn split(content: &str) -> Vec<Statement> {
+    let mut splitter = StatementSplitter::default();
+    let mut statements = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        statements.extend(splitter.push_line(index + 1, line));
+    }
+    statements.extend(splitter.finish());
+    statements
+}
+
+/// Statements of one section of a whole migration file, with their line numbers
+/// in the file
+pub fn split_section(content: &str, section: Section) -> Vec<Statement> {
+    let mut tracker = SectionTracker::default();
+    let mut splitter = StatementSplitter::default();
+    let mut statements = Vec::new();
+
+    for (index, line) in content.lines().enumerate() {
+        match tracker.push_line(line) {
+            Line::Marker(_) => statements.extend(splitter.finish()),
+            Line::In(current) if current == section => {
+                statements.extend(splitter.push_line(index + 1, line));
+            }
+            _ => {}
+        }
+    }
+    statements.extend(splitter.finish());
+    statements
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::parse_migration_content;
+
+    #[test]
+    fn test_split_statements() {
+        let content = r#"-- +migrate Up
+/* Example, not applied:
+-- +migrate Down
+DROP TABLE users;
+*/
+CREATE TABLE users (id int PRIMARY KEY, bio text); -- trailing; comment
+INSERT INTO users (id, bio) VALUES (1, 'likes ; and
+-- +migrate Down
+in strings');
+-- +migrate StatementBegin
+CREATE FUNCTION plus(a int, b int) RETURNS NULL ON NULL INPUT RETURNS int
+LANGUAGE lua AS $$ return a + b; $$;
+-- +migrate StatementEnd
+INSERT INTO "odd;name" (id) VALUES (2);
+
+-- +migrate Down
+DROP FUNCTION plus;
+DROP TABLE users;
+"#;
+        let (up, down) = parse_migration_content(content).unwrap();
+        let statements = split(&up);
+        assert_eq!(statements.len(), 4);
+        assert!(statements[0].text.starts_with("/* Example"));
+        assert!(statements[1].text.ends_with("in strings')"));
+        assert!(statements[2].text.starts_with("CREATE FUNCTION") && statements[2].text.ends_with("$$"));
+        assert_eq!(statements[3].text, "INSERT INTO \"odd;name\" (id) VALUES (2)");
+        assert_eq!(split(&down.unwrap()).len(), 2);
+
+        let lines: Vec<(usize, usize)> = split_section(content, Section::Up)
+            .iter()
+            .map(|statement| (statement.first_line, statement.last_line))
+            .collect();
+        assert_eq!(lines, vec![(6, 6), (7, 9), (11, 12), (14, 14)]);
+
+        // Comment-only fragments are not statements
+        assert!(split("/* nothing */;\n-- nor this;\n").is_empty());
+        assert!(parse_migration_content("-- +migrate Up\n-- +migrate StatementBegin\nSELECT 1;\n").is_err());
+    }
+
+    #[test]
+    fn test_directive_statement_block() {
+        let content = "INSERT INTO t (id) VALUES (1);\n-- db-migrate:only-env=dev\n-- db-migrate:statement-begin\nINSERT INTO t (id, doc) VALUES (2, '{\"a\": 1}'); -- one statement\nAPPLY BATCH;\n-- db-migrate:statement-end\n";
+        let statements = split(content);
+        assert_eq!(statements.len(), 2);
+        assert_eq!(
+            statements[1].text,
+            "-- db-migrate:only-env=dev\nINSERT INTO t (id, doc) VALUES (2, '{\"a\": 1}'); -- one statement\nAPPLY BATCH"
+        );
+        assert_eq!((statements[1].first_line, statements[1].last_line), (4, 5));
+    }
+}
@@ -0,0 +1,230 @@
+//! Streaming access to very large migration files
+//!
+//! Seed and backfill migrations can be hundreds of megabytes. Files larger than
+//! [`STREAMING_THRESHOLD`] are never loaded whole: their statements are read one
+//! at a time when executed and their checksum is computed over a buffered reader.
+//! Statements are split exactly like [`parse_migration_content`] followed by
+//! [`split_cql_statements`], so checksums and behaviour do not depend on the size.
+//!
+//! [`parse_migration_content`]: crate::utils::parse_migration_content
+//! [`split_cql_statements`]: crate::utils::split_cql_statements
+
+use crate::statements::{Line, SectionTracker, StatementSplitter};
+use sha2::{Digest, Sha256};
+use std::collections::VecDeque;
+use std::io;
+use std::path::Path;
+use tokio::fs::File;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, BufReader, Lines};
+
+/// Files larger than this are streamed instead of read into memory
+pub const STREAMING_THRESHOLD: u64 = 32 * 1024 * 1024;
+
+/// Section of a migration file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Section {
+    Up,
+    Down,
+}
+
+impl Section {
+    pub(crate) fn of_marker(line: &str) -> Option<Self> {
+        if line.starts_with("-- UP") || line.starts_with("-- +migrate Up") {
+            Some(Section::Up)
+        } else if line.starts_with("-- DOWN") || line.starts_with("-- +migrate Down") {
+            Some(Section::Down)
+        } else {
+            None
+        }
+    }
+}
+
+/// Incremental statement splitter over a buffered reader
+///
+/// Only one statement is held in memory at a time.
+pub struct StatementStream<R> {
+    lines: Lines<R>,
+    section: Section,
+    tracker: SectionTracker,
+    splitter: StatementSplitter,
+    line_number: usize,
+    pending: VecDeque<String>,
+    done: bool,
+}
+
+impl StatementStream<BufReader<File>> {
+    /// Stream the statements of one section of a migration file
+    pub async fn open(path: &Path, section: Section) -> io::Result<Self> {
+        Ok(Self::new(BufReader::new(File::open(path).await?), section))
+    }
+}
+
+impl<R: AsyncBufRead + Unpin> StatementStream<R> {
+    pub fn new(reader: R, section: Section) -> Self {
+        Self {
+            lines: reader.lines(),
+            section,
+            tracker: SectionTracker::default(),
+            splitter: StatementSplitter::default(),
+            line_number: 0,
+            pending: VecDeque::new(),
+            done: false,
+        }
+    }
+
+    /// The next statement of the section, or `None` at the end of the file
+    pub async fn next_statement(&mut self) -> io::Result<Option<String>> {
+        loop {
+            if let Some(statement) = self.pending.pop_front() {
+                return Ok(Some(statement));
+            }
+            if self.done {
+                return Ok(None);
+            }
+
+            match self.lines.next_line().await? {
+                Some(line) => self.push_line(&line),
+                None => {
+                    self.done = true;
+                    if let Some(line) = self.tracker.unterminated_block() {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!("statement block opened on line {} is never closed", line),
+                        ));
+                    }
+                    self.flush();
+                }
+            }
+        }
+    }
+
+    fn push_line(&mut self, line: &str) {
+        self.line_number += 1;
+        match self.tracker.push_line(line) {
+            Line::Marker(_) => self.flush(),
+            Line::In(section) if section == self.section => {
+                let statements = self.splitter.push_line(self.line_number, line);
+                self.pending.extend(statements.into_iter().map(|statement| statement.text));
+            }
+            _ => {}
+        }
+    }
+
+    fn flush(&mut self) {
+        self.pending.extend(self.splitter.finish().map(|statement| statement.text));
+    }
+}
+
+/// The leading comment block of a file, which holds its directives
+pub async fn read_header(path: &Path) -> io::Result<String> {
+    let mut lines = BufReader::new(File::open(path).await?).lines();
+    let mut header = String::new();
+
+    while let Some(line) = lines.next_line().await? {
+        let trimmed = line.trim();
+        if !trimmed.is_empty() && !trimmed.starts_with("--") {
+            break;
+        }
+        header.push_str(&line);
+        header.push('\n');
+    }
+
+    Ok(header)
+}
+
+/// SHA256 checksum of a file, computed without loading it into memory
+///
+/// Matches [`calculate_checksum`](crate::utils::calculate_checksum) of the content
+/// with CRLF line endings converted to LF, like files that are read whole.
+pub async fn checksum_file(path: &Path) -> io::Result<String> {
+    let mut reader = BufReader::new(File::open(path).await?);
+    let mut hasher = Sha256::new();
+    let mut chunk = vec![0; 64 * 1024];
+    let mut normalized = Vec::with_capacity(chunk.len());
+    // A `\r` at the end of a chunk may start a CRLF split across chunks
+    let mut pending_cr = false;
+
+    loop {
+        let read = reader.read(&mut chunk).await?;
+        if read == 0 {
+            break;
+        }
+
+        normalized.clear();
+        for &byte in &chunk[..read] {
+            if pending_cr && byte != b'\n' {
+                normalized.push(b'\r');
+            }
+            pending_cr = byte == b'\r';
+            if !pending_cr {
+                normalized.push(byte);
+            }
+        }
+        hasher.update(&normalized);
+    }
+    if pending_cr {
+        hasher.update(b"\r");
+    }
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::{calculate_checksum, parse_migration_content, split_cql_statements};
+
+    const CONTENT: &str = r#"-- Migration: seed countries
+-- db-migrate:tags=countries
+
+-- +migrate Up
+CREATE TABLE countries (code text PRIMARY KEY, name text);
+INSERT INTO countries (code, name)
+    VALUES ('AR', 'Argentina'); INSERT INTO countries (code, name) VALUES ('UY', 'Uruguay');
+-- INSERT INTO countries (code, name) VALUES ('XX', 'commented out');
+INSERT INTO countries (code, name) VALUES ('CI', 'Côte d''Ivoire; or
+-- +migrate Down
+Ivory Coast');
+-- +migrate StatementBegin
+CREATE FUNCTION upper_code(code text) RETURNS NULL ON NULL INPUT RETURNS text
+LANGUAGE lua AS $$ return string.upper(code); $$;
+-- +migrate StatementEnd
+
+-- +migrate Down
+DROP TABLE countries
+"#;
+
+    async fn collect(section: Section) -> Vec<String> {
+        let mut stream = StatementStream::new(CONTENT.as_bytes(), section);
+        let mut statements = Vec::new();
+        while let Some(statement) = stream.next_statement().await.unwrap() {
+            statements.push(statement);
+        }
+        statements
+    }
+
+    #[tokio::test]
+    async fn test_stream_matches_parser() {
+        let (up, down) = parse_migration_content(CONTENT).unwrap();
+
+        assert_eq!(collect(Section::Up).await, split_cql_statements(&up));
+        assert_eq!(collect(Section::Up).await.len(), 5);
+        assert_eq!(collect(Section::Down).await, split_cql_statements(&down.unwrap()));
+    }
+
+    #[tokio::test]
+    async fn test_header_and_checksum() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("20250101_001_seed_countries.cql");
+        std::fs::write(&path, CONTENT).unwrap();
+
+        let header = read_header(&path).await.unwrap();
+        assert!(header.contains("db-migrate:tags=countries"));
+        assert!(!header.contains("CREATE TABLE"));
+
+        assert_eq!(checksum_file(&path).await.unwrap(), calculate_checksum(CONTENT));
+
+        std::fs::write(&path, CONTENT.replace('\n', "\r\n")).unwrap();
+        assert_eq!(checksum_file(&path).await.unwrap(), calculate_checksum(CONTENT));
+    }
+}
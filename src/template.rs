@@ -0,0 +1,134 @@
+//! Placeholders in files generated by `create`
+//!
+//! Templates may reference `{{name}}` variables: `description`, `version`,
+//! `date`, `author` and `ticket`, plus any defined under `[templates.variables]`.
+//! `author` comes from `DB_MIGRATE_AUTHOR` or `git config user.name`; `ticket`
+//! from `DB_MIGRATE_TICKET` or a key like `DATA-123` in the current git branch.
+
+use crate::config::Config;
+use chrono::Utc;
+use std::collections::BTreeMap;
+use std::process::Command;
+
+/// Variables available to a template
+pub fn template_variables(config: &Config, description: &str, version: &str) -> BTreeMap<String, String> {
+    let mut variables = config.templates.variables.clone();
+    variables.insert("description".to_string(), description.to_string());
+    variables.insert("version".to_string(), version.to_string());
+    variables.insert("date".to_string(), Utc::now().format("%Y-%m-%d").to_string());
+
+    let author = std::env::var("DB_MIGRATE_AUTHOR")
+        .ok()
+        .or_else(|| git(&["config", "user.name"]));
+    let ticket = std::env::var("DB_MIGRATE_TICKET")
+        .ok()
+        .or_else(|| git(&["rev-parse", "--abbrev-ref", "HEAD"]).and_then(|b| ticket_from_branch(&b)));
+
+    for (name, value) in [("author", author), ("ticket", ticket)] {
+        if let Some(value) = value.filter(|v| !v.is_empty()) {
+            variables.entry(name.to_string()).or_insert(value);
+        }
+    }
+
+    variables
+}
+
+/// Replace `{{name}}` placeholders; unknown names are left as they are
+pub fn render(template: &str, variables: &BTreeMap<String, String>) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        rendered.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find("}}") else {
+            rest = &rest[start..];
+            break;
+        };
+
+        let placeholder = &rest[start..start + end + 2];
+        match variables.get(placeholder[2..placeholder.len() - 2].trim()) {
+            Some(value) => rendered.push_str(value),
+            None => {
+                tracing::warn!("Unknown template variable: {}", placeholder);
+                rendered.push_str(placeholder);
+            }
+        }
+        rest = &rest[start + end + 2..];
+    }
+
+    rendered.push_str(rest);
+    rendered
+}
+
+/// Body for an `ALTER`-style migration of an existing table, showing its current DDL
+pub fn table_template(table: &str, ddl: &str) -> String {
+    let current: String = ddl
+        .lines()
+        .map(|line| format!("-- {}\n", line).replace("-- \n", "--\n"))
+        .collect();
+
+    format!(
+        r#"-- +migrate Up
+-- Current definition of {table}:
+{current}
+-- ALTER TABLE {table} ADD new_column TEXT;
+
+-- +migrate Down
+-- ALTER TABLE {table} DROP new_column;
+"#
+    )
+}
+
+fn git(args: &[&str]) -> Option<String> {
+    let output = Command::new("git").args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// An issue key (`PROJ-123`) in a branch name such as `feature/PROJ-123-add-users`
+fn ticket_from_branch(branch: &str) -> Option<String> {
+    branch
+        .split(|c: char| !c.is_ascii_alphanumeric() && c != '-')
+        .flat_map(|part| {
+            let parts: Vec<&str> = part.split('-').collect();
+            parts
+                .windows(2)
+                .find(|pair| {
+                    pair[0].len() >= 2
+                        && pair[0].chars().next().is_some_and(|c| c.is_ascii_uppercase())
+                        && pair[0].chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+                        && !pair[1].is_empty()
+                        && pair[1].chars().all(|c| c.is_ascii_digit())
+                })
+                .map(|pair| format!("{}-{}", pair[0], pair[1]))
+        })
+        .next()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let variables = BTreeMap::from([
+            ("author".to_string(), "Ada".to_string()),
+            ("team".to_string(), "payments".to_string()),
+        ]);
+
+        assert_eq!(
+            render("-- Author: {{author}} ({{ team }})\n-- {{unknown}} {{", &variables),
+            "-- Author: Ada (payments)\n-- {{unknown}} {{"
+        );
+    }
+
+    #[test]
+    fn test_ticket_from_branch() {
+        assert_eq!(ticket_from_branch("feature/DATA-123-add-users"), Some("DATA-123".to_string()));
+        assert_eq!(ticket_from_branch("fix/OPS2-7"), Some("OPS2-7".to_string()));
+        assert_eq!(ticket_from_branch("main"), None);
+        assert_eq!(ticket_from_branch("add-2-columns"), None);
+    }
+}
@@ -0,0 +1,184 @@
+//! Tenant keyspaces of `up --all-tenants`
+//!
+//! `tenants.keyspaces` lists tenants explicitly. `tenants.discovery_query` finds
+//! more at the start of every run, filtered by `tenants.include` and
+//! `tenants.exclude`, so a new tenant keyspace is migrated by the next run
+//! without a configuration change.
+//!
+//! `tenants.manifest` gives each tenant values for `${var:NAME}` placeholders:
+//!
+//! ```toml
+//! [tenant_acme]
+//! shard_count = 16
+//! region = "us-east"
+//! ```
+
+use crate::{
+    config::{Config, TenantsConfig},
+    cql,
+    migration::SharedSession,
+    MigrationError,
+};
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::path::Path;
+use tracing::warn;
+
+/// Placeholder values per tenant keyspace
+pub type Manifest = BTreeMap<String, BTreeMap<String, String>>;
+
+/// A keyspace migrated by `up --all-tenants`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tenant {
+    pub keyspace: String,
+    /// Values of `${var:NAME}` placeholders from the manifest
+    pub variables: BTreeMap<String, String>,
+    /// Found by `tenants.discovery_query` rather than configured
+    pub discovered: bool,
+}
+
+/// Tenants to migrate: the configured keyspaces, those of the manifest, then
+/// any discovered ones
+pub async fn resolve(config: &Config) -> Result<Vec<Tenant>, MigrationError> {
+    let manifest = match &config.tenants.manifest {
+        Some(path) => load_manifest(path)?,
+        None => Manifest::new(),
+    };
+
+    let configured = config.tenants.keyspaces.iter().chain(manifest.keys()).map(|keyspace| (keyspace.clone(), false));
+    let discovered = discover(config).await?.into_iter().map(|keyspace| (keyspace, true));
+    let mut keyspaces: Vec<(String, bool)> = Vec::new();
+    for (keyspace, discovered) in configured.chain(discovered) {
+        if !keyspaces.iter().any(|(known, _)| *known == keyspace) {
+            keyspaces.push((keyspace, discovered));
+        }
+    }
+
+    Ok(keyspaces
+        .into_iter()
+        .map(|(keyspace, discovered)| Tenant {
+            variables: manifest.get(&keyspace).cloned().unwrap_or_default(),
+            keyspace,
+            discovered,
+        })
+        .collect())
+}
+
+/// Read `tenants.manifest`
+pub fn load_manifest(path: &Path) -> Result<Manifest, MigrationError> {
+    let content = std::fs::read_to_string(path).map_err(|e| {
+        MigrationError::ConfigError(format!("Failed to read tenants manifest {}: {}", path.display(), e))
+    })?;
+    parse_manifest(&content)
+        .map_err(|e| MigrationError::ConfigError(format!("Invalid tenants manifest {}: {}", path.display(), e)))
+}
+
+/// One table per keyspace; strings, numbers and booleans as values
+fn parse_manifest(content: &str) -> Result<Manifest, String> {
+    let tables: BTreeMap<String, toml::Table> = toml::from_str(content).map_err(|e| e.to_string())?;
+    let mut manifest = Manifest::new();
+    for (keyspace, table) in tables {
+        cql::validate_identifier(&keyspace).map_err(|e| format!("[{}]: {}", keyspace, e))?;
+        let mut variables = BTreeMap::new();
+        for (name, value) in table {
+            let value = match value {
+                toml::Value::String(value) => value,
+                toml::Value::Integer(value) => value.to_string(),
+                toml::Value::Float(value) => value.to_string(),
+                toml::Value::Boolean(value) => value.to_string(),
+                other => {
+                    return Err(format!(
+                        "{}.{} is a {}, expected a string, number or boolean",
+                        keyspace,
+                        name,
+                        other.type_str()
+                    ))
+                }
+            };
+            variables.insert(name, value);
+        }
+        manifest.insert(keyspace, variables);
+    }
+    Ok(manifest)
+}
+
+/// Keyspaces returned by `tenants.discovery_query` that pass the filters, sorted
+pub async fn discover(config: &Config) -> Result<Vec<String>, MigrationError> {
+    let Some(query) = &config.tenants.discovery_query else {
+        return Ok(Vec::new());
+    };
+    let shared = SharedSession::connect(config, None).await?;
+    let rows = shared.session().query(query.as_str(), &[]).await?;
+    let names = rows
+        .rows_typed::<(String,)>()
+        .map_err(|e| MigrationError::ConfigError(format!("tenants.discovery_query must return keyspace names: {}", e)))?
+        .map(|row| row.map(|(name,)| name))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| MigrationError::ConfigError(format!("tenants.discovery_query must return keyspace names: {}", e)))?;
+    select(&config.tenants, names)
+}
+
+/// Apply `include` and `exclude` to discovered names, dropping names that
+/// cannot be a keyspace
+pub fn select(tenants: &TenantsConfig, names: Vec<String>) -> Result<Vec<String>, MigrationError> {
+    let compile = |patterns: &[String]| {
+        patterns
+            .iter()
+            .map(|pattern| Regex::new(pattern))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| MigrationError::ConfigError(format!("Invalid tenants pattern: {}", e)))
+    };
+    let include = compile(&tenants.include)?;
+    let exclude = compile(&tenants.exclude)?;
+
+    let mut selected: Vec<String> = names
+        .into_iter()
+        .filter(|name| include.is_empty() || include.iter().any(|pattern| pattern.is_match(name)))
+        .filter(|name| !exclude.iter().any(|pattern| pattern.is_match(name)))
+        .filter(|name| match cql::validate_identifier(name) {
+            Ok(()) => true,
+            Err(e) => {
+                warn!("Skipping discovered tenant '{}': {}", name, e);
+                false
+            }
+        })
+        .collect();
+    selected.sort();
+    selected.dedup();
+    Ok(selected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select() {
+        let tenants = TenantsConfig {
+            include: vec!["^tenant_".to_string()],
+            exclude: vec!["_archived$".to_string()],
+            ..TenantsConfig::default()
+        };
+        let names = ["tenant_b", "system_schema", "tenant_a", "tenant_old_archived", "tenant_a"]
+            .map(str::to_string)
+            .to_vec();
+        assert_eq!(select(&tenants, names).unwrap(), vec!["tenant_a", "tenant_b"]);
+
+        let everything = TenantsConfig::default();
+        assert_eq!(
+            select(&everything, vec!["app".to_string(), "not a keyspace".to_string()]).unwrap(),
+            vec!["app"]
+        );
+    }
+
+    #[test]
+    fn test_parse_manifest() {
+        let manifest = parse_manifest("[tenant_acme]\nshard_count = 16\nregion = \"us-east\"\n\n[tenant_globex]\n").unwrap();
+        assert_eq!(manifest["tenant_acme"]["shard_count"], "16");
+        assert_eq!(manifest["tenant_acme"]["region"], "us-east");
+        assert!(manifest["tenant_globex"].is_empty());
+
+        assert!(parse_manifest("[tenant_acme]\nregions = [\"a\"]\n").is_err());
+        assert!(parse_manifest("[\"not a keyspace\"]\nshards = 1\n").is_err());
+    }
+}
@@ -0,0 +1,127 @@
+use crate::{config::TlsConfig, MigrationError};
+use openssl::pkey::PKey;
+use openssl::ssl::{SslContext, SslContextBuilder, SslFiletype, SslMethod, SslVerifyMode};
+
+/// Build an OpenSSL context from the TLS configuration
+///
+/// Server certificates are verified against `ca_cert`, or the system's trusted
+/// roots when none is configured. When a client certificate and key are
+/// configured the context presents them to the server, enabling
+/// certificate-based (mutual TLS) authentication.
+pub fn build_ssl_context(tls: &TlsConfig) -> Result<SslContext, MigrationError> {
+    let mut builder = SslContextBuilder::new(SslMethod::tls()).map_err(tls_error)?;
+
+    match &tls.ca_cert {
+        Some(ca_cert) => builder.set_ca_file(ca_cert).map_err(tls_error)?,
+        None => builder.set_default_verify_paths().map_err(tls_error)?,
+    }
+
+    builder.set_verify(if tls.verify_peer {
+        SslVerifyMode::PEER
+    } else {
+        SslVerifyMode::NONE
+    });
+
+    if let (Some(client_cert), Some(client_key)) = (&tls.client_cert, &tls.client_key) {
+        builder
+            .set_certificate_file(client_cert, SslFiletype::PEM)
+            .map_err(tls_error)?;
+
+        let key_pem = std::fs::read(client_key)?;
+        let key = match &tls.client_key_password {
+            Some(password) => PKey::private_key_from_pem_passphrase(&key_pem, password.as_bytes()),
+            None => PKey::private_key_from_pem(&key_pem),
+        }
+        .map_err(tls_error)?;
+
+        builder.set_private_key(&key).map_err(tls_error)?;
+        builder.check_private_key().map_err(tls_error)?;
+    }
+
+    Ok(builder.build())
+}
+
+fn tls_error(e: openssl::error::ErrorStack) -> MigrationError {
+    MigrationError::TlsError(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use openssl::asn1::Asn1Time;
+    use openssl::hash::MessageDigest;
+    use openssl::rsa::Rsa;
+    use openssl::symm::Cipher;
+    use openssl::x509::{X509NameBuilder, X509};
+    use std::path::{Path, PathBuf};
+
+    /// Write a new key and a self-signed certificate for it, returning their paths
+    fn write_identity(dir: &Path, name: &str, password: Option<&str>) -> (PathBuf, PathBuf) {
+        let key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+        let mut subject = X509NameBuilder::new().unwrap();
+        subject.append_entry_by_text("CN", name).unwrap();
+        let subject = subject.build();
+
+        let mut cert = X509::builder().unwrap();
+        cert.set_version(2).unwrap();
+        cert.set_subject_name(&subject).unwrap();
+        cert.set_issuer_name(&subject).unwrap();
+        cert.set_pubkey(&key).unwrap();
+        cert.set_not_before(&Asn1Time::days_from_now(0).unwrap()).unwrap();
+        cert.set_not_after(&Asn1Time::days_from_now(1).unwrap()).unwrap();
+        cert.sign(&key, MessageDigest::sha256()).unwrap();
+
+        let key_pem = match password {
+            Some(password) => key
+                .private_key_to_pem_pkcs8_passphrase(Cipher::aes_256_cbc(), password.as_bytes())
+                .unwrap(),
+            None => key.private_key_to_pem_pkcs8().unwrap(),
+        };
+        let cert_path = dir.join(format!("{}.crt", name));
+        let key_path = dir.join(format!("{}.key", name));
+        std::fs::write(&cert_path, cert.build().to_pem().unwrap()).unwrap();
+        std::fs::write(&key_path, key_pem).unwrap();
+        (cert_path, key_path)
+    }
+
+    #[test]
+    fn test_build_ssl_context() {
+        let dir = tempfile::tempdir().unwrap();
+        let (ca_cert, _) = write_identity(dir.path(), "ca", None);
+
+        // System roots without a CA file, the given bundle otherwise
+        let mut tls = TlsConfig {
+            enabled: true,
+            ..TlsConfig::default()
+        };
+        assert!(build_ssl_context(&tls).is_ok());
+        tls.ca_cert = Some(ca_cert);
+        assert!(build_ssl_context(&tls).is_ok());
+        tls.ca_cert = Some(dir.path().join("missing.crt"));
+        assert!(matches!(build_ssl_context(&tls), Err(MigrationError::TlsError(_))));
+    }
+
+    #[test]
+    fn test_build_ssl_context_with_client_certificate() {
+        let dir = tempfile::tempdir().unwrap();
+        let (client_cert, client_key) = write_identity(dir.path(), "client", Some("secret"));
+        let (_, other_key) = write_identity(dir.path(), "other", None);
+
+        let mut tls = TlsConfig {
+            enabled: true,
+            client_cert: Some(client_cert),
+            client_key: Some(client_key),
+            client_key_password: Some("secret".to_string()),
+            ..TlsConfig::default()
+        };
+        assert!(build_ssl_context(&tls).is_ok());
+
+        tls.client_key_password = Some("wrong".to_string());
+        assert!(matches!(build_ssl_context(&tls), Err(MigrationError::TlsError(_))));
+
+        // A key that does not belong to the certificate
+        tls.client_key = Some(other_key);
+        tls.client_key_password = None;
+        assert!(matches!(build_ssl_context(&tls), Err(MigrationError::TlsError(_))));
+    }
+}
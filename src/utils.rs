@@ -1,7 +1,11 @@
+use crate::statements::{self, Line, SectionTracker};
+use crate::stream::Section;
+use crate::version::VersionScheme;
 use chrono::{DateTime, Utc};
 use sha2::{Digest, Sha256};
-use std::path::Path;
-use tokio::fs;
+use crate::template::render;
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
 
 /// Generate a timestamp-based migration version
 pub fn generate_migration_version() -> String {
@@ -16,45 +20,65 @@ pub fn calculate_checksum(content: &str) -> String {
     format!("{:x}", hasher.finalize())
 }
 
+/// Checksum of a migration without its DOWN section
+///
+/// Everything else (header comments, directives, the UP section) is hashed, so
+/// fixing only the rollback of an applied migration leaves it unchanged.
+pub fn calculate_up_checksum(content: &str) -> String {
+    let mut tracker = SectionTracker::default();
+    let up: Vec<&str> = content
+        .lines()
+        .filter(|line| {
+            !matches!(
+                tracker.push_line(line),
+                Line::Marker(Section::Down) | Line::In(Section::Down)
+            )
+        })
+        .collect();
+    calculate_checksum(up.join("\n").trim_end())
+}
+
+/// Convert CRLF line endings to LF
+///
+/// Migration files are normalized when read, so a file checked out with Windows
+/// line endings parses and hashes the same as on other systems.
+pub fn normalize_line_endings(content: String) -> String {
+    if content.contains("\r\n") {
+        content.replace("\r\n", "\n")
+    } else {
+        content
+    }
+}
+
+/// Accept `\` as a separator in configured paths on systems where it is not one,
+/// so a config written on Windows (`db\\migrations`) works everywhere
+pub fn portable_path(path: &Path) -> PathBuf {
+    if cfg!(windows) {
+        return path.to_path_buf();
+    }
+    match path.to_str() {
+        Some(path) if path.contains('\\') => PathBuf::from(path.replace('\\', "/")),
+        _ => path.to_path_buf(),
+    }
+}
+
 /// Calculate SHA256 checksum of a file
 pub async fn calculate_file_checksum<P: AsRef<Path>>(file_path: P) -> Result<String, std::io::Error> {
-    let content = fs::read_to_string(file_path).await?;
-    Ok(calculate_checksum(&content))
+    crate::stream::checksum_file(file_path.as_ref()).await
 }
 
 /// Extract description from migration filename
-pub fn extract_description_from_filename(filename: &str) -> String {
+pub fn extract_description_from_filename(filename: &str, scheme: &VersionScheme) -> String {
     // Expected format: 20250115_001_add_user_table.cql
     let stem = filename.trim_end_matches(".cql");
-
-    // Split by underscore and take everything after the second underscore
-    let parts: Vec<&str> = stem.split('_').collect();
-    if parts.len() >= 3 {
-        parts[2..].join("_").replace('_', " ")
-    } else {
-        stem.to_string()
-    }
+    scheme.label(stem).replace('_', " ")
 }
 
 /// Extract version from migration filename
-pub fn extract_version_from_filename(filename: &str) -> Option<String> {
-    // Expected format: 20250115_001_add_user_table.cql
-    let stem = filename.trim_end_matches(".cql");
-
-    // Check if it matches the expected pattern: YYYYMMDD_NNN_description
-    if let Some(version_part) = stem.split('_').take(2).collect::<Vec<_>>().join("_").into() {
-        // Validate that the first part is a valid date format
-        if version_part.len() >= 9 {
-            let (date_part, seq_part) = version_part.split_at(8);
-            if date_part.chars().all(|c| c.is_ascii_digit()) &&
-                seq_part.starts_with('_') &&
-                seq_part[1..].chars().all(|c| c.is_ascii_digit()) {
-                return Some(stem.to_string());
-            }
-        }
-    }
-
-    None
+pub fn extract_version_from_filename(filename: &str, scheme: &VersionScheme) -> Option<String> {
+    // Expected format: 20250115_001_add_user_table.cql, unless [versioning] says otherwise
+    let stem = filename.strip_suffix(".cql").unwrap_or(filename);
+    scheme.parse(stem).ok()
 }
 
 /// Format a timestamp for display
@@ -63,63 +87,138 @@ pub fn format_timestamp(timestamp: DateTime<Utc>) -> String {
 }
 
 /// Validate migration filename format
-pub fn is_valid_migration_filename(filename: &str) -> bool {
-    extract_version_from_filename(filename).is_some()
+pub fn is_valid_migration_filename(filename: &str, scheme: &VersionScheme) -> bool {
+    extract_version_from_filename(filename, scheme).is_some()
 }
 
-/// Create a normalized migration filename
-pub fn create_migration_filename(description: &str) -> String {
-    let version = generate_migration_version();
-    let normalized_desc = description
-        .chars()
-        .map(|c| if c.is_alphanumeric() { c } else { '_' })
-        .collect::<String>()
-        .to_lowercase();
+/// Maximum length of the description part of a migration filename
+pub const MAX_DESCRIPTION_LENGTH: usize = 60;
 
-    format!("{}_{}.cql", version, normalized_desc)
+/// Create a normalized migration filename
+pub fn create_migration_filename(version: &str, description: &str) -> Result<String, String> {
+    Ok(format!("{}_{}.cql", version, normalize_description(description)?))
 }
 
-/// Parse migration content to extract UP and DOWN sections
-pub fn parse_migration_content(content: &str) -> Result<(String, Option<String>), String> {
-    let lines: Vec<&str> = content.lines().collect();
-    let mut up_section = Vec::new();
-    let mut down_section = Vec::new();
-    let mut current_section = None;
-
-    for line in lines {
-        let trimmed = line.trim();
+/// Turn a free-form description into a `snake_case` ASCII filename part
+///
+/// Accented Latin letters are transliterated, apostrophes dropped, and any other
+/// run of characters becomes a single underscore. Long descriptions are cut at a
+/// word boundary.
+pub fn normalize_description(description: &str) -> Result<String, String> {
+    let mut normalized = String::new();
 
-        if trimmed.starts_with("-- UP") || trimmed.starts_with("-- +migrate Up") {
-            current_section = Some("UP");
-            continue;
-        } else if trimmed.starts_with("-- DOWN") || trimmed.starts_with("-- +migrate Down") {
-            current_section = Some("DOWN");
+    for c in description.chars().flat_map(char::to_lowercase) {
+        if c == '\'' || c == '’' {
             continue;
         }
-
-        // Skip comments and empty lines at the beginning
-        if current_section.is_none() && (trimmed.is_empty() || trimmed.starts_with("--")) {
-            continue;
+        match transliterate(c) {
+            Some(ascii) => normalized.push_str(ascii),
+            None if c.is_ascii_alphanumeric() => normalized.push(c),
+            None if !normalized.is_empty() && !normalized.ends_with('_') => normalized.push('_'),
+            None => {}
         }
+    }
 
-        // If no section marker found, assume it's all UP
-        if current_section.is_none() {
-            current_section = Some("UP");
+    let mut normalized = normalized.trim_end_matches('_').to_string();
+    if normalized.len() > MAX_DESCRIPTION_LENGTH {
+        let cuts_word = normalized.as_bytes()[MAX_DESCRIPTION_LENGTH] != b'_';
+        normalized.truncate(MAX_DESCRIPTION_LENGTH);
+        if let Some(boundary) = normalized.rfind('_').filter(|_| cuts_word) {
+            normalized.truncate(boundary);
         }
+    }
 
-        match current_section {
-            Some("UP") => up_section.push(line),
-            Some("DOWN") => down_section.push(line),
-            _ => {}
+    if normalized.is_empty() {
+        return Err(format!(
+            "Description '{}' contains no letters or digits",
+            description
+        ));
+    }
+    Ok(normalized)
+}
+
+fn transliterate(c: char) -> Option<&'static str> {
+    Some(match c {
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ă' | 'ą' => "a",
+        'æ' => "ae",
+        'ç' | 'ć' | 'č' => "c",
+        'ď' | 'đ' | 'ð' => "d",
+        'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ė' | 'ę' | 'ě' => "e",
+        'ğ' => "g",
+        'ì' | 'í' | 'î' | 'ï' | 'ī' | 'į' | 'ı' => "i",
+        'ł' => "l",
+        'ñ' | 'ń' | 'ň' => "n",
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ő' => "o",
+        'œ' => "oe",
+        'ř' => "r",
+        'ś' | 'š' | 'ş' | 'ș' => "s",
+        'ß' => "ss",
+        'ť' | 'ţ' | 'ț' => "t",
+        'þ' => "th",
+        'ù' | 'ú' | 'û' | 'ü' | 'ū' | 'ů' | 'ű' | 'ų' => "u",
+        'ý' | 'ÿ' => "y",
+        'ź' | 'ż' | 'ž' => "z",
+        _ => return None,
+    })
+}
+
+/// Timestamp part of a version (`YYYYMMDD_HHMMSS`), without the description
+pub fn version_prefix(version: &str) -> &str {
+    match version.match_indices('_').nth(1) {
+        Some((index, _)) => &version[..index],
+        None => version,
+    }
+}
+
+/// Bump the sequence part of `prefix` until it no longer collides with `taken`
+///
+/// Two migrations created within the same second would otherwise share a prefix.
+pub fn next_free_prefix(prefix: &str, taken: &HashSet<&str>) -> String {
+    let Some((date, sequence)) = prefix.split_once('_') else {
+        return prefix.to_string();
+    };
+    let Ok(mut number) = sequence.parse::<u64>() else {
+        return prefix.to_string();
+    };
+
+    let mut candidate = prefix.to_string();
+    while taken.contains(candidate.as_str()) {
+        number += 1;
+        candidate = format!("{}_{:0width$}", date, number, width = sequence.len());
+    }
+    candidate
+}
+
+/// Parse migration content to extract UP and DOWN sections
+///
+/// Section markers inside comments, strings and statement blocks are text.
+pub fn parse_migration_content(content: &str) -> Result<(String, Option<String>), String> {
+    let mut tracker = SectionTracker::default();
+    let mut up_section = Vec::new();
+    let mut down_section = None;
+
+    for line in content.lines() {
+        match tracker.push_line(line) {
+            Line::Marker(Section::Down) => {
+                down_section.get_or_insert_with(Vec::new);
+            }
+            Line::In(Section::Up) => up_section.push(line),
+            Line::In(Section::Down) => down_section.get_or_insert_with(Vec::new).push(line),
+            Line::Marker(Section::Up) | Line::Header => {}
         }
     }
 
+    if let Some(line) = tracker.unterminated_block() {
+        return Err(format!(
+            "Statement block opened on line {} is never closed with '-- +migrate StatementEnd' or '-- db-migrate:statement-end'",
+            line
+        ));
+    }
+
     let up_content = up_section.join("\n").trim().to_string();
-    let down_content = if down_section.is_empty() {
-        None
-    } else {
-        Some(down_section.join("\n").trim().to_string())
-    };
+    let down_content = down_section
+        .filter(|lines| !lines.is_empty())
+        .map(|lines| lines.join("\n").trim().to_string());
 
     if up_content.is_empty() {
         return Err("Migration must contain at least UP section with CQL statements".to_string());
@@ -128,13 +227,43 @@ pub fn parse_migration_content(content: &str) -> Result<(String, Option<String>)
     Ok((up_content, down_content))
 }
 
-/// Generate migration template content
-pub fn generate_migration_template(description: &str) -> String {
-    format!(
-        r#"-- Migration: {}
--- Created at: {}
+/// Split CQL content into individual statements
+///
+/// A `;` ends a statement only outside strings and comments, statement blocks
+/// are kept whole, and fragments consisting only of comments (e.g.
+/// commented-out examples) are dropped.
+pub fn split_cql_statements(content: &str) -> Vec<String> {
+    statements::split(content)
+        .into_iter()
+        .map(|statement| statement.text)
+        .collect()
+}
 
--- +migrate Up
+/// 1-based first and last line of each statement of a section, in the order
+/// [`parse_migration_content`] and [`split_cql_statements`] produce them
+pub fn statement_lines(content: &str, section: Section) -> Vec<(usize, usize)> {
+    statements::split_section(content, section)
+        .into_iter()
+        .map(|statement| (statement.first_line, statement.last_line))
+        .collect()
+}
+
+/// Starting content for new migration files
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum MigrationTemplate {
+    /// Table creation example
+    #[default]
+    Default,
+    /// Role creation and grants, with the password read from a secret
+    Role,
+}
+
+impl MigrationTemplate {
+    /// Built-in body: the UP and DOWN sections with commented examples
+    pub fn body(self) -> &'static str {
+        match self {
+            MigrationTemplate::Default => {
+                r#"-- +migrate Up
 -- Add your UP migration statements here
 -- Example:
 -- CREATE TABLE IF NOT EXISTS example_table (
@@ -147,24 +276,144 @@ pub fn generate_migration_template(description: &str) -> String {
 -- Add your DOWN migration statements here (optional)
 -- Example:
 -- DROP TABLE IF EXISTS example_table;
-"#,
+"#
+            }
+            MigrationTemplate::Role => {
+                r#"-- +migrate Up
+-- Passwords are read from the environment or the secrets directory at execution
+-- time, never from this file.
+-- Example:
+-- CREATE ROLE IF NOT EXISTS app_role WITH PASSWORD = '${secret:APP_ROLE_PASSWORD}' AND LOGIN = true;
+-- GRANT SELECT ON KEYSPACE my_keyspace TO app_role;
+-- GRANT MODIFY ON my_keyspace.example_table TO app_role;
+
+-- +migrate Down
+-- Example:
+-- REVOKE MODIFY ON my_keyspace.example_table FROM app_role;
+-- REVOKE SELECT ON KEYSPACE my_keyspace FROM app_role;
+-- DROP ROLE IF EXISTS app_role;
+"#
+            }
+        }
+    }
+}
+
+/// Levenshtein distance between two names, ignoring ASCII case
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.to_ascii_lowercase().chars().collect();
+    let b: Vec<char> = b.to_ascii_lowercase().chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.iter().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Candidates close enough to `name` to be what was meant, closest first
+pub fn similar_names<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Vec<&'a str> {
+    let lowered = name.to_ascii_lowercase();
+    let max_distance = (name.len() / 3).max(2);
+    let mut similar: Vec<(usize, &str)> = candidates
+        .into_iter()
+        .filter_map(|candidate| {
+            let distance = edit_distance(name, candidate);
+            let related = candidate.to_ascii_lowercase().contains(&lowered)
+                || lowered.contains(&candidate.to_ascii_lowercase());
+            (distance <= max_distance || related).then_some((distance, candidate))
+        })
+        .collect();
+    similar.sort();
+    similar.into_iter().map(|(_, candidate)| candidate).collect()
+}
+
+/// Stands in for the DOWN statements of a new `require-down` migration until
+/// they are written; lint reports it as a warning rather than an error
+pub const DOWN_TODO: &str = "-- TODO: write the statements that undo the UP section";
+
+/// Generate migration file content: the header, directives and the rendered body
+pub fn generate_migration_template(
+    description: &str,
+    body: &str,
+    require_down: bool,
+    auto_down: bool,
+    variables: &BTreeMap<String, String>,
+) -> String {
+    let mut header = format!(
+        "-- Migration: {}\n-- Created at: {}\n",
         description,
         Utc::now().format("%Y-%m-%d %H:%M:%S UTC")
-    )
+    );
+    for (label, name) in [("Author", "author"), ("Ticket", "ticket")] {
+        if let Some(value) = variables.get(name) {
+            header.push_str(&format!("-- {}: {}\n", label, value));
+        }
+    }
+    if require_down {
+        header.push_str("-- db-migrate:require-down\n");
+    }
+    if auto_down {
+        header.push_str("-- db-migrate:auto-down\n");
+    }
+
+    let mut body = render(body, variables);
+    if require_down {
+        body = with_down_todo(body);
+    }
+    format!("{}\n{}", header, body)
+}
+
+/// Put [`DOWN_TODO`] in the DOWN section of `content` unless it has statements
+fn with_down_todo(content: String) -> String {
+    let down = parse_migration_content(&content).ok().and_then(|(_, down)| down);
+    if !split_cql_statements(down.as_deref().unwrap_or("")).is_empty() {
+        return content;
+    }
+
+    let mut tracker = SectionTracker::default();
+    let mut lines: Vec<&str> = content.lines().collect();
+    match lines
+        .iter()
+        .position(|line| tracker.push_line(line) == Line::Marker(Section::Down))
+    {
+        Some(marker) => lines.insert(marker + 1, DOWN_TODO),
+        None => lines.extend(["", "-- +migrate Down", DOWN_TODO]),
+    }
+    lines.join("\n") + "\n"
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_normalize_line_endings() {
+        let crlf = "-- +migrate Up\r\nCREATE TABLE a (id int PRIMARY KEY);\r\n".to_string();
+        let lf = "-- +migrate Up\nCREATE TABLE a (id int PRIMARY KEY);\n";
+        assert_eq!(normalize_line_endings(crlf.clone()), lf);
+        assert_eq!(calculate_checksum(&normalize_line_endings(crlf)), calculate_checksum(lf));
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn test_portable_path() {
+        assert_eq!(portable_path(Path::new("db\\migrations")), PathBuf::from("db/migrations"));
+        assert_eq!(portable_path(Path::new("db/migrations")), PathBuf::from("db/migrations"));
+    }
+
     #[test]
     fn test_extract_description_from_filename() {
         assert_eq!(
-            extract_description_from_filename("20250115_001_add_user_table.cql"),
+            extract_description_from_filename("20250115_001_add_user_table.cql", &VersionScheme::Timestamp),
             "add user table"
         );
         assert_eq!(
-            extract_description_from_filename("20250115_002_create_indexes.cql"),
+            extract_description_from_filename("20250115_002_create_indexes.cql", &VersionScheme::Timestamp),
             "create indexes"
         );
     }
@@ -172,15 +421,36 @@ mod tests {
     #[test]
     fn test_extract_version_from_filename() {
         assert_eq!(
-            extract_version_from_filename("20250115_001_add_user_table.cql"),
+            extract_version_from_filename("20250115_001_add_user_table.cql", &VersionScheme::Timestamp),
             Some("20250115_001_add_user_table".to_string())
         );
         assert_eq!(
-            extract_version_from_filename("invalid_filename.cql"),
+            extract_version_from_filename("invalid_filename.cql", &VersionScheme::Timestamp),
             None
         );
     }
 
+    #[test]
+    fn test_normalize_description() {
+        assert_eq!(normalize_description("Add user's table!!").unwrap(), "add_users_table");
+        assert_eq!(normalize_description("  alter -- users: add phone  ").unwrap(), "alter_users_add_phone");
+        assert_eq!(normalize_description("Größe für Café").unwrap(), "grosse_fur_cafe");
+        assert_eq!(normalize_description("add 用户 table").unwrap(), "add_table");
+        assert!(normalize_description("!!! 用户").is_err());
+
+        let long = normalize_description(&"orders ".repeat(20)).unwrap();
+        assert_eq!(long, ["orders"; 8].join("_"));
+    }
+
+    #[test]
+    fn test_next_free_prefix() {
+        assert_eq!(version_prefix("20250115_093000_add_users"), "20250115_093000");
+
+        let taken = HashSet::from(["20250115_093000", "20250115_093001"]);
+        assert_eq!(next_free_prefix("20250115_093000", &taken), "20250115_093002");
+        assert_eq!(next_free_prefix("20250115_093005", &taken), "20250115_093005");
+    }
+
     #[test]
     fn test_calculate_checksum() {
         let content = "CREATE TABLE test (id UUID PRIMARY KEY);";
@@ -188,6 +458,20 @@ mod tests {
         assert_eq!(checksum.len(), 64); // SHA256 produces 64 hex characters
     }
 
+    #[test]
+    fn test_calculate_up_checksum() {
+        let original = "-- +migrate Up\nCREATE TABLE a (id int PRIMARY KEY);\n\n-- +migrate Down\nDROP TABLE a;\n";
+        let down_fixed = "-- +migrate Up\nCREATE TABLE a (id int PRIMARY KEY);\n\n-- +migrate Down\nDROP TABLE IF EXISTS a;\n";
+        let up_changed = "-- +migrate Up\nCREATE TABLE a (id bigint PRIMARY KEY);\n\n-- +migrate Down\nDROP TABLE a;\n";
+        assert_eq!(calculate_up_checksum(original), calculate_up_checksum(down_fixed));
+        assert_ne!(calculate_up_checksum(original), calculate_up_checksum(up_changed));
+        // Adding a DOWN section later is a DOWN-only change too
+        assert_eq!(
+            calculate_up_checksum("-- +migrate Up\nCREATE TABLE a (id int PRIMARY KEY);\n"),
+            calculate_up_checksum(original)
+        );
+    }
+
     #[test]
     fn test_parse_migration_content() {
         let content = r#"
@@ -204,4 +488,42 @@ DROP TABLE users;
         assert!(up.contains("CREATE TABLE users"));
         assert!(down.unwrap().contains("DROP TABLE users"));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_split_cql_statements_skips_comment_only_fragments() {
+        let content = r#"
+-- Example:
+-- DROP TABLE IF EXISTS example_table;
+CREATE TABLE a (id INT PRIMARY KEY);
+CREATE TABLE b (id INT PRIMARY KEY);
+"#;
+
+        let statements = split_cql_statements(content);
+        assert_eq!(statements.len(), 2);
+        assert!(statements[0].ends_with("CREATE TABLE a (id INT PRIMARY KEY)"));
+        assert_eq!(statements[1], "CREATE TABLE b (id INT PRIMARY KEY)");
+    }
+
+    #[test]
+    fn test_statement_lines() {
+        let content = "-- db-migrate:tags=a\n-- +migrate Up\nCREATE TABLE a (\n  id INT PRIMARY KEY\n);\n\n-- DROP TABLE x;\nINSERT INTO a (id) VALUES (1); INSERT INTO a (id) VALUES (2);\n-- +migrate Down\nDROP TABLE a;\n";
+
+        let (up, down) = parse_migration_content(content).unwrap();
+        assert_eq!(split_cql_statements(&up).len(), 3);
+        assert_eq!(statement_lines(content, Section::Up), vec![(3, 5), (8, 8), (8, 8)]);
+        assert_eq!(split_cql_statements(&down.unwrap()).len(), 1);
+        assert_eq!(statement_lines(content, Section::Down), vec![(10, 10)]);
+    }
+
+    #[test]
+    fn test_similar_names() {
+        assert_eq!(edit_distance("analytics_prd", "Analytics_Prod"), 1);
+        let keyspaces = ["analytics_prod", "analytics_staging", "billing", "system"];
+        assert_eq!(
+            similar_names("analytics_prd", keyspaces),
+            vec!["analytics_prod"]
+        );
+        assert_eq!(similar_names("biling", keyspaces), vec!["billing"]);
+        assert!(similar_names("orders", keyspaces).is_empty());
+    }
+}
@@ -0,0 +1,395 @@
+//! Typed migration versions
+//!
+//! A version is the file stem `YYYYMMDD_NNN[_label]`: a date, a numeric sequence
+//! (a counter or `HHMMSS` for generated files) and the description. Versions are
+//! stored as strings; parse them whenever they are ordered so that `_9_` sorts
+//! before `_10_`.
+//!
+//! `[versioning]` can replace this scheme for existing migration sets: plain
+//! counters (`0042_add_users`), or any naming matched by a regular expression
+//! whose capture groups define the order (see [`VersionScheme`]).
+
+use crate::config::{VersioningConfig, VersioningScheme};
+use chrono::NaiveDate;
+use regex::Regex;
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MigrationVersion {
+    date: NaiveDate,
+    sequence: u64,
+    /// Digits of the sequence as written, so the version prints back unchanged
+    width: usize,
+    label: Option<String>,
+}
+
+impl MigrationVersion {
+    /// Parse a migration filename (`20250115_001_add_users.cql`)
+    pub fn from_filename(filename: &str) -> Option<Self> {
+        filename.strip_suffix(".cql").unwrap_or(filename).parse().ok()
+    }
+
+    pub fn date(&self) -> NaiveDate {
+        self.date
+    }
+
+    pub fn sequence(&self) -> u64 {
+        self.sequence
+    }
+
+    /// The description part, if any
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    /// `YYYYMMDD_NNN` without the label
+    pub fn prefix(&self) -> String {
+        format!(
+            "{}_{:0width$}",
+            self.date.format("%Y%m%d"),
+            self.sequence,
+            width = self.width
+        )
+    }
+
+    /// The version `self` is renamed to by `rename`: a bare `YYYYMMDD_NNN`
+    /// target keeps the current description
+    pub fn renamed(&self, target: &str) -> Result<Self, String> {
+        let mut renamed: Self = target.parse()?;
+        if renamed.label.is_none() {
+            renamed.label = self.label.clone();
+        }
+        Ok(renamed)
+    }
+}
+
+impl FromStr for MigrationVersion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.splitn(3, '_');
+        let date_part = parts.next().unwrap_or_default();
+        let sequence_part = parts.next().unwrap_or_default();
+        let label = parts.next().map(str::to_string);
+
+        if date_part.len() != 8 || !date_part.chars().all(|c| c.is_ascii_digit()) {
+            return Err(format!("'{}' does not start with a YYYYMMDD date", s));
+        }
+        let date = NaiveDate::parse_from_str(date_part, "%Y%m%d")
+            .map_err(|_| format!("'{}' is not a valid date in '{}'", date_part, s))?;
+
+        if sequence_part.is_empty() || !sequence_part.chars().all(|c| c.is_ascii_digit()) {
+            return Err(format!("'{}' has no numeric sequence after the date", s));
+        }
+        let sequence = sequence_part
+            .parse()
+            .map_err(|_| format!("Sequence '{}' in '{}' is too large", sequence_part, s))?;
+
+        Ok(Self {
+            date,
+            sequence,
+            width: sequence_part.len(),
+            label,
+        })
+    }
+}
+
+impl fmt::Display for MigrationVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.prefix())?;
+        if let Some(label) = &self.label {
+            write!(f, "_{}", label)?;
+        }
+        Ok(())
+    }
+}
+
+impl Ord for MigrationVersion {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.date
+            .cmp(&other.date)
+            .then(self.sequence.cmp(&other.sequence))
+            .then_with(|| self.label.cmp(&other.label))
+            .then(self.width.cmp(&other.width))
+    }
+}
+
+impl PartialOrd for MigrationVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// How versions are read from file names and ordered
+#[derive(Debug, Clone)]
+pub enum VersionScheme {
+    /// `YYYYMMDD_NNN[_label]`, see [`MigrationVersion`]
+    Timestamp,
+    /// `NNN[_label]`, ordered by the number, then the label
+    Sequential,
+    /// Stems matching `pattern`, ordered by the `order` capture groups
+    Custom { pattern: Regex, order: Vec<String> },
+}
+
+impl VersionScheme {
+    pub fn from_config(config: &VersioningConfig) -> Result<Self, String> {
+        match config.scheme {
+            VersioningScheme::Timestamp => Ok(Self::Timestamp),
+            VersioningScheme::Sequential => Ok(Self::Sequential),
+            VersioningScheme::CustomRegex => {
+                let pattern = config
+                    .pattern
+                    .as_deref()
+                    .ok_or("scheme 'custom-regex' needs a pattern")?;
+                let pattern = Regex::new(pattern).map_err(|e| format!("invalid pattern: {}", e))?;
+
+                let names: Vec<String> = pattern
+                    .capture_names()
+                    .enumerate()
+                    .skip(1)
+                    .map(|(index, name)| name.map_or_else(|| index.to_string(), str::to_string))
+                    .collect();
+                if names.is_empty() {
+                    return Err("pattern has no capture groups to order by".to_string());
+                }
+                let order = if config.order.is_empty() {
+                    names
+                } else {
+                    for group in &config.order {
+                        let numbered = group.parse::<usize>().is_ok_and(|i| i >= 1 && i <= names.len());
+                        if !numbered && !names.contains(group) {
+                            return Err(format!("order names '{}', which is not a capture group of the pattern", group));
+                        }
+                    }
+                    config.order.clone()
+                };
+                Ok(Self::Custom { pattern, order })
+            }
+        }
+    }
+
+    /// The version of a file stem, or why it has none
+    pub fn parse(&self, stem: &str) -> Result<String, String> {
+        match self {
+            Self::Timestamp => stem.parse::<MigrationVersion>().map(|version| version.to_string()),
+            Self::Sequential => {
+                let number = stem.split('_').next().unwrap_or_default();
+                if number.is_empty() || !number.chars().all(|c| c.is_ascii_digit()) {
+                    return Err(format!("'{}' does not start with a number", stem));
+                }
+                Ok(stem.to_string())
+            }
+            Self::Custom { pattern, .. } => {
+                if !pattern.is_match(stem) {
+                    return Err(format!("'{}' does not match versioning.pattern", stem));
+                }
+                Ok(stem.to_string())
+            }
+        }
+    }
+
+    /// The description part of a file stem, after its version
+    pub fn label<'s>(&self, stem: &'s str) -> &'s str {
+        let rest = match self {
+            Self::Timestamp => stem.splitn(3, '_').nth(2),
+            Self::Sequential => stem.split_once('_').map(|(_, label)| label),
+            Self::Custom { pattern, .. } => pattern.find(stem).map(|matched| &stem[matched.end()..]),
+        };
+        match rest.map(|rest| rest.trim_start_matches('_')) {
+            Some(rest) if !rest.is_empty() => rest,
+            _ => stem,
+        }
+    }
+
+    /// The next sequential version after `existing`, zero-padded like the
+    /// widest of them (4 digits for the first)
+    pub fn next_sequential<'v>(existing: impl IntoIterator<Item = &'v str>) -> String {
+        let (last, width) = existing
+            .into_iter()
+            .filter_map(|version| {
+                let number = version.split('_').next()?;
+                Some((number.parse::<u64>().ok()?, number.len()))
+            })
+            .fold((0, 4), |(last, width), (number, len)| (last.max(number), width.max(len)));
+        format!("{:0width$}", last + 1, width = width)
+    }
+
+    /// Order two versions; versions that don't parse (e.g. records written by
+    /// other tools) sort after those that do, in plain string order
+    pub fn compare(&self, a: &str, b: &str) -> Ordering {
+        let (key_a, key_b) = match self {
+            Self::Timestamp => {
+                return match (a.parse::<MigrationVersion>(), b.parse::<MigrationVersion>()) {
+                    (Ok(a), Ok(b)) => a.cmp(&b),
+                    (Ok(_), Err(_)) => Ordering::Less,
+                    (Err(_), Ok(_)) => Ordering::Greater,
+                    (Err(_), Err(_)) => a.cmp(b),
+                };
+            }
+            Self::Sequential => (self.sequential_key(a), self.sequential_key(b)),
+            Self::Custom { .. } => (self.custom_key(a), self.custom_key(b)),
+        };
+        match (key_a, key_b) {
+            (Some(key_a), Some(key_b)) => compare_keys(&key_a, &key_b).then_with(|| a.cmp(b)),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => a.cmp(b),
+        }
+    }
+
+    fn sequential_key<'v>(&self, version: &'v str) -> Option<Vec<&'v str>> {
+        self.parse(version).ok()?;
+        let (number, label) = version.split_once('_').unwrap_or((version, ""));
+        Some(vec![number, label])
+    }
+
+    fn custom_key<'v>(&self, version: &'v str) -> Option<Vec<&'v str>> {
+        let Self::Custom { pattern, order } = self else {
+            return None;
+        };
+        let captures = pattern.captures(version)?;
+        Some(
+            order
+                .iter()
+                .map(|group| {
+                    let capture = match group.parse::<usize>() {
+                        Ok(index) => captures.get(index),
+                        Err(_) => captures.name(group),
+                    };
+                    capture.map_or("", |capture| capture.as_str())
+                })
+                .collect(),
+        )
+    }
+}
+
+/// Compare parts pairwise, numerically where both are digits
+fn compare_keys(a: &[&str], b: &[&str]) -> Ordering {
+    for (a, b) in a.iter().zip(b) {
+        let numeric = |part: &str| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit());
+        let ordering = if numeric(a) && numeric(b) {
+            // Compare digits without overflow: fewer significant digits is smaller
+            let (a, b) = (a.trim_start_matches('0'), b.trim_start_matches('0'));
+            a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+        } else {
+            a.cmp(b)
+        };
+        if ordering.is_ne() {
+            return ordering;
+        }
+    }
+    a.len().cmp(&b.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compare(a: &str, b: &str) -> Ordering {
+        VersionScheme::Timestamp.compare(a, b)
+    }
+
+    #[test]
+    fn test_parse_and_display() {
+        let version: MigrationVersion = "20250115_001_add_user_table".parse().unwrap();
+        assert_eq!(version.date(), NaiveDate::from_ymd_opt(2025, 1, 15).unwrap());
+        assert_eq!(version.sequence(), 1);
+        assert_eq!(version.label(), Some("add_user_table"));
+        assert_eq!(version.prefix(), "20250115_001");
+        assert_eq!(version.to_string(), "20250115_001_add_user_table");
+
+        assert!(MigrationVersion::from_filename("20250115_093000.cql").is_some());
+        assert!("20251315_001_bad_month".parse::<MigrationVersion>().is_err());
+        assert!("20250115_add_users".parse::<MigrationVersion>().is_err());
+        assert!("invalid_filename".parse::<MigrationVersion>().is_err());
+    }
+
+    #[test]
+    fn test_numeric_ordering() {
+        let mut versions = vec![
+            "20250115_10_ten",
+            "20250116_001_next_day",
+            "20250115_9_nine",
+            "20250115_010_padded",
+        ];
+        versions.sort_by(|a, b| compare(a, b));
+
+        assert_eq!(
+            versions,
+            vec!["20250115_9_nine", "20250115_010_padded", "20250115_10_ten", "20250116_001_next_day"]
+        );
+        assert_eq!(compare("legacy", "20250115_001_a"), Ordering::Greater);
+    }
+
+    #[test]
+    fn test_ordering_is_independent_of_input_order() {
+        let expected = vec![
+            "20241231_999_last_year",
+            "20250115_143022_add_orders",
+            "20250115_143022_add_users",
+            "20250115_143022_ñandú_table",
+            "20250115_143023_Éclair",
+            "20250201_1_next_month",
+        ];
+
+        for reversed in [false, true] {
+            let mut versions = expected.clone();
+            if reversed {
+                versions.reverse();
+            }
+            versions.sort_by(|a, b| compare(a, b));
+            assert_eq!(versions, expected);
+        }
+    }
+
+    #[test]
+    fn test_schemes() {
+        let sequential = VersionScheme::Sequential;
+        assert!(sequential.parse("0042_add_users").is_ok());
+        assert!(sequential.parse("add_users").is_err());
+        assert_eq!(sequential.label("0042_add_users"), "add_users");
+        assert_eq!(VersionScheme::next_sequential(["0009_a", "10_b", "legacy"]), "0011");
+        assert_eq!(VersionScheme::next_sequential([]), "0001");
+        let mut versions = vec!["10_ten", "9_nine", "0010_padded", "legacy"];
+        versions.sort_by(|a, b| sequential.compare(a, b));
+        assert_eq!(versions, vec!["9_nine", "0010_padded", "10_ten", "legacy"]);
+
+        let config = |pattern: &str, order: &[&str]| VersioningConfig {
+            scheme: VersioningScheme::CustomRegex,
+            pattern: Some(pattern.to_string()),
+            order: order.iter().map(|group| group.to_string()).collect(),
+        };
+        let semver = VersionScheme::from_config(&config(
+            r"^v(?P<major>\d+)\.(?P<minor>\d+)\.(?P<patch>\d+)_",
+            &["major", "minor", "patch"],
+        ))
+        .unwrap();
+        assert!(semver.parse("v1.2.3_add_users").is_ok());
+        assert!(semver.parse("20250115_001_add_users").is_err());
+        assert_eq!(semver.label("v1.2.3_add_users"), "add_users");
+        let mut versions = vec!["v1.10.0_c", "v1.2.10_b", "v1.2.9_a", "v0.9.0_z"];
+        versions.sort_by(|a, b| semver.compare(a, b));
+        assert_eq!(versions, vec!["v0.9.0_z", "v1.2.9_a", "v1.2.10_b", "v1.10.0_c"]);
+
+        // Ticket numbers first, then a sequence within the ticket
+        let tickets = VersionScheme::from_config(&config(r"^[A-Z]+-(\d+)_(\d+)_", &["1", "2"])).unwrap();
+        assert!(tickets.compare("OPS-99_2_b", "OPS-100_1_a").is_lt());
+
+        assert!(VersionScheme::from_config(&config(r"^v(\d+)_", &["major"])).is_err());
+        assert!(VersionScheme::from_config(&config(r"^v\d+_", &[])).is_err());
+    }
+
+    #[test]
+    fn test_renamed() {
+        let version: MigrationVersion = "20250115_001_add_users".parse().unwrap();
+
+        assert_eq!(version.renamed("20250120_002").unwrap().to_string(), "20250120_002_add_users");
+        assert_eq!(
+            version.renamed("20250120_002_create_users").unwrap().to_string(),
+            "20250120_002_create_users"
+        );
+        assert!(version.renamed("add_users").is_err());
+    }
+}
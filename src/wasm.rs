@@ -0,0 +1,286 @@
+//! Sandboxed WebAssembly plugins
+//!
+//! Modules listed under `[[wasm_plugins]]` run in-process, with no access to
+//! the file system, network, clock or environment: the only host function a
+//! module may import is `db_migrate.log`, and only with the `log` capability.
+//! Every call gets a fresh instance with a fuel (instruction) budget and a
+//! memory limit. Requires building with the `wasm-plugins` feature.
+//!
+//! A module exports `memory`, `alloc(len: i32) -> i32` and the hooks its
+//! capabilities allow. Hooks take a pointer and length of UTF-8 input written
+//! into the module's memory and return the output packed as `(ptr << 32) | len`:
+//!
+//! - `validate` receives the same JSON as an external plugin (see
+//!   [`crate::plugins`]) and answers with a verdict
+//! - `transform` receives that JSON too and answers with the new content of the
+//!   migration file, used for execution and dry runs. The checksum and UP
+//!   checksum recorded stay those of the file as written; streamed files are
+//!   not transformed.
+//! - `db_migrate.log(ptr: i32, len: i32)` writes a message to db-migrate's log
+
+use crate::{
+    config::{Config, WasmCapability, WasmPluginConfig},
+    plugins::{Decision, PluginRequest, Verdict},
+    MigrationError, MigrationFile,
+};
+
+/// Ask a module with the `validate` capability about one migration
+pub fn validate(plugin: &WasmPluginConfig, request: &PluginRequest<'_>) -> Verdict {
+    let deny = |message: String| Verdict {
+        decision: Decision::Deny,
+        messages: vec![message],
+    };
+
+    let input = match serde_json::to_vec(request) {
+        Ok(input) => input,
+        Err(e) => return deny(format!("could not encode the request: {}", e)),
+    };
+    match runtime::call(plugin, "validate", &input) {
+        Ok(output) => serde_json::from_slice(&output)
+            .unwrap_or_else(|e| deny(format!("returned an invalid verdict: {}", e))),
+        Err(e) => deny(e),
+    }
+}
+
+/// Run the `transform` modules over a migration, in configuration order
+///
+/// Returns the transformed migration, or `None` if no module applies.
+pub fn transform(config: &Config, migration: &MigrationFile) -> Result<Option<MigrationFile>, MigrationError> {
+    let plugins: Vec<_> = config
+        .wasm_plugins
+        .iter()
+        .filter(|plugin| plugin.allows(WasmCapability::Transform))
+        .collect();
+    if plugins.is_empty() || migration.streamed {
+        return Ok(None);
+    }
+
+    let mut transformed = migration.clone();
+    for plugin in plugins {
+        let input = serde_json::to_vec(&PluginRequest::new(&transformed, config))
+            .map_err(|e| plugin_error(plugin, e.to_string()))?;
+        let output = runtime::call(plugin, "transform", &input).map_err(|e| plugin_error(plugin, e))?;
+        transformed.content = String::from_utf8(output)
+            .map_err(|_| plugin_error(plugin, "returned content that is not UTF-8".to_string()))?;
+    }
+
+    Ok(Some(transformed))
+}
+
+/// [`transform`] a migration about to be applied, along with the UP checksum
+/// to record for it: that of the file as written, which `verify` and `rename`
+/// compare the file against
+pub fn transform_for_apply(
+    config: &Config,
+    migration: &MigrationFile,
+) -> Result<(Option<MigrationFile>, String), MigrationError> {
+    let up_checksum = migration.up_checksum();
+    Ok((transform(config, migration)?, up_checksum))
+}
+
+fn plugin_error(plugin: &WasmPluginConfig, reason: String) -> MigrationError {
+    MigrationError::PluginError {
+        plugin: plugin.name.clone(),
+        reason,
+    }
+}
+
+#[cfg(feature = "wasm-plugins")]
+mod runtime {
+    use crate::config::{WasmCapability, WasmPluginConfig};
+    use std::collections::HashMap;
+    use std::path::{Path, PathBuf};
+    use std::sync::{Mutex, OnceLock};
+    use tracing::info;
+    use wasmtime::{Caller, Engine, Linker, Module, Store, StoreLimits, StoreLimitsBuilder};
+
+    struct State {
+        name: String,
+        limits: StoreLimits,
+    }
+
+    fn engine() -> &'static Engine {
+        static ENGINE: OnceLock<Engine> = OnceLock::new();
+        ENGINE.get_or_init(|| {
+            let mut config = wasmtime::Config::new();
+            config.consume_fuel(true);
+            Engine::new(&config).expect("fuel metering is supported")
+        })
+    }
+
+    /// Compile each module once per process
+    fn module(path: &Path) -> Result<Module, String> {
+        static MODULES: OnceLock<Mutex<HashMap<PathBuf, Module>>> = OnceLock::new();
+        let mut modules = MODULES
+            .get_or_init(Default::default)
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        if let Some(module) = modules.get(path) {
+            return Ok(module.clone());
+        }
+        let module = Module::from_file(engine(), path)
+            .map_err(|e| format!("could not load {}: {}", path.display(), e))?;
+        modules.insert(path.to_path_buf(), module.clone());
+        Ok(module)
+    }
+
+    /// Call one hook of a fresh instance with `input`, returning its output
+    pub fn call(plugin: &WasmPluginConfig, export: &str, input: &[u8]) -> Result<Vec<u8>, String> {
+        let module = module(&plugin.module)?;
+        let limits = StoreLimitsBuilder::new()
+            .memory_size((plugin.max_memory_mb as usize).saturating_mul(1024 * 1024))
+            .instances(1)
+            .build();
+        let mut store = Store::new(
+            engine(),
+            State {
+                name: plugin.name.clone(),
+                limits,
+            },
+        );
+        store.limiter(|state| &mut state.limits);
+        store.set_fuel(plugin.fuel).map_err(|e| e.to_string())?;
+
+        let mut linker = Linker::new(engine());
+        if plugin.allows(WasmCapability::Log) {
+            linker
+                .func_wrap("db_migrate", "log", |mut caller: Caller<'_, State>, ptr: i32, len: i32| {
+                    let Some(memory) = caller.get_export("memory").and_then(|e| e.into_memory()) else {
+                        return;
+                    };
+                    let (start, len) = (ptr as u32 as usize, len as u32 as usize);
+                    if let Some(message) = memory.data(&caller).get(start..start.saturating_add(len)) {
+                        info!("[{}] {}", caller.data().name, String::from_utf8_lossy(message));
+                    }
+                })
+                .map_err(|e| e.to_string())?;
+        }
+
+        // Any import beyond the granted capabilities fails here
+        let instance = linker
+            .instantiate(&mut store, &module)
+            .map_err(|e| format!("could not instantiate: {}", e))?;
+        let memory = instance
+            .get_memory(&mut store, "memory")
+            .ok_or_else(|| "does not export its memory".to_string())?;
+        let alloc = instance
+            .get_typed_func::<i32, i32>(&mut store, "alloc")
+            .map_err(|e| format!("invalid alloc export: {}", e))?;
+        let hook = instance
+            .get_typed_func::<(i32, i32), i64>(&mut store, export)
+            .map_err(|e| format!("invalid {} export: {}", export, e))?;
+
+        let len = i32::try_from(input.len()).map_err(|_| "input too large".to_string())?;
+        let ptr = alloc.call(&mut store, len).map_err(|e| format!("alloc failed: {}", e))?;
+        memory
+            .write(&mut store, ptr as u32 as usize, input)
+            .map_err(|e| format!("alloc returned an invalid pointer: {}", e))?;
+
+        let packed = hook
+            .call(&mut store, (ptr, len))
+            .map_err(|e| format!("{} failed: {}", export, e))? as u64;
+        let (start, len) = ((packed >> 32) as usize, (packed & 0xffff_ffff) as usize);
+        memory
+            .data(&store)
+            .get(start..start + len)
+            .map(<[u8]>::to_vec)
+            .ok_or_else(|| format!("{} returned an out-of-bounds result", export))
+    }
+}
+
+#[cfg(not(feature = "wasm-plugins"))]
+mod runtime {
+    use crate::config::WasmPluginConfig;
+
+    pub fn call(_plugin: &WasmPluginConfig, _export: &str, _input: &[u8]) -> Result<Vec<u8>, String> {
+        Err("db-migrate was built without the wasm-plugins feature".to_string())
+    }
+}
+
+#[cfg(all(test, feature = "wasm-plugins"))]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    /// Bump allocator; `validate` denies, `transform` echoes a fixed statement
+    const MODULE: &str = r#"
+        (module
+          (memory (export "memory") 1)
+          (global $next (mut i32) (i32.const 1024))
+          (data (i32.const 0) "{\"decision\":\"deny\",\"messages\":[\"no\"]}")
+          (data (i32.const 64) "-- +migrate Up\nSELECT 1;")
+          (func (export "alloc") (param $len i32) (result i32)
+            (local $ptr i32)
+            (local.set $ptr (global.get $next))
+            (global.set $next (i32.add (global.get $next) (local.get $len)))
+            (local.get $ptr))
+          (func (export "validate") (param i32 i32) (result i64)
+            (i64.const 37))
+          (func (export "transform") (param i32 i32) (result i64)
+            (i64.or (i64.shl (i64.const 64) (i64.const 32)) (i64.const 24))))
+    "#;
+
+    fn plugin(wat: &str, capabilities: Vec<WasmCapability>) -> (tempfile::NamedTempFile, WasmPluginConfig) {
+        let mut file = tempfile::Builder::new().suffix(".wat").tempfile().unwrap();
+        file.write_all(wat.as_bytes()).unwrap();
+        let plugin = WasmPluginConfig {
+            name: "tenant".to_string(),
+            module: file.path().to_path_buf(),
+            capabilities,
+            fuel: 1_000_000,
+            max_memory_mb: 16,
+        };
+        (file, plugin)
+    }
+
+    fn migration() -> MigrationFile {
+        MigrationFile::for_test("20250101_001_users", "-- +migrate Up\nCREATE TABLE users (id int PRIMARY KEY);")
+    }
+
+    #[test]
+    fn test_wasm_hooks() {
+        let migration = migration();
+        let mut config = Config::default();
+        let (_file, validator) = plugin(MODULE, vec![WasmCapability::Validate, WasmCapability::Transform]);
+
+        let verdict = validate(&validator, &PluginRequest::new(&migration, &config));
+        assert_eq!(verdict.decision, Decision::Deny);
+        assert_eq!(verdict.messages, vec!["no".to_string()]);
+
+        config.wasm_plugins = vec![validator];
+        let transformed = transform(&config, &migration).unwrap().unwrap();
+        assert_eq!(transformed.content, "-- +migrate Up\nSELECT 1;");
+        assert_eq!(transformed.checksum, migration.checksum);
+
+        // The recorded UP checksum is the file's, or a DOWN-only edit would block `up`
+        let (transformed, up_checksum) = transform_for_apply(&config, &migration).unwrap();
+        assert_ne!(transformed.unwrap().up_checksum(), migration.up_checksum());
+        assert_eq!(up_checksum, migration.up_checksum());
+    }
+
+    #[test]
+    fn test_wasm_sandbox() {
+        let (migration, config) = (migration(), Config::default());
+
+        // Imports are only satisfied for granted capabilities
+        let (_file, importer) = plugin(
+            r#"(module (import "db_migrate" "log" (func (param i32 i32))) (memory (export "memory") 1))"#,
+            vec![WasmCapability::Validate],
+        );
+        let verdict = validate(&importer, &PluginRequest::new(&migration, &config));
+        assert_eq!(verdict.decision, Decision::Deny);
+        assert!(verdict.messages[0].contains("could not instantiate"));
+
+        // Runaway modules run out of fuel
+        let (_file, spinner) = plugin(
+            r#"(module
+                 (memory (export "memory") 1)
+                 (func (export "alloc") (param i32) (result i32) (i32.const 0))
+                 (func (export "validate") (param i32 i32) (result i64) (loop (br 0)) (i64.const 0)))"#,
+            vec![WasmCapability::Validate],
+        );
+        let verdict = validate(&spinner, &PluginRequest::new(&migration, &config));
+        assert_eq!(verdict.decision, Decision::Deny);
+    }
+}